@@ -0,0 +1,29 @@
+//! Benchmark to measure the cost of checking if a client is part of a NetworkTarget
+use lightyear::prelude::ClientId;
+use lightyear::shared::replication::network_target::NetworkTarget;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+criterion_group!(network_target_benches, only_targets);
+criterion_main!(network_target_benches);
+
+const NUM_CLIENTS: &[u64] = &[10, 100, 1000, 5000];
+
+/// Check the membership of every client for a `NetworkTarget::Only` containing half of the clients
+fn only_targets(criterion: &mut Criterion) {
+    let mut group = criterion.benchmark_group("network_target/only_targets");
+    group.warm_up_time(std::time::Duration::from_millis(500));
+    group.measurement_time(std::time::Duration::from_millis(3000));
+    for n in NUM_CLIENTS.iter() {
+        let target = NetworkTarget::from_iter((0..*n).step_by(2).map(ClientId::Netcode));
+        group.bench_with_input(BenchmarkId::new("num_clients", n), n, |bencher, n| {
+            bencher.iter(|| {
+                let matcher = target.matcher();
+                (0..*n)
+                    .filter(|i| matcher.targets(&ClientId::Netcode(*i)))
+                    .count()
+            });
+        });
+    }
+    group.finish();
+}
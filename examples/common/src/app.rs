@@ -523,6 +523,7 @@ pub fn client_app(settings: Settings, net_config: client::NetConfig) -> (App, Cl
     let client_config = ClientConfig {
         shared: shared_config(),
         net: net_config,
+        packet: client::PacketConfig::default().with_compression(settings.shared.compression),
         replication: ReplicationConfig {
             send_interval: REPLICATION_INTERVAL,
             ..default()
@@ -557,6 +558,7 @@ pub fn server_app(
     let server_config = ServerConfig {
         shared: shared_config(),
         net: net_configs,
+        packet: server::PacketConfig::default().with_compression(settings.shared.compression),
         replication: ReplicationConfig {
             send_interval: REPLICATION_INTERVAL,
             ..default()
@@ -583,6 +585,7 @@ pub fn combined_app(
     let server_config = ServerConfig {
         shared: shared_config(),
         net: net_configs,
+        packet: server::PacketConfig::default().with_compression(settings.shared.compression),
         replication: ReplicationConfig {
             send_interval: REPLICATION_INTERVAL,
             ..default()
@@ -594,6 +597,7 @@ pub fn combined_app(
     let client_config = ClientConfig {
         shared: shared_config(),
         net: client_net_config,
+        packet: client::PacketConfig::default().with_compression(settings.shared.compression),
         ..default()
     };
     (app, client_config, server_config)
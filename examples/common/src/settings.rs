@@ -204,7 +204,6 @@ pub fn build_server_netcode_config(
     let io_config = server::IoConfig {
         transport: transport_config,
         conditioner,
-        compression: shared.compression,
     };
     server::NetConfig::Netcode {
         config: netcode_config,
@@ -384,7 +383,6 @@ pub fn build_client_netcode_config(
     let io_config = client::IoConfig {
         transport: transport_config,
         conditioner,
-        compression: shared.compression,
     };
     client::NetConfig::Netcode {
         auth,
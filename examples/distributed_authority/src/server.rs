@@ -106,13 +106,21 @@ pub(crate) fn movement(
 pub(crate) fn transfer_authority(
     // timer so that we only transfer authority every X seconds
     mut timer: Local<Timer>,
+    // true while the new authority hasn't acknowledged the transfer yet
+    mut transfer_pending: Local<bool>,
+    mut transfer_events: EventReader<AuthorityTransferEvent>,
     time: Res<Time>,
     mut connection: ResMut<ConnectionManager>,
     mut commands: Commands,
     ball_q: Query<(Entity, &Position), With<BallMarker>>,
     player_q: Query<(&PlayerId, &Position)>,
 ) {
-    if !timer.tick(time.delta()).finished() {
+    for event in transfer_events.read() {
+        *transfer_pending = !event.acknowledged;
+    }
+    // wait for the previous transfer to complete before handing the ball over again,
+    // otherwise the ball could bounce between peers under packet loss
+    if *transfer_pending || !timer.tick(time.delta()).finished() {
         return;
     }
     *timer = Timer::new(Duration::from_secs_f32(0.3), TimerMode::Once);
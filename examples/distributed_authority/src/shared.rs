@@ -54,10 +54,18 @@ pub(crate) fn shared_movement_behaviour(mut position: Mut<Position>, input: &Inp
 
 /// We move the ball only when we have authority over it.
 /// The peer that has authority could be the Server, a Client or no one
+///
+/// On the server, the ball is not simulated while an authority transfer is waiting for the
+/// acknowledgement of the new authority, so that two peers never simulate it during the handoff.
 pub(crate) fn ball_movement(
     mut balls: Query<
         (&mut Position, &mut Speed),
-        (With<BallMarker>, With<HasAuthority>, Without<Interpolated>),
+        (
+            With<BallMarker>,
+            With<HasAuthority>,
+            Without<Interpolated>,
+            Without<PendingAuthorityTransfer>,
+        ),
     >,
 ) {
     for (mut position, mut speed) in balls.iter_mut() {
@@ -2,7 +2,7 @@
 //! It is possible (and recommended) to run the server in headless mode (without any rendering plugins).
 //!
 //! The server will:
-//! - spawn a new player entity for each client that connects
+//! - spawn a new player entity for each client that connects (or give back its previous player entity to a client that reconnects)
 //! - read inputs from the clients and move the player entities accordingly
 //!
 //! Lightyear will handle the replication of entities automatically if you add a `Replicate` component to them.
@@ -12,6 +12,7 @@ use bevy::utils::HashMap;
 use lightyear::prelude::server::*;
 use lightyear::prelude::*;
 use std::sync::Arc;
+use std::time::Duration;
 
 use crate::protocol::*;
 use crate::shared;
@@ -21,10 +22,15 @@ pub struct ExampleServerPlugin;
 impl Plugin for ExampleServerPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<ClientEntityMap>();
+        // despawn the player entities of the clients that do not reconnect within a minute
+        app.insert_resource(OrphanedEntities::with_timeout(Duration::from_secs(60)));
         app.add_systems(Startup, start_server);
         // the physics/FixedUpdates systems that consume inputs should be run in this set.
         app.add_systems(FixedUpdate, movement);
-        app.add_systems(Update, (send_message, handle_connections));
+        app.add_systems(
+            Update,
+            (send_message, handle_connections, handle_disconnections),
+        );
         #[cfg(not(feature = "client"))]
         app.add_systems(Update, server_start_stop);
     }
@@ -41,25 +47,32 @@ fn start_server(mut commands: Commands) {
 }
 
 /// Server connection system, create a player upon connection
+///
+/// The player entities are persistent: when a client reconnects, it takes back the control of its previous player entity.
 pub(crate) fn handle_connections(
     mut connections: EventReader<ConnectEvent>,
     mut entity_map: ResMut<ClientEntityMap>,
+    orphaned: Res<OrphanedEntities>,
     mut commands: Commands,
 ) {
     for connection in connections.read() {
         let client_id = connection.client_id;
+        // identify the player across reconnections. Here we simply use the ClientId, but a real game
+        // would use an identifier obtained from authentication
+        let session_token = SessionToken(client_id.to_bits());
+        commands.entity(connection.entity).insert(session_token);
+        if let Some(&[entity]) = orphaned.get(session_token) {
+            // lightyear updates the `ControlledBy` of the entity to target the new client
+            entity_map.0.insert(client_id, entity);
+            info!("Client {:?} reclaims entity {:?}", client_id, entity);
+            continue;
+        }
         // in host-server mode, server and client are running in the same app, no need to replicate to the local client
-        let replicate = Replicate {
-            sync: SyncTarget {
-                prediction: NetworkTarget::Single(client_id),
-                interpolation: NetworkTarget::AllExceptSingle(client_id),
-            },
-            controlled_by: ControlledBy {
-                target: NetworkTarget::Single(client_id),
-                ..default()
-            },
-            ..default()
-        };
+        let replicate = Replicate::default()
+            .predicted_by(client_id)
+            .interpolated_by_others(client_id)
+            .controlled_by(client_id)
+            .with_lifetime(Lifetime::Persistent);
         let entity = commands.spawn((PlayerBundle::new(client_id, Vec2::ZERO), replicate));
 
         entity_map.0.insert(client_id, entity.id());
@@ -68,30 +81,29 @@ pub(crate) fn handle_connections(
     }
 }
 
-/// Handle client disconnections: we want to despawn every entity that was controlled by that client.
-///
-/// Lightyear creates one entity per client, which contains metadata associated with that client.
-/// You can find that entity by calling `ConnectionManager::client_entity(client_id)`.
+/// Handle client disconnections.
 ///
-/// That client entity contains the `ControlledEntities` component, which is a set of entities that are controlled by that client.
+/// The player entities are persistent: lightyear does not despawn them when their client disconnects,
+/// but keeps them in the `OrphanedEntities` resource so that the player can reclaim them when reconnecting.
 ///
-/// By default, lightyear automatically despawns all the `ControlledEntities` when the client disconnects;
-/// but in this example we will also do it manually to showcase how it can be done.
-/// (however we don't actually run the system)
+/// Lightyear creates one entity per client, which contains metadata associated with that client,
+/// such as the `ControlledEntities` component: the set of entities that are controlled by that client.
+/// The `ClientControlledEntities` SystemParam returns it directly from the `ClientId`, even while handling
+/// the `DisconnectEvent` of the client.
 pub(crate) fn handle_disconnections(
-    mut commands: Commands,
     mut disconnections: EventReader<DisconnectEvent>,
-    manager: Res<ConnectionManager>,
-    client_query: Query<&ControlledEntities>,
+    mut entity_map: ResMut<ClientEntityMap>,
+    controlled_entities: ClientControlledEntities,
 ) {
     for disconnection in disconnections.read() {
-        debug!("Client {:?} disconnected", disconnection.client_id);
-        if let Ok(client_entity) = manager.client_entity(disconnection.client_id) {
-            if let Ok(controlled_entities) = client_query.get(client_entity) {
-                for entity in controlled_entities.entities() {
-                    commands.entity(entity).despawn();
-                }
-            }
+        let client_id = disconnection.client_id;
+        entity_map.0.remove(&client_id);
+        if let Some(controlled_entities) = controlled_entities.get(client_id) {
+            info!(
+                "Client {:?} disconnected, keeping its entities {:?} until it reconnects",
+                client_id,
+                controlled_entities.entities()
+            );
         }
     }
 }
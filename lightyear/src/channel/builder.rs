@@ -2,6 +2,7 @@
 use bevy::utils::Duration;
 
 use lightyear_macros::ChannelInternal;
+use tracing::warn;
 
 use crate::channel::receivers::ordered_reliable::OrderedReliableReceiver;
 use crate::channel::receivers::sequenced_reliable::SequencedReliableReceiver;
@@ -106,7 +107,14 @@ impl ChannelContainer {
                 receiver = SequencedReliableReceiver::new().into();
                 sender = ReliableSender::new(reliable_settings, settings.send_frequency).into();
             }
-            ChannelMode::OrderedReliable(reliable_settings) => {
+            ChannelMode::OrderedReliable(mut reliable_settings) => {
+                if reliable_settings.window_full_policy == WindowFullPolicy::DropOldest {
+                    warn!(
+                        channel = name,
+                        "WindowFullPolicy::DropOldest cannot be used on an OrderedReliable channel, using WindowFullPolicy::StallClient instead"
+                    );
+                    reliable_settings.window_full_policy = WindowFullPolicy::StallClient;
+                }
                 receiver = OrderedReliableReceiver::new().into();
                 sender = ReliableSender::new(reliable_settings, settings.send_frequency).into();
             }
@@ -201,23 +209,64 @@ pub enum ChannelDirection {
 pub struct ReliableSettings {
     /// Multiplier of the current RTT estimate, used for delay to wait before resending a packet if it has not been acked.
     pub rtt_resend_factor: f32,
+    /// Multiplier of the current jitter estimate, added to the resend delay so that packets are not resent
+    /// too early on links where the RTT varies a lot (similar to the retransmission timeout of TCP)
+    pub jitter_resend_factor: f32,
     /// Minimum duration to wait before resending a packet if it has not been acked
     pub rtt_resend_min_delay: Duration,
+    /// Maximum duration to wait before resending a packet if it has not been acked.
+    /// If `None`, the resend delay is not bounded.
+    pub rtt_resend_max_delay: Option<Duration>,
+    /// Maximum number of messages that can be in-flight (sent but not acked yet) at the same time.
+    /// If `None`, the window of in-flight messages is unbounded.
+    pub max_unacked_messages: Option<usize>,
+    /// What to do when the window of in-flight messages is full
+    /// (for example because the remote peer is stalled and doesn't send acks anymore)
+    pub window_full_policy: WindowFullPolicy,
 }
 
 impl Default for ReliableSettings {
     fn default() -> Self {
         Self {
             rtt_resend_factor: 1.5,
+            jitter_resend_factor: 4.0,
             rtt_resend_min_delay: Duration::default(),
+            rtt_resend_max_delay: None,
+            max_unacked_messages: None,
+            window_full_policy: WindowFullPolicy::default(),
         }
     }
 }
 
+/// Policy applied by a reliable channel when its window of in-flight messages is full.
+///
+/// The window size is set via [`ReliableSettings::max_unacked_messages`].
+/// The policy only applies to the channel whose window is full; the other channels of the
+/// connection keep sending normally.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum WindowFullPolicy {
+    /// Stop sending new messages to the stalled remote peer on this channel until it acks the older ones.
+    /// New messages are still buffered and will be sent once there is room in the window.
+    #[default]
+    StallClient,
+    /// Same as [`WindowFullPolicy::StallClient`], but if the window stays full without receiving any ack
+    /// for longer than `timeout`, the remote peer gets disconnected.
+    DisconnectOnWindowFull { timeout: Duration },
+    /// Drop the oldest in-flight message to make room for the new one.
+    /// The dropped message is reported as lost to the nack subscribers of the channel.
+    ///
+    /// This cannot be used with [`ChannelMode::OrderedReliable`] channels because the receiver would
+    /// wait for the dropped messages forever; [`WindowFullPolicy::StallClient`] is used instead.
+    DropOldest,
+}
+
 impl ReliableSettings {
-    pub(crate) fn resend_delay(&self, rtt: Duration) -> Duration {
-        let delay = rtt.mul_f32(self.rtt_resend_factor);
-        std::cmp::max(delay, self.rtt_resend_min_delay)
+    /// Delay to wait before resending a packet, based on the current RTT and jitter estimates
+    pub(crate) fn resend_delay(&self, rtt: Duration, jitter: Duration) -> Duration {
+        let delay = rtt.mul_f32(self.rtt_resend_factor) + jitter.mul_f32(self.jitter_resend_factor);
+        let delay = std::cmp::max(delay, self.rtt_resend_min_delay);
+        self.rtt_resend_max_delay
+            .map_or(delay, |max_delay| std::cmp::min(delay, max_delay))
     }
 }
 
@@ -246,7 +295,42 @@ pub struct PongChannel;
 /// Default channel to send inputs from client to server. This is a Sequenced Unreliable channel.
 pub struct InputChannel;
 
+#[derive(ChannelInternal)]
+/// Channel to exchange the inputs and checksums of the deterministic lockstep mode.
+/// This is an Ordered Reliable channel.
+pub struct LockstepChannel;
+
 #[derive(ChannelInternal)]
 /// Channel to send messages related to Authority transfers
 /// This is an Ordered Reliable channel
 pub struct AuthorityChannel;
+
+#[derive(ChannelInternal)]
+/// Channel to send per-entity readiness notifications from client to server
+/// This is an Unordered Reliable channel
+pub struct EntityReadyChannel;
+
+#[derive(ChannelInternal)]
+/// Channel to notify a newly connected client of the tick of its initial replication snapshot
+/// This is an Unordered Reliable channel
+pub struct InitialReplicationChannel;
+
+#[derive(ChannelInternal)]
+/// Channel to send intents from client to server, and to broadcast their results
+/// This is an Ordered Reliable channel
+pub struct IntentChannel;
+
+#[derive(ChannelInternal)]
+/// Channel to send the events that clients play at a specific tick
+/// This is an Unordered Reliable channel
+pub struct TickEventChannel;
+
+#[derive(ChannelInternal)]
+/// Channel to send the component subscriptions of a client to the server
+/// This is an Ordered Reliable channel
+pub struct ComponentSubscriptionChannel;
+
+#[derive(ChannelInternal)]
+/// Channel to notify the clients that the server is shutting down
+/// This is an Unordered Reliable channel
+pub struct ShutdownChannel;
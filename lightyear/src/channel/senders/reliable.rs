@@ -7,7 +7,7 @@ use bytes::Bytes;
 use crossbeam_channel::{Receiver, Sender};
 use tracing::trace;
 
-use crate::channel::builder::ReliableSettings;
+use crate::channel::builder::{ReliableSettings, WindowFullPolicy};
 use crate::channel::senders::fragment_sender::FragmentSender;
 use crate::channel::senders::ChannelSend;
 use crate::packet::message::{FragmentData, MessageAck, MessageId, SendMessage, SingleData};
@@ -35,6 +35,18 @@ pub enum UnackedMessage {
     Fragmented(Vec<FragmentAck>),
 }
 
+impl UnackedMessage {
+    /// Returns true if the message (or one of its fragments) has been sent at least once
+    fn is_sent(&self) -> bool {
+        match self {
+            UnackedMessage::Single { last_sent, .. } => last_sent.is_some(),
+            UnackedMessage::Fragmented(fragment_acks) => {
+                fragment_acks.iter().any(|f| f.last_sent.is_some())
+            }
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct UnackedMessageWithPriority {
     pub unacked_message: UnackedMessage,
@@ -70,12 +82,22 @@ pub struct ReliableSender {
     /// List of senders that want to be notified when a message is lost
     nack_senders: Vec<Sender<MessageId>>,
     current_rtt: Duration,
+    current_jitter: Duration,
     current_time: WrappedTime,
     /// Internal timer to determine if the channel is ready to send messages
     timer: Option<Timer>,
     /// Factor that makes sure that the priority accumulates at the same right even the channel
     /// sends messages infrequently
     priority_multiplier: f32,
+    /// Timeout after which a full window is reported, for [`WindowFullPolicy::DisconnectOnWindowFull`]
+    window_full_timeout: Option<chrono::Duration>,
+    /// If the window of in-flight messages is currently full: the last time the window made
+    /// progress (it became full, or we received an ack for one of the in-flight messages)
+    window_full_since: Option<WrappedTime>,
+    /// True if we already notified that the window is full during the current stall
+    window_full_notified: bool,
+    /// True if the window became full since the last time we checked
+    window_full_event: bool,
 }
 
 impl ReliableSender {
@@ -85,6 +107,12 @@ impl ReliableSender {
         } else {
             Some(Timer::new(send_frequency, TimerMode::Repeating))
         };
+        let window_full_timeout = match reliable_settings.window_full_policy {
+            WindowFullPolicy::DisconnectOnWindowFull { timeout } => {
+                Some(chrono::Duration::from_std(timeout).unwrap_or(chrono::Duration::MAX))
+            }
+            _ => None,
+        };
         Self {
             reliable_settings,
             unacked_messages: Default::default(),
@@ -96,9 +124,98 @@ impl ReliableSender {
             ack_senders: vec![],
             nack_senders: vec![],
             current_rtt: Duration::default(),
+            current_jitter: Duration::default(),
             current_time: WrappedTime::default(),
             timer,
             priority_multiplier: 1.0,
+            window_full_timeout,
+            window_full_since: None,
+            window_full_notified: false,
+            window_full_event: false,
+        }
+    }
+
+    /// Returns true if the window of in-flight messages is full
+    pub(crate) fn is_window_full(&self) -> bool {
+        self.window_full_since.is_some()
+    }
+
+    /// Returns true if the window of in-flight messages became full since the last time
+    /// this function was called
+    pub(crate) fn take_window_full_event(&mut self) -> bool {
+        std::mem::take(&mut self.window_full_event)
+    }
+
+    /// Returns true if the window has been full without receiving any ack for longer than the
+    /// timeout specified in [`WindowFullPolicy::DisconnectOnWindowFull`].
+    ///
+    /// The timeout is only reported once per stall.
+    pub(crate) fn take_window_full_timeout(&mut self) -> bool {
+        let (Some(timeout), Some(since)) = (self.window_full_timeout, self.window_full_since)
+        else {
+            return false;
+        };
+        if self.current_time - since > timeout {
+            self.window_full_since = None;
+            return true;
+        }
+        false
+    }
+
+    /// Ids of the messages that have not been acked yet, oldest first
+    pub(crate) fn pending_messages(&self) -> impl Iterator<Item = MessageId> + '_ {
+        self.unacked_messages.keys().copied()
+    }
+
+    /// Stop sending the message `message_id`: it won't be sent or resent anymore.
+    ///
+    /// Returns false if the message is not pending anymore (for example because it was already acked)
+    pub(crate) fn cancel_message(&mut self, message_id: MessageId) -> bool {
+        if self.unacked_messages.remove(&message_id).is_none() {
+            return false;
+        }
+        trace!("Cancelled reliable message {:?}", message_id);
+        self.window_progress();
+        true
+    }
+
+    /// Number of messages that have been sent at least once and are still waiting for an ack
+    fn in_flight_messages(&self, window_size: usize) -> usize {
+        self.unacked_messages
+            .values()
+            .take(window_size)
+            .filter(|m| m.unacked_message.is_sent())
+            .count()
+    }
+
+    /// Update the window-full state after new messages were sent
+    fn update_window_state(&mut self) {
+        let Some(max_unacked_messages) = self.reliable_settings.max_unacked_messages else {
+            return;
+        };
+        if self.in_flight_messages(max_unacked_messages) < max_unacked_messages {
+            self.window_full_since = None;
+            self.window_full_notified = false;
+            return;
+        }
+        if self.window_full_since.is_none() {
+            self.window_full_since = Some(self.current_time);
+        }
+        // only notify if some messages actually have to wait for the window to free up
+        if !self.window_full_notified && self.unacked_messages.len() > max_unacked_messages {
+            trace!(
+                "Reliable window is full: {:?} unacked messages",
+                self.unacked_messages.len()
+            );
+            self.window_full_notified = true;
+            self.window_full_event = true;
+        }
+    }
+
+    /// An in-flight message got acked: restart the window-full timer
+    fn window_progress(&mut self) {
+        if self.window_full_since.is_some() {
+            self.window_full_since = Some(self.current_time);
         }
     }
 }
@@ -107,6 +224,7 @@ impl ChannelSend for ReliableSender {
     fn update(&mut self, time_manager: &TimeManager, ping_manager: &PingManager, _: &TickManager) {
         self.current_time = time_manager.current_time();
         self.current_rtt = ping_manager.rtt();
+        self.current_jitter = ping_manager.jitter();
         if let Some(timer) = &mut self.timer {
             timer.tick(time_manager.delta());
             self.priority_multiplier =
@@ -127,6 +245,34 @@ impl ChannelSend for ReliableSender {
         priority: f32,
     ) -> Result<Option<MessageId>, SerializationError> {
         let message_id = self.next_send_message_id;
+        if let (WindowFullPolicy::DropOldest, Some(max_unacked_messages)) = (
+            self.reliable_settings.window_full_policy,
+            self.reliable_settings.max_unacked_messages,
+        ) {
+            // make room in the window by dropping the oldest in-flight message
+            // (messages that were never sent are not taking room in the window)
+            let dropped_id =
+                if self.in_flight_messages(max_unacked_messages) >= max_unacked_messages {
+                    self.unacked_messages
+                        .iter()
+                        .find(|(_, m)| m.unacked_message.is_sent())
+                        .map(|(id, _)| *id)
+                } else {
+                    None
+                };
+            if let Some(dropped_id) = dropped_id {
+                self.unacked_messages.remove(&dropped_id);
+                trace!(
+                    "Reliable window is full, dropping oldest message {:?}",
+                    dropped_id
+                );
+                self.send_nacks(dropped_id);
+                if !self.window_full_notified {
+                    self.window_full_notified = true;
+                    self.window_full_event = true;
+                }
+            }
+        }
         let unacked_message = if message.len() > self.fragment_sender.fragment_size {
             let fragments = self
                 .fragment_sender
@@ -171,10 +317,12 @@ impl ChannelSend for ReliableSender {
         // Collect the list of messages that need to be sent
         // Either because they have never been sent, or because they need to be resent
 
-        // resend delay is based on the rtt
-        let resend_delay =
-            chrono::Duration::from_std(self.reliable_settings.resend_delay(self.current_rtt))
-                .unwrap();
+        // resend delay is based on the rtt and jitter
+        let resend_delay = chrono::Duration::from_std(
+            self.reliable_settings
+                .resend_delay(self.current_rtt, self.current_jitter),
+        )
+        .unwrap();
         let should_send = |last_sent: &Option<WrappedTime>| -> bool {
             match last_sent {
                 // send if the message has never been sent
@@ -184,8 +332,17 @@ impl ChannelSend for ReliableSender {
             }
         };
 
+        // only the messages inside the window can be sent; the other ones have to wait
+        // until the older messages are acked
+        let window_size = self
+            .reliable_settings
+            .max_unacked_messages
+            .unwrap_or(usize::MAX);
+
         // Iterate through all unacked messages, oldest message ids first
-        for (message_id, unacked_message_with_priority) in self.unacked_messages.iter_mut() {
+        for (message_id, unacked_message_with_priority) in
+            self.unacked_messages.iter_mut().take(window_size)
+        {
             // accumulate the priority for all messages (including the ones that were just added, since we set the accumulated priority to 0.0)
             unacked_message_with_priority.accumulated_priority +=
                 unacked_message_with_priority.base_priority * self.priority_multiplier;
@@ -246,6 +403,7 @@ impl ChannelSend for ReliableSender {
         // TODO: is this message_ids_to_send even useful? in which situation would we send the same message twice?
         // right now, we send everything; so we can reset
         self.message_ids_to_send.clear();
+        self.update_window_state();
         if !self.single_messages_to_send.is_empty() {
             trace!(
                 "Single messages to send: {:?}",
@@ -291,6 +449,7 @@ impl ChannelSend for ReliableSender {
                         sender.send(message_ack.message_id).unwrap();
                     }
                     self.unacked_messages.remove(&message_ack.message_id);
+                    self.window_progress();
                 }
                 UnackedMessage::Fragmented(fragment_acks) => {
                     let Some(fragment_id) = message_ack.fragment_id else {
@@ -306,6 +465,7 @@ impl ChannelSend for ReliableSender {
                                 sender.send(message_ack.message_id).unwrap();
                             }
                         }
+                        self.window_progress();
                     }
                 }
             }
@@ -351,6 +511,7 @@ mod tests {
             ReliableSettings {
                 rtt_resend_factor: 1.5,
                 rtt_resend_min_delay: Duration::from_millis(100),
+                ..Default::default()
             },
             Duration::default(),
         );
@@ -396,4 +557,237 @@ mod tests {
         let (single, _) = sender.send_packet();
         assert_eq!(single.len(), 0);
     }
+
+    /// On a high-latency link, messages are not resent before the resend delay computed from
+    /// the RTT and jitter estimates
+    #[test]
+    fn test_reliable_resend_delay_high_latency() {
+        let mut sender = ReliableSender::new(ReliableSettings::default(), Duration::default());
+        sender.current_rtt = Duration::from_millis(400);
+        sender.current_jitter = Duration::from_millis(25);
+        sender.current_time = WrappedTime::new(0);
+
+        sender.buffer_send(Bytes::from("hello"), 1.0).unwrap();
+        let (single, _) = sender.send_packet();
+        assert_eq!(single.len(), 1);
+
+        // 1.5 * rtt + 4 * jitter = 700ms
+        sender.current_time += Duration::from_millis(650);
+        let (single, _) = sender.send_packet();
+        assert_eq!(single.len(), 0);
+
+        sender.current_time += Duration::from_millis(100);
+        let (single, _) = sender.send_packet();
+        assert_eq!(single.len(), 1);
+    }
+
+    /// The resend delay is bounded by `rtt_resend_max_delay`
+    #[test]
+    fn test_reliable_resend_max_delay() {
+        let mut sender = ReliableSender::new(
+            ReliableSettings {
+                rtt_resend_max_delay: Some(Duration::from_millis(200)),
+                ..Default::default()
+            },
+            Duration::default(),
+        );
+        sender.current_rtt = Duration::from_millis(400);
+        sender.current_time = WrappedTime::new(0);
+
+        sender.buffer_send(Bytes::from("hello"), 1.0).unwrap();
+        let (single, _) = sender.send_packet();
+        assert_eq!(single.len(), 1);
+
+        sender.current_time += Duration::from_millis(150);
+        let (single, _) = sender.send_packet();
+        assert_eq!(single.len(), 0);
+
+        sender.current_time += Duration::from_millis(100);
+        let (single, _) = sender.send_packet();
+        assert_eq!(single.len(), 1);
+    }
+
+    fn window_sender(window_full_policy: WindowFullPolicy) -> ReliableSender {
+        let mut sender = ReliableSender::new(
+            ReliableSettings {
+                rtt_resend_factor: 1.5,
+                rtt_resend_min_delay: Duration::from_millis(100),
+                max_unacked_messages: Some(2),
+                window_full_policy,
+                ..Default::default()
+            },
+            Duration::default(),
+        );
+        sender.current_rtt = Duration::from_millis(100);
+        sender.current_time = WrappedTime::new(0);
+        sender
+    }
+
+    fn ack(sender: &mut ReliableSender, message_id: u16) {
+        sender.receive_ack(&MessageAck {
+            message_id: MessageId(message_id),
+            fragment_id: None,
+        });
+    }
+
+    /// Buffering messages that haven't been sent yet does not fill the window
+    #[test]
+    fn test_reliable_window_counts_in_flight_messages() {
+        let mut sender = window_sender(WindowFullPolicy::StallClient);
+        for _ in 0..2 {
+            sender.buffer_send(Bytes::from("hello"), 1.0).unwrap();
+        }
+        assert!(!sender.is_window_full());
+
+        // the window is full once the messages are in-flight, but no messages are waiting
+        // so there is no event
+        let (single, _) = sender.send_packet();
+        assert_eq!(single.len(), 2);
+        assert!(sender.is_window_full());
+        assert!(!sender.take_window_full_event());
+    }
+
+    /// With the StallClient policy, only the messages inside the window are sent
+    /// until the older messages get acked
+    #[test]
+    fn test_reliable_window_full_stall() {
+        let mut sender = window_sender(WindowFullPolicy::StallClient);
+        for _ in 0..3 {
+            sender.buffer_send(Bytes::from("hello"), 1.0).unwrap();
+        }
+        let (single, _) = sender.send_packet();
+        assert_eq!(single.len(), 2);
+        assert!(sender.is_window_full());
+        assert!(sender.take_window_full_event());
+        // the event is only emitted once per stall
+        let (single, _) = sender.send_packet();
+        assert!(single.is_empty());
+        assert!(!sender.take_window_full_event());
+
+        // ack the first message: the third message can now be sent
+        ack(&mut sender, 0);
+        let (single, _) = sender.send_packet();
+        assert_eq!(single.len(), 1);
+        assert_eq!(
+            single.front().unwrap().data,
+            SingleData::new(Some(MessageId(2)), Bytes::from("hello")).into()
+        );
+
+        // once all messages are acked, the window is free again
+        ack(&mut sender, 1);
+        ack(&mut sender, 2);
+        sender.send_packet();
+        assert!(!sender.is_window_full());
+        // the StallClient policy never times out
+        sender.current_time += Duration::from_secs(10);
+        assert!(!sender.take_window_full_timeout());
+    }
+
+    /// With the DropOldest policy, the oldest in-flight message is dropped to make room for the new one
+    #[test]
+    fn test_reliable_window_full_drop_oldest() {
+        let mut sender = window_sender(WindowFullPolicy::DropOldest);
+        let nack_receiver = sender.subscribe_nacks();
+        for _ in 0..2 {
+            sender.buffer_send(Bytes::from("hello"), 1.0).unwrap();
+        }
+        sender.send_packet();
+        assert!(sender.is_window_full());
+
+        sender.buffer_send(Bytes::from("hello"), 1.0).unwrap();
+        assert_eq!(sender.unacked_messages.len(), 2);
+        assert!(!sender.unacked_messages.contains_key(&MessageId(0)));
+        assert_eq!(nack_receiver.try_recv(), Ok(MessageId(0)));
+        assert!(sender.take_window_full_event());
+    }
+
+    /// With the DropOldest policy, buffering several messages in the same frame only drops
+    /// messages that were actually sent, to make room for the window
+    #[test]
+    fn test_reliable_window_full_drop_oldest_same_frame() {
+        let mut sender = window_sender(WindowFullPolicy::DropOldest);
+        let nack_receiver = sender.subscribe_nacks();
+        for _ in 0..2 {
+            sender.buffer_send(Bytes::from("hello"), 1.0).unwrap();
+        }
+        sender.send_packet();
+
+        // only the message 0 has to be dropped to make room for the new messages
+        for _ in 0..3 {
+            sender.buffer_send(Bytes::from("hello"), 1.0).unwrap();
+        }
+        assert_eq!(
+            sender.unacked_messages.keys().copied().collect::<Vec<_>>(),
+            vec![MessageId(1), MessageId(2), MessageId(3), MessageId(4)]
+        );
+        assert_eq!(nack_receiver.try_recv(), Ok(MessageId(0)));
+        assert!(nack_receiver.try_recv().is_err());
+
+        // the next message that was not sent yet is sent in the window
+        let (single, _) = sender.send_packet();
+        assert_eq!(single.len(), 1);
+        assert_eq!(
+            single.front().unwrap().data,
+            SingleData::new(Some(MessageId(2)), Bytes::from("hello")).into()
+        );
+    }
+
+    /// With the DisconnectOnWindowFull policy, the window times out if no acks are received
+    #[test]
+    fn test_reliable_window_full_disconnect() {
+        let mut sender = window_sender(WindowFullPolicy::DisconnectOnWindowFull {
+            timeout: Duration::from_millis(500),
+        });
+        for _ in 0..3 {
+            sender.buffer_send(Bytes::from("hello"), 1.0).unwrap();
+        }
+        sender.send_packet();
+        assert!(sender.is_window_full());
+
+        sender.current_time += Duration::from_millis(400);
+        sender.send_packet();
+        assert!(!sender.take_window_full_timeout());
+        sender.current_time += Duration::from_millis(200);
+        sender.send_packet();
+        assert!(sender.take_window_full_timeout());
+        // the timeout is only reported once
+        assert!(!sender.take_window_full_timeout());
+    }
+
+    /// The timeout measures the time without acks, not how long the queue stays long:
+    /// a client that keeps acking messages must not time out
+    #[test]
+    fn test_reliable_window_full_acks_flowing_no_timeout() {
+        let mut sender = window_sender(WindowFullPolicy::DisconnectOnWindowFull {
+            timeout: Duration::from_millis(500),
+        });
+        for _ in 0..4 {
+            sender.buffer_send(Bytes::from("hello"), 1.0).unwrap();
+        }
+        sender.send_packet();
+        for i in 0..20 {
+            // the producer keeps the queue at or above the window size
+            sender.buffer_send(Bytes::from("hello"), 1.0).unwrap();
+            sender.current_time += Duration::from_millis(300);
+            ack(&mut sender, i);
+            sender.send_packet();
+            assert!(sender.unacked_messages.len() >= 2);
+            assert!(sender.is_window_full());
+            assert!(!sender.take_window_full_timeout());
+        }
+    }
+
+    /// A timeout larger than what chrono can represent never triggers
+    #[test]
+    fn test_reliable_window_full_max_timeout() {
+        let mut sender = window_sender(WindowFullPolicy::DisconnectOnWindowFull {
+            timeout: Duration::MAX,
+        });
+        for _ in 0..2 {
+            sender.buffer_send(Bytes::from("hello"), 1.0).unwrap();
+        }
+        sender.send_packet();
+        sender.current_time += Duration::from_secs(1000);
+        assert!(!sender.take_window_full_timeout());
+    }
 }
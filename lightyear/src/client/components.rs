@@ -3,10 +3,13 @@ Defines components that are used for the client-side prediction and interpolatio
 */
 use std::fmt::Debug;
 
-use bevy::prelude::{Component, Entity, ReflectComponent};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::{Component, Entity, Has, Query, ReflectComponent, World};
 use bevy::reflect::Reflect;
 
-use crate::prelude::{Message, Tick};
+use crate::client::interpolation::Interpolated;
+use crate::client::prediction::Predicted;
+use crate::prelude::{Message, Replicated, Tick};
 
 /// Marks an entity that directly applies the replication updates from the remote
 ///
@@ -26,6 +29,75 @@ pub struct Confirmed {
     pub tick: Tick,
 }
 
+/// Role of a client entity in the replication of a server entity
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplicationMode {
+    /// The entity directly applies the replication updates from the server
+    /// (it has the [`Confirmed`] or the [`Replicated`] component)
+    Confirmed,
+    /// The entity is predicted (it has the [`Predicted`] component)
+    Predicted,
+    /// The entity is interpolated (it has the [`Interpolated`] component)
+    Interpolated,
+}
+
+impl ReplicationMode {
+    fn from_markers(predicted: bool, interpolated: bool, confirmed: bool) -> Option<Self> {
+        if predicted {
+            Some(ReplicationMode::Predicted)
+        } else if interpolated {
+            Some(ReplicationMode::Interpolated)
+        } else if confirmed {
+            Some(ReplicationMode::Confirmed)
+        } else {
+            None
+        }
+    }
+}
+
+/// Returns the [`ReplicationMode`] of the entity, or `None` if the entity is not replicated from the server
+pub fn replication_mode(entity: Entity, world: &World) -> Option<ReplicationMode> {
+    let entity = world.get_entity(entity).ok()?;
+    ReplicationMode::from_markers(
+        entity.contains::<Predicted>(),
+        entity.contains::<Interpolated>(),
+        entity.contains::<Confirmed>() || entity.contains::<Replicated>(),
+    )
+}
+
+/// [`SystemParam`] to get the [`ReplicationMode`] of entities
+///
+/// ```rust,ignore
+/// fn add_smoothing(mut commands: Commands, modes: ReplicationModes, query: Query<Entity, Added<Sprite>>) {
+///     for entity in query.iter() {
+///         if modes.get(entity) == Some(ReplicationMode::Interpolated) {
+///             commands.entity(entity).insert(Smoothing);
+///         }
+///     }
+/// }
+/// ```
+#[derive(SystemParam)]
+pub struct ReplicationModes<'w, 's> {
+    query: Query<
+        'w,
+        's,
+        (
+            Has<Predicted>,
+            Has<Interpolated>,
+            Has<Confirmed>,
+            Has<Replicated>,
+        ),
+    >,
+}
+
+impl ReplicationModes<'_, '_> {
+    /// Returns the [`ReplicationMode`] of the entity, or `None` if the entity is not replicated from the server
+    pub fn get(&self, entity: Entity) -> Option<ReplicationMode> {
+        let (predicted, interpolated, confirmed, replicated) = self.query.get(entity).ok()?;
+        ReplicationMode::from_markers(predicted, interpolated, confirmed || replicated)
+    }
+}
+
 pub trait SyncComponent: Component + Clone + PartialEq + Message {}
 impl<T> SyncComponent for T where T: Component + Clone + PartialEq + Message {}
 
@@ -68,3 +140,70 @@ pub enum ComponentSyncMode {
     /// The component is not copied from the Confirmed entity to the interpolated/predicted entity
     None,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::server::{Replicate, SyncTarget};
+    use crate::prelude::{client, NetworkTarget};
+    use crate::tests::protocol::ComponentSyncModeFull;
+    use crate::tests::stepper::BevyStepper;
+    use bevy::ecs::system::RunSystemOnce;
+
+    #[test]
+    fn test_replication_mode() {
+        let mut stepper = BevyStepper::default();
+        let synced = stepper
+            .server_app
+            .world_mut()
+            .spawn((
+                ComponentSyncModeFull(1.0),
+                Replicate {
+                    sync: SyncTarget {
+                        prediction: NetworkTarget::All,
+                        interpolation: NetworkTarget::All,
+                    },
+                    ..Default::default()
+                },
+            ))
+            .id();
+        let simple = stepper
+            .server_app
+            .world_mut()
+            .spawn((ComponentSyncModeFull(1.0), Replicate::default()))
+            .id();
+        for _ in 0..10 {
+            stepper.frame_step();
+        }
+        let world = stepper.client_app.world();
+        let remote_entity_map = &world
+            .resource::<client::ConnectionManager>()
+            .replication_receiver
+            .remote_entity_map;
+        let confirmed = remote_entity_map.get_local(synced).unwrap();
+        let simple = remote_entity_map.get_local(simple).unwrap();
+        let confirmed_component = world.get::<Confirmed>(confirmed).unwrap();
+        let predicted = confirmed_component.predicted.unwrap();
+        let interpolated = confirmed_component.interpolated.unwrap();
+        let local = stepper.client_app.world_mut().spawn_empty().id();
+
+        let expected = [
+            (confirmed, Some(ReplicationMode::Confirmed)),
+            (simple, Some(ReplicationMode::Confirmed)),
+            (predicted, Some(ReplicationMode::Predicted)),
+            (interpolated, Some(ReplicationMode::Interpolated)),
+            (local, None),
+        ];
+        for (entity, mode) in expected {
+            assert_eq!(replication_mode(entity, stepper.client_app.world()), mode);
+        }
+        let modes = stepper
+            .client_app
+            .world_mut()
+            .run_system_once(move |modes: ReplicationModes| {
+                expected.map(|(entity, _)| modes.get(entity))
+            })
+            .unwrap();
+        assert_eq!(modes, expected.map(|(_, mode)| mode));
+    }
+}
@@ -1,30 +1,34 @@
 //! Specify how a Client sends/receives messages with a Server
 use bevy::ecs::component::Tick as BevyTick;
-use bevy::ecs::entity::MapEntities;
-use bevy::prelude::{Resource, World};
+use bevy::ecs::entity::{EntityHashMap, MapEntities};
+use bevy::prelude::{Entity, Resource, World};
 use bevy::utils::Duration;
-#[cfg(feature = "leafwing")]
-use bevy::utils::HashMap;
 use bytes::Bytes;
 use tracing::{debug, trace, trace_span};
 
 use crate::channel::builder::{
-    EntityActionsChannel, EntityUpdatesChannel, PingChannel, PongChannel,
+    AuthorityChannel, EntityActionsChannel, EntityReadyChannel, EntityUpdatesChannel,
+    IntentChannel, PingChannel, PongChannel,
 };
 
 use crate::channel::receivers::ChannelReceive;
 use crate::channel::senders::ChannelSend;
 use crate::client::config::ClientConfig;
 use crate::client::error::ClientError;
+use crate::client::replication::ReplicationConvergence;
 use crate::client::sync::SyncConfig;
 use crate::connection::netcode::MAX_PACKET_SIZE;
+use crate::packet::compression::PacketCompressor;
 use crate::packet::message_manager::MessageManager;
 use crate::packet::packet_builder::{Payload, RecvPayload};
 use crate::packet::priority_manager::PriorityConfig;
 use crate::prelude::client::PredictionConfig;
-use crate::prelude::{ChannelKind, ClientId, Message, MessageRegistry, ReplicationConfig};
+use crate::prelude::{
+    Channel, ChannelKind, ClientId, Message, MessageId, MessageRegistry, ReplicationConfig,
+};
 use crate::protocol::channel::ChannelRegistry;
 use crate::protocol::component::ComponentRegistry;
+use crate::protocol::message::intent::IntentMessage;
 use crate::protocol::registry::NetId;
 use crate::serialize::reader::Reader;
 use crate::serialize::writer::Writer;
@@ -33,7 +37,9 @@ use crate::server::error::ServerError;
 use crate::shared::events::connection::ConnectionEvents;
 use crate::shared::ping::manager::{PingConfig, PingManager};
 use crate::shared::ping::message::{Ping, Pong};
+use crate::shared::replication::authority::AuthorityRequest;
 use crate::shared::replication::delta::DeltaManager;
+use crate::shared::replication::ready::EntityReady;
 use crate::shared::replication::receive::ReplicationReceiver;
 use crate::shared::replication::send::ReplicationSender;
 use crate::shared::replication::{EntityActionsMessage, EntityUpdatesMessage, ReplicationSend};
@@ -67,6 +73,8 @@ use super::sync::SyncManager;
 pub struct ConnectionManager {
     pub(crate) message_registry: MessageRegistry,
     pub(crate) message_manager: MessageManager,
+    /// Compresses the packets sent to the server and decompresses the packets received from it
+    compressor: PacketCompressor,
     pub(crate) delta_manager: DeltaManager,
     pub(crate) replication_sender: ReplicationSender,
     pub replication_receiver: ReplicationReceiver,
@@ -74,9 +82,6 @@ pub struct ConnectionManager {
     pub ping_manager: PingManager,
     pub(crate) sync_manager: SyncManager,
 
-    /// Used to read the leafwing InputMessages from other clients
-    #[cfg(feature = "leafwing")]
-    pub(crate) received_leafwing_input_messages: HashMap<NetId, Vec<Bytes>>,
     /// Used to transfer raw bytes to a system that can convert the bytes to the actual type
     pub(crate) received_messages: Vec<(NetId, Bytes)>,
     pub(crate) writer: Writer,
@@ -86,6 +91,22 @@ pub struct ConnectionManager {
     /// - in host server mode, we deserialize the bytes and push them to the server's Message Events queue directly
     /// - in non-host server mode, we buffer the bytes to the message manager as usual
     pub(crate) messages_to_send: Vec<(Bytes, ChannelKind)>,
+    /// True if the client is the local client of a host-server, in which case the messages
+    /// are not buffered in the message manager
+    pub(crate) host_server: bool,
+
+    /// Tick of the initial replication snapshot sent by the server
+    pub(crate) initial_replication_tick: Option<Tick>,
+
+    /// Previous states of the interpolated entities, sent by the server along with their spawn.
+    /// The entities are the entities in the server's World.
+    pub(crate) interpolation_snapshots: EntityHashMap<Vec<(Tick, Vec<Bytes>)>>,
+
+    /// Measured time between a replicated change on the server and its application on the client
+    pub(crate) replication_convergence: ReplicationConvergence,
+
+    /// Reason sent by the server when it shuts down gracefully
+    pub(crate) server_shutdown_reason: Option<String>,
 }
 
 // NOTE: useful when we sometimes need to create a temporary fake ConnectionManager
@@ -106,17 +127,21 @@ impl Default for ConnectionManager {
                 0.0,
                 PriorityConfig::default(),
             ),
+            compressor: PacketCompressor::default(),
             delta_manager: DeltaManager::default(),
             replication_sender,
             replication_receiver,
             ping_manager: PingManager::new(PingConfig::default()),
             sync_manager: SyncManager::new(SyncConfig::default(), PredictionConfig::default()),
             events: ConnectionEvents::default(),
-            #[cfg(feature = "leafwing")]
-            received_leafwing_input_messages: HashMap::default(),
             received_messages: Vec::default(),
             writer: Writer::with_capacity(0),
             messages_to_send: Vec::default(),
+            host_server: false,
+            initial_replication_tick: None,
+            interpolation_snapshots: EntityHashMap::default(),
+            replication_convergence: ReplicationConvergence::default(),
+            server_shutdown_reason: None,
         }
     }
 }
@@ -157,17 +182,21 @@ impl ConnectionManager {
         Self {
             message_registry: message_registry.clone(),
             message_manager,
+            compressor: PacketCompressor::new(client_config.packet.compression),
             delta_manager: DeltaManager::default(),
             replication_sender,
             replication_receiver,
             ping_manager: PingManager::new(client_config.ping),
             sync_manager: SyncManager::new(client_config.sync, client_config.prediction),
             events: ConnectionEvents::default(),
-            #[cfg(feature = "leafwing")]
-            received_leafwing_input_messages: HashMap::default(),
             received_messages: Vec::default(),
             writer: Writer::with_capacity(MAX_PACKET_SIZE),
             messages_to_send: Vec::default(),
+            host_server: false,
+            initial_replication_tick: None,
+            interpolation_snapshots: EntityHashMap::default(),
+            replication_convergence: ReplicationConvergence::default(),
+            server_shutdown_reason: None,
         }
     }
 
@@ -177,6 +206,78 @@ impl ConnectionManager {
         self.sync_manager.is_synced()
     }
 
+    /// Notify the server that a replicated entity is fully set up on the client
+    /// (for example once its assets are loaded).
+    ///
+    /// The entity must be the local entity that was replicated from the server; for predicted or
+    /// interpolated entities, use the [`Confirmed`](crate::prelude::client::Confirmed) entity.
+    /// The server can check it with
+    /// [`is_client_entity_ready`](crate::prelude::server::ConnectionManager::is_client_entity_ready).
+    pub fn mark_entity_ready(&mut self, entity: Entity) -> Result<(), ClientError> {
+        self.send_message::<EntityReadyChannel, _>(&EntityReady { entity })
+    }
+
+    /// Ask the server for authority over an entity.
+    ///
+    /// The server receives it as an [`AuthorityRequestEvent`](crate::prelude::server::AuthorityRequestEvent)
+    /// and decides whether to transfer the authority to this client.
+    /// The entity must be the local entity that was replicated from the server.
+    pub fn request_authority(&mut self, entity: Entity) -> Result<(), ClientError> {
+        self.send_message::<AuthorityChannel, _>(&AuthorityRequest { entity })
+    }
+
+    /// Submit an intent to the server.
+    ///
+    /// The server receives it as an [`IntentEvent`](crate::prelude::server::IntentEvent), and broadcasts
+    /// the result of its execution to all clients. The intent type must be registered with
+    /// [`register_intent`](crate::prelude::AppIntentExt::register_intent).
+    pub fn submit_intent<I: Message>(&mut self, intent: I) -> Result<(), ClientError> {
+        self.send_message::<IntentChannel, _>(&IntentMessage { intent })
+    }
+
+    /// Ids of the messages sent on the reliable channel `C` that have not been acked yet, oldest first
+    pub fn pending_messages<C: Channel>(&self) -> Result<Vec<MessageId>, ClientError> {
+        Ok(self
+            .message_manager
+            .pending_messages(ChannelKind::of::<C>())?)
+    }
+
+    /// Cancel a message sent on the reliable channel `C`, so that it is not resent if it has not been
+    /// received yet.
+    ///
+    /// Returns false if the message was already acked. Messages cannot be cancelled on
+    /// [`OrderedReliable`](crate::prelude::ChannelMode::OrderedReliable) channels.
+    pub fn cancel_message<C: Channel>(
+        &mut self,
+        message_id: MessageId,
+    ) -> Result<bool, ClientError> {
+        Ok(self
+            .message_manager
+            .cancel_message(ChannelKind::of::<C>(), message_id)?)
+    }
+
+    /// Tick at which the server sent the initial replication snapshot to this client.
+    ///
+    /// All the entities that were replicated to the client when it connected are confirmed at this tick.
+    /// Returns None until the server notifies the client.
+    pub fn initial_replication_tick(&self) -> Option<Tick> {
+        self.initial_replication_tick
+    }
+
+    /// Measured jitter of the arrival times of server packets.
+    ///
+    /// This includes the variations of the server send timing (for example because of variable
+    /// server frame times). It can be absorbed by the interpolation buffer with
+    /// [`InterpolationConfig::send_jitter_margin`](crate::prelude::client::InterpolationConfig::send_jitter_margin).
+    pub fn server_send_jitter(&self) -> Duration {
+        self.sync_manager.server_send_jitter
+    }
+
+    /// Statistics about how long it takes for a change on the server to be applied on the client
+    pub fn replication_convergence(&self) -> &ReplicationConvergence {
+        &self.replication_convergence
+    }
+
     /// Amount of input delay applied
     pub(crate) fn input_delay_ticks(&self) -> u16 {
         self.sync_manager.current_input_delay
@@ -202,7 +303,8 @@ impl ConnectionManager {
     ) {
         self.message_manager
             .update(time_manager, &self.ping_manager, tick_manager);
-        self.replication_sender.update(world_tick);
+        self.replication_sender
+            .update(world_tick, time_manager.current_time());
         self.ping_manager.update(time_manager);
 
         // (we update the sync manager in POST_UPDATE)
@@ -291,6 +393,17 @@ impl ConnectionManager {
         Ok(())
     }
 
+    /// Buffer the messages that we want to send into the message manager
+    pub(crate) fn buffer_messages_to_send(&mut self) -> Result<(), ClientError> {
+        self.messages_to_send
+            .drain(..)
+            .try_for_each(|(message_bytes, channel_kind)| {
+                self.message_manager
+                    .buffer_send(message_bytes, channel_kind)?;
+                Ok::<(), ClientError>(())
+            })
+    }
+
     /// Send packets that are ready to be sent.
     /// In non-host-server mode:
     /// - go through messages_to_send, buffer them to the message manager and then send packets that are ready
@@ -322,21 +435,19 @@ impl ConnectionManager {
                 Ok::<(), ClientError>(())
             })?;
 
-        // buffer the messages into the message manager
-        self.messages_to_send
-            .drain(..)
-            .try_for_each(|(message_bytes, channel_kind)| {
-                self.message_manager
-                    .buffer_send(message_bytes, channel_kind)?;
-                Ok::<(), ClientError>(())
-            })?;
+        self.buffer_messages_to_send()?;
 
         // get the payloads from the message manager
-        let payloads = self.message_manager.send_packets(tick_manager.tick());
+        let payloads = self
+            .message_manager
+            .send_packets(tick_manager.tick())?
+            .into_iter()
+            .map(|payload| self.compressor.compress(payload))
+            .collect();
 
         // update the replication sender about which messages were actually sent, and accumulate priority
         self.replication_sender.recv_send_notification();
-        payloads.map_err(Into::into)
+        Ok(payloads)
     }
 
     pub(crate) fn receive(
@@ -423,10 +534,26 @@ impl ConnectionManager {
                 tick_manager.tick(),
                 &mut self.events,
             );
+            self.record_replication_convergence(tick_manager.config.tick_duration);
         }
         Ok(())
     }
 
+    /// Record the convergence time of the replication messages that were just applied
+    fn record_replication_convergence(&mut self, tick_duration: Duration) {
+        let Some(latest_server_tick) = self.sync_manager.latest_received_server_tick else {
+            return;
+        };
+        // estimate of the time elapsed on the server since the latest server tick we received
+        let elapsed = self.sync_manager.duration_since_latest_received_server_tick
+            + self.ping_manager.rtt() / 2;
+        for remote_tick in self.replication_receiver.applied_ticks.drain(..) {
+            let ticks_since_change = (latest_server_tick - remote_tick).max(0) as u32;
+            self.replication_convergence
+                .record(tick_duration * ticks_since_change + elapsed);
+        }
+    }
+
     /// Receive a message from the server
     pub(crate) fn receive_message(&mut self, mut reader: Reader) -> Result<(), SerializationError> {
         // identify the type of message
@@ -442,6 +569,7 @@ impl ConnectionManager {
         tick_manager: &TickManager,
         component_registry: &ComponentRegistry,
     ) -> Result<(), ClientError> {
+        let packet = self.compressor.decompress(packet)?;
         // receive the packets, buffer them, update any sender that were waiting for their sent messages to be acked
         let tick = self.message_manager.recv_packet(packet)?;
         trace!("Received server packet with tick: {:?}", tick);
@@ -451,6 +579,8 @@ impl ConnectionManager {
             .map_or(true, |server_tick| tick >= server_tick)
         {
             trace!("new last recv server tick: {:?}", tick);
+            self.sync_manager
+                .record_server_packet_arrival(tick, tick_manager.config.tick_duration);
             self.sync_manager.latest_received_server_tick = Some(tick);
             // TODO: add 'received_new_server_tick' ?
             // we probably actually physically received the packet some time between our last `receive` and now.
@@ -484,6 +614,12 @@ impl ReplicationReceive for ConnectionManager {
     fn cleanup(&mut self, tick: Tick) {
         self.replication_receiver.cleanup(tick);
     }
+
+    fn get_local_entity(&self, remote_entity: Entity, _: Option<ClientId>) -> Option<Entity> {
+        self.replication_receiver
+            .remote_entity_map
+            .get_local(remote_entity)
+    }
 }
 
 impl ReplicationSend for ConnectionManager {
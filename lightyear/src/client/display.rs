@@ -0,0 +1,148 @@
+//! Smoothed display values for server-authoritative components
+//!
+//! Some components should always hold the authoritative value sent by the server (for example `Health`,
+//! which is used by gameplay systems), but should be displayed smoothly (for example a health bar
+//! that decreases progressively instead of snapping to the new value).
+//!
+//! Registering a component with [`add_display_smoothing`](crate::prelude::ComponentRegistration::add_display_smoothing)
+//! adds a [`DisplayValue<C>`] component on the replicated entities of the client. The component `C`
+//! is still updated with the server's value, while `DisplayValue<C>` moves every frame towards it.
+//!
+//! This is different from:
+//! - prediction: no inputs are involved and the authoritative value is never predicted
+//! - interpolation: the display value follows the latest authoritative value, not a buffer of past server states
+use bevy::prelude::*;
+
+use crate::client::components::SyncComponent;
+use crate::prelude::{Linear, MainSet, Replicated};
+
+/// Value of the component `C` that should be used for display.
+///
+/// It moves smoothly towards the authoritative value of `C` replicated from the server.
+/// Gameplay systems should read `C` directly.
+#[derive(Component, Debug, Clone, PartialEq, Reflect, Deref, DerefMut)]
+pub struct DisplayValue<C>(pub C);
+
+/// Smoothing rate used to update the [`DisplayValue<C>`]
+#[derive(Resource, Debug)]
+struct DisplaySmoothing<C> {
+    /// After `1 / rate` seconds, ~63% of the gap between the display value and the authoritative value is closed
+    rate: f32,
+    _marker: std::marker::PhantomData<C>,
+}
+
+pub(crate) fn add_display_smoothing_systems<C: SyncComponent + Linear>(app: &mut App, rate: f32) {
+    app.insert_resource(DisplaySmoothing::<C> {
+        rate,
+        _marker: std::marker::PhantomData,
+    });
+    app.add_systems(PreUpdate, insert_display_value::<C>.after(MainSet::Receive));
+    app.add_systems(PostUpdate, update_display_value::<C>);
+}
+
+/// When the component is first replicated, the display value starts at the authoritative value
+fn insert_display_value<C: SyncComponent>(
+    mut commands: Commands,
+    query: Query<(Entity, &C), (With<Replicated>, Without<DisplayValue<C>>)>,
+) {
+    for (entity, component) in query.iter() {
+        commands
+            .entity(entity)
+            .insert(DisplayValue(component.clone()));
+    }
+}
+
+/// Move the display value towards the authoritative value
+fn update_display_value<C: SyncComponent + Linear>(
+    time: Res<Time>,
+    smoothing: Res<DisplaySmoothing<C>>,
+    mut query: Query<(&C, &mut DisplayValue<C>)>,
+) {
+    let t = 1.0 - (-smoothing.rate * time.delta_secs()).exp();
+    for (component, mut display) in query.iter_mut() {
+        if display.0 != *component {
+            display.0 = C::lerp(&display.0, component, t);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::server::Replicate;
+    use crate::prelude::*;
+    use crate::tests::protocol::ComponentSyncModeFull2;
+    use crate::tests::stepper::BevyStepper;
+
+    #[test]
+    fn test_display_value_approaches_server_value() {
+        let mut stepper = BevyStepper::default();
+        let server_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn((ComponentSyncModeFull2(100.0), Replicate::default()))
+            .id();
+        stepper.frame_step();
+        stepper.frame_step();
+        let client_entity = stepper
+            .client_app
+            .world()
+            .resource::<client::ConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(server_entity)
+            .unwrap();
+        assert_eq!(
+            stepper
+                .client_app
+                .world()
+                .get::<DisplayValue<ComponentSyncModeFull2>>(client_entity),
+            Some(&DisplayValue(ComponentSyncModeFull2(100.0)))
+        );
+
+        // sudden change on the server
+        stepper
+            .server_app
+            .world_mut()
+            .get_mut::<ComponentSyncModeFull2>(server_entity)
+            .unwrap()
+            .0 = 0.0;
+        let mut previous = 100.0;
+        let mut authoritative_updated = false;
+        for _ in 0..20 {
+            stepper.frame_step();
+            let world = stepper.client_app.world();
+            let authoritative = world
+                .get::<ComponentSyncModeFull2>(client_entity)
+                .unwrap()
+                .0;
+            let display = world
+                .get::<DisplayValue<ComponentSyncModeFull2>>(client_entity)
+                .unwrap()
+                .0
+                 .0;
+            if authoritative == 0.0 {
+                authoritative_updated = true;
+                // the display value decreases smoothly instead of snapping to the server value
+                assert!(display < previous);
+                assert!(display > 0.0);
+            } else {
+                assert_eq!(display, 100.0);
+            }
+            previous = display;
+        }
+        assert!(authoritative_updated);
+        // the display value converges towards the server value
+        for _ in 0..200 {
+            stepper.frame_step();
+        }
+        let display = stepper
+            .client_app
+            .world()
+            .get::<DisplayValue<ComponentSyncModeFull2>>(client_entity)
+            .unwrap()
+            .0
+             .0;
+        assert!(display < 1.0);
+    }
+}
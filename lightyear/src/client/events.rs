@@ -18,9 +18,12 @@ use crate::connection::client::ConnectionError;
 use crate::prelude::ClientId;
 use crate::shared::events::plugin::EventsPlugin;
 use crate::shared::events::systems::push_component_events;
+use crate::shared::replication::components::Controlled;
 use crate::shared::sets::{ClientMarker, InternalMainSet};
 use bevy::app::{App, Plugin, PreUpdate};
-use bevy::prelude::{Component, Event, IntoSystemConfigs};
+use bevy::prelude::{
+    Component, Entity, Event, EventWriter, IntoSystemConfigs, OnAdd, OnRemove, Trigger,
+};
 
 /// Plugin that handles generating bevy [`Events`](Event) related to networking and replication
 #[derive(Default)]
@@ -32,6 +35,11 @@ impl Plugin for ClientEventsPlugin {
             // EVENTS
             .add_event::<ConnectEvent>()
             .add_event::<DisconnectEvent>()
+            .add_event::<ControlGained>()
+            .add_event::<ControlLost>()
+            // OBSERVERS
+            .add_observer(emit_control_gained)
+            .add_observer(emit_control_lost)
             // PLUGIN
             .add_plugins(EventsPlugin::<ConnectionManager>::default());
     }
@@ -48,6 +56,35 @@ pub(crate) fn emit_replication_events<C: Component>(app: &mut App) {
     );
 }
 
+/// Bevy [`Event`] emitted on the client when the server gives it control of an entity
+/// (the [`Controlled`] marker is added to the entity)
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ControlGained {
+    pub entity: Entity,
+}
+
+/// Bevy [`Event`] emitted on the client when the [`Controlled`] marker is removed from an entity
+/// (for example because the entity is despawned)
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ControlLost {
+    pub entity: Entity,
+}
+
+fn emit_control_gained(
+    trigger: Trigger<OnAdd, Controlled>,
+    mut events: EventWriter<ControlGained>,
+) {
+    events.send(ControlGained {
+        entity: trigger.entity(),
+    });
+}
+
+fn emit_control_lost(trigger: Trigger<OnRemove, Controlled>, mut events: EventWriter<ControlLost>) {
+    events.send(ControlLost {
+        entity: trigger.entity(),
+    });
+}
+
 /// Bevy [`Event`] emitted on the client on the frame where the connection is established
 ///
 /// We keep this separate from the server's ConnectEvent so that we have different events emitted on the client
@@ -76,6 +113,9 @@ pub type InputEvent<I> = crate::shared::events::components::InputEvent<I, ()>;
 pub type EntitySpawnEvent = crate::shared::events::components::EntitySpawnEvent<()>;
 /// Bevy [`Event`] emitted on the client when a EntityDespawn replication message is received
 pub type EntityDespawnEvent = crate::shared::events::components::EntityDespawnEvent<()>;
+/// Bevy [`Event`] emitted on the client when a replicated component received from the server could not be read
+pub type ComponentDeserializationErrorEvent =
+    crate::shared::events::components::ComponentDeserializationErrorEvent<()>;
 /// Bevy [`Event`] emitted on the client when a ComponentUpdate replication message is received
 pub type ComponentUpdateEvent<C> = crate::shared::events::components::ComponentUpdateEvent<C, ()>;
 /// Bevy [`Event`] emitted on the client when a ComponentInsert replication message is received
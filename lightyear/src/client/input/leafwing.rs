@@ -43,7 +43,6 @@ use crate::client::components::Confirmed;
 use crate::client::config::ClientConfig;
 use crate::client::connection::ConnectionManager;
 use crate::client::prediction::plugin::{is_in_rollback, PredictionSet};
-use crate::client::prediction::resource::PredictionManager;
 use crate::client::prediction::rollback::Rollback;
 use crate::client::prediction::Predicted;
 use crate::client::run_conditions::is_synced;
@@ -52,14 +51,12 @@ use crate::inputs::leafwing::input_buffer::InputBuffer;
 use crate::inputs::leafwing::input_message::InputTarget;
 use crate::inputs::leafwing::LeafwingUserAction;
 use crate::prelude::{
-    is_host_server, ChannelKind, ChannelRegistry, InputMessage, MessageRegistry,
+    is_host_server, ChannelKind, ChannelRegistry, ClientReceiveMessage, InputMessage,
     ReplicateOnceComponent, TickManager, TimeManager,
 };
-use crate::protocol::message::MessageKind;
-use crate::serialize::reader::Reader;
 use crate::shared::replication::components::PrePredicted;
 use crate::shared::sets::{ClientMarker, InternalMainSet};
-use crate::shared::tick_manager::TickEvent;
+use crate::shared::tick_manager::{Tick, TickEvent};
 
 // TODO: the resource should have a generic param, but not the user-facing config struct
 #[derive(Debug, Copy, Clone, Resource)]
@@ -219,11 +216,11 @@ impl<A: LeafwingUserAction> Plugin for LeafwingInputPlugin<A>
             FixedPreUpdate,
             (
                 (
-                    // update_action_state_remote_players::<A>,
                     buffer_action_state::<A>,
                     // If InputDelay is enabled, we get the ActionState for the current tick
                     // from the InputBuffer (which was added to the InputBuffer input_delay ticks ago)
                     get_non_rollback_action_state::<A>.run_if(is_input_delay),
+                    update_action_state_remote_players::<A>,
                 )
                     .chain()
                     .run_if(not(is_in_rollback)),
@@ -461,6 +458,49 @@ fn get_non_rollback_action_state<A: LeafwingUserAction>(
     }
 }
 
+/// Get the ActionState of a remote player for the given tick.
+///
+/// The inputs of the remote player for the most recent ticks have usually not been received yet
+/// (they are rebroadcast by the server): in that case we extrapolate from the last input that we received.
+fn remote_action_state<A: LeafwingUserAction>(
+    input_buffer: &InputBuffer<A>,
+    tick: Tick,
+) -> Option<&ActionState<A>> {
+    input_buffer.get(tick).or_else(|| {
+        input_buffer
+            .end_tick()
+            .is_some_and(|end_tick| tick > end_tick)
+            .then(|| input_buffer.get_last())
+            .flatten()
+    })
+}
+
+/// Update the ActionState of the predicted entities controlled by other clients, using the
+/// inputs rebroadcast by the server.
+///
+/// The remote players never consume the local inputs since they don't have an [`InputMap`].
+fn update_action_state_remote_players<A: LeafwingUserAction>(
+    tick_manager: Res<TickManager>,
+    mut remote_player_query: Query<
+        (Entity, &mut ActionState<A>, &InputBuffer<A>),
+        (With<Predicted>, Without<InputMap<A>>),
+    >,
+) {
+    let tick = tick_manager.tick();
+    for (entity, mut action_state, input_buffer) in remote_player_query.iter_mut() {
+        if let Some(action) = remote_action_state(input_buffer, tick) {
+            *action_state = action.clone();
+            trace!(
+                ?entity,
+                ?tick,
+                pressed = ?action_state.get_pressed(),
+                "updated action state of remote player using input_buffer: {}",
+                input_buffer
+            );
+        }
+    }
+}
+
 /// During rollback, fetch the action-state from the InputBuffer for the corresponding tick and use that
 /// to set the ActionState resource/component.
 ///
@@ -505,7 +545,9 @@ fn get_rollback_action_state<A: LeafwingUserAction>(
     }
     for (entity, mut action_state, input_buffer) in remote_player_query.iter_mut() {
         // TODO: should we reuse the existing ActionState as an optimization?
-        *action_state = input_buffer.get(tick).cloned().unwrap_or_default();
+        *action_state = remote_action_state(input_buffer, tick)
+            .cloned()
+            .unwrap_or_default();
         trace!(
             ?tick,
             ?entity,
@@ -726,10 +768,9 @@ fn receive_tick_events<A: LeafwingUserAction>(
 /// We will apply the diffs on the Predicted entity.
 fn receive_remote_player_input_messages<A: LeafwingUserAction>(
     mut commands: Commands,
-    tick_manager: Res<TickManager>,
-    mut connection: ResMut<ConnectionManager>,
-    prediction_manager: Res<PredictionManager>,
-    message_registry: Res<MessageRegistry>,
+    connection: Res<ConnectionManager>,
+    // we use an EventReader and not a drain because the user might also want to read the inputs
+    mut received_inputs: EventReader<ClientReceiveMessage<InputMessage<A>>>,
     // TODO: currently we do not handle entities that are controlled by multiple clients
     confirmed_query: Query<&Confirmed, Without<InputMap<A>>>,
     mut predicted_query: Query<
@@ -737,99 +778,73 @@ fn receive_remote_player_input_messages<A: LeafwingUserAction>(
         (Without<InputMap<A>>, With<Predicted>),
     >,
 ) {
-    let current_tick = tick_manager.tick();
-    let kind = MessageKind::of::<InputMessage<A>>();
-    let Some(net) = message_registry.kind_map.net_id(&kind).copied() else {
-        error!(
-            "Could not find the network id for the message kind: {:?}",
-            kind
-        );
-        return;
-    };
-
-    // enable split borrows
-    let connection = connection.as_mut();
-    if let Some(message_list) = connection.received_leafwing_input_messages.get_mut(&net) {
-        message_list.drain(..).for_each(|message_bytes| {
-            let mut reader = Reader::from(message_bytes);
-            match message_registry.deserialize::<InputMessage<A>>(
-                &mut reader,
-                &mut connection
-                    .replication_receiver
-                    .remote_entity_map
-                    .remote_to_local,
-            ) {
-                Ok(message) => {
-                    debug!(action = ?A::short_type_path(), ?message.end_tick, ?message.diffs, "received input message");
-                    for target_data in &message.diffs {
-                        // - the input target has already been set to the server entity in the InputMessage
-                        // - it has been mapped to a client-entity on the client during deserialization
-                        //   ONLY if it's PrePredicted (look at the MapEntities implementation)
-                        let entity = match target_data.target {
-                            InputTarget::Entity(entity) => {
-                                // TODO: find a better way!
-                                // if InputTarget = Entity, we still need to do the mapping
-                                connection
-                                    .replication_receiver
-                                    .remote_entity_map
-                                    .get_local(entity)
-                            }
-                            InputTarget::PrePredictedEntity(entity) => Some(entity),
-                            InputTarget::Global => continue,
-                        };
-                        if let Some(entity) = entity {
-                            debug!(
-                                "received input message for entity: {:?}. Applying to diff buffer.",
-                                entity
-                            );
-                            if let Ok(confirmed) = confirmed_query.get(entity) {
-                                if let Some(predicted) = confirmed.predicted {
-                                    if let Ok(input_buffer) = predicted_query.get_mut(predicted) {
-                                        debug!(?entity, ?target_data.diffs, end_tick = ?message.end_tick, "update action diff buffer for remote player PREDICTED using input message");
-                                        if let Some(mut input_buffer) = input_buffer {
-                                            input_buffer.update_from_message(
-                                                message.end_tick,
-                                                &target_data.start_state,
-                                                &target_data.diffs,
-                                            );
-                                            #[cfg(feature = "metrics")]
-                                            {
-                                                metrics::gauge!(format!(
-                                                    "inputs::{}::remote_player::{}::buffer_size",
-                                                    std::any::type_name::<A>(),
-                                                    entity
-                                                ))
-                                                .set(input_buffer.len() as f64);
-                                            }
-                                        } else {
-                                            // add the ActionState or InputBuffer if they are missing
-                                            let mut input_buffer = InputBuffer::<A>::default();
-                                            input_buffer.update_from_message(
-                                                message.end_tick,
-                                                &target_data.start_state,
-                                                &target_data.diffs,
-                                            );
-                                            // if the remote_player's predicted entity doesn't have the InputBuffer, we need to insert them
-                                            commands.entity(predicted).insert((
-                                                input_buffer,
-                                                ActionState::<A>::default(),
-                                            ));
-                                        }
-                                    }
-                                }
-                            } else {
-                                error!(?entity, ?target_data.diffs, end_tick = ?message.end_tick, "received input message for unrecognized entity");
-                            }
-                        } else {
-                            error!("received remote player input message for unrecognized entity");
-                        }
-                    }
+    for event in received_inputs.read() {
+        let message = &event.message;
+        debug!(action = ?A::short_type_path(), ?message.end_tick, ?message.diffs, "received input message");
+        for target_data in &message.diffs {
+            // - the input target has already been set to the server entity in the InputMessage
+            // - it has been mapped to a client-entity on the client during deserialization
+            //   ONLY if it's PrePredicted (look at the MapEntities implementation)
+            let entity = match target_data.target {
+                InputTarget::Entity(entity) => {
+                    // TODO: find a better way!
+                    // if InputTarget = Entity, we still need to do the mapping
+                    connection
+                        .replication_receiver
+                        .remote_entity_map
+                        .get_local(entity)
                 }
-                Err(e) => {
-                    error!(?e, "could not deserialize leafwing input message");
+                InputTarget::PrePredictedEntity(entity) => Some(entity),
+                InputTarget::Global => continue,
+            };
+            let Some(entity) = entity else {
+                error!("received remote player input message for unrecognized entity");
+                continue;
+            };
+            debug!(
+                "received input message for entity: {:?}. Applying to diff buffer.",
+                entity
+            );
+            let Ok(confirmed) = confirmed_query.get(entity) else {
+                error!(?entity, ?target_data.diffs, end_tick = ?message.end_tick, "received input message for unrecognized entity");
+                continue;
+            };
+            let Some(predicted) = confirmed.predicted else {
+                continue;
+            };
+            let Ok(input_buffer) = predicted_query.get_mut(predicted) else {
+                continue;
+            };
+            debug!(?entity, ?target_data.diffs, end_tick = ?message.end_tick, "update action diff buffer for remote player PREDICTED using input message");
+            if let Some(mut input_buffer) = input_buffer {
+                input_buffer.update_from_message(
+                    message.end_tick,
+                    &target_data.start_state,
+                    &target_data.diffs,
+                );
+                #[cfg(feature = "metrics")]
+                {
+                    metrics::gauge!(format!(
+                        "inputs::{}::remote_player::{}::buffer_size",
+                        std::any::type_name::<A>(),
+                        entity
+                    ))
+                    .set(input_buffer.len() as f64);
                 }
+            } else {
+                // add the ActionState or InputBuffer if they are missing
+                let mut input_buffer = InputBuffer::<A>::default();
+                input_buffer.update_from_message(
+                    message.end_tick,
+                    &target_data.start_state,
+                    &target_data.diffs,
+                );
+                // if the remote_player's predicted entity doesn't have the InputBuffer, we need to insert them
+                commands
+                    .entity(predicted)
+                    .insert((input_buffer, ActionState::<A>::default()));
             }
-        })
+        }
     }
 }
 
@@ -841,8 +856,12 @@ mod tests {
     use std::time::Duration;
 
     use crate::prelude::client::{InterpolationDelay, PredictionConfig};
-    use crate::prelude::server::Replicate;
-    use crate::prelude::{client, SharedConfig, TickConfig};
+    use crate::prelude::server::{ControlledBy, Replicate, SyncTarget};
+    use crate::prelude::{
+        client, ClientId, NetworkTarget, ServerReceiveMessage, ServerSendMessage, SharedConfig,
+        TickConfig,
+    };
+    use crate::tests::multi_stepper::{MultiBevyStepper, TEST_CLIENT_ID_1, TEST_CLIENT_ID_2};
     use crate::tests::protocol::*;
     use crate::tests::stepper::BevyStepper;
 
@@ -1111,4 +1130,164 @@ mod tests {
             .unwrap();
         assert_eq!(delay.delay_ms, 20);
     }
+
+    /// Server system that rebroadcasts the inputs of each client to the other clients
+    fn rebroadcast_inputs(
+        mut receive_inputs: ResMut<Events<ServerReceiveMessage<InputMessage<LeafwingInput1>>>>,
+        mut send_inputs: EventWriter<ServerSendMessage<InputMessage<LeafwingInput1>>>,
+    ) {
+        send_inputs.send_batch(receive_inputs.drain().map(|ev| {
+            ServerSendMessage::new_with_target::<InputChannel>(
+                ev.message,
+                NetworkTarget::AllExceptSingle(ev.from),
+            )
+        }));
+    }
+
+    #[derive(Resource, Default)]
+    struct RemoteRollbackActions(Vec<bool>);
+
+    /// Record if the remote players are jumping during rollback
+    fn record_remote_rollback_actions(
+        query: Query<
+            &ActionState<LeafwingInput1>,
+            (With<Predicted>, Without<InputMap<LeafwingInput1>>),
+        >,
+        mut actions: ResMut<RemoteRollbackActions>,
+    ) {
+        actions.0.extend(
+            query
+                .iter()
+                .map(|action_state| action_state.pressed(&LeafwingInput1::Jump)),
+        );
+    }
+
+    fn predicted_entity(app: &App, server_entity: Entity) -> Entity {
+        let confirmed = app
+            .world()
+            .resource::<ConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(server_entity)
+            .expect("entity was not replicated to client");
+        app.world()
+            .get::<Confirmed>(confirmed)
+            .unwrap()
+            .predicted
+            .expect("entity is not predicted")
+    }
+
+    /// Each client predicts the players of all clients.
+    /// The remote players are predicted using the inputs rebroadcast by the server, and during rollback
+    /// the inputs that were not received yet are extrapolated from the last received input.
+    #[test]
+    fn test_remote_player_prediction() {
+        let mut stepper = MultiBevyStepper::default();
+        stepper.server_app.add_systems(
+            PreUpdate,
+            rebroadcast_inputs.after(crate::server::input::leafwing::InputSystemSet::ReceiveInputs),
+        );
+        stepper
+            .client_app_1
+            .init_resource::<RemoteRollbackActions>();
+        stepper.client_app_1.add_systems(
+            FixedUpdate,
+            record_remote_rollback_actions.run_if(is_in_rollback),
+        );
+
+        let mut spawn_player = |client_id: u64| {
+            stepper
+                .server_app
+                .world_mut()
+                .spawn((
+                    ActionState::<LeafwingInput1>::default(),
+                    ComponentSyncModeFull(0.0),
+                    Replicate {
+                        sync: SyncTarget {
+                            prediction: NetworkTarget::All,
+                            ..default()
+                        },
+                        controlled_by: ControlledBy {
+                            target: NetworkTarget::Single(ClientId::Netcode(client_id)),
+                            ..default()
+                        },
+                        ..default()
+                    },
+                ))
+                .id()
+        };
+        let server_entity_1 = spawn_player(TEST_CLIENT_ID_1);
+        let server_entity_2 = spawn_player(TEST_CLIENT_ID_2);
+        for _ in 0..3 {
+            stepper.frame_step();
+        }
+
+        let local_player = predicted_entity(&stepper.client_app_1, server_entity_1);
+        let remote_player = predicted_entity(&stepper.client_app_1, server_entity_2);
+        let client_2_player = predicted_entity(&stepper.client_app_2, server_entity_2);
+        stepper
+            .client_app_1
+            .world_mut()
+            .entity_mut(local_player)
+            .insert(InputMap::<LeafwingInput1>::new([(
+                LeafwingInput1::Jump,
+                KeyCode::KeyA,
+            )]));
+        stepper
+            .client_app_2
+            .world_mut()
+            .entity_mut(client_2_player)
+            .insert(InputMap::<LeafwingInput1>::new([(
+                LeafwingInput1::Jump,
+                KeyCode::KeyA,
+            )]));
+        stepper.frame_step();
+
+        // client 2 keeps jumping
+        stepper
+            .client_app_2
+            .world_mut()
+            .resource_mut::<ButtonInput<KeyCode>>()
+            .press(KeyCode::KeyA);
+        for _ in 0..5 {
+            stepper.frame_step();
+        }
+
+        // client 1 predicts the remote player with the inputs of client 2, but not its own player
+        let client_world = stepper.client_app_1.world();
+        assert!(client_world
+            .get::<ActionState<LeafwingInput1>>(remote_player)
+            .unwrap()
+            .pressed(&LeafwingInput1::Jump));
+        assert!(!client_world
+            .get::<ActionState<LeafwingInput1>>(local_player)
+            .unwrap()
+            .pressed(&LeafwingInput1::Jump));
+
+        // force a mispredict of the remote player on client 1
+        stepper
+            .client_app_1
+            .world_mut()
+            .resource_mut::<RemoteRollbackActions>()
+            .0
+            .clear();
+        stepper
+            .server_app
+            .world_mut()
+            .get_mut::<ComponentSyncModeFull>(server_entity_2)
+            .unwrap()
+            .0 = 1.0;
+        stepper.frame_step();
+        stepper.frame_step();
+
+        let client_world = stepper.client_app_1.world();
+        assert_eq!(
+            client_world.get::<ComponentSyncModeFull>(remote_player),
+            Some(&ComponentSyncModeFull(1.0))
+        );
+        // the remote player kept jumping during the whole rollback
+        let rollback_actions = &client_world.resource::<RemoteRollbackActions>().0;
+        assert!(!rollback_actions.is_empty());
+        assert!(rollback_actions.iter().all(|pressed| *pressed));
+    }
 }
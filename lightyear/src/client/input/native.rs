@@ -42,6 +42,9 @@
 //! - handle inputs in your game logic in systems that run in the `FixedUpdate` schedule. These systems
 //!   will read the inputs using the [`InputEvent`] event.
 //!
+//! The input delay configured in the [`PredictionConfig`](crate::client::prediction::plugin::PredictionConfig)
+//! is applied: an input buffered for tick `T` is applied on tick `T + input_delay_ticks`.
+//!
 //! NOTE: I would advise to activate the `leafwing` feature to handle inputs via the `input_leafwing` module, instead.
 //! That module is more up-to-date and has more features.
 //! This module is kept for simplicity but might get removed in the future.
@@ -85,12 +88,15 @@ pub struct InputConfig {
 #[derive(Debug, Resource)]
 pub struct InputManager<A> {
     pub(crate) input_buffer: InputBuffer<A>,
+    /// Copy of the current input delay of the [`ConnectionManager`]
+    pub(crate) input_delay_ticks: u16,
 }
 
 impl<A> Default for InputManager<A> {
     fn default() -> Self {
         Self {
             input_buffer: InputBuffer::default(),
+            input_delay_ticks: 0,
         }
     }
 }
@@ -103,8 +109,12 @@ impl<A: UserAction> InputManager<A> {
     }
 
     /// Buffer a user action for the given tick
+    ///
+    /// The action will be applied on tick `tick + input_delay_ticks`, where the input delay is computed
+    /// from the [`PredictionConfig`](crate::client::prediction::plugin::PredictionConfig)
     pub fn add_input(&mut self, input: A, tick: Tick) {
-        self.input_buffer.set(tick, Some(input));
+        self.input_buffer
+            .set(tick + self.input_delay_ticks as i16, Some(input));
     }
 }
 
@@ -178,11 +188,23 @@ impl<A: UserAction> Plugin for InputPlugin<A> {
                 .chain(),
         );
 
+        // in host-server mode, the local client's inputs must be in the server's input buffers
+        // before the server writes the input events for the tick
+        app.configure_sets(
+            FixedPreUpdate,
+            InputSystemSet::WriteInputEvent
+                .before(crate::server::input::native::InputSystemSet::WriteInputEvents),
+        );
+
         // SYSTEMS
+        app.add_systems(
+            FixedPreUpdate,
+            update_input_delay::<A>.before(InputSystemSet::BufferInputs),
+        );
         // Host server mode only!
         app.add_systems(
             FixedPreUpdate,
-            send_input_directly_to_client_events::<A>
+            buffer_local_client_input::<A>
                 .in_set(InputSystemSet::WriteInputEvent)
                 .run_if(is_host_server),
         );
@@ -225,6 +247,17 @@ pub enum InputSystemSet {
     SendInputMessage,
 }
 
+/// Keep the input delay of the [`InputManager`] in sync with the one computed by the [`ConnectionManager`]
+fn update_input_delay<A: UserAction>(
+    connection: Option<Res<ConnectionManager>>,
+    mut input_manager: ResMut<InputManager<A>>,
+) {
+    let Some(connection) = connection else {
+        return;
+    };
+    input_manager.input_delay_ticks = connection.input_delay_ticks();
+}
+
 /// System that clears the input events.
 /// It is necessary because events are cleared every frame, but we want to clear every tick instead
 fn clear_input_events<A: UserAction>(mut input_events: EventReader<InputEvent<A>>) {
@@ -304,9 +337,11 @@ fn prepare_input_message<A: UserAction>(
     //  - buffer an input every frame; and require some redundancy (number of tick per frame)
     //  - or buffer an input only when we are sending, and require more redundancy
     // let message_len = 20 as u16;
+    // with input delay, the buffer already contains the inputs for the next `input_delay_ticks` ticks
+    let end_tick = current_tick + connection.input_delay_ticks() as i16;
     let message = input_manager
         .input_buffer
-        .create_message(tick_manager.tick(), message_len);
+        .create_message(end_tick, message_len);
     // all inputs are absent
     if !message.is_empty() {
         // TODO: should we provide variants of each user-facing function, so that it pushes the error
@@ -332,29 +367,47 @@ fn prepare_input_message<A: UserAction>(
     // .pop(current_tick - (message_len + 1));
 }
 
-/// In host server mode, we don't buffer inputs (because there is no rollback) and we don't send
-/// inputs through the network, we just send directly to the server's InputEvents
-fn send_input_directly_to_client_events<A: UserAction>(
+/// In host server mode, we don't keep the inputs in the client's buffer (because there is no rollback)
+/// and we don't send them through the network.
+///
+/// Instead, the input for the current tick is inserted directly in the server's input buffer for the local client,
+/// without any serialization. The server then emits the [`InputEvent`](crate::server::events::InputEvent)
+/// for the local client in the same tick and the same way as for remote clients.
+fn buffer_local_client_input<A: UserAction>(
     tick_manager: Res<TickManager>,
     client: Res<ClientConnection>,
     mut input_manager: ResMut<InputManager<A>>,
-    mut server_input_events: EventWriter<crate::server::events::InputEvent<A>>,
+    server_input_buffers: Option<ResMut<crate::server::input::native::InputBuffers<A>>>,
 ) {
+    let Some(mut server_input_buffers) = server_input_buffers else {
+        return;
+    };
     if let NetClientDispatch::Local(client) = &client.client {
         let tick = tick_manager.tick();
         let input = input_manager.input_buffer.pop(tick);
-        let event = crate::server::events::InputEvent::new(input, client.id());
-        server_input_events.send(event);
+        trace!(?tick, ?input, "Buffer local client input in the server");
+        server_input_buffers
+            .buffers
+            .entry(client.id())
+            .or_default()
+            .1
+            .set(tick, input);
     }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::client::input::native::InputSystemSet;
-    use crate::prelude::client::InputManager;
-    use crate::prelude::{server, TickManager};
-    use crate::tests::host_server_stepper::HostServerStepper;
-    use crate::tests::protocol::MyInput;
+    use crate::inputs::native::InputMessage;
+    use crate::prelude::client::{ClientConfig, InputManager};
+    use crate::prelude::{
+        client, server, ClientId, ServerReceiveMessage, Tick, TickManager, UserAction,
+    };
+    use crate::server::input::native::InputBuffers;
+    use crate::shared::sets::{InternalMainSet, ServerMarker};
+    use crate::tests::host_server_stepper::{HostServerStepper, LOCAL_CLIENT_ID};
+    use crate::tests::protocol::{MyInput, MyOtherInput};
+    use crate::tests::stepper::{BevyStepper, TEST_CLIENT_ID};
     use bevy::prelude::*;
 
     fn press_input(
@@ -393,4 +446,227 @@ mod tests {
         stepper.frame_step();
         assert!(stepper.server_app.world().resource::<Counter>().0 > 0);
     }
+
+    #[derive(Component, Default)]
+    struct Position(u32);
+
+    #[derive(Resource, Default)]
+    struct TickCount(u32);
+
+    fn movement(
+        mut query: Query<&mut Position>,
+        mut input: EventReader<server::InputEvent<MyInput>>,
+        mut tick_count: ResMut<TickCount>,
+    ) {
+        tick_count.0 += 1;
+        for input in input.read() {
+            if input.from() != ClientId::Local(LOCAL_CLIENT_ID) {
+                continue;
+            }
+            // the input buffered on this tick is available on the same tick
+            let MyInput(amount) = input.input().expect("missing host input");
+            for mut position in query.iter_mut() {
+                position.0 += amount as u32;
+            }
+        }
+    }
+
+    fn no_input_messages_from_host(
+        mut messages: EventReader<ServerReceiveMessage<InputMessage<MyInput>>>,
+    ) {
+        for message in messages.read() {
+            assert_ne!(message.from, ClientId::Local(LOCAL_CLIENT_ID));
+        }
+    }
+
+    /// Check that in host-server mode the inputs of the host are inserted in the server's input buffer
+    /// on the tick where they are buffered, and drive the host's entity through the same movement system
+    /// as the other clients, without going through the network
+    #[test]
+    fn test_host_server_input_drives_movement() {
+        let mut stepper = HostServerStepper::default_no_init();
+        stepper.server_app.init_resource::<TickCount>();
+        stepper.server_app.add_systems(
+            FixedPreUpdate,
+            press_input.in_set(InputSystemSet::BufferInputs),
+        );
+        stepper.server_app.add_systems(FixedUpdate, movement);
+        stepper.server_app.add_systems(
+            PreUpdate,
+            no_input_messages_from_host.after(InternalMainSet::<ServerMarker>::ReceiveEvents),
+        );
+        stepper.init();
+        let entity = stepper.server_app.world_mut().spawn(Position(0)).id();
+        let ticks_before = stepper.server_app.world().resource::<TickCount>().0;
+
+        for _ in 0..5 {
+            stepper.frame_step();
+        }
+        let ticks = stepper.server_app.world().resource::<TickCount>().0 - ticks_before;
+        assert!(ticks > 0);
+        assert_eq!(
+            stepper
+                .server_app
+                .world()
+                .get::<Position>(entity)
+                .unwrap()
+                .0,
+            2 * ticks
+        );
+        // the host's inputs are stored in the server's input buffers like remote clients
+        assert!(stepper
+            .server_app
+            .world()
+            .resource::<InputBuffers<MyInput>>()
+            .buffers
+            .contains_key(&ClientId::Local(LOCAL_CLIENT_ID)));
+    }
+
+    /// First tick where an input was applied
+    #[derive(Resource, Default)]
+    struct FirstInputTick(Option<Tick>);
+
+    fn record_client_input(
+        tick_manager: Res<TickManager>,
+        mut first_input_tick: ResMut<FirstInputTick>,
+        mut input: EventReader<client::InputEvent<MyInput>>,
+    ) {
+        for input in input.read() {
+            if input.input().is_some() && first_input_tick.0.is_none() {
+                first_input_tick.0 = Some(tick_manager.tick());
+            }
+        }
+    }
+
+    fn record_server_input(
+        tick_manager: Res<TickManager>,
+        mut first_input_tick: ResMut<FirstInputTick>,
+        mut input: EventReader<server::InputEvent<MyInput>>,
+    ) {
+        for input in input.read() {
+            if input.input().is_some() && first_input_tick.0.is_none() {
+                first_input_tick.0 = Some(tick_manager.tick());
+            }
+        }
+    }
+
+    /// Check that with an input delay, the input is applied `input_delay_ticks` after the tick
+    /// where it was buffered, on both the client and the server
+    #[test]
+    fn test_input_delay() {
+        let mut stepper = BevyStepper::default_no_init();
+        stepper
+            .client_app
+            .world_mut()
+            .resource_mut::<ClientConfig>()
+            .prediction
+            .set_fixed_input_delay_ticks(3);
+        stepper.client_app.init_resource::<FirstInputTick>();
+        stepper
+            .client_app
+            .add_systems(FixedUpdate, record_client_input);
+        stepper.server_app.init_resource::<FirstInputTick>();
+        stepper
+            .server_app
+            .add_systems(FixedUpdate, record_server_input);
+        stepper.init();
+        // the input delay is computed once the client is synced, and copied to the InputManager on the next tick
+        stepper.frame_step();
+
+        let tick = stepper.client_tick();
+        stepper
+            .client_app
+            .world_mut()
+            .resource_mut::<InputManager<MyInput>>()
+            .add_input(MyInput(1), tick);
+        for _ in 0..10 {
+            stepper.frame_step();
+        }
+        assert_eq!(
+            stepper.client_app.world().resource::<FirstInputTick>().0,
+            Some(tick + 3)
+        );
+        // the input arrived on the server before the delayed tick, and was kept until then
+        assert_eq!(
+            stepper.server_app.world().resource::<FirstInputTick>().0,
+            Some(tick + 3)
+        );
+    }
+
+    /// Inputs received by the server for a given input type
+    #[derive(Resource)]
+    struct ReceivedInputs<A>(Vec<(ClientId, Tick, A)>);
+
+    impl<A> Default for ReceivedInputs<A> {
+        fn default() -> Self {
+            Self(Vec::new())
+        }
+    }
+
+    fn record_inputs<A: UserAction>(
+        tick_manager: Res<TickManager>,
+        mut received: ResMut<ReceivedInputs<A>>,
+        mut input: EventReader<server::InputEvent<A>>,
+    ) {
+        for event in input.read() {
+            if let Some(input) = event.input() {
+                received
+                    .0
+                    .push((event.from(), tick_manager.tick(), input.clone()));
+            }
+        }
+    }
+
+    /// Check that multiple input types can be registered, and that each type is buffered
+    /// and delivered independently on the server
+    #[test]
+    fn test_multiple_input_types() {
+        let mut stepper = BevyStepper::default_no_init();
+        stepper
+            .server_app
+            .init_resource::<ReceivedInputs<MyInput>>()
+            .init_resource::<ReceivedInputs<MyOtherInput>>()
+            .add_systems(
+                FixedUpdate,
+                (record_inputs::<MyInput>, record_inputs::<MyOtherInput>),
+            );
+        stepper.init();
+
+        // buffer inputs of different types for different ticks
+        let tick = stepper.client_tick();
+        stepper
+            .client_app
+            .world_mut()
+            .resource_mut::<InputManager<MyInput>>()
+            .add_input(MyInput(1), tick);
+        stepper
+            .client_app
+            .world_mut()
+            .resource_mut::<InputManager<MyOtherInput>>()
+            .add_input(MyOtherInput(2), tick + 1);
+        for _ in 0..10 {
+            stepper.frame_step();
+        }
+
+        let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+        let inputs = &stepper
+            .server_app
+            .world()
+            .resource::<ReceivedInputs<MyInput>>()
+            .0;
+        assert_eq!(inputs.first(), Some(&(client_id, tick, MyInput(1))));
+        assert!(inputs.iter().all(|(_, _, input)| *input == MyInput(1)));
+        let other_inputs = &stepper
+            .server_app
+            .world()
+            .resource::<ReceivedInputs<MyOtherInput>>()
+            .0;
+        assert_eq!(
+            other_inputs.first(),
+            Some(&(client_id, tick + 1, MyOtherInput(2)))
+        );
+        assert!(other_inputs
+            .iter()
+            .all(|(_, _, input)| *input == MyOtherInput(2)));
+    }
 }
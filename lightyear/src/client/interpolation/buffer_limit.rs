@@ -0,0 +1,270 @@
+//! Limit the number of snapshots stored in the interpolation buffers of a client
+//!
+//! Every interpolated entity stores a [`ConfirmedHistory`] of the server updates for each of its
+//! interpolated components. When a client watches a large scene, these buffers can use a lot of memory.
+//! If [`InterpolationConfig::max_snapshots`](super::plugin::InterpolationConfig::max_snapshots) is set,
+//! the oldest snapshots of the entities with the lowest [`InterpolationPriority`] are evicted
+//! until the total number of snapshots is under the cap.
+//!
+//! Each buffer always keeps its [`MIN_SNAPSHOTS`] most recent snapshots, so that the entity can still be
+//! interpolated: the cap is not reached if these snapshots alone exceed it.
+use bevy::ecs::entity::EntityHashMap;
+use bevy::prelude::*;
+
+use crate::client::components::SyncComponent;
+use crate::client::config::ClientConfig;
+use crate::client::interpolation::{ConfirmedHistory, Interpolated};
+
+/// Number of snapshots that are never evicted from a [`ConfirmedHistory`], since we need two
+/// snapshots to interpolate between them
+pub const MIN_SNAPSHOTS: usize = 2;
+
+/// Priority of an interpolated entity when the interpolation buffers are full.
+///
+/// The snapshots of the entities with the lowest priority are evicted first.
+/// Entities without this component have a priority of 1.0
+#[derive(Component, Clone, Copy, Debug, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct InterpolationPriority(pub f32);
+
+impl Default for InterpolationPriority {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
+/// Current usage of the interpolation buffers of the client
+#[derive(Resource, Default, Debug)]
+pub struct InterpolationBufferUsage {
+    /// Total number of snapshots stored in the interpolation buffers, after eviction
+    pub snapshots: usize,
+    /// Number of snapshots that were evicted since the client started
+    pub evicted: usize,
+    /// Number of snapshots that can be evicted for each interpolated entity
+    /// (i.e. the snapshots above [`MIN_SNAPSHOTS`] in each of its buffers)
+    evictable: EntityHashMap<usize>,
+    /// Number of snapshots that need to be evicted for each interpolated entity
+    to_evict: EntityHashMap<usize>,
+}
+
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone, Copy)]
+pub(crate) enum BufferLimitSet {
+    /// Count the number of snapshots stored for each component
+    Record,
+    /// Evict the snapshots that exceed the cap
+    Evict,
+}
+
+pub(crate) fn reset_buffer_usage(mut usage: ResMut<InterpolationBufferUsage>) {
+    usage.snapshots = 0;
+    usage.evictable.clear();
+    usage.to_evict.clear();
+}
+
+/// Count the number of snapshots stored in the [`ConfirmedHistory<C>`] of each interpolated entity
+pub(crate) fn record_buffer_usage<C: SyncComponent>(
+    mut usage: ResMut<InterpolationBufferUsage>,
+    query: Query<(Entity, &ConfirmedHistory<C>), With<Interpolated>>,
+) {
+    for (entity, history) in query.iter() {
+        let len = history.buffer.len();
+        usage.snapshots += len;
+        *usage.evictable.entry(entity).or_default() += len.saturating_sub(MIN_SNAPSHOTS);
+    }
+}
+
+/// Decide how many snapshots to evict for each entity, starting with the lowest priority entities.
+///
+/// An entity only gives up its evictable snapshots; the rest of the excess is spread over the
+/// entities with the next lowest priorities.
+pub(crate) fn compute_evictions(
+    config: Res<ClientConfig>,
+    mut usage: ResMut<InterpolationBufferUsage>,
+    priorities: Query<&InterpolationPriority>,
+) {
+    let Some(max_snapshots) = config.interpolation.max_snapshots else {
+        return;
+    };
+    let Some(mut excess) = usage.snapshots.checked_sub(max_snapshots) else {
+        return;
+    };
+    let usage = usage.as_mut();
+    let mut entities: Vec<(Entity, usize, f32)> = usage
+        .evictable
+        .iter()
+        .map(|(entity, count)| {
+            let priority = priorities.get(*entity).map_or(1.0, |p| p.0);
+            (*entity, *count, priority)
+        })
+        .collect();
+    entities.sort_by(|a, b| a.2.total_cmp(&b.2));
+    for (entity, count, _) in entities {
+        if excess == 0 {
+            break;
+        }
+        let evict = count.min(excess);
+        usage.to_evict.insert(entity, evict);
+        excess -= evict;
+    }
+}
+
+/// Evict the oldest snapshots of the [`ConfirmedHistory<C>`] of the entities selected in [`compute_evictions`]
+pub(crate) fn evict_snapshots<C: SyncComponent>(
+    mut usage: ResMut<InterpolationBufferUsage>,
+    mut query: Query<&mut ConfirmedHistory<C>, With<Interpolated>>,
+) {
+    if usage.to_evict.is_empty() {
+        return;
+    }
+    let usage = usage.as_mut();
+    for (entity, remaining) in usage.to_evict.iter_mut() {
+        let Ok(mut history) = query.get_mut(*entity) else {
+            continue;
+        };
+        while *remaining > 0 && history.buffer.len() > MIN_SNAPSHOTS {
+            history.pop();
+            *remaining -= 1;
+            usage.snapshots -= 1;
+            usage.evicted += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::interpolation::plugin::InterpolationConfig;
+    use crate::prelude::Tick;
+    use crate::tests::protocol::ComponentSyncModeFull;
+
+    fn setup(max_snapshots: usize) -> App {
+        let mut app = App::new();
+        app.insert_resource(ClientConfig {
+            interpolation: InterpolationConfig::default().with_max_snapshots(max_snapshots),
+            ..default()
+        });
+        app.init_resource::<InterpolationBufferUsage>();
+        app.configure_sets(
+            Update,
+            (BufferLimitSet::Record, BufferLimitSet::Evict).chain(),
+        );
+        app.add_systems(
+            Update,
+            (
+                reset_buffer_usage.before(BufferLimitSet::Record),
+                record_buffer_usage::<ComponentSyncModeFull>.in_set(BufferLimitSet::Record),
+                compute_evictions
+                    .after(BufferLimitSet::Record)
+                    .before(BufferLimitSet::Evict),
+                evict_snapshots::<ComponentSyncModeFull>.in_set(BufferLimitSet::Evict),
+            ),
+        );
+        app
+    }
+
+    fn spawn_interpolated(app: &mut App, snapshots: u16, priority: f32) -> Entity {
+        let mut history = ConfirmedHistory::<ComponentSyncModeFull>::new();
+        for i in 0..snapshots {
+            history
+                .buffer
+                .push(Tick(i), ComponentSyncModeFull(i as f32));
+        }
+        app.world_mut()
+            .spawn((
+                Interpolated {
+                    confirmed_entity: Entity::PLACEHOLDER,
+                },
+                history,
+                InterpolationPriority(priority),
+            ))
+            .id()
+    }
+
+    /// Many low-priority interpolated entities: the total number of snapshots stays under the cap,
+    /// and the high-priority entities keep their snapshots
+    #[test]
+    fn test_interpolation_buffer_cap() {
+        let mut app = setup(300);
+        let low_priority: Vec<Entity> = (0..100)
+            .map(|_| spawn_interpolated(&mut app, 5, 0.5))
+            .collect();
+        let high_priority: Vec<Entity> = (0..5)
+            .map(|_| spawn_interpolated(&mut app, 5, 2.0))
+            .collect();
+
+        app.update();
+
+        let usage = app.world().resource::<InterpolationBufferUsage>();
+        assert_eq!(usage.snapshots, 300);
+        assert_eq!(usage.evicted, 105 * 5 - usage.snapshots);
+        let count = |app: &App, entity: Entity| {
+            app.world()
+                .get::<ConfirmedHistory<ComponentSyncModeFull>>(entity)
+                .unwrap()
+                .buffer
+                .len()
+        };
+        let total: usize = low_priority
+            .iter()
+            .chain(high_priority.iter())
+            .map(|e| count(&app, *e))
+            .sum();
+        assert_eq!(total, usage.snapshots);
+        for entity in high_priority {
+            assert_eq!(count(&app, entity), 5);
+        }
+        // the oldest snapshots were evicted first
+        for entity in low_priority {
+            let history = app
+                .world()
+                .get::<ConfirmedHistory<ComponentSyncModeFull>>(entity)
+                .unwrap();
+            assert!(history.buffer.len() >= MIN_SNAPSHOTS);
+            let item = history.buffer.heap.peek().unwrap();
+            assert_eq!(item.key, Tick(5 - history.buffer.len() as u16));
+        }
+    }
+
+    /// If the cap cannot be reached, every entity keeps its [`MIN_SNAPSHOTS`] most recent snapshots,
+    /// even the lowest priority ones
+    #[test]
+    fn test_interpolation_buffer_keeps_min_snapshots() {
+        let mut app = setup(3);
+        let low_priority = spawn_interpolated(&mut app, 5, 0.5);
+        let high_priority = spawn_interpolated(&mut app, 5, 2.0);
+
+        app.update();
+
+        let usage = app.world().resource::<InterpolationBufferUsage>();
+        assert_eq!(usage.snapshots, 2 * MIN_SNAPSHOTS);
+        assert_eq!(usage.evicted, 10 - 2 * MIN_SNAPSHOTS);
+        for entity in [low_priority, high_priority] {
+            let history = app
+                .world()
+                .get::<ConfirmedHistory<ComponentSyncModeFull>>(entity)
+                .unwrap();
+            assert_eq!(history.buffer.len(), MIN_SNAPSHOTS);
+            // the most recent snapshots are kept
+            assert_eq!(history.buffer.heap.peek().unwrap().key, Tick(3));
+        }
+    }
+
+    /// No snapshots are evicted if the buffers are under the cap
+    #[test]
+    fn test_interpolation_buffer_under_cap() {
+        let mut app = setup(50);
+        let entity = spawn_interpolated(&mut app, 5, 1.0);
+        app.update();
+        let usage = app.world().resource::<InterpolationBufferUsage>();
+        assert_eq!(usage.snapshots, 5);
+        assert_eq!(usage.evicted, 0);
+        assert_eq!(
+            app.world()
+                .get::<ConfirmedHistory<ComponentSyncModeFull>>(entity)
+                .unwrap()
+                .buffer
+                .len(),
+            5
+        );
+    }
+}
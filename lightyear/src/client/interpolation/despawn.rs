@@ -1,8 +1,9 @@
-use bevy::prelude::{Commands, DespawnRecursiveExt, OnRemove, Query, Trigger};
+use bevy::prelude::{Commands, DespawnRecursiveExt, OnRemove, Query, RemovedComponents, Trigger};
 
 use crate::client::components::{Confirmed, SyncComponent};
 use crate::client::interpolation::interpolate::InterpolateStatus;
 use crate::client::interpolation::interpolation_history::ConfirmedHistory;
+use crate::shared::replication::components::ShouldBeInterpolated;
 
 /// Remove the component from interpolated entities when it gets removed from confirmed
 pub(crate) fn removed_components<C: SyncComponent>(
@@ -34,3 +35,24 @@ pub(crate) fn despawn_interpolated(
         }
     }
 }
+
+/// Despawn the interpolated entity when the server stops interpolating the entity for this client
+/// (i.e. `ShouldBeInterpolated` is removed from the confirmed entity)
+// NOTE: this is a system and not an observer, so that entities that get despawned are handled
+//  only by `despawn_interpolated`
+pub(crate) fn remove_interpolated(
+    mut removed: RemovedComponents<ShouldBeInterpolated>,
+    mut query: Query<&mut Confirmed>,
+    mut commands: Commands,
+) {
+    for entity in removed.read() {
+        let Ok(mut confirmed) = query.get_mut(entity) else {
+            continue;
+        };
+        if let Some(interpolated) = confirmed.interpolated.take() {
+            if let Some(entity_mut) = commands.get_entity(interpolated) {
+                entity_mut.despawn_recursive();
+            }
+        }
+    }
+}
@@ -0,0 +1,175 @@
+//! Per-entity interpolation settings
+//!
+//! By default all interpolated entities use the interpolation delay of the
+//! [`InterpolationConfig`](super::plugin::InterpolationConfig).
+//! Adding an [`EntityInterpolationConfig`] to an [`Interpolated`](super::Interpolated) entity overrides
+//! that delay for this entity: for example fast projectiles can use a small delay, while slow
+//! background props use a bigger delay for a smoother interpolation.
+//!
+//! If the delay of an entity is too small, the entity can reach the end of its interpolation buffer
+//! before the next server update is received. In that case the entity keeps moving by extrapolating
+//! from its last two server updates, instead of freezing.
+use bevy::prelude::*;
+use bevy::utils::Duration;
+
+/// Interpolation settings of a single interpolated entity, that override the global
+/// [`InterpolationConfig`](super::plugin::InterpolationConfig).
+///
+/// Insert this component on the [`Interpolated`](super::Interpolated) entity.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct EntityInterpolationConfig {
+    /// How much behind the latest server updates the entity is interpolated
+    pub delay: Duration,
+    /// Minimum number of server updates that are buffered before the interpolated component
+    /// is inserted on the entity
+    pub min_buffer: usize,
+}
+
+impl EntityInterpolationConfig {
+    pub fn new(delay: Duration) -> Self {
+        Self {
+            delay,
+            min_buffer: 0,
+        }
+    }
+
+    pub fn with_min_buffer(mut self, min_buffer: usize) -> Self {
+        self.min_buffer = min_buffer;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::interpolation::InterpolateStatus;
+    use crate::prelude::client::{Confirmed, InterpolationConfig};
+    use crate::prelude::server::{Replicate, SyncTarget};
+    use crate::prelude::{client, NetworkTarget, SharedConfig, TickConfig};
+    use crate::tests::protocol::ComponentSyncModeFull;
+    use crate::tests::stepper::BevyStepper;
+
+    fn setup() -> BevyStepper {
+        let tick_duration = Duration::from_millis(10);
+        let shared_config = SharedConfig {
+            tick: TickConfig::new(tick_duration),
+            ..default()
+        };
+        let client_config = client::ClientConfig {
+            interpolation: InterpolationConfig::default()
+                .with_min_delay(Duration::from_millis(100))
+                .with_send_interval_ratio(0.0),
+            ..default()
+        };
+        let mut stepper = BevyStepper::new(shared_config, client_config, tick_duration);
+        stepper.build();
+        stepper.init();
+        stepper
+    }
+
+    fn spawn_interpolated(stepper: &mut BevyStepper) -> (Entity, Entity) {
+        let server_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn((
+                Replicate {
+                    sync: SyncTarget {
+                        interpolation: NetworkTarget::All,
+                        ..default()
+                    },
+                    ..default()
+                },
+                ComponentSyncModeFull(0.0),
+            ))
+            .id();
+        for _ in 0..10 {
+            stepper.frame_step();
+        }
+        let confirmed_entity = stepper
+            .client_app
+            .world()
+            .resource::<client::ConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(server_entity)
+            .expect("entity was not replicated to client");
+        let interpolated_entity = stepper
+            .client_app
+            .world()
+            .get::<Confirmed>(confirmed_entity)
+            .unwrap()
+            .interpolated
+            .expect("entity is not interpolated");
+        (server_entity, interpolated_entity)
+    }
+
+    fn status(stepper: &BevyStepper, entity: Entity) -> &InterpolateStatus<ComponentSyncModeFull> {
+        stepper
+            .client_app
+            .world()
+            .get::<InterpolateStatus<ComponentSyncModeFull>>(entity)
+            .unwrap()
+    }
+
+    /// An entity with an [`EntityInterpolationConfig`] is interpolated with its own delay,
+    /// the other entities keep using the global delay
+    #[test]
+    fn test_entity_interpolation_delay() {
+        let mut stepper = setup();
+        let (_, default_entity) = spawn_interpolated(&mut stepper);
+        let (_, fast_entity) = spawn_interpolated(&mut stepper);
+        stepper
+            .client_app
+            .world_mut()
+            .entity_mut(fast_entity)
+            .insert(EntityInterpolationConfig::new(Duration::from_millis(20)));
+        stepper.frame_step();
+
+        // the global delay is 100ms: the entity is interpolated 80ms (8 ticks) ahead
+        assert_eq!(
+            status(&stepper, fast_entity).current_tick
+                - status(&stepper, default_entity).current_tick,
+            8
+        );
+    }
+
+    /// If the delay of an entity is too small, the interpolation buffer underruns:
+    /// the entity is extrapolated from the last two server updates instead of freezing
+    #[test]
+    fn test_entity_interpolation_underrun() {
+        let mut stepper = setup();
+        let (server_entity, interpolated_entity) = spawn_interpolated(&mut stepper);
+        stepper
+            .client_app
+            .world_mut()
+            .entity_mut(interpolated_entity)
+            .insert(EntityInterpolationConfig::new(Duration::default()));
+
+        let mut extrapolated = false;
+        for i in 0..40 {
+            // the server sends an update every 4 ticks
+            if i % 4 == 0 {
+                stepper
+                    .server_app
+                    .world_mut()
+                    .get_mut::<ComponentSyncModeFull>(server_entity)
+                    .unwrap()
+                    .0 += 4.0;
+            }
+            stepper.frame_step();
+            let status = status(&stepper, interpolated_entity);
+            if status.end.is_none() && status.extrapolation_fraction().is_some() {
+                let (_, start_value) = status.start.as_ref().unwrap();
+                let value = stepper
+                    .client_app
+                    .world()
+                    .get::<ComponentSyncModeFull>(interpolated_entity)
+                    .unwrap();
+                assert!(value.0 > start_value.0);
+                extrapolated = true;
+            }
+        }
+        assert!(extrapolated);
+    }
+}
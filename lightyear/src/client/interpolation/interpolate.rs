@@ -4,6 +4,7 @@ use tracing::{debug, trace};
 use crate::client::components::SyncComponent;
 use crate::client::config::ClientConfig;
 use crate::client::connection::ConnectionManager;
+use crate::client::interpolation::entity_config::EntityInterpolationConfig;
 use crate::client::interpolation::interpolation_history::ConfirmedHistory;
 use crate::prelude::{ComponentRegistry, TickManager};
 use crate::shared::tick_manager::Tick;
@@ -16,6 +17,10 @@ use crate::shared::tick_manager::Tick;
 // TODO: this value should depend on jitter I think
 const SEND_INTERVAL_TICK_FACTOR: f32 = 1.3;
 
+// when an entity with an [`EntityInterpolationConfig`] has no server update to interpolate towards,
+// we extrapolate from the last two updates, up to this fraction of the interval between them
+const MAX_EXTRAPOLATION_FRACTION: f32 = 1.0;
+
 // TODO: the inner fields are pub just for integration testing.
 //  maybe put the test here?
 // NOTE: there's not a strict need for this, it just makes the logic easier to follow
@@ -27,6 +32,9 @@ pub struct InterpolateStatus<C: Component> {
     pub start: Option<(Tick, C)>,
     /// end tick to interpolate to, along with value
     pub end: Option<(Tick, C)>,
+    /// server update received before `start`, used to extrapolate when there is no `end`.
+    /// Only tracked for entities with an [`EntityInterpolationConfig`]
+    pub previous: Option<(Tick, C)>,
     /// current interpolation tick, which will belong to [start_tick, end_tick[
     pub current_tick: Tick,
     /// for more accurate interpolation, this is the fraction between [current_tick, current_tick + 1[
@@ -46,6 +54,21 @@ impl<C: Component> InterpolateStatus<C> {
             })
         })
     }
+
+    /// Fraction used to extrapolate from `previous` to `start` when there is no `end`.
+    ///
+    /// Returns a value in `]1.0, 1.0 + MAX_EXTRAPOLATION_FRACTION]`
+    pub(crate) fn extrapolation_fraction(&self) -> Option<f32> {
+        if self.end.is_some() {
+            return None;
+        }
+        let (start_tick, _) = self.start.as_ref()?;
+        let (previous_tick, _) = self.previous.as_ref()?;
+        let interval = (*start_tick - *previous_tick) as f32;
+        let elapsed = (self.current_tick - *start_tick) as f32 + self.current_overstep;
+        (interval > 0.0 && elapsed > 0.0)
+            .then(|| 1.0 + (elapsed / interval).min(MAX_EXTRAPOLATION_FRACTION))
+    }
 }
 
 /// At the end of each frame, interpolate the components between the last 2 confirmed server states
@@ -59,6 +82,7 @@ pub(crate) fn update_interpolate_status<C: SyncComponent>(
         Option<&mut C>,
         &mut InterpolateStatus<C>,
         &mut ConfirmedHistory<C>,
+        Option<&EntityInterpolationConfig>,
     )>,
 ) {
     let kind = std::any::type_name::<C>();
@@ -75,9 +99,25 @@ pub(crate) fn update_interpolate_status<C: SyncComponent>(
     let current_interpolate_overstep = connection
         .sync_manager
         .interpolation_overstep(tick_manager.as_ref());
-    for (entity, component, mut status, mut history) in query.iter_mut() {
+    for (entity, component, mut status, mut history, entity_config) in query.iter_mut() {
+        // entities with their own interpolation delay are interpolated at a different time
+        let (current_interpolate_tick, current_interpolate_overstep) = match entity_config {
+            Some(entity_config) => {
+                let time = connection.sync_manager.entity_interpolation_time(
+                    entity_config.delay,
+                    &config.interpolation,
+                    config.shared.server_replication_send_interval,
+                );
+                (
+                    time.to_tick(tick_manager.config.tick_duration),
+                    time.tick_overstep(tick_manager.config.tick_duration),
+                )
+            }
+            None => (current_interpolate_tick, current_interpolate_overstep),
+        };
         let mut start = status.start.take();
         let mut end = status.end.take();
+        let previous_start = entity_config.and_then(|_| start.clone());
 
         // if the interpolation tick is beyond the previous end tick,
         // we need to replace start with end, and clear end
@@ -164,6 +204,24 @@ pub(crate) fn update_interpolate_status<C: SyncComponent>(
         // }
         // end = temp_end;
 
+        // entities with an EntityInterpolationConfig keep track of the update before `start`,
+        // to extrapolate if the buffer underruns
+        let mut previous = status.previous.take();
+        if entity_config.is_some() {
+            if let (Some((start_tick, _)), Some((previous_tick, _))) = (&start, &previous_start) {
+                if start_tick != previous_tick {
+                    previous = previous_start;
+                }
+            }
+        }
+        // we can extrapolate for at most MAX_EXTRAPOLATION_FRACTION of the interval between the last two updates
+        let max_delta_tick = match (&start, &previous) {
+            (Some((start_tick, _)), Some((previous_tick, _))) => send_interval_delta_tick.max(
+                ((*start_tick - *previous_tick) as f32 * MAX_EXTRAPOLATION_FRACTION).ceil() as i16,
+            ),
+            _ => send_interval_delta_tick,
+        };
+
         // If it's been too long since we received an update, reset the start tick to None
         // (so that we wait again until interpolation_tick is between two server updates)
         // otherwise the interpolation will seem weird because the start tick is very old
@@ -172,8 +230,11 @@ pub(crate) fn update_interpolate_status<C: SyncComponent>(
         if end.is_none() {
             let temp_start = std::mem::take(&mut start);
             if let Some((start_tick, _)) = temp_start {
-                if current_interpolate_tick - start_tick < send_interval_delta_tick {
+                if current_interpolate_tick - start_tick < max_delta_tick {
                     start = temp_start;
+                } else if entity_config.is_some() {
+                    // keep the last update to extrapolate from it when we receive the next update
+                    previous = temp_start;
                 }
                 // else (if it's been too long), reset the server tick to None
             }
@@ -188,6 +249,7 @@ pub(crate) fn update_interpolate_status<C: SyncComponent>(
             start_tick = ?start.as_ref().map(|(tick, _)| tick),
             end_tick = ?end.as_ref().map(|(tick, _) | tick),
             "update_interpolate_status");
+        status.previous = previous;
         status.start = start;
         status.end = end;
         status.current_tick = current_interpolate_tick;
@@ -216,7 +278,15 @@ pub(crate) fn insert_interpolated_component<C: SyncComponent>(
     config: Res<ClientConfig>,
     tick_manager: Res<TickManager>,
     mut commands: Commands,
-    mut query: Query<(Entity, &InterpolateStatus<C>), Without<C>>,
+    mut query: Query<
+        (
+            Entity,
+            &InterpolateStatus<C>,
+            &ConfirmedHistory<C>,
+            Option<&EntityInterpolationConfig>,
+        ),
+        Without<C>,
+    >,
 ) {
     let tick = tick_manager.tick();
     // how many ticks between each interpolation update (add 1 to roughly take the ceil)
@@ -225,14 +295,17 @@ pub(crate) fn insert_interpolated_component<C: SyncComponent>(
         * config.shared.server_replication_send_interval.as_secs_f32()
         / config.shared.tick.tick_duration.as_secs_f32()) as i16
         + 1;
-    for (entity, status) in query.iter_mut() {
+    for (entity, status, history, entity_config) in query.iter_mut() {
         trace!("checking if we need to insert the component on the Interpolated entity");
         let mut entity_commands = commands.entity(entity);
+        // wait until enough server updates are buffered
+        let buffered = status.end.is_some() as usize + history.buffer.len() + 1;
+        let enough_buffered = entity_config.is_none_or(|c| buffered >= c.min_buffer);
         // NOTE: it is possible that we reach start_tick when end_tick is not set
         if let Some((start_tick, start_value)) = &status.start {
             trace!(is_end = ?status.end.is_some(), "start tick exists, checking if we need to insert the component");
             // we have two updates!, add the component
-            if let Some((end_tick, end_value)) = &status.end {
+            if let Some((end_tick, end_value)) = status.end.as_ref().filter(|_| enough_buffered) {
                 assert!(status.current_tick < *end_tick);
                 assert_ne!(start_tick, end_tick);
                 trace!("insert interpolated comp value because we have 2 updates");
@@ -241,7 +314,10 @@ pub(crate) fn insert_interpolated_component<C: SyncComponent>(
                 entity_commands.insert(value);
             } else {
                 // we only have one update, but enough time has passed that we should add the component anyway
-                if tick - *start_tick >= send_interval_delta_tick {
+                // (or the buffer underran and we can extrapolate from the previous update)
+                if tick - *start_tick >= send_interval_delta_tick
+                    || (status.previous.is_some() && enough_buffered)
+                {
                     trace!("insert interpolated comp value because enough time has passed");
                     entity_commands.insert(start_value.clone());
                 }
@@ -269,6 +345,11 @@ pub(crate) fn interpolate<C: Component + Clone>(
                 } else {
                     *component = start_value.clone();
                 }
+            } else if let Some(t) = status.extrapolation_fraction() {
+                // the buffer underran: extrapolate from the last two server updates
+                let (_, previous_value) = status.previous.as_ref().unwrap();
+                debug!(?start_tick, interpolate_tick=?status.current_tick, ?t, "doing extrapolation!");
+                *component = component_registry.interpolate(previous_value, start_value, t);
             }
         }
     }
@@ -527,3 +608,124 @@ pub(crate) fn interpolate<C: Component + Clone>(
 //         Ok(())
 //     }
 // }
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::{default, Entity};
+    use bevy::utils::Duration;
+
+    use super::*;
+    use crate::prelude::client::{Confirmed, InterpolationConfig};
+    use crate::prelude::server::{Replicate, SyncTarget};
+    use crate::prelude::{client, NetworkTarget, SharedConfig, TickConfig};
+    use crate::tests::protocol::{ComponentInterpolationSnap, ComponentInterpolationStep};
+    use crate::tests::stepper::BevyStepper;
+
+    fn status<C: Component>(stepper: &BevyStepper, entity: Entity) -> &InterpolateStatus<C> {
+        stepper
+            .client_app
+            .world()
+            .get::<InterpolateStatus<C>>(entity)
+            .unwrap()
+    }
+
+    /// Components are interpolated with their registered interpolation function,
+    /// and snap to the new value if they don't have one
+    #[test]
+    fn test_interpolation_fn() {
+        let tick_duration = Duration::from_millis(10);
+        let shared_config = SharedConfig {
+            tick: TickConfig::new(tick_duration),
+            ..default()
+        };
+        let client_config = client::ClientConfig {
+            interpolation: InterpolationConfig::default()
+                .with_min_delay(Duration::from_millis(100))
+                .with_send_interval_ratio(0.0),
+            ..default()
+        };
+        let mut stepper = BevyStepper::new(shared_config, client_config, tick_duration);
+        stepper.build();
+        stepper.init();
+
+        let server_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn((
+                Replicate {
+                    sync: SyncTarget {
+                        interpolation: NetworkTarget::All,
+                        ..default()
+                    },
+                    ..default()
+                },
+                ComponentInterpolationStep(0.0),
+                ComponentInterpolationSnap(0.0),
+            ))
+            .id();
+        for _ in 0..10 {
+            stepper.frame_step();
+        }
+        let confirmed_entity = stepper
+            .client_app
+            .world()
+            .resource::<client::ConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(server_entity)
+            .unwrap();
+        let interpolated_entity = stepper
+            .client_app
+            .world()
+            .get::<Confirmed>(confirmed_entity)
+            .unwrap()
+            .interpolated
+            .unwrap();
+
+        let mut before_half = false;
+        let mut after_half = false;
+        for i in 0..40 {
+            // the server sends an update every 4 ticks
+            if i % 4 == 0 {
+                let mut entity_mut = stepper.server_app.world_mut().entity_mut(server_entity);
+                entity_mut
+                    .get_mut::<ComponentInterpolationStep>()
+                    .unwrap()
+                    .0 += 4.0;
+                entity_mut
+                    .get_mut::<ComponentInterpolationSnap>()
+                    .unwrap()
+                    .0 += 4.0;
+            }
+            stepper.frame_step();
+            let step_status = status::<ComponentInterpolationStep>(&stepper, interpolated_entity);
+            let snap_status = status::<ComponentInterpolationSnap>(&stepper, interpolated_entity);
+            let (Some((_, step_start)), Some((_, step_end)), Some(t)) = (
+                &step_status.start,
+                &step_status.end,
+                step_status.interpolation_fraction(),
+            ) else {
+                continue;
+            };
+            let world = stepper.client_app.world();
+            let step = world
+                .get::<ComponentInterpolationStep>(interpolated_entity)
+                .unwrap();
+            let snap = world
+                .get::<ComponentInterpolationSnap>(interpolated_entity)
+                .unwrap();
+            // the intermediate values come from the custom step function instead of a linear interpolation
+            if t < 0.5 {
+                assert_eq!(step, step_start);
+                before_half = true;
+            } else {
+                assert_eq!(step, step_end);
+                after_half = true;
+            }
+            // without interpolation function, the value only changes when the interpolation reaches the server update
+            assert_eq!(snap, &snap_status.start.as_ref().unwrap().1);
+        }
+        assert!(before_half);
+        assert!(after_half);
+    }
+}
@@ -1,7 +1,7 @@
 use std::ops::Deref;
 
 use bevy::prelude::{
-    Commands, Component, DetectChanges, Entity, Has, Query, Ref, Res, With, Without,
+    Commands, Component, DetectChanges, Entity, Has, Query, Ref, Res, ResMut, With, Without,
 };
 use tracing::{debug, trace};
 
@@ -12,6 +12,10 @@ use crate::client::interpolation::interpolate::InterpolateStatus;
 use crate::client::interpolation::resource::InterpolationManager;
 use crate::client::interpolation::Interpolated;
 use crate::prelude::{ComponentRegistry, HasAuthority, TickManager};
+use crate::protocol::component::ComponentNetId;
+use crate::serialize::reader::Reader;
+use crate::serialize::ToBytes;
+use crate::shared::replication::entity_map::ReceiveEntityMap;
 use crate::shared::tick_manager::Tick;
 use crate::utils::ready_buffer::ReadyBuffer;
 
@@ -90,7 +94,7 @@ pub(crate) fn add_component_history<C: SyncComponent>(
     // we can't use Added<C> because the interpolated entity might be created
     // for a confirmed entity that already had the components inserted
     // (in case of authority transfer)
-    confirmed_entities: Query<(&Confirmed, &C)>,
+    confirmed_entities: Query<(Entity, &Confirmed, &C)>,
 ) {
     let current_tick = connection
         .sync_manager
@@ -98,19 +102,41 @@ pub(crate) fn add_component_history<C: SyncComponent>(
     let current_overstep = connection
         .sync_manager
         .interpolation_overstep(tick_manager.as_ref());
-    for (confirmed_entity, confirmed_component) in confirmed_entities.iter() {
+    for (entity, confirmed_entity, confirmed_component) in confirmed_entities.iter() {
         if let Some(p) = confirmed_entity.interpolated {
             if let Ok(interpolated_entity) = interpolated_entities.get(p) {
                 // safety: we know the entity exists
                 let mut interpolated_entity_mut = commands.get_entity(interpolated_entity).unwrap();
                 // insert history
-                let history = ConfirmedHistory::<C>::new();
+                let mut history = ConfirmedHistory::<C>::new();
                 // map any entities from confirmed to interpolated
                 let mut new_component = confirmed_component.clone();
                 let _ = manager.map_entities(&mut new_component, component_registry.as_ref());
                 match component_registry.interpolation_mode::<C>() {
                     ComponentSyncMode::Full => {
-                        trace!(?interpolated_entity, tick=?tick_manager.tick(), "spawn interpolation history");
+                        trace!(?interpolated_entity, tick=?tick_manager.tick(), confirmed_tick=?confirmed_entity.tick, "spawn interpolation history");
+                        // the confirmed value is the server state at the confirmed tick (for example the tick of
+                        // the initial replication snapshot). If the interpolation hasn't reached that tick yet,
+                        // keep the value in the history so that the interpolation starts from the correct tick.
+                        let start = if confirmed_entity.tick > current_tick {
+                            // pre-fill the history with the previous states sent by the server,
+                            // so that we can interpolate right away
+                            for (tick, mut snapshot) in interpolation_snapshots::<C>(
+                                &connection,
+                                component_registry.as_ref(),
+                                entity,
+                            )
+                            .filter(|(tick, _)| *tick < confirmed_entity.tick)
+                            {
+                                let _ = manager
+                                    .map_entities(&mut snapshot, component_registry.as_ref());
+                                history.buffer.push(tick, snapshot);
+                            }
+                            history.buffer.push(confirmed_entity.tick, new_component);
+                            None
+                        } else {
+                            Some((current_tick, new_component))
+                        };
                         interpolated_entity_mut.insert((
                             // NOTE: we probably do NOT want to insert the component right away, instead we want to wait until we have two updates
                             //  we can interpolate between. Otherwise it will look jarring if send_interval is low. (because the entity will
@@ -118,8 +144,9 @@ pub(crate) fn add_component_history<C: SyncComponent>(
                             // new_component,
                             history,
                             InterpolateStatus::<C> {
-                                start: Some((current_tick, new_component)),
+                                start,
                                 end: None,
+                                previous: None,
                                 current_tick,
                                 current_overstep,
                             },
@@ -136,6 +163,41 @@ pub(crate) fn add_component_history<C: SyncComponent>(
     }
 }
 
+/// Deserialize the values of the component `C` in the interpolation snapshots that the server
+/// sent for the confirmed entity
+fn interpolation_snapshots<'a, C: SyncComponent>(
+    connection: &'a ConnectionManager,
+    component_registry: &'a ComponentRegistry,
+    confirmed_entity: Entity,
+) -> impl Iterator<Item = (Tick, C)> + 'a {
+    let net_id = component_registry.net_id::<C>();
+    connection
+        .replication_receiver
+        .remote_entity_map
+        .get_remote(confirmed_entity)
+        .and_then(|server_entity| connection.interpolation_snapshots.get(&server_entity))
+        .into_iter()
+        .flatten()
+        .flat_map(move |(tick, components)| {
+            components.iter().filter_map(move |bytes| {
+                let mut reader = Reader::from(bytes.clone());
+                if ComponentNetId::from_bytes(&mut reader).ok()? != net_id {
+                    return None;
+                }
+                component_registry
+                    .raw_deserialize::<C>(&mut reader, &mut ReceiveEntityMap::default())
+                    .ok()
+                    .map(|component| (*tick, component))
+            })
+        })
+}
+
+/// Remove the interpolation snapshots sent by the server once the interpolation history
+/// of the entities has been created
+pub(crate) fn clear_interpolation_snapshots(mut connection: ResMut<ConnectionManager>) {
+    connection.interpolation_snapshots.clear();
+}
+
 /// When we receive a server update for an interpolated component, we need to store it in the confirmed history,
 pub(crate) fn apply_confirmed_update_mode_full<C: SyncComponent>(
     component_registry: Res<ComponentRegistry>,
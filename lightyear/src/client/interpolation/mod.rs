@@ -12,7 +12,9 @@ pub use visual_interpolation::{VisualInterpolateStatus, VisualInterpolationPlugi
 use crate::client::components::LerpFn;
 use crate::client::interpolation::resource::InterpolationManager;
 
+pub mod buffer_limit;
 mod despawn;
+pub mod entity_config;
 pub mod interpolate;
 pub mod interpolation_history;
 pub mod plugin;
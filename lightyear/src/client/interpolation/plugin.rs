@@ -1,11 +1,19 @@
 use bevy::prelude::*;
 use bevy::utils::Duration;
 
+use super::buffer_limit::{
+    compute_evictions, evict_snapshots, record_buffer_usage, reset_buffer_usage, BufferLimitSet,
+    InterpolationBufferUsage, InterpolationPriority,
+};
 use super::interpolation_history::{
     add_component_history, apply_confirmed_update_mode_full, apply_confirmed_update_mode_simple,
+    clear_interpolation_snapshots,
 };
 use crate::client::components::{ComponentSyncMode, SyncComponent};
-use crate::client::interpolation::despawn::{despawn_interpolated, removed_components};
+use crate::client::interpolation::despawn::{
+    despawn_interpolated, remove_interpolated, removed_components,
+};
+use crate::client::interpolation::entity_config::EntityInterpolationConfig;
 use crate::client::interpolation::interpolate::{
     insert_interpolated_component, interpolate, update_interpolate_status,
 };
@@ -67,6 +75,19 @@ pub struct InterpolationConfig {
     /// The higher the server update_rate (i.e. smaller send_interval), the smaller the interpolation delay
     /// Set to 0.0 if you want to only use the Delay
     pub send_interval_ratio: f32,
+    /// Maximum number of snapshots stored in the interpolation buffers of all interpolated entities.
+    /// When exceeded, the oldest snapshots of the entities with the lowest
+    /// [`InterpolationPriority`](super::buffer_limit::InterpolationPriority) are evicted.
+    /// Each buffer keeps at least [`MIN_SNAPSHOTS`](super::buffer_limit::MIN_SNAPSHOTS) snapshots.
+    ///
+    /// Set to `None` to not limit the size of the buffers
+    pub max_snapshots: Option<usize>,
+    /// Extra interpolation delay, as a multiple of the measured jitter of the arrival times of server packets.
+    ///
+    /// This absorbs the timing variations of the server's sends (for example because of variable
+    /// server frame times), independently of the network latency.
+    /// Set to 0.0 to not add any delay for the server send jitter
+    pub send_jitter_margin: f32,
 }
 
 impl Default for InterpolationConfig {
@@ -74,6 +95,8 @@ impl Default for InterpolationConfig {
         Self {
             min_delay: Duration::from_millis(0),
             send_interval_ratio: 2.0,
+            max_snapshots: None,
+            send_jitter_margin: 0.0,
         }
     }
 }
@@ -89,11 +112,26 @@ impl InterpolationConfig {
         self
     }
 
+    pub fn with_max_snapshots(mut self, max_snapshots: usize) -> Self {
+        self.max_snapshots = Some(max_snapshots);
+        self
+    }
+
+    pub fn with_send_jitter_margin(mut self, send_jitter_margin: f32) -> Self {
+        self.send_jitter_margin = send_jitter_margin;
+        self
+    }
+
     /// How much behind the latest server update we want the interpolation time to be
-    pub(crate) fn to_duration(self, server_send_interval: Duration) -> Duration {
+    pub(crate) fn to_duration(
+        self,
+        server_send_interval: Duration,
+        server_send_jitter: Duration,
+    ) -> Duration {
         // TODO: deal with server_send_interval = 0 (set to frame rate)
         let ratio_value = server_send_interval.mul_f32(self.send_interval_ratio);
         std::cmp::max(ratio_value, self.min_delay)
+            + server_send_jitter.mul_f32(self.send_jitter_margin)
     }
 }
 
@@ -123,6 +161,8 @@ pub enum InterpolationSet {
     SpawnHistory,
     /// Update component history, interpolation status
     PrepareInterpolation,
+    /// Evict snapshots from the component histories if they exceed the configured maximum
+    LimitBuffers,
     /// Interpolate between last 2 server states. Has to be overriden if
     /// `InterpolationConfig.custom_interpolation_logic` is set to true
     Interpolate,
@@ -160,6 +200,13 @@ pub fn add_prepare_interpolation_systems<C: SyncComponent>(
                     .chain()
                     .in_set(InterpolationSet::PrepareInterpolation),
             );
+            app.add_systems(
+                Update,
+                (
+                    record_buffer_usage::<C>.in_set(BufferLimitSet::Record),
+                    evict_snapshots::<C>.in_set(BufferLimitSet::Evict),
+                ),
+            );
         }
         ComponentSyncMode::Simple => {
             app.add_systems(
@@ -187,10 +234,13 @@ impl Plugin for InterpolationPlugin {
 
         // REFLECT
         app.register_type::<InterpolationConfig>()
-            .register_type::<Interpolated>();
+            .register_type::<Interpolated>()
+            .register_type::<InterpolationPriority>()
+            .register_type::<EntityInterpolationConfig>();
 
         // RESOURCES
         app.init_resource::<InterpolationManager>();
+        app.init_resource::<InterpolationBufferUsage>();
         // SETS
         app.configure_sets(
             Update,
@@ -199,11 +249,18 @@ impl Plugin for InterpolationPlugin {
                 InterpolationSet::SpawnHistory,
                 // PrepareInterpolation uses the sync values (which are used to compute interpolation)
                 InterpolationSet::PrepareInterpolation.after(SyncSet),
+                InterpolationSet::LimitBuffers,
                 InterpolationSet::Interpolate,
             )
                 .in_set(InterpolationSet::All)
                 .chain(),
         );
+        app.configure_sets(
+            Update,
+            (BufferLimitSet::Record, BufferLimitSet::Evict)
+                .chain()
+                .in_set(InterpolationSet::LimitBuffers),
+        );
         app.configure_sets(
             Update,
             InterpolationSet::All.run_if(should_run_interpolation),
@@ -211,7 +268,23 @@ impl Plugin for InterpolationPlugin {
         // SYSTEMS
         app.add_systems(
             Update,
-            spawn_interpolated_entity.in_set(InterpolationSet::SpawnInterpolation),
+            (remove_interpolated, spawn_interpolated_entity)
+                .chain()
+                .in_set(InterpolationSet::SpawnInterpolation),
+        );
+        app.add_systems(
+            Update,
+            clear_interpolation_snapshots.in_set(InterpolationSet::PrepareInterpolation),
+        );
+        app.add_systems(
+            Update,
+            (
+                reset_buffer_usage.before(BufferLimitSet::Record),
+                compute_evictions
+                    .after(BufferLimitSet::Record)
+                    .before(BufferLimitSet::Evict),
+            )
+                .in_set(InterpolationSet::LimitBuffers),
         );
         app.add_observer(despawn_interpolated);
     }
@@ -0,0 +1,186 @@
+//! Client side of the deterministic lockstep mode
+//!
+//! See [`crate::shared::lockstep`] for an overview.
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use crate::channel::builder::LockstepChannel;
+use crate::client::connection::ConnectionManager;
+use crate::client::message::ReceiveMessage;
+use crate::client::run_conditions::is_synced;
+use crate::prelude::{ClientId, Tick, TickManager, UserAction};
+use crate::shared::events::components::InputEvent;
+use crate::shared::lockstep::{
+    LockstepChecksumFns, LockstepChecksumMessage, LockstepConfig, LockstepInputMessage,
+    LockstepTickMessage, LockstepUpdate,
+};
+use crate::shared::sets::{ClientMarker, InternalMainSet};
+
+/// Number of checksums of previous ticks that are kept on the client
+const CHECKSUM_HISTORY_LEN: usize = 64;
+
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone, Copy)]
+pub enum LockstepSet {
+    // FIXED PRE UPDATE
+    /// System Set to buffer the input of the local client for the current tick.
+    /// The User should add their system here!!
+    BufferInputs,
+    /// System Set to send the buffered input to the server
+    SendInputs,
+
+    // FIXED UPDATE
+    /// System Set where the [`LockstepUpdate`] schedule is run for every tick that is ready.
+    /// It should run before the systems that depend on the simulation state.
+    Simulate,
+}
+
+/// Lockstep state of the client
+#[derive(Resource, Debug)]
+pub struct LockstepManager<A> {
+    config: LockstepConfig,
+    /// Input of the local client for the current tick
+    input: Option<A>,
+    /// Inputs received from the server for the ticks that haven't been simulated yet, in order
+    pending: VecDeque<LockstepTickMessage<A>>,
+    last_simulated_tick: Option<Tick>,
+    /// Checksums of the last simulated ticks, from oldest (front) to most recent (back)
+    checksums: VecDeque<(Tick, u64)>,
+}
+
+impl<A: UserAction> LockstepManager<A> {
+    fn new(config: LockstepConfig) -> Self {
+        Self {
+            config,
+            input: None,
+            pending: VecDeque::new(),
+            last_simulated_tick: None,
+            checksums: VecDeque::new(),
+        }
+    }
+
+    /// Buffer the input of the local client for the current tick.
+    ///
+    /// It will be simulated by every client `input_delay_ticks` ticks later.
+    pub fn add_input(&mut self, input: A) {
+        self.input = Some(input);
+    }
+
+    /// The last tick that was simulated in the [`LockstepUpdate`] schedule
+    pub fn last_simulated_tick(&self) -> Option<Tick> {
+        self.last_simulated_tick
+    }
+
+    /// The checksum of the simulation state after the given tick, if it is still in the history
+    pub fn checksum(&self, tick: Tick) -> Option<u64> {
+        self.checksums
+            .iter()
+            .find(|(t, _)| *t == tick)
+            .map(|(_, checksum)| *checksum)
+    }
+}
+
+pub struct ClientLockstepPlugin<A: UserAction> {
+    config: LockstepConfig,
+    _marker: std::marker::PhantomData<A>,
+}
+
+impl<A: UserAction> ClientLockstepPlugin<A> {
+    pub(crate) fn new(config: LockstepConfig) -> Self {
+        Self {
+            config,
+            _marker: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<A: UserAction> Plugin for ClientLockstepPlugin<A> {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(LockstepManager::<A>::new(self.config));
+        app.add_event::<InputEvent<A, ClientId>>();
+        app.init_schedule(LockstepUpdate);
+        app.configure_sets(
+            FixedPreUpdate,
+            (LockstepSet::BufferInputs, LockstepSet::SendInputs).chain(),
+        );
+        app.add_systems(
+            FixedPreUpdate,
+            send_input::<A>
+                .in_set(LockstepSet::SendInputs)
+                .run_if(is_synced),
+        );
+        app.add_systems(
+            PreUpdate,
+            receive_tick_inputs::<A>.after(InternalMainSet::<ClientMarker>::ReceiveEvents),
+        );
+        app.add_systems(FixedUpdate, simulate::<A>.in_set(LockstepSet::Simulate));
+    }
+}
+
+/// Send the buffered input to the server; it will be simulated `input_delay_ticks` ticks later
+fn send_input<A: UserAction>(
+    tick_manager: Res<TickManager>,
+    mut manager: ResMut<LockstepManager<A>>,
+    mut connection: ResMut<ConnectionManager>,
+) {
+    let message = LockstepInputMessage {
+        tick: tick_manager.tick() + manager.config.input_delay_ticks as i16,
+        input: manager.input.take(),
+    };
+    if let Err(e) = connection.send_message::<LockstepChannel, _>(&message) {
+        error!("could not send lockstep input: {:?}", e);
+    }
+}
+
+/// Buffer the inputs of all the clients received from the server
+fn receive_tick_inputs<A: UserAction>(
+    mut messages: ResMut<Events<ReceiveMessage<LockstepTickMessage<A>>>>,
+    mut manager: ResMut<LockstepManager<A>>,
+) {
+    // the messages are received in order, since they are sent on an ordered channel
+    manager
+        .pending
+        .extend(messages.drain().map(|event| event.message));
+}
+
+/// Run the [`LockstepUpdate`] schedule for every tick for which we received the inputs of all
+/// the clients, up to the current tick, and send the checksum of the resulting state
+fn simulate<A: UserAction>(world: &mut World) {
+    let current_tick = world.resource::<TickManager>().tick();
+    loop {
+        let mut manager = world.resource_mut::<LockstepManager<A>>();
+        if manager
+            .pending
+            .front()
+            .is_none_or(|message| message.tick > current_tick)
+        {
+            break;
+        }
+        let LockstepTickMessage { tick, inputs } = manager.pending.pop_front().unwrap();
+        world.send_event_batch(
+            inputs
+                .into_iter()
+                .map(|(client_id, input)| InputEvent::new(input, client_id)),
+        );
+        world.run_schedule(LockstepUpdate);
+        // the inputs must only be visible during the simulation of their tick
+        world
+            .resource_mut::<Events<InputEvent<A, ClientId>>>()
+            .clear();
+
+        let checksum = LockstepChecksumFns::checksum(world);
+        trace!(?tick, ?checksum, "Simulated lockstep tick");
+        let mut manager = world.resource_mut::<LockstepManager<A>>();
+        manager.last_simulated_tick = Some(tick);
+        manager.checksums.push_back((tick, checksum));
+        if manager.checksums.len() > CHECKSUM_HISTORY_LEN {
+            manager.checksums.pop_front();
+        }
+        if let Err(e) = world
+            .resource_mut::<ConnectionManager>()
+            .send_message::<LockstepChannel, _>(&LockstepChecksumMessage { tick, checksum })
+        {
+            error!("could not send lockstep checksum: {:?}", e);
+        }
+    }
+}
@@ -5,7 +5,7 @@ use crate::client::error::ClientError;
 use crate::prelude::client::{ClientConnection, NetClient};
 use crate::prelude::{
     client::is_connected, is_host_server, Channel, ChannelKind, ClientId, MainSet, Message,
-    MessageRegistry, MessageSend,
+    MessageId, MessageRegistry, MessageSend,
 };
 use crate::serialize::reader::Reader;
 use crate::serialize::{SerializationError, ToBytes};
@@ -152,6 +152,32 @@ impl ConnectionManager {
         self.send_message_to_target::<C, M>(message, NetworkTarget::None)
     }
 
+    /// Send a [`Message`] to the server using a specific [`Channel`], and return the id of the message.
+    ///
+    /// The id can be used to [`cancel_message`](Self::cancel_message) on reliable channels.
+    /// It is `None` if the channel does not assign message ids, or in host-server mode.
+    pub fn send_message_with_id<C: Channel, M: Message>(
+        &mut self,
+        message: &M,
+    ) -> Result<Option<MessageId>, ClientError> {
+        if self.host_server {
+            self.send_message::<C, M>(message)?;
+            return Ok(None);
+        }
+        NetworkTarget::None.to_bytes(&mut self.writer)?;
+        self.message_registry.serialize(
+            message,
+            &mut self.writer,
+            &mut self.replication_receiver.remote_entity_map.local_to_remote,
+        )?;
+        let message_bytes = self.writer.split();
+        // buffer the messages that were sent before this one first, to keep the send order
+        self.buffer_messages_to_send()?;
+        Ok(self
+            .message_manager
+            .buffer_send(message_bytes, ChannelKind::of::<C>())?)
+    }
+
     // TODO: find a way to make this work
     // /// Trigger a [`Message`] to the server using a specific [`Channel`]
     // pub fn trigger_event<C: Channel, E: Event + Message>(
@@ -345,7 +371,7 @@ mod tests {
     use crate::prelude::{ClientSendMessage, ServerReceiveMessage};
     use crate::serialize::writer::Writer;
     use crate::tests::host_server_stepper::HostServerStepper;
-    use crate::tests::protocol::{Channel1, IntegerEvent, StringMessage};
+    use crate::tests::protocol::{Channel1, IntegerEvent, ReliableChannel, StringMessage};
     use crate::tests::stepper::BevyStepper;
     use bevy::prelude::{EventReader, Resource, Update};
 
@@ -409,6 +435,64 @@ mod tests {
         assert_eq!(stepper.server_app.world().resource::<Counter>().0, 1);
     }
 
+    /// Cancel a message using the id returned when sending it via ConnectionManager
+    #[test]
+    fn client_cancel_message_with_id() {
+        let mut stepper = BevyStepper::default();
+        stepper.server_app.init_resource::<Counter>();
+        stepper.server_app.add_systems(Update, count_messages);
+
+        let mut manager = stepper
+            .client_app
+            .world_mut()
+            .resource_mut::<ConnectionManager>();
+        let message_id = manager
+            .send_message_with_id::<ReliableChannel, StringMessage>(&StringMessage("a".to_string()))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            manager.pending_messages::<ReliableChannel>().unwrap(),
+            vec![message_id]
+        );
+        assert!(manager
+            .cancel_message::<ReliableChannel>(message_id)
+            .unwrap());
+        stepper.frame_step();
+        stepper.frame_step();
+        assert_eq!(stepper.server_app.world().resource::<Counter>().0, 0);
+
+        // a message that is not cancelled is received
+        stepper
+            .client_app
+            .world_mut()
+            .resource_mut::<ConnectionManager>()
+            .send_message_with_id::<ReliableChannel, StringMessage>(&StringMessage("a".to_string()))
+            .unwrap()
+            .unwrap();
+        stepper.frame_step();
+        stepper.frame_step();
+        assert_eq!(stepper.server_app.world().resource::<Counter>().0, 1);
+    }
+
+    /// In host-server mode the message has no id, but it is still sent
+    #[test]
+    fn client_send_message_with_id_as_host_server() {
+        let mut stepper = HostServerStepper::default();
+        stepper.server_app.init_resource::<Counter>();
+        stepper.server_app.add_systems(Update, count_messages);
+
+        let message_id = stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<ConnectionManager>()
+            .send_message_with_id::<ReliableChannel, StringMessage>(&StringMessage("a".to_string()))
+            .unwrap();
+        assert!(message_id.is_none());
+        stepper.frame_step();
+        stepper.frame_step();
+        assert_eq!(stepper.server_app.world().resource::<Counter>().0, 1);
+    }
+
     #[test]
     fn client_send_message_via_send_event() {
         let mut stepper = BevyStepper::default();
@@ -13,6 +13,8 @@ pub mod input;
 
 pub mod interpolation;
 
+pub mod lockstep;
+
 pub mod plugin;
 
 pub mod prediction;
@@ -20,6 +22,7 @@ pub mod prediction;
 pub mod sync;
 
 pub mod diagnostics;
+pub mod display;
 mod easings;
 
 pub(crate) mod io;
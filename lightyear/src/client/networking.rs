@@ -11,17 +11,21 @@ use crate::client::config::ClientConfig;
 use crate::client::connection::ConnectionManager;
 use crate::client::events::{ConnectEvent, DisconnectEvent};
 use crate::client::io::ClientIoEvent;
+use crate::client::message::ReceiveMessage;
 use crate::client::replication::send::ReplicateToServer;
 use crate::client::run_conditions::is_disconnected;
-use crate::client::sync::SyncSet;
+use crate::client::sync::{SyncDiagnostics, SyncSet};
 use crate::connection::client::{ClientConnection, ConnectionError, ConnectionState, NetClient};
 use crate::connection::server::IoConfig;
+use crate::packet::compression::CompressionConfig;
 use crate::prelude::client::NetConfig;
 use crate::prelude::{
     is_host_server, server, ChannelRegistry, MainSet, MessageRegistry, TickManager, TimeManager,
 };
 use crate::protocol::component::ComponentRegistry;
 use crate::server::clients::ControlledEntities;
+use crate::server::shutdown::ServerShutdown;
+use crate::shared::identity::RelayState;
 use crate::shared::replication::components::Replicated;
 use crate::shared::sets::{ClientMarker, InternalMainSet};
 use crate::transport::io::IoState;
@@ -35,8 +39,10 @@ impl Plugin for ClientNetworkingPlugin {
             // REFLECTION
             .register_type::<HostServerMetadata>()
             .register_type::<IoConfig>()
+            .register_type::<SyncDiagnostics>()
             // RESOURCE
             .init_resource::<HostServerMetadata>()
+            .init_resource::<SyncDiagnostics>()
             // SYSTEM SETS
             .configure_sets(
                 PreUpdate,
@@ -69,6 +75,10 @@ impl Plugin for ClientNetworkingPlugin {
                 (listen_io_state, (receive_packets, receive).chain())
                     .in_set(InternalMainSet::<ClientMarker>::Receive),
             )
+            .add_systems(
+                PreUpdate,
+                handle_server_shutdown.after(InternalMainSet::<ClientMarker>::ReceiveEvents),
+            )
             .add_systems(
                 PostUpdate,
                 (
@@ -248,6 +258,7 @@ pub(crate) fn sync_update(
     mut time_manager: ResMut<TimeManager>,
     mut tick_manager: ResMut<TickManager>,
     mut virtual_time: ResMut<Time<Virtual>>,
+    mut diagnostics: ResMut<SyncDiagnostics>,
 ) {
     let connection = connection.into_inner();
     // NOTE: this triggers change detection
@@ -282,6 +293,11 @@ pub(crate) fn sync_update(
         let relative_speed = time_manager.get_relative_speed();
         virtual_time.set_relative_speed(relative_speed);
     }
+    diagnostics.set_if_neq(
+        connection
+            .sync_manager
+            .diagnostics(tick_manager.as_ref(), &connection.ping_manager),
+    );
 }
 
 /// Bevy [`State`] representing the networking state of the client.
@@ -382,13 +398,16 @@ fn on_connect_host_server(
     mut commands: Commands,
     netcode: Res<ClientConnection>,
     mut metadata: ResMut<HostServerMetadata>,
+    mut connection_manager: ResMut<ConnectionManager>,
     mut server_manager: ResMut<crate::server::connection::ConnectionManager>,
+    tick_manager: Res<TickManager>,
     mut connect_event_writer: EventWriter<ConnectEvent>,
 ) {
+    connection_manager.host_server = true;
     // spawn an entity for the client
     let client_entity = commands.spawn(ControlledEntities::default()).id();
     // start a server connection for that client (which will also send a ConnectEvent on the server)
-    server_manager.add(netcode.id(), client_entity);
+    server_manager.add(netcode.id(), client_entity, tick_manager.tick());
     server_manager
         .connection_mut(netcode.id())
         .unwrap()
@@ -399,6 +418,18 @@ fn on_connect_host_server(
     commands.trigger(ConnectEvent::new(netcode.id()));
 }
 
+/// Store the reason of the shutdown sent by the server, so that it can be included
+/// in the [`DisconnectEvent`] once the server disconnects us
+fn handle_server_shutdown(
+    mut connection_manager: ResMut<ConnectionManager>,
+    mut messages: ResMut<Events<ReceiveMessage<ServerShutdown>>>,
+) {
+    for message_event in messages.drain() {
+        debug!(reason = ?message_event.message.reason, "The server is shutting down");
+        connection_manager.server_shutdown_reason = Some(message_event.message.reason);
+    }
+}
+
 /// System that runs when we enter the Disconnected state
 /// Updates the DisconnectEvent events
 fn on_disconnecting(
@@ -424,7 +455,10 @@ fn on_disconnecting(
 
     // no need to update the io state, because we will recreate a new `ClientConnection`
     // for the next connection attempt
-    let reason = std::mem::take(&mut netclient.disconnect_reason);
+    let reason = match connection_manager.server_shutdown_reason.take() {
+        Some(shutdown_reason) => Some(ConnectionError::ServerShutdown(shutdown_reason)),
+        None => std::mem::take(&mut netclient.disconnect_reason),
+    };
     disconnect_event_writer.send(DisconnectEvent { reason });
     // TODO: how can we also provide a reason here? or do we even need to?
     // we need to also trigger the event because we sometimes react to it via observers
@@ -455,12 +489,18 @@ fn on_disconnecting_host_server(
 /// - the client connection's internal time is up-to-date (otherwise it might not be, since we don't call `update` while disconnected)
 /// - we can take into account any changes to the client config
 fn rebuild_client_connection(world: &mut World) {
-    let client_config = world.resource::<ClientConfig>().clone();
+    let mut client_config = world.resource::<ClientConfig>().clone();
+    // fall back to the compression of the IoConfig, which is deprecated
+    if matches!(client_config.packet.compression, CompressionConfig::None) {
+        client_config.packet.compression = client_config.net.io_compression();
+    }
 
     // if the server is started, that means we're planning to run in host-server mode
+    // (unless the app is a relay, in which case the client connects to a remote upstream server)
     if world
         .get_resource::<State<server::NetworkingState>>()
         .is_some_and(|s| s.get() == &server::NetworkingState::Started)
+        && !world.contains_resource::<State<RelayState>>()
     {
         assert!(
             matches!(client_config.net, NetConfig::Local { .. }),
@@ -518,6 +558,11 @@ pub trait ClientCommandsExt {
 
     /// Disconnect the client
     fn disconnect_client(&mut self);
+
+    /// Ask the server for authority over an entity
+    ///
+    /// See [`ConnectionManager::request_authority`]
+    fn request_authority(&mut self, entity: Entity);
 }
 
 impl ClientCommandsExt for Commands<'_, '_> {
@@ -528,6 +573,10 @@ impl ClientCommandsExt for Commands<'_, '_> {
     fn disconnect_client(&mut self) {
         self.queue(|world: &mut World| world.disconnect_client());
     }
+
+    fn request_authority(&mut self, entity: Entity) {
+        self.queue(move |world: &mut World| world.request_authority(entity));
+    }
 }
 
 impl ClientCommandsExt for World {
@@ -540,6 +589,13 @@ impl ClientCommandsExt for World {
         self.resource_mut::<NextState<NetworkingState>>()
             .set(NetworkingState::Disconnecting);
     }
+
+    fn request_authority(&mut self, entity: Entity) {
+        let _ = self
+            .resource_mut::<ConnectionManager>()
+            .request_authority(entity)
+            .inspect_err(|e| error!("could not request authority over {entity:?}: {e:?}"));
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,284 @@
+//! Handle authority transfers for predicted entities
+//!
+//! When a client gains authority over an entity that it was predicting, the client starts simulating the
+//! `Confirmed` entity directly. To avoid a visible snap back to the (older) confirmed state,
+//! the state of the `Predicted` entity is first copied to the `Confirmed` entity, and then the `Predicted`
+//! entity is released.
+//!
+//! When a client loses authority and should predict the entity again, the new `Predicted` entity is spawned
+//! from the current state of the `Confirmed` entity, so the prediction continues from the state that the
+//! client was simulating.
+//!
+//! The authority of predicted entities is tick-versioned: the [`AuthorityPeer`] of a `Predicted` entity is
+//! updated every tick from its [`AuthorityHistory`], so that ticks replayed during a rollback use the
+//! authority that was in effect at that tick.
+use bevy::prelude::*;
+use tracing::trace;
+
+use crate::client::components::{Confirmed, SyncComponent};
+use crate::client::prediction::rollback::Rollback;
+use crate::client::prediction::Predicted;
+use crate::prelude::TickManager;
+use crate::shared::replication::authority::{AuthorityHistory, AuthorityPeer, HasAuthority};
+
+/// Copy the component `C` from the `Predicted` entity to the `Confirmed` entity
+/// when the client gains authority over the entity
+pub(crate) fn handover_predicted_state<C: SyncComponent>(
+    mut confirmed_query: Query<(&Confirmed, &mut C), (Added<HasAuthority>, Without<Predicted>)>,
+    predicted_query: Query<&C, With<Predicted>>,
+) {
+    for (confirmed, mut component) in confirmed_query.iter_mut() {
+        let Some(predicted) = confirmed.predicted else {
+            continue;
+        };
+        if let Ok(predicted_component) = predicted_query.get(predicted) {
+            trace!(
+                ?predicted,
+                "Copying predicted {} to the confirmed entity after gaining authority",
+                std::any::type_name::<C>()
+            );
+            *component = predicted_component.clone();
+        }
+    }
+}
+
+/// Despawn the `Predicted` entity once the client has gained authority over the `Confirmed` entity.
+///
+/// This runs after [`handover_predicted_state`] for every predicted component.
+pub(crate) fn release_predicted_entity(
+    mut commands: Commands,
+    mut confirmed_query: Query<&mut Confirmed, Added<HasAuthority>>,
+) {
+    for mut confirmed in confirmed_query.iter_mut() {
+        if let Some(predicted) = confirmed.predicted.take() {
+            trace!(
+                ?predicted,
+                "Releasing the predicted entity after gaining authority"
+            );
+            if let Some(entity_commands) = commands.get_entity(predicted) {
+                entity_commands.despawn_recursive();
+            }
+        }
+    }
+}
+
+/// Set the [`AuthorityPeer`] of the `Predicted` entities to the authority that was in effect at the current tick.
+///
+/// This runs in `FixedPreUpdate`, so also before each tick replayed during rollback.
+pub(crate) fn update_authority_from_history(
+    mut commands: Commands,
+    tick_manager: Res<TickManager>,
+    rollback: Res<Rollback>,
+    query: Query<(Entity, &AuthorityHistory, Option<&AuthorityPeer>), With<Predicted>>,
+) {
+    let tick = tick_manager.tick_or_rollback_tick(rollback.as_ref());
+    for (entity, history, authority) in query.iter() {
+        let authority_at_tick = history.authority_at(tick);
+        if authority != Some(&authority_at_tick) {
+            trace!(
+                ?entity,
+                ?tick,
+                ?authority_at_tick,
+                "Updating authority of predicted entity"
+            );
+            commands.entity(entity).insert(authority_at_tick);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::client::Confirmed;
+    use crate::prelude::server::{Replicate, SyncTarget};
+    use crate::prelude::{client, ClientId, NetworkTarget};
+    use crate::server::replication::commands::AuthorityCommandExt;
+    use crate::shared::replication::authority::{AuthorityPeer, HasAuthority};
+    use crate::tests::multi_stepper::{MultiBevyStepper, TEST_CLIENT_ID_1, TEST_CLIENT_ID_2};
+    use crate::tests::protocol::ComponentSyncModeFull;
+    use bevy::prelude::*;
+
+    /// Value displayed for the entity on the client: the predicted entity if there is one,
+    /// otherwise the confirmed entity
+    fn displayed_value(app: &App, confirmed_entity: Entity) -> f32 {
+        let confirmed = app.world().get::<Confirmed>(confirmed_entity).unwrap();
+        let entity = confirmed.predicted.unwrap_or(confirmed_entity);
+        app.world().get::<ComponentSyncModeFull>(entity).unwrap().0
+    }
+
+    /// A moving predicted entity is transferred from the server to client 1, then from client 1 to client 2.
+    /// The value displayed on client 1 never jumps back.
+    #[test]
+    fn test_transfer_authority_predicted_entity() {
+        let mut stepper = MultiBevyStepper::default();
+        let client_1 = ClientId::Netcode(TEST_CLIENT_ID_1);
+        let client_2 = ClientId::Netcode(TEST_CLIENT_ID_2);
+
+        let server_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn((
+                Replicate {
+                    sync: SyncTarget {
+                        prediction: NetworkTarget::Single(client_1),
+                        interpolation: NetworkTarget::Single(client_2),
+                    },
+                    ..default()
+                },
+                ComponentSyncModeFull(1.0),
+            ))
+            .id();
+        for _ in 0..5 {
+            stepper.frame_step();
+        }
+        let confirmed_1 = stepper
+            .client_app_1
+            .world()
+            .resource::<client::ConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(server_entity)
+            .expect("entity was not replicated to client 1");
+        let confirmed_2 = stepper
+            .client_app_2
+            .world()
+            .resource::<client::ConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(server_entity)
+            .expect("entity was not replicated to client 2");
+        let predicted_1 = stepper
+            .client_app_1
+            .world()
+            .get::<Confirmed>(confirmed_1)
+            .unwrap()
+            .predicted
+            .expect("entity is not predicted on client 1");
+
+        // the predicted entity moved ahead of the confirmed state
+        stepper
+            .client_app_1
+            .world_mut()
+            .get_mut::<ComponentSyncModeFull>(predicted_1)
+            .unwrap()
+            .0 = 5.0;
+        let mut previous = displayed_value(&stepper.client_app_1, confirmed_1);
+        assert_eq!(previous, 5.0);
+
+        // transfer authority to client 1
+        stepper
+            .client_app_1
+            .world_mut()
+            .entity_mut(confirmed_1)
+            .insert(client::Replicate::default())
+            .remove::<HasAuthority>();
+        stepper
+            .server_app
+            .world_mut()
+            .commands()
+            .entity(server_entity)
+            .transfer_authority(AuthorityPeer::Client(client_1));
+        for _ in 0..5 {
+            stepper.frame_step();
+            let value = displayed_value(&stepper.client_app_1, confirmed_1);
+            assert!(value >= previous, "value jumped from {previous} to {value}");
+            previous = value;
+        }
+        // the client simulates the confirmed entity from the predicted state
+        assert!(stepper
+            .client_app_1
+            .world()
+            .get::<HasAuthority>(confirmed_1)
+            .is_some());
+        assert!(stepper
+            .client_app_1
+            .world()
+            .get_entity(predicted_1)
+            .is_err());
+        assert_eq!(
+            stepper
+                .client_app_1
+                .world()
+                .get::<ComponentSyncModeFull>(confirmed_1)
+                .unwrap(),
+            &ComponentSyncModeFull(5.0)
+        );
+
+        // the entity keeps moving on client 1, and the other peers receive the updates
+        for i in 0..10 {
+            stepper
+                .client_app_1
+                .world_mut()
+                .get_mut::<ComponentSyncModeFull>(confirmed_1)
+                .unwrap()
+                .0 = 6.0 + i as f32;
+            stepper.frame_step();
+            let value = displayed_value(&stepper.client_app_1, confirmed_1);
+            assert!(value >= previous, "value jumped from {previous} to {value}");
+            previous = value;
+        }
+        for _ in 0..5 {
+            stepper.frame_step();
+        }
+        assert_eq!(
+            stepper
+                .server_app
+                .world()
+                .get::<ComponentSyncModeFull>(server_entity)
+                .unwrap(),
+            &ComponentSyncModeFull(15.0)
+        );
+        assert_eq!(
+            stepper
+                .client_app_2
+                .world()
+                .get::<ComponentSyncModeFull>(confirmed_2)
+                .unwrap(),
+            &ComponentSyncModeFull(15.0)
+        );
+
+        // transfer authority to client 2: client 1 predicts the entity again, starting from its latest state
+        stepper
+            .client_app_2
+            .world_mut()
+            .entity_mut(confirmed_2)
+            .insert(client::Replicate::default())
+            .remove::<HasAuthority>();
+        stepper
+            .server_app
+            .world_mut()
+            .commands()
+            .entity(server_entity)
+            .transfer_authority(AuthorityPeer::Client(client_2));
+        for _ in 0..5 {
+            stepper.frame_step();
+            let value = displayed_value(&stepper.client_app_1, confirmed_1);
+            assert!(value >= previous, "value jumped from {previous} to {value}");
+            previous = value;
+        }
+        assert!(stepper
+            .client_app_1
+            .world()
+            .get::<HasAuthority>(confirmed_1)
+            .is_none());
+        let predicted_1 = stepper
+            .client_app_1
+            .world()
+            .get::<Confirmed>(confirmed_1)
+            .unwrap()
+            .predicted
+            .expect("entity is not predicted again on client 1");
+        assert_eq!(
+            stepper
+                .client_app_1
+                .world()
+                .get::<ComponentSyncModeFull>(predicted_1)
+                .unwrap(),
+            &ComponentSyncModeFull(15.0)
+        );
+        assert!(stepper
+            .client_app_2
+            .world()
+            .get::<HasAuthority>(confirmed_2)
+            .is_some());
+    }
+}
@@ -5,6 +5,7 @@ use bevy::ecs::world::DeferredWorld;
 use bevy::prelude::{Component, Entity, Reflect, ReflectComponent};
 use std::fmt::Debug;
 
+pub(crate) mod authority;
 pub mod correction;
 pub mod despawn;
 pub mod diagnostics;
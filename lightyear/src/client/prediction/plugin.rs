@@ -1,4 +1,7 @@
 use crate::client::components::{ComponentSyncMode, Confirmed, SyncComponent};
+use crate::client::prediction::authority::{
+    handover_predicted_state, release_predicted_entity, update_authority_from_history,
+};
 use crate::client::prediction::correction::{
     get_visually_corrected_state, restore_corrected_state,
 };
@@ -80,9 +83,7 @@ pub struct PredictionConfig {
 }
 
 impl Default for PredictionConfig {
-    /// By default we don't apply any input delay, because input_delay is only compatible with
-    /// the leafwing inputs
-    /// (Adding input delay would mess up the client timeline)
+    /// By default we don't apply any input delay: all the latency is covered by prediction
     fn default() -> Self {
         Self::no_input_delay()
     }
@@ -207,6 +208,12 @@ pub enum PredictionSet {
     /// Update the client's predicted history; runs after each physics step in the FixedUpdate Schedule
     UpdateHistory,
 
+    /// Set for systems with side effects (spawning effects, playing sounds, sending messages, etc.)
+    /// that should only run once per tick, and never during rollback resimulation.
+    ///
+    /// Can be used in the `FixedPreUpdate`, `FixedUpdate` and `FixedPostUpdate` schedules.
+    SkipDuringRollback,
+
     // PostUpdate Sets
     /// Visually interpolate the predicted components to the corrected state
     VisualCorrection,
@@ -289,6 +296,12 @@ pub fn add_prediction_systems<C: SyncComponent>(app: &mut App, prediction_mode:
             app.add_observer(apply_component_removal_predicted::<C>);
             app.add_observer(handle_tick_event_prediction_history::<C>);
             app.add_observer(add_prediction_history::<C>);
+            app.add_systems(
+                PreUpdate,
+                handover_predicted_state::<C>
+                    .before(release_predicted_entity)
+                    .in_set(PredictionSet::SpawnPrediction),
+            );
             app.add_systems(
                 PreUpdate,
                 // restore to the corrected state (as the visual state might be interpolating
@@ -319,6 +332,12 @@ pub fn add_prediction_systems<C: SyncComponent>(app: &mut App, prediction_mode:
         }
         ComponentSyncMode::Simple => {
             app.add_observer(apply_component_removal_confirmed::<C>);
+            app.add_systems(
+                PreUpdate,
+                handover_predicted_state::<C>
+                    .before(release_predicted_entity)
+                    .in_set(PredictionSet::SpawnPrediction),
+            );
             app.add_systems(
                 PreUpdate,
                 (
@@ -406,6 +425,8 @@ impl Plugin for PredictionPlugin {
                 spawn_predicted_entity
                     .after(PreSpawnedPlayerObjectSet::Spawn)
                     .in_set(PredictionSet::SpawnPrediction),
+                // when the client gains authority over a predicted entity, it simulates the confirmed entity directly
+                release_predicted_entity.in_set(PredictionSet::SpawnPrediction),
                 run_rollback.in_set(PredictionSet::Rollback),
                 #[cfg(feature = "metrics")]
                 super::rollback::no_rollback
@@ -438,6 +459,24 @@ impl Plugin for PredictionPlugin {
             FixedPostUpdate,
             PredictionSet::All.run_if(should_prediction_run.clone()),
         );
+        // systems in this set only run on the first simulation of each tick, not during rollback
+        app.configure_sets(
+            FixedPreUpdate,
+            PredictionSet::SkipDuringRollback.run_if(not(is_in_rollback)),
+        )
+        .configure_sets(
+            FixedUpdate,
+            PredictionSet::SkipDuringRollback.run_if(not(is_in_rollback)),
+        )
+        .configure_sets(
+            FixedPostUpdate,
+            PredictionSet::SkipDuringRollback.run_if(not(is_in_rollback)),
+        );
+        // the authority of predicted entities is tick-versioned, so that rollbacks use the authority of each replayed tick
+        app.add_systems(
+            FixedPreUpdate,
+            update_authority_from_history.run_if(should_prediction_run.clone()),
+        );
         app.add_systems(
             FixedPostUpdate,
             (
@@ -310,6 +310,10 @@ impl PreSpawnedPlayerObjectPlugin {
 #[derive(Serialize, Deserialize, Default, Debug, Copy, Clone, PartialEq, Eq, Reflect)]
 /// Added to indicate the client has prespawned the predicted version of this entity.
 ///
+/// When the server replicates an entity with the same hash, the client entity becomes the `Predicted` entity
+/// of the replicated `Confirmed` entity instead of spawning a duplicate. If no matching server entity is received
+/// before the interpolation tick catches up with the spawn tick, the client entity is despawned.
+///
 /// ```rust,ignore
 /// // Default hashing implementation: (tick + components)
 /// PreSpawnedPlayerObject::default();
@@ -641,6 +645,102 @@ mod tests {
             .is_err());
     }
 
+    /// Client and server prespawn an entity with different states.
+    /// The client entity becomes the Predicted entity, and its state is corrected to the server's confirmed state
+    #[test]
+    fn test_prespawn_confirmed_state() {
+        let mut stepper = BevyStepper::default();
+
+        let client_prespawn = stepper
+            .client_app
+            .world_mut()
+            .spawn((PreSpawnedPlayerObject::new(1), ComponentSyncModeFull(1.0)))
+            .id();
+        stepper.server_app.world_mut().spawn((
+            PreSpawnedPlayerObject::new(1),
+            ComponentSyncModeFull(2.0),
+            Replicate {
+                sync: SyncTarget {
+                    prediction: NetworkTarget::All,
+                    ..default()
+                },
+                ..default()
+            },
+        ));
+        stepper.frame_step();
+        stepper.frame_step();
+        stepper.frame_step();
+
+        // no duplicate predicted entity was spawned
+        let confirmed = stepper
+            .client_app
+            .world()
+            .get::<Predicted>(client_prespawn)
+            .unwrap()
+            .confirmed_entity
+            .unwrap();
+        assert_eq!(
+            stepper
+                .client_app
+                .world_mut()
+                .query::<&Predicted>()
+                .iter(stepper.client_app.world())
+                .count(),
+            1
+        );
+        assert_eq!(
+            stepper
+                .client_app
+                .world()
+                .get::<ComponentSyncModeFull>(confirmed)
+                .unwrap(),
+            &ComponentSyncModeFull(2.0)
+        );
+        assert_eq!(
+            stepper
+                .client_app
+                .world()
+                .get::<ComponentSyncModeFull>(client_prespawn)
+                .unwrap(),
+            &ComponentSyncModeFull(2.0)
+        );
+    }
+
+    /// Client prespawns an entity, but the server never spawns a matching entity.
+    /// The client entity gets despawned once the matching window has passed
+    #[test]
+    fn test_prespawn_no_match() {
+        let mut stepper = BevyStepper::default();
+
+        let client_prespawn = stepper
+            .client_app
+            .world_mut()
+            .spawn((PreSpawnedPlayerObject::new(1), ComponentSyncModeFull(1.0)))
+            .id();
+        stepper.frame_step();
+        assert!(stepper
+            .client_app
+            .world()
+            .get_entity(client_prespawn)
+            .is_ok());
+
+        // if enough frames pass without match, the entity gets cleaned
+        for _ in 0..6 {
+            stepper.frame_step();
+        }
+        assert!(stepper
+            .client_app
+            .world()
+            .get_entity(client_prespawn)
+            .is_err());
+        assert!(stepper
+            .client_app
+            .world()
+            .resource::<PredictionManager>()
+            .prespawn_hash_to_entities
+            .is_empty());
+    }
+
     /// Client and server run the same system to prespawn an entity
     /// The pre-spawn somehow fails on the client (no matching hash)
     /// The server entity should just get normally Predicted on the client
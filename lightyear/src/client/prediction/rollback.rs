@@ -1241,6 +1241,125 @@ mod integration_tests {
         println!("{:?}", stepper.client_app.world().resource::<TimeTracker>());
     }
 
+    /// Check that a rollback that crosses an authority change uses, for each replayed tick,
+    /// the authority that was in effect at that tick
+    #[test]
+    fn test_rollback_authority_history() {
+        use crate::prelude::server::AuthorityPeer;
+        use crate::prelude::{AuthorityHistory, ClientId, Tick, TickManager};
+
+        #[derive(Resource, Default)]
+        struct AuthorityTracker(Vec<(Tick, bool, AuthorityPeer)>);
+
+        fn track_authority(
+            tick_manager: Res<TickManager>,
+            rollback: Res<Rollback>,
+            query: Query<&AuthorityPeer, With<Predicted>>,
+            mut tracker: ResMut<AuthorityTracker>,
+        ) {
+            for authority in query.iter() {
+                tracker.0.push((
+                    tick_manager.tick_or_rollback_tick(rollback.as_ref()),
+                    rollback.is_rollback(),
+                    *authority,
+                ));
+            }
+        }
+
+        let (mut stepper, confirmed, predicted) = setup(false);
+        stepper
+            .client_app
+            .world_mut()
+            .entity_mut(confirmed)
+            .insert(ComponentSyncModeFull(0.0));
+        stepper.frame_step();
+
+        // the authority was transferred to the client at `tick - 1`
+        let tick = stepper.client_tick();
+        let client = AuthorityPeer::Client(ClientId::Netcode(0));
+        let mut history = AuthorityHistory::new(AuthorityPeer::Server);
+        history.add(tick - 1, client);
+        stepper
+            .client_app
+            .world_mut()
+            .entity_mut(predicted)
+            .insert(history);
+
+        // trigger a rollback that starts before the authority change
+        stepper
+            .client_app
+            .world_mut()
+            .get_mut::<ComponentSyncModeFull>(confirmed)
+            .unwrap()
+            .0 = 1.0;
+        received_confirmed_update(&mut stepper, confirmed, tick - 3);
+        stepper.client_app.init_resource::<AuthorityTracker>();
+        stepper.client_app.add_systems(FixedUpdate, track_authority);
+        stepper.frame_step();
+
+        assert_eq!(
+            stepper.client_app.world().resource::<AuthorityTracker>().0,
+            vec![
+                (tick - 2, true, AuthorityPeer::Server),
+                (tick - 1, true, client),
+                (tick, true, client),
+                (tick + 1, false, client),
+            ]
+        );
+    }
+
+    /// Check that systems in the `SkipDuringRollback` set run once per tick, even when
+    /// the frame contains several rollback ticks
+    #[test]
+    fn test_skip_during_rollback_set() {
+        use crate::prelude::{Tick, TickManager};
+
+        #[derive(Resource, Default)]
+        struct SideEffects {
+            runs: Vec<Tick>,
+        }
+
+        fn side_effect(tick_manager: Res<TickManager>, mut side_effects: ResMut<SideEffects>) {
+            side_effects.runs.push(tick_manager.tick());
+        }
+
+        let (mut stepper, confirmed, predicted) = setup(false);
+        stepper
+            .client_app
+            .world_mut()
+            .entity_mut(confirmed)
+            .insert(ComponentSyncModeFull(0.0));
+        stepper.frame_step();
+        assert!(stepper
+            .client_app
+            .world()
+            .get::<ComponentSyncModeFull>(predicted)
+            .is_some());
+
+        stepper.client_app.init_resource::<SideEffects>();
+        stepper.client_app.add_systems(
+            FixedUpdate,
+            side_effect.in_set(PredictionSet::SkipDuringRollback),
+        );
+
+        // trigger 3 rollback ticks
+        let tick = stepper.client_tick();
+        stepper
+            .client_app
+            .world_mut()
+            .get_mut::<ComponentSyncModeFull>(confirmed)
+            .unwrap()
+            .0 = 1.0;
+        received_confirmed_update(&mut stepper, confirmed, tick - 3);
+        stepper.frame_step();
+
+        // the side-effect system only ran for the new tick
+        assert_eq!(
+            stepper.client_app.world().resource::<SideEffects>().runs,
+            vec![stepper.client_tick()]
+        );
+    }
+
     /// Test that:
     /// - we remove a component from the predicted entity
     /// - rolling back before the remove should re-add it
@@ -26,9 +26,9 @@ pub(crate) fn spawn_predicted_entity(
 
     // only handle predicted that have ShouldBePredicted
     // (if the entity was handled by prespawn or prepredicted before, ShouldBePredicted gets removed)
-    mut confirmed_entities: Query<(Entity, Option<&mut Confirmed>), Added<ShouldBePredicted>>,
+    confirmed_entities: Query<(Entity, Option<&Confirmed>), Added<ShouldBePredicted>>,
 ) {
-    for (confirmed_entity, confirmed) in confirmed_entities.iter_mut() {
+    for (confirmed_entity, confirmed) in confirmed_entities.iter() {
         // skip if the entity already has a predicted entity
         if confirmed.as_ref().is_some_and(|c| c.predicted.is_some()) {
             continue;
@@ -53,8 +53,14 @@ pub(crate) fn spawn_predicted_entity(
         // safety: we know the entity exists
         let mut confirmed_entity_mut = commands.entity(confirmed_entity);
         confirmed_entity_mut.remove::<ShouldBePredicted>();
-        if let Some(mut confirmed) = confirmed {
-            confirmed.predicted = Some(predicted_entity);
+        if let Some(confirmed) = confirmed {
+            // re-insert Confirmed so that the components get synced to the new predicted entity
+            // (for example if the client lost authority over an entity that it was predicting)
+            confirmed_entity_mut.insert(Confirmed {
+                predicted: Some(predicted_entity),
+                interpolated: confirmed.interpolated,
+                tick: confirmed.tick,
+            });
         } else {
             // TODO: this is the same as the current tick no? or maybe not because we could have received updates before the spawn
             //  and they are applied simultaneously
@@ -7,15 +7,69 @@ use crate::shared::replication::plugin::receive::ReplicationReceivePlugin;
 use crate::shared::replication::plugin::send::ReplicationSendPlugin;
 use crate::shared::sets::{ClientMarker, InternalReplicationSet};
 
+/// SystemSet that runs in `PreUpdate` after all the replication updates received this frame
+/// (entity spawns/despawns, component inserts/updates/removals, resources, authority changes)
+/// have been applied to the client's `World`.
+///
+/// Systems in this set are guaranteed to observe a consistent snapshot of the replicated state for the frame,
+/// which makes it a good place for reactive logic (for example rebuilding a cache derived from replicated components).
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone, Copy)]
+pub struct ReplicationReceived;
+
+/// Measures how long it takes for a replicated change on the server to be applied on the client
+///
+/// Every time a replication message is applied, the convergence time is computed as the difference between
+/// the server tick at which the change was sent and our estimate of the current server time
+/// (the latest server tick we received, plus the time elapsed since then, plus half of the RTT).
+/// It includes the network latency and any time spent buffering the message on the client.
+///
+/// Accessible with [`ConnectionManager::replication_convergence`].
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ReplicationConvergence {
+    /// Number of replication messages that were measured
+    pub samples: u32,
+    /// Convergence time of the latest applied replication message
+    pub last: Duration,
+    /// Average convergence time over all the applied replication messages
+    pub average: Duration,
+    /// Maximum convergence time over all the applied replication messages
+    pub max: Duration,
+}
+
+impl ReplicationConvergence {
+    pub(crate) fn record(&mut self, convergence: Duration) {
+        self.samples += 1;
+        self.last = convergence;
+        self.max = self.max.max(convergence);
+        // incremental mean
+        self.average = if convergence > self.average {
+            self.average + (convergence - self.average) / self.samples
+        } else {
+            self.average - (self.average - convergence) / self.samples
+        };
+    }
+
+    /// Average convergence time expressed in ticks
+    pub fn average_ticks(&self, tick_duration: Duration) -> f32 {
+        self.average.as_secs_f32() / tick_duration.as_secs_f32()
+    }
+}
+
 pub(crate) mod receive {
     use super::*;
+    use crate::channel::builder::AuthorityChannel;
+    use crate::client::components::Confirmed;
     use crate::client::message::ReceiveMessage;
     use crate::prelude::{
         client::{is_connected, is_synced},
         is_host_server, ClientConnectionManager, Replicated, ReplicationGroup, ShouldBePredicted,
     };
-    use crate::shared::replication::authority::{AuthorityChange, HasAuthority};
+    use crate::shared::replication::authority::{
+        AuthorityChange, AuthorityHistory, AuthorityPeer, AuthorityTransferAck,
+        AuthorityTransferEvent, HasAuthority,
+    };
     use crate::shared::replication::components::{ReplicationGroupId, ShouldBeInterpolated};
+    use crate::shared::replication::initial::{InitialReplication, InterpolationSnapshots};
     use crate::shared::sets::InternalMainSet;
     use bevy::ecs::entity::Entities;
 
@@ -42,9 +96,22 @@ pub(crate) mod receive {
                     .run_if(is_connected.and(is_synced).and(not(is_host_server))),
             );
 
+            app.configure_sets(
+                PreUpdate,
+                ReplicationReceived
+                    .after(InternalMainSet::<ClientMarker>::ReceiveEvents)
+                    .after(InternalReplicationSet::<ClientMarker>::ReceiveResourceUpdates),
+            );
+
             app.add_systems(
                 PreUpdate,
-                handle_authority_change.after(InternalMainSet::<ClientMarker>::ReceiveEvents),
+                (
+                    handle_authority_change,
+                    handle_initial_replication,
+                    handle_interpolation_snapshots,
+                )
+                    .after(InternalMainSet::<ClientMarker>::ReceiveEvents)
+                    .before(ReplicationReceived),
             );
         }
     }
@@ -73,6 +140,7 @@ pub(crate) mod receive {
             if entities.get(entity).is_some() {
                 if message.gain_authority {
                     commands.queue(move |world: &mut World| {
+                        record_authority_history(world, &message);
                         let bevy_tick = world.change_tick();
                         // check that the entity has ReplicationGroup bundle
                         assert!(world.get::<ReplicationGroup>(entity).is_some(), "The Replicate bundle must be added to the entity BEFORE transferring authority to the client");
@@ -107,7 +175,19 @@ pub(crate) mod receive {
                             .or_default()
                             .send_tick = Some(bevy_tick);
 
-
+                        // notify the server that we took control of the entity
+                        let _ = world
+                            .resource_mut::<ClientConnectionManager>()
+                            .send_message::<AuthorityChannel, _>(&AuthorityTransferAck { entity })
+                            .inspect_err(|e| {
+                                error!("could not acknowledge the authority transfer: {:?}", e);
+                            });
+                        world.send_event(AuthorityTransferEvent {
+                            entity,
+                            from: message.from,
+                            to: message.to,
+                            acknowledged: true,
+                        });
                     });
                 } else {
                     // TODO: how do we know if the remote is still actively replicating to us?
@@ -115,6 +195,7 @@ pub(crate) mod receive {
                     //  Not sure how to handle this. We could include in the message if the authority is None,
                     //  but that's not very elegant
                     commands.queue(move |world: &mut World| {
+                        record_authority_history(world, &message);
                         world
                             .entity_mut(entity)
                             .remove::<HasAuthority>()
@@ -125,11 +206,183 @@ pub(crate) mod receive {
                         if message.add_interpolation {
                             world.entity_mut(entity).insert(ShouldBeInterpolated);
                         }
+                        // if the new authority is another client, we don't know when it takes control of the entity
+                        world.send_event(AuthorityTransferEvent {
+                            entity,
+                            from: message.from,
+                            to: message.to,
+                            acknowledged: !matches!(message.to, AuthorityPeer::Client(_)),
+                        });
                     })
                 }
             }
         }
     }
+
+    /// Record the authority change in the [`AuthorityHistory`] of the entity, and of its
+    /// predicted entity so that rollbacks use the authority that was in effect at each tick
+    fn record_authority_history(world: &mut World, message: &AuthorityChange) {
+        let predicted = world
+            .get::<Confirmed>(message.entity)
+            .and_then(|confirmed| confirmed.predicted);
+        for entity in std::iter::once(message.entity).chain(predicted) {
+            if let Ok(mut entity_mut) = world.get_entity_mut(entity) {
+                AuthorityHistory::record(&mut entity_mut, message.from, message.to, message.tick);
+            }
+        }
+    }
+
+    /// Store the tick of the initial replication snapshot sent by the server
+    fn handle_initial_replication(
+        mut connection: ResMut<ConnectionManager>,
+        mut messages: ResMut<Events<ReceiveMessage<InitialReplication>>>,
+    ) {
+        for message_event in messages.drain() {
+            let tick = message_event.message.tick;
+            debug!(
+                ?tick,
+                "Received the tick of the initial replication snapshot"
+            );
+            connection.initial_replication_tick = Some(tick);
+        }
+    }
+
+    /// Store the previous states of the interpolated entities sent by the server,
+    /// until the interpolation history of the entities is created
+    fn handle_interpolation_snapshots(
+        mut connection: ResMut<ConnectionManager>,
+        mut messages: ResMut<Events<ReceiveMessage<InterpolationSnapshots>>>,
+    ) {
+        for message_event in messages.drain() {
+            let InterpolationSnapshots { entity, snapshots } = message_event.message;
+            trace!(
+                ?entity,
+                num_snapshots = snapshots.len(),
+                "Received interpolation snapshots"
+            );
+            connection.interpolation_snapshots.insert(entity, snapshots);
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::prelude::server;
+        use crate::tests::protocol::ComponentSyncModeFull;
+        use crate::tests::stepper::BevyStepper;
+
+        /// Check that the measured replication convergence time matches the latency of the link
+        #[test]
+        fn test_replication_convergence() {
+            use crate::prelude::client::{ClientConfig, NetConfig};
+            use crate::prelude::{LinkConditionerConfig, SharedConfig, TickConfig};
+
+            let tick_duration = Duration::from_millis(10);
+            let shared_config = SharedConfig {
+                tick: TickConfig::new(tick_duration),
+                ..default()
+            };
+            let mut client_config = ClientConfig::default();
+            if let NetConfig::Netcode { io, .. } = &mut client_config.net {
+                io.conditioner = Some(LinkConditionerConfig {
+                    incoming_latency: Duration::from_millis(50),
+                    incoming_jitter: Duration::default(),
+                    incoming_loss: 0.0,
+                });
+            }
+            let mut stepper = BevyStepper::new(shared_config, client_config, tick_duration);
+            stepper.build();
+            stepper.init();
+
+            let server_entity = stepper
+                .server_app
+                .world_mut()
+                .spawn((ComponentSyncModeFull(0.0), server::Replicate::default()))
+                .id();
+            for _ in 0..50 {
+                stepper
+                    .server_app
+                    .world_mut()
+                    .get_mut::<ComponentSyncModeFull>(server_entity)
+                    .unwrap()
+                    .0 += 1.0;
+                stepper.frame_step();
+            }
+
+            let convergence = stepper
+                .client_app
+                .world()
+                .resource::<ConnectionManager>()
+                .replication_convergence()
+                .clone();
+            assert!(convergence.samples > 0);
+            // the change is applied 50ms after it was made on the server, up to one frame
+            let average_ticks = convergence.average_ticks(tick_duration);
+            assert!(
+                (average_ticks - 5.0).abs() <= 1.0,
+                "average convergence: {:?}",
+                convergence.average
+            );
+        }
+
+        #[derive(Resource, Default)]
+        struct ObservedValue(Option<f32>);
+
+        fn observe_value(
+            query: Query<&ComponentSyncModeFull, With<Replicated>>,
+            mut observed: ResMut<ObservedValue>,
+        ) {
+            observed.0 = query.get_single().ok().map(|c| c.0);
+        }
+
+        /// Check that a system in the [`ReplicationReceived`] set observes the replication updates
+        /// on the same frame that they are applied
+        #[test]
+        fn test_replication_received_set() {
+            let mut stepper = BevyStepper::default();
+            stepper.client_app.init_resource::<ObservedValue>();
+            stepper
+                .client_app
+                .add_systems(PreUpdate, observe_value.in_set(ReplicationReceived));
+
+            let server_entity = stepper
+                .server_app
+                .world_mut()
+                .spawn((ComponentSyncModeFull(1.0), server::Replicate::default()))
+                .id();
+            for _ in 0..5 {
+                stepper.frame_step();
+            }
+            assert_eq!(
+                stepper.client_app.world().resource::<ObservedValue>().0,
+                Some(1.0)
+            );
+
+            stepper
+                .server_app
+                .world_mut()
+                .entity_mut(server_entity)
+                .insert(ComponentSyncModeFull(2.0));
+            // on every frame, the value observed in the set must match the replicated state of the world
+            for _ in 0..5 {
+                stepper.frame_step();
+                let value = stepper
+                    .client_app
+                    .world_mut()
+                    .query_filtered::<&ComponentSyncModeFull, With<Replicated>>()
+                    .single(stepper.client_app.world())
+                    .0;
+                assert_eq!(
+                    stepper.client_app.world().resource::<ObservedValue>().0,
+                    Some(value)
+                );
+            }
+            assert_eq!(
+                stepper.client_app.world().resource::<ObservedValue>().0,
+                Some(2.0)
+            );
+        }
+    }
 }
 
 pub(crate) mod send {
@@ -148,7 +401,7 @@ pub(crate) mod send {
     use crate::protocol::component::ComponentKind;
 
     use crate::shared::replication::components::{
-        InitialReplicated, Replicating, ReplicationGroupId,
+        DespawnReason, InitialReplicated, Replicating, ReplicationGroupId,
     };
 
     use crate::shared::replication::archetypes::{
@@ -166,11 +419,7 @@ pub(crate) mod send {
 
     impl Plugin for ClientReplicationSendPlugin {
         fn build(&self, app: &mut App) {
-            let send_interval = app
-                .world()
-                .resource::<ClientConfig>()
-                .replication
-                .send_interval;
+            let replication_config = app.world().resource::<ClientConfig>().replication;
 
             app
                 // REFLECTION
@@ -178,7 +427,8 @@ pub(crate) mod send {
                 // PLUGIN
                 .add_plugins(ReplicationSendPlugin::<ConnectionManager>::new(
                     self.tick_interval,
-                    send_interval,
+                    replication_config.send_interval,
+                    replication_config.replication_interval,
                 ))
                 // SETS
                 .configure_sets(
@@ -489,7 +739,7 @@ pub(crate) mod send {
         //  in which case we don't want to replicate the despawn.
         //  i.e. if a user wants to despawn an entity without replicating the despawn
         //  I guess we can provide a command that first removes Replicating, and then despawns the entity.
-        query: Query<&ReplicationGroup, With<Replicating>>,
+        query: Query<(&ReplicationGroup, Option<&DespawnReason>), With<Replicating>>,
         mut sender: ResMut<ConnectionManager>,
     ) {
         let mut entity = trigger.entity();
@@ -498,11 +748,13 @@ pub(crate) mod send {
             .replication_receiver
             .remote_entity_map
             .to_remote(entity);
-        if let Ok(group) = query.get(entity) {
+        if let Ok((group, reason)) = query.get(entity) {
             trace!(?entity, "send entity despawn");
-            sender
-                .replication_sender
-                .prepare_entity_despawn(entity, group.group_id(Some(entity)));
+            sender.replication_sender.prepare_entity_despawn(
+                entity,
+                group.group_id(Some(entity)),
+                reason.copied().unwrap_or_default(),
+            );
         };
     }
 
@@ -601,9 +853,17 @@ pub(crate) mod send {
                     .send_tick;
 
                 // send the update for all changes newer than the last send bevy tick for the group
-                if send_tick.map_or(true, |c| {
-                    component_ticks.is_changed(c, system_ticks.this_run())
-                }) {
+                let mut changed = send_tick
+                    .is_none_or(|c| component_ticks.is_changed(c, system_ticks.this_run()));
+                if let Some(interval) = component_registry.max_send_rate(component_kind) {
+                    changed = sender.replication_sender.check_send_rate(
+                        entity,
+                        component_kind,
+                        interval,
+                        changed,
+                    );
+                }
+                if changed {
                     trace!(
                         change_tick = ?component_ticks.changed,
                         ?send_tick,
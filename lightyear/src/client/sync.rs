@@ -1,6 +1,6 @@
 /*! Handles syncing the time between the client and the server
 */
-use bevy::prelude::{Reflect, SystemSet};
+use bevy::prelude::{Reflect, Resource, SystemSet};
 use bevy::utils::Duration;
 use chrono::Duration as ChronoDuration;
 use tracing::{debug, trace};
@@ -45,6 +45,12 @@ pub struct SyncConfig {
     // TODO: instead of constant speedup_factor, the speedup should be linear w.r.t the offset
     /// By how much should we speed up the simulation to make ticks stay in sync with server?
     pub speedup_factor: f32,
+    /// Maximum number of ticks that the client tick can be adjusted by in a single frame when the
+    /// prediction time is too far from the objective (i.e. the error is above `max_error_margin`).
+    ///
+    /// If `None`, the client tick snaps directly to the objective tick. Setting a limit spreads the
+    /// resync over multiple frames to avoid a visible snap.
+    pub max_tick_adjustment_per_frame: Option<u16>,
 
     // Integration
     pub server_time_estimate_smoothing: f32,
@@ -59,6 +65,7 @@ impl Default for SyncConfig {
             error_margin: 0.5,
             max_error_margin: 5.0,
             speedup_factor: 1.05,
+            max_tick_adjustment_per_frame: None,
             // server_time_estimate_smoothing: 0.0,
             server_time_estimate_smoothing: 0.2,
         }
@@ -72,6 +79,19 @@ impl SyncConfig {
     }
 }
 
+/// Read-only diagnostics about the synchronization of the client with the server.
+///
+/// Updated every frame by the client.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Reflect)]
+pub struct SyncDiagnostics {
+    /// Number of ticks that the client tick is ahead of the latest tick received from the server
+    pub tick_offset: i16,
+    /// Estimated round-trip time between the client and the server
+    pub rtt: Duration,
+    /// Estimated jitter of the round-trip time
+    pub jitter: Duration,
+}
+
 #[derive(Default)]
 pub struct SentPacketStore {
     buffer: ReadyBuffer<WrappedTime, PacketId>,
@@ -85,6 +105,9 @@ impl SentPacketStore {
     }
 }
 
+/// Weight of the latest sample in the estimate of the server send jitter
+const SERVER_SEND_JITTER_SMOOTHING: f32 = 1.0 / 16.0;
+
 /// In charge of syncing the client's tick/time with the server's tick/time
 /// right after the connection is established
 #[derive(Debug)]
@@ -120,6 +143,9 @@ pub struct SyncManager {
     /// The Tick associated with the 'server_tick_generation' (it might not be the same as latest_received_server_tick
     /// because we update the generation only from pong messages)
     pub(crate) server_pong_tick: Tick,
+    /// Estimate of the jitter of the time between the arrivals of two consecutive server packets,
+    /// compared to the time between the ticks at which the server sent them
+    pub(crate) server_send_jitter: Duration,
 }
 
 // TODO: split into PredictionTime Manager, InterpolationTime Manager
@@ -140,7 +166,28 @@ impl SyncManager {
             new_latest_received_server_tick: false,
             server_pong_generation: 0,
             server_pong_tick: Tick(0),
+            server_send_jitter: Duration::default(),
+        }
+    }
+
+    /// Update the estimate of the server send jitter when we receive a packet for a new server tick.
+    ///
+    /// This must be called before `duration_since_latest_received_server_tick` is reset.
+    pub(crate) fn record_server_packet_arrival(&mut self, tick: Tick, tick_duration: Duration) {
+        let Some(latest_tick) = self.latest_received_server_tick else {
+            return;
+        };
+        if tick <= latest_tick {
+            return;
         }
+        let expected = tick_duration * (tick - latest_tick) as u32;
+        let elapsed = self.duration_since_latest_received_server_tick;
+        let deviation = elapsed.abs_diff(expected);
+        // exponential moving average of the deviation, as in RFC 3550
+        self.server_send_jitter = self
+            .server_send_jitter
+            .mul_f32(1.0 - SERVER_SEND_JITTER_SMOOTHING)
+            + deviation.mul_f32(SERVER_SEND_JITTER_SMOOTHING);
     }
 
     /// We want to run this update at PostUpdate, after both ticks/time have been updated
@@ -349,9 +396,10 @@ impl SyncManager {
         // let objective_time = self.server_time_estimate();
         // how much we want interpolation time to be behind the latest received server tick?
         // TODO: use a specified config margin + add std of time_between_server_updates?
-        let objective_delta =
-            chrono::Duration::from_std(interpolation_delay.to_duration(server_send_interval))
-                .unwrap();
+        let objective_delta = chrono::Duration::from_std(
+            interpolation_delay.to_duration(server_send_interval, self.server_send_jitter),
+        )
+        .unwrap();
         // info!("objective_delta: {:?}", objective_delta);
         self.server_time_estimate() - objective_delta
     }
@@ -383,6 +431,20 @@ impl SyncManager {
             .tick_overstep(tick_manager.config.tick_duration)
     }
 
+    /// Interpolation time of an entity that is interpolated with its own `delay` instead of
+    /// the global interpolation delay
+    pub(crate) fn entity_interpolation_time(
+        &self,
+        delay: Duration,
+        interpolation_delay: &InterpolationConfig,
+        server_send_interval: Duration,
+    ) -> WrappedTime {
+        let global_delay =
+            interpolation_delay.to_duration(server_send_interval, self.server_send_jitter);
+        self.interpolation_time + ChronoDuration::from_std(global_delay).unwrap()
+            - ChronoDuration::from_std(delay).unwrap()
+    }
+
     // TODO: only run when there's a change? (new server tick received or new ping received)
     // TODO: change name to make it clear that we might modify speed
     pub(crate) fn update_interpolation_time(
@@ -488,7 +550,13 @@ impl SyncManager {
                 "Error too big, snapping prediction time/tick to objective",
             );
 
-            return self.finalize(time_manager, tick_manager, ping_manager, prediction_config);
+            return self.resync(
+                time_manager,
+                tick_manager,
+                ping_manager,
+                prediction_config,
+                self.config.max_tick_adjustment_per_frame,
+            );
         }
 
         time_manager.sync_relative_speed = if error > error_margin_time {
@@ -536,6 +604,24 @@ impl SyncManager {
         tick_manager: &mut TickManager,
         ping_manager: &PingManager,
         prediction_config: &PredictionConfig,
+    ) -> Option<TickEvent> {
+        self.resync(
+            time_manager,
+            tick_manager,
+            ping_manager,
+            prediction_config,
+            None,
+        )
+    }
+
+    /// Set the client tick to the objective tick, moving it by at most `max_tick_adjustment` ticks
+    fn resync(
+        &mut self,
+        time_manager: &mut TimeManager,
+        tick_manager: &mut TickManager,
+        ping_manager: &PingManager,
+        prediction_config: &PredictionConfig,
+        max_tick_adjustment: Option<u16>,
     ) -> Option<TickEvent> {
         let tick_duration = tick_manager.config.tick_duration;
         let rtt = ping_manager.rtt();
@@ -563,7 +649,11 @@ impl SyncManager {
         let client_ideal_tick =
             Tick((client_ideal_time.elapsed.as_nanos() / tick_duration.as_nanos()) as u16);
 
-        let delta_tick = client_ideal_tick - tick_manager.tick();
+        let mut delta_tick = client_ideal_tick - tick_manager.tick();
+        if let Some(max) = max_tick_adjustment {
+            let max = max.min(i16::MAX as u16) as i16;
+            delta_tick = delta_tick.clamp(-max, max);
+        }
         // Update client ticks
         if rtt != Duration::default() {
             debug!(
@@ -581,7 +671,22 @@ impl SyncManager {
                 "Finished syncing!"
             );
         }
-        Some(tick_manager.set_tick_to(client_ideal_tick))
+        Some(tick_manager.set_tick_to(tick_manager.tick() + delta_tick))
+    }
+
+    /// Diagnostics about the current state of the sync with the server
+    pub(crate) fn diagnostics(
+        &self,
+        tick_manager: &TickManager,
+        ping_manager: &PingManager,
+    ) -> SyncDiagnostics {
+        SyncDiagnostics {
+            tick_offset: self
+                .latest_received_server_tick
+                .map_or(0, |server_tick| tick_manager.tick() - server_tick),
+            rtt: ping_manager.rtt(),
+            jitter: ping_manager.jitter(),
+        }
     }
 }
 
@@ -591,6 +696,7 @@ mod tests {
     use bevy::utils::Duration;
 
     use crate::client::input::native::InputManager;
+    use crate::prelude::client::{ClientConfig, ConnectionManager};
     use crate::prelude::server::Replicate;
     use crate::prelude::*;
     use crate::server::events::InputEvent;
@@ -616,6 +722,108 @@ mod tests {
         }
     }
 
+    /// Check that a synthetic RTT added to the ping measurements is reflected in how far ahead
+    /// of the server the client predicts
+    #[test]
+    fn test_sync_synthetic_rtt() {
+        let tick_duration = Duration::from_millis(10);
+        let prediction_offset = |synthetic_rtt: Duration| {
+            let shared_config = SharedConfig {
+                tick: TickConfig::new(tick_duration),
+                ..default()
+            };
+            let client_config = ClientConfig {
+                ping: PingConfig {
+                    synthetic_rtt,
+                    ..default()
+                },
+                ..default()
+            };
+            let mut stepper = BevyStepper::new(shared_config, client_config, tick_duration);
+            stepper.build();
+            stepper.init();
+            for _ in 0..50 {
+                stepper.frame_step();
+            }
+            let rtt = stepper
+                .client_app
+                .world()
+                .resource::<ConnectionManager>()
+                .ping_manager
+                .rtt();
+            (stepper.client_tick() - stepper.server_tick(), rtt)
+        };
+        let (offset, rtt) = prediction_offset(Duration::default());
+        let (synthetic_offset, synthetic_rtt) = prediction_offset(Duration::from_millis(100));
+        assert!((synthetic_rtt.as_secs_f64() - rtt.as_secs_f64() - 0.1).abs() < 0.001);
+        // 100ms of RTT corresponds to 10 ticks
+        assert!(
+            (synthetic_offset - offset - 10).abs() <= 1,
+            "offset without synthetic rtt: {offset}, offset with synthetic rtt: {synthetic_offset}"
+        );
+    }
+
+    /// Check that when the client tick is too far from the objective, the client converges back
+    /// while adjusting its tick by at most `max_tick_adjustment_per_frame` per frame
+    #[test]
+    fn test_sync_max_tick_adjustment() {
+        let tick_duration = Duration::from_millis(10);
+        let shared_config = SharedConfig {
+            tick: TickConfig::new(tick_duration),
+            ..default()
+        };
+        let client_config = ClientConfig {
+            sync: SyncConfig {
+                max_tick_adjustment_per_frame: Some(2),
+                ..default()
+            },
+            ..default()
+        };
+        let mut stepper = BevyStepper::new(shared_config, client_config, tick_duration);
+        stepper.build();
+        stepper.init();
+        for _ in 0..50 {
+            stepper.frame_step();
+        }
+        let offset = stepper.client_tick() - stepper.server_tick();
+        let diagnostics = *stepper.client_app.world().resource::<SyncDiagnostics>();
+        assert!(diagnostics.tick_offset > 0);
+        assert_eq!(
+            diagnostics.rtt,
+            stepper
+                .client_app
+                .world()
+                .resource::<ConnectionManager>()
+                .ping_manager
+                .rtt()
+        );
+
+        // force the client tick to be way ahead of the server
+        let client_tick = stepper.client_tick();
+        stepper
+            .client_app
+            .world_mut()
+            .resource_mut::<TickManager>()
+            .set_tick_to(client_tick + 30);
+        let mut previous_offset = stepper.client_tick() - stepper.server_tick();
+        for _ in 0..50 {
+            stepper.frame_step();
+            let new_offset = stepper.client_tick() - stepper.server_tick();
+            // the client tick can also move by one tick because of the speedup/slowdown
+            assert!(
+                (new_offset - previous_offset).abs() <= 3,
+                "offset went from {previous_offset} to {new_offset}"
+            );
+            previous_offset = new_offset;
+        }
+        // the client is back within the `max_error_margin`, where it converges by
+        // speeding up or slowing down instead of adjusting its tick
+        assert!(
+            (previous_offset - offset).abs() <= SyncConfig::default().max_error_margin as i16,
+            "offset before: {offset}, offset after: {previous_offset}"
+        );
+    }
+
     /// Check that after a big tick discrepancy between server/client, the client tick gets updated
     /// to match the server tick
     #[test]
@@ -682,4 +890,54 @@ mod tests {
             &ComponentSyncModeFull(1.0)
         );
     }
+
+    /// The server sends a packet every tick, but the packets arrive with a variable lateness
+    /// (for example because of variable server frame times).
+    /// The interpolation delay adapts to the measured jitter so that there is always a server snapshot
+    /// to interpolate towards.
+    #[test]
+    fn test_server_send_jitter_interpolation_buffer() {
+        let tick_duration = Duration::from_millis(16);
+        let config = InterpolationConfig::default()
+            .with_send_interval_ratio(1.0)
+            .with_send_jitter_margin(2.0);
+        let mut manager = SyncManager::new(SyncConfig::default(), PredictionConfig::default());
+        let lateness_ms = [0, 10, 2, 12, 0, 8];
+
+        // arrival time (relative to the send time of tick 0) of each server packet
+        let arrival = |tick: u16| {
+            Duration::from_millis(tick as u64 * 16 + lateness_ms[tick as usize % lateness_ms.len()])
+        };
+        let mut interpolation_is_smooth = true;
+        let mut interpolation_is_smooth_without_margin = true;
+        for tick in 1..200 {
+            manager.latest_received_server_tick = Some(Tick(tick - 1));
+            manager.duration_since_latest_received_server_tick = arrival(tick) - arrival(tick - 1);
+            // just before the packet for `tick` arrives, the latest snapshot is the one for `tick - 1`.
+            // The interpolation time must not be past it.
+            let latest_snapshot_time = tick_duration * (tick as u32 - 1);
+            if tick > 100 {
+                interpolation_is_smooth &= latest_snapshot_time
+                    + config.to_duration(tick_duration, manager.server_send_jitter)
+                    >= arrival(tick);
+                interpolation_is_smooth_without_margin &= latest_snapshot_time
+                    + config.to_duration(tick_duration, Duration::default())
+                    >= arrival(tick);
+            }
+            manager.record_server_packet_arrival(Tick(tick), tick_duration);
+        }
+        assert!(manager.server_send_jitter > Duration::from_millis(5));
+        assert!(manager.server_send_jitter < Duration::from_millis(15));
+        assert!(interpolation_is_smooth);
+        assert!(!interpolation_is_smooth_without_margin);
+
+        // the jitter estimate decreases once the server sends packets at a regular rate again
+        let jitter = manager.server_send_jitter;
+        for tick in 200..300 {
+            manager.latest_received_server_tick = Some(Tick(tick - 1));
+            manager.duration_since_latest_received_server_tick = tick_duration;
+            manager.record_server_packet_arrival(Tick(tick), tick_duration);
+        }
+        assert!(manager.server_send_jitter < jitter / 4);
+    }
 }
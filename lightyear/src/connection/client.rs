@@ -12,6 +12,7 @@ use crate::client::config::NetcodeConfig;
 use crate::client::io::Io;
 use crate::connection::id::ClientId;
 use crate::connection::netcode::ConnectToken;
+use crate::packet::compression::CompressionConfig;
 
 #[cfg(all(feature = "steam", not(target_family = "wasm")))]
 use crate::connection::steam::{client::SteamConfig, steamworks_client::SteamworksClient};
@@ -124,6 +125,15 @@ impl Default for NetConfig {
 }
 
 impl NetConfig {
+    /// The compression set with the deprecated [`SharedIoConfig::compression`]
+    #[allow(deprecated)]
+    pub(crate) fn io_compression(&self) -> CompressionConfig {
+        match self {
+            NetConfig::Netcode { io, .. } => io.compression,
+            _ => CompressionConfig::None,
+        }
+    }
+
     pub fn build_client(self) -> ClientConnection {
         match self {
             NetConfig::Netcode {
@@ -339,6 +349,10 @@ pub enum ConnectionError {
     Netcode(#[from] super::netcode::error::Error),
     #[error("netcode state: {0:?}")]
     NetcodeState(super::netcode::ClientState),
+    #[error("connection denied by the server: {0:?}")]
+    Denied(crate::connection::server::DeniedReason),
+    #[error("the server shut down: {0}")]
+    ServerShutdown(String),
     #[error(transparent)]
     #[cfg(all(feature = "steam", not(target_family = "wasm")))]
     SteamInvalidHandle(#[from] steamworks::networking_sockets::InvalidHandle),
@@ -6,6 +6,7 @@ use tracing::{debug, error, info, trace};
 use crate::client::io::Io;
 use crate::connection::client::{ConnectionError, ConnectionState, IoConfig, NetClient};
 use crate::connection::id;
+use crate::connection::server::DeniedReason;
 use crate::packet::packet_builder::RecvPayload;
 use crate::transport::io::IoState;
 use crate::transport::{PacketReceiver, PacketSender, LOCAL_SOCKET};
@@ -189,6 +190,7 @@ pub struct NetcodeClient<Ctx = ()> {
     replay_protection: ReplayProtection,
     should_disconnect: bool,
     should_disconnect_state: ClientState,
+    denied_reason: Option<DeniedReason>,
     packet_queue: VecDeque<RecvPayload>,
     buffer_pool: Pool<Vec<u8>>,
     cfg: ClientConfig<Ctx>,
@@ -224,6 +226,7 @@ impl<Ctx> NetcodeClient<Ctx> {
             replay_protection: ReplayProtection::new(),
             should_disconnect: false,
             should_disconnect_state: ClientState::Disconnected,
+            denied_reason: None,
             packet_queue: VecDeque::new(),
             buffer_pool: Pool::new(10, || vec![0u8; MAX_PKT_BUF_SIZE]),
             cfg,
@@ -382,6 +385,7 @@ impl<Ctx> NetcodeClient<Ctx> {
                 );
                 self.should_disconnect = true;
                 self.should_disconnect_state = ClientState::ConnectionDenied;
+                self.denied_reason = Some(pkt.reason);
             }
             (Packet::Challenge(pkt), ClientState::SendingConnectionRequest) => {
                 debug!("client received connection challenge packet from server");
@@ -524,6 +528,7 @@ impl<Ctx> NetcodeClient<Ctx> {
     /// This function does not perform any IO, it only readies the client to send/receive packets on the next call to [`update`](NetcodeClient::update). <br>
     pub fn connect(&mut self) {
         self.reset_connection();
+        self.denied_reason = None;
         self.set_state(ClientState::SendingConnectionRequest);
         info!(
             "client connecting to server {} [{}/{}]",
@@ -632,6 +637,10 @@ impl<Ctx> NetcodeClient<Ctx> {
     pub fn state(&self) -> ClientState {
         self.state
     }
+    /// Gets the reason sent by the server when it denied the connection request.
+    pub fn denied_reason(&self) -> Option<&DeniedReason> {
+        self.denied_reason.as_ref()
+    }
     /// Returns true if the client is in an error state.
     pub fn is_error(&self) -> bool {
         self.state < ClientState::Disconnected
@@ -691,6 +700,15 @@ pub(crate) mod connection {
                     ConnectionState::Connecting
                 }
                 ClientState::Connected => ConnectionState::Connected,
+                ClientState::ConnectionDenied if self.client.denied_reason.is_some() => {
+                    ConnectionState::Disconnected {
+                        reason: self
+                            .client
+                            .denied_reason
+                            .clone()
+                            .map(ConnectionError::Denied),
+                    }
+                }
                 _ => ConnectionState::Disconnected {
                     reason: Some(ConnectionError::NetcodeState(self.client.state)),
                 },
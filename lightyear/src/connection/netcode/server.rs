@@ -1,4 +1,4 @@
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
@@ -30,7 +30,7 @@ use super::{
     },
     replay::ReplayProtection,
     token::{ChallengeToken, ConnectToken, ConnectTokenBuilder, ConnectTokenPrivate},
-    MAC_BYTES, MAX_PACKET_SIZE, MAX_PKT_BUF_SIZE, PACKET_SEND_RATE_SEC,
+    MAC_BYTES, MAX_PACKET_SIZE, MAX_PKT_BUF_SIZE, PACKET_SEND_RATE_SEC, USER_DATA_BYTES,
 };
 
 pub const MAX_CLIENTS: usize = 256;
@@ -189,6 +189,19 @@ impl ConnectionCache {
         self.clients.remove(&client_id);
     }
 
+    /// Remove a client that was never connected (for example because its connection request was rejected)
+    fn remove_pending(&mut self, client_id: ClientId) {
+        let Some(conn) = self.clients.get(&client_id) else {
+            return;
+        };
+        if conn.is_connected() {
+            return;
+        }
+        self.client_id_map.remove(&conn.addr);
+        self.replay_protection.remove(&client_id);
+        self.clients.remove(&client_id);
+    }
+
     fn ids(&self) -> Vec<ClientId> {
         self.clients.keys().cloned().collect()
     }
@@ -217,12 +230,17 @@ impl ConnectionCache {
 
 pub type Callback<Ctx> = Box<dyn FnMut(ClientId, SocketAddr, &mut Ctx) + Send + Sync + 'static>;
 
+pub type RequestCallback<Ctx> =
+    Box<dyn FnMut(ClientId, SocketAddr, &[u8; USER_DATA_BYTES], &mut Ctx) + Send + Sync + 'static>;
+
 /// Configuration for a server.
 ///
 /// * `num_disconnect_packets` - The number of redundant disconnect packets that will be sent to a client when the server is disconnecting it.
 /// * `keep_alive_send_rate` - The rate at which keep-alive packets will be sent to clients.
 /// * `on_connect` - A callback that will be called when a client is connected to the server.
 /// * `on_disconnect` - A callback that will be called when a client is disconnected from the server.
+/// * `on_connection_request` - A callback that will be called when a client completed the handshake; if provided,
+///   the client is only connected once the request is accepted with [`NetcodeServer::accept_connection`].
 ///
 /// # Example
 /// ```
@@ -250,6 +268,7 @@ pub struct ServerConfig<Ctx> {
     context: Ctx,
     on_connect: Option<Callback<Ctx>>,
     on_disconnect: Option<Callback<Ctx>>,
+    on_connection_request: Option<RequestCallback<Ctx>>,
 }
 
 impl Default for ServerConfig<()> {
@@ -264,6 +283,7 @@ impl Default for ServerConfig<()> {
             context: (),
             on_connect: None,
             on_disconnect: None,
+            on_connection_request: None,
         }
     }
 }
@@ -285,6 +305,7 @@ impl<Ctx> ServerConfig<Ctx> {
             context: ctx,
             on_connect: None,
             on_disconnect: None,
+            on_connection_request: None,
         }
     }
     /// Set the number of redundant disconnect packets that will be sent to a client when the server is disconnecting it. <br>
@@ -339,6 +360,18 @@ impl<Ctx> ServerConfig<Ctx> {
         self.on_disconnect = Some(Box::new(cb));
         self
     }
+    /// Provide a callback that will be called when a client sends a valid challenge response. <br>
+    /// The callback will be called with the client index, the user data of the connect token and the context that was provided.
+    ///
+    /// When this callback is set, the client is not connected right away: the connection request stays pending until
+    /// it is accepted with [`NetcodeServer::accept_connection`] or rejected with [`NetcodeServer::reject_connection`].
+    pub fn on_connection_request<F>(mut self, cb: F) -> Self
+    where
+        F: FnMut(ClientId, SocketAddr, &[u8; USER_DATA_BYTES], &mut Ctx) + Send + Sync + 'static,
+    {
+        self.on_connection_request = Some(Box::new(cb));
+        self
+    }
 }
 
 /// The `netcode` server.
@@ -386,6 +419,8 @@ pub struct NetcodeServer<Ctx = ()> {
     token_entries: TokenEntries,
     cfg: ServerConfig<Ctx>,
     client_errors: Vec<ConnectionError>,
+    // clients that completed the handshake but are waiting for their connection request to be accepted or rejected
+    pending_requests: HashSet<ClientId>,
 }
 
 impl NetcodeServer {
@@ -405,6 +440,7 @@ impl NetcodeServer {
             token_entries: TokenEntries::new(),
             cfg: ServerConfig::default(),
             client_errors: vec![],
+            pending_requests: HashSet::new(),
         };
         // info!("server started on {}", server.io.local_addr());
         Ok(server)
@@ -440,6 +476,7 @@ impl<Ctx> NetcodeServer<Ctx> {
             token_entries: TokenEntries::new(),
             cfg,
             client_errors: vec![],
+            pending_requests: HashSet::new(),
         };
         // info!("server started on {}", server.addr());
         Ok(server)
@@ -462,6 +499,16 @@ impl<Ctx> NetcodeServer<Ctx> {
             cb(client_id, addr, &mut self.cfg.context)
         }
     }
+    fn on_connection_request(
+        &mut self,
+        client_id: ClientId,
+        addr: SocketAddr,
+        user_data: &[u8; USER_DATA_BYTES],
+    ) {
+        if let Some(cb) = self.cfg.on_connection_request.as_mut() {
+            cb(client_id, addr, user_data, &mut self.cfg.context)
+        }
+    }
     fn handle_client_error(&mut self, error: Error) {
         self.client_errors.push(ConnectionError::Netcode(error));
     }
@@ -704,6 +751,24 @@ impl<Ctx> NetcodeServer<Ctx> {
             return Err(Error::ServerIsFull(id::ClientId::Netcode(id)));
         }
 
+        if self.cfg.on_connection_request.is_some() {
+            // the client keeps sending responses until it is connected, we only notify the first one
+            if self.pending_requests.insert(id) {
+                debug!(
+                    "server received connection request from client {id}, waiting for a decision"
+                );
+                self.on_connection_request(id, from_addr, &challenge_token.user_data);
+            }
+            return Ok(());
+        }
+        self.connect_client(id, from_addr, sender)
+    }
+    fn connect_client(
+        &mut self,
+        id: ClientId,
+        from_addr: SocketAddr,
+        sender: &mut impl PacketSender,
+    ) -> Result<()> {
         let client = self
             .conn_cache
             .clients
@@ -713,10 +778,7 @@ impl<Ctx> NetcodeServer<Ctx> {
         client.connect();
         client.last_send_time = self.time;
         client.last_receive_time = self.time;
-        debug!(
-            "server accepted client {} with id {}",
-            id, challenge_token.client_id
-        );
+        debug!("server accepted client {}", id);
         self.send_to_client(KeepAlivePacket::create(id), id, sender)?;
         self.on_connect(id, from_addr);
         Ok(())
@@ -996,9 +1058,57 @@ impl<Ctx> NetcodeServer<Ctx> {
         self.disconnect(*client_id, io)
     }
 
+    /// Accepts the pending connection request of a client, which will then be connected to the server.
+    ///
+    /// Connection requests are only pending if a [`ServerConfig::on_connection_request`] callback was provided.
+    pub fn accept_connection(&mut self, client_id: ClientId, io: &mut Io) -> Result<()> {
+        if !self.pending_requests.contains(&client_id) {
+            return Err(Error::UnknownClient(id::ClientId::Netcode(client_id)));
+        }
+        if self.num_connected_clients() >= MAX_CLIENTS {
+            self.reject_connection(client_id, DeniedReason::ServerFull, io)?;
+            return Err(Error::ServerIsFull(id::ClientId::Netcode(client_id)));
+        }
+        self.pending_requests.remove(&client_id);
+        let conn = self
+            .conn_cache
+            .find_by_id(client_id)
+            .ok_or(Error::ClientNotFound(id::ClientId::Netcode(client_id)))?;
+        self.connect_client(client_id, conn.addr, io)
+    }
+
+    /// Rejects the pending connection request of a client.
+    ///
+    /// The client receives the [`DeniedReason`] and will not be connected to the server.
+    pub fn reject_connection(
+        &mut self,
+        client_id: ClientId,
+        reason: DeniedReason,
+        io: &mut Io,
+    ) -> Result<()> {
+        if !self.pending_requests.remove(&client_id) {
+            return Err(Error::UnknownClient(id::ClientId::Netcode(client_id)));
+        }
+        let conn = self
+            .conn_cache
+            .find_by_id(client_id)
+            .ok_or(Error::ClientNotFound(id::ClientId::Netcode(client_id)))?;
+        debug!("server rejected client {client_id}: {reason:?}");
+        self.conn_cache.remove_pending(client_id);
+        self.send_to_addr(DeniedPacket::create(reason), conn.addr, conn.send_key, io)
+    }
+
+    /// Returns the ids of the clients whose connection request is waiting to be accepted or rejected.
+    pub fn pending_client_ids(&self) -> impl Iterator<Item = ClientId> + '_ {
+        self.pending_requests.iter().copied()
+    }
+
     /// Disconnects all clients.
     pub fn disconnect_all(&mut self, io: &mut Io) -> Result<()> {
         debug!("Server preparing to disconnect all clients");
+        for id in self.pending_requests.clone() {
+            self.reject_connection(id, DeniedReason::InternalError, io)?;
+        }
         for id in self.conn_cache.ids() {
             let Some(conn) = self.conn_cache.clients.get_mut(&id) else {
                 warn!("Could not disconnect client {id:?} because the connection was not found");
@@ -1045,13 +1155,15 @@ impl<Ctx> NetcodeServer<Ctx> {
 
 pub(crate) mod connection {
     use super::*;
-    use crate::connection::server::ConnectionError;
+    use crate::connection::server::{ConnectionError, ConnectionRequestDefault};
+    use crate::server::events::ConnectionRequestEvent;
     use core::result::Result;
 
     #[derive(Default)]
     pub(crate) struct NetcodeServerContext {
         pub(crate) connections: Vec<id::ClientId>,
         pub(crate) disconnections: Vec<id::ClientId>,
+        pub(crate) connection_requests: Vec<ConnectionRequestEvent>,
         sender: Option<ServerNetworkEventSender>,
     }
 
@@ -1060,6 +1172,9 @@ pub(crate) mod connection {
         pub(crate) server: NetcodeServer<NetcodeServerContext>,
         io_config: IoConfig,
         io: Option<Io>,
+        connection_request_default: ConnectionRequestDefault,
+        /// Answers to the pending connection requests, applied during the next update
+        connection_request_answers: Vec<(ClientId, Option<DeniedReason>)>,
     }
 
     impl NetServer for Server {
@@ -1115,12 +1230,29 @@ pub(crate) mod connection {
         }
 
         fn try_update(&mut self, delta_ms: f64) -> Result<Vec<ConnectionError>, ConnectionError> {
-            let io = self.io.as_mut().ok_or(ConnectionError::IoNotInitialized)?;
+            if self.io.is_none() {
+                return Err(ConnectionError::IoNotInitialized);
+            }
             // reset the new connections, disconnections, and errors
             self.server.cfg.context.connections.clear();
             self.server.cfg.context.disconnections.clear();
+            self.server.cfg.context.connection_requests.clear();
             self.server.client_errors.clear();
 
+            // answer the connection requests received during the previous update; the ones that
+            // didn't get an answer use the default
+            for (client_id, denied_reason) in std::mem::take(&mut self.connection_request_answers) {
+                self.answer_pending_request(client_id, denied_reason);
+            }
+            let unanswered: Vec<ClientId> = self.server.pending_client_ids().collect();
+            for client_id in unanswered {
+                self.answer_pending_request(
+                    client_id,
+                    self.connection_request_default.denied_reason(),
+                );
+            }
+
+            let io = self.io.as_mut().ok_or(ConnectionError::IoNotInitialized)?;
             let client_errors = self.server.try_update(delta_ms, io)?;
             Ok(client_errors)
         }
@@ -1148,6 +1280,23 @@ pub(crate) mod connection {
             self.server.cfg.context.disconnections.clone()
         }
 
+        fn new_connection_requests(&self) -> Vec<ConnectionRequestEvent> {
+            self.server.cfg.context.connection_requests.clone()
+        }
+
+        fn answer_connection_request(
+            &mut self,
+            client_id: id::ClientId,
+            denied_reason: Option<DeniedReason>,
+        ) -> Result<(), ConnectionError> {
+            let id::ClientId::Netcode(client_id) = client_id else {
+                return Err(ConnectionError::InvalidConnectionType);
+            };
+            self.connection_request_answers
+                .push((client_id, denied_reason));
+            Ok(())
+        }
+
         fn client_addr(&self, client_id: crate::prelude::ClientId) -> Option<SocketAddr> {
             match client_id {
                 id::ClientId::Netcode(id) => self.server.client_addr(id),
@@ -1183,6 +1332,14 @@ pub(crate) mod connection {
                     }
                     ctx.disconnections.push(id::ClientId::Netcode(id));
                 });
+            if config.emit_connection_requests {
+                cfg = cfg.on_connection_request(|id, addr, user_data, ctx| {
+                    ctx.connection_requests.push(ConnectionRequestEvent {
+                        client_id: id::ClientId::Netcode(id),
+                        connect_token_data: *user_data,
+                    });
+                });
+            }
             cfg = cfg.keep_alive_send_rate(config.keep_alive_send_rate);
             cfg = cfg.num_disconnect_packets(config.num_disconnect_packets);
             cfg = cfg.client_timeout_secs(config.client_timeout_secs);
@@ -1194,6 +1351,26 @@ pub(crate) mod connection {
                 server,
                 io_config,
                 io: None,
+                connection_request_default: config.connection_request_default,
+                connection_request_answers: vec![],
+            }
+        }
+
+        /// Accept or reject a pending connection request, the errors are reported as client errors
+        fn answer_pending_request(
+            &mut self,
+            client_id: ClientId,
+            denied_reason: Option<DeniedReason>,
+        ) {
+            let Some(io) = self.io.as_mut() else {
+                return;
+            };
+            let result = match denied_reason {
+                None => self.server.accept_connection(client_id, io),
+                Some(reason) => self.server.reject_connection(client_id, reason, io),
+            };
+            if let Err(e) = result {
+                self.server.handle_client_error(e);
             }
         }
 
@@ -11,11 +11,13 @@ use std::sync::Arc;
 use crate::connection::id::ClientId;
 #[cfg(all(feature = "steam", not(target_family = "wasm")))]
 use crate::connection::steam::{server::SteamConfig, steamworks_client::SteamworksClient};
+use crate::packet::compression::CompressionConfig;
 use crate::packet::packet_builder::RecvPayload;
 use crate::prelude::server::ServerTransport;
 #[cfg(all(feature = "steam", not(target_family = "wasm")))]
 use crate::prelude::LinkConditionerConfig;
 use crate::server::config::NetcodeConfig;
+use crate::server::events::ConnectionRequestEvent;
 use crate::server::io::Io;
 use crate::transport::config::SharedIoConfig;
 
@@ -49,6 +51,28 @@ impl ConnectionRequestHandler for DefaultConnectionRequestHandler {
     }
 }
 
+/// What to do with a [`ConnectionRequestEvent`] that was not accepted or rejected during the frame
+/// where it was emitted.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub enum ConnectionRequestDefault {
+    /// The client is connected to the server
+    #[default]
+    Accept,
+    /// The client is denied with the given reason
+    Reject(DeniedReason),
+}
+
+impl ConnectionRequestDefault {
+    /// The answer to a connection request: `None` if the connection is accepted,
+    /// `Some(reason)` if it is denied
+    pub(crate) fn denied_reason(&self) -> Option<DeniedReason> {
+        match self {
+            ConnectionRequestDefault::Accept => None,
+            ConnectionRequestDefault::Reject(reason) => Some(reason.clone()),
+        }
+    }
+}
+
 #[enum_dispatch]
 pub trait NetServer: Send + Sync {
     /// Start the server
@@ -59,8 +83,6 @@ pub trait NetServer: Send + Sync {
     /// (i.e. stop listening for client connections and stop all networking)
     fn stop(&mut self) -> Result<(), ConnectionError>;
 
-    // TODO: should we also have an API for accepting a client? i.e. we receive a connection request
-    //  and we decide whether to accept it or not
     /// Disconnect a specific client
     /// Is also responsible for adding the client to the list of new disconnections.
     fn disconnect(&mut self, client_id: ClientId) -> Result<(), ConnectionError>;
@@ -81,6 +103,24 @@ pub trait NetServer: Send + Sync {
 
     fn new_disconnections(&self) -> Vec<ClientId>;
 
+    /// Return the connection requests received during the last update, that are waiting to be
+    /// accepted or rejected with [`NetServer::answer_connection_request`]
+    fn new_connection_requests(&self) -> Vec<ConnectionRequestEvent> {
+        vec![]
+    }
+
+    /// Accept (if `denied_reason` is `None`) or reject a pending connection request.
+    ///
+    /// Connection requests that did not get an answer before the next update are answered
+    /// with the server's [`ConnectionRequestDefault`].
+    fn answer_connection_request(
+        &mut self,
+        client_id: ClientId,
+        denied_reason: Option<DeniedReason>,
+    ) -> Result<(), ConnectionError> {
+        Err(ConnectionError::ConnectionNotFound)
+    }
+
     /// Returns the client's `SocketAddr` if available
     fn client_addr(&self, client_id: ClientId) -> Option<SocketAddr>;
 
@@ -129,6 +169,16 @@ impl NetConfig {
             }
         }
     }
+
+    /// The compression set with the deprecated [`SharedIoConfig::compression`]
+    #[allow(deprecated)]
+    pub(crate) fn io_compression(&self) -> CompressionConfig {
+        match self {
+            NetConfig::Netcode { io, .. } => io.compression,
+            #[cfg(all(feature = "steam", not(target_family = "wasm")))]
+            NetConfig::Steam { .. } => CompressionConfig::None,
+        }
+    }
 }
 
 impl Default for NetConfig {
@@ -183,6 +233,8 @@ pub struct ServerConnections {
     pub(crate) servers: Vec<ServerConnection>,
     /// Mapping from the connection's [`ClientId`] into the index of the [`ServerConnection`] in the `servers` list
     pub(crate) client_server_map: HashMap<ClientId, ServerConnectionIdx>,
+    /// Mapping from the [`ClientId`] of a pending connection request into the index of the [`ServerConnection`] that received it
+    pub(crate) pending_requests: HashMap<ClientId, ServerConnectionIdx>,
 }
 
 impl ServerConnections {
@@ -195,6 +247,7 @@ impl ServerConnections {
         ServerConnections {
             servers,
             client_server_map: HashMap::default(),
+            pending_requests: HashMap::default(),
         }
     }
 
@@ -227,6 +280,20 @@ impl ServerConnections {
         )
     }
 
+    /// Accept (if `denied_reason` is `None`) or reject a pending connection request
+    pub(crate) fn answer_connection_request(
+        &mut self,
+        client_id: ClientId,
+        denied_reason: Option<DeniedReason>,
+    ) -> Result<(), ConnectionError> {
+        self.pending_requests.remove(&client_id).map_or(
+            Err(ConnectionError::ConnectionNotFound),
+            |server_idx| {
+                self.servers[server_idx].answer_connection_request(client_id, denied_reason)
+            },
+        )
+    }
+
     /// Returns the client's `SocketAddr` if available
     pub fn client_addr(&self, client_id: ClientId) -> Option<SocketAddr> {
         self.client_server_map
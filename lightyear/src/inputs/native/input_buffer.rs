@@ -12,6 +12,11 @@ use super::UserAction;
 pub struct InputBuffer<T> {
     pub buffer: VecDeque<Option<T>>,
     pub start_tick: Option<Tick>,
+    /// Whether inputs were already popped from the buffer. If true, all the ticks before `start_tick`
+    /// have been consumed
+    pub(crate) popped: bool,
+    /// End tick of the most recent [`InputMessage`] that was used to update the buffer
+    pub(crate) last_message_end_tick: Option<Tick>,
 }
 
 // TODO: add encode directive to encode even more efficiently
@@ -52,6 +57,8 @@ impl<T> Default for InputBuffer<T> {
             // buffer: SequenceBuffer::new(),
             buffer: VecDeque::new(),
             start_tick: None,
+            popped: false,
+            last_message_end_tick: None,
             // end_tick: Tick(0),
         }
     }
@@ -72,6 +79,7 @@ impl<T: UserAction> InputBuffer<T> {
         if tick < start_tick {
             return None;
         }
+        self.popped = true;
         if tick > start_tick + (self.buffer.len() as i16 - 1) {
             // pop everything
             self.buffer = VecDeque::new();
@@ -110,8 +118,17 @@ impl<T: UserAction> InputBuffer<T> {
             self.buffer.push_back(value);
             return;
         };
-        // cannot set lower values than start_tick
         if tick < start_tick {
+            // the inputs before start_tick have already been consumed
+            if self.popped {
+                return;
+            }
+            // the input arrived out of order, before any input was consumed
+            for _ in 0..(start_tick - tick - 1) {
+                self.buffer.push_front(None);
+            }
+            self.buffer.push_front(value);
+            self.start_tick = Some(tick);
             return;
         }
         let end_tick = start_tick + (self.buffer.len() as i16 - 1);
@@ -127,17 +144,50 @@ impl<T: UserAction> InputBuffer<T> {
     }
 
     /// We received a new input message from the user, and use it to update the input buffer
+    ///
+    /// Messages can arrive out of order: the inputs are stored by tick, so an older message that arrives
+    /// after a newer one is still applied, but only for the ticks that the newer message didn't already provide.
+    /// Inputs for ticks that were already popped are too late and are dropped.
+    /// Returns false if the message was dropped because it lags behind the most recent message
+    /// by more than `reorder_window` ticks.
+    ///
     /// TODO: should we keep track of which inputs in the input buffer are absent and only update those?
     ///  The current tick is the current server tick, no need to update the buffer for ticks that are older than that
-    pub(crate) fn update_from_message(&mut self, message: &InputMessage<T>) {
+    pub(crate) fn update_from_message(
+        &mut self,
+        message: &InputMessage<T>,
+        reorder_window: u16,
+    ) -> bool {
         let message_start_tick = Tick(message.end_tick.0) - message.inputs.len() as u16 + 1;
+        // whether a more recent message was already used to update the buffer
+        let mut is_late = false;
+        match self.last_message_end_tick {
+            Some(last_end_tick) if message.end_tick < last_end_tick => {
+                if last_end_tick - message.end_tick > reorder_window as i16 {
+                    return false;
+                }
+                is_late = true;
+            }
+            _ => {
+                self.last_message_end_tick = Some(message.end_tick);
+            }
+        }
         let mut prev_value = None;
 
         for (delta, input) in message.inputs.iter().enumerate() {
             let tick = message_start_tick + Tick(delta as u16);
+            match input {
+                InputData::Absent => prev_value = None,
+                InputData::Input(input) => prev_value = Some(input.clone()),
+                InputData::SameAsPrecedent => {}
+            }
+            // a late message only fills the ticks that are missing, without overwriting
+            // the inputs provided by a more recent message
+            if is_late && self.get(tick).is_some() {
+                continue;
+            }
             match input {
                 InputData::Absent => {
-                    prev_value = None;
                     self.set(tick, None);
                 }
                 InputData::SameAsPrecedent => {
@@ -155,6 +205,7 @@ impl<T: UserAction> InputBuffer<T> {
                 }
             }
         }
+        true
     }
 
     // Convert the last N ticks up to end_tick included into a compressed message that we can send to the server
@@ -255,7 +306,7 @@ mod tests {
                 InputData::SameAsPrecedent,
             ],
         };
-        input_buffer.update_from_message(&message);
+        input_buffer.update_from_message(&message, 16);
 
         assert_eq!(input_buffer.get(Tick(20)), None);
         assert_eq!(input_buffer.get(Tick(19)), None);
@@ -266,4 +317,69 @@ mod tests {
         assert_eq!(input_buffer.get(Tick(14)), Some(&0));
         assert_eq!(input_buffer.get(Tick(13)), None);
     }
+
+    /// Two input messages are swapped by the network: both are applied and the inputs
+    /// are consumed in tick order
+    #[test]
+    fn test_update_from_reordered_messages() {
+        let mut input_buffer = InputBuffer::default();
+        let first = InputMessage {
+            end_tick: Tick(11),
+            inputs: vec![InputData::Input(1), InputData::Input(2)],
+        };
+        let second = InputMessage {
+            end_tick: Tick(13),
+            inputs: vec![InputData::Input(3), InputData::SameAsPrecedent],
+        };
+
+        assert!(input_buffer.update_from_message(&second, 16));
+        assert!(input_buffer.update_from_message(&first, 16));
+
+        assert_eq!(input_buffer.pop(Tick(10)), Some(1));
+        assert_eq!(input_buffer.pop(Tick(11)), Some(2));
+        assert_eq!(input_buffer.pop(Tick(12)), Some(3));
+        assert_eq!(input_buffer.pop(Tick(13)), Some(3));
+
+        // a late message does not overwrite the inputs of a more recent message
+        let mut input_buffer = InputBuffer::default();
+        let recent = InputMessage {
+            end_tick: Tick(12),
+            inputs: vec![InputData::Input(2), InputData::SameAsPrecedent],
+        };
+        let late = InputMessage {
+            end_tick: Tick(11),
+            inputs: vec![InputData::Input(1), InputData::Absent],
+        };
+        assert!(input_buffer.update_from_message(&recent, 16));
+        assert!(input_buffer.update_from_message(&late, 16));
+        assert_eq!(input_buffer.get(Tick(10)), Some(&1));
+        assert_eq!(input_buffer.get(Tick(11)), Some(&2));
+        assert_eq!(input_buffer.get(Tick(12)), Some(&2));
+    }
+
+    /// Inputs that arrive after their tick was consumed, or outside of the reorder window, are dropped
+    #[test]
+    fn test_update_from_too_late_messages() {
+        let mut input_buffer = InputBuffer::default();
+        let recent = InputMessage {
+            end_tick: Tick(30),
+            inputs: vec![InputData::Input(3)],
+        };
+        let late = InputMessage {
+            end_tick: Tick(20),
+            inputs: vec![InputData::Input(2)],
+        };
+        assert!(input_buffer.update_from_message(&recent, 4));
+        assert!(!input_buffer.update_from_message(&late, 4));
+        assert_eq!(input_buffer.get(Tick(20)), None);
+
+        let consumed = InputMessage {
+            end_tick: Tick(29),
+            inputs: vec![InputData::Input(2)],
+        };
+        assert_eq!(input_buffer.pop(Tick(30)), Some(3));
+        assert!(input_buffer.update_from_message(&consumed, 4));
+        assert_eq!(input_buffer.get(Tick(29)), None);
+        assert_eq!(input_buffer.start_tick, Some(Tick(31)));
+    }
 }
@@ -59,6 +59,8 @@ You can also disconnect with the [`disconnect_client`](prelude::client::ClientCo
 
 On the server, you can start listening for connections by using the [`start_server`](prelude::server::ServerCommandsExt::start_server) Command.
 You can stop the server using the [`stop_server`](prelude::server::ServerCommandsExt::stop_server) Command.
+To notify the clients before stopping, use the [`stop_server_graceful`](prelude::server::ServerCommandsExt::stop_server_graceful) Command:
+the clients receive the shutdown reason in their [`DisconnectEvent`](prelude::client::DisconnectEvent).
 
 While the client or server are disconnected, you can update the [`ClientConfig`](prelude::client::ClientConfig) and [`ServerConfig`](prelude::server::ServerConfig) resources,
 and the new configuration will take effect on the next connection attempt.
@@ -181,12 +183,12 @@ pub(crate) mod _internal {
 
 /// Prelude containing commonly used types
 pub mod prelude {
-    pub use lightyear_macros::Channel;
+    pub use lightyear_macros::{Channel, Diffable};
     pub use serde::{Deserialize, Serialize};
 
     pub use crate::channel::builder::{
         Channel, ChannelBuilder, ChannelContainer, ChannelDirection, ChannelMode, ChannelSettings,
-        InputChannel, ReliableSettings,
+        InputChannel, ReliableSettings, WindowFullPolicy,
     };
     pub use crate::client::prediction::prespawn::PreSpawnedPlayerObject;
     pub use crate::connection::id::ClientId;
@@ -194,13 +196,16 @@ pub mod prelude {
     #[cfg(feature = "leafwing")]
     pub use crate::inputs::leafwing::{input_message::InputMessage, LeafwingUserAction};
     pub use crate::inputs::native::UserAction;
+    pub use crate::packet::compression::CompressionConfig;
     pub use crate::packet::error::PacketError;
-    pub use crate::packet::message::Message;
+    pub use crate::packet::message::{Message, MessageId};
     pub use crate::protocol::channel::{AppChannelExt, ChannelKind, ChannelRegistry};
     pub use crate::protocol::component::{AppComponentExt, ComponentRegistry, Linear};
     pub use crate::protocol::message::{
+        intent::AppIntentExt,
         registry::{AppMessageExt, MessageRegistry},
         resource::AppResourceExt,
+        tick_event::AppTickEventExt,
     };
     pub use crate::protocol::serialize::AppSerializeExt;
     pub use crate::shared::config::SharedConfig;
@@ -208,18 +213,27 @@ pub mod prelude {
     #[cfg(feature = "leafwing")]
     pub use crate::shared::input::leafwing::LeafwingInputPlugin;
     pub use crate::shared::input::native::InputPlugin;
+    pub use crate::shared::lockstep::{
+        AppLockstepExt, LockstepConfig, LockstepPlugin, LockstepUpdate,
+    };
     pub use crate::shared::message::MessageSend;
     pub use crate::shared::ping::manager::PingConfig;
     pub use crate::shared::plugin::SharedPlugin;
-    pub use crate::shared::replication::authority::HasAuthority;
+    pub use crate::shared::replication::authority::{
+        AuthorityConflictPolicy, AuthorityHistory, AuthorityTransferEvent, HasAuthority,
+        PendingAuthorityTransfer,
+    };
     pub use crate::shared::replication::components::{
-        DeltaCompression, DisabledComponents, NetworkRelevanceMode, OverrideTargetComponent,
-        PrePredicted, ReplicateHierarchy, ReplicateOnceComponent, Replicated, Replicating,
-        ReplicationGroup, ShouldBePredicted, TargetEntity,
+        DeltaCompression, DespawnReason, DisabledComponents, NetworkId, NetworkRelevanceMode,
+        OverrideTargetComponent, PrePredicted, ReplicateHierarchy, ReplicateOnceComponent,
+        Replicated, Replicating, ReplicationGroup, ReplicationInterval, ReplicationPriority,
+        ShouldBePredicted, TargetEntity,
     };
+    pub use crate::shared::replication::delta::Diffable;
     pub use crate::shared::replication::entity_map::RemoteEntityMap;
     pub use crate::shared::replication::hierarchy::ParentSync;
     pub use crate::shared::replication::network_target::NetworkTarget;
+    pub use crate::shared::replication::origin::OriginRebase;
     pub use crate::shared::replication::plugin::ReplicationConfig;
     pub use crate::shared::replication::plugin::SendUpdatesMode;
     pub use crate::shared::replication::resources::{
@@ -230,7 +244,6 @@ pub mod prelude {
     pub use crate::shared::tick_manager::TickManager;
     pub use crate::shared::tick_manager::{Tick, TickConfig};
     pub use crate::shared::time_manager::TimeManager;
-    pub use crate::transport::middleware::compression::CompressionConfig;
     pub use crate::transport::middleware::conditioner::LinkConditionerConfig;
     pub use crate::utils::history_buffer::{HistoryBuffer, HistoryState};
 
@@ -266,18 +279,25 @@ pub mod prelude {
 
     pub mod client {
         pub use crate::client::components::{
-            ComponentSyncMode, Confirmed, LerpFn, SyncComponent, SyncMetadata,
+            replication_mode, ComponentSyncMode, Confirmed, LerpFn, ReplicationMode,
+            ReplicationModes, SyncComponent, SyncMetadata,
         };
         pub use crate::client::config::{ClientConfig, NetcodeConfig, PacketConfig};
         pub use crate::client::connection::ConnectionManager;
+        pub use crate::client::display::DisplayValue;
         pub use crate::client::error::ClientError;
         pub use crate::client::events::{
-            ComponentInsertEvent, ComponentRemoveEvent, ComponentUpdateEvent, ConnectEvent,
-            DisconnectEvent, EntityDespawnEvent, EntitySpawnEvent, InputEvent,
+            ComponentDeserializationErrorEvent, ComponentInsertEvent, ComponentRemoveEvent,
+            ComponentUpdateEvent, ConnectEvent, ControlGained, ControlLost, DisconnectEvent,
+            EntityDespawnEvent, EntitySpawnEvent, InputEvent,
         };
         #[cfg(feature = "leafwing")]
         pub use crate::client::input::leafwing::LeafwingInputConfig;
         pub use crate::client::input::native::{InputConfig, InputManager};
+        pub use crate::client::interpolation::buffer_limit::{
+            InterpolationBufferUsage, InterpolationPriority,
+        };
+        pub use crate::client::interpolation::entity_config::EntityInterpolationConfig;
         pub use crate::client::interpolation::interpolation_history::ConfirmedHistory;
         pub use crate::client::interpolation::plugin::{
             InterpolationConfig, InterpolationDelay, InterpolationSet,
@@ -287,6 +307,7 @@ pub mod prelude {
         };
         pub use crate::client::io::config::ClientTransport;
         pub use crate::client::io::Io;
+        pub use crate::client::lockstep::{LockstepManager, LockstepSet};
         pub use crate::client::message::ReceiveMessage;
         pub use crate::client::networking::{ClientCommandsExt, ConnectedState, NetworkingState};
         pub use crate::client::plugin::ClientPlugins;
@@ -298,35 +319,59 @@ pub mod prelude {
         pub use crate::client::prediction::Predicted;
         pub use crate::client::replication::commands::DespawnReplicationCommandExt;
         pub use crate::client::replication::send::{Replicate, ReplicateToServer};
+        pub use crate::client::replication::{ReplicationConvergence, ReplicationReceived};
         pub use crate::client::run_conditions::{is_connected, is_disconnected, is_synced};
-        pub use crate::client::sync::SyncConfig;
+        pub use crate::client::sync::{SyncConfig, SyncDiagnostics};
         pub use crate::connection::client::{
             Authentication, ClientConnection, IoConfig, NetClient, NetConfig,
         };
         #[cfg(all(feature = "steam", not(target_family = "wasm")))]
         pub use crate::connection::steam::client::{SocketConfig, SteamConfig};
         pub use crate::protocol::message::client::ClientTriggerExt;
+        pub use crate::protocol::message::intent::IntentResultEvent;
+        pub use crate::protocol::message::tick_event::TickEvent;
+        pub use crate::shared::replication::subscription::ComponentSubscriptionCommandsExt;
     }
     pub mod server {
         #[cfg(all(feature = "webtransport", not(target_family = "wasm")))]
         pub use wtransport::tls::Identity;
 
-        pub use crate::connection::server::{IoConfig, NetConfig, NetServer, ServerConnection};
+        pub use crate::connection::server::{
+            ConnectionRequestDefault, DeniedReason, IoConfig, NetConfig, NetServer,
+            ServerConnection,
+        };
         #[cfg(all(feature = "steam", not(target_family = "wasm")))]
         pub use crate::connection::steam::server::{SocketConfig, SteamConfig};
+        pub use crate::protocol::message::intent::{IntentEvent, IntentResult};
         pub use crate::protocol::message::server::ServerTriggerExt;
-        pub use crate::server::clients::ControlledEntities;
-        pub use crate::server::config::{NetcodeConfig, PacketConfig, ServerConfig};
-        pub use crate::server::connection::ConnectionManager;
-        pub use crate::server::error::ServerError;
+        pub use crate::server::clients::{
+            ClientControlledEntities, ClientEntitySet, ControlGained, ControlLost, ControlRejected,
+            ControlValidator, ControlledByRoom, ControlledEntities, OrphanedEntities,
+            RoomControllers, SessionToken,
+        };
+        pub use crate::server::config::{
+            ClientEntityConfig, InputConfig, NetcodeConfig, NetworkIdConfig, PacketConfig,
+            ServerConfig,
+        };
+        pub use crate::server::connection::{
+            ClientMetadata, ClientShardKey, ConnectionManager, EntityReplicationState,
+            ServerInstanceId,
+        };
+        pub use crate::server::error::{ClientLookupError, ServerError};
         pub use crate::server::events::{
-            ComponentInsertEvent, ComponentRemoveEvent, ComponentUpdateEvent, ConnectEvent,
-            DisconnectEvent, EntityDespawnEvent, EntitySpawnEvent, InputEvent,
+            AuthorityViolation, ComponentDeserializationErrorEvent, ComponentInsertEvent,
+            ComponentRemoveEvent, ComponentUpdateEvent, ConnectEvent, ConnectionRequestEvent,
+            DisconnectEvent, EntityDespawnEvent, EntitySpawnEvent, InputEvent, ReliableWindowFull,
         };
+        pub use crate::server::interpolation_snapshots::InterpolationSnapshotConfig;
         pub use crate::server::io::config::ServerTransport;
         pub use crate::server::io::Io;
+        pub use crate::server::lag_compensation::{ComponentSnapshot, LagCompensationConfig};
+        pub use crate::server::lockstep::LockstepDesyncEvent;
+        pub use crate::server::metrics::{ClientNetworkMetrics, NetworkMetrics};
         pub use crate::server::networking::{NetworkingState, ServerCommandsExt};
         pub use crate::server::plugin::ServerPlugins;
+        pub use crate::server::relay::RelayPlugin;
         pub use crate::server::relevance::immediate::RelevanceManager;
         pub use crate::server::relevance::room::{RoomId, RoomManager};
         pub use crate::server::replication::commands::AuthorityCommandExt;
@@ -338,7 +383,10 @@ pub mod prelude {
             ReplicationSet, ServerReplicationSet,
         };
         pub use crate::server::run_conditions::{is_started, is_stopped};
-        pub use crate::shared::replication::authority::AuthorityPeer;
+        pub use crate::shared::replication::authority::{AuthorityPeer, AuthorityRequestEvent};
+        pub use crate::shared::replication::dry_run::{
+            DryRunMessageKind, DryRunRecord, ReplicationDryRun,
+        };
     }
 
     #[cfg(all(feature = "steam", not(target_family = "wasm")))]
@@ -0,0 +1,97 @@
+//! Lz4 compression
+
+use crate::packet::compression::Codec;
+use lz4_flex::block::{compress_into, decompress_into, get_maximum_output_size};
+
+#[derive(Default)]
+pub(crate) struct Lz4 {
+    /// lz4 only compresses into buffers that can hold the worst-case compressed size
+    buffer: Vec<u8>,
+}
+
+impl Codec for Lz4 {
+    fn compress(&mut self, data: &[u8], out: &mut [u8]) -> Option<usize> {
+        self.buffer.resize(get_maximum_output_size(data.len()), 0);
+        let size = compress_into(data, &mut self.buffer).ok()?;
+        out.get_mut(..size)?.copy_from_slice(&self.buffer[..size]);
+        Some(size)
+    }
+
+    fn decompress(&mut self, data: &[u8], out: &mut [u8]) -> std::io::Result<usize> {
+        decompress_into(data, out)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::Events;
+    use bevy::utils::Duration;
+
+    use crate::connection::server::{NetServer, ServerConnections};
+    use crate::packet::compression::CompressionConfig;
+    use crate::prelude::client::ClientConfig;
+    use crate::prelude::{server, ClientId, ClientReceiveMessage, SharedConfig, TickConfig};
+    use crate::tests::protocol::{Channel1, StringMessage};
+    use crate::tests::stepper::{BevyStepper, TEST_CLIENT_ID};
+
+    /// Send a large repetitive message from the server to the client, and return the number
+    /// of bytes that the server sent on the wire
+    fn send_large_message(compression: CompressionConfig) -> usize {
+        let frame_duration = Duration::from_millis(10);
+        let shared_config = SharedConfig {
+            tick: TickConfig::new(frame_duration),
+            ..Default::default()
+        };
+        let mut client_config = ClientConfig::default();
+        client_config.packet.compression = compression;
+        let mut stepper = BevyStepper::new(shared_config, client_config, frame_duration);
+        stepper.build();
+        stepper.init();
+
+        let bytes_sent = |stepper: &BevyStepper| {
+            stepper
+                .server_app
+                .world()
+                .resource::<ServerConnections>()
+                .servers[0]
+                .io()
+                .unwrap()
+                .stats()
+                .bytes_sent
+        };
+        let message = StringMessage("hello world".repeat(80));
+        let before = bytes_sent(&stepper);
+        stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<server::ConnectionManager>()
+            .send_message::<Channel1, _>(ClientId::Netcode(TEST_CLIENT_ID), &message)
+            .unwrap();
+        stepper.frame_step();
+        let sent = bytes_sent(&stepper) - before;
+
+        // the message is decompressed by the client on the next frame
+        stepper.frame_step();
+        let received: Vec<_> = stepper
+            .client_app
+            .world_mut()
+            .resource_mut::<Events<ClientReceiveMessage<StringMessage>>>()
+            .drain()
+            .map(|event| event.message)
+            .collect();
+        assert_eq!(received, vec![message]);
+        sent
+    }
+
+    /// The packets are compressed before being encrypted by netcode, so the size on the wire shrinks
+    #[test]
+    fn test_compression_netcode() {
+        let uncompressed = send_large_message(CompressionConfig::None);
+        let compressed = send_large_message(CompressionConfig::Lz4 { threshold: 100 });
+        assert!(
+            compressed * 2 < uncompressed,
+            "compressed: {compressed}, uncompressed: {uncompressed}"
+        );
+    }
+}
@@ -0,0 +1,207 @@
+//! Compression of the packets above a size threshold.
+//!
+//! Packets are compressed by the `ConnectionManager` before they are handed to the connection
+//! (which, for netcode, encrypts them: encrypted data cannot be compressed), and decompressed
+//! right after being received.
+//!
+//! A compressed packet starts with the [`COMPRESSED`] byte, which is never the first byte of an
+//! uncompressed packet (uncompressed packets start with their [`PacketType`](super::packet_type::PacketType)).
+//! Packets smaller than the threshold, or for which compression does not reduce the size, are sent unchanged.
+#![cfg_attr(not(any(feature = "zstd", feature = "lz4")), allow(dead_code))]
+use bevy::prelude::Reflect;
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+
+use crate::connection::netcode::MAX_PACKET_SIZE;
+use crate::packet::error::{PacketError, Result};
+use crate::packet::packet_builder::{Payload, RecvPayload};
+
+#[cfg(feature = "zstd")]
+pub(crate) mod zstd;
+
+#[cfg(feature = "lz4")]
+pub(crate) mod lz4;
+
+/// Default size (in bytes) above which packets are compressed
+pub const DEFAULT_COMPRESSION_THRESHOLD: usize = 256;
+
+/// Compression of the packets sent to the remote peer.
+///
+/// The client and the server must use the same compression algorithm.
+#[derive(Clone, Copy, Debug, Default, Reflect, Serialize, Deserialize)]
+pub enum CompressionConfig {
+    #[default]
+    None,
+    /// Compress the packets that are at least `threshold` bytes long with zstd
+    #[cfg(feature = "zstd")]
+    Zstd { level: i32, threshold: usize },
+    /// Compress the packets that are at least `threshold` bytes long with lz4
+    #[cfg(feature = "lz4")]
+    Lz4 { threshold: usize },
+}
+
+/// First byte of a compressed packet
+const COMPRESSED: u8 = u8::MAX;
+
+/// A compression algorithm
+pub(crate) trait Codec: Send + Sync {
+    /// Compress `data` into `out` and return the compressed size,
+    /// or `None` if the compressed data does not fit in `out`
+    fn compress(&mut self, data: &[u8], out: &mut [u8]) -> Option<usize>;
+
+    /// Decompress `data` into `out` and return the decompressed size
+    fn decompress(&mut self, data: &[u8], out: &mut [u8]) -> std::io::Result<usize>;
+}
+
+/// Compresses the packets sent to a remote peer, and decompresses the packets received from it
+#[derive(Default)]
+pub(crate) struct PacketCompressor {
+    /// `None` if compression is disabled
+    codec: Option<Box<dyn Codec>>,
+    /// Packets smaller than this are not compressed
+    threshold: usize,
+    buffer: Vec<u8>,
+}
+
+impl std::fmt::Debug for PacketCompressor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PacketCompressor")
+            .field("enabled", &self.codec.is_some())
+            .field("threshold", &self.threshold)
+            .finish()
+    }
+}
+
+impl PacketCompressor {
+    pub(crate) fn new(config: CompressionConfig) -> Self {
+        match config {
+            CompressionConfig::None => Self::default(),
+            #[cfg(feature = "zstd")]
+            CompressionConfig::Zstd { level, threshold } => {
+                Self::with_codec(zstd::Zstd::new(level), threshold)
+            }
+            #[cfg(feature = "lz4")]
+            CompressionConfig::Lz4 { threshold } => {
+                Self::with_codec(lz4::Lz4::default(), threshold)
+            }
+        }
+    }
+
+    fn with_codec(codec: impl Codec + 'static, threshold: usize) -> Self {
+        Self {
+            codec: Some(Box::new(codec)),
+            threshold,
+            buffer: vec![0; MAX_PACKET_SIZE],
+        }
+    }
+
+    /// Return the packet to send: the [`COMPRESSED`] byte followed by the compressed packet,
+    /// or the packet unchanged if compressing it would not make it smaller
+    pub(crate) fn compress(&mut self, packet: Payload) -> Payload {
+        let Some(codec) = self.codec.as_mut() else {
+            return packet;
+        };
+        if packet.is_empty() || packet.len() < self.threshold {
+            return packet;
+        }
+        let max_len = packet.len().min(self.buffer.len()) - 1;
+        match codec.compress(&packet, &mut self.buffer[1..=max_len]) {
+            // only keep the compressed packet if it is strictly smaller
+            Some(size) if size + 1 < packet.len() => {
+                self.buffer[0] = COMPRESSED;
+                self.buffer[..size + 1].to_vec()
+            }
+            _ => packet,
+        }
+    }
+
+    /// Return the packet received from the remote peer, decompressed if needed
+    pub(crate) fn decompress(&mut self, packet: RecvPayload) -> Result<RecvPayload> {
+        if packet.first() != Some(&COMPRESSED) {
+            return Ok(packet);
+        }
+        let Some(codec) = self.codec.as_mut() else {
+            return Err(PacketError::Decompression(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "received a compressed packet but compression is disabled",
+            )));
+        };
+        let size = codec.decompress(&packet[1..], &mut self.buffer)?;
+        Ok(Bytes::copy_from_slice(&self.buffer[..size]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Run-length encoding: pairs of (run length, byte)
+    struct RunLength;
+
+    impl Codec for RunLength {
+        fn compress(&mut self, data: &[u8], out: &mut [u8]) -> Option<usize> {
+            let mut size = 0;
+            for run in data.chunk_by(|a, b| a == b) {
+                for chunk in run.chunks(u8::MAX as usize) {
+                    out.get_mut(size..size + 2)?
+                        .copy_from_slice(&[chunk.len() as u8, chunk[0]]);
+                    size += 2;
+                }
+            }
+            Some(size)
+        }
+
+        fn decompress(&mut self, data: &[u8], out: &mut [u8]) -> std::io::Result<usize> {
+            let mut size = 0;
+            for pair in data.chunks_exact(2) {
+                let len = pair[0] as usize;
+                out[size..size + len].fill(pair[1]);
+                size += len;
+            }
+            Ok(size)
+        }
+    }
+
+    /// A large repetitive packet is compressed, and decompressed to the original packet
+    #[test]
+    fn test_compress_large_packet() {
+        let mut compressor = PacketCompressor::with_codec(RunLength, 100);
+        let packet = [[0u8; 500], [9u8; 500]].concat();
+
+        let compressed = compressor.compress(packet.clone());
+        assert_eq!(compressed[0], COMPRESSED);
+        assert!(compressed.len() < packet.len());
+        assert_eq!(
+            compressor.decompress(Bytes::from(compressed)).unwrap(),
+            packet
+        );
+    }
+
+    /// Packets below the threshold, or that would not get smaller, are sent unchanged
+    #[test]
+    fn test_compression_fallback_to_raw() {
+        let mut compressor = PacketCompressor::with_codec(RunLength, 100);
+
+        // below the threshold
+        let small = vec![0u8; 50];
+        assert_eq!(compressor.compress(small.clone()), small);
+        assert_eq!(
+            compressor.decompress(Bytes::from(small.clone())).unwrap(),
+            small
+        );
+
+        // run-length encoding doubles the size of a packet without repetitions
+        let incompressible: Vec<u8> = (0..200).collect();
+        assert_eq!(compressor.compress(incompressible.clone()), incompressible);
+    }
+
+    /// Compressed packets cannot be read if compression is disabled
+    #[test]
+    fn test_decompress_without_codec() {
+        let mut compressor = PacketCompressor::with_codec(RunLength, 100);
+        let compressed = compressor.compress(vec![0u8; 500]);
+        assert!(PacketCompressor::default()
+            .decompress(Bytes::from(compressed))
+            .is_err());
+    }
+}
@@ -0,0 +1,51 @@
+//! Zstd compression
+
+use crate::packet::compression::Codec;
+use zstd::bulk::{Compressor, Decompressor};
+
+pub(crate) struct Zstd {
+    compressor: Compressor<'static>,
+    decompressor: Decompressor<'static>,
+}
+
+impl Zstd {
+    pub(crate) fn new(level: i32) -> Self {
+        Zstd {
+            compressor: Compressor::new(level).unwrap(),
+            decompressor: Decompressor::new().unwrap(),
+        }
+    }
+}
+
+impl Codec for Zstd {
+    fn compress(&mut self, data: &[u8], out: &mut [u8]) -> Option<usize> {
+        // fails if the output buffer is too small
+        self.compressor.compress_to_buffer(data, out).ok()
+    }
+
+    fn decompress(&mut self, data: &[u8], out: &mut [u8]) -> std::io::Result<usize> {
+        self.decompressor.decompress_to_buffer(data, out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use crate::packet::compression::{CompressionConfig, PacketCompressor};
+
+    #[test]
+    fn test_compression() {
+        let mut compressor = PacketCompressor::new(CompressionConfig::Zstd {
+            level: 0,
+            threshold: 100,
+        });
+        let packet = [b"hello world".as_slice(); 50].concat();
+        let compressed = compressor.compress(packet.clone());
+        assert!(compressed.len() < packet.len());
+        assert_eq!(
+            compressor.decompress(Bytes::from(compressed)).unwrap(),
+            packet
+        );
+    }
+}
@@ -10,6 +10,10 @@ pub enum PacketError {
     Serialization(#[from] SerializationError),
     #[error("channel was not found")]
     ChannelNotFound,
+    #[error("messages cannot be cancelled on ordered reliable channels")]
+    CancelOnOrderedChannel,
     #[error("receiver channel error: {0}")]
     ChannelReceiveError(#[from] ChannelReceiveError),
+    #[error("could not decompress the packet: {0}")]
+    Decompression(#[from] std::io::Error),
 }
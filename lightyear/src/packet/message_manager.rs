@@ -6,9 +6,9 @@ use tracing::trace;
 #[cfg(feature = "trace")]
 use tracing::{instrument, Level};
 
-use crate::channel::builder::ChannelContainer;
+use crate::channel::builder::{ChannelContainer, ChannelMode};
 use crate::channel::receivers::ChannelReceive;
-use crate::channel::senders::ChannelSend;
+use crate::channel::senders::{ChannelSend, ChannelSender};
 #[cfg(feature = "trace")]
 use crate::channel::stats::send::ChannelSendStats;
 use crate::packet::error::PacketError;
@@ -44,7 +44,7 @@ pub struct MessageManager {
     pub(crate) writer: Writer,
     /// Handles sending/receiving packets (including acks)
     packet_manager: PacketBuilder,
-    priority_manager: PriorityManager,
+    pub(crate) priority_manager: PriorityManager,
     pub(crate) channels: HashMap<ChannelKind, ChannelContainer>,
     pub(crate) channel_registry: ChannelRegistry,
     // TODO: can use Vec<ChannelKind, Vec<MessageId>> to be more efficient?
@@ -116,6 +116,85 @@ impl MessageManager {
         }
     }
 
+    /// Returns the list of reliable channels whose window of unacked messages became full
+    /// since the last time this function was called
+    pub(crate) fn take_reliable_window_full_events(&mut self) -> Vec<ChannelKind> {
+        self.channels
+            .iter_mut()
+            .filter_map(|(channel_kind, channel)| match &mut channel.sender {
+                ChannelSender::Reliable(sender) => {
+                    sender.take_window_full_event().then_some(*channel_kind)
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns true if any reliable channel has had its window full for longer than the timeout
+    /// of its [`WindowFullPolicy::DisconnectOnWindowFull`](crate::channel::builder::WindowFullPolicy::DisconnectOnWindowFull) policy
+    ///
+    /// Each timeout is only reported once.
+    pub(crate) fn take_reliable_window_full_timeout(&mut self) -> bool {
+        self.channels
+            .values_mut()
+            .map(|channel| match &mut channel.sender {
+                ChannelSender::Reliable(sender) => sender.take_window_full_timeout(),
+                _ => false,
+            })
+            // don't short-circuit, so that every timed-out channel gets reset
+            .fold(false, |timed_out, channel_timed_out| {
+                timed_out | channel_timed_out
+            })
+    }
+
+    /// Returns true if any reliable channel has messages that have not been acked yet
+    pub(crate) fn has_pending_reliable_messages(&self) -> bool {
+        self.channels.values().any(|channel| match &channel.sender {
+            ChannelSender::Reliable(sender) => sender.pending_messages().next().is_some(),
+            _ => false,
+        })
+    }
+
+    /// Ids of the messages sent on the channel that have not been acked yet, oldest first
+    ///
+    /// Only reliable channels keep track of the unacked messages; other channels return no messages.
+    pub(crate) fn pending_messages(
+        &self,
+        channel_kind: ChannelKind,
+    ) -> Result<Vec<MessageId>, PacketError> {
+        let channel = self
+            .channels
+            .get(&channel_kind)
+            .ok_or(PacketError::ChannelNotFound)?;
+        Ok(match &channel.sender {
+            ChannelSender::Reliable(sender) => sender.pending_messages().collect(),
+            _ => vec![],
+        })
+    }
+
+    /// Cancel a message sent on a reliable channel that has not been acked yet, so that it is not resent.
+    ///
+    /// Returns false if the message was not pending anymore.
+    /// Messages cannot be cancelled on [`ChannelMode::OrderedReliable`] channels, because the
+    /// remote peer would wait for the cancelled message forever.
+    pub(crate) fn cancel_message(
+        &mut self,
+        channel_kind: ChannelKind,
+        message_id: MessageId,
+    ) -> Result<bool, PacketError> {
+        let channel = self
+            .channels
+            .get_mut(&channel_kind)
+            .ok_or(PacketError::ChannelNotFound)?;
+        if matches!(channel.setting.mode, ChannelMode::OrderedReliable(_)) {
+            return Err(PacketError::CancelOnOrderedChannel);
+        }
+        Ok(match &mut channel.sender {
+            ChannelSender::Reliable(sender) => sender.cancel_message(message_id),
+            _ => false,
+        })
+    }
+
     /// Buffer a message to be sent on this connection
     /// Returns the message id associated with the message, if there is one
     pub fn buffer_send(
@@ -438,6 +517,7 @@ mod tests {
     use std::collections::HashMap;
 
     use bevy::prelude::default;
+    use bevy::utils::Duration;
 
     use crate::packet::message::MessageId;
     use crate::packet::packet::FRAGMENT_SIZE;
@@ -690,4 +770,68 @@ mod tests {
         assert_eq!(update_acks_tracker.try_recv().unwrap(), message_id);
         Ok(())
     }
+
+    #[test]
+    /// A reliable message that is cancelled before being acked is never delivered nor resent
+    fn test_cancel_reliable_message() -> Result<(), PacketError> {
+        let mut channel_registry = ChannelRegistry::default();
+        channel_registry.add_channel::<Channel1>(ChannelSettings {
+            mode: ChannelMode::UnorderedReliable(ReliableSettings::default()),
+            ..default()
+        });
+        channel_registry.add_channel::<Channel2>(ChannelSettings {
+            mode: ChannelMode::OrderedReliable(ReliableSettings::default()),
+            ..default()
+        });
+        let mut client_message_manager =
+            MessageManager::new(&channel_registry, 1.5, PriorityConfig::default());
+        let mut server_message_manager =
+            MessageManager::new(&channel_registry, 1.5, PriorityConfig::default());
+        let channel_kind = ChannelKind::of::<Channel1>();
+
+        // the message is cancelled before being sent
+        let message_id = client_message_manager
+            .buffer_send(vec![0].into(), channel_kind)?
+            .unwrap();
+        assert_eq!(
+            client_message_manager.pending_messages(channel_kind)?,
+            vec![message_id]
+        );
+        assert!(client_message_manager.cancel_message(channel_kind, message_id)?);
+        assert!(client_message_manager
+            .pending_messages(channel_kind)?
+            .is_empty());
+        assert!(client_message_manager.send_packets(Tick(0))?.is_empty());
+
+        // the message is sent, then cancelled before being acked: the lost packet is not resent
+        let message_id = client_message_manager
+            .buffer_send(vec![1].into(), channel_kind)?
+            .unwrap();
+        assert_eq!(client_message_manager.send_packets(Tick(0))?.len(), 1);
+        assert!(client_message_manager.cancel_message(channel_kind, message_id)?);
+        let mut time_manager = TimeManager::default();
+        time_manager.update(Duration::from_secs(1));
+        client_message_manager.update(
+            &time_manager,
+            &PingManager::new(PingConfig::default()),
+            &TickManager::from_config(TickConfig::new(Duration::from_secs(1))),
+        );
+        for payload in client_message_manager.send_packets(Tick(1))? {
+            server_message_manager.recv_packet(payload.into())?;
+        }
+        let data = MessageManager::collect_messages(server_message_manager.read_messages());
+        assert!(data.is_empty());
+        assert!(!client_message_manager.cancel_message(channel_kind, message_id)?);
+
+        // messages cannot be cancelled on ordered channels
+        let ordered_channel_kind = ChannelKind::of::<Channel2>();
+        let message_id = client_message_manager
+            .buffer_send(vec![2].into(), ordered_channel_kind)?
+            .unwrap();
+        assert!(matches!(
+            client_message_manager.cancel_message(ordered_channel_kind, message_id),
+            Err(PacketError::CancelOnOrderedChannel)
+        ));
+        Ok(())
+    }
 }
@@ -19,6 +19,9 @@ Packets that are over the maximum packet size will be fragmented into multiple [
 [`FragmentData`]: message::FragmentData
 */
 
+/// Compresses the packets above a size threshold
+pub(crate) mod compression;
+
 /// Manages the [`PacketHeader`](header::PacketHeader) which includes important packet information
 pub(crate) mod header;
 
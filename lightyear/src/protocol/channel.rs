@@ -5,10 +5,13 @@ use std::any::TypeId;
 use std::collections::HashMap;
 
 use crate::channel::builder::{
-    AuthorityChannel, Channel, ChannelBuilder, ChannelSettings, PongChannel,
+    AuthorityChannel, Channel, ChannelBuilder, ChannelSettings, ComponentSubscriptionChannel,
+    EntityReadyChannel, InitialReplicationChannel, IntentChannel, PongChannel, ShutdownChannel,
+    TickEventChannel,
 };
 use crate::channel::builder::{
-    ChannelContainer, EntityActionsChannel, EntityUpdatesChannel, InputChannel, PingChannel,
+    ChannelContainer, EntityActionsChannel, EntityUpdatesChannel, InputChannel, LockstepChannel,
+    PingChannel,
 };
 use crate::prelude::{ChannelMode, ReliableSettings};
 use crate::protocol::registry::{NetId, TypeKind, TypeMapper};
@@ -110,12 +113,48 @@ impl ChannelRegistry {
             // we always want to include the inputs in the packet
             priority: f32::INFINITY,
         });
+        registry.add_channel::<LockstepChannel>(ChannelSettings {
+            mode: ChannelMode::OrderedReliable(ReliableSettings::default()),
+            send_frequency: Duration::default(),
+            // the simulation of every client is waiting for the lockstep inputs
+            priority: f32::INFINITY,
+        });
         registry.add_channel::<AuthorityChannel>(ChannelSettings {
             mode: ChannelMode::OrderedReliable(ReliableSettings::default()),
             send_frequency: Duration::default(),
             // we want to send the authority transfers as soon as possible
             priority: 10.0,
         });
+        registry.add_channel::<EntityReadyChannel>(ChannelSettings {
+            mode: ChannelMode::UnorderedReliable(ReliableSettings::default()),
+            send_frequency: Duration::default(),
+            priority: 10.0,
+        });
+        registry.add_channel::<InitialReplicationChannel>(ChannelSettings {
+            mode: ChannelMode::UnorderedReliable(ReliableSettings::default()),
+            send_frequency: Duration::default(),
+            priority: 10.0,
+        });
+        registry.add_channel::<IntentChannel>(ChannelSettings {
+            mode: ChannelMode::OrderedReliable(ReliableSettings::default()),
+            send_frequency: Duration::default(),
+            priority: 10.0,
+        });
+        registry.add_channel::<TickEventChannel>(ChannelSettings {
+            mode: ChannelMode::UnorderedReliable(ReliableSettings::default()),
+            send_frequency: Duration::default(),
+            priority: 10.0,
+        });
+        registry.add_channel::<ComponentSubscriptionChannel>(ChannelSettings {
+            mode: ChannelMode::OrderedReliable(ReliableSettings::default()),
+            send_frequency: Duration::default(),
+            priority: 10.0,
+        });
+        registry.add_channel::<ShutdownChannel>(ChannelSettings {
+            mode: ChannelMode::UnorderedReliable(ReliableSettings::default()),
+            send_frequency: Duration::default(),
+            priority: 10.0,
+        });
         registry
     }
 
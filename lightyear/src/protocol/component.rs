@@ -4,7 +4,7 @@ use bevy::prelude::{
     App, Component, Entity, EntityWorldMut, Mut, Reflect, Resource, TypePath, World,
 };
 use bevy::ptr::{OwningPtr, Ptr};
-use bevy::utils::{hashbrown, HashMap};
+use bevy::utils::{hashbrown, HashMap, HashSet};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use std::alloc::Layout;
@@ -13,11 +13,13 @@ use std::fmt::Debug;
 use std::hash::Hash;
 use std::ops::{Add, Mul};
 use std::ptr::NonNull;
+use std::time::Duration;
 
 use tracing::{debug, error, trace};
 
 use crate::client::components::ComponentSyncMode;
 use crate::client::config::ClientConfig;
+use crate::client::display::add_display_smoothing_systems;
 use crate::client::interpolation::{add_interpolation_systems, add_prepare_interpolation_systems};
 use crate::client::prediction::plugin::{
     add_non_networked_rollback_systems, add_prediction_systems, add_resource_rollback_systems,
@@ -27,12 +29,18 @@ use crate::prelude::server::ServerConfig;
 use crate::prelude::{ChannelDirection, Message, Tick};
 use crate::protocol::delta::ErasedDeltaFns;
 use crate::protocol::registry::{NetId, TypeKind, TypeMapper};
-use crate::protocol::serialize::{ErasedSerializeFns, SerializeFns};
+use crate::protocol::serialize::{
+    check_round_trip, ErasedRoundTripCheckFn, ErasedSerializeFns, SerializeFns,
+};
 use crate::serialize::reader::Reader;
+use crate::serialize::writer::Writer;
 use crate::serialize::SerializationError;
+use crate::server::interpolation_snapshots::add_interpolation_snapshot_systems;
+use crate::server::lag_compensation::add_lag_compensation_systems;
 use crate::shared::events::connection::ConnectionEvents;
 use crate::shared::replication::delta::{DeltaMessage, Diffable};
 use crate::shared::replication::entity_map::{EntityMap, ReceiveEntityMap};
+use crate::shared::replication::origin::{ErasedOriginRebaseFns, OriginRebase};
 
 pub type ComponentNetId = NetId;
 
@@ -86,6 +94,9 @@ pub enum ComponentError {
 /// Provided that your type implements [`MapEntities`], you can extend the protocol to support this behaviour, by
 /// calling the [`add_map_entities`](ComponentRegistration::add_map_entities) method.
 ///
+/// If the component references an entity that hasn't been replicated yet, the reference is mapped to
+/// `Entity::PLACEHOLDER` and updated once that entity gets replicated.
+///
 /// #### Prediction
 /// When client-prediction is enabled, we create two distinct entities on the client when the server replicates an entity: a Confirmed entity and a Predicted entity.
 /// The Confirmed entity will just get updated when the client receives the server updates, while the Predicted entity will be updated by the client's prediction system.
@@ -113,12 +124,17 @@ pub enum ComponentError {
 /// You can do this by calling the [`add_interpolation`](ComponentRegistration::add_interpolation) method.
 /// You will have to provide a [`ComponentSyncMode`] that defines the behaviour of the interpolation system.
 ///
-/// You will also need to provide an interpolation function that will be used to interpolate between two states.
+/// You should also provide an interpolation function that will be used to interpolate between two states.
 /// If your component implements the [`Linear`] trait, you can use the [`add_linear_interpolation_fn`](ComponentRegistration::add_linear_interpolation_fn) method,
 /// which means that we will interpolate using linear interpolation.
 ///
 /// You can also use your own interpolation function by using the [`add_interpolation_fn`](ComponentRegistration::add_interpolation_fn) method.
 ///
+/// Components that should not be interpolated (for example a discrete animation state) can use the
+/// [`add_snap_interpolation_fn`](ComponentRegistration::add_snap_interpolation_fn) method: the component keeps its previous value
+/// and snaps to the new value when the interpolation reaches the tick of the server update.
+/// This is also what happens if no interpolation function is provided.
+///
 /// ```rust
 /// use bevy::prelude::*;
 /// use lightyear::prelude::*;
@@ -148,7 +164,12 @@ pub struct ComponentRegistry {
     interpolation_map: HashMap<ComponentKind, InterpolationMetadata>,
     prediction_map: HashMap<ComponentKind, PredictionMetadata>,
     serialize_fns_map: HashMap<ComponentKind, ErasedSerializeFns>,
+    /// Checks that the custom serialization functions of a component round-trip (in debug builds)
+    round_trip_check_map: HashMap<ComponentKind, ErasedRoundTripCheckFn>,
     delta_fns_map: HashMap<ComponentKind, ErasedDeltaFns>,
+    origin_rebase_fns_map: HashMap<ComponentKind, ErasedOriginRebaseFns>,
+    max_send_rate_map: HashMap<ComponentKind, Duration>,
+    server_authoritative: HashSet<ComponentKind>,
     pub(crate) kind_map: TypeMapper<ComponentKind>,
 }
 
@@ -280,6 +301,15 @@ pub type LerpFn<C> = fn(start: &C, other: &C, t: f32) -> C;
 /// Defaults to PartialEq::ne
 type ShouldRollbackFn<C> = fn(this: &C, that: &C) -> bool;
 
+/// Interpolation function that doesn't interpolate: it returns `start` until `t` reaches 1.0, and then `other`
+pub(crate) fn snap<C: Clone>(start: &C, other: &C, t: f32) -> C {
+    if t < 1.0 {
+        start.clone()
+    } else {
+        other.clone()
+    }
+}
+
 pub trait Linear {
     fn lerp(start: &Self, other: &Self, t: f32) -> Self;
 }
@@ -316,6 +346,7 @@ impl ComponentRegistry {
 
     /// Check that the protocol is correct:
     /// - emits warnings for every component that has prediction/interpolation metadata but wasn't registered
+    /// - logs the components registered for interpolation without an interpolation function (they will snap)
     pub fn check(&self) {
         for component_kind in self.prediction_map.keys() {
             if !self.serialize_fns_map.contains_key(component_kind) {
@@ -334,7 +365,7 @@ impl ComponentRegistry {
                     .get(component_kind)
                     .unwrap()
                     .type_name;
-                panic!("The Component {name:?} was registered for interpolation with ComponentSyncMode::FULL but no interpolation function was provided!");
+                debug!("The Component {name:?} was registered for interpolation with ComponentSyncMode::FULL but no interpolation function was provided: it will snap to the new value instead of being interpolated");
             }
         }
     }
@@ -362,6 +393,28 @@ impl ComponentRegistry {
             ErasedSerializeFns::new_custom_serde::<C>(serialize_fns),
         );
     }
+
+    /// Replace the serialization functions of a component that is already registered.
+    ///
+    /// In debug builds, every serialized value is checked to round-trip through the new functions.
+    pub(crate) fn set_custom_serde<C: Component + Message + PartialEq>(
+        &mut self,
+        serialize_fns: SerializeFns<C>,
+    ) {
+        let kind = ComponentKind::of::<C>();
+        let erased_fns = self.serialize_fns_map.get_mut(&kind).unwrap_or_else(|| {
+            panic!(
+                "Component {} is not part of the protocol",
+                std::any::type_name::<C>()
+            )
+        });
+        // keep the entity-mapping functions that were already registered
+        let custom_fns = ErasedSerializeFns::new_custom_serde::<C>(serialize_fns);
+        erased_fns.serialize = custom_fns.serialize;
+        erased_fns.deserialize = custom_fns.deserialize;
+        self.round_trip_check_map
+            .insert(kind, check_round_trip::<C>);
+    }
 }
 
 mod serialize {
@@ -370,6 +423,9 @@ mod serialize {
     use crate::serialize::writer::Writer;
     use crate::serialize::ToBytes;
     use crate::shared::replication::entity_map::SendEntityMap;
+    use crate::shared::replication::origin::{read_origin, write_origin};
+    use bevy::math::Vec3;
+    use bevy::ptr::PtrMut;
 
     impl ComponentRegistry {
         pub(crate) fn try_add_map_entities<C: Clone + MapEntities + 'static>(&mut self) {
@@ -409,6 +465,22 @@ mod serialize {
             erased_fns.map_entities.is_some()
         }
 
+        pub(crate) fn add_origin_rebasing<C: Message + OriginRebase>(&mut self) {
+            let kind = ComponentKind::of::<C>();
+            assert!(
+                self.serialize_fns_map.contains_key(&kind),
+                "Component {} is not part of the protocol",
+                std::any::type_name::<C>()
+            );
+            self.origin_rebase_fns_map
+                .insert(kind, ErasedOriginRebaseFns::new::<C>());
+        }
+
+        /// Returns true if the component is serialized relative to a per-client origin
+        pub(crate) fn erased_is_origin_rebased(&self, kind: ComponentKind) -> bool {
+            self.origin_rebase_fns_map.contains_key(&kind)
+        }
+
         pub(crate) fn serialize<C: Message>(
             &self,
             component: &mut C,
@@ -423,6 +495,13 @@ mod serialize {
             let net_id = self.kind_map.net_id(&kind).unwrap();
 
             net_id.to_bytes(writer)?;
+            if self.erased_is_origin_rebased(kind) {
+                write_origin(writer, None)?;
+            }
+            // SAFETY: the ErasedFns corresponds to type C
+            unsafe {
+                self.check_round_trip(kind, erased_fns, Ptr::from(&*component));
+            }
             // SAFETY: the ErasedFns corresponds to type C
             unsafe {
                 erased_fns.serialize(component, writer, entity_map)?;
@@ -430,6 +509,23 @@ mod serialize {
             Ok(())
         }
 
+        /// In debug builds, panic if the custom serialization functions of the component
+        /// do not round-trip the value.
+        ///
+        /// SAFETY: the Ptr and the ErasedSerializeFns must correspond to the ComponentKind
+        unsafe fn check_round_trip(
+            &self,
+            kind: ComponentKind,
+            erased_fns: &ErasedSerializeFns,
+            component: Ptr,
+        ) {
+            if cfg!(debug_assertions) {
+                if let Some(check) = self.round_trip_check_map.get(&kind) {
+                    check(erased_fns, component);
+                }
+            }
+        }
+
         /// SAFETY: the Ptr must correspond to the correct ComponentKind
         pub(crate) fn erased_serialize(
             &self,
@@ -437,6 +533,21 @@ mod serialize {
             writer: &mut Writer,
             kind: ComponentKind,
             entity_map: &mut SendEntityMap,
+        ) -> Result<(), ComponentError> {
+            self.erased_serialize_with_origin(component, writer, kind, entity_map, None)
+        }
+
+        /// Serialize the component; if the component is registered for origin rebasing,
+        /// the value is serialized relative to `origin`.
+        ///
+        /// SAFETY: the Ptr must correspond to the correct ComponentKind
+        pub(crate) fn erased_serialize_with_origin(
+            &self,
+            component: Ptr,
+            writer: &mut Writer,
+            kind: ComponentKind,
+            entity_map: &mut SendEntityMap,
+            origin: Option<Vec3>,
         ) -> Result<(), ComponentError> {
             let erased_fns = self
                 .serialize_fns_map
@@ -445,6 +556,22 @@ mod serialize {
             let net_id = self.kind_map.net_id(&kind).unwrap();
             net_id.to_bytes(writer)?;
             // SAFETY: the ErasedSerializeFns corresponds to type C
+            unsafe {
+                self.check_round_trip(kind, erased_fns, component);
+            }
+            if let Some(rebase_fns) = self.origin_rebase_fns_map.get(&kind) {
+                write_origin(writer, origin)?;
+                if let Some(origin) = origin {
+                    // SAFETY: the ErasedOriginRebaseFns and ErasedSerializeFns correspond to type C
+                    unsafe {
+                        (rebase_fns.serialize_rebased)(
+                            erased_fns, component, origin, writer, entity_map,
+                        )?;
+                    }
+                    return Ok(());
+                }
+            }
+            // SAFETY: the ErasedSerializeFns corresponds to type C
             unsafe {
                 (erased_fns.erased_serialize)(erased_fns, component, writer, entity_map)?;
             }
@@ -462,8 +589,19 @@ mod serialize {
                 .serialize_fns_map
                 .get(&kind)
                 .ok_or(ComponentError::MissingSerializationFns)?;
+            let Some(rebase_fns) = self.origin_rebase_fns_map.get(&kind) else {
+                // SAFETY: the ErasedFns corresponds to type C
+                return unsafe { erased_fns.deserialize(reader, entity_map) }.map_err(Into::into);
+            };
+            let origin = read_origin(reader)?;
             // SAFETY: the ErasedFns corresponds to type C
-            unsafe { erased_fns.deserialize(reader, entity_map) }.map_err(Into::into)
+            let mut component: C = unsafe { erased_fns.deserialize(reader, entity_map) }?;
+            if let Some(origin) = origin {
+                // add back the origin that the value was rebased around
+                // SAFETY: the ErasedOriginRebaseFns corresponds to type C
+                unsafe { (rebase_fns.restore)(PtrMut::from(&mut component), origin) };
+            }
+            Ok(component)
         }
 
         pub(crate) fn deserialize<C: Component>(
@@ -720,14 +858,19 @@ mod interpolation {
                     metadata.interpolation_mode
                 })
         }
-        pub(crate) fn interpolate<C: Component>(&self, start: &C, end: &C, t: f32) -> C {
+        /// Interpolate between `start` and `end` with the interpolation function of the component.
+        ///
+        /// If no interpolation function was registered, the component snaps from `start` to `end`.
+        pub(crate) fn interpolate<C: Component + Clone>(&self, start: &C, end: &C, t: f32) -> C {
             let kind = ComponentKind::of::<C>();
             let interpolation_metadata = self
                 .interpolation_map
                 .get(&kind)
                 .expect("the component is not part of the protocol");
-            let interpolation_fn: LerpFn<C> =
-                unsafe { std::mem::transmute(interpolation_metadata.interpolation.unwrap()) };
+            let Some(interpolation_fn) = interpolation_metadata.interpolation else {
+                return snap(start, end, t);
+            };
+            let interpolation_fn: LerpFn<C> = unsafe { std::mem::transmute(interpolation_fn) };
             interpolation_fn(start, end, t)
         }
     }
@@ -750,6 +893,52 @@ mod replication {
                 .map(|metadata| metadata.direction)
         }
 
+        pub(crate) fn set_max_send_rate<C: Component>(&mut self, interval: Duration) {
+            let kind = ComponentKind::of::<C>();
+            assert!(
+                self.serialize_fns_map.contains_key(&kind),
+                "Component {} is not part of the protocol",
+                std::any::type_name::<C>()
+            );
+            self.max_send_rate_map.insert(kind, interval);
+        }
+
+        /// Returns the minimum interval between two updates of the component, if any
+        pub(crate) fn max_send_rate(&self, kind: ComponentKind) -> Option<Duration> {
+            self.max_send_rate_map.get(&kind).copied()
+        }
+
+        pub(crate) fn set_server_authoritative<C: Component>(&mut self) {
+            let kind = ComponentKind::of::<C>();
+            assert!(
+                self.serialize_fns_map.contains_key(&kind),
+                "Component {} is not part of the protocol",
+                std::any::type_name::<C>()
+            );
+            self.server_authoritative.insert(kind);
+        }
+
+        /// Returns true if the component is always replicated from the server,
+        /// regardless of which peer has authority over the entity
+        pub(crate) fn is_server_authoritative(&self, kind: ComponentKind) -> bool {
+            self.server_authoritative.contains(&kind)
+        }
+
+        /// Same as [`Self::is_server_authoritative`], but from the net id of the component
+        pub(crate) fn is_server_authoritative_net_id(&self, net_id: ComponentNetId) -> bool {
+            self.kind_map
+                .kind(net_id)
+                .is_some_and(|kind| self.is_server_authoritative(*kind))
+        }
+
+        /// Same as [`Self::is_server_authoritative`], but from the serialized component
+        /// (which starts with the net id of the component)
+        pub(crate) fn is_server_authoritative_raw(&self, component_bytes: &Bytes) -> bool {
+            let mut reader = Reader::from(component_bytes.clone());
+            ComponentNetId::from_bytes(&mut reader)
+                .is_ok_and(|net_id| self.is_server_authoritative_net_id(net_id))
+        }
+
         pub(crate) fn set_replication_fns<C: Component + PartialEq>(
             &mut self,
             world: &mut World,
@@ -776,6 +965,10 @@ mod replication {
         ///
         /// This method will insert all the components simultaneously.
         /// If any component already existed on the entity, it will be updated instead of inserted.
+        ///
+        /// Components that cannot be read are skipped and reported via a
+        /// [`ComponentDeserializationErrorEvent`](crate::shared::events::components::ComponentDeserializationErrorEvent);
+        /// the other components are still inserted.
         pub(crate) fn batch_insert(
             &mut self,
             component_bytes: Vec<Bytes>,
@@ -784,31 +977,30 @@ mod replication {
             entity_map: &mut ReceiveEntityMap,
             events: &mut ConnectionEvents,
         ) -> Result<(), ComponentError> {
-            component_bytes.into_iter().try_for_each(|b| {
+            for b in component_bytes {
                 // TODO: reuse a single reader that reads through the entire message ?
-                let mut reader = Reader::from(b);
-                let net_id =
-                    ComponentNetId::from_bytes(&mut reader).map_err(SerializationError::from)?;
-                let kind = self
-                    .kind_map
-                    .kind(net_id)
-                    .ok_or(ComponentError::NotRegistered)?;
-                let replication_metadata = self
-                    .replication_map
-                    .get(kind)
-                    .ok_or(ComponentError::MissingReplicationFns)?;
+                let mut reader = Reader::from(b.clone());
+                entity_map.take_unmapped();
                 // buffer the component data into the temporary buffer so that
                 // all components can be inserted at once
-                (replication_metadata.buffer_insert_fn)(
-                    self,
+                match self.buffer_insert_raw(
                     &mut reader,
                     tick,
                     entity_world_mut,
                     entity_map,
                     events,
-                )?;
-                Ok::<(), ComponentError>(())
-            })?;
+                ) {
+                    Ok(kind) => entity_map.track_pending(entity_world_mut.id(), kind, tick, b),
+                    Err((kind, e)) => {
+                        error!(?e, "could not insert the component to the entity");
+                        events.push_component_error(
+                            entity_world_mut.id(),
+                            kind.and_then(|k| self.serialize_fns_map.get(&k).map(|f| f.type_name)),
+                            &e,
+                        );
+                    }
+                }
+            }
 
             // TODO: sort by component id for cache efficiency!
             //  maybe it's not needed because on the server side we iterate through archetypes in a deterministic order?
@@ -822,6 +1014,44 @@ mod replication {
             Ok(())
         }
 
+        /// Read a single component and buffer it in the temporary buffer.
+        ///
+        /// Returns the kind of the component. On error, also returns the kind of the component if it
+        /// could be identified.
+        fn buffer_insert_raw(
+            &mut self,
+            reader: &mut Reader,
+            tick: Tick,
+            entity_world_mut: &mut EntityWorldMut,
+            entity_map: &mut ReceiveEntityMap,
+            events: &mut ConnectionEvents,
+        ) -> Result<ComponentKind, (Option<ComponentKind>, ComponentError)> {
+            let net_id = ComponentNetId::from_bytes(reader).map_err(|e| (None, e.into()))?;
+            let kind = *self
+                .kind_map
+                .kind(net_id)
+                .ok_or((None, ComponentError::NotRegistered))?;
+            let replication_metadata = self
+                .replication_map
+                .get(&kind)
+                .ok_or((Some(kind), ComponentError::MissingReplicationFns))?;
+            (replication_metadata.buffer_insert_fn)(
+                self,
+                reader,
+                tick,
+                entity_world_mut,
+                entity_map,
+                events,
+            )
+            .map(|_| kind)
+            .map_err(|e| (Some(kind), e))
+        }
+
+        /// Update a single component on the entity.
+        ///
+        /// If the component cannot be read, it is skipped and reported via a
+        /// [`ComponentDeserializationErrorEvent`](crate::shared::events::components::ComponentDeserializationErrorEvent).
+        ///
         /// SAFETY: the ReadWordBuffer must contain bytes corresponding to the correct component type
         pub(crate) fn raw_write(
             &self,
@@ -831,17 +1061,60 @@ mod replication {
             entity_map: &mut ReceiveEntityMap,
             events: &mut ConnectionEvents,
         ) -> Result<ComponentKind, ComponentError> {
-            let net_id = ComponentNetId::from_bytes(reader).map_err(SerializationError::from)?;
-            let kind = self
+            let bytes = reader.clone().consume();
+            entity_map.take_unmapped();
+            let net_id = ComponentNetId::from_bytes(reader)
+                .map_err(ComponentError::from)
+                .inspect_err(|e| events.push_component_error(entity_world_mut.id(), None, e))?;
+            let kind = *self
                 .kind_map
                 .kind(net_id)
-                .ok_or(ComponentError::NotRegistered)?;
+                .ok_or(ComponentError::NotRegistered)
+                .inspect_err(|e| events.push_component_error(entity_world_mut.id(), None, e))?;
             let replication_metadata = self
                 .replication_map
-                .get(kind)
-                .ok_or(ComponentError::MissingReplicationFns)?;
-            (replication_metadata.write)(self, reader, tick, entity_world_mut, entity_map, events)?;
-            Ok(*kind)
+                .get(&kind)
+                .ok_or(ComponentError::MissingReplicationFns)
+                .inspect_err(|e| {
+                    events.push_component_error(entity_world_mut.id(), Some(self.name(kind)), e)
+                })?;
+            (replication_metadata.write)(self, reader, tick, entity_world_mut, entity_map, events)
+                .inspect_err(|e| {
+                    events.push_component_error(entity_world_mut.id(), Some(self.name(kind)), e)
+                })?;
+            entity_map.track_pending(entity_world_mut.id(), kind, tick, bytes);
+            Ok(kind)
+        }
+
+        /// Write again the components that referenced remote entities that were not replicated
+        /// yet, so that their references are mapped to the entities that have been replicated since.
+        ///
+        /// Components that still contain unmapped entities stay pending.
+        pub(crate) fn apply_pending_entity_mappings(
+            &self,
+            world: &mut World,
+            entity_map: &mut ReceiveEntityMap,
+            events: &mut ConnectionEvents,
+        ) {
+            for ((local_entity, _), (tick, bytes)) in std::mem::take(&mut entity_map.pending) {
+                let Ok(mut entity_world_mut) = world.get_entity_mut(local_entity) else {
+                    continue;
+                };
+                let _ = self
+                    .raw_write(
+                        &mut Reader::from(bytes),
+                        &mut entity_world_mut,
+                        tick,
+                        entity_map,
+                        events,
+                    )
+                    .inspect_err(|e| {
+                        error!(
+                            "could not write the pending component to the entity: {:?}",
+                            e
+                        )
+                    });
+            }
         }
 
         /// Method that buffers a pointer to the component data that will be inserted
@@ -950,9 +1223,12 @@ mod replication {
             net_id: ComponentNetId,
             entity_world_mut: &mut EntityWorldMut,
             tick: Tick,
+            entity_map: &mut ReceiveEntityMap,
             events: &mut ConnectionEvents,
         ) {
             let kind = self.kind_map.kind(net_id).expect("unknown component kind");
+            // the removed component must not be written again once its entities get mapped
+            entity_map.pending.remove(&(entity_world_mut.id(), *kind));
             let replication_metadata = self
                 .replication_map
                 .get(kind)
@@ -1337,6 +1613,9 @@ pub trait AppComponentExt {
     /// Add a `Interpolation` behaviour to this component.
     fn add_interpolation_fn<C: SyncComponent>(&mut self, interpolation_fn: LerpFn<C>);
 
+    /// Add a `Interpolation` behaviour to this component that snaps to the new value instead of interpolating.
+    fn add_snap_interpolation_fn<C: SyncComponent>(&mut self);
+
     /// Enable delta compression when serializing this component
     fn add_delta_compression<C: Component + PartialEq + Diffable>(&mut self)
     where
@@ -1349,6 +1628,29 @@ pub struct ComponentRegistration<'a, C> {
 }
 
 impl<C> ComponentRegistration<'_, C> {
+    /// Use custom functions to serialize and deserialize the component, instead of the
+    /// derived `serde` implementation. This can be used to bitpack the component.
+    ///
+    /// The two functions must be symmetric; in debug builds, every replicated value is
+    /// checked to round-trip and a mismatch panics. (Use
+    /// [`register_component_custom_serde`](AppComponentExt::register_component_custom_serde)
+    /// for lossy functions, such as quantization)
+    pub fn with_serde(
+        self,
+        serialize: fn(&C, &mut Writer) -> Result<(), SerializationError>,
+        deserialize: fn(&mut Reader) -> Result<C, SerializationError>,
+    ) -> Self
+    where
+        C: Component + Message + PartialEq,
+    {
+        let mut registry = self.app.world_mut().resource_mut::<ComponentRegistry>();
+        registry.set_custom_serde::<C>(SerializeFns {
+            serialize,
+            deserialize,
+        });
+        self
+    }
+
     /// Specify that the component contains entities which should be mapped from the remote world to the local world
     /// upon deserialization
     pub fn add_map_entities(self) -> Self
@@ -1438,6 +1740,33 @@ impl<C> ComponentRegistration<'_, C> {
         self
     }
 
+    /// Add a `Interpolation` behaviour to this component that snaps to the new value instead of interpolating.
+    ///
+    /// The interpolated component keeps its previous value until the interpolation reaches the tick of the next
+    /// server update. Use this for discrete components that cannot be interpolated.
+    pub fn add_snap_interpolation_fn(self) -> Self
+    where
+        C: SyncComponent,
+    {
+        self.app.add_snap_interpolation_fn::<C>();
+        self
+    }
+
+    /// Add a [`DisplayValue<C>`](crate::prelude::client::DisplayValue) component on the client that moves smoothly
+    /// towards the authoritative value of the component replicated from the server.
+    ///
+    /// After `1 / rate` seconds, ~63% of the gap between the display value and the authoritative value is closed.
+    pub fn add_display_smoothing(self, rate: f32) -> Self
+    where
+        C: SyncComponent + Linear,
+    {
+        let is_client = self.app.world().get_resource::<ClientConfig>().is_some();
+        if is_client {
+            add_display_smoothing_systems::<C>(self.app, rate);
+        }
+        self
+    }
+
     /// Enable delta compression when serializing this component
     pub fn add_delta_compression(self) -> Self
     where
@@ -1447,6 +1776,59 @@ impl<C> ComponentRegistration<'_, C> {
         self.app.add_delta_compression::<C>();
         self
     }
+
+    /// Serialize this component relative to the replication origin of each client
+    /// (see [`OriginRebase`]). This is not applied to delta-compressed updates.
+    pub fn add_origin_rebasing(self) -> Self
+    where
+        C: Message + OriginRebase,
+    {
+        let mut registry = self.app.world_mut().resource_mut::<ComponentRegistry>();
+        registry.add_origin_rebasing::<C>();
+        self
+    }
+
+    /// Send updates for this component at most once every `interval`, in real time.
+    ///
+    /// The limit does not depend on the tick rate or the send interval: if the component changes more
+    /// often, the changes in between are skipped and the latest value is sent once the interval has elapsed.
+    /// This only applies to updates: inserts and removals are always sent immediately.
+    pub fn max_send_rate(self, interval: Duration) -> Self
+    where
+        C: Component,
+    {
+        let mut registry = self.app.world_mut().resource_mut::<ComponentRegistry>();
+        registry.set_max_send_rate::<C>(interval);
+        self
+    }
+
+    /// Record the history of this component on the server, so that entities can be rewound to a past tick
+    /// with [`ConnectionManager::rewind`](crate::prelude::server::ConnectionManager::rewind).
+    ///
+    /// See [`lag_compensation`](crate::server::lag_compensation) for more details.
+    pub fn add_lag_compensation(self) -> Self
+    where
+        C: Component + Clone,
+    {
+        let is_server = self.app.world().get_resource::<ServerConfig>().is_some();
+        if is_server {
+            add_lag_compensation_systems::<C>(self.app);
+        }
+        self
+    }
+
+    /// The component is always replicated from the server, even if a client has authority over the entity.
+    ///
+    /// For example a client could have authority over the `Position` of its character, but the `Health`
+    /// should still be controlled by the server. Writes to this component coming from a client are ignored.
+    pub fn server_authoritative(self) -> Self
+    where
+        C: Component,
+    {
+        let mut registry = self.app.world_mut().resource_mut::<ComponentRegistry>();
+        registry.set_server_authoritative::<C>();
+        self
+    }
 }
 
 impl AppComponentExt for App {
@@ -1565,6 +1947,11 @@ impl AppComponentExt for App {
                 add_interpolation_systems::<C>(self);
             }
         }
+        // record the previous values of the component to pre-fill the history of late-joining clients
+        let is_server = self.world().get_resource::<ServerConfig>().is_some();
+        if is_server && interpolation_mode == ComponentSyncMode::Full {
+            add_interpolation_snapshot_systems::<C>(self);
+        }
     }
 
     fn add_linear_interpolation_fn<C: SyncComponent + Linear>(&mut self) {
@@ -1577,6 +1964,11 @@ impl AppComponentExt for App {
         registry.set_interpolation::<C>(interpolation_fn);
     }
 
+    fn add_snap_interpolation_fn<C: SyncComponent>(&mut self) {
+        let mut registry = self.world_mut().resource_mut::<ComponentRegistry>();
+        registry.set_interpolation::<C>(snap::<C>);
+    }
+
     fn add_delta_compression<C: Component + PartialEq + Diffable>(&mut self)
     where
         C::Delta: Serialize + DeserializeOwned,
@@ -1610,9 +2002,116 @@ impl From<TypeId> for ComponentKind {
 mod tests {
     use super::*;
     use crate::serialize::writer::Writer;
+    use crate::serialize::ToBytes;
     use crate::shared::replication::entity_map::SendEntityMap;
     use crate::tests::protocol::*;
-    use bevy::prelude::{Commands, OnAdd, OnInsert, Query, Trigger};
+    use bevy::color::palettes::basic;
+    use bevy::prelude::{Color, Commands, OnAdd, OnInsert, Query, Trigger};
+    use byteorder::{ReadBytesExt, WriteBytesExt};
+    use serde::Deserialize;
+
+    const PALETTE: [Color; 16] = [
+        Color::Srgba(basic::AQUA),
+        Color::Srgba(basic::BLACK),
+        Color::Srgba(basic::BLUE),
+        Color::Srgba(basic::FUCHSIA),
+        Color::Srgba(basic::GRAY),
+        Color::Srgba(basic::GREEN),
+        Color::Srgba(basic::LIME),
+        Color::Srgba(basic::MAROON),
+        Color::Srgba(basic::NAVY),
+        Color::Srgba(basic::OLIVE),
+        Color::Srgba(basic::PURPLE),
+        Color::Srgba(basic::RED),
+        Color::Srgba(basic::SILVER),
+        Color::Srgba(basic::TEAL),
+        Color::Srgba(basic::WHITE),
+        Color::Srgba(basic::YELLOW),
+    ];
+
+    #[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq)]
+    struct PlayerColor(Color);
+
+    /// Serialize the color as its index in the palette
+    fn write_palette_color(
+        color: &PlayerColor,
+        writer: &mut Writer,
+    ) -> Result<(), SerializationError> {
+        let index = PALETTE
+            .iter()
+            .position(|c| *c == color.0)
+            .unwrap_or_default();
+        writer.write_u8(index as u8)?;
+        Ok(())
+    }
+
+    fn read_palette_color(reader: &mut Reader) -> Result<PlayerColor, SerializationError> {
+        let index = reader.read_u8()? as usize;
+        PALETTE
+            .get(index)
+            .map(|color| PlayerColor(*color))
+            .ok_or(SerializationError::InvalidValue)
+    }
+
+    /// A component with custom serialization functions is bitpacked into a single byte
+    #[test]
+    fn test_set_custom_serde() {
+        let mut world = World::new();
+        let mut registry = ComponentRegistry::default();
+        registry.register_component::<PlayerColor>(&mut world);
+        let mut component = PlayerColor(PALETTE[10]);
+
+        let mut writer = Writer::default();
+        registry
+            .serialize(&mut component, &mut writer, &mut SendEntityMap::default())
+            .unwrap();
+        let default_len = writer.to_bytes().len();
+
+        registry.set_custom_serde::<PlayerColor>(SerializeFns {
+            serialize: write_palette_color,
+            deserialize: read_palette_color,
+        });
+        let mut writer = Writer::default();
+        registry
+            .serialize(&mut component, &mut writer, &mut SendEntityMap::default())
+            .unwrap();
+        let data = writer.to_bytes();
+        let mut net_id_writer = Writer::default();
+        registry
+            .net_id::<PlayerColor>()
+            .to_bytes(&mut net_id_writer)
+            .unwrap();
+        // the payload is the net id followed by a single byte
+        assert_eq!(data.len(), net_id_writer.to_bytes().len() + 1);
+        assert!(data.len() < default_len);
+
+        let mut reader = Reader::from(data);
+        let read = registry
+            .deserialize::<PlayerColor>(&mut reader, &mut ReceiveEntityMap::default())
+            .unwrap();
+        assert_eq!(component, read);
+    }
+
+    /// In debug builds, custom serialization functions that do not round-trip are detected
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "not symmetric")]
+    fn test_custom_serde_round_trip_check() {
+        let mut world = World::new();
+        let mut registry = ComponentRegistry::default();
+        registry.register_component::<PlayerColor>(&mut world);
+        registry.set_custom_serde::<PlayerColor>(SerializeFns {
+            serialize: write_palette_color,
+            deserialize: read_palette_color,
+        });
+        // the color is not in the palette
+        let mut component = PlayerColor(Color::srgb(0.1, 0.2, 0.3));
+        let _ = registry.serialize(
+            &mut component,
+            &mut Writer::default(),
+            &mut SendEntityMap::default(),
+        );
+    }
 
     #[test]
     fn test_custom_serde() {
@@ -1639,6 +2138,56 @@ mod tests {
         assert_eq!(component, read);
     }
 
+    /// With a field mask, the serialized diff only contains the fields that changed
+    #[test]
+    fn test_field_mask_diff_smaller_than_full_component() {
+        let mut world = World::new();
+        let mut registry = ComponentRegistry::default();
+        registry.register_component::<ComponentDeltaCompressionFieldMask>(&mut world);
+        registry.set_delta_compression::<ComponentDeltaCompressionFieldMask>(&mut world);
+        let kind = ComponentKind::of::<ComponentDeltaCompressionFieldMask>();
+
+        let mut old = ComponentDeltaCompressionFieldMask {
+            slots: vec![7; 64],
+            gold: 10,
+        };
+        let mut new = old.clone();
+        new.gold = 20;
+
+        let mut writer = Writer::default();
+        registry
+            .serialize(&mut new, &mut writer, &mut SendEntityMap::default())
+            .unwrap();
+        let full_len = writer.to_bytes().len();
+
+        let mut writer = Writer::default();
+        unsafe {
+            registry
+                .serialize_diff(
+                    Tick(0),
+                    Ptr::from(&old),
+                    Ptr::from(&new),
+                    &mut writer,
+                    kind,
+                    &mut SendEntityMap::default(),
+                )
+                .unwrap();
+        }
+        let data = writer.to_bytes();
+        assert!(data.len() < full_len);
+
+        // the receiver applies the partial update onto its existing value
+        let mut reader = Reader::from(data);
+        let delta = registry
+            .deserialize::<DeltaMessage<ComponentDeltaCompressionFieldMaskFieldMask>>(
+                &mut reader,
+                &mut ReceiveEntityMap::default(),
+            )
+            .unwrap();
+        old.apply_diff(&delta.delta);
+        assert_eq!(old, new);
+    }
+
     #[derive(Debug, Default, Clone, PartialEq, TypePath, Resource)]
     struct Buffer(TempWriteBuffer);
 
@@ -0,0 +1,243 @@
+//! Discrete commands that clients submit to the server
+//!
+//! Inputs are sent every tick and model a continuous state (which buttons are pressed).
+//! Intents instead model discrete commands ("move unit A to X"), for example for turn-based games:
+//! - the client submits an intent with
+//!   [`ConnectionManager::submit_intent`](crate::prelude::client::ConnectionManager::submit_intent)
+//! - the server receives it as an [`IntentEvent`], stamped with the tick at which the server executes it
+//! - after validating and executing the intent, the server writes an [`IntentResult`] which is broadcast
+//!   to all clients, where it is received as an [`IntentResultEvent`] with the same tick.
+//!
+//! The intents and the results are sent reliably and in order.
+use crate::channel::builder::IntentChannel;
+use crate::client::config::ClientConfig;
+use crate::prelude::server::ServerConfig;
+use crate::prelude::{
+    server, ChannelDirection, ClientId, Deserialize, Message, MessageSend, NetworkTarget, Tick,
+    TickManager,
+};
+use crate::protocol::message::registry::AppMessageInternalExt;
+use crate::shared::sets::{ClientMarker, InternalMainSet, ServerMarker};
+use bevy::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tracing::error;
+
+pub trait AppIntentExt {
+    /// Registers an intent `I` that clients can submit to the server, and the result `R` that
+    /// the server broadcasts to all clients after executing an intent
+    fn register_intent<
+        I: Message + Serialize + DeserializeOwned,
+        R: Message + Serialize + DeserializeOwned,
+    >(
+        &mut self,
+    );
+}
+
+/// Message sent by a client to submit an intent
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct IntentMessage<I> {
+    pub(crate) intent: I,
+}
+
+/// Message broadcast by the server with the result of an intent
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct IntentResultMessage<R> {
+    pub(crate) client_id: ClientId,
+    pub(crate) tick: Tick,
+    pub(crate) result: R,
+}
+
+/// Event emitted on the server when a client submits an intent
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct IntentEvent<I> {
+    /// The client that submitted the intent
+    pub client_id: ClientId,
+    pub intent: I,
+    /// Server tick at which the intent is executed
+    pub tick: Tick,
+}
+
+impl<I> IntentEvent<I> {
+    /// Create the [`IntentResult`] of this intent, to broadcast it to all clients
+    pub fn result<R>(&self, result: R) -> IntentResult<R> {
+        IntentResult {
+            client_id: self.client_id,
+            tick: self.tick,
+            result,
+        }
+    }
+}
+
+/// Write this event on the server to broadcast the result of an intent to all clients
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct IntentResult<R> {
+    /// The client that submitted the intent
+    pub client_id: ClientId,
+    /// Server tick at which the intent was executed
+    pub tick: Tick,
+    pub result: R,
+}
+
+/// Event emitted on the client when the server broadcasts the result of an intent
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct IntentResultEvent<R> {
+    /// The client that submitted the intent
+    pub client_id: ClientId,
+    /// Server tick at which the intent was executed
+    pub tick: Tick,
+    pub result: R,
+}
+
+impl AppIntentExt for App {
+    fn register_intent<
+        I: Message + Serialize + DeserializeOwned,
+        R: Message + Serialize + DeserializeOwned,
+    >(
+        &mut self,
+    ) {
+        self.register_message_internal::<IntentMessage<I>>(ChannelDirection::ClientToServer);
+        self.register_message_internal::<IntentResultMessage<R>>(ChannelDirection::ServerToClient);
+        let is_client = self.world().get_resource::<ClientConfig>().is_some();
+        let is_server = self.world().get_resource::<ServerConfig>().is_some();
+        if is_server {
+            self.add_event::<IntentEvent<I>>();
+            self.add_event::<IntentResult<R>>();
+            self.add_systems(
+                PreUpdate,
+                receive_intents::<I>.after(InternalMainSet::<ServerMarker>::ReceiveEvents),
+            );
+            self.add_systems(
+                PostUpdate,
+                broadcast_intent_results::<R>.in_set(InternalMainSet::<ServerMarker>::SendEvents),
+            );
+        }
+        if is_client {
+            self.add_event::<IntentResultEvent<R>>();
+            self.add_systems(
+                PreUpdate,
+                receive_intent_results::<R>.after(InternalMainSet::<ClientMarker>::ReceiveEvents),
+            );
+        }
+    }
+}
+
+/// Convert the intents received from the clients into [`IntentEvent`]s, stamped with the current server tick
+fn receive_intents<I: Message>(
+    tick_manager: Res<TickManager>,
+    mut messages: ResMut<Events<crate::server::message::ReceiveMessage<IntentMessage<I>>>>,
+    mut events: EventWriter<IntentEvent<I>>,
+) {
+    let tick = tick_manager.tick();
+    events.send_batch(messages.drain().map(|message| IntentEvent {
+        client_id: message.from,
+        intent: message.message.intent,
+        tick,
+    }));
+}
+
+/// Broadcast the [`IntentResult`]s to all clients
+fn broadcast_intent_results<R: Message>(
+    mut results: ResMut<Events<IntentResult<R>>>,
+    mut manager: ResMut<server::ConnectionManager>,
+) {
+    for result in results.drain() {
+        let message = IntentResultMessage {
+            client_id: result.client_id,
+            tick: result.tick,
+            result: result.result,
+        };
+        if let Err(e) =
+            manager.send_message_to_target::<IntentChannel, _>(&message, NetworkTarget::All)
+        {
+            error!(
+                "Could not broadcast the result of an intent of client {:?}: {:?}",
+                message.client_id, e
+            );
+        }
+    }
+}
+
+/// Convert the intent results received from the server into [`IntentResultEvent`]s
+fn receive_intent_results<R: Message>(
+    mut messages: ResMut<Events<crate::client::message::ReceiveMessage<IntentResultMessage<R>>>>,
+    mut events: EventWriter<IntentResultEvent<R>>,
+) {
+    events.send_batch(messages.drain().map(|message| {
+        let message = message.message;
+        IntentResultEvent {
+            client_id: message.client_id,
+            tick: message.tick,
+            result: message.result,
+        }
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::client::ConnectionManager;
+    use crate::tests::multi_stepper::{MultiBevyStepper, TEST_CLIENT_ID_1};
+    use crate::tests::protocol::{MoveIntent, MoveResult};
+
+    #[derive(Resource, Default)]
+    struct ReceivedResults(Vec<IntentResultEvent<MoveResult>>);
+
+    #[derive(Resource, Default)]
+    struct ExecutedTicks(Vec<Tick>);
+
+    fn record_results(
+        mut events: EventReader<IntentResultEvent<MoveResult>>,
+        mut received: ResMut<ReceivedResults>,
+    ) {
+        received.0.extend(events.read().cloned());
+    }
+
+    /// Validate and execute the intents on the server
+    fn execute_intents(
+        mut intents: EventReader<IntentEvent<MoveIntent>>,
+        mut results: EventWriter<IntentResult<MoveResult>>,
+        mut executed: ResMut<ExecutedTicks>,
+    ) {
+        for intent in intents.read() {
+            executed.0.push(intent.tick);
+            results.send(intent.result(MoveResult {
+                position: intent.intent.target,
+            }));
+        }
+    }
+
+    /// An intent submitted by a client is executed by the server, and the result is received by all clients
+    /// with the tick at which it was executed
+    #[test]
+    fn test_intent() {
+        let mut stepper = MultiBevyStepper::default();
+        stepper.server_app.init_resource::<ExecutedTicks>();
+        stepper.server_app.add_systems(Update, execute_intents);
+        for app in [&mut stepper.client_app_1, &mut stepper.client_app_2] {
+            app.init_resource::<ReceivedResults>();
+            app.add_systems(Update, record_results);
+        }
+
+        stepper
+            .client_app_1
+            .world_mut()
+            .resource_mut::<ConnectionManager>()
+            .submit_intent(MoveIntent { target: 3 })
+            .unwrap();
+        for _ in 0..5 {
+            stepper.frame_step();
+        }
+
+        let results_1 = &stepper.client_app_1.world().resource::<ReceivedResults>().0;
+        let results_2 = &stepper.client_app_2.world().resource::<ReceivedResults>().0;
+        assert_eq!(results_1.len(), 1);
+        assert_eq!(results_1, results_2);
+        assert_eq!(results_1[0].client_id, ClientId::Netcode(TEST_CLIENT_ID_1));
+        assert_eq!(results_1[0].result, MoveResult { position: 3 });
+        assert_eq!(
+            stepper.server_app.world().resource::<ExecutedTicks>().0,
+            vec![results_1[0].tick]
+        );
+    }
+}
@@ -3,12 +3,16 @@ use std::any::TypeId;
 
 pub(crate) mod client;
 
+pub(crate) mod intent;
+
 pub(crate) mod registry;
 
 pub(crate) mod server;
 
 pub(crate) mod resource;
 
+pub(crate) mod tick_event;
+
 pub(crate) mod trigger;
 
 #[derive(thiserror::Error, Debug)]
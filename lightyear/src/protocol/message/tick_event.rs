@@ -0,0 +1,183 @@
+//! One-shot events that the clients play at a specific tick
+//!
+//! Messages are handled by the clients as soon as they are received, so a one-shot effect sent as a
+//! message (for example "explosion at position P") plays at a different moment on each client, and out
+//! of sync with the interpolated entities around it.
+//!
+//! Instead, the server can send a tick event with
+//! [`ConnectionManager::send_tick_event`](crate::prelude::server::ConnectionManager::send_tick_event):
+//! the event is buffered on the clients, and emitted as a [`TickEvent`] once the interpolation tick of the
+//! client reaches the tick of the event, so that it plays at the same point of the timeline as the
+//! interpolated entities.
+//!
+//! An event that arrives after its tick has already been reached is emitted immediately, with a warning.
+//! The events are sent reliably.
+use crate::client::config::ClientConfig;
+use crate::prelude::{client, ChannelDirection, Deserialize, Message, Tick, TickManager};
+use crate::protocol::message::registry::AppMessageInternalExt;
+use crate::shared::sets::{ClientMarker, InternalMainSet};
+use bevy::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tracing::warn;
+
+pub trait AppTickEventExt {
+    /// Registers an event `E` that the server can send to the clients with
+    /// [`send_tick_event`](crate::prelude::server::ConnectionManager::send_tick_event)
+    fn register_tick_event<E: Message + Serialize + DeserializeOwned>(&mut self);
+}
+
+/// Message sent by the server with an event that should be played at `tick`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct TickEventMessage<E> {
+    pub(crate) tick: Tick,
+    pub(crate) event: E,
+}
+
+/// Event emitted on the client when its interpolation tick reaches the tick of an event sent by the server
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct TickEvent<E> {
+    /// Tick at which the server scheduled the event
+    pub tick: Tick,
+    pub event: E,
+}
+
+/// Tick events received from the server that haven't been emitted yet
+#[derive(Resource, Debug)]
+struct TickEventBuffer<E>(Vec<TickEventMessage<E>>);
+
+impl AppTickEventExt for App {
+    fn register_tick_event<E: Message + Serialize + DeserializeOwned>(&mut self) {
+        self.register_message_internal::<TickEventMessage<E>>(ChannelDirection::ServerToClient);
+        let is_client = self.world().get_resource::<ClientConfig>().is_some();
+        if is_client {
+            self.add_event::<TickEvent<E>>();
+            self.insert_resource(TickEventBuffer::<E>(Vec::new()));
+            self.add_systems(
+                PreUpdate,
+                emit_tick_events::<E>.after(InternalMainSet::<ClientMarker>::ReceiveEvents),
+            );
+        }
+    }
+}
+
+/// Buffer the tick events received from the server, and emit the ones whose tick has been reached
+/// by the interpolation tick
+fn emit_tick_events<E: Message>(
+    tick_manager: Res<TickManager>,
+    connection: Res<client::ConnectionManager>,
+    mut messages: ResMut<Events<client::ReceiveMessage<TickEventMessage<E>>>>,
+    mut buffer: ResMut<TickEventBuffer<E>>,
+    mut events: EventWriter<TickEvent<E>>,
+) {
+    // the interpolation tick is only meaningful once we are synced with the server
+    if !connection.is_synced() {
+        buffer
+            .0
+            .extend(messages.drain().map(|message| message.message));
+        return;
+    }
+    let interpolation_tick = connection.sync_manager.interpolation_tick(&tick_manager);
+    for message in messages.drain() {
+        let message = message.message;
+        if message.tick < interpolation_tick {
+            warn!(
+                tick = ?message.tick,
+                ?interpolation_tick,
+                "Received a tick event after its tick was reached, emitting it immediately"
+            );
+        }
+        buffer.0.push(message);
+    }
+    let (ready, pending): (Vec<_>, Vec<_>) = std::mem::take(&mut buffer.0)
+        .into_iter()
+        .partition(|message| message.tick <= interpolation_tick);
+    buffer.0 = pending;
+    events.send_batch(ready.into_iter().map(|message| TickEvent {
+        tick: message.tick,
+        event: message.event,
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::server::ConnectionManager;
+    use crate::prelude::NetworkTarget;
+    use crate::tests::protocol::ExplosionEvent;
+    use crate::tests::stepper::BevyStepper;
+
+    /// Tick events received on the client, with the interpolation tick at which they were emitted
+    #[derive(Resource, Default)]
+    struct ReceivedTickEvents(Vec<(TickEvent<ExplosionEvent>, Tick)>);
+
+    fn record_tick_events(
+        tick_manager: Res<TickManager>,
+        connection: Res<client::ConnectionManager>,
+        mut events: EventReader<TickEvent<ExplosionEvent>>,
+        mut received: ResMut<ReceivedTickEvents>,
+    ) {
+        let interpolation_tick = connection.sync_manager.interpolation_tick(&tick_manager);
+        received.0.extend(
+            events
+                .read()
+                .map(|event| (event.clone(), interpolation_tick)),
+        );
+    }
+
+    fn send_explosion(stepper: &mut BevyStepper, tick: Tick) {
+        stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<ConnectionManager>()
+            .send_tick_event(ExplosionEvent { position: 1 }, tick, NetworkTarget::All)
+            .unwrap();
+    }
+
+    /// An event scheduled for a future tick is emitted on the client when its interpolation tick
+    /// reaches that tick
+    #[test]
+    fn test_tick_event() {
+        let mut stepper = BevyStepper::default();
+        stepper.client_app.init_resource::<ReceivedTickEvents>();
+        stepper.client_app.add_systems(Update, record_tick_events);
+
+        let tick = stepper.interpolation_tick() + 20;
+        send_explosion(&mut stepper, tick);
+        for _ in 0..50 {
+            stepper.frame_step();
+        }
+        let received = &stepper
+            .client_app
+            .world()
+            .resource::<ReceivedTickEvents>()
+            .0;
+        assert_eq!(received.len(), 1);
+        let (event, emitted_at) = &received[0];
+        assert_eq!(event.tick, tick);
+        assert_eq!(event.event, ExplosionEvent { position: 1 });
+        // the event is emitted on the first frame where the interpolation tick reaches its tick
+        assert!(*emitted_at >= tick && *emitted_at - tick <= 1);
+    }
+
+    /// An event whose tick has already been reached when it arrives is emitted immediately
+    #[test]
+    fn test_tick_event_in_the_past() {
+        let mut stepper = BevyStepper::default();
+        stepper.client_app.init_resource::<ReceivedTickEvents>();
+        stepper.client_app.add_systems(Update, record_tick_events);
+
+        let tick = stepper.interpolation_tick() - 10;
+        send_explosion(&mut stepper, tick);
+        for _ in 0..5 {
+            stepper.frame_step();
+        }
+        let received = &stepper
+            .client_app
+            .world()
+            .resource::<ReceivedTickEvents>()
+            .0;
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].0.tick, tick);
+    }
+}
@@ -55,6 +55,10 @@ type DeserializeFn<M> = fn(reader: &mut Reader) -> Result<M, SerializationError>
 
 type CloneFn<M> = fn(&M) -> M;
 
+/// Type of the function that checks that the serialize and deserialize functions are symmetric
+pub(crate) type ErasedRoundTripCheckFn =
+    unsafe fn(erased_serialize_fns: &ErasedSerializeFns, message: Ptr);
+
 /// Type of the entity mapping function
 pub(crate) type ErasedMapEntitiesFn =
     for<'a> unsafe fn(message: PtrMut<'a>, entity_map: &mut EntityMap);
@@ -104,6 +108,29 @@ fn default_deserialize<M: Message + DeserializeOwned>(
     Ok(data)
 }
 
+/// Panics if deserializing the serialized message does not return the original message.
+///
+/// SAFETY: the Ptr must be a valid pointer to a value of type M, and the ErasedSerializeFns
+/// must be created for the type M
+pub(crate) unsafe fn check_round_trip<M: Message + PartialEq>(
+    erased_serialize_fns: &ErasedSerializeFns,
+    message: Ptr,
+) {
+    let message = message.deref::<M>();
+    let fns = erased_serialize_fns.typed::<M>();
+    let mut writer = Writer::default();
+    // serialization errors are surfaced by the actual serialization
+    if (fns.serialize)(message, &mut writer).is_err() {
+        return;
+    }
+    let mut reader = Reader::from(writer.to_bytes());
+    assert!(
+        (fns.deserialize)(&mut reader).is_ok_and(|read| read == *message),
+        "The serialization functions of {} are not symmetric: deserializing a serialized value does not return the same value",
+        erased_serialize_fns.type_name
+    );
+}
+
 fn erased_clone<M: Clone>(message: &M) -> M {
     message.clone()
 }
@@ -244,10 +271,11 @@ impl AppSerializeExt for App {
 
 #[cfg(test)]
 mod tests {
+    use crate::prelude::{ClientId, Tick};
     use crate::protocol::serialize::{erased_serialize_fn, ErasedSerializeFns};
     use crate::serialize::reader::Reader;
     use crate::serialize::writer::Writer;
-    use crate::shared::replication::authority::AuthorityChange;
+    use crate::shared::replication::authority::{AuthorityChange, AuthorityPeer};
     use crate::shared::replication::entity_map::{ReceiveEntityMap, SendEntityMap};
     use bevy::prelude::Entity;
     use bevy::ptr::Ptr;
@@ -261,8 +289,11 @@ mod tests {
         let message = AuthorityChange {
             entity: Entity::from_raw(1),
             gain_authority: true,
+            from: AuthorityPeer::Server,
+            to: AuthorityPeer::Client(ClientId::Netcode(1)),
             add_prediction: false,
             add_interpolation: false,
+            tick: Tick(1),
         };
         let mut writer = Writer::default();
         let _ = unsafe {
@@ -285,8 +316,11 @@ mod tests {
             AuthorityChange {
                 entity: Entity::PLACEHOLDER,
                 gain_authority: true,
+                from: AuthorityPeer::Server,
+                to: AuthorityPeer::Client(ClientId::Netcode(1)),
                 add_prediction: false,
                 add_interpolation: false,
+                tick: Tick(1),
             }
         );
     }
@@ -300,8 +334,11 @@ mod tests {
         let message = AuthorityChange {
             entity: Entity::from_raw(1),
             gain_authority: true,
+            from: AuthorityPeer::Server,
+            to: AuthorityPeer::Client(ClientId::Netcode(1)),
             add_prediction: false,
             add_interpolation: false,
+            tick: Tick(1),
         };
         let mut writer = Writer::default();
         let mut entity_map = SendEntityMap::default();
@@ -326,8 +363,11 @@ mod tests {
             AuthorityChange {
                 entity: Entity::from_raw(2),
                 gain_authority: true,
+                from: AuthorityPeer::Server,
+                to: AuthorityPeer::Client(ClientId::Netcode(1)),
                 add_prediction: false,
                 add_interpolation: false,
+                tick: Tick(1),
             }
         );
     }
@@ -1,11 +1,18 @@
 //! The server spawns an entity per connected client to store metadata about them.
 //!
 //! This module contains components and systems to manage the metadata on client entities.
+use crate::prelude::{ClientId, NetworkTarget};
 use crate::server::clients::systems::handle_controlled_by_remove;
-use crate::server::replication::send::Lifetime;
+use crate::server::connection::ConnectionManager;
+use crate::server::relevance::room::RoomId;
+use crate::server::replication::send::{ControlledBy, Lifetime};
+use crate::server::replication::ServerReplicationSet;
+use crate::server::run_conditions::is_started;
 use crate::shared::sets::{InternalReplicationSet, ServerMarker};
 use bevy::ecs::entity::EntityHashMap;
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
+use bevy::utils::{Duration, HashMap};
 
 /// List of entities under the control of a client
 #[derive(Component, Default, Debug, Deref, DerefMut, PartialEq)]
@@ -23,49 +30,366 @@ impl ControlledEntities {
     }
 }
 
+/// [`SystemParam`] to get the [`ControlledEntities`] of a client from its [`ClientId`]
+///
+/// The [`ControlledEntities`] of a client that disconnected during the frame are still available,
+/// so that they can be read while handling the [`DisconnectEvent`](crate::prelude::server::DisconnectEvent).
+///
+/// ```rust,ignore
+/// fn log_controlled_entities(
+///     mut disconnections: EventReader<DisconnectEvent>,
+///     controlled_entities: ClientControlledEntities,
+/// ) {
+///     for disconnection in disconnections.read() {
+///         if let Some(controlled_entities) = controlled_entities.get(disconnection.client_id) {
+///             info!(
+///                 "Client {:?} controlled {:?}",
+///                 disconnection.client_id,
+///                 controlled_entities.entities()
+///             );
+///         }
+///     }
+/// }
+/// ```
+#[derive(SystemParam)]
+pub struct ClientControlledEntities<'w, 's> {
+    manager: Res<'w, ConnectionManager>,
+    query: Query<'w, 's, &'static ControlledEntities>,
+}
+
+impl ClientControlledEntities<'_, '_> {
+    /// Returns the [`ControlledEntities`] of the client, or `None` if the client is not connected
+    /// and did not disconnect during this frame
+    pub fn get(&self, client_id: ClientId) -> Option<&ControlledEntities> {
+        self.manager.controlled_entities(client_id, &self.query)
+    }
+}
+
+/// Companion component to [`ControlledBy`]: the entity is controlled by the client(s) that own the room,
+/// as specified by the [`RoomControllers`] resource.
+///
+/// The [`ControlledBy::target`] of the entity is updated whenever this component or the [`RoomControllers`]
+/// resource changes, so reassigning the owner of a room updates the [`ControlledEntities`] of the clients.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+#[require(ControlledBy)]
+pub struct ControlledByRoom(pub RoomId);
+
+/// Mapping from a room to the client(s) that control the entities with a [`ControlledByRoom`] component
+///
+/// Entities in a room that is not present in the mapping are not controlled by any client.
+#[derive(Resource, Default, Debug)]
+pub struct RoomControllers(HashMap<RoomId, NetworkTarget>);
+
+impl RoomControllers {
+    /// Set the client(s) that control the entities of the room
+    pub fn set(&mut self, room_id: RoomId, target: NetworkTarget) {
+        self.0.insert(room_id, target);
+    }
+
+    /// Remove the controllers of the room
+    pub fn remove(&mut self, room_id: RoomId) -> Option<NetworkTarget> {
+        self.0.remove(&room_id)
+    }
+
+    /// Get the client(s) that control the entities of the room
+    pub fn get(&self, room_id: RoomId) -> Option<&NetworkTarget> {
+        self.0.get(&room_id)
+    }
+}
+
+/// Persistent identifier of a player that stays the same across reconnections, unlike the [`ClientId`]
+/// which identifies a connection.
+///
+/// Insert it on the client entity (for example when handling the [`ConnectEvent`](crate::prelude::server::ConnectEvent),
+/// after identifying the player). When the client disconnects, the entities it controlled with a
+/// [`Lifetime::Persistent`] lifetime are stored in [`OrphanedEntities`]; when a new client entity
+/// gets the same [`SessionToken`], the [`ControlledBy`] of these entities is updated to target the new [`ClientId`].
+///
+/// ```rust,ignore
+/// fn handle_connections(
+///     mut commands: Commands,
+///     mut connections: EventReader<ConnectEvent>,
+///     orphaned: Res<OrphanedEntities>,
+/// ) {
+///     for connection in connections.read() {
+///         let session_token = SessionToken(authenticated_player_id(connection.client_id));
+///         commands.entity(connection.entity).insert(session_token);
+///         // the player will reclaim the entities of its previous session
+///         if orphaned.get(session_token).is_none() {
+///             commands.spawn(PlayerBundle::new(connection.client_id));
+///         }
+///     }
+/// }
+/// ```
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Component)]
+pub struct SessionToken(pub u64);
+
+/// Entities with a [`Lifetime::Persistent`] lifetime that were controlled by a player whose
+/// client disconnected, waiting to be reclaimed by a new client with the same [`SessionToken`]
+///
+/// By default the sessions never expire: the orphaned entities of players that never reconnect have to be
+/// removed with [`OrphanedEntities::remove`]. To despawn them automatically if they are not reclaimed in time,
+/// replace the resource after adding the server plugins:
+/// ```rust,ignore
+/// app.insert_resource(OrphanedEntities::with_timeout(Duration::from_secs(60)));
+/// ```
+#[derive(Resource, Default, Debug)]
+pub struct OrphanedEntities {
+    sessions: HashMap<SessionToken, OrphanedSession>,
+    /// The orphaned entities that are not reclaimed within this duration are despawned
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug)]
+struct OrphanedSession {
+    /// [`ClientId`] of the client that controlled the entities
+    client_id: ClientId,
+    entities: Vec<Entity>,
+    /// Time elapsed since the client disconnected
+    elapsed: Duration,
+}
+
+impl OrphanedEntities {
+    /// The orphaned entities that are not reclaimed within `timeout` after the disconnection
+    /// of their client are despawned
+    pub fn with_timeout(timeout: Duration) -> Self {
+        Self {
+            sessions: HashMap::default(),
+            timeout: Some(timeout),
+        }
+    }
+
+    /// Get the entities orphaned by the previous session of the player
+    pub fn get(&self, session_token: SessionToken) -> Option<&[Entity]> {
+        self.sessions
+            .get(&session_token)
+            .map(|session| session.entities.as_slice())
+    }
+
+    /// Remove the orphaned entities of the player, so that they won't be reclaimed.
+    ///
+    /// The entities are not despawned.
+    pub fn remove(&mut self, session_token: SessionToken) -> Option<Vec<Entity>> {
+        self.sessions
+            .remove(&session_token)
+            .map(|session| session.entities)
+    }
+}
+
+/// Event emitted on the server when an entity is added to the [`ControlledEntities`] of a client
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ControlGained {
+    pub client_id: ClientId,
+    pub entity: Entity,
+}
+
+/// Event emitted on the server when an entity is removed from the [`ControlledEntities`] of a client,
+/// because the [`ControlledBy`] target changed or the [`ControlledBy`] component was removed
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ControlLost {
+    pub client_id: ClientId,
+    pub entity: Entity,
+}
+
+/// Event emitted on the server when the [`ControlValidator`] rejects the control of an entity by a client.
+///
+/// The client is removed from the [`ControlledBy`] target of the entity.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ControlRejected {
+    pub client_id: ClientId,
+    pub entity: Entity,
+}
+
+/// Server-side validation of the [`ControlledBy`] assignments.
+///
+/// When this resource is present, every time the [`ControlledBy`] component of an entity changes, the
+/// validation function is called for each connected client targeted by the component, before the
+/// [`ControlledEntities`] are updated. The clients that are rejected are removed from the
+/// [`ControlledBy`] target (so an assignment that targets multiple clients is trimmed to the allowed ones),
+/// and a [`ControlRejected`] event is emitted for each of them.
+///
+/// ```rust,ignore
+/// // only the server can control structures
+/// app.insert_resource(ControlValidator::new(|world, entity, _client_id| {
+///     world.get::<Structure>(entity).is_none()
+/// }));
+/// ```
+#[derive(Resource)]
+pub struct ControlValidator(Box<dyn Fn(&World, Entity, ClientId) -> bool + Send + Sync>);
+
+impl ControlValidator {
+    /// Create a validator from a function that returns true if the client is allowed
+    /// to control the entity
+    pub fn new(
+        validate: impl Fn(&World, Entity, ClientId) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        Self(Box::new(validate))
+    }
+}
+
+/// System sets related to the client entities
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone, Copy)]
+pub enum ClientEntitySet {
+    /// Runs in `Last`: despawns the entities of the clients that disconnected during the frame
+    /// (unless [`ClientEntityConfig::despawn_on_disconnect`](crate::server::config::ClientEntityConfig::despawn_on_disconnect)
+    /// is false).
+    ///
+    /// Systems that need the client entity of a disconnected client, for example to read its
+    /// [`ControlledEntities`], can run before this set.
+    Despawn,
+}
+
+/// Previous [`ControlledBy`] target of an entity, so that we can
+/// compute which clients lost control of the entity when the target changes
+#[derive(Component, Debug, PartialEq)]
+pub(crate) struct PrevControlledBy(pub(crate) NetworkTarget);
+
 pub(crate) struct ClientsMetadataPlugin;
 
 mod systems {
     use super::*;
-    use crate::prelude::server::ControlledBy;
+    use crate::prelude::{DespawnReason, Replicated};
     use crate::server::clients::ControlledEntities;
-    use crate::server::connection::ConnectionManager;
+    use crate::server::config::ServerConfig;
+    use crate::server::connection::ServerInstanceId;
+    use crate::server::error::ClientLookupError;
     use crate::server::events::DisconnectEvent;
-    use tracing::{debug, trace};
+    use crate::shared::replication::authority::{
+        AuthorityPeer, AuthorityTransferEvent, HasAuthority, PendingAuthorityTransfer,
+    };
+    use bevy::ecs::entity::EntityHashSet;
+    use tracing::{debug, error, trace};
 
-    // TODO: remove entity in ControlledEntities lists after the component gets updated
-    //  (e.g. control goes from client 1 to client 2)
-    //  need to detect what the previous ControlledBy was to compute the change
-    //  i.e. add the previous ControlledBy to the replicate cache?
+    /// Resolve the [`ControlledBy`] target of the entities with a [`ControlledByRoom`] component,
+    /// using the [`RoomControllers`] mapping
+    pub(super) fn resolve_controlled_by_room(
+        controllers: Res<RoomControllers>,
+        mut query: Query<(Ref<ControlledByRoom>, &mut ControlledBy)>,
+    ) {
+        let controllers_changed = controllers.is_changed();
+        for (room, mut controlled_by) in query.iter_mut() {
+            if !controllers_changed && !room.is_changed() {
+                continue;
+            }
+            let target = controllers
+                .get(room.0)
+                .cloned()
+                .unwrap_or(NetworkTarget::None);
+            // only update the target if it changed, to not trigger change detection needlessly
+            if controlled_by.target != target {
+                trace!(room_id = ?room.0, ?target, "Updating ControlledBy target from room");
+                controlled_by.target = target;
+            }
+        }
+    }
+
+    /// Remove the clients rejected by the [`ControlValidator`] from the [`ControlledBy`] targets
+    /// that changed, before the [`ControlledEntities`] get updated
+    pub(super) fn validate_controlled_by(
+        world: &mut World,
+        query: &mut QueryState<(Entity, &ControlledBy), Changed<ControlledBy>>,
+    ) {
+        let sender = world.resource::<ConnectionManager>();
+        let validator = world.resource::<ControlValidator>();
+        let mut rejected = vec![];
+        for (entity, controlled_by) in query.iter(world) {
+            for client_id in controlled_by.resolve(sender) {
+                if !(validator.0)(world, entity, client_id) {
+                    rejected.push(ControlRejected { client_id, entity });
+                }
+            }
+        }
+        for event in rejected {
+            debug!(
+                "Rejecting the control of entity {:?} by client {:?}",
+                event.entity, event.client_id
+            );
+            if let Some(mut controlled_by) = world.get_mut::<ControlledBy>(event.entity) {
+                controlled_by
+                    .target
+                    .difference(&NetworkTarget::Single(event.client_id));
+            }
+            world.send_event(event);
+        }
+    }
 
     /// If the [`ControlledBy`] component gets updated, update the [`ControlledEntities`] component
     /// on the Client Entity
+    ///
+    /// The entity is removed from the [`ControlledEntities`] of the clients that were targeted by the
+    /// previous [`ControlledBy`] but are not targeted anymore.
     pub(super) fn handle_controlled_by_update(
+        mut commands: Commands,
         sender: Res<ConnectionManager>,
-        query: Query<(Entity, &ControlledBy), Changed<ControlledBy>>,
+        mut query: Query<
+            (
+                Entity,
+                &ControlledBy,
+                Option<&mut PrevControlledBy>,
+                Option<&ServerInstanceId>,
+            ),
+            Changed<ControlledBy>,
+        >,
         mut client_query: Query<&mut ControlledEntities>,
+        mut gained_events: EventWriter<ControlGained>,
+        mut lost_events: EventWriter<ControlLost>,
     ) {
-        for (entity, controlled_by) in query.iter() {
-            // TODO: avoid clone
-            sender
-                .connected_targets(&controlled_by.target)
-                .for_each(|connection| {
-                    let client_id = connection.client_id;
-                    if let Ok(client_entity) = sender.client_entity(client_id) {
-                        if let Ok(mut controlled_entities) = client_query.get_mut(client_entity) {
-                            // first check if it already contains, to not trigger change detection needlessly
-                            if controlled_entities.contains_key(&entity) {
-                                return;
-                            }
-                            trace!(
-                                "Adding entity {:?} to client {:?}'s controlled entities",
-                                entity,
-                                client_id,
-                            );
-                            controlled_entities.insert(entity, controlled_by.lifetime);
-                        }
+        for (entity, controlled_by, prev_controlled_by, instance) in query.iter_mut() {
+            if let Some(mut prev_controlled_by) = prev_controlled_by {
+                sender
+                    .connected_targets(&prev_controlled_by.0)
+                    .map(|connection| connection.client_id)
+                    .filter(|client_id| !controlled_by.targets(client_id))
+                    .for_each(|client_id| {
+                        remove_controlled_entity(
+                            &sender,
+                            &mut client_query,
+                            &mut lost_events,
+                            client_id,
+                            entity,
+                        );
+                    });
+                if prev_controlled_by.0 != controlled_by.target {
+                    prev_controlled_by.0 = controlled_by.target.clone();
+                }
+            } else {
+                commands
+                    .entity(entity)
+                    .insert(PrevControlledBy(controlled_by.target.clone()));
+            }
+            controlled_by.resolve(&sender).for_each(|client_id| {
+                // clients cannot control entities of another instance
+                if !sender.is_in_instance(client_id, instance) {
+                    trace!(
+                        ?entity,
+                        ?client_id,
+                        "Client is not in the instance of the entity"
+                    );
+                    return;
+                }
+                let client_entity = match sender.client_entity(client_id) {
+                    Ok(client_entity) => client_entity,
+                    Err(e) => {
+                        log_client_lookup_error(e, entity);
+                        return;
+                    }
+                };
+                if let Ok(mut controlled_entities) = client_query.get_mut(client_entity) {
+                    // first check if it already contains, to not trigger change detection needlessly
+                    if controlled_entities.contains_key(&entity) {
+                        return;
                     }
-                });
+                    trace!(
+                        "Adding entity {:?} to client {:?}'s controlled entities",
+                        entity,
+                        client_id,
+                    );
+                    controlled_entities.insert(entity, controlled_by.lifetime);
+                    gained_events.send(ControlGained { client_id, entity });
+                }
+            });
         }
     }
 
@@ -76,65 +400,296 @@ mod systems {
         query: Query<&ControlledBy>,
         mut client_query: Query<&mut ControlledEntities>,
         sender: Res<ConnectionManager>,
+        mut lost_events: EventWriter<ControlLost>,
     ) {
         // OnRemove observers trigger before the actual removal
         let entity = trigger.entity();
         if let Ok(controlled_by) = query.get(entity) {
-            // TODO: avoid clone
-            sender
-                .connected_targets(&controlled_by.target)
-                .for_each(|connection| {
-                    let client_id = connection.client_id;
-                    if let Ok(client_entity) = sender.client_entity(client_id) {
-                        if let Ok(mut controlled_entities) = client_query.get_mut(client_entity) {
-                            // first check if it already contains, to not trigger change detection needlessly
-                            if !controlled_entities.contains_key(&entity) {
-                                return;
-                            }
-                            trace!(
-                                "Removing entity {:?} to client {:?}'s controlled entities",
-                                entity,
-                                client_id,
-                            );
-                            controlled_entities.remove(&entity);
-                        }
-                    }
-                })
+            controlled_by.resolve(&sender).for_each(|client_id| {
+                remove_controlled_entity(
+                    &sender,
+                    &mut client_query,
+                    &mut lost_events,
+                    client_id,
+                    entity,
+                );
+            })
+        }
+    }
+
+    /// Remove the entity from the [`ControlledEntities`] of the client
+    fn remove_controlled_entity(
+        sender: &ConnectionManager,
+        client_query: &mut Query<&mut ControlledEntities>,
+        lost_events: &mut EventWriter<ControlLost>,
+        client_id: ClientId,
+        entity: Entity,
+    ) {
+        let client_entity = match sender.client_entity(client_id) {
+            Ok(client_entity) => client_entity,
+            Err(e) => {
+                log_client_lookup_error(e, entity);
+                return;
+            }
+        };
+        if let Ok(mut controlled_entities) = client_query.get_mut(client_entity) {
+            // first check if it contains, to not trigger change detection needlessly
+            if !controlled_entities.contains_key(&entity) {
+                return;
+            }
+            trace!(
+                "Removing entity {:?} from client {:?}'s controlled entities",
+                entity,
+                client_id,
+            );
+            controlled_entities.remove(&entity);
+            lost_events.send(ControlLost { client_id, entity });
+        }
+    }
+
+    /// A client that disconnected this frame is expected, but an unknown client id
+    /// means that the [`ControlledBy`] target is stale
+    fn log_client_lookup_error(error: ClientLookupError, entity: Entity) {
+        match error {
+            ClientLookupError::UnknownClient(client_id) => {
+                error!(
+                    ?client_id,
+                    ?entity,
+                    "Cannot update the controlled entities of an unknown client"
+                );
+            }
+            _ => {
+                debug!(?entity, "Cannot update the controlled entities: {error}");
+            }
         }
     }
 
     /// When a client disconnects, we despawn all the entities it controlled if the lifetime
-    /// is SesssionBased
+    /// is SesssionBased. The despawns are replicated with [`DespawnReason::OwnerDisconnected`].
+    ///
+    /// Only the entities whose ancestors are not despawned as well are despawned (recursively), so that
+    /// a hierarchy of controlled entities doesn't get despawned twice.
+    ///
+    /// If the lifetime is Persistent and the client had authority over the entity, the authority
+    /// is given back to the server.
+    ///
+    /// The authority transfers to the client that were not acknowledged yet are also reverted to the server.
+    ///
+    /// If the client entity has a [`SessionToken`], the persistent entities are stored in [`OrphanedEntities`].
+    ///
+    /// The client entity itself is despawned later, in [`ClientEntitySet::Despawn`].
     pub(super) fn handle_client_disconnect(
         trigger: Trigger<DisconnectEvent>,
         mut commands: Commands,
-        client_query: Query<&ControlledEntities>,
+        client_query: Query<(&ControlledEntities, Option<&SessionToken>)>,
+        mut orphaned: ResMut<OrphanedEntities>,
+        parent_query: Query<&Parent>,
+        authority_query: Query<&AuthorityPeer>,
+        pending_transfer_query: Query<(Entity, &PendingAuthorityTransfer)>,
+        mut transfer_events: EventWriter<AuthorityTransferEvent>,
     ) {
         // TODO: should directly we use the client entity as the trigger entity?
         let client_entity = trigger.event().entity;
         let client_id = trigger.event().client_id;
+        let mut handled = EntityHashSet::default();
+        let mut despawned = EntityHashSet::default();
         // despawn all the controlled entities for the disconnected client
-        if let Ok(controlled_entities) = client_query.get(client_entity) {
+        if let Ok((controlled_entities, session_token)) = client_query.get(client_entity) {
             debug!(
                 "Despawning all entities controlled by disconnected client {:?}",
                 client_id
             );
+            if let Some(session_token) = session_token {
+                let entities: Vec<Entity> = controlled_entities
+                    .iter()
+                    .filter(|(_, lifetime)| **lifetime == Lifetime::Persistent)
+                    .map(|(entity, _)| *entity)
+                    .collect();
+                if !entities.is_empty() {
+                    trace!(
+                        ?session_token,
+                        ?entities,
+                        "Storing the persistent entities of disconnected client {:?}",
+                        client_id
+                    );
+                    orphaned.sessions.insert(
+                        *session_token,
+                        OrphanedSession {
+                            client_id,
+                            entities,
+                            elapsed: Duration::ZERO,
+                        },
+                    );
+                }
+            }
             for (entity, lifetime) in controlled_entities.iter() {
                 if lifetime == &Lifetime::SessionBased {
+                    despawned.insert(*entity);
+                } else if authority_query
+                    .get(*entity)
+                    .is_ok_and(|peer| peer == &AuthorityPeer::Client(client_id))
+                {
                     trace!(
-                        "Despawning entity {entity:?} controlled by disconnected client {:?}",
+                        "Giving authority over entity {entity:?} back to the server after client {:?} disconnected",
                         client_id
                     );
-                    if let Some(command) = commands.get_entity(*entity) {
-                        command.despawn_recursive();
+                    // we cannot use `transfer_authority` because the client is not connected anymore
+                    if let Some(mut command) = commands.get_entity(*entity) {
+                        command
+                            .remove::<(Replicated, PendingAuthorityTransfer)>()
+                            .insert((HasAuthority, AuthorityPeer::Server));
                     }
+                    transfer_events.send(AuthorityTransferEvent {
+                        entity: *entity,
+                        from: AuthorityPeer::Client(client_id),
+                        to: AuthorityPeer::Server,
+                        acknowledged: true,
+                    });
+                    handled.insert(*entity);
                 }
             }
         }
-        // despawn the client entity itself
-        if let Some(command) = commands.get_entity(client_entity) {
-            command.despawn_recursive();
+        // only despawn the roots: the descendants of a despawned entity are despawned by `despawn_recursive`
+        let has_despawned_ancestor = |entity: Entity| {
+            parent_query
+                .iter_ancestors(entity)
+                .any(|ancestor| despawned.contains(&ancestor))
+        };
+        for entity in despawned.iter() {
+            if has_despawned_ancestor(*entity) {
+                continue;
+            }
+            trace!(
+                "Despawning entity {entity:?} controlled by disconnected client {:?}",
+                client_id
+            );
+            if let Some(mut command) = commands.get_entity(*entity) {
+                command.insert(DespawnReason::OwnerDisconnected);
+                command.despawn_recursive();
+            }
+        }
+        // revert the authority transfers that the client didn't acknowledge
+        for (entity, pending) in pending_transfer_query.iter() {
+            if pending.to != AuthorityPeer::Client(client_id)
+                || handled.contains(&entity)
+                || despawned.contains(&entity)
+                || has_despawned_ancestor(entity)
+            {
+                continue;
+            }
+            trace!(
+                "Reverting the authority transfer of entity {entity:?} to the server after client {:?} disconnected",
+                client_id
+            );
+            commands
+                .entity(entity)
+                .remove::<(Replicated, PendingAuthorityTransfer)>()
+                .insert((HasAuthority, AuthorityPeer::Server));
+            transfer_events.send(AuthorityTransferEvent {
+                entity,
+                from: AuthorityPeer::Client(client_id),
+                to: AuthorityPeer::Server,
+                acknowledged: true,
+            });
+        }
+    }
+
+    /// Despawn the entities of the clients that disconnected.
+    ///
+    /// This runs in `Last` to let the user handle the [`DisconnectEvent`] however they want first,
+    /// before the client entity gets despawned.
+    pub(super) fn despawn_client_entities(
+        mut commands: Commands,
+        config: Res<ServerConfig>,
+        mut disconnections: EventReader<DisconnectEvent>,
+    ) {
+        if !config.client_entity.despawn_on_disconnect {
+            disconnections.clear();
+            return;
+        }
+        for disconnection in disconnections.read() {
+            trace!(
+                "Despawning the entity {:?} of disconnected client {:?}",
+                disconnection.entity,
+                disconnection.client_id
+            );
+            if let Some(command) = commands.get_entity(disconnection.entity) {
+                command.despawn_recursive();
+            }
+        }
+    }
+
+    /// When a [`SessionToken`] is inserted on a client entity, the new client takes control of the entities
+    /// orphaned by the previous session of the player.
+    pub(super) fn reclaim_orphaned_entities(
+        trigger: Trigger<OnInsert, SessionToken>,
+        token_query: Query<&SessionToken>,
+        sender: Res<ConnectionManager>,
+        mut orphaned: ResMut<OrphanedEntities>,
+        mut controlled_by_query: Query<&mut ControlledBy>,
+    ) {
+        let client_entity = trigger.entity();
+        let Ok(session_token) = token_query.get(client_entity) else {
+            return;
+        };
+        let Some(client_id) = sender
+            .connections
+            .iter()
+            .find(|(_, connection)| connection.metadata.entity == client_entity)
+            .map(|(client_id, _)| *client_id)
+        else {
+            return;
+        };
+        let Some(session) = orphaned.sessions.remove(session_token) else {
+            return;
+        };
+        for entity in session.entities {
+            // the entity might have been despawned while the player was disconnected
+            if let Ok(mut controlled_by) = controlled_by_query.get_mut(entity) {
+                trace!(
+                    ?session_token,
+                    "Client {:?} reclaims entity {entity:?} from client {:?}",
+                    client_id,
+                    session.client_id
+                );
+                controlled_by
+                    .target
+                    .difference(&NetworkTarget::Single(session.client_id));
+                controlled_by
+                    .target
+                    .union(&NetworkTarget::Single(client_id));
+            }
+        }
+    }
+
+    /// Despawn the orphaned entities that were not reclaimed before the timeout of [`OrphanedEntities`]
+    pub(super) fn expire_orphaned_entities(
+        mut commands: Commands,
+        time: Res<Time>,
+        mut orphaned: ResMut<OrphanedEntities>,
+    ) {
+        let Some(timeout) = orphaned.timeout else {
+            return;
         };
+        let delta = time.delta();
+        orphaned.sessions.retain(|session_token, session| {
+            session.elapsed += delta;
+            if session.elapsed < timeout {
+                return true;
+            }
+            debug!(
+                ?session_token,
+                "Despawning the orphaned entities of client {:?} that were not reclaimed",
+                session.client_id
+            );
+            for entity in session.entities.iter() {
+                if let Some(mut command) = commands.get_entity(*entity) {
+                    command.insert(DespawnReason::OwnerDisconnected);
+                    command.despawn_recursive();
+                }
+            }
+            false
+        });
     }
 
     // TODO: is this necessary? calling server.stop() should already run the disconnection process
@@ -154,33 +709,76 @@ mod systems {
 
 impl Plugin for ClientsMetadataPlugin {
     fn build(&self, app: &mut App) {
+        app.register_type::<ControlledByRoom>();
+        app.register_type::<SessionToken>();
+        app.add_event::<ControlGained>();
+        app.add_event::<ControlLost>();
+        app.add_event::<ControlRejected>();
+        app.init_resource::<RoomControllers>();
+        app.init_resource::<OrphanedEntities>();
         app.add_systems(
             PostUpdate,
-            systems::handle_controlled_by_update
+            (
+                systems::resolve_controlled_by_room,
+                systems::validate_controlled_by.run_if(resource_exists::<ControlValidator>),
+                systems::handle_controlled_by_update,
+            )
+                .chain()
                 .in_set(InternalReplicationSet::<ServerMarker>::BeforeBuffer),
         );
+        app.configure_sets(
+            PostUpdate,
+            ServerReplicationSet::AfterControlledEntitiesUpdate
+                .run_if(is_started)
+                .after(systems::handle_controlled_by_update)
+                .before(InternalReplicationSet::<ServerMarker>::Buffer),
+        );
         app.add_observer(handle_controlled_by_remove);
-        // TODO: should we have a system that runs in the `Last` SystemSet instead? because the user might want to still have access
-        //  to the client entity
         app.add_observer(systems::handle_client_disconnect);
-        // we handle this in the `Last` `SystemSet` to let the user handle the disconnect event
-        // however they want first, before the client entity gets despawned
-        // app.add_systems(Last, systems::handle_server_disconnect);
+        app.add_observer(systems::reclaim_orphaned_entities);
+        app.add_systems(
+            PostUpdate,
+            systems::expire_orphaned_entities
+                .in_set(InternalReplicationSet::<ServerMarker>::BeforeBuffer),
+        );
+        app.add_systems(
+            Last,
+            systems::despawn_client_entities.in_set(ClientEntitySet::Despawn),
+        );
     }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::client::networking::ClientCommandsExt;
-    use crate::prelude::server::{ConnectionManager, ControlledBy, Replicate};
-    use crate::prelude::{client, ClientId, NetworkTarget, Replicated};
-    use crate::server::clients::ControlledEntities;
+    use crate::prelude::client::EntityDespawnEvent as ClientEntityDespawnEvent;
+    use crate::prelude::server::{
+        ConnectionManager, ControlledBy, DisconnectEvent, Replicate, ServerConfig,
+    };
+    use crate::prelude::{
+        client, ClientId, DespawnReason, LinkConditionerConfig, NetworkTarget, Replicated,
+    };
+    use crate::server::clients::{
+        ClientControlledEntities, ClientEntitySet, ControlGained, ControlLost, ControlRejected,
+        ControlValidator, ControlledByRoom, ControlledEntities, OrphanedEntities, RoomControllers,
+        SessionToken,
+    };
+    use crate::server::relevance::room::RoomId;
     use crate::server::replication::send::Lifetime;
     use crate::server::replication::send::ReplicationTarget;
+    use crate::server::replication::ServerReplicationSet;
+    use crate::shared::replication::authority::{AuthorityPeer, HasAuthority};
+    use crate::shared::replication::components::{Controlled, Replicating};
     use crate::tests::multi_stepper::{MultiBevyStepper, TEST_CLIENT_ID_1, TEST_CLIENT_ID_2};
     use crate::tests::stepper::{BevyStepper, TEST_CLIENT_ID};
     use bevy::ecs::entity::EntityHashMap;
-    use bevy::prelude::{default, Entity, With};
+    use bevy::ecs::event::EventCursor;
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::prelude::{
+        default, Added, BuildChildren, Entity, EventReader, Events, IntoSystemConfigs, Last,
+        OnInsert, OnRemove, PostUpdate, Query, ResMut, Resource, Trigger, Update, With,
+    };
+    use bevy::utils::Duration;
 
     /// Check that the Client Entities are updated after ControlledBy is added
     #[test]
@@ -236,61 +834,204 @@ mod tests {
         );
     }
 
-    /// Check that the ControlledEntities components are updated after ControlledBy is removed
+    /// Same as `test_insert_controlled_by`, but half of the packets are dropped:
+    /// the ControlledEntities on the server and the Controlled marker on the clients still converge
     #[test]
-    fn test_removed_controlled_by() {
-        let mut stepper = BevyStepper::default();
+    fn test_insert_controlled_by_packet_loss() {
+        let mut stepper = MultiBevyStepper::with_conditioner(LinkConditionerConfig {
+            incoming_latency: Duration::from_millis(20),
+            incoming_jitter: Duration::from_millis(5),
+            incoming_loss: 0.5,
+        });
 
         let server_entity = stepper
             .server_app
             .world_mut()
             .spawn(Replicate {
                 controlled_by: ControlledBy {
-                    target: NetworkTarget::All,
+                    target: NetworkTarget::Single(ClientId::Netcode(TEST_CLIENT_ID_1)),
                     ..default()
                 },
                 ..default()
             })
             .id();
+        for _ in 0..200 {
+            stepper.frame_step();
+        }
 
-        stepper.frame_step();
-
-        // check that the entity was marked as controlled by the client
-        let client_entity = stepper
+        let client_entity_1 = stepper
             .server_app
             .world()
             .resource::<ConnectionManager>()
-            .client_entity(ClientId::Netcode(TEST_CLIENT_ID))
+            .client_entity(ClientId::Netcode(TEST_CLIENT_ID_1))
+            .unwrap();
+        let client_entity_2 = stepper
+            .server_app
+            .world()
+            .resource::<ConnectionManager>()
+            .client_entity(ClientId::Netcode(TEST_CLIENT_ID_2))
             .unwrap();
         assert_eq!(
             stepper
                 .server_app
                 .world()
-                .get::<ControlledEntities>(client_entity)
+                .get::<ControlledEntities>(client_entity_1)
                 .unwrap(),
             &ControlledEntities(EntityHashMap::from_iter([(
                 server_entity,
                 Lifetime::SessionBased
             )]))
         );
+        assert_eq!(
+            stepper
+                .server_app
+                .world()
+                .get::<ControlledEntities>(client_entity_2)
+                .unwrap(),
+            &ControlledEntities(EntityHashMap::default())
+        );
 
-        // despawn the entity (which removes ControlledBy)
-        stepper.server_app.world_mut().despawn(server_entity);
+        // the entity was replicated to both clients, and only client 1 knows that it controls it
+        let client_1_entity = stepper
+            .client_app_1
+            .world()
+            .resource::<client::ConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(server_entity)
+            .expect("entity was not replicated to client 1");
+        let client_2_entity = stepper
+            .client_app_2
+            .world()
+            .resource::<client::ConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(server_entity)
+            .expect("entity was not replicated to client 2");
+        assert!(stepper
+            .client_app_1
+            .world()
+            .get::<Controlled>(client_1_entity)
+            .is_some());
+        assert!(stepper
+            .client_app_2
+            .world()
+            .get::<Controlled>(client_2_entity)
+            .is_none());
+    }
 
-        // check that the ControlledBy Entities have been updated (via observer)
-        assert!(!stepper
+    /// By default an entity is not controlled by any client, even with `..default()`
+    #[test]
+    fn test_controlled_by_default_targets_no_client() {
+        assert_eq!(
+            ControlledBy::default(),
+            ControlledBy {
+                target: NetworkTarget::None,
+                lifetime: Lifetime::SessionBased,
+            }
+        );
+
+        let mut stepper = BevyStepper::default();
+        stepper.server_app.world_mut().spawn(Replicate {
+            controlled_by: ControlledBy {
+                lifetime: Lifetime::Persistent,
+                ..default()
+            },
+            ..default()
+        });
+        stepper.frame_step();
+
+        let client_entity = stepper
+            .server_app
+            .world()
+            .resource::<ConnectionManager>()
+            .client_entity(ClientId::Netcode(TEST_CLIENT_ID))
+            .unwrap();
+        assert!(stepper
             .server_app
             .world()
             .get::<ControlledEntities>(client_entity)
             .unwrap()
-            .contains_key(&server_entity));
+            .is_empty());
     }
 
-    /// Check that when a client disconnects, its controlled entities get despawned
-    /// on the server
+    /// Check that ControlledBy::resolve only returns the connected clients matching the target
     #[test]
-    fn test_controlled_by_despawn_on_client_disconnect() {
+    fn test_controlled_by_resolve() {
+        let stepper = MultiBevyStepper::default();
+        let client_1 = ClientId::Netcode(TEST_CLIENT_ID_1);
+        let client_2 = ClientId::Netcode(TEST_CLIENT_ID_2);
+        let sender = stepper.server_app.world().resource::<ConnectionManager>();
+        let resolve = |target: NetworkTarget| {
+            let mut clients = ControlledBy {
+                target,
+                ..default()
+            }
+            .resolve(sender)
+            .collect::<Vec<_>>();
+            clients.sort_by_key(|client_id| client_id.to_bits());
+            clients
+        };
+
+        assert_eq!(resolve(NetworkTarget::None), vec![]);
+        assert_eq!(resolve(NetworkTarget::Single(client_1)), vec![client_1]);
+        assert_eq!(resolve(NetworkTarget::All), vec![client_1, client_2]);
+        assert_eq!(
+            resolve(NetworkTarget::AllExceptSingle(client_1)),
+            vec![client_2]
+        );
+        // clients that are not connected are ignored
+        assert_eq!(
+            resolve(NetworkTarget::Only(vec![client_2, ClientId::Netcode(3)])),
+            vec![client_2]
+        );
+    }
+
+    #[derive(Resource, Default)]
+    struct ControlledOnSpawnFrame(bool);
+
+    /// Check that systems in `AfterControlledEntitiesUpdate` see the ControlledEntities
+    /// updated with the ControlledBy changes of the same frame
+    #[test]
+    fn test_after_controlled_entities_update_set() {
         let mut stepper = BevyStepper::default();
+        stepper.server_app.init_resource::<ControlledOnSpawnFrame>();
+        stepper.server_app.add_systems(
+            PostUpdate,
+            (|query: Query<&ControlledEntities>,
+              new_entities: Query<Entity, Added<ControlledBy>>,
+              mut controlled: ResMut<ControlledOnSpawnFrame>| {
+                for entity in new_entities.iter() {
+                    controlled.0 = query.iter().any(|c| c.contains(&entity));
+                }
+            })
+            .in_set(ServerReplicationSet::AfterControlledEntitiesUpdate),
+        );
+
+        stepper.server_app.world_mut().spawn(Replicate {
+            controlled_by: ControlledBy {
+                target: NetworkTarget::Single(ClientId::Netcode(TEST_CLIENT_ID)),
+                ..default()
+            },
+            ..default()
+        });
+        stepper.frame_step();
+        assert!(
+            stepper
+                .server_app
+                .world()
+                .resource::<ControlledOnSpawnFrame>()
+                .0
+        );
+    }
+
+    /// Check that the entity is removed from the ControlledEntities of the clients that
+    /// are not targeted anymore after ControlledBy is updated
+    #[test]
+    fn test_update_controlled_by() {
+        let mut stepper = MultiBevyStepper::default();
+        let client_1 = ClientId::Netcode(TEST_CLIENT_ID_1);
+        let client_2 = ClientId::Netcode(TEST_CLIENT_ID_2);
 
         let server_entity = stepper
             .server_app
@@ -303,55 +1044,909 @@ mod tests {
                 ..default()
             })
             .id();
-        let server_entity_2 = stepper
-            .server_app
-            .world_mut()
-            .spawn(Replicate {
-                controlled_by: ControlledBy {
-                    target: NetworkTarget::All,
-                    lifetime: Lifetime::Persistent,
-                },
-                ..default()
-            })
-            .id();
-
         stepper.frame_step();
 
-        // check that the entity was marked as controlled by client_1
-        let client_entity = stepper
+        let client_entity_1 = stepper
             .server_app
             .world()
             .resource::<ConnectionManager>()
-            .client_entity(ClientId::Netcode(TEST_CLIENT_ID))
+            .client_entity(client_1)
             .unwrap();
-        assert_eq!(
+        let client_entity_2 = stepper
+            .server_app
+            .world()
+            .resource::<ConnectionManager>()
+            .client_entity(client_2)
+            .unwrap();
+        let controls = |stepper: &MultiBevyStepper, client_entity: Entity| {
             stepper
                 .server_app
                 .world()
                 .get::<ControlledEntities>(client_entity)
-                .unwrap(),
-            &ControlledEntities(EntityHashMap::from_iter([
-                (server_entity, Lifetime::SessionBased),
-                (server_entity_2, Lifetime::Persistent)
-            ]))
-        );
-
-        // client disconnects
-        stepper.client_app.world_mut().disconnect_client();
+                .unwrap()
+                .contains(&server_entity)
+        };
+        assert!(controls(&stepper, client_entity_1));
+        assert!(controls(&stepper, client_entity_2));
 
-        // TODO: why do we need to run frame_step twice for this to work?
-        stepper.frame_step();
-        stepper.frame_step();
+        // All -> Single: client 1 loses control
+        stepper
+            .server_app
+            .world_mut()
+            .get_mut::<ControlledBy>(server_entity)
+            .unwrap()
+            .target = NetworkTarget::Single(client_2);
+        stepper.frame_step();
+        assert!(!controls(&stepper, client_entity_1));
+        assert!(controls(&stepper, client_entity_2));
+
+        // Only([1,2]) -> Only([2,3]): only client 1 loses control
+        stepper
+            .server_app
+            .world_mut()
+            .get_mut::<ControlledBy>(server_entity)
+            .unwrap()
+            .target = NetworkTarget::Only(vec![client_1, client_2]);
+        stepper.frame_step();
+        assert!(controls(&stepper, client_entity_1));
+        assert!(controls(&stepper, client_entity_2));
+        stepper
+            .server_app
+            .world_mut()
+            .get_mut::<ControlledBy>(server_entity)
+            .unwrap()
+            .target = NetworkTarget::Only(vec![client_2, ClientId::Netcode(3)]);
+        stepper.frame_step();
+        assert!(!controls(&stepper, client_entity_1));
+        assert!(controls(&stepper, client_entity_2));
+    }
+
+    /// Check that the ControlledEntities are updated when the room of an entity or the owner
+    /// of a room changes
+    #[test]
+    fn test_controlled_by_room() {
+        let mut stepper = MultiBevyStepper::default();
+        let client_1 = ClientId::Netcode(TEST_CLIENT_ID_1);
+        let client_2 = ClientId::Netcode(TEST_CLIENT_ID_2);
+        let room_1 = RoomId(1);
+        let room_2 = RoomId(2);
+        {
+            let mut controllers = stepper
+                .server_app
+                .world_mut()
+                .resource_mut::<RoomControllers>();
+            controllers.set(room_1, NetworkTarget::Single(client_1));
+            controllers.set(room_2, NetworkTarget::Single(client_2));
+        }
+
+        let server_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn((Replicate::default(), ControlledByRoom(room_1)))
+            .id();
+        stepper.frame_step();
+
+        let client_entity_1 = stepper
+            .server_app
+            .world()
+            .resource::<ConnectionManager>()
+            .client_entity(client_1)
+            .unwrap();
+        let client_entity_2 = stepper
+            .server_app
+            .world()
+            .resource::<ConnectionManager>()
+            .client_entity(client_2)
+            .unwrap();
+        let controls = |stepper: &MultiBevyStepper, client_entity: Entity| {
+            stepper
+                .server_app
+                .world()
+                .get::<ControlledEntities>(client_entity)
+                .unwrap()
+                .contains(&server_entity)
+        };
+        assert!(controls(&stepper, client_entity_1));
+        assert!(!controls(&stepper, client_entity_2));
+
+        // move the entity to room 2
+        stepper
+            .server_app
+            .world_mut()
+            .entity_mut(server_entity)
+            .insert(ControlledByRoom(room_2));
+        stepper.frame_step();
+        assert!(!controls(&stepper, client_entity_1));
+        assert!(controls(&stepper, client_entity_2));
+
+        // reassign the owner of room 2
+        stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<RoomControllers>()
+            .set(room_2, NetworkTarget::Only(vec![client_1, client_2]));
+        stepper.frame_step();
+        assert!(controls(&stepper, client_entity_1));
+        assert!(controls(&stepper, client_entity_2));
+
+        // the room doesn't have any controller
+        stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<RoomControllers>()
+            .remove(room_2);
+        stepper.frame_step();
+        assert!(!controls(&stepper, client_entity_1));
+        assert!(!controls(&stepper, client_entity_2));
+    }
+
+    /// Check that exactly one ControlGained/ControlLost event is emitted for each change of the
+    /// ControlledEntities, and none for updates that don't change them
+    #[test]
+    fn test_control_events() {
+        let mut stepper = MultiBevyStepper::default();
+        let client_1 = ClientId::Netcode(TEST_CLIENT_ID_1);
+        let client_2 = ClientId::Netcode(TEST_CLIENT_ID_2);
+        fn drain_events(stepper: &mut MultiBevyStepper) -> (Vec<ControlGained>, Vec<ControlLost>) {
+            let world = stepper.server_app.world_mut();
+            let gained = world
+                .resource_mut::<Events<ControlGained>>()
+                .drain()
+                .collect();
+            let lost = world
+                .resource_mut::<Events<ControlLost>>()
+                .drain()
+                .collect();
+            (gained, lost)
+        }
+
+        let entity = stepper
+            .server_app
+            .world_mut()
+            .spawn(Replicate {
+                controlled_by: ControlledBy {
+                    target: NetworkTarget::Single(client_1),
+                    ..default()
+                },
+                ..default()
+            })
+            .id();
+        stepper.frame_step();
+        stepper.frame_step();
+        assert_eq!(
+            drain_events(&mut stepper),
+            (
+                vec![ControlGained {
+                    client_id: client_1,
+                    entity
+                }],
+                vec![]
+            )
+        );
+        // the client is notified that it gained control of the entity
+        let client_gained: Vec<client::ControlGained> = stepper
+            .client_app_1
+            .world_mut()
+            .resource_mut::<Events<client::ControlGained>>()
+            .drain()
+            .collect();
+        assert_eq!(client_gained.len(), 1);
+        assert!(stepper
+            .client_app_2
+            .world()
+            .resource::<Events<client::ControlGained>>()
+            .is_empty());
+
+        // no-op update
+        stepper
+            .server_app
+            .world_mut()
+            .get_mut::<ControlledBy>(entity)
+            .unwrap()
+            .target = NetworkTarget::Only(vec![client_1]);
+        stepper.frame_step();
+        assert_eq!(drain_events(&mut stepper), (vec![], vec![]));
+
+        // client 1 loses control, client 2 gains control
+        stepper
+            .server_app
+            .world_mut()
+            .get_mut::<ControlledBy>(entity)
+            .unwrap()
+            .target = NetworkTarget::Single(client_2);
+        stepper.frame_step();
+        assert_eq!(
+            drain_events(&mut stepper),
+            (
+                vec![ControlGained {
+                    client_id: client_2,
+                    entity
+                }],
+                vec![ControlLost {
+                    client_id: client_1,
+                    entity
+                }]
+            )
+        );
+
+        // ControlledBy is removed
+        stepper
+            .server_app
+            .world_mut()
+            .entity_mut(entity)
+            .remove::<ControlledBy>();
+        stepper.frame_step();
+        assert_eq!(
+            drain_events(&mut stepper),
+            (
+                vec![],
+                vec![ControlLost {
+                    client_id: client_2,
+                    entity
+                }]
+            )
+        );
+    }
+
+    /// Check that the ControlledEntities of a client can be looked up from its ClientId
+    #[test]
+    fn test_client_controlled_entities() {
+        let mut stepper = MultiBevyStepper::default();
+        let client_1 = ClientId::Netcode(TEST_CLIENT_ID_1);
+        let client_2 = ClientId::Netcode(TEST_CLIENT_ID_2);
+        let server_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn(Replicate {
+                controlled_by: ControlledBy {
+                    target: NetworkTarget::Single(client_1),
+                    ..default()
+                },
+                ..default()
+            })
+            .id();
+        stepper.frame_step();
+
+        stepper
+            .server_app
+            .world_mut()
+            .run_system_once(move |controlled_entities: ClientControlledEntities| {
+                assert_eq!(
+                    controlled_entities.get(client_1).unwrap().entities(),
+                    vec![server_entity]
+                );
+                // connected client that doesn't control any entity
+                assert!(controlled_entities.get(client_2).unwrap().is_empty());
+                // unknown client
+                assert!(controlled_entities.get(ClientId::Netcode(3)).is_none());
+            })
+            .unwrap();
+    }
+
+    #[derive(Resource, Default)]
+    struct ControlledOnDisconnectEvent(Vec<Option<Vec<Entity>>>);
+
+    /// Check that the ControlledEntities of a client can be looked up from its ClientId
+    /// while handling its DisconnectEvent
+    #[test]
+    fn test_client_controlled_entities_on_disconnect() {
+        let mut stepper = BevyStepper::default();
+        stepper
+            .server_app
+            .init_resource::<ControlledOnDisconnectEvent>();
+        stepper.server_app.add_systems(
+            Update,
+            |mut disconnections: EventReader<DisconnectEvent>,
+             controlled_entities: ClientControlledEntities,
+             mut controlled: ResMut<ControlledOnDisconnectEvent>| {
+                for disconnection in disconnections.read() {
+                    controlled.0.push(
+                        controlled_entities
+                            .get(disconnection.client_id)
+                            .map(|c| c.entities()),
+                    );
+                }
+            },
+        );
+        let server_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn(Replicate {
+                controlled_by: ControlledBy {
+                    target: NetworkTarget::All,
+                    ..default()
+                },
+                ..default()
+            })
+            .id();
+        stepper.frame_step();
+
+        stepper.client_app.world_mut().disconnect_client();
+        stepper.frame_step();
+        stepper.frame_step();
+        assert_eq!(
+            stepper
+                .server_app
+                .world()
+                .resource::<ControlledOnDisconnectEvent>()
+                .0,
+            vec![Some(vec![server_entity])]
+        );
+        // the client entity is despawned at the end of the frame
+        stepper
+            .server_app
+            .world_mut()
+            .run_system_once(|controlled_entities: ClientControlledEntities| {
+                assert!(controlled_entities
+                    .get(ClientId::Netcode(TEST_CLIENT_ID))
+                    .is_none());
+            })
+            .unwrap();
+    }
+
+    /// Check that the ControlledEntities components are updated after ControlledBy is removed
+    #[test]
+    fn test_removed_controlled_by() {
+        let mut stepper = BevyStepper::default();
+
+        let server_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn(Replicate {
+                controlled_by: ControlledBy {
+                    target: NetworkTarget::All,
+                    ..default()
+                },
+                ..default()
+            })
+            .id();
+
+        stepper.frame_step();
+
+        // check that the entity was marked as controlled by the client
+        let client_entity = stepper
+            .server_app
+            .world()
+            .resource::<ConnectionManager>()
+            .client_entity(ClientId::Netcode(TEST_CLIENT_ID))
+            .unwrap();
+        assert_eq!(
+            stepper
+                .server_app
+                .world()
+                .get::<ControlledEntities>(client_entity)
+                .unwrap(),
+            &ControlledEntities(EntityHashMap::from_iter([(
+                server_entity,
+                Lifetime::SessionBased
+            )]))
+        );
+
+        // despawn the entity (which removes ControlledBy)
+        stepper.server_app.world_mut().despawn(server_entity);
+
+        // check that the ControlledBy Entities have been updated (via observer)
+        assert!(!stepper
+            .server_app
+            .world()
+            .get::<ControlledEntities>(client_entity)
+            .unwrap()
+            .contains_key(&server_entity));
+    }
+
+    /// Check that when a client disconnects, its controlled entities get despawned
+    /// on the server
+    #[test]
+    fn test_controlled_by_despawn_on_client_disconnect() {
+        let mut stepper = BevyStepper::default();
+
+        let server_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn(Replicate {
+                controlled_by: ControlledBy {
+                    target: NetworkTarget::All,
+                    ..default()
+                },
+                ..default()
+            })
+            .id();
+        let server_entity_2 = stepper
+            .server_app
+            .world_mut()
+            .spawn(Replicate {
+                controlled_by: ControlledBy {
+                    target: NetworkTarget::All,
+                    lifetime: Lifetime::Persistent,
+                },
+                ..default()
+            })
+            .id();
+
+        stepper.frame_step();
+
+        // check that the entity was marked as controlled by client_1
+        let client_entity = stepper
+            .server_app
+            .world()
+            .resource::<ConnectionManager>()
+            .client_entity(ClientId::Netcode(TEST_CLIENT_ID))
+            .unwrap();
+        assert_eq!(
+            stepper
+                .server_app
+                .world()
+                .get::<ControlledEntities>(client_entity)
+                .unwrap(),
+            &ControlledEntities(EntityHashMap::from_iter([
+                (server_entity, Lifetime::SessionBased),
+                (server_entity_2, Lifetime::Persistent)
+            ]))
+        );
+
+        // client disconnects
+        stepper.client_app.world_mut().disconnect_client();
+
+        // TODO: why do we need to run frame_step twice for this to work?
+        stepper.frame_step();
+        stepper.frame_step();
+        assert!(stepper
+            .server_app
+            .world()
+            .get_entity(server_entity)
+            .is_err());
+        assert!(stepper
+            .server_app
+            .world()
+            .get_entity(server_entity_2)
+            .is_ok());
+    }
+
+    #[derive(Resource, Default)]
+    struct ControlledOnDisconnect(Vec<Option<Vec<Entity>>>);
+
+    /// Check that systems that run before `ClientEntitySet::Despawn` can still read the
+    /// ControlledEntities of a client that disconnected, and that the client entity is despawned after
+    #[test]
+    fn test_client_entity_despawn_set() {
+        let mut stepper = BevyStepper::default();
+        stepper.server_app.init_resource::<ControlledOnDisconnect>();
+        stepper.server_app.add_systems(
+            Last,
+            (|mut disconnections: EventReader<DisconnectEvent>,
+              query: Query<&ControlledEntities>,
+              mut controlled: ResMut<ControlledOnDisconnect>| {
+                for disconnection in disconnections.read() {
+                    controlled
+                        .0
+                        .push(query.get(disconnection.entity).ok().map(|c| c.entities()));
+                }
+            })
+            .before(ClientEntitySet::Despawn),
+        );
+        let server_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn(Replicate {
+                controlled_by: ControlledBy {
+                    target: NetworkTarget::All,
+                    ..default()
+                },
+                ..default()
+            })
+            .id();
+        stepper.frame_step();
+        let client_entity = stepper
+            .server_app
+            .world()
+            .resource::<ConnectionManager>()
+            .client_entity(ClientId::Netcode(TEST_CLIENT_ID))
+            .unwrap();
+
+        stepper.client_app.world_mut().disconnect_client();
+        stepper.frame_step();
+        stepper.frame_step();
+        assert_eq!(
+            stepper
+                .server_app
+                .world()
+                .resource::<ControlledOnDisconnect>()
+                .0,
+            vec![Some(vec![server_entity])]
+        );
+        assert!(stepper
+            .server_app
+            .world()
+            .get_entity(client_entity)
+            .is_err());
+    }
+
+    /// Check that the client entity is kept after the client disconnects if
+    /// `despawn_on_disconnect` is false
+    #[test]
+    fn test_client_entity_kept_on_disconnect() {
+        let mut stepper = BevyStepper::default();
+        stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<ServerConfig>()
+            .client_entity
+            .despawn_on_disconnect = false;
+        let client_entity = stepper
+            .server_app
+            .world()
+            .resource::<ConnectionManager>()
+            .client_entity(ClientId::Netcode(TEST_CLIENT_ID))
+            .unwrap();
+
+        stepper.client_app.world_mut().disconnect_client();
+        for _ in 0..5 {
+            stepper.frame_step();
+        }
         assert!(stepper
             .server_app
             .world()
-            .get_entity(server_entity)
+            .resource::<ConnectionManager>()
+            .client_entity(ClientId::Netcode(TEST_CLIENT_ID))
             .is_err());
         assert!(stepper
             .server_app
             .world()
-            .get_entity(server_entity_2)
+            .get::<ControlledEntities>(client_entity)
+            .is_some());
+    }
+
+    /// Number of times each entity was despawned, and was sent a despawn command
+    #[derive(Resource, Default)]
+    struct HierarchyDespawns {
+        despawned: EntityHashMap<usize>,
+        despawn_commands: EntityHashMap<usize>,
+    }
+
+    /// Check that when a client disconnects, a hierarchy of controlled entities is despawned
+    /// only once, from its root
+    #[test]
+    fn test_controlled_hierarchy_despawn_on_client_disconnect() {
+        let mut stepper = BevyStepper::default();
+        stepper.server_app.init_resource::<HierarchyDespawns>();
+        stepper.server_app.add_observer(
+            |trigger: Trigger<OnRemove, Replicating>, mut despawns: ResMut<HierarchyDespawns>| {
+                *despawns.despawned.entry(trigger.entity()).or_default() += 1;
+            },
+        );
+        // the disconnection inserts the DespawnReason on each entity that it despawns
+        stepper.server_app.add_observer(
+            |trigger: Trigger<OnInsert, DespawnReason>, mut despawns: ResMut<HierarchyDespawns>| {
+                *despawns
+                    .despawn_commands
+                    .entry(trigger.entity())
+                    .or_default() += 1;
+            },
+        );
+        let controlled_by = ControlledBy {
+            target: NetworkTarget::All,
+            ..default()
+        };
+        let parent = stepper
+            .server_app
+            .world_mut()
+            .spawn(Replicate {
+                controlled_by: controlled_by.clone(),
+                ..default()
+            })
+            .id();
+        let child = stepper
+            .server_app
+            .world_mut()
+            .spawn(Replicate {
+                controlled_by: controlled_by.clone(),
+                ..default()
+            })
+            .set_parent(parent)
+            .id();
+        let grandchild = stepper
+            .server_app
+            .world_mut()
+            .spawn(Replicate {
+                controlled_by,
+                ..default()
+            })
+            .set_parent(child)
+            .id();
+        stepper.frame_step();
+
+        let client_entity = stepper
+            .server_app
+            .world()
+            .resource::<ConnectionManager>()
+            .client_entity(ClientId::Netcode(TEST_CLIENT_ID))
+            .unwrap();
+        let controlled_entities = stepper
+            .server_app
+            .world()
+            .get::<ControlledEntities>(client_entity)
+            .unwrap();
+        assert!(controlled_entities.contains(&parent));
+        assert!(controlled_entities.contains(&child));
+        assert!(controlled_entities.contains(&grandchild));
+
+        // client disconnects
+        stepper.client_app.world_mut().disconnect_client();
+        stepper.frame_step();
+        stepper.frame_step();
+        for entity in [parent, child, grandchild, client_entity] {
+            assert!(stepper.server_app.world().get_entity(entity).is_err());
+        }
+        let despawns = stepper.server_app.world().resource::<HierarchyDespawns>();
+        // only the root is despawned explicitly, its descendants are despawned recursively
+        assert_eq!(despawns.despawn_commands.len(), 1);
+        assert_eq!(despawns.despawn_commands.get(&parent), Some(&1));
+        for entity in [parent, child, grandchild] {
+            assert_eq!(despawns.despawned.get(&entity), Some(&1));
+        }
+    }
+
+    /// Check that the other clients are told that the entity was despawned because its owner disconnected
+    #[test]
+    fn test_despawn_reason_on_client_disconnect() {
+        let mut stepper = MultiBevyStepper::default();
+        let server_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn(Replicate {
+                controlled_by: ControlledBy {
+                    target: NetworkTarget::Single(ClientId::Netcode(TEST_CLIENT_ID_1)),
+                    ..default()
+                },
+                ..default()
+            })
+            .id();
+        stepper.frame_step();
+        stepper.frame_step();
+        let client_entity = stepper
+            .client_app_2
+            .world()
+            .resource::<client::ConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(server_entity)
+            .unwrap();
+
+        // client 1 disconnects
+        stepper.client_app_1.world_mut().disconnect_client();
+        let mut cursor = EventCursor::<ClientEntityDespawnEvent>::default();
+        let mut despawns = vec![];
+        for _ in 0..4 {
+            stepper.frame_step();
+            let events = stepper
+                .client_app_2
+                .world()
+                .resource::<Events<ClientEntityDespawnEvent>>();
+            despawns.extend(
+                cursor
+                    .read(events)
+                    .map(|event| (event.entity(), event.reason())),
+            );
+        }
+        assert_eq!(
+            despawns,
+            vec![(client_entity, DespawnReason::OwnerDisconnected)]
+        );
+    }
+
+    /// Check that when a client disconnects, the persistent entities that it had authority over
+    /// are not despawned and the authority is given back to the server
+    #[test]
+    fn test_controlled_by_persistent_authority_on_client_disconnect() {
+        let mut stepper = BevyStepper::default();
+        let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+
+        let session_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn(Replicate {
+                authority: AuthorityPeer::Client(client_id),
+                controlled_by: ControlledBy {
+                    target: NetworkTarget::Single(client_id),
+                    lifetime: Lifetime::SessionBased,
+                },
+                ..default()
+            })
+            .id();
+        let persistent_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn(Replicate {
+                authority: AuthorityPeer::Client(client_id),
+                controlled_by: ControlledBy {
+                    target: NetworkTarget::Single(client_id),
+                    lifetime: Lifetime::Persistent,
+                },
+                ..default()
+            })
+            .id();
+        stepper.frame_step();
+        assert!(stepper
+            .server_app
+            .world()
+            .get::<HasAuthority>(persistent_entity)
+            .is_none());
+
+        // client disconnects
+        stepper.client_app.world_mut().disconnect_client();
+        stepper.frame_step();
+        stepper.frame_step();
+
+        assert!(stepper
+            .server_app
+            .world()
+            .get_entity(session_entity)
+            .is_err());
+        assert!(stepper
+            .server_app
+            .world()
+            .get_entity(persistent_entity)
             .is_ok());
+        assert_eq!(
+            stepper
+                .server_app
+                .world()
+                .get::<AuthorityPeer>(persistent_entity),
+            Some(&AuthorityPeer::Server)
+        );
+        assert!(stepper
+            .server_app
+            .world()
+            .get::<HasAuthority>(persistent_entity)
+            .is_some());
+    }
+
+    /// Check that a client that reconnects with the same SessionToken takes back the control of
+    /// the persistent entities of its previous session
+    #[test]
+    fn test_reclaim_orphaned_entities_on_reconnect() {
+        let mut stepper = BevyStepper::default();
+        let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+        let session_token = SessionToken(7);
+        let client_entity = stepper
+            .server_app
+            .world()
+            .resource::<ConnectionManager>()
+            .client_entity(client_id)
+            .unwrap();
+        stepper
+            .server_app
+            .world_mut()
+            .entity_mut(client_entity)
+            .insert(session_token);
+        let persistent_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn(Replicate {
+                controlled_by: ControlledBy {
+                    target: NetworkTarget::Single(client_id),
+                    lifetime: Lifetime::Persistent,
+                },
+                ..default()
+            })
+            .id();
+        stepper.frame_step();
+
+        // client disconnects
+        stepper.client_app.world_mut().disconnect_client();
+        stepper.frame_step();
+        stepper.frame_step();
+        assert_eq!(
+            stepper
+                .server_app
+                .world()
+                .resource::<OrphanedEntities>()
+                .get(session_token),
+            Some([persistent_entity].as_slice())
+        );
+
+        // client reconnects
+        stepper.client_app.world_mut().connect_client();
+        for _ in 0..100 {
+            if stepper
+                .server_app
+                .world()
+                .resource::<ConnectionManager>()
+                .client_entity(client_id)
+                .is_ok()
+            {
+                break;
+            }
+            stepper.frame_step();
+        }
+        let new_client_entity = stepper
+            .server_app
+            .world()
+            .resource::<ConnectionManager>()
+            .client_entity(client_id)
+            .unwrap();
+        assert_ne!(new_client_entity, client_entity);
+        stepper
+            .server_app
+            .world_mut()
+            .entity_mut(new_client_entity)
+            .insert(session_token);
+        stepper.frame_step();
+
+        assert!(stepper
+            .server_app
+            .world()
+            .resource::<OrphanedEntities>()
+            .get(session_token)
+            .is_none());
+        assert!(stepper
+            .server_app
+            .world()
+            .get::<ControlledEntities>(new_client_entity)
+            .unwrap()
+            .contains(&persistent_entity));
+    }
+
+    /// The orphaned entities that are not reclaimed before the timeout are despawned
+    #[test]
+    fn test_orphaned_entities_timeout() {
+        let mut stepper = BevyStepper::default();
+        stepper
+            .server_app
+            .insert_resource(OrphanedEntities::with_timeout(Duration::from_millis(100)));
+        let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+        let session_token = SessionToken(7);
+        let client_entity = stepper
+            .server_app
+            .world()
+            .resource::<ConnectionManager>()
+            .client_entity(client_id)
+            .unwrap();
+        stepper
+            .server_app
+            .world_mut()
+            .entity_mut(client_entity)
+            .insert(session_token);
+        let persistent_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn(Replicate {
+                controlled_by: ControlledBy {
+                    target: NetworkTarget::Single(client_id),
+                    lifetime: Lifetime::Persistent,
+                },
+                ..default()
+            })
+            .id();
+        stepper.frame_step();
+
+        stepper.client_app.world_mut().disconnect_client();
+        stepper.frame_step();
+        stepper.frame_step();
+        assert_eq!(
+            stepper
+                .server_app
+                .world()
+                .resource::<OrphanedEntities>()
+                .get(session_token),
+            Some([persistent_entity].as_slice())
+        );
+
+        // the player does not reconnect before the timeout
+        for _ in 0..20 {
+            stepper.frame_step();
+        }
+        assert!(stepper
+            .server_app
+            .world()
+            .resource::<OrphanedEntities>()
+            .get(session_token)
+            .is_none());
+        assert!(stepper
+            .server_app
+            .world()
+            .get_entity(persistent_entity)
+            .is_err());
     }
 
     /// The owning client despawns the entity that they control.
@@ -400,4 +1995,68 @@ mod tests {
             stepper.frame_step();
         }
     }
+
+    /// Check that the ControlValidator can trim the clients of a multi-target ControlledBy
+    #[test]
+    fn test_control_validator_rejects_client() {
+        let mut stepper = MultiBevyStepper::default();
+        let client_1 = ClientId::Netcode(TEST_CLIENT_ID_1);
+        let client_2 = ClientId::Netcode(TEST_CLIENT_ID_2);
+        stepper
+            .server_app
+            .insert_resource(ControlValidator::new(move |_, _, client_id| {
+                client_id != client_2
+            }));
+
+        let entity = stepper
+            .server_app
+            .world_mut()
+            .spawn(Replicate {
+                controlled_by: ControlledBy {
+                    target: NetworkTarget::Only(vec![client_1, client_2]),
+                    ..default()
+                },
+                ..default()
+            })
+            .id();
+        stepper.frame_step();
+
+        // the target was trimmed to the allowed client
+        let controlled_by = stepper
+            .server_app
+            .world()
+            .get::<ControlledBy>(entity)
+            .unwrap();
+        assert!(controlled_by.targets(&client_1));
+        assert!(!controlled_by.targets(&client_2));
+        let controls = |client_id: ClientId| {
+            let client_entity = stepper
+                .server_app
+                .world()
+                .resource::<ConnectionManager>()
+                .client_entity(client_id)
+                .unwrap();
+            stepper
+                .server_app
+                .world()
+                .get::<ControlledEntities>(client_entity)
+                .unwrap()
+                .contains(&entity)
+        };
+        assert!(controls(client_1));
+        assert!(!controls(client_2));
+        let rejected: Vec<ControlRejected> = stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<Events<ControlRejected>>()
+            .drain()
+            .collect();
+        assert_eq!(
+            rejected,
+            vec![ControlRejected {
+                client_id: client_2,
+                entity
+            }]
+        );
+    }
 }
@@ -3,12 +3,16 @@ use bevy::prelude::Resource;
 use governor::Quota;
 use nonzero_ext::nonzero;
 use std::sync::Arc;
+use std::time::Duration;
 
 use crate::connection::netcode::{Key, PRIVATE_KEY_BYTES};
 use crate::connection::server::{
-    ConnectionRequestHandler, DefaultConnectionRequestHandler, NetConfig,
+    ConnectionRequestDefault, ConnectionRequestHandler, DefaultConnectionRequestHandler, NetConfig,
 };
+use crate::packet::compression::CompressionConfig;
 use crate::prelude::ReplicationConfig;
+use crate::server::interpolation_snapshots::InterpolationSnapshotConfig;
+use crate::server::lag_compensation::LagCompensationConfig;
 use crate::shared::config::SharedConfig;
 use crate::shared::ping::manager::PingConfig;
 
@@ -22,8 +26,21 @@ pub struct NetcodeConfig {
     pub client_timeout_secs: i32,
     pub protocol_id: u64,
     pub private_key: Key,
-    /// A closure that will be used to accept or reject incoming connections
+    /// A closure that will be used to accept or reject incoming connections.
+    ///
+    /// It runs first, when the client's `ConnectToken` is received: a client that it rejects
+    /// never produces a [`ConnectionRequestEvent`](crate::server::events::ConnectionRequestEvent).
     pub connection_request_handler: Arc<dyn ConnectionRequestHandler>,
+    /// If true, a [`ConnectionRequestEvent`](crate::server::events::ConnectionRequestEvent) is emitted
+    /// for every client accepted by the `connection_request_handler`, and the client is only connected
+    /// once a system accepts the request (or the `connection_request_default` applies on the next frame).
+    ///
+    /// The default is false: clients are connected as soon as the handshake completes.
+    pub emit_connection_requests: bool,
+    /// What to do with a [`ConnectionRequestEvent`](crate::server::events::ConnectionRequestEvent)
+    /// that no system accepted or rejected during the frame.
+    /// The default is to accept the connection.
+    pub connection_request_default: ConnectionRequestDefault,
 }
 
 impl Default for NetcodeConfig {
@@ -35,6 +52,8 @@ impl Default for NetcodeConfig {
             protocol_id: 0,
             private_key: [0; PRIVATE_KEY_BYTES],
             connection_request_handler: Arc::new(DefaultConnectionRequestHandler),
+            emit_connection_requests: false,
+            connection_request_default: ConnectionRequestDefault::default(),
         }
     }
 }
@@ -53,6 +72,19 @@ impl NetcodeConfig {
         self.client_timeout_secs = client_timeout_secs;
         self
     }
+
+    pub fn with_connection_request_events(mut self, emit_connection_requests: bool) -> Self {
+        self.emit_connection_requests = emit_connection_requests;
+        self
+    }
+
+    pub fn with_connection_request_default(
+        mut self,
+        connection_request_default: ConnectionRequestDefault,
+    ) -> Self {
+        self.connection_request_default = connection_request_default;
+        self
+    }
 }
 
 /// Configuration related to sending packets
@@ -67,6 +99,8 @@ pub struct PacketConfig {
     pub per_client_send_bandwidth_cap: Quota,
     /// If false, there is no bandwidth cap and all messages are sent as soon as possible
     pub bandwidth_cap_enabled: bool,
+    /// Compression of the packets sent to the clients (must be the same as on the clients)
+    pub compression: CompressionConfig,
 }
 
 impl Default for PacketConfig {
@@ -76,6 +110,7 @@ impl Default for PacketConfig {
             // 56 KB/s bandwidth cap
             per_client_send_bandwidth_cap: Quota::per_second(nonzero!(56000u32)),
             bandwidth_cap_enabled: false,
+            compression: CompressionConfig::default(),
         }
     }
 }
@@ -96,6 +131,111 @@ impl PacketConfig {
         self.bandwidth_cap_enabled = true;
         self
     }
+
+    pub fn with_compression(mut self, compression: CompressionConfig) -> Self {
+        self.compression = compression;
+        self
+    }
+}
+
+/// Configuration related to the inputs received from the clients
+#[derive(Clone, Copy, Debug)]
+pub struct InputConfig {
+    /// How many ticks an input message can lag behind the most recent input message received
+    /// from the same client and still be applied.
+    ///
+    /// Input messages can be reordered by the network; the server buffers inputs by tick so that
+    /// a message that arrives late is still applied in tick order, as long as its ticks haven't
+    /// been consumed yet. Messages that are older than this window are dropped.
+    ///
+    /// The default is 16 ticks.
+    pub reorder_window: u16,
+}
+
+impl Default for InputConfig {
+    fn default() -> Self {
+        Self { reorder_window: 16 }
+    }
+}
+
+impl InputConfig {
+    pub fn with_reorder_window(mut self, reorder_window: u16) -> Self {
+        self.reorder_window = reorder_window;
+        self
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ShutdownConfig {
+    /// During a graceful shutdown (see [`stop_server_graceful`](crate::prelude::server::ServerCommandsExt::stop_server_graceful)),
+    /// the server waits for the clients to ack the shutdown notice and all the pending reliable messages.
+    /// Clients that haven't acked them after this duration are disconnected anyway.
+    ///
+    /// The default is 2 seconds.
+    pub timeout: Duration,
+}
+
+impl Default for ShutdownConfig {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(2),
+        }
+    }
+}
+
+impl ShutdownConfig {
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+/// Configuration of the entity that the server spawns for each connected client
+#[derive(Clone, Copy, Debug)]
+pub struct ClientEntityConfig {
+    /// If true, the client entity is despawned when the client disconnects.
+    ///
+    /// The despawn happens in the [`ClientEntitySet::Despawn`](crate::prelude::server::ClientEntitySet::Despawn)
+    /// set of the `Last` schedule, so that the systems handling the
+    /// [`DisconnectEvent`](crate::prelude::server::DisconnectEvent) can still access the client entity
+    /// and its [`ControlledEntities`](crate::prelude::server::ControlledEntities).
+    ///
+    /// If false, the client entity is kept after the disconnection (for example to keep statistics
+    /// across reconnections), and the user is responsible for despawning it.
+    ///
+    /// The default is true.
+    pub despawn_on_disconnect: bool,
+}
+
+impl Default for ClientEntityConfig {
+    fn default() -> Self {
+        Self {
+            despawn_on_disconnect: true,
+        }
+    }
+}
+
+impl ClientEntityConfig {
+    pub fn with_despawn_on_disconnect(mut self, despawn_on_disconnect: bool) -> Self {
+        self.despawn_on_disconnect = despawn_on_disconnect;
+        self
+    }
+}
+
+/// How the server allocates the [`NetworkId`](crate::prelude::NetworkId) of the entities it replicates
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum NetworkIdConfig {
+    /// Replicated entities do not get a [`NetworkId`](crate::prelude::NetworkId)
+    #[default]
+    Disabled,
+    /// Replicated entities get sequential ids starting from `seed`, in the order in which they
+    /// start being replicated.
+    ///
+    /// The allocation restarts from `seed` every time the server is started, so two runs with the same
+    /// inputs assign the same ids to the same entities, which makes it possible to correlate the logs
+    /// of different peers and of different runs.
+    /// Entities that start being replicated while the server is stopped do not get an id.
+    Deterministic { seed: u64 },
 }
 
 /// Configuration for the server plugin.
@@ -111,8 +251,14 @@ pub struct ServerConfig {
     /// clients can connect using the transport they prefer, and still play with each other!
     pub net: Vec<NetConfig>,
     pub packet: PacketConfig,
+    pub input: InputConfig,
     pub replication: ReplicationConfig,
     pub ping: PingConfig,
+    pub lag_compensation: LagCompensationConfig,
+    pub interpolation_snapshots: InterpolationSnapshotConfig,
+    pub shutdown: ShutdownConfig,
+    pub client_entity: ClientEntityConfig,
+    pub network_id: NetworkIdConfig,
 }
 
 #[cfg(test)]
@@ -1,10 +1,12 @@
 //! Specify how a Server sends/receives messages with a Client
 use bevy::ecs::component::Tick as BevyTick;
-use bevy::ecs::entity::{EntityHash, MapEntities};
-use bevy::prelude::{Component, Entity, Resource, World};
+use bevy::ecs::entity::{EntityHash, EntityHashSet, MapEntities};
+use bevy::math::Vec3;
+use bevy::prelude::{Component, Entity, Query, Reflect, ReflectComponent, Resource, World};
 use bevy::ptr::Ptr;
+use bevy::tasks::{ComputeTaskPool, TaskPool};
 use bevy::utils::{hashbrown, hashbrown::hash_map::Entry};
-use bevy::utils::{Duration, HashMap};
+use bevy::utils::{Duration, HashMap, HashSet};
 use bytes::Bytes;
 use tracing::{debug, info, info_span, trace, trace_span};
 #[cfg(feature = "trace")]
@@ -19,12 +21,13 @@ use crate::channel::senders::ChannelSend;
 use crate::client::message::ClientMessage;
 use crate::connection::id::ClientId;
 use crate::connection::netcode::MAX_PACKET_SIZE;
+use crate::packet::compression::PacketCompressor;
 use crate::packet::message_manager::MessageManager;
 use crate::packet::packet_builder::{Payload, RecvPayload};
 use crate::prelude::server::DisconnectEvent;
 use crate::prelude::{
-    ChannelKind, Message, PreSpawnedPlayerObject, ReplicationConfig, ReplicationGroup,
-    ShouldBePredicted,
+    Channel, ChannelKind, Message, MessageId, PreSpawnedPlayerObject, ReplicationConfig,
+    ReplicationGroup, ShouldBePredicted,
 };
 use crate::protocol::channel::ChannelRegistry;
 use crate::protocol::component::{
@@ -36,18 +39,27 @@ use crate::protocol::registry::NetId;
 use crate::serialize::reader::Reader;
 use crate::serialize::writer::Writer;
 use crate::serialize::{SerializationError, ToBytes};
+use crate::server::clients::ControlledEntities;
 use crate::server::config::PacketConfig;
-use crate::server::error::ServerError;
-use crate::server::events::{ConnectEvent, ServerEvents};
+use crate::server::error::{ClientLookupError, ServerError};
+use crate::server::events::{ConnectEvent, ReliableWindowFull, ServerEvents};
+use crate::server::interpolation_snapshots::{
+    InterpolationSnapshotConfig, InterpolationSnapshotHistory,
+};
+use crate::server::lag_compensation::{
+    ComponentSnapshot, LagCompensationConfig, LagCompensationHistory,
+};
 use crate::shared::events::connection::ConnectionEvents;
 use crate::shared::ping::manager::{PingConfig, PingManager};
 use crate::shared::ping::message::{Ping, Pong};
-use crate::shared::replication::components::ReplicationGroupId;
+use crate::shared::replication::authority::AuthorityPeer;
+use crate::shared::replication::components::{DespawnReason, ReplicationGroupId};
 use crate::shared::replication::delta::DeltaManager;
 use crate::shared::replication::entity_map::SendEntityMap;
 use crate::shared::replication::network_target::NetworkTarget;
 use crate::shared::replication::receive::ReplicationReceiver;
 use crate::shared::replication::send::ReplicationSender;
+use crate::shared::replication::subscription::ComponentSubscriptions;
 use crate::shared::replication::{EntityActionsMessage, EntityUpdatesMessage, ReplicationPeer};
 use crate::shared::replication::{ReplicationReceive, ReplicationSend};
 use crate::shared::sets::ServerMarker;
@@ -68,7 +80,21 @@ pub struct ConnectionManager {
     // list of clients that connected since the last time we sent replication messages
     // (we want to keep track of them because we need to replicate the entire world state to them)
     pub(crate) new_clients: Vec<ClientId>,
+    // list of clients that the server requested to disconnect
+    pub(crate) pending_disconnects: Vec<ClientId>,
+    // clients that disconnected during this frame, with their client entity
+    // (which is only despawned at the end of the frame)
+    pub(crate) disconnected_clients: HashMap<ClientId, Entity>,
+    // true if the server is started
+    pub(crate) started: bool,
     pub(crate) writer: Writer,
+    pub(crate) lag_compensation: LagCompensationHistory,
+    pub(crate) interpolation_snapshots: InterpolationSnapshotHistory,
+    /// Replication state of the entities replicated to the clients, for debugging
+    pub(crate) replication_state: EntityHashMap<Entity, EntityReplicationState>,
+    /// Cached target of the clients of each [`ServerInstanceId`] (`None` for the clients
+    /// that are not assigned to any instance). Empty if no client is assigned to an instance.
+    instance_targets: HashMap<Option<ServerInstanceId>, NetworkTarget>,
 
     // CONFIG
     replication_config: ReplicationConfig,
@@ -85,6 +111,8 @@ impl Default for ConnectionManager {
             ReplicationConfig::default(),
             PacketConfig::default(),
             PingConfig::default(),
+            LagCompensationConfig::default(),
+            InterpolationSnapshotConfig::default(),
         )
     }
 }
@@ -96,6 +124,8 @@ impl ConnectionManager {
         replication_config: ReplicationConfig,
         packet_config: PacketConfig,
         ping_config: PingConfig,
+        lag_compensation_config: LagCompensationConfig,
+        interpolation_snapshot_config: InterpolationSnapshotConfig,
     ) -> Self {
         Self {
             connections: HashMap::default(),
@@ -104,16 +134,70 @@ impl ConnectionManager {
             events: ServerEvents::new(),
             delta_manager: DeltaManager::default(),
             new_clients: vec![],
+            pending_disconnects: vec![],
+            disconnected_clients: HashMap::default(),
+            started: false,
             writer: Writer::with_capacity(MAX_PACKET_SIZE),
+            lag_compensation: LagCompensationHistory::new(lag_compensation_config),
+            interpolation_snapshots: InterpolationSnapshotHistory::new(
+                interpolation_snapshot_config,
+            ),
+            replication_state: EntityHashMap::default(),
+            instance_targets: HashMap::default(),
             replication_config,
             packet_config,
             ping_config,
         }
     }
 
+    /// Return the snapshot of the lag-compensated components of the entity at the given tick.
+    ///
+    /// Returns `None` if the tick is outside the window of the
+    /// [`LagCompensationConfig`], or if the entity has no lag-compensated components.
+    /// See [`lag_compensation`](crate::server::lag_compensation) for more details.
+    pub fn rewind(&self, entity: Entity, tick: Tick) -> Option<&ComponentSnapshot> {
+        self.lag_compensation.get(entity, tick)
+    }
+
     /// Return the [`Entity`] associated with the given [`ClientId`]
-    pub fn client_entity(&self, client_id: ClientId) -> Result<Entity, ServerError> {
-        self.connection(client_id).map(|c| c.entity)
+    ///
+    /// The [`ClientLookupError`] lets you distinguish between a client that was never
+    /// connected, a client that disconnected during this frame, and a server that is not started.
+    pub fn client_entity(&self, client_id: ClientId) -> Result<Entity, ClientLookupError> {
+        self.lookup_connection(client_id).map(|c| c.metadata.entity)
+    }
+
+    fn lookup_connection(&self, client_id: ClientId) -> Result<&Connection, ClientLookupError> {
+        if !self.started {
+            return Err(ClientLookupError::ServerNotStarted);
+        }
+        self.connections.get(&client_id).ok_or_else(|| {
+            if self.disconnected_clients.contains_key(&client_id) {
+                ClientLookupError::Disconnected(client_id)
+            } else {
+                ClientLookupError::UnknownClient(client_id)
+            }
+        })
+    }
+
+    /// Return the [`ControlledEntities`] of the given [`ClientId`]
+    ///
+    /// Returns `None` if the client is not connected.
+    /// The [`ControlledEntities`] of a client that disconnected during this frame can still be looked up
+    /// (for example while handling the [`DisconnectEvent`]) until its client entity is despawned at the end of the frame.
+    ///
+    /// See also [`ClientControlledEntities`](crate::prelude::server::ClientControlledEntities)
+    /// to do the lookup with a single [`SystemParam`](bevy::ecs::system::SystemParam).
+    pub fn controlled_entities<'q>(
+        &self,
+        client_id: ClientId,
+        query: &'q Query<&ControlledEntities>,
+    ) -> Option<&'q ControlledEntities> {
+        let client_entity = self
+            .client_entity(client_id)
+            .ok()
+            .or_else(|| self.disconnected_clients.get(&client_id).copied())?;
+        query.get(client_entity).ok()
     }
 
     /// Return the list of connected [`ClientId`]s
@@ -121,6 +205,62 @@ impl ConnectionManager {
         self.connections.keys().copied()
     }
 
+    /// Return the number of connected clients
+    pub fn connected_count(&self) -> usize {
+        self.connections.len()
+    }
+
+    /// Return the connected [`ClientId`]s along with their [`ClientMetadata`]
+    pub fn connected_clients_metadata(
+        &self,
+    ) -> impl Iterator<Item = (ClientId, &ClientMetadata)> + '_ {
+        self.connections
+            .iter()
+            .map(|(client_id, connection)| (*client_id, &connection.metadata))
+    }
+
+    /// Disconnect the client `client_id`.
+    ///
+    /// The client is disconnected during the next [`Send`](crate::prelude::MainSet::Send) set,
+    /// and a [`DisconnectEvent`] is emitted as if the client had disconnected by itself.
+    ///
+    /// Returns an error if the client is not connected.
+    pub fn disconnect(&mut self, client_id: ClientId) -> Result<(), ServerError> {
+        self.connection(client_id)?;
+        if !self.pending_disconnects.contains(&client_id) {
+            self.pending_disconnects.push(client_id);
+        }
+        Ok(())
+    }
+
+    /// Ids of the messages sent to `client_id` on the reliable channel `C` that have not been acked yet,
+    /// oldest first
+    pub fn pending_messages<C: Channel>(
+        &self,
+        client_id: ClientId,
+    ) -> Result<Vec<MessageId>, ServerError> {
+        Ok(self
+            .connection(client_id)?
+            .message_manager
+            .pending_messages(ChannelKind::of::<C>())?)
+    }
+
+    /// Cancel a message sent to `client_id` on the reliable channel `C`, so that it is not resent
+    /// if it has not been received yet.
+    ///
+    /// Returns false if the message was already acked. Messages cannot be cancelled on
+    /// [`OrderedReliable`](crate::prelude::ChannelMode::OrderedReliable) channels.
+    pub fn cancel_message<C: Channel>(
+        &mut self,
+        client_id: ClientId,
+        message_id: MessageId,
+    ) -> Result<bool, ServerError> {
+        Ok(self
+            .connection_mut(client_id)?
+            .message_manager
+            .cancel_message(ChannelKind::of::<C>(), message_id)?)
+    }
+
     // TODO: we need `&mut self` because MapEntities requires `&mut EntityMapper` even though it's not needed here
     /// Convert entities in the message to be compatible with the remote world of the provided client
     pub fn map_entities_to_remote<M: Message + MapEntities>(
@@ -169,23 +309,167 @@ impl ConnectionManager {
                     .values()
                     .filter(move |c| c.client_id != *client_id),
             ),
-            NetworkTarget::AllExcept(client_ids) => Box::new(
-                self.connections
-                    .values()
-                    .filter(move |c| !client_ids.contains(&c.client_id)),
-            ),
             NetworkTarget::Single(client_id) => {
                 Box::new(self.connections.get(client_id).into_iter())
             }
-            NetworkTarget::Only(client_ids) => Box::new(
-                self.connections
-                    .values()
-                    .filter(move |c| client_ids.contains(&c.client_id)),
-            ),
+            NetworkTarget::AllExcept(_) | NetworkTarget::Only(_) => {
+                let matcher = target.matcher();
+                Box::new(
+                    self.connections
+                        .values()
+                        .filter(move |c| matcher.targets(&c.client_id)),
+                )
+            }
             NetworkTarget::None => Box::new(std::iter::empty()),
         }
     }
 
+    /// Set the origin around which the components registered with `add_origin_rebasing`
+    /// are serialized when they are replicated to the given client.
+    ///
+    /// Use `None` to send the absolute values.
+    pub fn set_replication_origin(
+        &mut self,
+        client_id: ClientId,
+        origin: Option<Vec3>,
+    ) -> Result<(), ServerError> {
+        self.connection_mut(client_id)?.replication_origin = origin;
+        Ok(())
+    }
+
+    /// Stop sending replication messages to the client, while keeping the connection alive.
+    ///
+    /// This can be used to save bandwidth while the client is on a loading screen.
+    /// Entity despawns and component removals are still sent, so that the client doesn't keep stale data.
+    pub fn pause_client_replication(&mut self, client_id: ClientId) -> Result<(), ServerError> {
+        debug!(?client_id, "Pausing replication");
+        self.connection_mut(client_id)?.replication_sender.pause();
+        Ok(())
+    }
+
+    /// Resume sending replication messages to a client for which replication was paused
+    /// with [`pause_client_replication`](Self::pause_client_replication).
+    ///
+    /// The client receives a full snapshot of the entities that are replicated to it,
+    /// the same way as a newly connected client.
+    /// Entities that use network relevance are not resynced; they are sent again when they gain relevance.
+    pub fn resume_client_replication(&mut self, client_id: ClientId) -> Result<(), ServerError> {
+        let connection = self.connection_mut(client_id)?;
+        if !connection.replication_sender.paused {
+            return Ok(());
+        }
+        debug!(?client_id, "Resuming replication");
+        connection.replication_sender.resume();
+        if !self.new_clients.contains(&client_id) {
+            self.new_clients.push(client_id);
+        }
+        Ok(())
+    }
+
+    /// Iterate through all the entities that are replicated to at least one client,
+    /// along with their [`EntityReplicationState`].
+    ///
+    /// This is meant for debugging, for example to display an overlay of what is being
+    /// replicated to whom.
+    pub fn replication_state(&self) -> impl Iterator<Item = (Entity, &EntityReplicationState)> {
+        self.replication_state
+            .iter()
+            .map(|(entity, state)| (*entity, state))
+    }
+
+    /// Returns the [`EntityReplicationState`] of the entity, if it is replicated to at least one client
+    pub fn entity_replication_state(&self, entity: Entity) -> Option<&EntityReplicationState> {
+        self.replication_state.get(&entity)
+    }
+
+    /// Record that the entity is being spawned on the connected clients in `target`
+    pub(crate) fn track_entity_spawn(
+        &mut self,
+        entity: Entity,
+        group_id: ReplicationGroupId,
+        authority: AuthorityPeer,
+        target: &NetworkTarget,
+    ) {
+        let clients: Vec<ClientId> = self
+            .connected_targets(target)
+            .map(|c| c.client_id)
+            .collect();
+        if clients.is_empty() {
+            return;
+        }
+        let state =
+            self.replication_state
+                .entry(entity)
+                .or_insert_with(|| EntityReplicationState {
+                    clients: HashSet::default(),
+                    group: group_id,
+                    last_send_tick: None,
+                    authority,
+                });
+        state.group = group_id;
+        state.authority = authority;
+        state.clients.extend(clients);
+    }
+
+    /// Record that replication data for the entity was buffered at the given tick
+    fn track_entity_send(&mut self, entity: Entity, tick: Tick) {
+        if let Some(state) = self.replication_state.get_mut(&entity) {
+            state.last_send_tick = Some(tick);
+        }
+    }
+
+    /// Returns true if the client notified the server that `entity` is ready, with
+    /// [`mark_entity_ready`](crate::prelude::client::ConnectionManager::mark_entity_ready).
+    ///
+    /// The readiness is reset when the entity is despawned for that client.
+    /// Returns false if the client is not connected.
+    pub fn is_client_entity_ready(&self, client_id: ClientId, entity: Entity) -> bool {
+        self.connection(client_id)
+            .is_ok_and(|connection| connection.ready_entities.contains(&entity))
+    }
+
+    /// Returns the clients that unsubscribed from the component `kind` of the entity,
+    /// and the clients that subscribed to it again since the last replication send
+    pub(crate) fn component_subscriptions(
+        &self,
+        entity: Entity,
+        kind: ComponentKind,
+    ) -> (NetworkTarget, NetworkTarget) {
+        let mut unsubscribed = vec![];
+        let mut resubscribed = vec![];
+        for (client_id, connection) in self.connections.iter() {
+            let subscriptions = &connection.component_subscriptions;
+            if subscriptions.is_empty() {
+                continue;
+            }
+            if !subscriptions.is_subscribed(entity, kind) {
+                unsubscribed.push(*client_id);
+            } else if subscriptions.is_resubscribed(entity, kind) {
+                resubscribed.push(*client_id);
+            }
+        }
+        (
+            NetworkTarget::from(unsubscribed),
+            NetworkTarget::from(resubscribed),
+        )
+    }
+
+    /// Tick at which the initial replication snapshot was sent to the client.
+    ///
+    /// All the entities that were replicated to the client when it connected are sent with this tick.
+    /// Returns None if the snapshot hasn't been sent yet.
+    pub fn initial_replication_tick(
+        &self,
+        client_id: ClientId,
+    ) -> Result<Option<Tick>, ServerError> {
+        Ok(self.connection(client_id)?.initial_replication_tick)
+    }
+
+    /// Returns true if replication to the client is currently paused
+    pub fn is_client_replication_paused(&self, client_id: ClientId) -> Result<bool, ServerError> {
+        Ok(self.connection(client_id)?.replication_sender.paused)
+    }
+
     pub fn connection(&self, client_id: ClientId) -> Result<&Connection, ServerError> {
         self.connections
             .get(&client_id)
@@ -207,10 +491,38 @@ impl ConnectionManager {
         self.connections.values_mut().for_each(|connection| {
             connection.update(world_tick, time_manager, tick_manager);
         });
+        for (client_id, connection) in self.connections.iter_mut() {
+            for channel in connection
+                .message_manager
+                .take_reliable_window_full_events()
+            {
+                self.events
+                    .add_reliable_window_full_event(ReliableWindowFull {
+                        client_id: *client_id,
+                        channel,
+                    });
+            }
+        }
+    }
+
+    /// Returns the clients for which a reliable channel window has been full for longer than
+    /// the timeout of [`WindowFullPolicy::DisconnectOnWindowFull`](crate::channel::builder::WindowFullPolicy::DisconnectOnWindowFull)
+    ///
+    /// Each client is only returned once per timeout.
+    pub(crate) fn take_reliable_window_timed_out_clients(&mut self) -> Vec<ClientId> {
+        self.connections
+            .iter_mut()
+            .filter_map(|(client_id, connection)| {
+                connection
+                    .message_manager
+                    .take_reliable_window_full_timeout()
+                    .then_some(*client_id)
+            })
+            .collect()
     }
 
     /// Add a new [`Connection`] to the list of connections with the given [`ClientId`]
-    pub(crate) fn add(&mut self, client_id: ClientId, client_entity: Entity) {
+    pub(crate) fn add(&mut self, client_id: ClientId, client_entity: Entity, tick: Tick) {
         if let Entry::Vacant(e) = self.connections.entry(client_id) {
             #[cfg(feature = "metrics")]
             metrics::gauge!("server::connected_clients").increment(1.0);
@@ -219,6 +531,7 @@ impl ConnectionManager {
             let connection = Connection::new(
                 client_id,
                 client_entity,
+                tick,
                 &self.channel_registry,
                 self.replication_config,
                 self.packet_config,
@@ -230,6 +543,7 @@ impl ConnectionManager {
             });
             self.new_clients.push(client_id);
             e.insert(connection);
+            self.update_instance_targets();
         } else {
             info!("Client {} was already in the connections list", client_id);
         }
@@ -239,15 +553,20 @@ impl ConnectionManager {
     ///
     /// Emits a server [`DisconnectEvent`].
     pub(crate) fn remove(&mut self, client_id: ClientId) {
-        if let Ok(entity) = self.client_entity(client_id) {
+        if let Some(connection) = self.connections.remove(&client_id) {
+            let entity = connection.metadata.entity;
             debug!("Sending Client DisconnectEvent");
             self.events
                 .add_disconnect_event(DisconnectEvent { client_id, entity });
-        }
-        if self.connections.remove(&client_id).is_some() {
             #[cfg(feature = "metrics")]
             metrics::gauge!("server::connected_clients").decrement(1.0);
             info!("Client {} disconnected", client_id);
+            self.disconnected_clients.insert(client_id, entity);
+            self.replication_state.retain(|_, state| {
+                state.clients.remove(&client_id);
+                !state.clients.is_empty()
+            });
+            self.update_instance_targets();
         };
     }
 
@@ -262,9 +581,140 @@ impl ConnectionManager {
         time_manager: &TimeManager,
     ) -> Result<(), ServerError> {
         let _span = info_span!("buffer_replication_messages").entered();
+        // partition the connections by shard; every connection has its own buffers so the shards
+        // can be processed independently.
+        // Entities that are replicated to clients in multiple shards are handled correctly because the
+        // replication data is prepared for each connection separately before this point.
+        let mut shards: HashMap<ClientShardKey, Vec<&mut Connection>> = HashMap::default();
+        for connection in self.connections.values_mut() {
+            shards
+                .entry(connection.shard_key)
+                .or_default()
+                .push(connection);
+        }
+        if shards.len() <= 1 {
+            return shards
+                .into_values()
+                .flatten()
+                .try_for_each(|c| c.buffer_replication_messages(tick, bevy_tick, time_manager));
+        }
+        ComputeTaskPool::get_or_init(TaskPool::default)
+            .scope(|scope| {
+                for connections in shards.into_values() {
+                    scope.spawn(async move {
+                        connections.into_iter().try_for_each(|c| {
+                            c.buffer_replication_messages(tick, bevy_tick, time_manager)
+                        })
+                    });
+                }
+            })
+            .into_iter()
+            .collect()
+    }
+
+    /// Assign the client to a shard.
+    ///
+    /// The replication messages of the clients in different shards are buffered in parallel.
+    pub fn set_client_shard_key(
+        &mut self,
+        client_id: ClientId,
+        shard_key: ClientShardKey,
+    ) -> Result<(), ServerError> {
+        self.connection_mut(client_id)?.shard_key = shard_key;
+        Ok(())
+    }
+
+    /// Assign the client to a [`ServerInstanceId`].
+    ///
+    /// The client will only receive the entities of its instance. This should be done when the client
+    /// connects (for example when handling the [`ConnectEvent`]), before any entity is replicated to it.
+    pub fn set_client_instance(
+        &mut self,
+        client_id: ClientId,
+        instance: ServerInstanceId,
+    ) -> Result<(), ServerError> {
+        self.connection_mut(client_id)?.instance = Some(instance);
+        self.update_instance_targets();
+        Ok(())
+    }
+
+    /// Rebuild the cached target of each instance
+    fn update_instance_targets(&mut self) {
+        self.instance_targets.clear();
+        // instances are not in use: nothing to restrict
+        if self.connections.values().all(|c| c.instance.is_none()) {
+            return;
+        }
+        let mut instance_clients: HashMap<Option<ServerInstanceId>, Vec<ClientId>> =
+            HashMap::default();
+        for (client_id, connection) in self.connections.iter() {
+            instance_clients
+                .entry(connection.instance)
+                .or_default()
+                .push(*client_id);
+        }
+        self.instance_targets = instance_clients
+            .into_iter()
+            .map(|(instance, clients)| (instance, NetworkTarget::from(clients)))
+            .collect();
+    }
+
+    /// Return the [`ServerInstanceId`] that the client is assigned to, if any
+    pub fn client_instance(
+        &self,
+        client_id: ClientId,
+    ) -> Result<Option<ServerInstanceId>, ClientLookupError> {
+        self.lookup_connection(client_id).map(|c| c.instance)
+    }
+
+    /// Return the list of connected [`ClientId`]s assigned to the instance
+    pub fn instance_clients(
+        &self,
+        instance: ServerInstanceId,
+    ) -> impl Iterator<Item = ClientId> + '_ {
         self.connections
-            .values_mut()
-            .try_for_each(move |c| c.buffer_replication_messages(tick, bevy_tick, time_manager))
+            .iter()
+            .filter(move |(_, connection)| connection.instance == Some(instance))
+            .map(|(client_id, _)| *client_id)
+    }
+
+    /// Return a [`NetworkTarget`] that targets all the clients of the instance
+    pub fn instance_target(&self, instance: ServerInstanceId) -> NetworkTarget {
+        self.instance_targets
+            .get(&Some(instance))
+            .cloned()
+            .unwrap_or(NetworkTarget::None)
+    }
+
+    /// Returns true if the client can receive the data of the instance.
+    ///
+    /// Once instances are in use, a client only receives the data of its own instance,
+    /// and the data without an instance only goes to the clients that are not assigned to any instance.
+    pub(crate) fn is_in_instance(
+        &self,
+        client_id: ClientId,
+        instance: Option<&ServerInstanceId>,
+    ) -> bool {
+        self.instance_targets.is_empty()
+            || self
+                .connections
+                .get(&client_id)
+                .is_some_and(|c| c.instance.as_ref() == instance)
+    }
+
+    /// Restrict the target to the clients of the instance (see [`Self::is_in_instance`])
+    pub(crate) fn restrict_to_instance(
+        &self,
+        target: &mut NetworkTarget,
+        instance: Option<&ServerInstanceId>,
+    ) {
+        if self.instance_targets.is_empty() {
+            return;
+        }
+        match self.instance_targets.get(&instance.copied()) {
+            Some(instance_target) => target.intersection(instance_target),
+            None => *target = NetworkTarget::None,
+        }
     }
 
     #[cfg_attr(feature = "trace", instrument(level = Level::INFO, skip_all))]
@@ -329,6 +779,29 @@ impl ConnectionManager {
     }
 }
 
+/// User-defined key used to partition the clients into shards (for example by region or team)
+///
+/// The replication sends of each shard are processed on a separate task.
+/// All clients are in the default shard unless they are assigned with
+/// [`ConnectionManager::set_client_shard_key`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
+pub struct ClientShardKey(pub u64);
+
+/// Identifier of an isolated instance of the server (for example a match), used to run
+/// several instances in a single server.
+///
+/// Clients are assigned to an instance with [`ConnectionManager::set_client_instance`].
+/// An entity with this component is only replicated to the clients of the same instance, regardless
+/// of its [`ReplicationTarget`](crate::prelude::server::ReplicationTarget) or network relevance, and can
+/// only be controlled by them.
+///
+/// Once a client is assigned to an instance, the entities without this component and the replicated
+/// resources are only replicated to the clients that are not assigned to any instance.
+/// Messages are scoped to an instance by sending them with [`ConnectionManager::send_message_to_instance`].
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Component)]
+pub struct ServerInstanceId(pub u32);
+
 /// Find the list of connected clients that match the provided [`NetworkTarget`]
 pub(crate) fn connected_targets_mut<'a: 'b, 'b>(
     connections: &'a mut HashMap<ClientId, Connection>,
@@ -342,28 +815,52 @@ pub(crate) fn connected_targets_mut<'a: 'b, 'b>(
                 .values_mut()
                 .filter(move |c| c.client_id != *client_id),
         ),
-        NetworkTarget::AllExcept(client_ids) => Box::new(
-            connections
-                .values_mut()
-                .filter(move |c| !client_ids.contains(&c.client_id)),
-        ),
         NetworkTarget::Single(client_id) => Box::new(connections.get_mut(client_id).into_iter()),
-        NetworkTarget::Only(client_ids) => Box::new(
-            connections
-                .values_mut()
-                .filter(move |c| client_ids.contains(&c.client_id)),
-        ),
+        NetworkTarget::AllExcept(_) | NetworkTarget::Only(_) => {
+            let matcher = target.matcher();
+            Box::new(
+                connections
+                    .values_mut()
+                    .filter(move |c| matcher.targets(&c.client_id)),
+            )
+        }
         NetworkTarget::None => Box::new(std::iter::empty()),
     }
 }
 
+/// Replication state of an entity on the server, returned by
+/// [`ConnectionManager::replication_state`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntityReplicationState {
+    /// Clients that the entity is currently replicated to
+    pub clients: HashSet<ClientId>,
+    /// Replication group of the entity
+    pub group: ReplicationGroupId,
+    /// Tick at which component data for the entity was last buffered for sending
+    pub last_send_tick: Option<Tick>,
+    /// Peer that has authority over the entity
+    pub authority: AuthorityPeer,
+}
+
 /// Wrapper that handles the connection between the server and a client
-pub struct Connection {
-    pub(crate) client_id: ClientId,
+/// Metadata about a connected client
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientMetadata {
     /// We create one entity per connected client, so that users
     /// can store metadata about the client using the ECS
-    pub(crate) entity: Entity,
+    pub entity: Entity,
+    /// Server tick at which the client connected
+    pub connected_tick: Tick,
+    /// Server tick at which we last received a packet from the client
+    pub last_seen_tick: Tick,
+}
+
+pub struct Connection {
+    pub(crate) client_id: ClientId,
+    pub(crate) metadata: ClientMetadata,
     pub message_manager: MessageManager,
+    /// Compresses the packets sent to the client and decompresses the packets received from it
+    compressor: PacketCompressor,
     pub(crate) replication_sender: ReplicationSender,
     pub replication_receiver: ReplicationReceiver,
     pub(crate) events: ConnectionEvents,
@@ -383,12 +880,27 @@ pub struct Connection {
     is_local_client: bool,
     /// Messages to send to the local client (we don't buffer them in the MessageManager because there is no io)
     pub(crate) local_messages_to_send: Vec<Bytes>,
+    /// Origin around which origin-rebased components are serialized for this client
+    pub(crate) replication_origin: Option<Vec3>,
+    /// Shard that the client belongs to
+    pub(crate) shard_key: ClientShardKey,
+    /// Server instance that the client belongs to
+    pub(crate) instance: Option<ServerInstanceId>,
+    /// Entities that the client has marked as ready
+    pub(crate) ready_entities: EntityHashSet,
+    /// Tick of the initial replication snapshot sent to the client
+    pub(crate) initial_replication_tick: Option<Tick>,
+    /// Components that the client unsubscribed from
+    pub(crate) component_subscriptions: ComponentSubscriptions,
+    /// Number of entities included in the replication messages buffered during the last tick
+    pub(crate) replicated_entities: usize,
 }
 
 impl Connection {
     pub(crate) fn new(
         client_id: ClientId,
         entity: Entity,
+        connected_tick: Tick,
         channel_registry: &ChannelRegistry,
         replication_config: ReplicationConfig,
         packet_config: PacketConfig,
@@ -422,8 +934,13 @@ impl Connection {
         let replication_receiver = ReplicationReceiver::new();
         Self {
             client_id,
-            entity,
+            metadata: ClientMetadata {
+                entity,
+                connected_tick,
+                last_seen_tick: connected_tick,
+            },
             message_manager,
+            compressor: PacketCompressor::new(packet_config.compression),
             replication_sender,
             replication_receiver,
             ping_manager: PingManager::new(ping_config),
@@ -436,6 +953,13 @@ impl Connection {
             messages_to_rebroadcast: vec![],
             is_local_client: false,
             local_messages_to_send: vec![],
+            replication_origin: None,
+            shard_key: ClientShardKey::default(),
+            instance: None,
+            ready_entities: EntityHashSet::default(),
+            initial_replication_tick: None,
+            component_subscriptions: ComponentSubscriptions::default(),
+            replicated_entities: 0,
         }
     }
 
@@ -449,6 +973,11 @@ impl Connection {
         self.is_local_client
     }
 
+    /// Return the [`ClientMetadata`] of this connection
+    pub fn metadata(&self) -> &ClientMetadata {
+        &self.metadata
+    }
+
     /// Return the latest estimate of rtt
     pub fn rtt(&self) -> Duration {
         self.ping_manager.rtt()
@@ -470,15 +999,17 @@ impl Connection {
         }
         self.message_manager
             .update(time_manager, &self.ping_manager, tick_manager);
-        self.replication_sender.update(world_tick);
+        self.replication_sender
+            .update(world_tick, time_manager.current_time());
         self.ping_manager.update(time_manager);
     }
 
+    /// Buffer the message, and return its id if the channel assigns one
     pub(crate) fn buffer_message(
         &mut self,
         message: Bytes,
         channel: ChannelKind,
-    ) -> Result<(), ServerError> {
+    ) -> Result<Option<MessageId>, ServerError> {
         // TODO: i know channel names never change so i should be able to get them as static
         // TODO: just have a channel registry enum as well?
         let channel_name = self
@@ -487,8 +1018,7 @@ impl Connection {
             .name(&channel)
             .ok_or::<ServerError>(MessageError::NotRegistered.into())?;
         // message.emit_send_logs(&channel_name);
-        self.message_manager.buffer_send(message, channel)?;
-        Ok(())
+        Ok(self.message_manager.buffer_send(message, channel)?)
     }
 
     #[cfg_attr(feature = "trace", instrument(level = Level::INFO, skip_all))]
@@ -498,7 +1028,11 @@ impl Connection {
         bevy_tick: BevyTick,
         time_manager: &TimeManager,
     ) -> Result<(), ServerError> {
+        if self.replication_sender.paused {
+            self.replication_sender.discard_paused_messages();
+        }
         self.replication_sender.accumulate_priority(time_manager);
+        self.replicated_entities = self.replication_sender.num_pending_entities();
         self.replication_sender.send_actions_messages(
             tick,
             bevy_tick,
@@ -561,7 +1095,12 @@ impl Connection {
                 self.send_pong(pong)?;
                 Ok::<(), ServerError>(())
             })?;
-        let payloads = self.message_manager.send_packets(tick_manager.tick())?;
+        let payloads = self
+            .message_manager
+            .send_packets(tick_manager.tick())?
+            .into_iter()
+            .map(|payload| self.compressor.compress(payload))
+            .collect();
 
         // update the replication sender about which messages were actually sent, and accumulate priority
         self.replication_sender.recv_send_notification();
@@ -678,8 +1217,10 @@ impl Connection {
         component_registry: &ComponentRegistry,
         delta_manager: &mut DeltaManager,
     ) -> Result<(), ServerError> {
+        let packet = self.compressor.decompress(packet)?;
         // receive the packets, buffer them, update any sender that were waiting for their sent messages to be acked
         let tick = self.message_manager.recv_packet(packet)?;
+        self.metadata.last_seen_tick = tick_manager.tick();
         // notify the replication sender that some sent messages were received
         self.replication_sender
             .recv_update_acks(component_registry, delta_manager);
@@ -713,8 +1254,19 @@ impl ConnectionManager {
         &mut self,
         mut entity: Entity,
         group_id: ReplicationGroupId,
+        reason: DespawnReason,
         target: NetworkTarget,
     ) -> Result<(), ServerError> {
+        let local_entity = entity;
+        if let Entry::Occupied(mut state) = self.replication_state.entry(local_entity) {
+            state
+                .get_mut()
+                .clients
+                .retain(|client_id| !target.targets(client_id));
+            if state.get().clients.is_empty() {
+                state.remove();
+            }
+        }
         connected_targets_mut(&mut self.connections, &target).try_for_each(|connection| {
             // trace!(
             //     ?entity,
@@ -722,6 +1274,10 @@ impl ConnectionManager {
             //     "Send entity despawn for tick {:?}",
             //     self.tick_manager.tick()
             // );
+            connection.ready_entities.remove(&local_entity);
+            connection
+                .component_subscriptions
+                .remove_entity(local_entity);
 
             // convert the entity to a network entity (possibly mapped)
             entity = connection
@@ -731,7 +1287,7 @@ impl ConnectionManager {
 
             connection
                 .replication_sender
-                .prepare_entity_despawn(entity, group_id);
+                .prepare_entity_despawn(entity, group_id, reason);
             Ok(())
         })
     }
@@ -818,9 +1374,10 @@ impl ConnectionManager {
             );
         }
 
-        // there is no entity mapping, so we can serialize the component once for all clients
+        // there is no entity mapping or origin rebasing, so we can serialize the component once for all clients
+        let per_client_origin = component_registry.erased_is_origin_rebased(kind);
         let mut raw_data: Option<Bytes> = None;
-        if !component_registry.erased_is_map_entities(kind) {
+        if !component_registry.erased_is_map_entities(kind) && !per_client_origin {
             if delta_compression {
                 // SAFETY: the component_data corresponds to the kind
                 unsafe {
@@ -851,7 +1408,7 @@ impl ConnectionManager {
             // there is entity mapping, so we might need to serialize the component differently for each client
             // (although most of the time there is not mapping done on the send side)
             // It would be nice if we could check ahead of time if there is any mapping that needs to be done
-            if raw_data.is_none() {
+            if raw_data.is_none() || per_client_origin {
                 if delta_compression {
                     // SAFETY: the component_data corresponds to the kind
                     unsafe {
@@ -867,7 +1424,7 @@ impl ConnectionManager {
                         )?;
                     }
                 } else {
-                    component_registry.erased_serialize(
+                    component_registry.erased_serialize_with_origin(
                         component_data,
                         &mut self.writer,
                         kind,
@@ -876,6 +1433,7 @@ impl ConnectionManager {
                             .replication_receiver
                             .remote_entity_map
                             .local_to_remote,
+                        connection.replication_origin,
                     )?;
                 };
                 // write a new message for each client, because we need to do entity mapping
@@ -901,6 +1459,7 @@ impl ConnectionManager {
                 raw_data.clone().unwrap(),
             );
         }
+        self.track_entity_send(entity, tick);
         Ok(())
     }
 
@@ -936,9 +1495,13 @@ impl ConnectionManager {
                 "prepare entity update changed check (we want the component-change-tick to be higher than send_tick)"
             );
 
-            if send_tick.map_or(true, |tick| {
+            let mut changed = send_tick.is_none_or(|tick| {
                 component_change_tick.is_newer_than(tick, system_current_tick)
-            }) {
+            });
+            if let Some(interval) = registry.max_send_rate(kind) {
+                changed = connection.replication_sender.check_send_rate(entity, kind, interval, changed);
+            }
+            if changed {
                 num_targets += 1;
                 debug!(
                     ?entity,
@@ -955,9 +1518,9 @@ impl ConnectionManager {
                 } else {
                     // we serialize once and re-use the result for all clients
                     // serialize only if there is at least one client that needs the update
-                    if existing_bytes.is_none() || registry.erased_is_map_entities(kind) {
-                        registry.erased_serialize(component, &mut connection.writer, kind, &mut connection.replication_receiver.remote_entity_map.local_to_remote)?;
-                        // we re-serialize every time if there is entity mapping
+                    if existing_bytes.is_none() || registry.erased_is_map_entities(kind) || registry.erased_is_origin_rebased(kind) {
+                        registry.erased_serialize_with_origin(component, &mut connection.writer, kind, &mut connection.replication_receiver.remote_entity_map.local_to_remote, connection.replication_origin)?;
+                        // we re-serialize every time if there is entity mapping or origin rebasing
                         existing_bytes = Some(connection.writer.split());
                     }
                     let raw_data = existing_bytes.clone().unwrap();
@@ -972,6 +1535,9 @@ impl ConnectionManager {
             Ok::<(), ServerError>(())
         })?;
 
+        if num_targets > 0 {
+            self.track_entity_send(entity, tick);
+        }
         if delta_compression && num_targets > 0 {
             // store the component value in a storage shared between all connections, so that we can compute diffs
             self.delta_manager
@@ -1068,6 +1634,14 @@ impl ReplicationReceive for ConnectionManager {
             connection.replication_receiver.cleanup(tick);
         }
     }
+
+    fn get_local_entity(&self, remote_entity: Entity, from: Option<ClientId>) -> Option<Entity> {
+        self.connections
+            .get(&from?)?
+            .replication_receiver
+            .remote_entity_map
+            .get_local(remote_entity)
+    }
 }
 
 impl ReplicationSend for ConnectionManager {
@@ -1087,4 +1661,9 @@ impl ReplicationSend for ConnectionManager {
             connection.replication_sender.cleanup(tick);
         }
     }
+
+    /// Resources do not belong to any [`ServerInstanceId`]
+    fn restrict_resource_target(&self, target: &mut NetworkTarget) {
+        self.restrict_to_instance(target, None);
+    }
 }
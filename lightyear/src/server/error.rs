@@ -13,6 +13,8 @@ pub enum ServerError {
     #[error("client id {0:?} was not found")]
     ClientIdNotFound(ClientId),
     #[error(transparent)]
+    ClientLookup(#[from] ClientLookupError),
+    #[error(transparent)]
     Packet(#[from] crate::packet::error::PacketError),
     #[error(transparent)]
     Serialization(#[from] crate::serialize::SerializationError),
@@ -25,3 +27,17 @@ pub enum ServerError {
     #[error(transparent)]
     ReplicationError(#[from] crate::shared::replication::error::ReplicationError),
 }
+
+/// Error returned when looking up a client in the [`ConnectionManager`](crate::server::connection::ConnectionManager)
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientLookupError {
+    /// The client is not connected, and did not disconnect during this frame
+    #[error("client id {0:?} is unknown")]
+    UnknownClient(ClientId),
+    /// The client disconnected during this frame
+    #[error("client id {0:?} disconnected")]
+    Disconnected(ClientId),
+    /// The server is not started, so there are no connected clients
+    #[error("the server is not started")]
+    ServerNotStarted,
+}
@@ -5,13 +5,17 @@ use bevy::prelude::*;
 use bevy::utils::{hashbrown, HashMap};
 
 use crate::connection::id::ClientId;
+use crate::connection::netcode::USER_DATA_BYTES;
+use crate::protocol::channel::ChannelKind;
 use crate::server::connection::ConnectionManager;
 use crate::shared::events::connection::{
-    ConnectionEvents, IterComponentInsertEvent, IterComponentRemoveEvent, IterComponentUpdateEvent,
-    IterEntityDespawnEvent, IterEntitySpawnEvent,
+    ConnectionEvents, IterComponentDeserializationErrorEvent, IterComponentInsertEvent,
+    IterComponentRemoveEvent, IterComponentUpdateEvent, IterEntityDespawnEvent,
+    IterEntitySpawnEvent,
 };
 use crate::shared::events::plugin::EventsPlugin;
 use crate::shared::events::systems::push_component_events;
+use crate::shared::replication::components::DespawnReason;
 use crate::shared::sets::{InternalMainSet, ServerMarker};
 
 type EntityHashMap<K, V> = hashbrown::HashMap<K, V, EntityHash>;
@@ -24,8 +28,11 @@ impl Plugin for ServerEventsPlugin {
     fn build(&self, app: &mut App) {
         app
             // EVENTS
+            .add_event::<ConnectionRequestEvent>()
             .add_event::<ConnectEvent>()
             .add_event::<DisconnectEvent>()
+            .add_event::<ReliableWindowFull>()
+            .add_event::<AuthorityViolation>()
             // PLUGIN
             .add_plugins(EventsPlugin::<ConnectionManager>::default())
             // SYSTEMS
@@ -42,6 +49,8 @@ fn emit_connect_events(
     mut commands: Commands,
     mut connect_events: EventWriter<ConnectEvent>,
     mut disconnect_events: EventWriter<DisconnectEvent>,
+    mut window_full_events: EventWriter<ReliableWindowFull>,
+    mut authority_violation_events: EventWriter<AuthorityViolation>,
     mut connection_manager: ResMut<ConnectionManager>,
 ) {
     // EVENTS: Write the received events into bevy events
@@ -70,6 +79,24 @@ fn emit_connect_events(
                 // world.trigger(disconnect_event);
             }
         }
+
+        if connection_manager.events.has_reliable_window_full() {
+            for window_full_event in connection_manager.events.iter_reliable_window_full() {
+                debug!(
+                    "Reliable channel window full for client: {}",
+                    window_full_event.client_id
+                );
+                window_full_events.send(window_full_event);
+            }
+        }
+
+        for violation in connection_manager.events.iter_authority_violations() {
+            debug!(
+                "Client {} sent replication updates for entity {:?} without authority",
+                violation.client_id, violation.entity
+            );
+            authority_violation_events.send(violation);
+        }
     }
 }
 
@@ -77,6 +104,7 @@ fn emit_connect_events(
 pub struct ServerEvents {
     pub connections: Vec<ConnectEvent>,
     pub disconnections: Vec<DisconnectEvent>,
+    pub reliable_window_full: Vec<ReliableWindowFull>,
     pub events: HashMap<ClientId, ConnectionEvents>,
     pub empty: bool,
 }
@@ -96,6 +124,7 @@ impl crate::shared::events::connection::ClearEvents for ServerEvents {
     fn clear(&mut self) {
         self.connections = Vec::new();
         self.disconnections = Vec::new();
+        self.reliable_window_full = Vec::new();
         self.empty = true;
         self.events = HashMap::default();
     }
@@ -106,6 +135,7 @@ impl ServerEvents {
         Self {
             connections: Vec::new(),
             disconnections: Vec::new(),
+            reliable_window_full: Vec::new(),
             events: HashMap::default(),
             empty: true,
         }
@@ -153,6 +183,33 @@ impl ServerEvents {
         !self.disconnections.is_empty()
     }
 
+    pub fn iter_reliable_window_full(&mut self) -> Vec<ReliableWindowFull> {
+        std::mem::take(&mut self.reliable_window_full)
+    }
+
+    pub fn has_reliable_window_full(&self) -> bool {
+        !self.reliable_window_full.is_empty()
+    }
+
+    pub fn iter_authority_violations(&mut self) -> Vec<AuthorityViolation> {
+        self.events
+            .iter_mut()
+            .flat_map(|(client_id, events)| {
+                std::mem::take(&mut events.authority_violations)
+                    .into_iter()
+                    .map(|entity| AuthorityViolation {
+                        client_id: *client_id,
+                        entity,
+                    })
+            })
+            .collect()
+    }
+
+    pub(crate) fn add_reliable_window_full_event(&mut self, event: ReliableWindowFull) {
+        self.reliable_window_full.push(event);
+        self.empty = false;
+    }
+
     pub(crate) fn add_connect_event(&mut self, connect_event: ConnectEvent) {
         self.connections.push(connect_event);
         self.empty = false;
@@ -189,11 +246,14 @@ impl IterEntitySpawnEvent<ClientId> for ServerEvents {
 }
 
 impl IterEntityDespawnEvent<ClientId> for ServerEvents {
-    fn into_iter_entity_despawn(&mut self) -> Box<dyn Iterator<Item = (Entity, ClientId)> + '_> {
+    fn into_iter_entity_despawn(
+        &mut self,
+    ) -> Box<dyn Iterator<Item = (Entity, DespawnReason, ClientId)> + '_> {
         Box::new(self.events.iter_mut().flat_map(|(client_id, events)| {
-            let entities = events.into_iter_entity_despawn().map(|(entity, _)| entity);
-            let client_ids = std::iter::once(*client_id).cycle();
-            entities.zip(client_ids)
+            let client_id = *client_id;
+            events
+                .into_iter_entity_despawn()
+                .map(move |(entity, reason, _)| (entity, reason, client_id))
         }))
     }
 
@@ -204,6 +264,25 @@ impl IterEntityDespawnEvent<ClientId> for ServerEvents {
     }
 }
 
+impl IterComponentDeserializationErrorEvent<ClientId> for ServerEvents {
+    fn into_iter_component_errors(
+        &mut self,
+    ) -> Box<dyn Iterator<Item = (Entity, Option<&'static str>, String, ClientId)> + '_> {
+        Box::new(self.events.iter_mut().flat_map(|(client_id, events)| {
+            let client_id = *client_id;
+            events
+                .into_iter_component_errors()
+                .map(move |(entity, component, error, _)| (entity, component, error, client_id))
+        }))
+    }
+
+    fn has_component_errors(&self) -> bool {
+        self.events
+            .iter()
+            .any(|(_, connection_events)| connection_events.has_component_errors())
+    }
+}
+
 impl IterComponentUpdateEvent<ClientId> for ServerEvents {
     fn iter_component_update<'a, 'b: 'a, C: Component>(
         &'a mut self,
@@ -246,6 +325,25 @@ impl IterComponentInsertEvent<ClientId> for ServerEvents {
     }
 }
 
+/// Bevy [`Event`] emitted on the server when a client completed the connection handshake,
+/// before it is connected and before its client entity is spawned.
+///
+/// The connection can be accepted or rejected with [`ServerCommandsExt::accept_connection`](crate::prelude::server::ServerCommandsExt::accept_connection)
+/// and [`ServerCommandsExt::reject_connection`](crate::prelude::server::ServerCommandsExt::reject_connection);
+/// the rejection reason is sent to the client.
+/// If no system answers during the frame, the [`ConnectionRequestDefault`](crate::connection::server::ConnectionRequestDefault)
+/// of the server's config is used.
+///
+/// Only emitted by the netcode transport, if [`NetcodeConfig::emit_connection_requests`](crate::server::config::NetcodeConfig::emit_connection_requests)
+/// is enabled. The [`ConnectionRequestHandler`](crate::connection::server::ConnectionRequestHandler) runs
+/// before this event: clients that it rejects are never emitted.
+#[derive(Event, Debug, Clone)]
+pub struct ConnectionRequestEvent {
+    pub client_id: ClientId,
+    /// The user data contained in the client's `ConnectToken`
+    pub connect_token_data: [u8; USER_DATA_BYTES],
+}
+
 /// Bevy [`Event`] emitted on the server on the frame where a client is connected
 #[derive(Event, Debug, Copy, Clone)]
 pub struct ConnectEvent {
@@ -260,12 +358,38 @@ pub struct DisconnectEvent {
     pub entity: Entity,
 }
 
+/// Bevy [`Event`] emitted on the server when the window of unacked messages of a reliable channel
+/// becomes full for a client (for example because the client is stalled and stopped sending acks).
+///
+/// See [`WindowFullPolicy`](crate::channel::builder::WindowFullPolicy) for how the channel behaves in that case.
+#[derive(Event, Debug, Copy, Clone, PartialEq)]
+pub struct ReliableWindowFull {
+    pub client_id: ClientId,
+    pub channel: ChannelKind,
+}
+
+/// Bevy [`Event`] emitted on the server when a client sends replication updates for an entity over which
+/// it doesn't have authority, and the updates are rejected by the
+/// [`AuthorityConflictPolicy`](crate::prelude::AuthorityConflictPolicy) of the entity.
+///
+/// This can indicate a cheating or buggy client. Note that it is also emitted for a short time after
+/// an authority transfer, for the updates that the previous authority sent before receiving the transfer.
+#[derive(Event, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct AuthorityViolation {
+    pub client_id: ClientId,
+    /// The server entity
+    pub entity: Entity,
+}
+
 /// Bevy [`Event`] emitted on the server on the frame where an input message from a client is received
 pub type InputEvent<I> = crate::shared::events::components::InputEvent<I, ClientId>;
 /// Bevy [`Event`] emitted on the server on the frame where a EntitySpawn replication message is received
 pub type EntitySpawnEvent = crate::shared::events::components::EntitySpawnEvent<ClientId>;
 /// Bevy [`Event`] emitted on the server on the frame where a EntityDepawn replication message is received
 pub type EntityDespawnEvent = crate::shared::events::components::EntityDespawnEvent<ClientId>;
+/// Bevy [`Event`] emitted on the server on the frame where a replicated component received from a client could not be read
+pub type ComponentDeserializationErrorEvent =
+    crate::shared::events::components::ComponentDeserializationErrorEvent<ClientId>;
 /// Bevy [`Event`] emitted on the server on the frame where a ComponentUpdate replication message is received
 pub type ComponentUpdateEvent<C> =
     crate::shared::events::components::ComponentUpdateEvent<C, ClientId>;
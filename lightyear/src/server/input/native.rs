@@ -4,7 +4,7 @@ use bevy::utils::HashMap;
 
 use crate::inputs::native::input_buffer::InputBuffer;
 use crate::inputs::native::InputMessage;
-use crate::prelude::server::DisconnectEvent;
+use crate::prelude::server::{DisconnectEvent, ServerConfig};
 use crate::prelude::{
     server::is_started, ClientId, MessageRegistry, ServerReceiveMessage, TickManager, UserAction,
 };
@@ -100,16 +100,24 @@ fn receive_input_message<A: UserAction>(
     // we use an EventReader in case the user wants to read the inputs in another system
     mut received_messages: EventReader<ServerReceiveMessage<InputMessage<A>>>,
     mut input_buffers: ResMut<InputBuffers<A>>,
+    config: Res<ServerConfig>,
 ) {
     received_messages.read().for_each(|event| {
         trace!("Received input message: {:?}", event);
         let client = event.from;
-        input_buffers
+        if !input_buffers
             .buffers
             .entry(event.from)
             .or_default()
             .1
-            .update_from_message(&event.message);
+            .update_from_message(&event.message, config.input.reorder_window)
+        {
+            debug!(
+                ?client,
+                end_tick = ?event.message.end_tick,
+                "Dropping input message that arrived too late"
+            );
+        }
         // TODO: allow automatic rebroadcast?
     });
 }
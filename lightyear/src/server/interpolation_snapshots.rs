@@ -0,0 +1,287 @@
+//! Interpolation snapshots for late-joining clients
+//!
+//! A client needs two server states of an interpolated entity before it can interpolate it. When an
+//! entity is spawned on a client (because the client just connected, or because the entity just became
+//! relevant to it), the client only receives the current state: the entity stays still until the next
+//! server update arrives, and only then starts moving.
+//!
+//! To avoid this, the server can keep the last few updates of the components that are interpolated with
+//! [`ComponentSyncMode::Full`](crate::prelude::client::ComponentSyncMode::Full), and send them along with
+//! the entity spawn to pre-fill the interpolation history of the client.
+//!
+//! The snapshots are only sent to the clients for which the entity is spawned and that interpolate it,
+//! so they follow the replication target, the [`SyncTarget`](crate::prelude::server::SyncTarget) and
+//! the network relevance of the entity.
+//! They are sent on the same channel as the tick of the initial replication snapshot, which has a
+//! higher priority than the replication updates.
+//!
+//! ```rust,ignore
+//! let server_config = ServerConfig {
+//!     interpolation_snapshots: InterpolationSnapshotConfig::default().with_snapshots(3),
+//!     ..default()
+//! };
+//! ```
+//!
+//! Components that contain entities are not recorded, since they would need to be mapped separately
+//! for each client.
+use std::collections::VecDeque;
+
+use bevy::ecs::entity::EntityHashMap;
+use bevy::prelude::*;
+use bytes::Bytes;
+
+use crate::channel::builder::InitialReplicationChannel;
+use crate::client::components::SyncComponent;
+use crate::prelude::{ComponentRegistry, MessageSend, NetworkTarget, Tick, TickManager};
+use crate::server::connection::ConnectionManager;
+use crate::server::error::ServerError;
+use crate::server::replication::send::SyncTarget;
+use crate::server::run_conditions::is_started;
+use crate::shared::replication::components::Replicating;
+use crate::shared::replication::entity_map::SendEntityMap;
+use crate::shared::replication::initial::InterpolationSnapshots;
+
+/// Configuration for the interpolation snapshots sent to late-joining clients
+#[derive(Clone, Copy, Debug, Default)]
+pub struct InterpolationSnapshotConfig {
+    /// Number of previous updates of the interpolated components that are kept for each entity
+    /// and sent to the clients when the entity is spawned for them.
+    ///
+    /// The default is 0, which disables the snapshots.
+    pub snapshots: u16,
+}
+
+impl InterpolationSnapshotConfig {
+    pub fn with_snapshots(mut self, snapshots: u16) -> Self {
+        self.snapshots = snapshots;
+        self
+    }
+}
+
+/// Stores the last `snapshots` updates of the interpolated components of each entity
+#[derive(Debug, Default)]
+pub(crate) struct InterpolationSnapshotHistory {
+    config: InterpolationSnapshotConfig,
+    // serialized components, from oldest (front) to most recent (back)
+    entities: EntityHashMap<VecDeque<(Tick, Vec<Bytes>)>>,
+}
+
+impl InterpolationSnapshotHistory {
+    pub(crate) fn new(config: InterpolationSnapshotConfig) -> Self {
+        Self {
+            config,
+            entities: EntityHashMap::default(),
+        }
+    }
+
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.config.snapshots > 0
+    }
+
+    /// Record the serialized value of a component of the entity at the given tick
+    pub(crate) fn record(&mut self, entity: Entity, tick: Tick, component: Bytes) {
+        let history = self.entities.entry(entity).or_default();
+        match history.back_mut() {
+            Some((last_tick, components)) if *last_tick == tick => components.push(component),
+            _ => {
+                history.push_back((tick, vec![component]));
+                if history.len() > self.config.snapshots as usize {
+                    history.pop_front();
+                }
+            }
+        }
+    }
+
+    pub(crate) fn remove(&mut self, entity: Entity) {
+        self.entities.remove(&entity);
+    }
+
+    pub(crate) fn get(&self, entity: Entity) -> Option<&VecDeque<(Tick, Vec<Bytes>)>> {
+        self.entities.get(&entity)
+    }
+}
+
+impl ConnectionManager {
+    /// Send the recorded snapshots of the entity to the clients of the target
+    pub(crate) fn send_interpolation_snapshots(
+        &mut self,
+        entity: Entity,
+        target: &NetworkTarget,
+    ) -> Result<(), ServerError> {
+        let Some(history) = self.interpolation_snapshots.get(entity) else {
+            return Ok(());
+        };
+        // local clients share the world of the server and don't interpolate
+        let clients: Vec<_> = self
+            .connections
+            .iter()
+            .filter(|(client_id, connection)| {
+                target.targets(client_id) && !connection.is_local_client()
+            })
+            .map(|(client_id, _)| *client_id)
+            .collect();
+        if clients.is_empty() {
+            return Ok(());
+        }
+        let message = InterpolationSnapshots {
+            entity,
+            snapshots: history.iter().cloned().collect(),
+        };
+        self.send_message_to_target::<InitialReplicationChannel, _>(
+            &message,
+            NetworkTarget::Only(clients),
+        )
+    }
+}
+
+pub(crate) fn add_interpolation_snapshot_systems<C: SyncComponent>(app: &mut App) {
+    app.add_systems(FixedPostUpdate, record_snapshot::<C>.run_if(is_started));
+}
+
+/// Record the value of the component for the interpolated entities that changed during this tick
+fn record_snapshot<C: SyncComponent>(
+    tick_manager: Res<TickManager>,
+    component_registry: Res<ComponentRegistry>,
+    mut sender: ResMut<ConnectionManager>,
+    query: Query<(Entity, Ref<C>, &SyncTarget), With<Replicating>>,
+) {
+    if !sender.interpolation_snapshots.is_enabled() || component_registry.is_map_entities::<C>() {
+        return;
+    }
+    let tick = tick_manager.tick();
+    let sender = sender.as_mut();
+    for (entity, component, sync_target) in query.iter() {
+        if sync_target.interpolation.is_empty() || !component.is_changed() {
+            continue;
+        }
+        if let Err(e) = component_registry.serialize(
+            &mut (*component).clone(),
+            &mut sender.writer,
+            &mut SendEntityMap::default(),
+        ) {
+            error!(?entity, "could not record interpolation snapshot: {:?}", e);
+            continue;
+        }
+        let bytes = sender.writer.split();
+        sender.interpolation_snapshots.record(entity, tick, bytes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::client::interpolation::{ConfirmedHistory, InterpolateStatus};
+    use crate::client::networking::ClientCommandsExt;
+    use crate::prelude::client::{Confirmed, InterpolationConfig};
+    use crate::prelude::server::{Replicate, ServerCommandsExt, ServerConfig};
+    use crate::prelude::{client, SharedConfig, TickConfig};
+    use crate::tests::protocol::ComponentSyncModeFull;
+    use crate::tests::stepper::BevyStepper;
+
+    /// A client that connects while an interpolated entity is moving receives the previous states
+    /// of the entity, and can interpolate it as soon as it is spawned
+    #[test]
+    fn test_late_join_interpolation_snapshots() {
+        let tick_duration = Duration::from_millis(10);
+        let shared_config = SharedConfig {
+            tick: TickConfig::new(tick_duration),
+            ..default()
+        };
+        let client_config = client::ClientConfig {
+            interpolation: InterpolationConfig::default()
+                .with_min_delay(Duration::from_millis(100)),
+            ..default()
+        };
+        let mut stepper = BevyStepper::new(shared_config, client_config, tick_duration);
+        stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<ServerConfig>()
+            .interpolation_snapshots = InterpolationSnapshotConfig::default().with_snapshots(3);
+        stepper.build();
+        let _ = stepper.server_app.world_mut().start_server();
+        let server_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn((
+                Replicate {
+                    sync: SyncTarget {
+                        interpolation: NetworkTarget::All,
+                        ..default()
+                    },
+                    ..default()
+                },
+                ComponentSyncModeFull(0.0),
+            ))
+            .id();
+        let move_entity = |stepper: &mut BevyStepper| {
+            stepper
+                .server_app
+                .world_mut()
+                .get_mut::<ComponentSyncModeFull>(server_entity)
+                .unwrap()
+                .0 += 1.0;
+            stepper.frame_step();
+        };
+        for _ in 0..5 {
+            move_entity(&mut stepper);
+        }
+        assert_eq!(
+            stepper
+                .server_app
+                .world()
+                .resource::<ConnectionManager>()
+                .interpolation_snapshots
+                .get(server_entity)
+                .unwrap()
+                .len(),
+            3
+        );
+
+        // the client joins while the entity keeps moving
+        let _ = stepper.client_app.world_mut().connect_client();
+        for _ in 0..200 {
+            move_entity(&mut stepper);
+            let Some(interpolated_entity) = stepper
+                .client_app
+                .world()
+                .resource::<client::ConnectionManager>()
+                .replication_receiver
+                .remote_entity_map
+                .get_local(server_entity)
+                .and_then(|confirmed| stepper.client_app.world().get::<Confirmed>(confirmed))
+                .and_then(|confirmed| confirmed.interpolated)
+            else {
+                continue;
+            };
+            let Some(history) = stepper
+                .client_app
+                .world()
+                .get::<ConfirmedHistory<ComponentSyncModeFull>>(interpolated_entity)
+            else {
+                continue;
+            };
+            // first frame where the interpolation history exists: we already have at least
+            // two server states to interpolate between
+            let status = stepper
+                .client_app
+                .world()
+                .get::<InterpolateStatus<ComponentSyncModeFull>>(interpolated_entity)
+                .unwrap();
+            let samples = history.buffer.len()
+                + status.start.is_some() as usize
+                + status.end.is_some() as usize;
+            assert!(samples >= 2, "only {samples} interpolation samples");
+            assert!(stepper
+                .client_app
+                .world()
+                .resource::<client::ConnectionManager>()
+                .interpolation_snapshots
+                .is_empty());
+            return;
+        }
+        panic!("the interpolated entity was not spawned on the client");
+    }
+}
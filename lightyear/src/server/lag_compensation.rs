@@ -0,0 +1,266 @@
+//! Lag compensation
+//!
+//! Clients see the other entities in the past: when a client fires at a target, the target has already
+//! moved on the server. To validate hits fairly, the server keeps the history of some components for a
+//! window of ticks, and can rewind an entity to the tick at which the client fired.
+//!
+//! The components that should be recorded are registered with
+//! [`add_lag_compensation`](crate::prelude::ComponentRegistration::add_lag_compensation).
+//! A snapshot of these components is recorded for every replicated entity at the end of each tick
+//! (in `FixedPostUpdate`), and can be retrieved with [`ConnectionManager::rewind`](crate::prelude::server::ConnectionManager::rewind).
+//!
+//! ```rust,ignore
+//! app.register_component::<Position>(ChannelDirection::ServerToClient)
+//!     .add_lag_compensation();
+//!
+//! fn validate_hit(sender: Res<ConnectionManager>, tick_manager: Res<TickManager>) {
+//!     // the tick at which the world was displayed on the client when it fired
+//!     let fire_tick = tick_manager.tick() - interpolation_delay_ticks;
+//!     if let Some(position) = sender
+//!         .rewind(target, fire_tick)
+//!         .and_then(|snapshot| snapshot.get::<Position>())
+//!     {
+//!         // check the hit against the historical position
+//!     }
+//! }
+//! ```
+//!
+//! Inputs are applied on the server at the tick for which they were generated by the client, so
+//! the tick to rewind to is usually the current tick minus the interpolation delay of the client.
+use std::any::Any;
+use std::collections::VecDeque;
+
+use bevy::ecs::entity::EntityHashMap;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use crate::prelude::{Tick, TickManager};
+use crate::protocol::component::ComponentKind;
+use crate::server::connection::ConnectionManager;
+use crate::server::run_conditions::is_started;
+use crate::shared::replication::components::Replicating;
+
+/// Configuration for lag compensation
+#[derive(Clone, Copy, Debug)]
+pub struct LagCompensationConfig {
+    /// Number of ticks of history that are kept for each entity.
+    ///
+    /// Rewinding to a tick older than this window returns `None`.
+    ///
+    /// The default is 32 ticks.
+    pub window: u16,
+}
+
+impl Default for LagCompensationConfig {
+    fn default() -> Self {
+        Self { window: 32 }
+    }
+}
+
+impl LagCompensationConfig {
+    pub fn with_window(mut self, window: u16) -> Self {
+        self.window = window;
+        self
+    }
+}
+
+/// Values of the lag-compensated components of an entity at a given tick
+#[derive(Default)]
+pub struct ComponentSnapshot {
+    components: HashMap<ComponentKind, Box<dyn Any + Send + Sync>>,
+}
+
+impl std::fmt::Debug for ComponentSnapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ComponentSnapshot")
+            .field("components", &self.components.keys())
+            .finish()
+    }
+}
+
+impl ComponentSnapshot {
+    /// Get the value of the component `C` in the snapshot
+    ///
+    /// Returns `None` if the component is not lag-compensated, or if the entity didn't have the component
+    pub fn get<C: Component>(&self) -> Option<&C> {
+        self.components
+            .get(&ComponentKind::of::<C>())
+            .and_then(|component| component.downcast_ref::<C>())
+    }
+
+    fn insert<C: Component + Clone>(&mut self, component: &C) {
+        self.components
+            .insert(ComponentKind::of::<C>(), Box::new(component.clone()));
+    }
+}
+
+/// Stores the [`ComponentSnapshot`] of each entity for the last `window` ticks
+#[derive(Debug, Default)]
+pub(crate) struct LagCompensationHistory {
+    config: LagCompensationConfig,
+    // from oldest (front) to most recent (back)
+    entities: EntityHashMap<VecDeque<(Tick, ComponentSnapshot)>>,
+}
+
+impl LagCompensationHistory {
+    pub(crate) fn new(config: LagCompensationConfig) -> Self {
+        Self {
+            config,
+            entities: EntityHashMap::default(),
+        }
+    }
+
+    /// Record the value of the component `C` for the entity at the given tick
+    pub(crate) fn record<C: Component + Clone>(
+        &mut self,
+        entity: Entity,
+        tick: Tick,
+        component: &C,
+    ) {
+        let history = self.entities.entry(entity).or_default();
+        match history.back_mut() {
+            Some((last_tick, snapshot)) if *last_tick == tick => snapshot.insert(component),
+            _ => {
+                let mut snapshot = ComponentSnapshot::default();
+                snapshot.insert(component);
+                history.push_back((tick, snapshot));
+            }
+        }
+    }
+
+    /// Remove the snapshots that are older than the window, as of the given tick
+    pub(crate) fn clear_older_than_window(&mut self, current_tick: Tick) {
+        let window = self.config.window as i16;
+        self.entities.retain(|_, history| {
+            while history
+                .front()
+                .is_some_and(|(tick, _)| current_tick - *tick > window)
+            {
+                history.pop_front();
+            }
+            !history.is_empty()
+        });
+    }
+
+    /// Get the snapshot of the entity at the given tick
+    pub(crate) fn get(&self, entity: Entity, tick: Tick) -> Option<&ComponentSnapshot> {
+        let history = self.entities.get(&entity)?;
+        let index = history
+            .binary_search_by(|(snapshot_tick, _)| snapshot_tick.cmp(&tick))
+            .ok()?;
+        history.get(index).map(|(_, snapshot)| snapshot)
+    }
+}
+
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum LagCompensationSet {
+    /// Remove the snapshots that are outside the window
+    Clear,
+    /// Record the snapshots for the current tick
+    Record,
+}
+
+pub(crate) struct LagCompensationPlugin;
+
+impl Plugin for LagCompensationPlugin {
+    fn build(&self, app: &mut App) {
+        app.configure_sets(
+            FixedPostUpdate,
+            (LagCompensationSet::Clear, LagCompensationSet::Record)
+                .chain()
+                .run_if(is_started),
+        );
+        app.add_systems(
+            FixedPostUpdate,
+            clear_history.in_set(LagCompensationSet::Clear),
+        );
+    }
+}
+
+pub(crate) fn add_lag_compensation_systems<C: Component + Clone>(app: &mut App) {
+    app.add_systems(
+        FixedPostUpdate,
+        record_history::<C>.in_set(LagCompensationSet::Record),
+    );
+}
+
+fn clear_history(tick_manager: Res<TickManager>, mut sender: ResMut<ConnectionManager>) {
+    sender
+        .lag_compensation
+        .clear_older_than_window(tick_manager.tick());
+}
+
+/// Record the value of the component for all replicated entities at the current tick
+fn record_history<C: Component + Clone>(
+    tick_manager: Res<TickManager>,
+    mut sender: ResMut<ConnectionManager>,
+    query: Query<(Entity, &C), With<Replicating>>,
+) {
+    let tick = tick_manager.tick();
+    for (entity, component) in query.iter() {
+        sender.lag_compensation.record(entity, tick, component);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::server::{Replicate, ServerConfig};
+    use crate::tests::protocol::ComponentSyncModeFull;
+    use crate::tests::stepper::BevyStepper;
+
+    /// The target moves every tick; a shot aimed at the position that the target had a few ticks ago
+    /// misses the current position but hits the historical position.
+    #[test]
+    fn test_rewind_moved_target() {
+        let mut stepper = BevyStepper::default_no_init();
+        stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<ServerConfig>()
+            .lag_compensation = LagCompensationConfig::default().with_window(5);
+        stepper.init();
+
+        let target = stepper
+            .server_app
+            .world_mut()
+            .spawn((Replicate::default(), ComponentSyncModeFull(0.0)))
+            .id();
+        let mut ticks = vec![];
+        for i in 0..10 {
+            stepper
+                .server_app
+                .world_mut()
+                .get_mut::<ComponentSyncModeFull>(target)
+                .unwrap()
+                .0 = i as f32;
+            stepper.frame_step();
+            ticks.push(stepper.server_app.world().resource::<TickManager>().tick());
+        }
+
+        // the client fired at the target when it was at position 7
+        let fire_tick = ticks[7];
+        let shot = 7.0;
+        let hit = |position: &ComponentSyncModeFull| (position.0 - shot).abs() < 0.5;
+        let world = stepper.server_app.world();
+        assert!(!hit(world.get::<ComponentSyncModeFull>(target).unwrap()));
+        let sender = world.resource::<ConnectionManager>();
+        let position = sender
+            .rewind(target, fire_tick)
+            .and_then(|snapshot| snapshot.get::<ComponentSyncModeFull>())
+            .unwrap();
+        assert!(hit(position));
+
+        // the most recent tick is also available
+        assert_eq!(
+            sender
+                .rewind(target, ticks[9])
+                .and_then(|snapshot| snapshot.get::<ComponentSyncModeFull>()),
+            Some(&ComponentSyncModeFull(9.0))
+        );
+        // ticks older than the window return None instead of the oldest snapshot
+        assert!(sender.rewind(target, ticks[1]).is_none());
+        // ticks in the future return None
+        assert!(sender.rewind(target, ticks[9] + 1i16).is_none());
+    }
+}
@@ -0,0 +1,223 @@
+//! Server side of the deterministic lockstep mode
+//!
+//! The server does not run the simulation: it collects the inputs of the clients and broadcasts
+//! them once they are complete (or timed out), and compares the checksums reported by the clients.
+//! See [`crate::shared::lockstep`] for an overview.
+use bevy::prelude::*;
+use bevy::utils::{Duration, HashMap};
+
+use crate::channel::builder::LockstepChannel;
+use crate::prelude::{ClientId, MessageSend, NetworkTarget, Tick, TickManager, UserAction};
+use crate::server::connection::ConnectionManager;
+use crate::server::message::ReceiveMessage;
+use crate::server::run_conditions::is_started;
+use crate::shared::lockstep::{
+    LockstepChecksumMessage, LockstepConfig, LockstepInputMessage, LockstepTickMessage,
+};
+use crate::shared::sets::{InternalMainSet, ServerMarker};
+
+/// Checksums of ticks older than this (compared to the most recent checksum) are discarded
+const CHECKSUM_HISTORY_TICKS: i16 = 64;
+
+/// Event emitted on the server when a client reports a different checksum than the other
+/// clients for a lockstep tick, which means that the simulations have diverged
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LockstepDesyncEvent {
+    pub tick: Tick,
+    pub client_id: ClientId,
+    pub checksum: u64,
+    /// Checksum reported for that tick by the first client
+    pub expected_checksum: u64,
+}
+
+/// Inputs received from the clients for the lockstep ticks that haven't been broadcast yet
+#[derive(Resource, Debug)]
+pub(crate) struct LockstepInputBuffer<A> {
+    config: LockstepConfig,
+    /// Next tick to broadcast. It starts at the server tick when the first client is connected,
+    /// and is reset while no client is connected
+    next_tick: Option<Tick>,
+    inputs: HashMap<Tick, HashMap<ClientId, Option<A>>>,
+    /// First checksum reported for each recent tick
+    checksums: HashMap<Tick, (ClientId, u64)>,
+}
+
+impl<A> LockstepInputBuffer<A> {
+    fn new(config: LockstepConfig) -> Self {
+        Self {
+            config,
+            next_tick: None,
+            inputs: HashMap::default(),
+            checksums: HashMap::default(),
+        }
+    }
+}
+
+pub struct ServerLockstepPlugin<A: UserAction> {
+    config: LockstepConfig,
+    _marker: std::marker::PhantomData<A>,
+}
+
+impl<A: UserAction> ServerLockstepPlugin<A> {
+    pub(crate) fn new(config: LockstepConfig) -> Self {
+        Self {
+            config,
+            _marker: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<A: UserAction> Plugin for ServerLockstepPlugin<A> {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(LockstepInputBuffer::<A>::new(self.config));
+        app.add_event::<LockstepDesyncEvent>();
+        app.add_systems(
+            PreUpdate,
+            (receive_inputs::<A>, receive_checksums::<A>)
+                .after(InternalMainSet::<ServerMarker>::ReceiveEvents)
+                .run_if(is_started),
+        );
+        app.add_systems(
+            PostUpdate,
+            broadcast_inputs::<A>
+                .before(InternalMainSet::<ServerMarker>::SendEvents)
+                .run_if(is_started),
+        );
+    }
+}
+
+/// Buffer the inputs received from the clients
+fn receive_inputs<A: UserAction>(
+    tick_manager: Res<TickManager>,
+    mut messages: ResMut<Events<ReceiveMessage<LockstepInputMessage<A>>>>,
+    mut buffer: ResMut<LockstepInputBuffer<A>>,
+) {
+    // if we haven't started broadcasting yet, we will start at the current tick
+    let next_tick = buffer.next_tick.unwrap_or(tick_manager.tick());
+    for event in messages.drain() {
+        let LockstepInputMessage { tick, input } = event.message;
+        if tick < next_tick {
+            debug!(client_id = ?event.from, ?tick, "Dropping lockstep input received after the tick was broadcast");
+            continue;
+        }
+        buffer
+            .inputs
+            .entry(tick)
+            .or_default()
+            .insert(event.from, input);
+    }
+}
+
+/// Compare the checksums reported by the clients
+fn receive_checksums<A: UserAction>(
+    mut messages: ResMut<Events<ReceiveMessage<LockstepChecksumMessage>>>,
+    mut buffer: ResMut<LockstepInputBuffer<A>>,
+    mut events: EventWriter<LockstepDesyncEvent>,
+) {
+    for event in messages.drain() {
+        let LockstepChecksumMessage { tick, checksum } = event.message;
+        let (first_client, expected_checksum) = *buffer
+            .checksums
+            .entry(tick)
+            .or_insert((event.from, checksum));
+        if expected_checksum != checksum {
+            error!(
+                ?tick,
+                client_id = ?event.from,
+                ?first_client,
+                "Lockstep desync: the client reported a different checksum"
+            );
+            events.send(LockstepDesyncEvent {
+                tick,
+                client_id: event.from,
+                checksum,
+                expected_checksum,
+            });
+        }
+        buffer
+            .checksums
+            .retain(|t, _| tick - *t < CHECKSUM_HISTORY_TICKS);
+    }
+}
+
+/// Broadcast the inputs of every tick for which all the connected clients have sent their input,
+/// or for which the input timeout has elapsed
+fn broadcast_inputs<A: UserAction>(
+    tick_manager: Res<TickManager>,
+    mut buffer: ResMut<LockstepInputBuffer<A>>,
+    mut sender: ResMut<ConnectionManager>,
+) {
+    let mut clients: Vec<ClientId> = sender
+        .connections
+        .iter()
+        .filter(|(_, connection)| !connection.is_local_client())
+        .map(|(client_id, _)| *client_id)
+        .collect();
+    if clients.is_empty() {
+        // restart from the current tick when clients connect, instead of broadcasting
+        // all the ticks that elapsed in the meantime
+        buffer.next_tick = None;
+        buffer.inputs.clear();
+        return;
+    }
+    clients.sort();
+    let mut tick = *buffer.next_tick.get_or_insert(tick_manager.tick());
+    let timeout_ticks = timeout_ticks(
+        buffer.config.input_timeout,
+        tick_manager.config.tick_duration,
+    );
+    loop {
+        let complete = buffer
+            .inputs
+            .get(&tick)
+            .is_some_and(|inputs| clients.iter().all(|c| inputs.contains_key(c)));
+        let timed_out = tick_manager.tick() - tick >= timeout_ticks;
+        if !complete && !timed_out {
+            break;
+        }
+        let mut received = buffer.inputs.remove(&tick).unwrap_or_default();
+        if !complete {
+            debug!(
+                ?tick,
+                "Lockstep tick timed out waiting for the inputs of some clients"
+            );
+        }
+        let message = LockstepTickMessage {
+            tick,
+            inputs: clients
+                .iter()
+                .map(|client_id| (*client_id, received.remove(client_id).flatten()))
+                .collect(),
+        };
+        if let Err(e) =
+            sender.send_message_to_target::<LockstepChannel, _>(&message, NetworkTarget::All)
+        {
+            error!("could not send lockstep inputs: {:?}", e);
+        }
+        tick = tick + 1;
+    }
+    buffer.next_tick = Some(tick);
+}
+
+/// Number of ticks after which a tick times out, saturated to the largest tick difference
+fn timeout_ticks(input_timeout: Duration, tick_duration: Duration) -> i16 {
+    let ticks = input_timeout.as_nanos().div_ceil(tick_duration.as_nanos());
+    i16::try_from(ticks).unwrap_or(i16::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timeout_ticks() {
+        let tick_duration = Duration::from_millis(10);
+        assert_eq!(timeout_ticks(Duration::from_millis(200), tick_duration), 20);
+        assert_eq!(timeout_ticks(Duration::from_millis(205), tick_duration), 21);
+        // long timeouts don't wrap around
+        assert_eq!(
+            timeout_ticks(Duration::from_secs(3600), tick_duration),
+            i16::MAX
+        );
+    }
+}
@@ -1,9 +1,12 @@
+use crate::channel::builder::TickEventChannel;
 use crate::prelude::server::{is_stopped, RoomId, RoomManager, ServerError};
 use crate::prelude::{
-    is_host_server, Channel, ChannelKind, ClientId, MainSet, Message, MessageRegistry, MessageSend,
+    is_host_server, Channel, ChannelKind, ClientId, MainSet, Message, MessageId, MessageRegistry,
+    MessageSend, Tick,
 };
+use crate::protocol::message::tick_event::TickEventMessage;
 use crate::serialize::reader::Reader;
-use crate::server::connection::ConnectionManager;
+use crate::server::connection::{ConnectionManager, ServerInstanceId};
 use crate::server::relevance::error::RelevanceError;
 use crate::shared::message::private::InternalMessageSend;
 use crate::shared::replication::entity_map::SendEntityMap;
@@ -242,15 +245,78 @@ impl ConnectionManager {
         self.send_message_to_target::<C, M>(message, target)
     }
 
+    /// Send a message to all clients of a [`ServerInstanceId`]
+    pub fn send_message_to_instance<C: Channel, M: Message>(
+        &mut self,
+        message: &M,
+        instance: ServerInstanceId,
+    ) -> Result<(), ServerError> {
+        let target = self.instance_target(instance);
+        self.send_message_to_target::<C, M>(message, target)
+    }
+
     /// Queues up a message to be sent to a client
+    ///
+    /// Returns an error if the client is not connected.
     pub fn send_message<C: Channel, M: Message>(
         &mut self,
         client_id: ClientId,
         message: &M,
     ) -> Result<(), ServerError> {
+        self.connection(client_id)?;
         self.send_message_to_target::<C, M>(message, NetworkTarget::Single(client_id))
     }
 
+    /// Queues up a message to be sent to a client, and returns the id of the message.
+    ///
+    /// The id can be used to [`cancel_message`](Self::cancel_message) on reliable channels.
+    /// It is `None` if the channel does not assign message ids, or if the client is a local client.
+    ///
+    /// Returns an error if the client is not connected.
+    pub fn send_message_with_id<C: Channel, M: Message>(
+        &mut self,
+        client_id: ClientId,
+        message: &M,
+    ) -> Result<Option<MessageId>, ServerError> {
+        let connection = self
+            .connections
+            .get_mut(&client_id)
+            .ok_or(ServerError::ClientIdNotFound(client_id))?;
+        self.message_registry.serialize(
+            message,
+            &mut self.writer,
+            &mut connection
+                .replication_receiver
+                .remote_entity_map
+                .local_to_remote,
+        )?;
+        let message_bytes = self.writer.split();
+        // for local clients, we don't want to buffer messages in the MessageManager since
+        // there is no io
+        if connection.is_local_client() {
+            connection.local_messages_to_send.push(message_bytes);
+            return Ok(None);
+        }
+        connection.buffer_message(message_bytes, ChannelKind::of::<C>())
+    }
+
+    /// Send an event that the clients of the target emit as a
+    /// [`TickEvent`](crate::prelude::client::TickEvent) when their interpolation tick reaches `tick`.
+    ///
+    /// The event type must be registered with
+    /// [`register_tick_event`](crate::prelude::AppTickEventExt::register_tick_event).
+    pub fn send_tick_event<E: Message>(
+        &mut self,
+        event: E,
+        tick: Tick,
+        target: NetworkTarget,
+    ) -> Result<(), ServerError> {
+        self.send_message_to_target::<TickEventChannel, _>(
+            &TickEventMessage { tick, event },
+            target,
+        )
+    }
+
     pub(crate) fn buffer_message_bytes(
         &mut self,
         message: Bytes,
@@ -360,10 +426,12 @@ impl InternalMessageSend for ConnectionManager {
 #[cfg(test)]
 mod tests {
     use crate::prelude::server::ServerTriggerExt;
-    use crate::prelude::{ClientReceiveMessage, NetworkTarget, ServerSendMessage};
+    use crate::prelude::{ClientId, ClientReceiveMessage, NetworkTarget, ServerSendMessage};
+    use crate::server::error::ServerError;
     use crate::shared::message::MessageSend;
     use crate::tests::host_server_stepper::HostServerStepper;
-    use crate::tests::protocol::{Channel1, IntegerEvent, StringMessage};
+    use crate::tests::protocol::{Channel1, IntegerEvent, ReliableChannel, StringMessage};
+    use crate::tests::stepper::{BevyStepper, TEST_CLIENT_ID};
     use bevy::app::Update;
     use bevy::prelude::{EventReader, Events, ResMut, Resource, Trigger};
 
@@ -478,5 +546,84 @@ mod tests {
         assert_eq!(stepper.client_app.world().resource::<Counter>().0, 10);
     }
 
+    /// Send a message via ConnectionManager to a single client, and check that sending
+    /// a message to a client that is not connected returns an error
+    #[test]
+    fn server_send_message_single_client() {
+        let mut stepper = BevyStepper::default();
+        stepper.client_app.init_resource::<Counter>();
+        stepper.client_app.add_systems(Update, count_messages);
+
+        let mut manager = stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<crate::prelude::server::ConnectionManager>();
+        manager
+            .send_message::<Channel1, StringMessage>(
+                ClientId::Netcode(TEST_CLIENT_ID),
+                &StringMessage("a".to_string()),
+            )
+            .unwrap();
+        assert!(matches!(
+            manager.send_message::<Channel1, StringMessage>(
+                ClientId::Netcode(TEST_CLIENT_ID + 1),
+                &StringMessage("a".to_string()),
+            ),
+            Err(ServerError::ClientIdNotFound(ClientId::Netcode(id))) if id == TEST_CLIENT_ID + 1
+        ));
+        stepper.frame_step();
+        stepper.frame_step();
+
+        assert_eq!(stepper.client_app.world().resource::<Counter>().0, 1);
+    }
+
+    /// Cancel a message using the id returned when sending it via ConnectionManager
+    #[test]
+    fn server_cancel_message_with_id() {
+        let mut stepper = BevyStepper::default();
+        stepper.client_app.init_resource::<Counter>();
+        stepper.client_app.add_systems(Update, count_messages);
+        let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+
+        let mut manager = stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<crate::prelude::server::ConnectionManager>();
+        let message_id = manager
+            .send_message_with_id::<ReliableChannel, StringMessage>(
+                client_id,
+                &StringMessage("a".to_string()),
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            manager
+                .pending_messages::<ReliableChannel>(client_id)
+                .unwrap(),
+            vec![message_id]
+        );
+        assert!(manager
+            .cancel_message::<ReliableChannel>(client_id, message_id)
+            .unwrap());
+        stepper.frame_step();
+        stepper.frame_step();
+        assert_eq!(stepper.client_app.world().resource::<Counter>().0, 0);
+
+        // a message that is not cancelled is received
+        stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<crate::prelude::server::ConnectionManager>()
+            .send_message_with_id::<ReliableChannel, StringMessage>(
+                client_id,
+                &StringMessage("a".to_string()),
+            )
+            .unwrap()
+            .unwrap();
+        stepper.frame_step();
+        stepper.frame_step();
+        assert_eq!(stepper.client_app.world().resource::<Counter>().0, 1);
+    }
+
     // TODO: send_trigger via ConnectionManager
 }
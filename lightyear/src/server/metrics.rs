@@ -0,0 +1,123 @@
+//! Per-client network metrics
+//!
+//! The [`NetworkMetrics`] resource keeps track of how much data the server exchanges with each client,
+//! which is useful to find out which client's view is the most expensive to replicate.
+//!
+//! ```rust,ignore
+//! fn log_bandwidth(metrics: Res<NetworkMetrics>) {
+//!     for (client_id, client_metrics) in metrics.iter() {
+//!         info!(?client_id, bytes_sent = client_metrics.bytes_sent, "bandwidth");
+//!     }
+//! }
+//! ```
+use bevy::prelude::{Reflect, Resource};
+use bevy::utils::HashMap;
+
+use crate::prelude::ClientId;
+
+/// Network metrics for a single client
+#[derive(Debug, Default, Clone, Copy, PartialEq, Reflect)]
+pub struct ClientNetworkMetrics {
+    /// Total number of bytes sent to the client
+    pub bytes_sent: u64,
+    /// Total number of bytes received from the client
+    pub bytes_received: u64,
+    /// Total number of packets sent to the client
+    pub packets_sent: u64,
+    /// Total number of packets received from the client
+    pub packets_received: u64,
+    /// Number of entities included in the replication messages buffered for the client during the last tick
+    pub replicated_entities: usize,
+}
+
+/// Resource tracking the network metrics of each connected client.
+///
+/// It is updated by the server when packets are sent to or received from a client.
+/// The metrics of a client are removed when it disconnects.
+#[derive(Resource, Debug, Default)]
+pub struct NetworkMetrics {
+    clients: HashMap<ClientId, ClientNetworkMetrics>,
+}
+
+impl NetworkMetrics {
+    /// Get the metrics of a given client
+    pub fn get(&self, client_id: ClientId) -> Option<&ClientNetworkMetrics> {
+        self.clients.get(&client_id)
+    }
+
+    /// Iterate through the metrics of all clients
+    pub fn iter(&self) -> impl Iterator<Item = (&ClientId, &ClientNetworkMetrics)> {
+        self.clients.iter()
+    }
+
+    pub(crate) fn record_send(&mut self, client_id: ClientId, bytes: usize) {
+        let metrics = self.clients.entry(client_id).or_default();
+        metrics.bytes_sent += bytes as u64;
+        metrics.packets_sent += 1;
+    }
+
+    pub(crate) fn record_receive(&mut self, client_id: ClientId, bytes: usize) {
+        let metrics = self.clients.entry(client_id).or_default();
+        metrics.bytes_received += bytes as u64;
+        metrics.packets_received += 1;
+    }
+
+    pub(crate) fn set_replicated_entities(&mut self, client_id: ClientId, count: usize) {
+        self.clients
+            .entry(client_id)
+            .or_default()
+            .replicated_entities = count;
+    }
+
+    pub(crate) fn remove(&mut self, client_id: ClientId) {
+        self.clients.remove(&client_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::server::{Replicate, ReplicationTarget};
+    use crate::prelude::NetworkTarget;
+    use crate::tests::multi_stepper::{MultiBevyStepper, TEST_CLIENT_ID_1, TEST_CLIENT_ID_2};
+    use crate::tests::protocol::ComponentSyncModeSimple;
+
+    #[test]
+    fn test_network_metrics_per_client() {
+        let mut stepper = MultiBevyStepper::default();
+        let client_id_1 = ClientId::Netcode(TEST_CLIENT_ID_1);
+        let client_id_2 = ClientId::Netcode(TEST_CLIENT_ID_2);
+
+        const N: usize = 5;
+        for i in 0..N {
+            stepper.server_app.world_mut().spawn((
+                Replicate {
+                    target: ReplicationTarget {
+                        target: NetworkTarget::Single(client_id_1),
+                    },
+                    ..Default::default()
+                },
+                ComponentSyncModeSimple(i as f32),
+            ));
+        }
+        stepper.frame_step();
+
+        let metrics = stepper.server_app.world().resource::<NetworkMetrics>();
+        let metrics_1 = metrics.get(client_id_1).unwrap();
+        let metrics_2 = metrics.get(client_id_2).unwrap();
+        assert_eq!(metrics_1.replicated_entities, N);
+        assert_eq!(metrics_2.replicated_entities, 0);
+        // the spawns are only sent to client 1
+        assert!(metrics_1.bytes_sent > metrics_2.bytes_sent);
+        assert!(metrics_1.packets_sent > 0);
+
+        stepper.frame_step();
+        stepper.frame_step();
+        let metrics = stepper.server_app.world().resource::<NetworkMetrics>();
+        let metrics_1 = metrics.get(client_id_1).unwrap();
+        // nothing changed, so no entities are replicated
+        assert_eq!(metrics_1.replicated_entities, 0);
+        assert!(metrics_1.packets_received > 0);
+        assert!(metrics_1.bytes_received > 0);
+    }
+}
@@ -13,15 +13,24 @@ pub mod events;
 
 pub mod input;
 
+pub mod interpolation_snapshots;
+
+pub mod lag_compensation;
+
+pub mod lockstep;
+
 pub(crate) mod io;
 
 pub mod plugin;
 
 pub mod message;
+pub mod metrics;
 pub(crate) mod prediction;
 
 pub mod clients;
 pub(crate) mod networking;
+pub mod relay;
 pub mod relevance;
 pub mod replication;
 pub mod run_conditions;
+pub mod shutdown;
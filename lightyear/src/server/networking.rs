@@ -1,8 +1,10 @@
 //! Defines the server bevy systems and run conditions
 use crate::connection::netcode::Error as NetcodeError;
 use crate::connection::server::{
-    ConnectionError, IoConfig, NetServer, ServerConnection, ServerConnections,
+    ConnectionError, DeniedReason, IoConfig, NetConfig, NetServer, ServerConnection,
+    ServerConnections,
 };
+use crate::packet::compression::CompressionConfig;
 use crate::prelude::server::is_stopped;
 use crate::prelude::{
     is_host_server, ChannelRegistry, ClientId, MainSet, MessageRegistry, TickManager, TimeManager,
@@ -13,14 +15,20 @@ use crate::server::clients::ControlledEntities;
 use crate::server::config::ServerConfig;
 use crate::server::connection::ConnectionManager;
 use crate::server::error::ServerError;
+use crate::server::events::ConnectionRequestEvent;
 use crate::server::io::ServerIoEvent;
+use crate::server::metrics::{ClientNetworkMetrics, NetworkMetrics};
+use crate::server::replication::send::NetworkIdAllocator;
 use crate::server::run_conditions::is_started_ref;
+use crate::server::shutdown::{
+    handle_graceful_shutdown, start_graceful_shutdown, GracefulShutdown,
+};
 use crate::shared::sets::{InternalMainSet, ServerMarker};
 use crate::transport::error::Error as TransportError;
 use async_channel::TryRecvError;
 use bevy::ecs::system::{RunSystemOnce, SystemChangeTick};
 use bevy::prelude::*;
-use tracing::{debug, error, trace};
+use tracing::{debug, error, trace, warn};
 
 /// Plugin handling the server networking systems: sending/receiving packets to clients
 #[derive(Default)]
@@ -38,6 +46,9 @@ impl Plugin for ServerNetworkingPlugin {
             // REFLECTION
             .register_type::<IoConfig>()
             .register_type::<NetworkingState>()
+            .register_type::<ClientNetworkMetrics>()
+            // RESOURCES
+            .init_resource::<NetworkMetrics>()
             // SYSTEM SETS
             .configure_sets(
                 PreUpdate,
@@ -62,7 +73,14 @@ impl Plugin for ServerNetworkingPlugin {
             )
             .add_systems(
                 PostUpdate,
-                (send, send_host_server.run_if(is_host_server))
+                (
+                    handle_pending_disconnects.before(send),
+                    send,
+                    handle_graceful_shutdown
+                        .after(send)
+                        .run_if(resource_exists::<GracefulShutdown>),
+                    send_host_server.run_if(is_host_server),
+                )
                     .in_set(InternalMainSet::<ServerMarker>::Send),
             );
 
@@ -98,8 +116,11 @@ pub(crate) fn receive_packets(
     message_registry: Res<MessageRegistry>,
     system_change_tick: SystemChangeTick,
     aggregate_client_errors: Local<Vec<(usize, ConnectionError)>>,
+    mut network_metrics: ResMut<NetworkMetrics>,
+    mut connection_request_events: EventWriter<ConnectionRequestEvent>,
 ) {
     trace!("Receive client packets");
+    connection_manager.disconnected_clients.clear();
     let delta = virtual_time.delta();
     // UPDATE: update server state, send keep-alives, receive packets from io
     // update time manager
@@ -109,6 +130,8 @@ pub(crate) fn receive_packets(
     // update server net connections
     // reborrow trick to enable split borrows
     let netservers = &mut *netservers;
+    // the requests that were not answered are handled with the default answer during the update
+    netservers.pending_requests.clear();
     for (server_idx, netserver) in netservers.servers.iter_mut().enumerate() {
         if let Some(io) = netserver.io_mut() {
             if let Some(receiver) = &mut io.context.event_receiver {
@@ -155,13 +178,21 @@ pub(crate) fn receive_packets(
             }
         }
 
+        for request in netserver.new_connection_requests() {
+            debug!(client_id = ?request.client_id, "Received connection request");
+            netservers
+                .pending_requests
+                .insert(request.client_id, server_idx);
+            connection_request_events.send(request);
+        }
+
         for client_id in netserver.new_connections() {
             netservers.client_server_map.insert(client_id, server_idx);
             // spawn an entity for the client
             let client_entity = commands
                 .spawn((ControlledEntities::default(), Name::new("Client")))
                 .id();
-            connection_manager.add(client_id, client_entity);
+            connection_manager.add(client_id, client_entity, tick_manager.tick());
         }
 
         // TODO: handle disconnections in a separate system that listens to ServerDisconnect events
@@ -171,6 +202,7 @@ pub(crate) fn receive_packets(
             if netservers.client_server_map.remove(&client_id).is_some() {
                 debug!("removing connection from connection manager");
                 connection_manager.remove(client_id);
+                network_metrics.remove(client_id);
                 // NOTE: we don't despawn the entity right away to let the user react to
                 // the disconnect event
             } else {
@@ -186,6 +218,15 @@ pub(crate) fn receive_packets(
         tick_manager.as_ref(),
     );
 
+    // disconnect the clients that have stopped acking messages on a reliable channel
+    for client_id in connection_manager.take_reliable_window_timed_out_clients() {
+        warn!(
+            ?client_id,
+            "Disconnecting client because its reliable channel window was full for too long"
+        );
+        commands.disconnect(client_id);
+    }
+
     // RECV_PACKETS: buffer packets into message managers
     // enable split borrows on connection manager
     let connection_manager = &mut *connection_manager;
@@ -207,6 +248,7 @@ pub(crate) fn receive_packets(
             // packets from a client
             // TODO: use connection to apply on BOTH message manager and replication manager
             if let Some(connection) = connection_manager.connections.get_mut(&client_id) {
+                network_metrics.record_receive(client_id, payload.len());
                 connection
                     .recv_packet(
                         payload,
@@ -272,6 +314,7 @@ pub(crate) fn send(
     mut connection_manager: ResMut<ConnectionManager>,
     tick_manager: Res<TickManager>,
     time_manager: Res<TimeManager>,
+    mut network_metrics: ResMut<NetworkMetrics>,
 ) {
     trace!("Send packets to clients");
     let span = info_span!("send_packets").entered();
@@ -292,6 +335,7 @@ pub(crate) fn send(
                 .servers
                 .get_mut(netserver_idx)
                 .ok_or(ServerError::ServerConnectionNotFound)?;
+            network_metrics.set_replicated_entities(*client_id, connection.replicated_entities);
             for packet_byte in connection.send_packets(&time_manager, &tick_manager)? {
                 #[cfg(feature = "metrics")]
                 {
@@ -304,6 +348,7 @@ pub(crate) fn send(
                     metrics::counter!("transport::send::packets").increment(packets);
                     metrics::counter!("transport::send::kb").increment(bytes);
                 }
+                network_metrics.record_send(*client_id, packet_byte.len());
                 if let Err(e) = netserver.send(packet_byte.as_slice(), *client_id) {
                     log_client_error(e);
                 }
@@ -315,6 +360,18 @@ pub(crate) fn send(
         });
 }
 
+/// Disconnect the clients that were requested via [`ConnectionManager::disconnect`]
+fn handle_pending_disconnects(world: &mut World) {
+    let pending_disconnects = std::mem::take(
+        &mut world
+            .resource_mut::<ConnectionManager>()
+            .pending_disconnects,
+    );
+    for client_id in pending_disconnects {
+        world.disconnect(client_id);
+    }
+}
+
 fn log_client_error(error: ConnectionError) {
     let suppress_error = match &error {
         ConnectionError::Netcode(NetcodeError::Transport(transport_error)) => {
@@ -372,7 +429,18 @@ pub enum NetworkingState {
 /// - we can take into account any changes to the server config
 fn rebuild_server_connections(world: &mut World) {
     debug!("Rebuild server connection");
-    let server_config = world.resource::<ServerConfig>().clone();
+    let mut server_config = world.resource::<ServerConfig>().clone();
+    // fall back to the compression of the IoConfigs, which is deprecated
+    if matches!(server_config.packet.compression, CompressionConfig::None) {
+        if let Some(compression) = server_config
+            .net
+            .iter()
+            .map(NetConfig::io_compression)
+            .find(|compression| !matches!(compression, CompressionConfig::None))
+        {
+            server_config.packet.compression = compression;
+        }
+    }
 
     // insert a new connection manager (to reset message numbers, ping manager, etc.)
     let connection_manager = ConnectionManager::new(
@@ -381,6 +449,8 @@ fn rebuild_server_connections(world: &mut World) {
         server_config.replication,
         server_config.packet,
         server_config.ping,
+        server_config.lag_compensation,
+        server_config.interpolation_snapshots,
     );
     // // make sure the previous replication metadata is ported over to the new manager
     // if let Some(mut previous_manager) = world.get_resource_mut::<ConnectionManager>() {
@@ -388,6 +458,8 @@ fn rebuild_server_connections(world: &mut World) {
     //         std::mem::take(&mut previous_manager.replicate_component_cache);
     // }
     world.insert_resource(connection_manager);
+    // restart the allocation of the network ids
+    world.insert_resource(NetworkIdAllocator::new(server_config.network_id));
 
     // rebuild the server connections and insert them
     let server_connections = ServerConnections::new(server_config.net);
@@ -409,15 +481,20 @@ fn on_starting(world: &mut World) {
         .resource_mut::<ServerConnections>()
         .start()
         .inspect_err(|e| error!("Error starting server connections: {:?}", e));
+    world.resource_mut::<ConnectionManager>().started = true;
     world.insert_resource(NextState::Pending(NetworkingState::Started));
     info!("Server is started.");
 }
 
 /// System that runs when we enter the Stopped state
 fn on_stopping(
+    mut commands: Commands,
     mut server_connections: ResMut<ServerConnections>,
+    mut connection_manager: ResMut<ConnectionManager>,
     mut server_state: ResMut<NextState<NetworkingState>>,
 ) {
+    commands.remove_resource::<GracefulShutdown>();
+    connection_manager.started = false;
     let _ = server_connections
         .stop()
         .inspect_err(|e| error!("Error stopping server connections: {:?}", e));
@@ -435,8 +512,28 @@ pub trait ServerCommandsExt {
     /// Stop the server: disconnect all clients and stop listening for connections
     fn stop_server(&mut self);
 
+    /// Stop the server gracefully:
+    /// - send a [`ServerShutdown`](crate::server::shutdown::ServerShutdown) notice with the `reason` to all clients
+    /// - keep sending packets until the clients have acked all the pending reliable messages,
+    ///   or until the [`ShutdownConfig::timeout`](crate::server::config::ShutdownConfig::timeout) has elapsed
+    /// - disconnect every client, which emits a [`DisconnectEvent`](crate::prelude::server::DisconnectEvent) for each of them
+    /// - stop listening for connections
+    fn stop_server_graceful(&mut self, reason: impl Into<String>);
+
     /// Disconnect a given client
+    ///
+    /// Does nothing if the client is not connected.
     fn disconnect(&mut self, client_id: ClientId);
+
+    /// Accept the connection request of a client (see [`ConnectionRequestEvent`]).
+    ///
+    /// The client will be connected and its client entity spawned on the next frame.
+    fn accept_connection(&mut self, client_id: ClientId);
+
+    /// Reject the connection request of a client (see [`ConnectionRequestEvent`]).
+    ///
+    /// The [`DeniedReason`] is sent to the client, and no client entity is spawned.
+    fn reject_connection(&mut self, client_id: ClientId, reason: DeniedReason);
 }
 
 impl ServerCommandsExt for Commands<'_, '_> {
@@ -452,11 +549,30 @@ impl ServerCommandsExt for Commands<'_, '_> {
         });
     }
 
+    fn stop_server_graceful(&mut self, reason: impl Into<String>) {
+        let reason = reason.into();
+        self.queue(move |world: &mut World| {
+            world.stop_server_graceful(reason);
+        });
+    }
+
     fn disconnect(&mut self, client_id: ClientId) {
         self.queue(move |world: &mut World| {
             world.disconnect(client_id);
         });
     }
+
+    fn accept_connection(&mut self, client_id: ClientId) {
+        self.queue(move |world: &mut World| {
+            world.accept_connection(client_id);
+        });
+    }
+
+    fn reject_connection(&mut self, client_id: ClientId, reason: DeniedReason) {
+        self.queue(move |world: &mut World| {
+            world.reject_connection(client_id, reason);
+        });
+    }
 }
 
 impl ServerCommandsExt for World {
@@ -468,7 +584,18 @@ impl ServerCommandsExt for World {
         self.insert_resource(NextState::Pending(NetworkingState::Stopping));
     }
 
+    fn stop_server_graceful(&mut self, reason: impl Into<String>) {
+        start_graceful_shutdown(self, reason.into());
+    }
+
     fn disconnect(&mut self, client_id: ClientId) {
+        if self
+            .get_resource::<ConnectionManager>()
+            .is_none_or(|manager| manager.connection(client_id).is_err())
+        {
+            debug!(?client_id, "Cannot disconnect client: it is not connected");
+            return;
+        }
         if let Some(mut connections) = self.get_resource_mut::<ServerConnections>() {
             // remove the client from the client-server map
             // call disconnect on the NetServer
@@ -486,14 +613,133 @@ impl ServerCommandsExt for World {
             connection_manager.remove(client_id);
         }
     }
+
+    fn accept_connection(&mut self, client_id: ClientId) {
+        if let Some(mut connections) = self.get_resource_mut::<ServerConnections>() {
+            connections
+                .answer_connection_request(client_id, None)
+                .unwrap_or_else(|e| {
+                    error!(?client_id, "Error accepting connection request: {:?}", e);
+                });
+        }
+    }
+
+    fn reject_connection(&mut self, client_id: ClientId, reason: DeniedReason) {
+        if let Some(mut connections) = self.get_resource_mut::<ServerConnections>() {
+            connections
+                .answer_connection_request(client_id, Some(reason))
+                .unwrap_or_else(|e| {
+                    error!(?client_id, "Error rejecting connection request: {:?}", e);
+                });
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::connection::client::{
+        ClientConnection, ConnectionError, ConnectionState, NetClient,
+    };
+    use crate::connection::server::{ConnectionRequestDefault, DeniedReason};
+    use crate::prelude::client::ClientCommandsExt;
     use crate::prelude::server::{ControlledBy, ControlledEntities, ServerCommandsExt};
-    use crate::prelude::{client, server, ClientId, NetworkTarget, ServerConnectionManager};
+    use crate::prelude::{
+        client, server, ClientId, NetworkTarget, ServerConnectionManager, TickManager,
+    };
+    use crate::server::error::ClientLookupError;
+    use crate::tests::multi_stepper::{MultiBevyStepper, TEST_CLIENT_ID_1, TEST_CLIENT_ID_2};
     use crate::tests::stepper::{BevyStepper, TEST_CLIENT_ID};
-    use bevy::prelude::{default, Entity, With};
+    use bevy::prelude::{
+        default, Commands, Entity, EventReader, ResMut, Resource, State, Update, With,
+    };
+
+    /// The number of connected clients and their metadata match the connected test clients
+    #[test]
+    fn test_connected_clients_metadata() {
+        let mut stepper = MultiBevyStepper::default();
+        stepper.frame_step();
+
+        let server_tick = stepper.server_app.world().resource::<TickManager>().tick();
+        let manager = stepper
+            .server_app
+            .world()
+            .resource::<ServerConnectionManager>();
+        assert_eq!(manager.connected_count(), 2);
+
+        let mut metadata = manager.connected_clients_metadata().collect::<Vec<_>>();
+        metadata.sort_by_key(|(client_id, _)| client_id.to_bits());
+        assert_eq!(
+            metadata
+                .iter()
+                .map(|(client_id, _)| *client_id)
+                .collect::<Vec<_>>(),
+            vec![
+                ClientId::Netcode(TEST_CLIENT_ID_1),
+                ClientId::Netcode(TEST_CLIENT_ID_2)
+            ]
+        );
+        for (client_id, metadata) in metadata {
+            assert_eq!(metadata.entity, manager.client_entity(client_id).unwrap());
+            // packets were received from the client since it connected
+            assert!(metadata.connected_tick < metadata.last_seen_tick);
+            assert!(metadata.last_seen_tick <= server_tick);
+        }
+
+        // the count is updated when a client disconnects
+        let _ = stepper.client_app_2.world_mut().disconnect_client();
+        for _ in 0..10 {
+            stepper.frame_step();
+        }
+        assert_eq!(
+            stepper
+                .server_app
+                .world()
+                .resource::<ServerConnectionManager>()
+                .connected_count(),
+            1
+        );
+    }
+
+    /// Client lookups return a specific error for unknown clients, for clients that
+    /// disconnected this frame, and when the server is not started
+    #[test]
+    fn test_client_entity_lookup_errors() {
+        let mut stepper = BevyStepper::default();
+        let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+        let unknown_id = ClientId::Netcode(TEST_CLIENT_ID + 1);
+        let client_entity = |stepper: &BevyStepper, client_id| {
+            stepper
+                .server_app
+                .world()
+                .resource::<ServerConnectionManager>()
+                .client_entity(client_id)
+        };
+
+        assert!(client_entity(&stepper, client_id).is_ok());
+        assert_eq!(
+            client_entity(&stepper, unknown_id),
+            Err(ClientLookupError::UnknownClient(unknown_id))
+        );
+
+        // the client is reported as disconnected until the end of the frame
+        stepper.server_app.world_mut().disconnect(client_id);
+        assert_eq!(
+            client_entity(&stepper, client_id),
+            Err(ClientLookupError::Disconnected(client_id))
+        );
+        stepper.frame_step();
+        assert_eq!(
+            client_entity(&stepper, client_id),
+            Err(ClientLookupError::UnknownClient(client_id))
+        );
+
+        stepper.server_app.world_mut().stop_server();
+        stepper.frame_step();
+        assert_eq!(
+            client_entity(&stepper, client_id),
+            Err(ClientLookupError::ServerNotStarted)
+        );
+    }
 
     /// Test that when the server stops:
     /// - Controlled entities are removed
@@ -568,4 +814,206 @@ mod tests {
             .get_entity(client_entity)
             .is_err());
     }
+
+    #[derive(Resource, Default)]
+    struct ConnectionRequests(Vec<ClientId>);
+
+    fn reject_connection_requests(
+        mut commands: Commands,
+        mut events: EventReader<server::ConnectionRequestEvent>,
+        mut requests: ResMut<ConnectionRequests>,
+    ) {
+        for event in events.read() {
+            requests.0.push(event.client_id);
+            commands.reject_connection(event.client_id, DeniedReason::Custom("banned".into()));
+        }
+    }
+
+    #[derive(Resource, Default)]
+    struct ClientDeniedReasons(Vec<Option<DeniedReason>>);
+
+    fn record_client_disconnects(
+        mut events: EventReader<client::DisconnectEvent>,
+        mut reasons: ResMut<ClientDeniedReasons>,
+    ) {
+        reasons
+            .0
+            .extend(events.read().map(|event| match &event.reason {
+                Some(ConnectionError::Denied(reason)) => Some(reason.clone()),
+                _ => None,
+            }));
+    }
+
+    fn assert_denied(stepper: &BevyStepper, expected: DeniedReason) {
+        let client = ClientId::Netcode(TEST_CLIENT_ID);
+        // the client entity was never spawned on the server
+        assert!(stepper
+            .server_app
+            .world()
+            .resource::<ServerConnectionManager>()
+            .connection(client)
+            .is_err());
+        assert_eq!(
+            stepper
+                .client_app
+                .world()
+                .resource::<State<client::NetworkingState>>()
+                .get(),
+            &client::NetworkingState::Disconnected
+        );
+        // the client received the reason why the connection was denied
+        let reasons = &stepper
+            .client_app
+            .world()
+            .resource::<ClientDeniedReasons>()
+            .0;
+        assert_eq!(reasons, &vec![Some(expected)]);
+    }
+
+    /// Enable the [`server::ConnectionRequestEvent`]s on every netcode server
+    fn enable_connection_requests(stepper: &mut BevyStepper, default: ConnectionRequestDefault) {
+        for net_config in stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<server::ServerConfig>()
+            .net
+            .iter_mut()
+        {
+            #[allow(irrefutable_let_patterns)]
+            let server::NetConfig::Netcode { config, .. } = net_config
+            else {
+                unreachable!()
+            };
+            config.emit_connection_requests = true;
+            config.connection_request_default = default.clone();
+        }
+    }
+
+    /// Test that the server can reject a connection request:
+    /// - the client entity is not spawned
+    /// - the client receives the reason of the rejection
+    #[test]
+    fn test_reject_connection_request() {
+        let mut stepper = BevyStepper::default_no_init();
+        enable_connection_requests(&mut stepper, ConnectionRequestDefault::Accept);
+        stepper.server_app.init_resource::<ConnectionRequests>();
+        stepper
+            .server_app
+            .add_systems(Update, reject_connection_requests);
+        stepper.client_app.init_resource::<ClientDeniedReasons>();
+        stepper
+            .client_app
+            .add_systems(Update, record_client_disconnects);
+
+        let _ = stepper.server_app.world_mut().start_server();
+        let _ = stepper.client_app.world_mut().connect_client();
+        for _ in 0..20 {
+            stepper.frame_step();
+        }
+
+        assert_eq!(
+            stepper
+                .server_app
+                .world()
+                .resource::<ConnectionRequests>()
+                .0,
+            vec![ClientId::Netcode(TEST_CLIENT_ID)]
+        );
+        assert_denied(&stepper, DeniedReason::Custom("banned".into()));
+    }
+
+    /// Test that connection requests that no system answered use the default from the config
+    #[test]
+    fn test_connection_request_default() {
+        let mut stepper = BevyStepper::default_no_init();
+        enable_connection_requests(
+            &mut stepper,
+            ConnectionRequestDefault::Reject(DeniedReason::ServerFull),
+        );
+        stepper.client_app.init_resource::<ClientDeniedReasons>();
+        stepper
+            .client_app
+            .add_systems(Update, record_client_disconnects);
+
+        let _ = stepper.server_app.world_mut().start_server();
+        let _ = stepper.client_app.world_mut().connect_client();
+        for _ in 0..20 {
+            stepper.frame_step();
+        }
+
+        assert_denied(&stepper, DeniedReason::ServerFull);
+    }
+
+    #[derive(Resource, Default)]
+    struct Disconnected(Vec<ClientId>);
+
+    fn record_disconnects(
+        mut events: EventReader<server::DisconnectEvent>,
+        mut disconnected: ResMut<Disconnected>,
+    ) {
+        disconnected
+            .0
+            .extend(events.read().map(|event| event.client_id));
+    }
+
+    /// Test that the server can disconnect a specific client:
+    /// - a DisconnectEvent is emitted
+    /// - the client's controlled entities are despawned
+    /// - disconnecting a client that is not connected returns an error
+    #[test]
+    fn test_server_disconnect_client() {
+        let mut stepper = BevyStepper::default();
+        stepper.server_app.init_resource::<Disconnected>();
+        stepper.server_app.add_systems(Update, record_disconnects);
+
+        let client = ClientId::Netcode(TEST_CLIENT_ID);
+        let server_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn(server::Replicate {
+                controlled_by: ControlledBy {
+                    target: NetworkTarget::Single(client),
+                    ..default()
+                },
+                ..default()
+            })
+            .id();
+        stepper.frame_step();
+        stepper.frame_step();
+
+        stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<ServerConnectionManager>()
+            .disconnect(client)
+            .unwrap();
+        stepper.frame_step();
+        stepper.frame_step();
+
+        assert_eq!(
+            stepper.server_app.world().resource::<Disconnected>().0,
+            vec![client]
+        );
+        assert!(stepper
+            .server_app
+            .world()
+            .get_entity(server_entity)
+            .is_err());
+        assert!(!matches!(
+            stepper
+                .client_app
+                .world()
+                .resource::<ClientConnection>()
+                .state(),
+            ConnectionState::Connected
+        ));
+
+        // the client is not connected anymore
+        assert!(stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<ServerConnectionManager>()
+            .disconnect(client)
+            .is_err());
+    }
 }
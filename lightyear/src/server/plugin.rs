@@ -13,6 +13,7 @@ use bevy::app::PluginGroupBuilder;
 use bevy::prelude::*;
 
 use crate::server::events::ServerEventsPlugin;
+use crate::server::lag_compensation::LagCompensationPlugin;
 use crate::server::message::ServerMessagePlugin;
 use crate::server::networking::ServerNetworkingPlugin;
 use crate::server::relevance::immediate::NetworkRelevancePlugin;
@@ -66,6 +67,7 @@ impl PluginGroup for ServerPlugins {
             .add(NetworkRelevancePlugin)
             .add(RoomPlugin)
             .add(ClientsMetadataPlugin)
+            .add(LagCompensationPlugin)
             .add(ServerReplicationReceivePlugin { tick_interval })
             .add(ServerReplicationSendPlugin { tick_interval })
     }
@@ -0,0 +1,247 @@
+//! Relay the entities of an upstream server to the clients of this server
+//!
+//! In a relay/proxy topology, a server forwards the entities of another (upstream) server to its own clients.
+//! The relay app runs both the [`ClientPlugins`](crate::prelude::client::ClientPlugins), connected to the
+//! upstream server, and the [`ServerPlugins`](crate::prelude::server::ServerPlugins), to which its own clients connect.
+//!
+//! With the [`RelayPlugin`]:
+//! - the upstream entities are received and interpolated by the client plugins, like on any client
+//! - the [`Interpolated`] entities are then replicated to the clients of the relay, which has authority over them.
+//!
+//! The clients of the relay therefore receive the smoothed state of the upstream entities.
+use bevy::prelude::*;
+
+use crate::client::interpolation::Interpolated;
+use crate::prelude::client::InterpolationSet;
+use crate::prelude::server::Replicate;
+pub use crate::shared::identity::RelayState;
+
+/// Plugin that re-replicates the entities interpolated from an upstream server to the clients of this server
+///
+/// Add this plugin after the [`ClientPlugins`](crate::prelude::client::ClientPlugins) and the
+/// [`ServerPlugins`](crate::prelude::server::ServerPlugins).
+#[derive(Default)]
+pub struct RelayPlugin {
+    /// How the relayed entities are replicated to the clients of this server
+    pub replicate: Replicate,
+}
+
+impl RelayPlugin {
+    pub fn new(replicate: Replicate) -> Self {
+        Self { replicate }
+    }
+}
+
+/// Settings used to replicate the relayed entities
+#[derive(Resource, Clone)]
+struct RelayReplicate(Replicate);
+
+impl Plugin for RelayPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_state(RelayState);
+        app.insert_resource(RelayReplicate(self.replicate.clone()));
+        app.add_systems(
+            Update,
+            relay_interpolated_entities.after(InterpolationSet::All),
+        );
+    }
+}
+
+/// Replicate the newly interpolated entities to the clients of this server
+fn relay_interpolated_entities(
+    mut commands: Commands,
+    replicate: Res<RelayReplicate>,
+    query: Query<Entity, Added<Interpolated>>,
+) {
+    for entity in query.iter() {
+        trace!(?entity, "relaying interpolated entity");
+        commands.entity(entity).insert(replicate.0.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::utils::Duration;
+
+    use super::*;
+    use crate::prelude::client::ClientCommandsExt;
+    use crate::prelude::client::InterpolationConfig;
+    use crate::prelude::server::ServerCommandsExt;
+    use crate::prelude::server::{Replicate, SyncTarget};
+    use crate::prelude::{
+        client, server, AppIdentityExt, NetworkIdentityState, NetworkTarget, SharedConfig,
+        TickConfig,
+    };
+    use crate::tests::protocol::ComponentSyncModeFull;
+    use crate::tests::relay_stepper::RelayStepper;
+
+    fn relay_stepper() -> RelayStepper {
+        let tick_duration = Duration::from_millis(10);
+        let shared_config = SharedConfig {
+            tick: TickConfig::new(tick_duration),
+            ..default()
+        };
+        let mut stepper = RelayStepper::new(
+            shared_config,
+            client::ClientConfig::default(),
+            tick_duration,
+        );
+        stepper.build();
+        stepper
+    }
+
+    /// A relay is both a client and a server, but not a host-server
+    #[test]
+    fn test_relay_identity() {
+        let mut stepper = relay_stepper();
+        stepper.init();
+        let world = stepper.relay_app.world();
+        assert!(world.is_client());
+        assert!(world.is_server());
+        assert!(!world.is_host_server());
+
+        // stopping the server: the relay is still a relay while the server is stopping
+        assert_eq!(
+            NetworkIdentityState::compute((
+                Some(client::NetworkingState::Connected),
+                Some(server::NetworkingState::Stopping),
+                Some(RelayState),
+            )),
+            Some(NetworkIdentityState::Relay)
+        );
+        stepper.relay_app.world_mut().stop_server();
+        stepper.frame_step();
+        stepper.frame_step();
+        assert_eq!(
+            stepper
+                .relay_app
+                .world()
+                .resource::<State<NetworkIdentityState>>()
+                .get(),
+            &NetworkIdentityState::Client
+        );
+    }
+
+    /// Disconnecting from the upstream server: the relay is still a relay while the client is disconnecting
+    #[test]
+    fn test_relay_identity_client_disconnect() {
+        let mut stepper = relay_stepper();
+        stepper.init();
+        assert_eq!(
+            NetworkIdentityState::compute((
+                Some(client::NetworkingState::Disconnecting),
+                Some(server::NetworkingState::Started),
+                Some(RelayState),
+            )),
+            Some(NetworkIdentityState::Relay)
+        );
+        stepper.relay_app.world_mut().disconnect_client();
+        stepper.frame_step();
+        stepper.frame_step();
+        assert_eq!(
+            stepper
+                .relay_app
+                .world()
+                .resource::<State<NetworkIdentityState>>()
+                .get(),
+            &NetworkIdentityState::Server
+        );
+    }
+
+    /// The relay can connect to the upstream server after its own server has started
+    /// (it must not be mistaken for a host-server)
+    #[test]
+    fn test_relay_connect_after_server_started() {
+        let mut stepper = relay_stepper();
+        stepper.source_app.world_mut().start_server();
+        stepper.relay_app.world_mut().start_server();
+        stepper.frame_step();
+        assert_eq!(
+            stepper
+                .relay_app
+                .world()
+                .resource::<State<NetworkIdentityState>>()
+                .get(),
+            &NetworkIdentityState::Server
+        );
+        stepper.relay_app.world_mut().connect_client();
+        for _ in 0..20 {
+            stepper.frame_step();
+        }
+        assert_eq!(
+            stepper
+                .relay_app
+                .world()
+                .resource::<State<NetworkIdentityState>>()
+                .get(),
+            &NetworkIdentityState::Relay
+        );
+    }
+
+    /// The source server replicates a moving entity to the relay, which interpolates it and replicates it
+    /// to its own client: the client receives the smoothed state of the entity
+    #[test]
+    fn test_relay_interpolated_entities() {
+        let tick_duration = Duration::from_millis(10);
+        let shared_config = SharedConfig {
+            tick: TickConfig::new(tick_duration),
+            ..default()
+        };
+        let relay_client_config = client::ClientConfig {
+            interpolation: InterpolationConfig::default()
+                .with_min_delay(Duration::from_millis(50))
+                .with_send_interval_ratio(0.0),
+            ..default()
+        };
+        let mut stepper = RelayStepper::new(shared_config, relay_client_config, tick_duration);
+        stepper.build();
+        stepper.init();
+        assert_eq!(
+            stepper
+                .relay_app
+                .world()
+                .resource::<State<NetworkIdentityState>>()
+                .get(),
+            &NetworkIdentityState::Relay
+        );
+
+        let source_entity = stepper
+            .source_app
+            .world_mut()
+            .spawn((
+                Replicate {
+                    sync: SyncTarget {
+                        interpolation: NetworkTarget::All,
+                        ..default()
+                    },
+                    ..default()
+                },
+                ComponentSyncModeFull(0.0),
+            ))
+            .id();
+
+        // the source server sends an update every 4 ticks
+        let mut values = vec![];
+        for i in 0..60 {
+            if i % 4 == 0 {
+                stepper
+                    .source_app
+                    .world_mut()
+                    .get_mut::<ComponentSyncModeFull>(source_entity)
+                    .unwrap()
+                    .0 += 4.0;
+            }
+            stepper.frame_step();
+            let client_world = stepper.client_app.world_mut();
+            let mut query = client_world.query::<&ComponentSyncModeFull>();
+            if let Ok(value) = query.get_single(client_world) {
+                values.push(value.0);
+            }
+        }
+
+        // the relay interpolated the updates of the source server
+        assert!(!values.is_empty());
+        assert!(values.windows(2).all(|w| w[0] <= w[1]));
+        assert!(values.iter().any(|v| v % 4.0 != 0.0));
+    }
+}
@@ -8,7 +8,7 @@ use crate::client::prediction::Predicted;
 use crate::connection::client::NetClient;
 use crate::prelude::client::ClientConnection;
 use crate::prelude::{server::is_started, PrePredicted};
-use crate::server::config::ServerConfig;
+use crate::server::config::{NetworkIdConfig, ServerConfig};
 use crate::server::connection::ConnectionManager;
 use crate::server::prediction::compute_hash;
 use crate::shared::replication::plugin::receive::ReplicationReceivePlugin;
@@ -19,12 +19,29 @@ use crate::shared::sets::{InternalMainSet, InternalReplicationSet, ServerMarker}
 pub enum ServerReplicationSet {
     // You can use this SystemSet to add Replicate components to entities received from clients (to rebroadcast them to other clients)
     ClientReplication,
+    /// Runs in `PostUpdate`, after the [`ControlledEntities`](crate::prelude::server::ControlledEntities) of each client
+    /// have been updated from the [`ControlledBy`](crate::prelude::server::ControlledBy) changes of the current frame,
+    /// and before the replication messages are buffered.
+    ///
+    /// You can use this SystemSet to read consistent control data, for example to decide which client should
+    /// get authority over an entity.
+    AfterControlledEntitiesUpdate,
 }
 
 pub type ReplicationSet = InternalReplicationSet<ServerMarker>;
 
 pub(crate) mod receive {
     use super::*;
+    use crate::prelude::server::AuthorityCommandExt;
+    use crate::prelude::ComponentRegistry;
+    use crate::prelude::TickManager;
+    use crate::server::message::ReceiveMessage;
+    use crate::shared::replication::authority::{
+        AuthorityPeer, AuthorityRequest, AuthorityRequestEvent, AuthorityTransferAck,
+        AuthorityTransferEvent, PendingAuthorityTransfer, ScheduledAuthorityTransfer,
+    };
+    use crate::shared::replication::ready::EntityReady;
+    use crate::shared::replication::subscription::ComponentSubscription;
 
     #[derive(Default)]
     pub struct ServerReplicationReceivePlugin {
@@ -38,19 +55,150 @@ pub(crate) mod receive {
                 .add_plugins(ReplicationReceivePlugin::<ConnectionManager>::new(
                     self.tick_interval,
                 ))
+                // EVENTS
+                .add_event::<AuthorityRequestEvent>()
                 // SETS
                 .configure_sets(
                     PreUpdate,
                     ServerReplicationSet::ClientReplication
                         .run_if(is_started)
                         .after(InternalMainSet::<ServerMarker>::ReceiveEvents),
+                )
+                // SYSTEMS
+                .add_systems(
+                    PreUpdate,
+                    (
+                        handle_entity_ready,
+                        handle_authority_transfer_ack,
+                        handle_authority_request,
+                        handle_component_subscription,
+                    )
+                        .after(InternalMainSet::<ServerMarker>::ReceiveEvents),
+                )
+                .add_systems(
+                    FixedPreUpdate,
+                    apply_scheduled_authority_transfers.run_if(is_started),
+                );
+        }
+    }
+
+    /// Apply the authority transfers scheduled with
+    /// [`transfer_authority_at`](crate::prelude::server::AuthorityCommandExt::transfer_authority_at)
+    /// once their tick is reached
+    fn apply_scheduled_authority_transfers(
+        mut commands: Commands,
+        tick_manager: Res<TickManager>,
+        query: Query<(Entity, &ScheduledAuthorityTransfer)>,
+    ) {
+        let tick = tick_manager.tick();
+        for (entity, scheduled) in query.iter() {
+            if scheduled.tick > tick {
+                continue;
+            }
+            trace!(?entity, ?tick, to = ?scheduled.to, "Applying scheduled authority transfer");
+            commands
+                .entity(entity)
+                .remove::<ScheduledAuthorityTransfer>()
+                .transfer_authority(scheduled.to);
+        }
+    }
+
+    /// Record the entities that clients have marked as ready
+    fn handle_entity_ready(
+        mut messages: ResMut<Events<ReceiveMessage<EntityReady>>>,
+        mut manager: ResMut<ConnectionManager>,
+    ) {
+        for message_event in messages.drain() {
+            let entity = message_event.message.entity;
+            if entity == Entity::PLACEHOLDER {
+                continue;
+            }
+            if let Ok(connection) = manager.connection_mut(message_event.from) {
+                trace!(client_id = ?message_event.from, ?entity, "Entity ready");
+                connection.ready_entities.insert(entity);
+            }
+        }
+    }
+
+    /// Update the component subscriptions of the clients
+    fn handle_component_subscription(
+        component_registry: Res<ComponentRegistry>,
+        mut messages: ResMut<Events<ReceiveMessage<ComponentSubscription>>>,
+        mut manager: ResMut<ConnectionManager>,
+    ) {
+        for message_event in messages.drain() {
+            let subscription = message_event.message;
+            if subscription.entity == Some(Entity::PLACEHOLDER) {
+                continue;
+            }
+            let Some(kind) = component_registry
+                .kind_map
+                .kind(subscription.net_id)
+                .copied()
+            else {
+                error!(net_id = ?subscription.net_id, "Received a subscription to an unknown component");
+                continue;
+            };
+            if let Ok(connection) = manager.connection_mut(message_event.from) {
+                trace!(client_id = ?message_event.from, ?subscription, "Component subscription");
+                connection.component_subscriptions.update(
+                    subscription.entity,
+                    kind,
+                    subscription.subscribed,
                 );
+            }
+        }
+    }
+
+    /// Complete the authority transfers that clients have acknowledged
+    fn handle_authority_transfer_ack(
+        mut commands: Commands,
+        mut messages: ResMut<Events<ReceiveMessage<AuthorityTransferAck>>>,
+        query: Query<&PendingAuthorityTransfer>,
+        mut events: EventWriter<AuthorityTransferEvent>,
+    ) {
+        for message_event in messages.drain() {
+            let entity = message_event.message.entity;
+            let Ok(pending) = query.get(entity) else {
+                continue;
+            };
+            // ignore acks for a previous transfer of the entity
+            if pending.to != AuthorityPeer::Client(message_event.from) {
+                continue;
+            }
+            trace!(client_id = ?message_event.from, ?entity, "Authority transfer acknowledged");
+            commands.entity(entity).remove::<PendingAuthorityTransfer>();
+            events.send(AuthorityTransferEvent {
+                entity,
+                from: pending.from,
+                to: pending.to,
+                acknowledged: true,
+            });
+        }
+    }
+
+    /// Emit an [`AuthorityRequestEvent`] for each authority request received from the clients
+    fn handle_authority_request(
+        mut messages: ResMut<Events<ReceiveMessage<AuthorityRequest>>>,
+        mut events: EventWriter<AuthorityRequestEvent>,
+    ) {
+        for message_event in messages.drain() {
+            let entity = message_event.message.entity;
+            if entity == Entity::PLACEHOLDER {
+                continue;
+            }
+            trace!(client_id = ?message_event.from, ?entity, "Authority requested");
+            events.send(AuthorityRequestEvent {
+                client_id: message_event.from,
+                entity,
+            });
         }
     }
 }
 
 pub(crate) mod send {
     use super::*;
+    use crate::channel::builder::InitialReplicationChannel;
     use crate::prelude::server::AuthorityCommandExt;
     use crate::prelude::{
         is_host_server, ClientId, ComponentRegistry, DisabledComponents, NetworkRelevanceMode,
@@ -58,6 +206,7 @@ pub(crate) mod send {
         TargetEntity, Tick, TickManager, TimeManager,
     };
     use crate::protocol::component::ComponentKind;
+    use crate::server::connection::ServerInstanceId;
     use crate::server::error::ServerError;
     use crate::server::prediction::handle_pre_predicted;
     use crate::server::relevance::immediate::{CachedNetworkRelevance, ClientRelevance};
@@ -67,9 +216,11 @@ pub(crate) mod send {
     };
     use crate::shared::replication::authority::{AuthorityPeer, HasAuthority};
     use crate::shared::replication::components::{
-        Cached, Controlled, InitialReplicated, Replicating, ReplicationGroupId,
-        ShouldBeInterpolated,
+        Cached, Controlled, DespawnReason, InitialReplicated, NetworkId, Replicating,
+        ReplicationGroupId, ReplicationPriority, ShouldBeInterpolated,
     };
+    use crate::shared::replication::dry_run::ReplicationDryRun;
+    use crate::shared::replication::initial::InitialReplication;
     use crate::shared::replication::network_target::NetworkTarget;
     use crate::shared::replication::ReplicationSend;
     use bevy::ecs::component::ComponentTicks;
@@ -83,19 +234,17 @@ pub(crate) mod send {
 
     impl Plugin for ServerReplicationSendPlugin {
         fn build(&self, app: &mut App) {
-            let send_interval = app
-                .world()
-                .resource::<ServerConfig>()
-                .replication
-                .send_interval;
+            let replication_config = app.world().resource::<ServerConfig>().replication;
 
             app
                 // REFLECTION
                 .register_type::<Replicate>()
+                .register_type::<ServerInstanceId>()
                 // PLUGIN
                 .add_plugins(ReplicationSendPlugin::<ConnectionManager>::new(
                     self.tick_interval,
-                    send_interval,
+                    replication_config.send_interval,
+                    replication_config.replication_interval,
                 ))
                 // SYSTEM SETS
                 .configure_sets(
@@ -124,7 +273,9 @@ pub(crate) mod send {
                         .in_set(InternalReplicationSet::<ServerMarker>::BufferEntityUpdates)
                         .in_set(InternalReplicationSet::<ServerMarker>::BufferComponentUpdates),
                     (
-                        handle_replication_target_update,
+                        handle_target_update::<ReplicationTarget>,
+                        handle_target_update::<SyncTarget>,
+                        handle_replication_priority_update.before(buffer_replication_messages),
                         buffer_replication_messages,
                     )
                         .in_set(InternalReplicationSet::<ServerMarker>::AfterBuffer),
@@ -141,6 +292,8 @@ pub(crate) mod send {
             app.add_observer(replicate_entity_local_despawn);
             app.add_observer(add_has_authority_component);
             app.add_observer(handle_pre_predicted);
+            app.init_resource::<NetworkIdAllocator>();
+            app.add_observer(allocate_network_id);
         }
     }
 
@@ -167,7 +320,10 @@ pub(crate) mod send {
     /// Component storing metadata about which clients have control over the entity
     ///
     /// This is only used for server to client replication.
-    #[derive(Component, Clone, Debug, Default, PartialEq, Reflect)]
+    ///
+    /// By default, the entity is not controlled by any client ([`NetworkTarget::None`]), so that
+    /// building a `ControlledBy { lifetime, ..default() }` never grants control to every client.
+    #[derive(Component, Clone, Debug, PartialEq, Reflect)]
     #[reflect(Component)]
     pub struct ControlledBy {
         /// Which client(s) control this entity?
@@ -176,11 +332,30 @@ pub(crate) mod send {
         pub lifetime: Lifetime,
     }
 
+    impl Default for ControlledBy {
+        fn default() -> Self {
+            Self {
+                target: NetworkTarget::None,
+                lifetime: Lifetime::SessionBased,
+            }
+        }
+    }
+
     impl ControlledBy {
         /// Returns true if the entity is controlled by the specified client
         pub fn targets(&self, client_id: &ClientId) -> bool {
             self.target.targets(client_id)
         }
+
+        /// Iterate through the currently connected clients that control the entity
+        pub fn resolve<'a>(
+            &'a self,
+            sender: &'a ConnectionManager,
+        ) -> impl Iterator<Item = ClientId> + 'a {
+            sender
+                .connected_targets(&self.target)
+                .map(|connection| connection.client_id)
+        }
     }
 
     #[derive(Clone, Copy, Debug, Default, PartialEq, Reflect)]
@@ -264,13 +439,69 @@ pub(crate) mod send {
         pub marker: Replicating,
     }
 
+    /// Builder methods to avoid having to construct the nested [`Replicate`] components by hand.
+    ///
+    /// The resulting [`Replicate`] is identical to one built field-by-field, so the builder methods
+    /// can be mixed with the struct-update syntax:
+    ///
+    /// ```rust
+    /// use bevy::prelude::*;
+    /// use lightyear::prelude::*;
+    /// use lightyear::prelude::server::*;
+    ///
+    /// let client_id = ClientId::Netcode(0);
+    /// let replicate = Replicate {
+    ///     group: ReplicationGroup::new_id(1),
+    ///     ..default()
+    /// }
+    /// .predicted_by(client_id)
+    /// .interpolated_by_others(client_id)
+    /// .controlled_by(client_id);
+    /// ```
+    impl Replicate {
+        /// Replicate the entity to the clients in the given [`NetworkTarget`]
+        pub fn replicate_to(mut self, target: NetworkTarget) -> Self {
+            self.target.target = target;
+            self
+        }
+
+        /// The entity will be predicted by the given client
+        pub fn predicted_by(mut self, client_id: ClientId) -> Self {
+            self.sync.prediction = NetworkTarget::Single(client_id);
+            self
+        }
+
+        /// The entity will be interpolated by every client except the given client
+        pub fn interpolated_by_others(mut self, client_id: ClientId) -> Self {
+            self.sync.interpolation = NetworkTarget::AllExceptSingle(client_id);
+            self
+        }
+
+        /// The entity will be controlled by the given client
+        pub fn controlled_by(mut self, client_id: ClientId) -> Self {
+            self.controlled_by.target = NetworkTarget::Single(client_id);
+            self
+        }
+
+        /// Set what happens to the entity when the controlling client disconnects
+        pub fn with_lifetime(mut self, lifetime: Lifetime) -> Self {
+            self.controlled_by.lifetime = lifetime;
+            self
+        }
+    }
+
     /// Buffer the replication messages into channels
     fn buffer_replication_messages(
         change_tick: SystemChangeTick,
         mut connection_manager: ResMut<ConnectionManager>,
         tick_manager: Res<TickManager>,
         time_manager: Res<TimeManager>,
+        dry_run: Option<ResMut<ReplicationDryRun>>,
     ) {
+        connection_manager
+            .connections
+            .values_mut()
+            .for_each(|c| c.replication_sender.dry_run = dry_run.is_some());
         connection_manager
             .buffer_replication_messages(
                 tick_manager.tick(),
@@ -280,11 +511,48 @@ pub(crate) mod send {
             .unwrap_or_else(|e| {
                 error!("Error preparing replicate send: {}", e);
             });
+        if let Some(mut dry_run) = dry_run {
+            dry_run.records.clear();
+            for (client_id, connection) in connection_manager.connections.iter_mut() {
+                let records = std::mem::take(&mut connection.replication_sender.dry_run_records);
+                debug!(
+                    ?client_id,
+                    num_messages = records.len(),
+                    num_bytes = records.iter().map(|r| r.num_bytes).sum::<usize>(),
+                    "Dry-run replication send"
+                );
+                dry_run.records.insert(*client_id, records);
+            }
+        }
         // TODO: how to handle this for replication groups that update less frequently?
         //  only component updates should update less frequently, but entity spawns/removals
         //  should be sent with the same frequency!
+        // notify the newly connected clients of the tick of their initial replication snapshot
+        let tick = tick_manager.tick();
+        for client_id in connection_manager.new_clients.clone() {
+            let Ok(connection) = connection_manager.connection_mut(client_id) else {
+                continue;
+            };
+            // clients whose replication was resumed already received their initial snapshot
+            if connection.initial_replication_tick.is_some() {
+                continue;
+            }
+            connection.initial_replication_tick = Some(tick);
+            let _ = connection_manager
+                .send_message::<InitialReplicationChannel, _>(
+                    client_id,
+                    &InitialReplication { tick },
+                )
+                .inspect_err(|e| {
+                    error!("Error sending the initial replication tick: {}", e);
+                });
+        }
         // clear the list of newly connected clients
         connection_manager.new_clients.clear();
+        connection_manager
+            .connections
+            .values_mut()
+            .for_each(|c| c.component_subscriptions.clear_resubscribed());
     }
 
     /// In HostServer mode, we will add the Predicted/Interpolated components to the server entities
@@ -356,20 +624,41 @@ pub(crate) mod send {
         pub(crate) replication_clients_cache: Vec<ClientId>,
     }
 
-    /// Keep a cached version of the [`ReplicationTarget`] component so that when it gets updated
-    /// we can compute a diff with the previous value.
+    /// Update the base priority of the replication group of entities whose [`ReplicationPriority`] changed.
+    ///
+    /// (the priority is also set when the entity is spawned on the remote, in the `replicate` system)
+    pub(crate) fn handle_replication_priority_update(
+        mut connection_manager: ResMut<ConnectionManager>,
+        query: Query<
+            (Entity, &ReplicationPriority, Option<&ReplicationGroup>),
+            Changed<ReplicationPriority>,
+        >,
+    ) {
+        for (entity, priority, group) in query.iter() {
+            let group_id =
+                group.map_or(ReplicationGroupId::default(), |g| g.group_id(Some(entity)));
+            connection_manager
+                .connections
+                .values_mut()
+                .for_each(|connection| {
+                    if let Some(channel) = connection
+                        .replication_sender
+                        .group_channels
+                        .get_mut(&group_id)
+                    {
+                        channel.base_priority = priority.0;
+                    }
+                });
+        }
+    }
+
+    /// Keep a cached version of the [`ReplicationTarget`] and [`SyncTarget`] components so that when
+    /// they get updated we can compute a diff with the previous value.
     ///
     /// This needs to run after we compute the diff, so after the `replicate` system runs
-    pub(crate) fn handle_replication_target_update(
+    pub(crate) fn handle_target_update<T: Component + Clone>(
         mut commands: Commands,
-        mut query: Query<
-            (
-                Entity,
-                &ReplicationTarget,
-                Option<&mut Cached<ReplicationTarget>>,
-            ),
-            Changed<ReplicationTarget>,
-        >,
+        mut query: Query<(Entity, &T, Option<&mut Cached<T>>), Changed<T>>,
     ) {
         for (entity, replication_target, cached) in query.iter_mut() {
             if let Some(mut cached) = cached {
@@ -409,6 +698,50 @@ pub(crate) mod send {
         }
     }
 
+    /// Allocates the [`NetworkId`] of the entities replicated by the server.
+    ///
+    /// It is reset from the [`NetworkIdConfig`] every time the server starts.
+    #[derive(Resource, Debug, Default)]
+    pub(crate) struct NetworkIdAllocator {
+        /// The next id to allocate, or None if the allocation is disabled
+        next: Option<u64>,
+    }
+
+    impl NetworkIdAllocator {
+        pub(crate) fn new(config: NetworkIdConfig) -> Self {
+            match config {
+                NetworkIdConfig::Disabled => Self::default(),
+                NetworkIdConfig::Deterministic { seed } => Self { next: Some(seed) },
+            }
+        }
+
+        fn allocate(&mut self) -> Option<NetworkId> {
+            let id = self.next?;
+            self.next = Some(id.wrapping_add(1));
+            Some(NetworkId(id))
+        }
+    }
+
+    /// Allocate a [`NetworkId`] to the entities that start being replicated by the server.
+    ///
+    /// Observers run in the order in which the entities start being replicated, so the ids are
+    /// deterministic for a given sequence of spawns.
+    fn allocate_network_id(
+        trigger: Trigger<OnAdd, Replicating>,
+        mut allocator: ResMut<NetworkIdAllocator>,
+        query: Query<(), (With<ReplicationTarget>, Without<NetworkId>)>,
+        mut commands: Commands,
+    ) {
+        let entity = trigger.entity();
+        if query.get(entity).is_err() {
+            return;
+        }
+        if let Some(network_id) = allocator.allocate() {
+            trace!(?entity, ?network_id, "Allocating NetworkId");
+            commands.entity(entity).insert(network_id);
+        }
+    }
+
     pub(crate) fn replicate(
         tick_manager: Res<TickManager>,
         component_registry: Res<ComponentRegistry>,
@@ -451,14 +784,23 @@ pub(crate) mod send {
                 let group_id = group.map_or(ReplicationGroupId::default(), |g| {
                     g.group_id(Some(entity.id()))
                 });
-                let priority = group.map_or(1.0, |g| g.priority());
+                let priority = entity_ref
+                    .get::<ReplicationPriority>()
+                    .map(|p| p.0)
+                    .unwrap_or_else(|| group.map_or(1.0, |g| g.priority()));
                 let cached_replication_target = entity_ref.get::<Cached<ReplicationTarget>>();
                 let visibility = entity_ref.get::<CachedNetworkRelevance>();
                 let sync_target = entity_ref.get::<SyncTarget>();
+                let cached_sync_target = entity_ref.get::<Cached<SyncTarget>>();
                 let target_entity = entity_ref.get::<TargetEntity>();
                 let controlled_by = entity_ref.get::<ControlledBy>();
                 let authority_peer = entity_ref.get::<AuthorityPeer>();
                 let initial_replicated = entity_ref.get::<InitialReplicated>();
+                let instance = entity_ref.get::<ServerInstanceId>();
+                // keep the debug replication state in sync with authority transfers
+                if let Some(state) = sender.replication_state.get_mut(&entity.id()) {
+                    state.authority = authority_peer.copied().unwrap_or_default();
+                }
 
                 let disabled_components = entity_ref.get::<DisabledComponents>();
 
@@ -482,10 +824,32 @@ pub(crate) mod send {
                     cached_replication_target,
                     authority_peer,
                     visibility,
+                    instance,
                     &mut sender,
                 );
 
-                // c. add all entity spawns
+                // c. update the prediction/interpolation of the clients that already have the entity
+                if sync_target.is_some() {
+                    let sync_target = shared::replication::utils::get_ref::<SyncTarget>(
+                        world,
+                        entity.id(),
+                        system_ticks.last_run(),
+                        system_ticks.this_run(),
+                    );
+                    let _ = replicate_sync_target_update(
+                        &component_registry,
+                        entity.id(),
+                        group_id,
+                        &sync_target,
+                        cached_sync_target,
+                        &mut sender,
+                    )
+                    .inspect_err(|e| {
+                        error!("error sending sync target update: {:?}", e);
+                    });
+                }
+
+                // d. add all entity spawns
                 replicate_entity_spawn(
                     &component_registry,
                     entity.id(),
@@ -499,6 +863,7 @@ pub(crate) mod send {
                     target_entity,
                     authority_peer,
                     visibility,
+                    instance,
                     &mut sender,
                     &system_ticks,
                 );
@@ -508,7 +873,7 @@ pub(crate) mod send {
                     continue;
                 }
 
-                // d. all components that were added or changed and that are not disabled
+                // e. all components that were added or changed and that are not disabled
                 for replicated_component in replicated_archetype
                     .components
                     .iter()
@@ -544,6 +909,7 @@ pub(crate) mod send {
                         group_id,
                         authority_peer,
                         visibility,
+                        instance,
                         replicated_component.delta_compression,
                         replicated_component.replicate_once,
                         override_target,
@@ -552,7 +918,7 @@ pub(crate) mod send {
                     );
                 }
 
-                // e. add all removed components
+                // f. add all removed components
             }
         }
 
@@ -577,6 +943,7 @@ pub(crate) mod send {
         target_entity: Option<&TargetEntity>,
         authority_peer: Option<&AuthorityPeer>,
         visibility: Option<&CachedNetworkRelevance>,
+        instance: Option<&ServerInstanceId>,
         connection_manager: &mut ConnectionManager,
         system_ticks: &SystemChangeTick,
     ) {
@@ -629,7 +996,7 @@ pub(crate) mod send {
                     if let Some(cached_target) = cached_replication_target {
                         // do not re-send a spawn message to the clients for which we already have
                         // replicated the entity
-                        target.exclude(&cached_target.value.target)
+                        target.difference(&cached_target.value.target)
                     }
                 }
 
@@ -647,15 +1014,17 @@ pub(crate) mod send {
         };
         // we don't send messages to the client that has authority
         if let Some(AuthorityPeer::Client(c)) = authority_peer {
-            target.exclude(&NetworkTarget::Single(*c));
+            target.difference(&NetworkTarget::Single(*c));
         }
+        // never replicate outside of the entity's instance
+        connection_manager.restrict_to_instance(&mut target, instance);
 
         // NOT NEEDED ANYMORE! WE DO SEND A SPAWN SO THAT THE CLIENT HAS A
         // - an action tick so that updates can now be received
         // - a GroupChannel with a Confirmed tick
         // // we don't send entity-spawn to the client who originally spawned the entity
         // if let Some(client_id) = initial_replicated.and_then(|r| r.from) {
-        //     target.exclude(&NetworkTarget::Single(client_id));
+        //     target.difference(&NetworkTarget::Single(client_id));
         // };
 
         if target.is_empty() {
@@ -727,6 +1096,22 @@ pub(crate) mod send {
         .inspect_err(|e: &ServerError| {
             error!("error sending entity spawn: {:?}", e);
         });
+        // send the previous states of the entity to the clients that interpolate it
+        if let Some(sync_target) = sync_target {
+            let mut interpolation_target = sync_target.interpolation.clone();
+            interpolation_target.intersection(&target);
+            let _ = connection_manager
+                .send_interpolation_snapshots(entity, &interpolation_target)
+                .inspect_err(|e| {
+                    error!("error sending interpolation snapshots: {:?}", e);
+                });
+        }
+        connection_manager.track_entity_spawn(
+            entity,
+            group_id,
+            authority_peer.copied().unwrap_or_default(),
+            &target,
+        );
     }
 
     /// Despawn entities when the entity gets despawned on local world
@@ -739,6 +1124,8 @@ pub(crate) mod send {
                 &ReplicationGroup,
                 &ReplicationTarget,
                 Option<&CachedNetworkRelevance>,
+                Option<&DespawnReason>,
+                Option<&ServerInstanceId>,
             ),
             With<Replicating>,
         >,
@@ -747,7 +1134,11 @@ pub(crate) mod send {
         mut sender: ResMut<ConnectionManager>,
     ) {
         let entity = trigger.entity();
-        if let Ok((replication_group, network_target, cached_relevance)) = query.get(entity) {
+        sender.replication_state.remove(&entity);
+        sender.interpolation_snapshots.remove(entity);
+        if let Ok((replication_group, network_target, cached_relevance, reason, instance)) =
+            query.get(entity)
+        {
             trace!(?entity, "Replicate entity despawn");
             // only send the despawn to clients who were in the target of the entity
             let mut target = network_target.clone().target;
@@ -758,9 +1149,15 @@ pub(crate) mod send {
                     network_relevance.clients_cache.keys().copied().collect(),
                 ))
             }
+            sender.restrict_to_instance(&mut target, instance);
             trace!(?entity, ?target, "send entity despawn");
             let _ = sender
-                .prepare_entity_despawn(entity, replication_group.group_id(Some(entity)), target)
+                .prepare_entity_despawn(
+                    entity,
+                    replication_group.group_id(Some(entity)),
+                    reason.copied().unwrap_or_default(),
+                    target,
+                )
                 // TODO: bubble up errors to user via ConnectionEvents?
                 .inspect_err(|e| {
                     error!("error sending entity despawn: {:?}", e);
@@ -778,6 +1175,7 @@ pub(crate) mod send {
         cached_replication_target: Option<&Cached<ReplicationTarget>>,
         authority_peer: Option<&AuthorityPeer>,
         visibility: Option<&CachedNetworkRelevance>,
+        instance: Option<&ServerInstanceId>,
         sender: &mut ConnectionManager,
     ) {
         // 1. send despawn for clients that lost visibility
@@ -807,24 +1205,104 @@ pub(crate) mod send {
             if let Some(cached_target) = cached_replication_target {
                 // get targets that we had before but not anymore
                 let mut new_despawn = cached_target.value.target.clone();
-                new_despawn.exclude(&replication_target.target);
+                new_despawn.difference(&replication_target.target);
                 target.union(&new_despawn);
             }
         }
         // 3. we don't send messages to the client that has authority
         if let Some(AuthorityPeer::Client(c)) = authority_peer {
-            target.exclude(&NetworkTarget::Single(*c));
+            target.difference(&NetworkTarget::Single(*c));
         }
+        // 4. never replicate outside of the entity's instance
+        sender.restrict_to_instance(&mut target, instance);
 
         if !target.is_empty() {
             let _ = sender
-                .prepare_entity_despawn(entity, group_id, target)
+                .prepare_entity_despawn(entity, group_id, DespawnReason::OutOfView, target)
                 .inspect_err(|e| {
                     error!("error sending entity despawn: {:?}", e);
                 });
         }
     }
 
+    /// When the [`SyncTarget`] of an entity changes, update the clients that already have the entity:
+    /// - clients that were added to the prediction or interpolation target receive the [`ShouldBePredicted`]
+    ///   or [`ShouldBeInterpolated`] marker, so that they spawn the Predicted or Interpolated entity
+    /// - clients that were removed from the interpolation target despawn their Interpolated entity
+    ///
+    /// Clients that the entity gets spawned for receive the markers along with the entity spawn.
+    pub(crate) fn replicate_sync_target_update(
+        component_registry: &ComponentRegistry,
+        entity: Entity,
+        group_id: ReplicationGroupId,
+        sync_target: &Ref<SyncTarget>,
+        cached_sync_target: Option<&Cached<SyncTarget>>,
+        sender: &mut ConnectionManager,
+    ) -> Result<(), ServerError> {
+        if !sync_target.is_changed() || sync_target.is_added() {
+            return Ok(());
+        }
+        let Some(cached_sync_target) = cached_sync_target else {
+            return Ok(());
+        };
+        // the clients for which the entity is already spawned
+        let Some(clients) = sender
+            .replication_state
+            .get(&entity)
+            .map(|state| state.clients.iter().copied().collect::<Vec<_>>())
+        else {
+            return Ok(());
+        };
+        for client_id in clients {
+            let was_predicted = cached_sync_target.value.prediction.targets(&client_id);
+            let was_interpolated = cached_sync_target.value.interpolation.targets(&client_id);
+            let is_predicted = sync_target.prediction.targets(&client_id);
+            let is_interpolated = sync_target.interpolation.targets(&client_id);
+            if was_predicted == is_predicted && was_interpolated == is_interpolated {
+                continue;
+            }
+            let Ok(connection) = sender.connection_mut(client_id) else {
+                continue;
+            };
+            let remote_entity = connection
+                .replication_receiver
+                .remote_entity_map
+                .to_remote(entity);
+            trace!(
+                ?entity,
+                ?client_id,
+                is_predicted,
+                is_interpolated,
+                "Update the sync target of the entity"
+            );
+            if is_predicted && !was_predicted {
+                connection.prepare_typed_component_insert(
+                    remote_entity,
+                    group_id,
+                    component_registry,
+                    &mut ShouldBePredicted,
+                )?;
+            }
+            // TODO: clients that are removed from the prediction target keep their Predicted entity
+            if is_interpolated && !was_interpolated {
+                connection.prepare_typed_component_insert(
+                    remote_entity,
+                    group_id,
+                    component_registry,
+                    &mut ShouldBeInterpolated,
+                )?;
+            }
+            if was_interpolated && !is_interpolated {
+                connection.replication_sender.prepare_component_remove(
+                    remote_entity,
+                    group_id,
+                    component_registry.net_id::<ShouldBeInterpolated>(),
+                );
+            }
+        }
+        Ok(())
+    }
+
     // TODO: if replication target changed and we are replicating to client 1,
     //  we need to also send component inserts to client 1!
     /// This system sends updates for all components that were added or changed
@@ -849,6 +1327,7 @@ pub(crate) mod send {
         group_id: ReplicationGroupId,
         authority_peer: Option<&AuthorityPeer>,
         visibility: Option<&CachedNetworkRelevance>,
+        instance: Option<&ServerInstanceId>,
         delta_compression: bool,
         replicate_once: bool,
         override_target: Option<&NetworkTarget>,
@@ -948,14 +1427,42 @@ pub(crate) mod send {
                 }
             };
 
-        // we don't send messages to the client that has authority
+        // handle the clients that unsubscribed from the component, or that subscribed to it again
+        // and need to receive its current value
+        let (unsubscribed, mut resubscribed) =
+            sender.component_subscriptions(entity, component_kind);
+        if !resubscribed.is_empty() {
+            resubscribed.intersection(target);
+            if let Some(visibility) = visibility {
+                resubscribed.intersection(&NetworkTarget::from(
+                    visibility
+                        .clients_cache
+                        .iter()
+                        .filter(|(_, relevance)| **relevance != ClientRelevance::Lost)
+                        .map(|(client_id, _)| *client_id)
+                        .collect::<Vec<_>>(),
+                ));
+            }
+            insert_target.union(&resubscribed);
+        }
+        insert_target.difference(&unsubscribed);
+        update_target.difference(&unsubscribed);
+
+        // we don't send messages to the client that has authority,
+        // unless the component is always controlled by the server
         if let Some(AuthorityPeer::Client(c)) = authority_peer {
-            insert_target.exclude(&NetworkTarget::Single(*c));
-            update_target.exclude(&NetworkTarget::Single(*c));
+            if !component_registry.is_server_authoritative(component_kind) {
+                insert_target.difference(&NetworkTarget::Single(*c));
+                update_target.difference(&NetworkTarget::Single(*c));
+            }
         }
 
+        // never replicate outside of the entity's instance
+        sender.restrict_to_instance(&mut insert_target, instance);
+        sender.restrict_to_instance(&mut update_target, instance);
+
         // do not send a component as both update and insert
-        update_target.exclude(&insert_target);
+        update_target.difference(&insert_target);
 
         if !insert_target.is_empty() || !update_target.is_empty() {
             if !insert_target.is_empty() {
@@ -1008,6 +1515,7 @@ pub(crate) mod send {
                 Option<&CachedNetworkRelevance>,
                 Option<&DisabledComponents>,
                 Option<&OverrideTargetComponent<C>>,
+                Option<&ServerInstanceId>,
             ),
             With<Replicating>,
         >,
@@ -1023,6 +1531,7 @@ pub(crate) mod send {
                 visibility,
                 disabled_components,
                 override_target,
+                instance,
             )) = query.get(entity)
             {
                 // do not replicate components that are disabled
@@ -1058,8 +1567,11 @@ pub(crate) mod send {
                     }
                 };
                 if let Some(AuthorityPeer::Client(c)) = authority_peer {
-                    target.exclude(&NetworkTarget::Single(*c));
+                    if !registry.is_server_authoritative(ComponentKind::of::<C>()) {
+                        target.difference(&NetworkTarget::Single(*c));
+                    }
                 }
+                sender.restrict_to_instance(&mut target, instance);
                 if target.is_empty() {
                     return;
                 }
@@ -1091,24 +1603,153 @@ pub(crate) mod send {
     mod tests {
         use super::*;
         use crate::client::events::ComponentUpdateEvent;
-        use crate::prelude::client::Confirmed;
-        use crate::prelude::server::{ControlledBy, NetConfig, RelevanceManager, Replicate};
+        use crate::client::networking::ClientCommandsExt;
+        use crate::connection::client::{ClientConnection, NetClient};
+        use crate::prelude::client::{
+            Confirmed, InterpolationConfig, PredictionConfig, SyncConfig,
+        };
+        use crate::prelude::server::{
+            ClientShardKey, ControlledBy, ControlledEntities, NetConfig, RelevanceManager,
+            Replicate, ServerCommandsExt, ServerInstanceId,
+        };
         use crate::prelude::{
             client, server, ChannelDirection, DeltaCompression, LinkConditionerConfig,
-            ReplicateOnceComponent, Replicated,
+            ReplicateOnceComponent, ReplicateResourceExt, Replicated, SharedConfig, TickConfig,
         };
+        use crate::protocol::component::ComponentNetId;
+        use crate::serialize::reader::Reader;
+        use crate::serialize::ToBytes;
         use crate::server::replication::send::SyncTarget;
-        use crate::shared::replication::components::{Controlled, ReplicationGroupId};
-        use crate::shared::replication::delta::DeltaComponentHistory;
+        use crate::shared::replication::components::{
+            Controlled, ReplicationGroupId, ReplicationInterval,
+        };
+        use crate::shared::replication::delta::{DeltaComponentHistory, DeltaMessage, DeltaType};
+        use crate::shared::replication::entity_map::ReceiveEntityMap;
         use crate::shared::replication::systems;
         use crate::tests::multi_stepper::{MultiBevyStepper, TEST_CLIENT_ID_1, TEST_CLIENT_ID_2};
         use crate::tests::protocol::*;
         use crate::tests::stepper::{BevyStepper, TEST_CLIENT_ID};
+        use crate::transport::PacketReceiver;
         use bevy::ecs::system::RunSystemOnce;
-        use bevy::prelude::{default, EventReader, Resource, Update};
+        use bevy::prelude::{default, EventReader, IVec2, Resource, Update};
         use bevy::utils::HashSet;
+        use std::num::NonZeroU32;
+
+        /// Run the same script of spawns, and return for each spawned entity the [`NetworkId`] allocated
+        /// by the server and the [`NetworkId`] received by the client
+        fn scripted_network_ids() -> Vec<(Option<NetworkId>, Option<NetworkId>)> {
+            let mut stepper = BevyStepper::default();
+            stepper.stop();
+            stepper
+                .server_app
+                .world_mut()
+                .resource_mut::<ServerConfig>()
+                .network_id = NetworkIdConfig::Deterministic { seed: 100 };
+            stepper.start();
+
+            let mut entities = vec![
+                stepper
+                    .server_app
+                    .world_mut()
+                    .spawn(Replicate::default())
+                    .id(),
+                // entities that are not replicated do not get an id
+                stepper
+                    .server_app
+                    .world_mut()
+                    .spawn(ComponentSyncModeFull(1.0))
+                    .id(),
+                stepper
+                    .server_app
+                    .world_mut()
+                    .spawn((Replicate::default(), ComponentSyncModeFull(1.0)))
+                    .id(),
+            ];
+            stepper.frame_step();
+            entities.push(
+                stepper
+                    .server_app
+                    .world_mut()
+                    .spawn(Replicate::default())
+                    .id(),
+            );
+            stepper.frame_step();
+            stepper.frame_step();
+
+            entities
+                .into_iter()
+                .map(|server_entity| {
+                    let server_id = stepper
+                        .server_app
+                        .world()
+                        .get::<NetworkId>(server_entity)
+                        .copied();
+                    let client_id = stepper
+                        .client_app
+                        .world()
+                        .resource::<client::ConnectionManager>()
+                        .replication_receiver
+                        .remote_entity_map
+                        .get_local(server_entity)
+                        .and_then(|client_entity| {
+                            stepper
+                                .client_app
+                                .world()
+                                .get::<NetworkId>(client_entity)
+                                .copied()
+                        });
+                    (server_id, client_id)
+                })
+                .collect()
+        }
+
+        /// Two identical runs allocate the same sequential NetworkIds, which are replicated to the client
+        #[test]
+        fn test_deterministic_network_id() {
+            let network_ids = scripted_network_ids();
+            assert_eq!(
+                network_ids,
+                vec![
+                    (Some(NetworkId(100)), Some(NetworkId(100))),
+                    (None, None),
+                    (Some(NetworkId(101)), Some(NetworkId(101))),
+                    (Some(NetworkId(102)), Some(NetworkId(102))),
+                ]
+            );
+            assert_eq!(scripted_network_ids(), network_ids);
+        }
 
-        // TODO: test entity spawn newly connected client
+        #[test]
+        fn test_replicate_builder() {
+            let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+            let replicate = Replicate {
+                group: ReplicationGroup::new_id(1),
+                ..default()
+            }
+            .replicate_to(NetworkTarget::AllExceptSingle(ClientId::Netcode(2)))
+            .predicted_by(client_id)
+            .interpolated_by_others(client_id)
+            .controlled_by(client_id)
+            .with_lifetime(Lifetime::Persistent);
+            assert_eq!(
+                replicate,
+                Replicate {
+                    target: ReplicationTarget {
+                        target: NetworkTarget::AllExceptSingle(ClientId::Netcode(2)),
+                    },
+                    sync: SyncTarget {
+                        prediction: NetworkTarget::Single(client_id),
+                        interpolation: NetworkTarget::AllExceptSingle(client_id),
+                    },
+                    controlled_by: ControlledBy {
+                        target: NetworkTarget::Single(client_id),
+                        lifetime: Lifetime::Persistent,
+                    },
+                    group: ReplicationGroup::new_id(1),
+                    ..default()
+                }
+            );
+        }
 
         #[test]
         fn test_entity_spawn() {
@@ -1350,21 +1991,159 @@ pub(crate) mod send {
             // TODO: check that client 1 did not receive another entity-spawn message
         }
 
+        /// Check that an entity is replicated to a client that connects after it was spawned,
+        /// and that updating the SyncTarget switches the client from interpolation to prediction
+        /// without respawning the entity
         #[test]
-        fn test_entity_despawn() {
-            let mut stepper = BevyStepper::default();
+        fn test_entity_spawn_newly_connected_client_sync_target_update() {
+            let tick_duration = Duration::from_millis(10);
+            let mut stepper = MultiBevyStepper::new(
+                SharedConfig {
+                    tick: TickConfig::new(tick_duration),
+                    ..default()
+                },
+                SyncConfig::default().speedup_factor(1.0),
+                PredictionConfig::default(),
+                InterpolationConfig::default(),
+                tick_duration,
+            );
+            stepper.build();
+            let _ = stepper.server_app.world_mut().start_server();
+            let _ = stepper.client_app_1.world_mut().connect_client();
+            for _ in 0..100 {
+                stepper.frame_step();
+            }
 
-            // spawn an entity on server
+            // the entity is predicted by client 1 and interpolated by the others
+            let client_id_1 = ClientId::Netcode(TEST_CLIENT_ID_1);
+            let client_id_2 = ClientId::Netcode(TEST_CLIENT_ID_2);
             let server_entity = stepper
                 .server_app
                 .world_mut()
-                .spawn(Replicate::default())
+                .spawn((
+                    Replicate {
+                        sync: SyncTarget {
+                            prediction: NetworkTarget::Single(client_id_1),
+                            interpolation: NetworkTarget::AllExceptSingle(client_id_1),
+                        },
+                        ..default()
+                    },
+                    ComponentSyncModeFull(1.0),
+                ))
                 .id();
             stepper.frame_step();
             stepper.frame_step();
 
-            // check that the entity was spawned
-            let client_entity = stepper
+            // client 2 connects after the entity was spawned
+            let _ = stepper.client_app_2.world_mut().connect_client();
+            for _ in 0..100 {
+                if stepper.is_synced() {
+                    break;
+                }
+                stepper.frame_step();
+            }
+            stepper.frame_step();
+            stepper.frame_step();
+            let confirmed_entity = stepper
+                .client_app_2
+                .world()
+                .resource::<client::ConnectionManager>()
+                .replication_receiver
+                .remote_entity_map
+                .get_local(server_entity)
+                .expect("entity was not replicated to the newly connected client");
+            let confirmed = stepper
+                .client_app_2
+                .world()
+                .get::<Confirmed>(confirmed_entity)
+                .unwrap();
+            assert!(confirmed.predicted.is_none());
+            let interpolated_entity = confirmed
+                .interpolated
+                .expect("entity is not interpolated by the newly connected client");
+
+            // client 2 now predicts the entity
+            stepper
+                .server_app
+                .world_mut()
+                .get_mut::<SyncTarget>(server_entity)
+                .unwrap()
+                .prediction = NetworkTarget::All;
+            stepper
+                .server_app
+                .world_mut()
+                .get_mut::<SyncTarget>(server_entity)
+                .unwrap()
+                .interpolation = NetworkTarget::None;
+            stepper.frame_step();
+            stepper.frame_step();
+
+            // the confirmed entity was not respawned
+            assert_eq!(
+                stepper
+                    .client_app_2
+                    .world()
+                    .resource::<client::ConnectionManager>()
+                    .replication_receiver
+                    .remote_entity_map
+                    .get_local(server_entity),
+                Some(confirmed_entity)
+            );
+            let confirmed = stepper
+                .client_app_2
+                .world()
+                .get::<Confirmed>(confirmed_entity)
+                .unwrap();
+            assert!(confirmed.interpolated.is_none());
+            assert!(stepper
+                .client_app_2
+                .world()
+                .get_entity(interpolated_entity)
+                .is_err());
+            let predicted_entity = confirmed
+                .predicted
+                .expect("entity is not predicted by the newly connected client");
+            assert_eq!(
+                stepper
+                    .client_app_2
+                    .world()
+                    .get::<ComponentSyncModeFull>(predicted_entity),
+                Some(&ComponentSyncModeFull(1.0))
+            );
+
+            // client 1 still predicts the entity
+            let confirmed_entity = stepper
+                .client_app_1
+                .world()
+                .resource::<client::ConnectionManager>()
+                .replication_receiver
+                .remote_entity_map
+                .get_local(server_entity)
+                .unwrap();
+            let confirmed = stepper
+                .client_app_1
+                .world()
+                .get::<Confirmed>(confirmed_entity)
+                .unwrap();
+            assert!(confirmed.predicted.is_some());
+            assert!(confirmed.interpolated.is_none());
+        }
+
+        #[test]
+        fn test_entity_despawn() {
+            let mut stepper = BevyStepper::default();
+
+            // spawn an entity on server
+            let server_entity = stepper
+                .server_app
+                .world_mut()
+                .spawn(Replicate::default())
+                .id();
+            stepper.frame_step();
+            stepper.frame_step();
+
+            // check that the entity was spawned
+            let client_entity = stepper
                 .client_app
                 .world()
                 .resource::<client::ConnectionManager>()
@@ -2137,6 +2916,134 @@ pub(crate) mod send {
             );
         }
 
+        /// With a `ReplicationInterval` of 3 ticks, the updates are sent right away when the entity
+        /// starts being replicated, and then every 3 ticks
+        #[test]
+        fn test_component_update_replication_interval() {
+            let mut stepper = BevyStepper::default();
+            // the component value is the tick at which the update is buffered
+            stepper.server_app.add_systems(
+                Update,
+                |tick_manager: Res<TickManager>,
+                 mut query: Query<&mut ComponentSyncModeFull, With<Replicating>>| {
+                    for mut component in query.iter_mut() {
+                        component.0 = tick_manager.tick().0 as f32;
+                    }
+                },
+            );
+
+            let server_entity = stepper
+                .server_app
+                .world_mut()
+                .spawn((
+                    Replicate::default(),
+                    ReplicationInterval(3),
+                    ComponentSyncModeFull(0.0),
+                ))
+                .id();
+            stepper.frame_step();
+            let spawn_tick = stepper.server_tick();
+            let mut received_ticks = vec![];
+            for _ in 0..8 {
+                stepper.frame_step();
+                let Some(client_entity) = stepper
+                    .client_app
+                    .world()
+                    .resource::<client::ConnectionManager>()
+                    .replication_receiver
+                    .remote_entity_map
+                    .get_local(server_entity)
+                else {
+                    continue;
+                };
+                let received = stepper
+                    .client_app
+                    .world()
+                    .get::<ComponentSyncModeFull>(client_entity)
+                    .unwrap()
+                    .0 as u16;
+                if received_ticks.last() != Some(&received) {
+                    received_ticks.push(received);
+                }
+            }
+            assert_eq!(
+                received_ticks,
+                vec![spawn_tick.0, spawn_tick.0 + 3, spawn_tick.0 + 6]
+            );
+        }
+
+        /// A component registered with `max_send_rate` is sent about once per second,
+        /// even if it changes every frame and the tick rate changes
+        #[test]
+        fn test_component_update_max_send_rate() {
+            let mut stepper = BevyStepper::default();
+            stepper
+                .server_app
+                .world_mut()
+                .resource_mut::<ComponentRegistry>()
+                .set_max_send_rate::<ComponentSyncModeFull>(Duration::from_secs(1));
+
+            let server_entity = stepper
+                .server_app
+                .world_mut()
+                .spawn((Replicate::default(), ComponentSyncModeFull(0.0)))
+                .id();
+            stepper.frame_step();
+            stepper.frame_step();
+            let client_entity = stepper
+                .client_app
+                .world()
+                .resource::<client::ConnectionManager>()
+                .replication_receiver
+                .remote_entity_map
+                .get_local(server_entity)
+                .expect("entity was not replicated to client");
+
+            let mut value = 0.0;
+            let mut previous = ComponentSyncModeFull(0.0);
+            for tick_duration in [Duration::from_millis(10), Duration::from_millis(40)] {
+                // change the tick rate of the server
+                stepper
+                    .server_app
+                    .world_mut()
+                    .resource_mut::<Time<Fixed>>()
+                    .set_timestep(tick_duration);
+                stepper
+                    .server_app
+                    .world_mut()
+                    .resource_mut::<TickManager>()
+                    .config
+                    .tick_duration = tick_duration;
+
+                // the component changes every frame for 3 seconds
+                let mut num_updates = 0;
+                for _ in 0..300 {
+                    value += 1.0;
+                    stepper
+                        .server_app
+                        .world_mut()
+                        .get_mut::<ComponentSyncModeFull>(server_entity)
+                        .unwrap()
+                        .0 = value;
+                    stepper.frame_step();
+                    let received = stepper
+                        .client_app
+                        .world()
+                        .get::<ComponentSyncModeFull>(client_entity)
+                        .unwrap()
+                        .clone();
+                    if received != previous {
+                        num_updates += 1;
+                        previous = received;
+                    }
+                }
+                assert!(
+                    (2..=4).contains(&num_updates),
+                    "expected about one update per second with a tick duration of {tick_duration:?}, got {num_updates} updates in 3 seconds"
+                );
+            }
+        }
+
         #[test]
         fn test_component_update_delta() {
             let mut stepper = BevyStepper::default();
@@ -2279,26 +3186,178 @@ pub(crate) mod send {
                 .is_none());
         }
 
-        /// One component is delta, the other is not
-        /// This fails to work if we don't have an ack tick specific to the delta component
+        /// Two clients receive the same update of a delta-compressed component in the same tick.
+        /// The client that has acked a baseline receives a diff from that baseline, while the client
+        /// that just started receiving the entity (and has no baseline) receives the full state.
         #[test]
-        fn test_component_update_delta_with_non_delta_component() {
-            let mut stepper = BevyStepper::default();
+        fn test_component_update_delta_per_client_baseline() {
+            let mut stepper = MultiBevyStepper::default();
+            let client_1 = ClientId::Netcode(TEST_CLIENT_ID_1);
+            let client_2 = ClientId::Netcode(TEST_CLIENT_ID_2);
+            let kind = ComponentKind::of::<ComponentDeltaCompression>();
 
-            // spawn an entity on server
+            // the entity is only replicated to client 1 at first
             let server_entity = stepper
                 .server_app
                 .world_mut()
                 .spawn((
-                    Replicate::default(),
-                    ComponentSyncModeFull(1.0),
+                    Replicate {
+                        target: ReplicationTarget {
+                            target: NetworkTarget::Single(client_1),
+                        },
+                        ..default()
+                    },
                     ComponentDeltaCompression(vec![1, 2]),
                     DeltaCompression::<ComponentDeltaCompression>::default(),
                 ))
                 .id();
             let group_id = ReplicationGroupId(server_entity.to_bits());
             stepper.frame_step();
-            let insert_tick = stepper.server_tick();
+            stepper.frame_step();
+            // send an update and wait until client 1 acks it, so that it has a baseline
+            stepper
+                .server_app
+                .world_mut()
+                .get_mut::<ComponentDeltaCompression>(server_entity)
+                .unwrap()
+                .0 = vec![1, 2, 3];
+            for _ in 0..4 {
+                // the server only processes acks when it receives a packet from the client
+                stepper
+                    .client_app_1
+                    .world_mut()
+                    .resource_mut::<client::ConnectionManager>()
+                    .send_message::<Channel1, StringMessage>(&StringMessage("ack".to_string()))
+                    .unwrap();
+                stepper.frame_step();
+            }
+            // client 2 joins the replication of the entity mid-stream
+            stepper
+                .server_app
+                .world_mut()
+                .entity_mut(server_entity)
+                .insert(ReplicationTarget {
+                    target: NetworkTarget::All,
+                });
+            stepper.frame_step();
+            stepper.frame_step();
+
+            let has_baseline = |stepper: &MultiBevyStepper, client_id: ClientId| {
+                stepper
+                    .server_app
+                    .world()
+                    .resource::<ConnectionManager>()
+                    .connection(client_id)
+                    .unwrap()
+                    .replication_sender
+                    .group_channels
+                    .get(&group_id)
+                    .is_some_and(|channel| {
+                        channel.delta_ack_ticks.contains_key(&(server_entity, kind))
+                    })
+            };
+            assert!(has_baseline(&stepper, client_1));
+            assert!(!has_baseline(&stepper, client_2));
+
+            // prepare the same update for both clients
+            stepper
+                .server_app
+                .world_mut()
+                .get_mut::<ComponentDeltaCompression>(server_entity)
+                .unwrap()
+                .0 = vec![1, 2, 3, 4];
+            let world = stepper.server_app.world_mut();
+            let tick = world.resource::<TickManager>().tick();
+            let component_change_tick = world
+                .entity(server_entity)
+                .get_change_ticks::<ComponentDeltaCompression>()
+                .unwrap()
+                .changed;
+            let system_current_tick = world.change_tick();
+            world.resource_scope(|world, mut manager: Mut<ConnectionManager>| {
+                let registry = world.resource::<ComponentRegistry>();
+                let component = world
+                    .get::<ComponentDeltaCompression>(server_entity)
+                    .unwrap();
+                manager
+                    .prepare_component_update(
+                        server_entity,
+                        kind,
+                        Ptr::from(component),
+                        registry,
+                        group_id,
+                        NetworkTarget::All,
+                        component_change_tick,
+                        system_current_tick,
+                        tick,
+                        true,
+                    )
+                    .unwrap();
+            });
+
+            let take_delta = |stepper: &mut MultiBevyStepper, client_id: ClientId| {
+                let world = stepper.server_app.world_mut();
+                let bytes = world
+                    .resource_mut::<ConnectionManager>()
+                    .connection_mut(client_id)
+                    .unwrap()
+                    .replication_sender
+                    .group_channels
+                    .get_mut(&group_id)
+                    .unwrap()
+                    .pending_updates
+                    .remove(&server_entity)
+                    .expect("no update was prepared for the client");
+                assert_eq!(bytes.len(), 1);
+                let mut reader = Reader::from(bytes[0].clone());
+                ComponentNetId::from_bytes(&mut reader).unwrap();
+                world
+                    .resource::<ComponentRegistry>()
+                    .raw_deserialize::<DeltaMessage<Vec<usize>>>(
+                        &mut reader,
+                        &mut ReceiveEntityMap::default(),
+                    )
+                    .unwrap()
+            };
+            let delta_1 = take_delta(&mut stepper, client_1);
+            let delta_2 = take_delta(&mut stepper, client_2);
+            assert!(matches!(delta_1.delta_type, DeltaType::Normal { .. }));
+            assert_eq!(delta_1.delta, vec![4]);
+            // the diff from the base value `[1]` contains the full state
+            assert_eq!(delta_2.delta_type, DeltaType::FromBase);
+            assert_eq!(delta_2.delta, vec![2, 3, 4]);
+        }
+
+        /// Drop all the packets that were sent to the client but not received yet
+        fn drop_client_packets(stepper: &mut BevyStepper) {
+            let mut connection = stepper
+                .client_app
+                .world_mut()
+                .resource_mut::<ClientConnection>();
+            let io = connection.io_mut().unwrap();
+            while let Ok(Some(_)) = io.recv() {}
+        }
+
+        /// The packets containing most of the updates of a delta-compressed component are lost.
+        /// The server only computes diffs from a value that the client acked (or from the base value),
+        /// and re-sends the lost updates, so the client reconstructs the exact final value
+        /// even though the diffs are not idempotent.
+        #[test]
+        fn test_component_update_delta_packet_loss() {
+            let mut stepper = BevyStepper::default();
+            let kind = ComponentKind::of::<ComponentDeltaCompressionPosition>();
+
+            let server_entity = stepper
+                .server_app
+                .world_mut()
+                .spawn((
+                    Replicate::default(),
+                    ComponentDeltaCompressionPosition(IVec2::ZERO),
+                    DeltaCompression::<ComponentDeltaCompressionPosition>::default(),
+                ))
+                .id();
+            let group_id = ReplicationGroupId(server_entity.to_bits());
+            stepper.frame_step();
             stepper.frame_step();
             let client_entity = stepper
                 .client_app
@@ -2308,38 +3367,179 @@ pub(crate) mod send {
                 .remote_entity_map
                 .get_local(server_entity)
                 .expect("entity was not replicated to client");
-            // check that the component was replicated
+
+            let mut position = IVec2::ZERO;
+            for i in 1..=20 {
+                position += IVec2::new(i, -2 * i);
+                stepper
+                    .server_app
+                    .world_mut()
+                    .entity_mut(server_entity)
+                    .get_mut::<ComponentDeltaCompressionPosition>()
+                    .unwrap()
+                    .0 = position;
+                stepper.frame_step();
+                // drop 2 out of every 3 updates, including the last one
+                if i % 3 != 0 {
+                    drop_client_packets(&mut stepper);
+                }
+            }
+            // let the server notice that the packets were lost and send the updates again
+            for _ in 0..20 {
+                stepper.frame_step();
+            }
+
             assert_eq!(
                 stepper
                     .client_app
                     .world()
                     .entity(client_entity)
-                    .get::<ComponentDeltaCompression>()
+                    .get::<ComponentDeltaCompressionPosition>()
                     .expect("component missing"),
-                &ComponentDeltaCompression(vec![1, 2])
+                &ComponentDeltaCompressionPosition(position)
             );
-            // check that the component value was stored in the delta manager cache
+            // the updates were sent as diffs from a value acked by the client
             assert!(stepper
                 .server_app
                 .world()
                 .resource::<ConnectionManager>()
-                .delta_manager
-                .data
-                .get_component_value(
-                    server_entity,
-                    insert_tick,
-                    ComponentKind::of::<ComponentDeltaCompression>(),
-                    group_id,
-                )
-                .is_some());
+                .connection(ClientId::Netcode(TEST_CLIENT_ID))
+                .unwrap()
+                .replication_sender
+                .group_channels
+                .get(&group_id)
+                .unwrap()
+                .delta_ack_ticks
+                .contains_key(&(server_entity, kind)));
+        }
 
-            // apply non-delta update
-            stepper
+        /// Component that derives `Diffable` with a field mask: the receiver applies the partial
+        /// update (only the fields that changed) onto its existing value
+        #[test]
+        fn test_component_update_delta_field_mask() {
+            let mut stepper = BevyStepper::default();
+
+            let server_entity = stepper
                 .server_app
                 .world_mut()
-                .entity_mut(server_entity)
-                .get_mut::<ComponentSyncModeFull>()
-                .unwrap()
+                .spawn((
+                    Replicate::default(),
+                    ComponentDeltaCompressionFieldMask {
+                        slots: vec![1, 2, 3],
+                        gold: 10,
+                    },
+                    DeltaCompression::<ComponentDeltaCompressionFieldMask>::default(),
+                ))
+                .id();
+            stepper.frame_step();
+            stepper.frame_step();
+            let client_entity = stepper
+                .client_app
+                .world()
+                .resource::<client::ConnectionManager>()
+                .replication_receiver
+                .remote_entity_map
+                .get_local(server_entity)
+                .expect("entity was not replicated to client");
+            // the client has never seen the component, so all the fields are received
+            assert_eq!(
+                stepper
+                    .client_app
+                    .world()
+                    .entity(client_entity)
+                    .get::<ComponentDeltaCompressionFieldMask>()
+                    .expect("component missing"),
+                &ComponentDeltaCompressionFieldMask {
+                    slots: vec![1, 2, 3],
+                    gold: 10,
+                }
+            );
+
+            // update a single field
+            stepper
+                .server_app
+                .world_mut()
+                .entity_mut(server_entity)
+                .get_mut::<ComponentDeltaCompressionFieldMask>()
+                .unwrap()
+                .gold = 20;
+            stepper.frame_step();
+            stepper.frame_step();
+            assert_eq!(
+                stepper
+                    .client_app
+                    .world()
+                    .entity(client_entity)
+                    .get::<ComponentDeltaCompressionFieldMask>()
+                    .expect("component missing"),
+                &ComponentDeltaCompressionFieldMask {
+                    slots: vec![1, 2, 3],
+                    gold: 20,
+                }
+            );
+        }
+
+        /// One component is delta, the other is not
+        /// This fails to work if we don't have an ack tick specific to the delta component
+        #[test]
+        fn test_component_update_delta_with_non_delta_component() {
+            let mut stepper = BevyStepper::default();
+
+            // spawn an entity on server
+            let server_entity = stepper
+                .server_app
+                .world_mut()
+                .spawn((
+                    Replicate::default(),
+                    ComponentSyncModeFull(1.0),
+                    ComponentDeltaCompression(vec![1, 2]),
+                    DeltaCompression::<ComponentDeltaCompression>::default(),
+                ))
+                .id();
+            let group_id = ReplicationGroupId(server_entity.to_bits());
+            stepper.frame_step();
+            let insert_tick = stepper.server_tick();
+            stepper.frame_step();
+            let client_entity = stepper
+                .client_app
+                .world()
+                .resource::<client::ConnectionManager>()
+                .replication_receiver
+                .remote_entity_map
+                .get_local(server_entity)
+                .expect("entity was not replicated to client");
+            // check that the component was replicated
+            assert_eq!(
+                stepper
+                    .client_app
+                    .world()
+                    .entity(client_entity)
+                    .get::<ComponentDeltaCompression>()
+                    .expect("component missing"),
+                &ComponentDeltaCompression(vec![1, 2])
+            );
+            // check that the component value was stored in the delta manager cache
+            assert!(stepper
+                .server_app
+                .world()
+                .resource::<ConnectionManager>()
+                .delta_manager
+                .data
+                .get_component_value(
+                    server_entity,
+                    insert_tick,
+                    ComponentKind::of::<ComponentDeltaCompression>(),
+                    group_id,
+                )
+                .is_some());
+
+            // apply non-delta update
+            stepper
+                .server_app
+                .world_mut()
+                .entity_mut(server_entity)
+                .get_mut::<ComponentSyncModeFull>()
+                .unwrap()
                 .0 = 1.0;
             stepper.frame_step();
             stepper.frame_step();
@@ -2986,16 +4186,23 @@ pub(crate) mod send {
             );
         }
 
+        /// Pause the replication to a client: no spawns/updates are sent to it, but despawns are.
+        /// After resuming, the client receives a full resync.
         #[test]
-        fn test_component_remove() {
+        fn test_pause_client_replication() {
             let mut stepper = BevyStepper::default();
+            let client_id = ClientId::Netcode(TEST_CLIENT_ID);
 
-            // spawn an entity on server
             let server_entity = stepper
                 .server_app
                 .world_mut()
                 .spawn((Replicate::default(), ComponentSyncModeFull(1.0)))
                 .id();
+            let server_entity_despawned = stepper
+                .server_app
+                .world_mut()
+                .spawn(Replicate::default())
+                .id();
             stepper.frame_step();
             stepper.frame_step();
             let client_entity = stepper
@@ -3006,113 +4213,581 @@ pub(crate) mod send {
                 .remote_entity_map
                 .get_local(server_entity)
                 .expect("entity was not replicated to client");
+            let client_entity_despawned = stepper
+                .client_app
+                .world()
+                .resource::<client::ConnectionManager>()
+                .replication_receiver
+                .remote_entity_map
+                .get_local(server_entity_despawned)
+                .expect("entity was not replicated to client");
+
+            // pause replication and update the world
+            stepper
+                .server_app
+                .world_mut()
+                .resource_mut::<server::ConnectionManager>()
+                .pause_client_replication(client_id)
+                .unwrap();
+            stepper
+                .server_app
+                .world_mut()
+                .entity_mut(server_entity)
+                .insert(ComponentSyncModeFull(2.0));
+            let server_entity_new = stepper
+                .server_app
+                .world_mut()
+                .spawn((Replicate::default(), ComponentSyncModeFull(5.0)))
+                .id();
+            stepper
+                .server_app
+                .world_mut()
+                .despawn(server_entity_despawned);
+            for _ in 0..5 {
+                stepper.frame_step();
+            }
+
+            // no updates or spawns were sent, but the despawn was
             assert_eq!(
                 stepper
                     .client_app
                     .world()
-                    .entity(client_entity)
-                    .get::<ComponentSyncModeFull>()
+                    .get::<ComponentSyncModeFull>(client_entity)
                     .expect("component missing"),
                 &ComponentSyncModeFull(1.0)
             );
+            assert!(stepper
+                .client_app
+                .world()
+                .resource::<client::ConnectionManager>()
+                .replication_receiver
+                .remote_entity_map
+                .get_local(server_entity_new)
+                .is_none());
+            assert!(stepper
+                .client_app
+                .world()
+                .get_entity(client_entity_despawned)
+                .is_err());
 
-            // remove component
+            // resume replication: the client receives the full state
             stepper
                 .server_app
                 .world_mut()
-                .entity_mut(server_entity)
-                .remove::<ComponentSyncModeFull>();
+                .resource_mut::<server::ConnectionManager>()
+                .resume_client_replication(client_id)
+                .unwrap();
             stepper.frame_step();
             stepper.frame_step();
-
-            // check that the component was replicated
-            assert!(stepper
+            assert_eq!(
+                stepper
+                    .client_app
+                    .world()
+                    .get::<ComponentSyncModeFull>(client_entity)
+                    .expect("component missing"),
+                &ComponentSyncModeFull(2.0)
+            );
+            let client_entity_new = stepper
                 .client_app
                 .world()
-                .entity(client_entity)
-                .get::<ComponentSyncModeFull>()
-                .is_none());
+                .resource::<client::ConnectionManager>()
+                .replication_receiver
+                .remote_entity_map
+                .get_local(server_entity_new)
+                .expect("entity was not replicated to client after resuming");
+            assert_eq!(
+                stepper
+                    .client_app
+                    .world()
+                    .get::<ComponentSyncModeFull>(client_entity_new)
+                    .expect("component missing"),
+                &ComponentSyncModeFull(5.0)
+            );
         }
 
-        /// Check that if we switch the visibility mode, the entity gets spawned
-        /// to the clients that now have visibility
+        /// Clients in different shards all receive the updates of an entity that is replicated to both shards
         #[test]
-        fn test_change_visibility_mode_spawn() {
-            let mut stepper = BevyStepper::default();
+        fn test_replication_with_client_shards() {
+            let mut stepper = MultiBevyStepper::default();
+            let mut manager = stepper
+                .server_app
+                .world_mut()
+                .resource_mut::<server::ConnectionManager>();
+            manager
+                .set_client_shard_key(ClientId::Netcode(TEST_CLIENT_ID_1), ClientShardKey(1))
+                .unwrap();
+            manager
+                .set_client_shard_key(ClientId::Netcode(TEST_CLIENT_ID_2), ClientShardKey(2))
+                .unwrap();
 
             let server_entity = stepper
                 .server_app
                 .world_mut()
-                .spawn(Replicate {
-                    target: ReplicationTarget {
-                        target: NetworkTarget::None,
-                    },
-                    ..default()
-                })
+                .spawn((Replicate::default(), ComponentSyncModeFull(1.0)))
                 .id();
             stepper.frame_step();
             stepper.frame_step();
-
-            // set visibility to interest management
             stepper
                 .server_app
                 .world_mut()
                 .entity_mut(server_entity)
-                .insert((
-                    NetworkRelevanceMode::InterestManagement,
-                    ReplicationTarget {
-                        target: NetworkTarget::All,
-                    },
-                ));
-            stepper
-                .server_app
-                .world_mut()
-                .resource_mut::<RelevanceManager>()
-                .gain_relevance(ClientId::Netcode(TEST_CLIENT_ID), server_entity);
-
+                .insert(ComponentSyncModeFull(2.0));
             stepper.frame_step();
             stepper.frame_step();
-            stepper
-                .client_app
-                .world()
-                .resource::<client::ConnectionManager>()
-                .replication_receiver
-                .remote_entity_map
-                .get_local(server_entity)
-                .expect("entity was not replicated to client");
+
+            for client_app in [&stepper.client_app_1, &stepper.client_app_2] {
+                let client_entity = client_app
+                    .world()
+                    .resource::<client::ConnectionManager>()
+                    .replication_receiver
+                    .remote_entity_map
+                    .get_local(server_entity)
+                    .expect("entity was not replicated to client");
+                assert_eq!(
+                    client_app
+                        .world()
+                        .get::<ComponentSyncModeFull>(client_entity)
+                        .expect("component missing"),
+                    &ComponentSyncModeFull(2.0)
+                );
+            }
         }
 
-        /// Check if we send an update with a component that is already equal to the component on the remote,
-        /// then we do not apply the update to the remote (to avoid triggering change detection)
+        /// Entities of a server instance are only replicated to, and controlled by,
+        /// the clients of that instance. Entities without an instance are not replicated to them.
         #[test]
-        fn test_equal_update_does_not_trigger_change_detection() {
-            let mut stepper = BevyStepper::default();
+        fn test_replication_with_server_instances() {
+            let mut stepper = MultiBevyStepper::default();
+            let client_1 = ClientId::Netcode(TEST_CLIENT_ID_1);
+            let client_2 = ClientId::Netcode(TEST_CLIENT_ID_2);
+            let mut manager = stepper
+                .server_app
+                .world_mut()
+                .resource_mut::<server::ConnectionManager>();
+            manager
+                .set_client_instance(client_1, ServerInstanceId(1))
+                .unwrap();
+            manager
+                .set_client_instance(client_2, ServerInstanceId(2))
+                .unwrap();
 
-            stepper.client_app.add_systems(
-                Update,
-                |mut events: EventReader<ComponentUpdateEvent<ComponentSyncModeFull>>| {
-                    if let Some(event) = events.read().next() {
-                        panic!(
-                            "ComponentUpdateEvent received for entity: {:?}",
-                            event.entity()
-                        );
-                    }
+            let replicate = Replicate {
+                controlled_by: ControlledBy {
+                    target: NetworkTarget::All,
+                    ..default()
                 },
-            );
-
-            // spawn an entity on server
-            let server_entity = stepper
+                ..default()
+            };
+            let entity_1 = stepper
                 .server_app
                 .world_mut()
-                .spawn(ComponentSyncModeFull(1.0))
+                .spawn((
+                    replicate.clone(),
+                    ComponentSyncModeFull(1.0),
+                    ServerInstanceId(1),
+                ))
                 .id();
-            // spawn an entity on the client with the component value
-            let client_entity = stepper
-                .client_app
+            let entity_2 = stepper
+                .server_app
                 .world_mut()
-                .spawn(ComponentSyncModeFull(1.0))
-                .id();
-
+                .spawn((
+                    replicate.clone(),
+                    ComponentSyncModeFull(2.0),
+                    ServerInstanceId(2),
+                ))
+                .id();
+            let shared_entity = stepper
+                .server_app
+                .world_mut()
+                .spawn((Replicate::default(), ComponentSyncModeFull(0.0)))
+                .id();
+            stepper.frame_step();
+            stepper.frame_step();
+
+            for (client_app, client_id, own_entity, other_entity) in [
+                (&stepper.client_app_1, client_1, entity_1, entity_2),
+                (&stepper.client_app_2, client_2, entity_2, entity_1),
+            ] {
+                let remote_entity_map = &client_app
+                    .world()
+                    .resource::<client::ConnectionManager>()
+                    .replication_receiver
+                    .remote_entity_map;
+                assert!(remote_entity_map.get_local(own_entity).is_some());
+                assert!(remote_entity_map.get_local(shared_entity).is_none());
+                assert!(remote_entity_map.get_local(other_entity).is_none());
+
+                let manager = stepper
+                    .server_app
+                    .world()
+                    .resource::<server::ConnectionManager>();
+                let controlled_entities = stepper
+                    .server_app
+                    .world()
+                    .get::<ControlledEntities>(manager.client_entity(client_id).unwrap())
+                    .unwrap();
+                assert!(controlled_entities.contains(&own_entity));
+                assert!(!controlled_entities.contains(&other_entity));
+            }
+        }
+
+        /// Once instances are in use, the entities without an instance and the replicated resources
+        /// are only replicated to the clients that are not assigned to any instance
+        #[test]
+        fn test_replication_with_server_instances_unassigned_client() {
+            let mut stepper = MultiBevyStepper::default();
+            let client_1 = ClientId::Netcode(TEST_CLIENT_ID_1);
+            stepper
+                .server_app
+                .world_mut()
+                .resource_mut::<server::ConnectionManager>()
+                .set_client_instance(client_1, ServerInstanceId(1))
+                .unwrap();
+
+            let instance_entity = stepper
+                .server_app
+                .world_mut()
+                .spawn((Replicate::default(), ServerInstanceId(1)))
+                .id();
+            let untagged_entity = stepper
+                .server_app
+                .world_mut()
+                .spawn(Replicate::default())
+                .id();
+            stepper
+                .server_app
+                .world_mut()
+                .insert_resource(Resource1(1.0));
+            stepper
+                .server_app
+                .replicate_resource::<Resource1, Channel1>(NetworkTarget::All);
+            stepper.frame_step();
+            stepper.frame_step();
+
+            let remote_entity_map_1 = &stepper
+                .client_app_1
+                .world()
+                .resource::<client::ConnectionManager>()
+                .replication_receiver
+                .remote_entity_map;
+            assert!(remote_entity_map_1.get_local(instance_entity).is_some());
+            assert!(remote_entity_map_1.get_local(untagged_entity).is_none());
+            assert!(stepper
+                .client_app_1
+                .world()
+                .get_resource::<Resource1>()
+                .is_none());
+
+            let remote_entity_map_2 = &stepper
+                .client_app_2
+                .world()
+                .resource::<client::ConnectionManager>()
+                .replication_receiver
+                .remote_entity_map;
+            assert!(remote_entity_map_2.get_local(instance_entity).is_none());
+            assert!(remote_entity_map_2.get_local(untagged_entity).is_some());
+            assert_eq!(
+                stepper.client_app_2.world().resource::<Resource1>(),
+                &Resource1(1.0)
+            );
+        }
+
+        /// The debug replication state reports which clients each entity is replicated to
+        #[test]
+        fn test_replication_state() {
+            let mut stepper = MultiBevyStepper::default();
+            let client_1 = ClientId::Netcode(TEST_CLIENT_ID_1);
+            let client_2 = ClientId::Netcode(TEST_CLIENT_ID_2);
+
+            let entity_a = stepper
+                .server_app
+                .world_mut()
+                .spawn((
+                    Replicate::default().replicate_to(NetworkTarget::AllExceptSingle(client_1)),
+                    ComponentSyncModeFull(1.0),
+                ))
+                .id();
+            let entity_b = stepper
+                .server_app
+                .world_mut()
+                .spawn((
+                    Replicate {
+                        group: ReplicationGroup::new_id(7),
+                        ..default()
+                    }
+                    .replicate_to(NetworkTarget::Single(client_1)),
+                    ComponentSyncModeFull(2.0),
+                ))
+                .id();
+            stepper.frame_step();
+            stepper.frame_step();
+
+            let manager = stepper
+                .server_app
+                .world()
+                .resource::<server::ConnectionManager>();
+            assert_eq!(manager.replication_state().count(), 2);
+            let state_a = manager.entity_replication_state(entity_a).unwrap();
+            assert_eq!(state_a.clients, HashSet::from_iter([client_2]));
+            assert_eq!(state_a.group, ReplicationGroupId(entity_a.to_bits()));
+            assert_eq!(state_a.authority, AuthorityPeer::Server);
+            assert!(state_a.last_send_tick.is_some());
+            let state_b = manager.entity_replication_state(entity_b).unwrap();
+            assert_eq!(state_b.clients, HashSet::from_iter([client_1]));
+            assert_eq!(state_b.group, ReplicationGroupId(7));
+
+            // update the replication targets
+            stepper
+                .server_app
+                .world_mut()
+                .entity_mut(entity_a)
+                .insert(ReplicationTarget {
+                    target: NetworkTarget::None,
+                });
+            stepper
+                .server_app
+                .world_mut()
+                .entity_mut(entity_b)
+                .insert(ReplicationTarget {
+                    target: NetworkTarget::All,
+                });
+            stepper.frame_step();
+
+            let manager = stepper
+                .server_app
+                .world()
+                .resource::<server::ConnectionManager>();
+            assert!(manager.entity_replication_state(entity_a).is_none());
+            assert_eq!(
+                manager.entity_replication_state(entity_b).unwrap().clients,
+                HashSet::from_iter([client_1, client_2])
+            );
+
+            // despawn the entity
+            stepper.server_app.world_mut().despawn(entity_b);
+            stepper.frame_step();
+            assert_eq!(
+                stepper
+                    .server_app
+                    .world()
+                    .resource::<server::ConnectionManager>()
+                    .replication_state()
+                    .count(),
+                0
+            );
+        }
+
+        #[test]
+        fn test_component_remove() {
+            let mut stepper = BevyStepper::default();
+
+            // spawn an entity on server
+            let server_entity = stepper
+                .server_app
+                .world_mut()
+                .spawn((Replicate::default(), ComponentSyncModeFull(1.0)))
+                .id();
+            stepper.frame_step();
+            stepper.frame_step();
+            let client_entity = stepper
+                .client_app
+                .world()
+                .resource::<client::ConnectionManager>()
+                .replication_receiver
+                .remote_entity_map
+                .get_local(server_entity)
+                .expect("entity was not replicated to client");
+            assert_eq!(
+                stepper
+                    .client_app
+                    .world()
+                    .entity(client_entity)
+                    .get::<ComponentSyncModeFull>()
+                    .expect("component missing"),
+                &ComponentSyncModeFull(1.0)
+            );
+
+            // remove component
+            stepper
+                .server_app
+                .world_mut()
+                .entity_mut(server_entity)
+                .remove::<ComponentSyncModeFull>();
+            stepper.frame_step();
+            stepper.frame_step();
+
+            // check that the component was replicated
+            assert!(stepper
+                .client_app
+                .world()
+                .entity(client_entity)
+                .get::<ComponentSyncModeFull>()
+                .is_none());
+        }
+
+        #[test]
+        fn test_component_remove_marker() {
+            let mut stepper = BevyStepper::default();
+
+            // spawn an entity on server
+            let server_entity = stepper
+                .server_app
+                .world_mut()
+                .spawn((Replicate::default(), ComponentMarker))
+                .id();
+            stepper.frame_step();
+            stepper.frame_step();
+            let client_entity = stepper
+                .client_app
+                .world()
+                .resource::<client::ConnectionManager>()
+                .replication_receiver
+                .remote_entity_map
+                .get_local(server_entity)
+                .expect("entity was not replicated to client");
+            assert!(stepper
+                .client_app
+                .world()
+                .entity(client_entity)
+                .contains::<ComponentMarker>());
+
+            // remove the marker component
+            stepper
+                .server_app
+                .world_mut()
+                .entity_mut(server_entity)
+                .remove::<ComponentMarker>();
+            stepper.frame_step();
+            stepper.frame_step();
+
+            // check that the removal was replicated
+            assert!(!stepper
+                .client_app
+                .world()
+                .entity(client_entity)
+                .contains::<ComponentMarker>());
+        }
+
+        /// Check that if an entity is despawned in the same tick as a component is removed,
+        /// the entity is correctly despawned on the client
+        #[test]
+        fn test_component_remove_and_despawn() {
+            let mut stepper = BevyStepper::default();
+
+            // spawn an entity on server
+            let server_entity = stepper
+                .server_app
+                .world_mut()
+                .spawn((Replicate::default(), ComponentMarker))
+                .id();
+            stepper.frame_step();
+            stepper.frame_step();
+            let client_entity = stepper
+                .client_app
+                .world()
+                .resource::<client::ConnectionManager>()
+                .replication_receiver
+                .remote_entity_map
+                .get_local(server_entity)
+                .expect("entity was not replicated to client");
+
+            // remove the component and despawn the entity in the same tick
+            stepper
+                .server_app
+                .world_mut()
+                .entity_mut(server_entity)
+                .remove::<ComponentMarker>();
+            stepper.server_app.world_mut().despawn(server_entity);
+            stepper.frame_step();
+            stepper.frame_step();
+
+            // check that the entity was despawned
+            assert!(stepper
+                .client_app
+                .world()
+                .get_entity(client_entity)
+                .is_err());
+        }
+
+        /// Check that if we switch the visibility mode, the entity gets spawned
+        /// to the clients that now have visibility
+        #[test]
+        fn test_change_visibility_mode_spawn() {
+            let mut stepper = BevyStepper::default();
+
+            let server_entity = stepper
+                .server_app
+                .world_mut()
+                .spawn(Replicate {
+                    target: ReplicationTarget {
+                        target: NetworkTarget::None,
+                    },
+                    ..default()
+                })
+                .id();
+            stepper.frame_step();
+            stepper.frame_step();
+
+            // set visibility to interest management
+            stepper
+                .server_app
+                .world_mut()
+                .entity_mut(server_entity)
+                .insert((
+                    NetworkRelevanceMode::InterestManagement,
+                    ReplicationTarget {
+                        target: NetworkTarget::All,
+                    },
+                ));
+            stepper
+                .server_app
+                .world_mut()
+                .resource_mut::<RelevanceManager>()
+                .gain_relevance(ClientId::Netcode(TEST_CLIENT_ID), server_entity);
+
+            stepper.frame_step();
+            stepper.frame_step();
+            stepper
+                .client_app
+                .world()
+                .resource::<client::ConnectionManager>()
+                .replication_receiver
+                .remote_entity_map
+                .get_local(server_entity)
+                .expect("entity was not replicated to client");
+        }
+
+        /// Check if we send an update with a component that is already equal to the component on the remote,
+        /// then we do not apply the update to the remote (to avoid triggering change detection)
+        #[test]
+        fn test_equal_update_does_not_trigger_change_detection() {
+            let mut stepper = BevyStepper::default();
+
+            stepper.client_app.add_systems(
+                Update,
+                |mut events: EventReader<ComponentUpdateEvent<ComponentSyncModeFull>>| {
+                    if let Some(event) = events.read().next() {
+                        panic!(
+                            "ComponentUpdateEvent received for entity: {:?}",
+                            event.entity()
+                        );
+                    }
+                },
+            );
+
+            // spawn an entity on server
+            let server_entity = stepper
+                .server_app
+                .world_mut()
+                .spawn(ComponentSyncModeFull(1.0))
+                .id();
+            // spawn an entity on the client with the component value
+            let client_entity = stepper
+                .client_app
+                .world_mut()
+                .spawn(ComponentSyncModeFull(1.0))
+                .id();
+
             // add replication with a pre-existing target
             stepper
                 .server_app
@@ -3187,6 +4862,105 @@ pub(crate) mod send {
             );
         }
 
+        #[derive(Resource, Default)]
+        struct UpdateCounts(bevy::utils::HashMap<Entity, u32>);
+
+        /// With a tight bandwidth budget, an entity with a high [`ReplicationPriority`] should be
+        /// updated every tick, while an entity with a low priority should still be updated once its
+        /// accumulated priority is high enough.
+        #[test]
+        fn test_replication_priority_bandwidth_cap() {
+            let mut stepper = BevyStepper::default_no_init();
+            stepper
+                .server_app
+                .world_mut()
+                .resource_mut::<ServerConfig>()
+                .packet = server::PacketConfig::default().enable_bandwidth_cap();
+            stepper.init();
+
+            let server_high = stepper
+                .server_app
+                .world_mut()
+                .spawn((
+                    Replicate::default(),
+                    ComponentSyncModeFull(0.0),
+                    ReplicationPriority(10.0),
+                ))
+                .id();
+            let server_low = stepper
+                .server_app
+                .world_mut()
+                .spawn((Replicate::default(), ComponentSyncModeFull(0.0)))
+                .id();
+            for _ in 0..10 {
+                stepper.frame_step();
+            }
+            let client_high = stepper
+                .client_app
+                .world()
+                .resource::<client::ConnectionManager>()
+                .replication_receiver
+                .remote_entity_map
+                .get_local(server_high)
+                .unwrap();
+            let client_low = stepper
+                .client_app
+                .world()
+                .resource::<client::ConnectionManager>()
+                .replication_receiver
+                .remote_entity_map
+                .get_local(server_low)
+                .unwrap();
+
+            stepper.client_app.init_resource::<UpdateCounts>();
+            stepper.client_app.add_systems(
+                Update,
+                |mut events: EventReader<ComponentUpdateEvent<ComponentSyncModeFull>>,
+                 mut counts: ResMut<UpdateCounts>| {
+                    for event in events.read() {
+                        *counts.0.entry(event.entity()).or_default() += 1;
+                    }
+                },
+            );
+            const FRAMES: u32 = 100;
+            for i in 1..=FRAMES {
+                for entity in [server_high, server_low] {
+                    stepper
+                        .server_app
+                        .world_mut()
+                        .get_mut::<ComponentSyncModeFull>(entity)
+                        .unwrap()
+                        .0 = i as f32;
+                }
+                // the rate limiter uses the real time, so we reset it every frame to get
+                // a fixed send budget per frame (enough for the pings and a single update)
+                let budget = NonZeroU32::new(90).unwrap();
+                stepper
+                    .server_app
+                    .world_mut()
+                    .resource_mut::<ConnectionManager>()
+                    .connection_mut(ClientId::Netcode(TEST_CLIENT_ID))
+                    .unwrap()
+                    .message_manager
+                    .priority_manager
+                    .limiter = governor::DefaultDirectRateLimiter::direct(
+                    governor::Quota::per_second(budget).allow_burst(budget),
+                );
+                stepper.frame_step();
+            }
+            let counts = &stepper.client_app.world().resource::<UpdateCounts>().0;
+            let high = counts.get(&client_high).copied().unwrap_or_default();
+            let low = counts.get(&client_low).copied().unwrap_or_default();
+            // the high priority entity is updated almost every tick; it only skips the ticks
+            // where the accumulated priority of the low priority entity exceeds its own
+            assert!(high >= FRAMES * 8 / 10, "high priority updates: {high}");
+            // the low priority entity is not starved: it is updated roughly every 10 ticks
+            assert!(
+                (FRAMES / 20..=FRAMES / 5).contains(&low),
+                "low priority updates: {low}"
+            );
+        }
+
         /// Make sure that ClientToServer components are not replicated to the client
         #[test]
         fn test_component_direction() {
@@ -3235,20 +5009,41 @@ pub(crate) mod commands {
     use crate::prelude::{
         ClientId, PrePredicted, Replicated, Replicating, ReplicationGroup, ServerConnectionManager,
     };
-    use crate::shared::replication::authority::{AuthorityChange, AuthorityPeer, HasAuthority};
-    use crate::shared::replication::components::{InitialReplicated, ReplicationGroupId};
+    use crate::prelude::{Tick, TickManager};
+    use crate::shared::replication::authority::{
+        AuthorityChange, AuthorityHistory, AuthorityPeer, AuthorityTransferEvent, HasAuthority,
+        PendingAuthorityTransfer, ScheduledAuthorityTransfer,
+    };
+    use crate::shared::replication::components::{
+        DespawnReason, InitialReplicated, ReplicationGroupId,
+    };
     use bevy::ecs::system::EntityCommands;
-    use bevy::prelude::{Entity, World};
+    use bevy::prelude::{DespawnRecursiveExt, Entity, World};
 
     pub trait AuthorityCommandExt {
         /// This command is used to transfer the authority of an entity to a different peer.
         fn transfer_authority(&mut self, new_owner: AuthorityPeer);
+
+        /// Transfer the authority of an entity to a different peer once the server reaches the given tick.
+        ///
+        /// The tick of the transfer is recorded in the [`AuthorityHistory`] of the entity on the server and
+        /// on the clients, so that all peers agree on which peer had authority at each tick, even during rollback.
+        /// If the tick is already in the past, the transfer is applied on the next tick.
+        fn transfer_authority_at(&mut self, new_owner: AuthorityPeer, tick: Tick);
     }
 
     impl AuthorityCommandExt for EntityCommands<'_> {
+        fn transfer_authority_at(&mut self, new_owner: AuthorityPeer, tick: Tick) {
+            self.insert(ScheduledAuthorityTransfer {
+                to: new_owner,
+                tick,
+            });
+        }
+
         fn transfer_authority(&mut self, new_owner: AuthorityPeer) {
             self.queue(move |entity: Entity, world: &mut World| {
                 let bevy_tick = world.change_tick();
+                let tick = world.resource::<TickManager>().tick();
                 // check who the current owner is
                 let current_owner =
                     world
@@ -3264,19 +5059,13 @@ pub(crate) mod commands {
                     if world.get::<PrePredicted>(entity).is_some() {
                         return (false, false)
                     }
-                    let initial_replicated = world.get::<InitialReplicated>(entity);
                     let sync_target = world.get::<SyncTarget>(entity);
-                    // if the entity was originally spawned by a client C1,
-                    // then C1 might want to add prediction or interpolation now that they lose authority
-                    // over it
-                    let add_prediction = initial_replicated.is_some_and(|initial| {
-                        initial.from == Some(c)
-                            && sync_target.is_some_and(|target| target.prediction.targets(&c))
-                    });
-                    let add_interpolation = initial_replicated.is_some_and(|initial| {
-                        initial.from == Some(c)
-                            && sync_target.is_some_and(|target| target.interpolation.targets(&c))
-                    });
+                    // the client that loses authority might want to add prediction or interpolation:
+                    // - if the entity was originally spawned by the client, it was never predicted/interpolated
+                    // - if the client gained authority over a predicted entity, the predicted entity was released
+                    // The client ignores this if it already has a predicted/interpolated entity.
+                    let add_prediction = sync_target.is_some_and(|target| target.prediction.targets(&c));
+                    let add_interpolation = sync_target.is_some_and(|target| target.interpolation.targets(&c));
                     (add_prediction, add_interpolation)
                 };
 
@@ -3348,8 +5137,11 @@ pub(crate) mod commands {
                                 &AuthorityChange {
                                     entity,
                                     gain_authority: true,
+                                    from: current_owner,
+                                    to: new_owner,
                                     add_prediction,
                                     add_interpolation,
+                                    tick,
                                 },
                             )
                             .expect("could not send message");
@@ -3372,8 +5164,11 @@ pub(crate) mod commands {
                                 &AuthorityChange {
                                     entity,
                                     gain_authority: false,
+                                    from: current_owner,
+                                    to: new_owner,
                                     add_prediction: false,
                                     add_interpolation: false,
+                                    tick,
                                 },
                             )
                             .expect("could not send message");
@@ -3395,8 +5190,11 @@ pub(crate) mod commands {
                                 &AuthorityChange {
                                     entity,
                                     gain_authority: false,
+                                    from: current_owner,
+                                    to: new_owner,
                                     add_prediction,
                                     add_interpolation,
+                                    tick,
                                 },
                             )
                             .expect("could not send message");
@@ -3413,9 +5211,12 @@ pub(crate) mod commands {
                                 &AuthorityChange {
                                     entity,
                                     gain_authority: true,
+                                    from: current_owner,
+                                    to: new_owner,
                                     // TODO: should we compute these again?
                                     add_prediction: false,
                                     add_interpolation: false,
+                                    tick,
                                 },
                             )
                             .expect("could not send message");
@@ -3430,11 +5231,14 @@ pub(crate) mod commands {
                             .resource_mut::<ServerConnectionManager>()
                             .send_message::<AuthorityChannel, _>(
                                 c1,
-                                & AuthorityChange {
+                                &AuthorityChange {
                                     entity,
                                     gain_authority: false,
+                                    from: current_owner,
+                                    to: new_owner,
                                     add_prediction,
                                     add_interpolation,
+                                    tick,
                                 },
                             )
                             .expect("could not send message");
@@ -3445,14 +5249,44 @@ pub(crate) mod commands {
                                 &AuthorityChange {
                                     entity,
                                     gain_authority: true,
+                                    from: current_owner,
+                                    to: new_owner,
                                     add_prediction: false,
                                     add_interpolation: false,
+                                    tick,
                                 },
                             )
                             .expect("could not send message");
                     }
                     _ => unreachable!(),
                 }
+
+                if current_owner != new_owner {
+                    // record the tick at which the authority changed
+                    AuthorityHistory::record(
+                        &mut world.entity_mut(entity),
+                        current_owner,
+                        new_owner,
+                        tick,
+                    );
+                    // transfers to a client are only acknowledged once the client confirms that it took control
+                    let acknowledged = if let AuthorityPeer::Client(_) = new_owner {
+                        world.entity_mut(entity).insert(PendingAuthorityTransfer {
+                            from: current_owner,
+                            to: new_owner,
+                        });
+                        false
+                    } else {
+                        world.entity_mut(entity).remove::<PendingAuthorityTransfer>();
+                        true
+                    };
+                    world.send_event(AuthorityTransferEvent {
+                        entity,
+                        from: current_owner,
+                        to: new_owner,
+                        acknowledged,
+                    });
+                }
             });
         }
     }
@@ -3466,21 +5300,40 @@ pub(crate) mod commands {
         }
     }
 
+    fn despawn_with_reason(reason: DespawnReason) -> impl FnOnce(Entity, &mut World) {
+        move |entity: Entity, world: &mut World| {
+            if let Ok(mut entity_mut) = world.get_entity_mut(entity) {
+                entity_mut.insert(reason);
+                entity_mut.despawn_recursive();
+            }
+        }
+    }
+
     pub trait DespawnReplicationCommandExt {
         /// Despawn the entity and makes sure that the despawn won't be replicated.
         fn despawn_without_replication(&mut self);
+
+        /// Despawn the entity (and its descendants); the [`DespawnReason`] is sent to the clients along with the despawn.
+        fn despawn_with_reason(&mut self, reason: DespawnReason);
     }
     impl DespawnReplicationCommandExt for EntityCommands<'_> {
         fn despawn_without_replication(&mut self) {
             self.queue(despawn_without_replication);
         }
+
+        fn despawn_with_reason(&mut self, reason: DespawnReason) {
+            self.queue(despawn_with_reason(reason));
+        }
     }
 
     #[cfg(test)]
     mod tests {
-        use bevy::prelude::With;
+        use bevy::prelude::{default, Events, With};
 
-        use crate::prelude::server::Replicate;
+        use crate::prelude::client::EntityDespawnEvent;
+        use crate::prelude::server::{RelevanceManager, Replicate};
+        use crate::prelude::{client, ClientId, NetworkRelevanceMode};
+        use crate::tests::multi_stepper::{MultiBevyStepper, TEST_CLIENT_ID_1, TEST_CLIENT_ID_2};
         use crate::tests::protocol::*;
         use crate::tests::stepper::BevyStepper;
 
@@ -3549,5 +5402,119 @@ pub(crate) mod commands {
                 .get_single(stepper.client_app.world())
                 .is_ok());
         }
+
+        /// The reason of the despawn is available in the EntityDespawnEvent on the client
+        #[test]
+        fn test_despawn_with_reason() {
+            let mut stepper = BevyStepper::default();
+
+            let entity = stepper
+                .server_app
+                .world_mut()
+                .spawn((ComponentSyncModeFull(1.0), Replicate::default()))
+                .id();
+            stepper.frame_step();
+            stepper.frame_step();
+            let client_entity = stepper
+                .client_app
+                .world_mut()
+                .query_filtered::<Entity, With<ComponentSyncModeFull>>()
+                .get_single(stepper.client_app.world())
+                .unwrap();
+
+            stepper
+                .server_app
+                .world_mut()
+                .commands()
+                .entity(entity)
+                .despawn_with_reason(DespawnReason::Custom(7));
+            stepper.server_app.world_mut().flush();
+            stepper.frame_step();
+            stepper.frame_step();
+            assert!(stepper
+                .client_app
+                .world()
+                .get_entity(client_entity)
+                .is_err());
+            let events = stepper
+                .client_app
+                .world()
+                .resource::<Events<EntityDespawnEvent>>();
+            let reasons: Vec<_> = events
+                .get_cursor()
+                .read(events)
+                .map(|event| (event.entity(), event.reason()))
+                .collect();
+            assert_eq!(reasons, vec![(client_entity, DespawnReason::Custom(7))]);
+        }
+
+        /// An entity that loses relevance for a client is despawned on that client with
+        /// the `OutOfView` reason, while it stays replicated to the other clients
+        #[test]
+        fn test_despawn_lose_relevance_reason() {
+            let mut stepper = MultiBevyStepper::default();
+
+            let server_entity = stepper
+                .server_app
+                .world_mut()
+                .spawn(Replicate {
+                    relevance_mode: NetworkRelevanceMode::InterestManagement,
+                    ..default()
+                })
+                .id();
+            stepper
+                .server_app
+                .world_mut()
+                .resource_mut::<RelevanceManager>()
+                .gain_relevance(ClientId::Netcode(TEST_CLIENT_ID_1), server_entity)
+                .gain_relevance(ClientId::Netcode(TEST_CLIENT_ID_2), server_entity);
+            stepper.frame_step();
+            stepper.frame_step();
+            let client_entity_1 = stepper
+                .client_app_1
+                .world()
+                .resource::<client::ConnectionManager>()
+                .replication_receiver
+                .remote_entity_map
+                .get_local(server_entity)
+                .expect("entity was not replicated to client 1");
+            let client_entity_2 = stepper
+                .client_app_2
+                .world()
+                .resource::<client::ConnectionManager>()
+                .replication_receiver
+                .remote_entity_map
+                .get_local(server_entity)
+                .expect("entity was not replicated to client 2");
+
+            stepper
+                .server_app
+                .world_mut()
+                .resource_mut::<RelevanceManager>()
+                .lose_relevance(ClientId::Netcode(TEST_CLIENT_ID_1), server_entity);
+            stepper.frame_step();
+            stepper.frame_step();
+
+            assert!(stepper
+                .client_app_1
+                .world()
+                .get_entity(client_entity_1)
+                .is_err());
+            let events = stepper
+                .client_app_1
+                .world()
+                .resource::<Events<EntityDespawnEvent>>();
+            let reasons: Vec<_> = events
+                .get_cursor()
+                .read(events)
+                .map(|event| (event.entity(), event.reason()))
+                .collect();
+            assert_eq!(reasons, vec![(client_entity_1, DespawnReason::OutOfView)]);
+            assert!(stepper
+                .client_app_2
+                .world()
+                .get_entity(client_entity_2)
+                .is_ok());
+        }
     }
 }
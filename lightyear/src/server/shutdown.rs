@@ -0,0 +1,237 @@
+//! Graceful shutdown of the server: notify the clients and flush the pending messages before
+//! disconnecting them
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::channel::builder::ShutdownChannel;
+use crate::prelude::server::ServerCommandsExt;
+use crate::prelude::{MessageSend, NetworkTarget};
+use crate::server::config::ServerConfig;
+use crate::server::connection::ConnectionManager;
+use crate::server::networking::NetworkingState;
+use crate::server::run_conditions::is_started_ref;
+
+/// Message sent to all the clients when the server shuts down gracefully.
+///
+/// The client emits the reason in its [`DisconnectEvent`](crate::prelude::client::DisconnectEvent)
+/// as [`ConnectionError::ServerShutdown`](crate::connection::client::ConnectionError::ServerShutdown).
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ServerShutdown {
+    pub reason: String,
+}
+
+/// Resource present while the server is waiting for the clients to ack the shutdown
+#[derive(Resource, Debug)]
+pub(crate) struct GracefulShutdown {
+    timer: Timer,
+}
+
+/// Send the shutdown notice to all clients and start waiting for their acks.
+///
+/// If the server is not started, it is stopped right away.
+pub(crate) fn start_graceful_shutdown(world: &mut World, reason: String) {
+    if world.contains_resource::<GracefulShutdown>() {
+        debug!("The server is already shutting down");
+        return;
+    }
+    if is_started_ref(world.get_resource_ref::<State<NetworkingState>>()) {
+        let timeout = world.resource::<ServerConfig>().shutdown.timeout;
+        let _ = world
+            .resource_mut::<ConnectionManager>()
+            .send_message_to_target::<ShutdownChannel, _>(
+                &ServerShutdown { reason },
+                NetworkTarget::All,
+            )
+            .inspect_err(|e| warn!("Could not send the shutdown notice: {:?}", e));
+        world.insert_resource(GracefulShutdown {
+            timer: Timer::new(timeout, TimerMode::Once),
+        });
+    } else {
+        world.stop_server();
+    }
+}
+
+/// Once every client has acked all the reliable messages (including the shutdown notice), or
+/// once the timeout has elapsed, disconnect all the clients and stop the server.
+pub(crate) fn handle_graceful_shutdown(world: &mut World) {
+    let delta = world.resource::<Time>().delta();
+    let timed_out = world
+        .resource_mut::<GracefulShutdown>()
+        .timer
+        .tick(delta)
+        .finished();
+    let connection_manager = world.resource::<ConnectionManager>();
+    let pending_clients: Vec<_> = connection_manager
+        .connections
+        .iter()
+        .filter(|(_, connection)| {
+            !connection.is_local_client()
+                && connection.message_manager.has_pending_reliable_messages()
+        })
+        .map(|(client_id, _)| *client_id)
+        .collect();
+    if !pending_clients.is_empty() && !timed_out {
+        return;
+    }
+    for client_id in pending_clients {
+        warn!(
+            ?client_id,
+            "Force-closing client that did not ack the shutdown before the timeout"
+        );
+    }
+    // disconnecting the clients emits a DisconnectEvent for each of them
+    let client_ids: Vec<_> = connection_manager.connections.keys().copied().collect();
+    for client_id in client_ids {
+        world.disconnect(client_id);
+    }
+    world.remove_resource::<GracefulShutdown>();
+    world.stop_server();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::networking::NetworkingState as ClientNetworkingState;
+    use crate::connection::client::ConnectionError;
+    use crate::prelude::{client, server, ClientId};
+    use crate::tests::stepper::{BevyStepper, TEST_CLIENT_ID};
+    use bevy::utils::Duration;
+
+    #[derive(Resource, Default)]
+    struct ClientDisconnectReasons(Vec<Option<String>>);
+
+    #[derive(Resource, Default)]
+    struct ServerDisconnects(Vec<ClientId>);
+
+    fn setup(stepper: &mut BevyStepper) {
+        stepper
+            .client_app
+            .init_resource::<ClientDisconnectReasons>()
+            .add_systems(
+                Update,
+                |mut events: EventReader<client::DisconnectEvent>,
+                 mut reasons: ResMut<ClientDisconnectReasons>| {
+                    for event in events.read() {
+                        reasons
+                            .0
+                            .push(event.reason.as_ref().map(|reason| match reason {
+                                ConnectionError::ServerShutdown(reason) => reason.clone(),
+                                other => format!("{other:?}"),
+                            }));
+                    }
+                },
+            );
+        stepper
+            .server_app
+            .init_resource::<ServerDisconnects>()
+            .add_systems(
+                Update,
+                |mut events: EventReader<server::DisconnectEvent>,
+                 mut disconnects: ResMut<ServerDisconnects>| {
+                    for event in events.read() {
+                        disconnects.0.push(event.client_id);
+                    }
+                },
+            );
+    }
+
+    /// The client receives the shutdown reason before being disconnected,
+    /// and the server emits a DisconnectEvent for the client before stopping
+    #[test]
+    fn test_stop_server_graceful() {
+        let mut stepper = BevyStepper::default();
+        setup(&mut stepper);
+
+        stepper
+            .server_app
+            .world_mut()
+            .stop_server_graceful("maintenance");
+        for _ in 0..20 {
+            stepper.frame_step();
+        }
+
+        assert_eq!(
+            stepper
+                .client_app
+                .world()
+                .resource::<ClientDisconnectReasons>()
+                .0,
+            vec![Some("maintenance".to_string())]
+        );
+        assert_eq!(
+            stepper
+                .client_app
+                .world()
+                .resource::<State<ClientNetworkingState>>()
+                .get(),
+            &ClientNetworkingState::Disconnected
+        );
+        assert_eq!(
+            stepper.server_app.world().resource::<ServerDisconnects>().0,
+            vec![ClientId::Netcode(TEST_CLIENT_ID)]
+        );
+        assert_eq!(
+            stepper
+                .server_app
+                .world()
+                .resource::<State<NetworkingState>>()
+                .get(),
+            &NetworkingState::Stopped
+        );
+        assert!(!stepper
+            .server_app
+            .world()
+            .contains_resource::<GracefulShutdown>());
+    }
+
+    /// A client that does not ack the shutdown notice is force-closed after the timeout
+    #[test]
+    fn test_stop_server_graceful_timeout() {
+        let mut stepper = BevyStepper::default();
+        setup(&mut stepper);
+        stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<ServerConfig>()
+            .shutdown
+            .timeout = Duration::from_millis(100);
+
+        stepper
+            .server_app
+            .world_mut()
+            .stop_server_graceful("maintenance");
+        // the client is stalled, so it never acks the shutdown notice
+        for _ in 0..5 {
+            stepper.advance_time(stepper.frame_duration);
+            stepper.server_app.update();
+        }
+        assert!(stepper
+            .server_app
+            .world()
+            .contains_resource::<GracefulShutdown>());
+        assert!(stepper
+            .server_app
+            .world()
+            .resource::<ServerDisconnects>()
+            .0
+            .is_empty());
+
+        for _ in 0..10 {
+            stepper.advance_time(stepper.frame_duration);
+            stepper.server_app.update();
+        }
+        assert_eq!(
+            stepper.server_app.world().resource::<ServerDisconnects>().0,
+            vec![ClientId::Netcode(TEST_CLIENT_ID)]
+        );
+        assert_eq!(
+            stepper
+                .server_app
+                .world()
+                .resource::<State<NetworkingState>>()
+                .get(),
+            &NetworkingState::Stopped
+        );
+    }
+}
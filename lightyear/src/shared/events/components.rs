@@ -4,6 +4,8 @@ use std::marker::PhantomData;
 
 use bevy::prelude::{Component, Entity, Event};
 
+use crate::shared::replication::components::DespawnReason;
+
 #[derive(Event)]
 /// Event emitted on server every time we receive an event
 pub struct InputEvent<I: crate::inputs::native::UserAction, Ctx = ()> {
@@ -25,6 +27,53 @@ impl<I: crate::inputs::native::UserAction, Ctx: Copy> InputEvent<I, Ctx> {
     }
 }
 
+#[derive(Event, Debug)]
+/// Event emitted whenever a component received from the remote world could not be read
+/// (for example because of a protocol mismatch or a corrupted payload).
+///
+/// The component is skipped, but the rest of the replication message is still applied.
+pub struct ComponentDeserializationErrorEvent<Ctx = ()> {
+    entity: Entity,
+    component: Option<&'static str>,
+    error: String,
+    context: Ctx,
+}
+
+impl<Ctx> ComponentDeserializationErrorEvent<Ctx> {
+    pub fn new(
+        entity: Entity,
+        component: Option<&'static str>,
+        error: String,
+        context: Ctx,
+    ) -> Self {
+        Self {
+            entity,
+            component,
+            error,
+            context,
+        }
+    }
+
+    /// The local entity that the component should have been applied to
+    pub fn entity(&self) -> Entity {
+        self.entity
+    }
+
+    /// The type name of the component, if the component could be identified
+    pub fn component(&self) -> Option<&'static str> {
+        self.component
+    }
+
+    /// Description of the error
+    pub fn error(&self) -> &str {
+        &self.error
+    }
+
+    pub fn context(&self) -> &Ctx {
+        &self.context
+    }
+}
+
 #[derive(Event)]
 /// Event emitted whenever we spawn an entity from the remote world
 pub struct EntitySpawnEvent<Ctx = ()> {
@@ -50,18 +99,28 @@ impl<Ctx> EntitySpawnEvent<Ctx> {
 #[derive(Event)]
 pub struct EntityDespawnEvent<Ctx = ()> {
     entity: Entity,
+    reason: DespawnReason,
     context: Ctx,
 }
 
 impl<Ctx> EntityDespawnEvent<Ctx> {
-    pub fn new(entity: Entity, context: Ctx) -> Self {
-        Self { entity, context }
+    pub fn new(entity: Entity, reason: DespawnReason, context: Ctx) -> Self {
+        Self {
+            entity,
+            reason,
+            context,
+        }
     }
 
     pub fn entity(&self) -> Entity {
         self.entity
     }
 
+    /// The reason why the entity was despawned by the remote
+    pub fn reason(&self) -> DespawnReason {
+        self.reason
+    }
+
     pub fn context(&self) -> &Ctx {
         &self.context
     }
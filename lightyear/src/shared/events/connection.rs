@@ -7,15 +7,16 @@ use bevy::utils::HashMap;
 use tracing::trace;
 
 use crate::prelude::Tick;
-use crate::protocol::component::ComponentKind;
+use crate::protocol::component::{ComponentError, ComponentKind};
 use crate::protocol::EventContext;
+use crate::shared::replication::components::DespawnReason;
 
 // TODO: don't make fields pub but instead make accessors
 #[derive(Debug, Resource)]
 pub struct ConnectionEvents {
     // replication
     pub spawns: Vec<Entity>,
-    pub despawns: Vec<Entity>,
+    pub despawns: Vec<(Entity, DespawnReason)>,
 
     // TODO: [IMPORTANT]: add ticks as well?
     // - should we just return the latest update for a given component/entity, or all of them?
@@ -30,6 +31,10 @@ pub struct ConnectionEvents {
     //  let's just start with the kind...
     //  also, normally the updates are sequenced
     pub component_updates: HashMap<ComponentKind, Vec<Entity>>,
+    /// Components that could not be read: (entity, component name, error)
+    pub component_errors: Vec<(Entity, Option<&'static str>, String)>,
+    /// Entities for which the remote sent replication updates without having authority over them
+    pub authority_violations: Vec<Entity>,
     // // TODO: what happens if we receive on the same frame an Update for tick 4 and update for tick 10?
     // //  can we just discard the older one? what about for inserts/removes?
     // pub component_updates: EntityHashMap<Entity, HashMap<P::ComponentKinds, Tick>>,
@@ -50,6 +55,8 @@ impl ClearEvents for ConnectionEvents {
         self.component_inserts.clear();
         self.component_removes.clear();
         self.component_updates.clear();
+        self.component_errors.clear();
+        self.authority_violations.clear();
         self.empty = true;
     }
 }
@@ -69,6 +76,8 @@ impl ConnectionEvents {
             component_inserts: Default::default(),
             component_removes: Default::default(),
             component_updates: Default::default(),
+            component_errors: Vec::new(),
+            authority_violations: Vec::new(),
             // bookkeeping
             empty: true,
         }
@@ -88,13 +97,13 @@ impl ConnectionEvents {
         self.empty = false;
     }
 
-    pub(crate) fn push_despawn(&mut self, entity: Entity) {
-        trace!(?entity, "Received entity despawn");
+    pub(crate) fn push_despawn(&mut self, entity: Entity, reason: DespawnReason) {
+        trace!(?entity, ?reason, "Received entity despawn");
         #[cfg(feature = "metrics")]
         {
             metrics::counter!("replication::receive::entity::despawn").increment(1);
         }
-        self.despawns.push(entity);
+        self.despawns.push((entity, reason));
         self.empty = false;
     }
 
@@ -146,6 +155,55 @@ impl ConnectionEvents {
         // .push((entity, tick));
         self.empty = false;
     }
+
+    /// A component received for `entity` could not be read; it is skipped
+    pub(crate) fn push_component_error(
+        &mut self,
+        entity: Entity,
+        component: Option<&'static str>,
+        error: &ComponentError,
+    ) {
+        #[cfg(feature = "metrics")]
+        {
+            metrics::counter!("replication::receive::component::error").increment(1);
+        }
+        self.component_errors
+            .push((entity, component, error.to_string()));
+        self.empty = false;
+    }
+
+    /// The remote sent replication updates for `entity` without having authority over it; they were rejected
+    pub(crate) fn push_authority_violation(&mut self, entity: Entity) {
+        if !self.authority_violations.contains(&entity) {
+            self.authority_violations.push(entity);
+        }
+        self.empty = false;
+    }
+}
+
+pub trait IterComponentDeserializationErrorEvent<Ctx: EventContext = ()> {
+    #[allow(clippy::wrong_self_convention)]
+    fn into_iter_component_errors(
+        &mut self,
+    ) -> Box<dyn Iterator<Item = (Entity, Option<&'static str>, String, Ctx)> + '_>;
+    fn has_component_errors(&self) -> bool;
+}
+
+impl IterComponentDeserializationErrorEvent for ConnectionEvents {
+    fn into_iter_component_errors(
+        &mut self,
+    ) -> Box<dyn Iterator<Item = (Entity, Option<&'static str>, String, ())> + '_> {
+        let errors = std::mem::take(&mut self.component_errors);
+        Box::new(
+            errors
+                .into_iter()
+                .map(|(entity, component, error)| (entity, component, error, ())),
+        )
+    }
+
+    fn has_component_errors(&self) -> bool {
+        !self.component_errors.is_empty()
+    }
 }
 
 pub trait IterEntitySpawnEvent<Ctx: EventContext = ()> {
@@ -167,14 +225,22 @@ impl IterEntitySpawnEvent for ConnectionEvents {
 
 pub trait IterEntityDespawnEvent<Ctx: EventContext = ()> {
     #[allow(clippy::wrong_self_convention)]
-    fn into_iter_entity_despawn(&mut self) -> Box<dyn Iterator<Item = (Entity, Ctx)> + '_>;
+    fn into_iter_entity_despawn(
+        &mut self,
+    ) -> Box<dyn Iterator<Item = (Entity, DespawnReason, Ctx)> + '_>;
     fn has_entity_despawn(&self) -> bool;
 }
 
 impl IterEntityDespawnEvent for ConnectionEvents {
-    fn into_iter_entity_despawn(&mut self) -> Box<dyn Iterator<Item = (Entity, ())> + '_> {
+    fn into_iter_entity_despawn(
+        &mut self,
+    ) -> Box<dyn Iterator<Item = (Entity, DespawnReason, ())> + '_> {
         let despawns = std::mem::take(&mut self.despawns);
-        Box::new(despawns.into_iter().map(|entity| (entity, ())))
+        Box::new(
+            despawns
+                .into_iter()
+                .map(|(entity, reason)| (entity, reason, ())),
+        )
     }
 
     fn has_entity_despawn(&self) -> bool {
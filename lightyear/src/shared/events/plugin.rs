@@ -3,7 +3,9 @@
 use bevy::app::{App, PreUpdate};
 use bevy::prelude::{IntoSystemConfigs, Plugin};
 
-use crate::shared::events::components::{EntityDespawnEvent, EntitySpawnEvent};
+use crate::shared::events::components::{
+    ComponentDeserializationErrorEvent, EntityDespawnEvent, EntitySpawnEvent,
+};
 use crate::shared::events::systems::{clear_events, push_entity_events};
 use crate::shared::replication::ReplicationReceive;
 use crate::shared::sets::InternalMainSet;
@@ -24,7 +26,8 @@ impl<R: ReplicationReceive> Plugin for EventsPlugin<R> {
     fn build(&self, app: &mut App) {
         // EVENTS
         app.add_event::<EntitySpawnEvent<R::EventContext>>()
-            .add_event::<EntityDespawnEvent<R::EventContext>>();
+            .add_event::<EntityDespawnEvent<R::EventContext>>()
+            .add_event::<ComponentDeserializationErrorEvent<R::EventContext>>();
         // SYSTEMS
         app.add_systems(
             PreUpdate,
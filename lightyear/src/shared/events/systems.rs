@@ -1,12 +1,13 @@
 use bevy::prelude::{Component, EventWriter, ResMut};
 
 use crate::shared::events::components::{
-    ComponentInsertEvent, ComponentRemoveEvent, ComponentUpdateEvent, EntityDespawnEvent,
-    EntitySpawnEvent,
+    ComponentDeserializationErrorEvent, ComponentInsertEvent, ComponentRemoveEvent,
+    ComponentUpdateEvent, EntityDespawnEvent, EntitySpawnEvent,
 };
 use crate::shared::events::connection::{
-    ClearEvents, IterComponentInsertEvent, IterComponentRemoveEvent, IterComponentUpdateEvent,
-    IterEntityDespawnEvent, IterEntitySpawnEvent,
+    ClearEvents, IterComponentDeserializationErrorEvent, IterComponentInsertEvent,
+    IterComponentRemoveEvent, IterComponentUpdateEvent, IterEntityDespawnEvent,
+    IterEntitySpawnEvent,
 };
 use crate::shared::replication::ReplicationReceive;
 
@@ -42,6 +43,7 @@ pub(crate) fn push_entity_events<R: ReplicationReceive>(
     mut connection_manager: ResMut<R>,
     mut entity_spawn_events: EventWriter<EntitySpawnEvent<R::EventContext>>,
     mut entity_despawn_events: EventWriter<EntityDespawnEvent<R::EventContext>>,
+    mut component_error_events: EventWriter<ComponentDeserializationErrorEvent<R::EventContext>>,
 ) {
     entity_spawn_events.send_batch(
         connection_manager
@@ -53,7 +55,15 @@ pub(crate) fn push_entity_events<R: ReplicationReceive>(
         connection_manager
             .events()
             .into_iter_entity_despawn()
-            .map(|(entity, ctx)| EntityDespawnEvent::new(entity, ctx)),
+            .map(|(entity, reason, ctx)| EntityDespawnEvent::new(entity, reason, ctx)),
+    );
+    component_error_events.send_batch(
+        connection_manager
+            .events()
+            .into_iter_component_errors()
+            .map(|(entity, component, error, ctx)| {
+                ComponentDeserializationErrorEvent::new(entity, component, error, ctx)
+            }),
     );
 }
 
@@ -1,6 +1,14 @@
 use crate::prelude::{client, server};
 use bevy::ecs::system::SystemParam;
-use bevy::prelude::{ComputedStates, Res, State, World};
+use bevy::prelude::{ComputedStates, Res, State, States, World};
+
+/// State that is present when the app is a relay.
+///
+/// When the client is connected to the upstream server and the server is started,
+/// the [`NetworkIdentityState`] is `Relay` instead of `HostServer`.
+/// It is inserted by the [`RelayPlugin`](crate::server::relay::RelayPlugin).
+#[derive(States, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RelayState;
 
 /// State that will contain the current role of the peer. This state is only active if the peer is connected
 #[derive(Clone, PartialEq, Eq, Hash, Debug)]
@@ -8,17 +16,45 @@ pub enum NetworkIdentityState {
     Client,
     Server,
     HostServer,
+    /// The client is connected to an upstream server and the server relays its entities
+    /// to its own clients (see [`RelayPlugin`](crate::server::relay::RelayPlugin)).
+    /// A relay is both a client and a server.
+    Relay,
 }
 
 impl ComputedStates for NetworkIdentityState {
     type SourceStates = (
         Option<client::NetworkingState>,
         Option<server::NetworkingState>,
+        Option<RelayState>,
     );
 
     /// We then define the compute function, which takes in the AppState
     fn compute(sources: Self::SourceStates) -> Option<Self> {
-        match sources {
+        let (client, server, relay) = sources;
+        if relay.is_some() {
+            return match (client, server) {
+                // A relay is connected to the upstream server and serves its own clients
+                (
+                    Some(client::NetworkingState::Connected),
+                    Some(server::NetworkingState::Started),
+                ) => Some(NetworkIdentityState::Relay),
+                // we include these so that the relay doesn't briefly become a Client or a Server
+                // while one side is shutting down
+                (
+                    Some(client::NetworkingState::Connected),
+                    Some(server::NetworkingState::Stopping),
+                ) => Some(NetworkIdentityState::Relay),
+                (
+                    Some(client::NetworkingState::Disconnecting),
+                    Some(server::NetworkingState::Started),
+                ) => Some(NetworkIdentityState::Relay),
+                (Some(client::NetworkingState::Connected), _) => Some(NetworkIdentityState::Client),
+                (_, Some(server::NetworkingState::Started)) => Some(NetworkIdentityState::Server),
+                _ => None,
+            };
+        }
+        match (client, server) {
             // If client and server states are both present and started, then we must be a HostServer
             (Some(client::NetworkingState::Connected), Some(server::NetworkingState::Started)) => {
                 Some(NetworkIdentityState::HostServer)
@@ -48,9 +84,12 @@ pub struct NetworkIdentity<'w> {
 
 impl NetworkIdentity<'_> {
     pub fn is_client(&self) -> bool {
-        self.identity
-            .as_ref()
-            .is_some_and(|i| i.get() == &NetworkIdentityState::Client)
+        self.identity.as_ref().is_some_and(|i| {
+            matches!(
+                i.get(),
+                NetworkIdentityState::Client | NetworkIdentityState::Relay
+            )
+        })
     }
     pub fn is_server(&self) -> bool {
         self.identity
@@ -75,7 +114,12 @@ pub trait AppIdentityExt {
 impl AppIdentityExt for World {
     fn is_client(&self) -> bool {
         self.get_resource::<State<NetworkIdentityState>>()
-            .is_some_and(|i| i.get() == &NetworkIdentityState::Client)
+            .is_some_and(|i| {
+                matches!(
+                    i.get(),
+                    NetworkIdentityState::Client | NetworkIdentityState::Relay
+                )
+            })
     }
 
     fn is_server(&self) -> bool {
@@ -0,0 +1,350 @@
+//! Deterministic lockstep
+//!
+//! By default the server replicates the state of the entities, and the clients predict or interpolate
+//! them. For games that simulate a very large number of entities (for example an RTS with thousands of
+//! units), replicating the state does not scale. In deterministic lockstep only the inputs are
+//! exchanged: every client runs the same deterministic simulation with the inputs of all the clients,
+//! and ends up in the same state.
+//!
+//! - every tick, each client sends its buffered input to the server. The input is applied
+//!   `input_delay_ticks` ticks later, to give it time to reach the other clients.
+//! - the server waits until it has the inputs of all the connected clients for a tick, and then
+//!   broadcasts them. A slow client does not stall the others: once the server reaches
+//!   `tick + input_timeout`, it broadcasts the tick with `None` for the missing inputs, and drops
+//!   the inputs that arrive later.
+//! - the clients run the [`LockstepUpdate`] schedule once for each tick, in order, as soon as they
+//!   have received the inputs for that tick. The inputs of all the clients are available in that
+//!   schedule as [`InputEvent<A, ClientId>`](crate::shared::events::components::InputEvent)
+//!   events.
+//! - after each tick, the clients compute a checksum of the components registered with
+//!   [`AppLockstepExt::add_lockstep_checksum`] and send it to the server, which emits a
+//!   [`LockstepDesyncEvent`](crate::server::lockstep::LockstepDesyncEvent) if two clients computed
+//!   different checksums for the same tick.
+//!
+//! ```rust,ignore
+//! app.add_plugins(LockstepPlugin::<MyInput>::new(LockstepConfig::default()));
+//! app.add_lockstep_checksum::<UnitPosition>();
+//!
+//! // client: buffer the input of the local player
+//! fn buffer_input(mut manager: ResMut<LockstepManager<MyInput>>) {
+//!     manager.add_input(MyInput::Move(target));
+//! }
+//! app.add_systems(FixedPreUpdate, buffer_input.in_set(LockstepSet::BufferInputs));
+//!
+//! // client: simulate the tick with the inputs of all the clients
+//! fn move_units(mut inputs: EventReader<InputEvent<MyInput, ClientId>>, ...) {}
+//! app.add_systems(LockstepUpdate, move_units);
+//! ```
+//!
+//! The lockstep plugin replaces the [`InputPlugin`](crate::prelude::InputPlugin) for the input type;
+//! only one lockstep input type can be registered. Host-server mode is not supported.
+use std::any::type_name;
+use std::hash::Hasher;
+
+use bevy::ecs::schedule::ScheduleLabel;
+use bevy::prelude::*;
+use bevy::utils::Duration;
+use serde::{Deserialize, Serialize};
+
+use crate::client::config::ClientConfig;
+use crate::prelude::{ChannelDirection, ClientId, Tick, UserAction};
+use crate::protocol::message::registry::AppMessageInternalExt;
+use crate::server::config::ServerConfig;
+
+#[derive(Clone, Copy, Debug)]
+pub struct LockstepConfig {
+    /// Number of ticks between the tick where a client buffers an input and the tick where the
+    /// input is simulated.
+    ///
+    /// A higher delay leaves more time for the inputs to reach the server and the other clients
+    /// before the tick is simulated, at the cost of input latency.
+    pub input_delay_ticks: u16,
+    /// How long the server waits for the input of a client for a tick before broadcasting
+    /// the tick without it
+    pub input_timeout: Duration,
+}
+
+impl Default for LockstepConfig {
+    fn default() -> Self {
+        Self {
+            input_delay_ticks: 4,
+            input_timeout: Duration::from_millis(200),
+        }
+    }
+}
+
+impl LockstepConfig {
+    pub fn with_input_delay_ticks(mut self, input_delay_ticks: u16) -> Self {
+        self.input_delay_ticks = input_delay_ticks;
+        self
+    }
+
+    pub fn with_input_timeout(mut self, input_timeout: Duration) -> Self {
+        self.input_timeout = input_timeout;
+        self
+    }
+}
+
+/// Schedule that runs the deterministic simulation once for each lockstep tick on the clients
+#[derive(ScheduleLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LockstepUpdate;
+
+/// Input of a client for a lockstep tick
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct LockstepInputMessage<A> {
+    pub(crate) tick: Tick,
+    pub(crate) input: Option<A>,
+}
+
+/// Inputs of all the clients for a lockstep tick, sorted by [`ClientId`]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct LockstepTickMessage<A> {
+    pub(crate) tick: Tick,
+    pub(crate) inputs: Vec<(ClientId, Option<A>)>,
+}
+
+/// Checksum of the simulation of a client after a lockstep tick
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct LockstepChecksumMessage {
+    pub(crate) tick: Tick,
+    pub(crate) checksum: u64,
+}
+
+pub struct LockstepPlugin<A: UserAction> {
+    config: LockstepConfig,
+    _marker: std::marker::PhantomData<A>,
+}
+
+impl<A: UserAction> LockstepPlugin<A> {
+    pub fn new(config: LockstepConfig) -> Self {
+        Self {
+            config,
+            _marker: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<A: UserAction> Default for LockstepPlugin<A> {
+    fn default() -> Self {
+        Self::new(LockstepConfig::default())
+    }
+}
+
+impl<A: UserAction> Plugin for LockstepPlugin<A> {
+    fn build(&self, app: &mut App) {
+        app.register_message_internal::<LockstepInputMessage<A>>(ChannelDirection::ClientToServer);
+        app.register_message_internal::<LockstepTickMessage<A>>(ChannelDirection::ServerToClient);
+        app.register_message_internal::<LockstepChecksumMessage>(ChannelDirection::ClientToServer);
+        app.init_resource::<LockstepChecksumFns>();
+        let is_client = app.world().get_resource::<ClientConfig>().is_some();
+        let is_server = app.world().get_resource::<ServerConfig>().is_some();
+        assert!(is_client || is_server, "Either ClientConfig or ServerConfig must be present! Make sure that your SharedPlugin is registered after the ClientPlugins/ServerPlugins");
+        if is_client {
+            app.add_plugins(crate::client::lockstep::ClientLockstepPlugin::<A>::new(
+                self.config,
+            ));
+        }
+        if is_server {
+            app.add_plugins(crate::server::lockstep::ServerLockstepPlugin::<A>::new(
+                self.config,
+            ));
+        }
+    }
+}
+
+/// Functions that hash the components registered with [`AppLockstepExt::add_lockstep_checksum`]
+#[derive(Resource, Default)]
+pub(crate) struct LockstepChecksumFns(Vec<fn(&mut World) -> u64>);
+
+impl LockstepChecksumFns {
+    /// Compute the checksum of the simulation state
+    pub(crate) fn checksum(world: &mut World) -> u64 {
+        world.resource_scope(|world, fns: Mut<LockstepChecksumFns>| {
+            fns.0
+                .iter()
+                .fold(0u64, |checksum, f| checksum.wrapping_add(f(world)))
+        })
+    }
+}
+
+pub trait AppLockstepExt {
+    /// Include the component in the checksum of the simulation that the clients compute after
+    /// every lockstep tick to detect desyncs
+    fn add_lockstep_checksum<C: Component + Serialize>(&mut self);
+}
+
+impl AppLockstepExt for App {
+    fn add_lockstep_checksum<C: Component + Serialize>(&mut self) {
+        self.world_mut()
+            .get_resource_or_init::<LockstepChecksumFns>()
+            .0
+            .push(component_checksum::<C>);
+    }
+}
+
+/// Hash every instance of the component in the world.
+///
+/// The entities are not iterated in the same order on every client, so the hashes are summed.
+fn component_checksum<C: Component + Serialize>(world: &mut World) -> u64 {
+    let mut query = world.query::<&C>();
+    query.iter(world).fold(0u64, |checksum, component| {
+        let mut hasher = seahash::SeaHasher::new();
+        hasher.write(type_name::<C>().as_bytes());
+        match bincode::serde::encode_to_vec(component, bincode::config::standard()) {
+            Ok(bytes) => hasher.write(&bytes),
+            Err(e) => error!(
+                "could not serialize {} for the lockstep checksum: {:?}",
+                type_name::<C>(),
+                e
+            ),
+        }
+        checksum.wrapping_add(hasher.finish())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::lockstep::{LockstepManager, LockstepSet};
+    use crate::prelude::client::{InterpolationConfig, PredictionConfig, SyncConfig};
+    use crate::prelude::{SharedConfig, TickConfig};
+    use crate::server::lockstep::LockstepDesyncEvent;
+    use crate::shared::events::components::InputEvent;
+    use crate::tests::multi_stepper::MultiBevyStepper;
+
+    #[derive(Component, Serialize, Deserialize, Debug, PartialEq)]
+    struct Counter(i64);
+
+    fn buffer_input(mut manager: ResMut<LockstepManager<i64>>) {
+        manager.add_input(1);
+    }
+
+    fn simulate(
+        mut inputs: EventReader<InputEvent<i64, ClientId>>,
+        mut query: Query<&mut Counter>,
+    ) {
+        for event in inputs.read() {
+            if let Some(input) = event.input() {
+                for mut counter in query.iter_mut() {
+                    counter.0 += input;
+                }
+            }
+        }
+    }
+
+    fn setup(config: LockstepConfig) -> MultiBevyStepper {
+        let frame_duration = Duration::from_millis(10);
+        let shared_config = SharedConfig {
+            tick: TickConfig::new(frame_duration),
+            ..default()
+        };
+        let mut stepper = MultiBevyStepper::new(
+            shared_config,
+            SyncConfig::default(),
+            PredictionConfig::default(),
+            InterpolationConfig::default(),
+            frame_duration,
+        );
+        stepper
+            .server_app
+            .add_plugins(LockstepPlugin::<i64>::new(config));
+        for app in [&mut stepper.client_app_1, &mut stepper.client_app_2] {
+            app.add_plugins(LockstepPlugin::<i64>::new(config));
+            app.add_lockstep_checksum::<Counter>();
+            app.add_systems(
+                FixedPreUpdate,
+                buffer_input.in_set(LockstepSet::BufferInputs),
+            );
+            app.add_systems(LockstepUpdate, simulate);
+            app.world_mut().spawn(Counter(0));
+        }
+        stepper.build();
+        stepper.init();
+        stepper
+    }
+
+    fn last_simulated_tick(app: &App) -> Option<Tick> {
+        app.world()
+            .resource::<LockstepManager<i64>>()
+            .last_simulated_tick()
+    }
+
+    /// Two clients that receive the same inputs run the same simulation, and compute the same
+    /// checksum for every tick
+    #[test]
+    fn test_lockstep_checksums_match() {
+        let mut stepper = setup(LockstepConfig::default());
+        for _ in 0..50 {
+            stepper.frame_step();
+        }
+        let last_tick_1 = last_simulated_tick(&stepper.client_app_1).unwrap();
+        let last_tick_2 = last_simulated_tick(&stepper.client_app_2).unwrap();
+        let last_tick = if last_tick_1 < last_tick_2 {
+            last_tick_1
+        } else {
+            last_tick_2
+        };
+        let manager_1 = stepper
+            .client_app_1
+            .world()
+            .resource::<LockstepManager<i64>>();
+        let manager_2 = stepper
+            .client_app_2
+            .world()
+            .resource::<LockstepManager<i64>>();
+        let mut compared = 0;
+        for i in 0..20i16 {
+            let tick = last_tick + (-i);
+            let (Some(checksum_1), Some(checksum_2)) =
+                (manager_1.checksum(tick), manager_2.checksum(tick))
+            else {
+                continue;
+            };
+            assert_eq!(checksum_1, checksum_2, "checksums differ at {tick:?}");
+            compared += 1;
+        }
+        assert!(
+            compared >= 10,
+            "only {compared} ticks were simulated by both clients"
+        );
+        assert!(stepper
+            .server_app
+            .world()
+            .resource::<Events<LockstepDesyncEvent>>()
+            .is_empty());
+
+        // both clients applied the inputs of both clients
+        let counter = stepper
+            .client_app_1
+            .world_mut()
+            .query::<&Counter>()
+            .single(stepper.client_app_1.world())
+            .0;
+        assert!(counter > 0 && counter % 2 == 0);
+    }
+
+    /// A client that stops sending inputs does not stall the simulation of the other clients
+    #[test]
+    fn test_lockstep_input_timeout() {
+        let mut stepper =
+            setup(LockstepConfig::default().with_input_timeout(Duration::from_millis(100)));
+        for _ in 0..20 {
+            stepper.frame_step();
+        }
+        let stalled_tick = last_simulated_tick(&stepper.client_app_1).unwrap();
+
+        // only update the server and the first client
+        for _ in 0..50 {
+            stepper.advance_time(stepper.frame_duration);
+            stepper.client_app_1.update();
+            std::thread::sleep(Duration::from_millis(1));
+            stepper.server_app.update();
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        let last_tick = last_simulated_tick(&stepper.client_app_1).unwrap();
+        assert!(
+            last_tick - stalled_tick >= 30,
+            "the simulation only advanced from {stalled_tick:?} to {last_tick:?}"
+        );
+    }
+}
@@ -16,6 +16,7 @@ pub mod tick_manager;
 
 pub mod identity;
 pub mod input;
+pub mod lockstep;
 pub(crate) mod message;
 pub mod run_conditions;
 pub mod time_manager;
@@ -19,6 +19,11 @@ pub struct PingConfig {
     /// Duration of the rolling buffer of stats to compute RTT/jitter
     /// NOTE: this must be high enough to have received enough pongs to sync
     pub stats_buffer_duration: Duration,
+    /// Fixed round-trip delay that is added to every RTT measurement.
+    ///
+    /// This is mostly useful for tests: the sync and prediction logic will behave exactly as if the
+    /// connection had this extra latency, without having to delay the packets with a link conditioner.
+    pub synthetic_rtt: Duration,
 }
 
 impl Default for PingConfig {
@@ -26,6 +31,7 @@ impl Default for PingConfig {
         PingConfig {
             ping_interval: Duration::from_millis(100),
             stats_buffer_duration: Duration::from_secs(4),
+            synthetic_rtt: Duration::default(),
         }
     }
 }
@@ -241,7 +247,8 @@ impl PingManager {
             // info!(pong_sent_time = ?pong.pong_sent_time, ping_received_time = ?pong.ping_received_time, "server process time");
             let server_process_time = pong.pong_sent_time - pong.ping_received_time;
             trace!(?rtt, ?received_time, ?ping_sent_time, ?server_process_time, ?pong.pong_sent_time, ?pong.ping_received_time, "process pong");
-            let round_trip_delay = (rtt - server_process_time).to_std().unwrap_or_default();
+            let round_trip_delay = (rtt - server_process_time).to_std().unwrap_or_default()
+                + self.config.synthetic_rtt;
 
             // update stats buffer
             self.sync_stats
@@ -282,6 +289,7 @@ mod tests {
         let config = PingConfig {
             ping_interval: Duration::from_millis(100),
             stats_buffer_duration: Duration::from_secs(4),
+            ..PingConfig::default()
         };
         let mut ping_manager = PingManager::new(config);
         let mut time_manager = TimeManager::default();
@@ -1,19 +1,26 @@
 //! Bevy [`Plugin`] used by both the server and the client
 use crate::client::config::ClientConfig;
+use crate::packet::compression::CompressionConfig;
 use crate::prelude::client::ComponentSyncMode;
 use crate::prelude::{
     client, server, AppComponentExt, AppMessageExt, ChannelDirection, ChannelRegistry,
     ComponentRegistry, LinkConditionerConfig, MessageRegistry, NetworkIdentityState, ParentSync,
     PingConfig, PrePredicted, PreSpawnedPlayerObject, ShouldBePredicted, TickConfig,
 };
+use crate::server::shutdown::ServerShutdown;
 use crate::shared::config::SharedConfig;
+use crate::shared::identity::RelayState;
 use crate::shared::plugin::utils::AppStateExt;
-use crate::shared::replication::authority::AuthorityChange;
-use crate::shared::replication::components::{Controlled, ShouldBeInterpolated};
+use crate::shared::replication::authority::{
+    AuthorityChange, AuthorityRequest, AuthorityTransferAck, AuthorityTransferEvent,
+};
+use crate::shared::replication::components::{Controlled, NetworkId, ShouldBeInterpolated};
+use crate::shared::replication::initial::{InitialReplication, InterpolationSnapshots};
+use crate::shared::replication::ready::EntityReady;
+use crate::shared::replication::subscription::ComponentSubscription;
 use crate::shared::tick_manager::TickManagerPlugin;
 use crate::shared::time_manager::TimePlugin;
 use crate::transport::io::{IoState, IoStats};
-use crate::transport::middleware::compression::CompressionConfig;
 use bevy::prelude::*;
 use bevy::utils::Duration;
 
@@ -51,6 +58,9 @@ impl Plugin for SharedPlugin {
             self.config.tick.tick_duration.as_secs_f64(),
         ));
 
+        // EVENTS
+        app.add_event::<AuthorityTransferEvent>();
+
         // PLUGINS
         // we always keep running the tick_manager and time_manager even the client or server are stopped
         app.add_plugins(TickManagerPlugin {
@@ -78,6 +88,9 @@ impl Plugin for SharedPlugin {
         // we need to include both client and server networking states so that the NetworkIdentity ComputedState can be computed correctly
         app.init_state_without_entering(client::NetworkingState::Disconnected);
         app.init_state_without_entering(server::NetworkingState::Stopped);
+        // the RelayState is only inserted by the RelayPlugin, but its transition events must exist
+        // for the NetworkIdentity ComputedState to be computed
+        app.add_event::<bevy::state::state::StateTransitionEvent<RelayState>>();
         app.add_sub_state::<client::ConnectedState>();
         app.add_computed_state::<NetworkIdentityState>();
         // PROTOCOL
@@ -101,6 +114,25 @@ impl Plugin for SharedPlugin {
 
         app.register_message::<AuthorityChange>(ChannelDirection::ServerToClient)
             .add_map_entities();
+        app.register_message::<EntityReady>(ChannelDirection::ClientToServer)
+            .add_map_entities();
+        app.register_message::<AuthorityTransferAck>(ChannelDirection::ClientToServer)
+            .add_map_entities();
+        app.register_message::<AuthorityRequest>(ChannelDirection::ClientToServer)
+            .add_map_entities();
+        app.register_message::<InitialReplication>(ChannelDirection::ServerToClient);
+        app.register_message::<InterpolationSnapshots>(ChannelDirection::ServerToClient);
+        app.register_message::<ServerShutdown>(ChannelDirection::ServerToClient);
+        app.register_message::<ComponentSubscription>(ChannelDirection::ClientToServer)
+            .add_map_entities();
+    }
+
+    fn cleanup(&self, app: &mut App) {
+        // NetworkId is registered after the components of every plugin's `finish`, so that
+        // it is the last component of the protocol and doesn't shift the network ids of the other components
+        app.register_component::<NetworkId>(ChannelDirection::ServerToClient)
+            .add_prediction(ComponentSyncMode::Once)
+            .add_interpolation(ComponentSyncMode::Once);
 
         // check that the protocol was built correctly
         app.world().resource::<ComponentRegistry>().check();
@@ -199,6 +199,16 @@ impl<C: Component> ReplicatedArchetypes<C> {
                         );
                         return;
                     }
+                    // clients never send the components that are controlled by the server
+                    if self.has_authority_component_id.is_some()
+                        && registry.is_server_authoritative(kind)
+                    {
+                        trace!(
+                            "not including {:?} because it is server-authoritative",
+                            info.name()
+                        );
+                        return;
+                    }
                     trace!("including {:?} in replicated components", info.name());
 
                     // check per component metadata
@@ -6,9 +6,10 @@
 //! In this case C1 has authority even though the server is still replicating some states.
 //!
 
-use crate::prelude::{ClientId, Deserialize, Serialize};
+use crate::prelude::{ClientId, Deserialize, Serialize, Tick};
 use bevy::ecs::entity::MapEntities;
 use bevy::prelude::*;
+use std::collections::VecDeque;
 
 /// Authority is used to define who is in charge of simulating an entity.
 ///
@@ -20,27 +21,203 @@ use bevy::prelude::*;
 #[reflect(Component)]
 pub struct HasAuthority;
 
+/// Peer that has authority over an entity, as tracked by the server.
 #[derive(
     Component, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default, Reflect,
 )]
 #[reflect(Component)]
 pub enum AuthorityPeer {
+    /// No peer is simulating the entity: the entity is frozen.
+    ///
+    /// - no peer has the [`HasAuthority`] component, so systems that only simulate entities
+    ///   `With<HasAuthority>` (for example the systems applying inputs) leave the entity untouched
+    /// - the server rejects replication updates for the entity from every client
+    /// - the entity keeps the last state that was replicated from the previous authority
     None,
+    /// The server simulates the entity and replicates it to the clients
     #[default]
     Server,
+    /// The client simulates the entity; the server only accepts replication updates for
+    /// the entity from that client
     Client(ClientId),
+    // NOTE: there is no `Shared` authority where several clients propose changes that the server
+    //  merges or validates. Clients only send replication updates for the entities they have
+    //  authority over, and ignore the server updates for those entities, so this would require a
+    //  separate proposal path. The closest behaviour is an `AuthorityConflictPolicy` that lets the
+    //  server accept updates from several clients.
+}
+
+/// How the server handles the replication updates for an entity that are received from a client
+/// that doesn't have authority over it.
+///
+/// This can happen during an authority handoff, when two clients both believe that they have authority
+/// over the entity and send conflicting updates. Add this component on the server entity to choose which
+/// updates are applied. Rejected updates emit an [`AuthorityViolation`](crate::prelude::server::AuthorityViolation)
+/// event on the server.
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+pub enum AuthorityConflictPolicy {
+    /// Only accept the updates from the [`AuthorityPeer`] chosen by the server; the updates
+    /// from every other client are rejected
+    #[default]
+    ServerArbitrated,
+    /// Accept the updates from every client: the last update received overwrites the previous ones
+    LastWriterWins,
+    /// When a client has authority over the entity, also accept the updates from clients with a higher
+    /// [`ClientId`], so that the client with the highest id wins the conflicts.
+    /// The updates from the other clients are rejected
+    HighestClientId,
+}
+
+impl AuthorityConflictPolicy {
+    /// Returns true if the server accepts an update from `client_id` for an entity
+    /// whose current authority is `authority`
+    pub(crate) fn accepts(&self, authority: Option<&AuthorityPeer>, client_id: ClientId) -> bool {
+        match (self, authority) {
+            (_, Some(AuthorityPeer::Client(c))) if *c == client_id => true,
+            (Self::LastWriterWins, _) => true,
+            (Self::HighestClientId, Some(AuthorityPeer::Client(c))) => client_id > *c,
+            _ => false,
+        }
+    }
+}
+
+/// Tick-versioned authority of an entity.
+///
+/// Records the tick at which each authority change took effect, so that we can retrieve the authority
+/// that was in effect at a past tick. This is used during rollback: each replayed tick uses the
+/// authority that was in effect at that tick, even if the rollback crosses an authority change.
+///
+/// It is added on the server entity when the authority is transferred, and on the client's `Confirmed`
+/// and `Predicted` entities when the client receives the authority change.
+#[derive(Component, Debug, Clone, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct AuthorityHistory {
+    /// Authority before the oldest recorded change
+    initial: AuthorityPeer,
+    /// Authority changes, ordered by tick
+    changes: VecDeque<(Tick, AuthorityPeer)>,
+}
+
+impl AuthorityHistory {
+    /// Maximum number of authority changes that are kept in the history
+    const MAX_LEN: usize = 16;
+
+    pub fn new(initial: AuthorityPeer) -> Self {
+        Self {
+            initial,
+            changes: VecDeque::new(),
+        }
+    }
+
+    /// Authority that is in effect at the given tick
+    pub fn authority_at(&self, tick: Tick) -> AuthorityPeer {
+        self.changes
+            .iter()
+            .rev()
+            .find(|(change_tick, _)| *change_tick <= tick)
+            .map_or(self.initial, |(_, peer)| *peer)
+    }
+
+    /// Latest authority of the entity
+    pub fn current(&self) -> AuthorityPeer {
+        self.changes.back().map_or(self.initial, |(_, peer)| *peer)
+    }
+
+    /// Record that the authority changed to `peer` at the given tick.
+    ///
+    /// Changes recorded at or after `tick` are replaced.
+    pub(crate) fn add(&mut self, tick: Tick, peer: AuthorityPeer) {
+        while self
+            .changes
+            .back()
+            .is_some_and(|(change_tick, _)| *change_tick >= tick)
+        {
+            self.changes.pop_back();
+        }
+        self.changes.push_back((tick, peer));
+        while self.changes.len() > Self::MAX_LEN {
+            if let Some((_, peer)) = self.changes.pop_front() {
+                self.initial = peer;
+            }
+        }
+    }
+
+    /// Record the authority change in the [`AuthorityHistory`] of the entity, creating the history if needed
+    pub(crate) fn record(
+        entity_mut: &mut EntityWorldMut,
+        from: AuthorityPeer,
+        to: AuthorityPeer,
+        tick: Tick,
+    ) {
+        if let Some(mut history) = entity_mut.get_mut::<AuthorityHistory>() {
+            history.add(tick, to);
+        } else {
+            let mut history = AuthorityHistory::new(from);
+            history.add(tick, to);
+            entity_mut.insert(history);
+        }
+    }
+}
+
+/// Authority transfer that will be applied by the server at a specific tick.
+///
+/// Added by [`transfer_authority_at`](crate::prelude::server::AuthorityCommandExt::transfer_authority_at).
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ScheduledAuthorityTransfer {
+    pub to: AuthorityPeer,
+    pub tick: Tick,
+}
+
+/// Bevy [`Event`] emitted when the authority over an entity changes.
+///
+/// On the server, the event is emitted with `acknowledged: false` when the authority is transferred to a client,
+/// and again with `acknowledged: true` once the client has confirmed that it took control of the entity.
+/// Transfers to the server or to no one are acknowledged immediately.
+/// If the client disconnects before acknowledging the transfer, the authority reverts to the server and a final
+/// event is emitted with `to: AuthorityPeer::Server`.
+///
+/// On the clients, the event is emitted when the authority change is received:
+/// - the client that gains authority emits it with `acknowledged: true`
+/// - the client that loses authority emits it with `acknowledged: false` if the new authority is another client,
+///   since it doesn't know when that client takes control of the entity
+///
+/// The peer that has authority should only start simulating the entity once the transfer is acknowledged,
+/// to avoid two peers simulating the entity during the handoff.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AuthorityTransferEvent {
+    pub entity: Entity,
+    pub from: AuthorityPeer,
+    pub to: AuthorityPeer,
+    pub acknowledged: bool,
+}
+
+/// Transfer of authority that the server sent to a client, and that the client hasn't acknowledged yet.
+///
+/// This component is present on the server entity until the transfer is acknowledged, so it can be used
+/// to avoid simulating the entity during the handoff.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PendingAuthorityTransfer {
+    pub from: AuthorityPeer,
+    pub to: AuthorityPeer,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
 pub(crate) struct AuthorityChange {
     pub entity: Entity,
     pub gain_authority: bool,
+    /// Previous authority of the entity
+    pub from: AuthorityPeer,
+    /// New authority of the entity
+    pub to: AuthorityPeer,
     /// Should we add prediction for that entity? This can be useful if the entity was originally
     /// spawned by a client C1, and then the authority was transferred away from that client.
     /// Now we want to start predicting the entity on client C1, but we cannot just rely on the normal
     /// systems because the entity already exists, and ShouldBePredicted only gets sent on the initial Spawn message
     pub add_prediction: bool,
     pub add_interpolation: bool,
+    /// Server tick at which the authority change took effect
+    pub tick: Tick,
 }
 
 impl MapEntities for AuthorityChange {
@@ -49,20 +226,177 @@ impl MapEntities for AuthorityChange {
     }
 }
 
+/// Message sent by a client to notify the server that it took control of an entity after gaining authority
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct AuthorityTransferAck {
+    pub entity: Entity,
+}
+
+impl MapEntities for AuthorityTransferAck {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        self.entity = entity_mapper.map_entity(self.entity);
+    }
+}
+
+/// Message sent by a client to ask the server for authority over an entity
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct AuthorityRequest {
+    pub entity: Entity,
+}
+
+impl MapEntities for AuthorityRequest {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        self.entity = entity_mapper.map_entity(self.entity);
+    }
+}
+
+/// Bevy [`Event`] emitted on the server when a client requests authority over an entity
+/// with [`request_authority`](crate::prelude::client::ClientCommandsExt::request_authority).
+///
+/// The server decides whether to grant the request, for example with
+/// `commands.entity(event.entity).transfer_authority(AuthorityPeer::Client(event.client_id))`,
+/// or to ignore it. If several clients request authority over the same entity on the same tick,
+/// one event is emitted for each request, and the game logic can pick a winner.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AuthorityRequestEvent {
+    pub client_id: ClientId,
+    /// The server entity
+    pub entity: Entity,
+}
+
 #[cfg(test)]
 mod tests {
+    use crate::client::networking::ClientCommandsExt;
     use crate::client::prediction::predicted_history::PredictionHistory;
     use crate::prelude::client::{Confirmed, ConfirmedHistory};
     use crate::prelude::server::{Replicate, SyncTarget};
+    use crate::prelude::Tick;
     use crate::prelude::{client, server, ClientId, NetworkTarget, Replicated};
     use crate::server::replication::commands::AuthorityCommandExt;
-    use crate::shared::replication::authority::{AuthorityPeer, HasAuthority};
+    use crate::shared::replication::authority::{
+        AuthorityConflictPolicy, AuthorityHistory, AuthorityPeer, AuthorityRequestEvent,
+        AuthorityTransferEvent, HasAuthority, PendingAuthorityTransfer,
+    };
     use crate::tests::multi_stepper::{MultiBevyStepper, TEST_CLIENT_ID_1, TEST_CLIENT_ID_2};
     use crate::tests::protocol::{
-        ComponentMapEntities, ComponentSyncModeFull, ComponentSyncModeSimple,
+        ComponentMapEntities, ComponentServerAuthoritative, ComponentSyncModeFull,
+        ComponentSyncModeSimple,
     };
     use crate::tests::stepper::{BevyStepper, TEST_CLIENT_ID};
-    use bevy::prelude::{default, Entity};
+    use bevy::prelude::*;
+
+    #[test]
+    fn test_authority_history() {
+        let client = AuthorityPeer::Client(ClientId::Netcode(TEST_CLIENT_ID));
+        let mut history = AuthorityHistory::new(AuthorityPeer::Server);
+        history.add(Tick(10), client);
+        history.add(Tick(20), AuthorityPeer::None);
+        assert_eq!(history.authority_at(Tick(5)), AuthorityPeer::Server);
+        assert_eq!(history.authority_at(Tick(10)), client);
+        assert_eq!(history.authority_at(Tick(19)), client);
+        assert_eq!(history.authority_at(Tick(25)), AuthorityPeer::None);
+        assert_eq!(history.current(), AuthorityPeer::None);
+
+        // a change at an earlier tick replaces the later changes
+        history.add(Tick(15), AuthorityPeer::Server);
+        assert_eq!(history.authority_at(Tick(12)), client);
+        assert_eq!(history.authority_at(Tick(25)), AuthorityPeer::Server);
+
+        // the oldest changes are dropped once the history is full
+        for i in 0..AuthorityHistory::MAX_LEN as u16 {
+            history.add(Tick(100 + i), client);
+        }
+        assert_eq!(history.authority_at(Tick(0)), AuthorityPeer::Server);
+        assert_eq!(history.authority_at(Tick(100)), client);
+    }
+
+    /// The authority is transferred at the requested tick, and the tick of the transfer
+    /// is recorded in the AuthorityHistory on the server and on the client
+    #[test]
+    fn test_transfer_authority_at_tick() {
+        let mut stepper = BevyStepper::default();
+        let client = AuthorityPeer::Client(ClientId::Netcode(TEST_CLIENT_ID));
+
+        let server_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn((server::Replicate::default(), ComponentSyncModeSimple(1.0)))
+            .id();
+        stepper.frame_step();
+        stepper.frame_step();
+        let client_entity = stepper
+            .client_app
+            .world()
+            .resource::<client::ConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(server_entity)
+            .expect("entity was not replicated to client");
+        stepper
+            .client_app
+            .world_mut()
+            .entity_mut(client_entity)
+            .insert(client::Replicate::default())
+            .remove::<HasAuthority>();
+
+        let transfer_tick = stepper.server_tick() + 3;
+        stepper
+            .server_app
+            .world_mut()
+            .commands()
+            .entity(server_entity)
+            .transfer_authority_at(client, transfer_tick);
+
+        // the authority is not transferred before the tick
+        while stepper.server_tick() < transfer_tick - 1 {
+            stepper.frame_step();
+            assert_eq!(
+                stepper
+                    .server_app
+                    .world()
+                    .get::<AuthorityPeer>(server_entity),
+                Some(&AuthorityPeer::Server)
+            );
+        }
+        stepper.frame_step();
+        assert_eq!(stepper.server_tick(), transfer_tick);
+        assert_eq!(
+            stepper
+                .server_app
+                .world()
+                .get::<AuthorityPeer>(server_entity),
+            Some(&client)
+        );
+        let history = stepper
+            .server_app
+            .world()
+            .get::<AuthorityHistory>(server_entity)
+            .unwrap();
+        assert_eq!(
+            history.authority_at(transfer_tick - 1),
+            AuthorityPeer::Server
+        );
+        assert_eq!(history.authority_at(transfer_tick), client);
+
+        // the client records the same tick for the transfer
+        stepper.frame_step();
+        stepper.frame_step();
+        assert!(stepper
+            .client_app
+            .world()
+            .get::<HasAuthority>(client_entity)
+            .is_some());
+        let history = stepper
+            .client_app
+            .world()
+            .get::<AuthorityHistory>(client_entity)
+            .unwrap();
+        assert_eq!(
+            history.authority_at(transfer_tick - 1),
+            AuthorityPeer::Server
+        );
+        assert_eq!(history.authority_at(transfer_tick), client);
+    }
 
     #[test]
     fn test_transfer_authority_server_to_client() {
@@ -408,6 +742,113 @@ mod tests {
         );
     }
 
+    /// Transfer authority from a client to None.
+    /// The last state replicated by the client is kept, and the server stops accepting updates
+    /// for the entity, even from a client that still believes it has authority
+    #[test]
+    fn test_authority_none_freezes_entity() {
+        let mut stepper = BevyStepper::default();
+
+        let client_entity = stepper
+            .client_app
+            .world_mut()
+            .spawn((client::Replicate::default(), ComponentSyncModeFull(1.0)))
+            .id();
+        for _ in 0..20 {
+            stepper.frame_step();
+        }
+        let server_entity = stepper
+            .server_app
+            .world()
+            .resource::<server::ConnectionManager>()
+            .connection(ClientId::Netcode(TEST_CLIENT_ID))
+            .expect("client connection missing")
+            .replication_receiver
+            .remote_entity_map
+            .get_local(client_entity)
+            .expect("entity was not replicated to server");
+        stepper
+            .server_app
+            .world_mut()
+            .entity_mut(server_entity)
+            .insert(server::Replicate {
+                authority: AuthorityPeer::Client(ClientId::Netcode(TEST_CLIENT_ID)),
+                ..default()
+            });
+
+        // the client has authority: its updates are accepted
+        stepper
+            .client_app
+            .world_mut()
+            .get_mut::<ComponentSyncModeFull>(client_entity)
+            .unwrap()
+            .0 = 2.0;
+        for _ in 0..20 {
+            stepper.frame_step();
+        }
+        assert_eq!(
+            stepper
+                .server_app
+                .world()
+                .get::<ComponentSyncModeFull>(server_entity)
+                .unwrap()
+                .0,
+            2.0
+        );
+
+        // transfer authority to None
+        stepper
+            .server_app
+            .world_mut()
+            .commands()
+            .entity(server_entity)
+            .transfer_authority(AuthorityPeer::None);
+        stepper.frame_step();
+        stepper.frame_step();
+        assert_eq!(
+            stepper
+                .server_app
+                .world()
+                .get::<AuthorityPeer>(server_entity),
+            Some(&AuthorityPeer::None)
+        );
+        assert!(stepper
+            .server_app
+            .world()
+            .get::<HasAuthority>(server_entity)
+            .is_none());
+        assert!(stepper
+            .client_app
+            .world()
+            .get::<HasAuthority>(client_entity)
+            .is_none());
+
+        // even if the client still sends updates, the server ignores them
+        stepper
+            .client_app
+            .world_mut()
+            .entity_mut(client_entity)
+            .insert(HasAuthority);
+        stepper
+            .client_app
+            .world_mut()
+            .get_mut::<ComponentSyncModeFull>(client_entity)
+            .unwrap()
+            .0 = 3.0;
+        for _ in 0..20 {
+            stepper.frame_step();
+        }
+        assert_eq!(
+            stepper
+                .server_app
+                .world()
+                .get::<ComponentSyncModeFull>(server_entity)
+                .unwrap()
+                .0,
+            2.0
+        );
+    }
+
     /// Spawn on client, transfer authority to server
     /// Update on server, the updates from the server use entity mapping on the send side.
     /// (both for the Entity in Updates and for the content of the components in the Update)
@@ -1029,4 +1470,539 @@ mod tests {
             2.0
         );
     }
+
+    #[derive(Resource, Default)]
+    struct TransferEvents(Vec<AuthorityTransferEvent>);
+
+    fn record_transfer_events(
+        mut reader: EventReader<AuthorityTransferEvent>,
+        mut events: ResMut<TransferEvents>,
+    ) {
+        events.0.extend(reader.read().copied());
+    }
+
+    fn record_transfer_events_on(app: &mut App) {
+        app.init_resource::<TransferEvents>();
+        app.add_systems(Last, record_transfer_events);
+    }
+
+    /// The server emits an unacknowledged event when the transfer starts, and an acknowledged event
+    /// once the client has taken control of the entity
+    #[test]
+    fn test_authority_transfer_event() {
+        let mut stepper = BevyStepper::default();
+        record_transfer_events_on(&mut stepper.server_app);
+        record_transfer_events_on(&mut stepper.client_app);
+        let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+
+        let server_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn((server::Replicate::default(), ComponentSyncModeSimple(1.0)))
+            .id();
+        stepper.frame_step();
+        stepper.frame_step();
+        let client_entity = stepper
+            .client_app
+            .world()
+            .resource::<client::ConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(server_entity)
+            .expect("entity was not replicated to client");
+        stepper
+            .client_app
+            .world_mut()
+            .entity_mut(client_entity)
+            .insert(client::Replicate::default())
+            .remove::<HasAuthority>();
+
+        stepper
+            .server_app
+            .world_mut()
+            .commands()
+            .entity(server_entity)
+            .transfer_authority(AuthorityPeer::Client(client_id));
+        stepper.flush();
+        // the transfer is pending until the client acknowledges it
+        assert!(stepper
+            .server_app
+            .world()
+            .get::<PendingAuthorityTransfer>(server_entity)
+            .is_some());
+        for _ in 0..5 {
+            stepper.frame_step();
+        }
+
+        assert!(stepper
+            .server_app
+            .world()
+            .get::<PendingAuthorityTransfer>(server_entity)
+            .is_none());
+        assert_eq!(
+            stepper.server_app.world().resource::<TransferEvents>().0,
+            vec![
+                AuthorityTransferEvent {
+                    entity: server_entity,
+                    from: AuthorityPeer::Server,
+                    to: AuthorityPeer::Client(client_id),
+                    acknowledged: false,
+                },
+                AuthorityTransferEvent {
+                    entity: server_entity,
+                    from: AuthorityPeer::Server,
+                    to: AuthorityPeer::Client(client_id),
+                    acknowledged: true,
+                },
+            ]
+        );
+        assert_eq!(
+            stepper.client_app.world().resource::<TransferEvents>().0,
+            vec![AuthorityTransferEvent {
+                entity: client_entity,
+                from: AuthorityPeer::Server,
+                to: AuthorityPeer::Client(client_id),
+                acknowledged: true,
+            }]
+        );
+
+        // transfers to the server are acknowledged immediately
+        stepper
+            .server_app
+            .world_mut()
+            .commands()
+            .entity(server_entity)
+            .transfer_authority(AuthorityPeer::Server);
+        for _ in 0..5 {
+            stepper.frame_step();
+        }
+        assert_eq!(
+            stepper
+                .server_app
+                .world()
+                .resource::<TransferEvents>()
+                .0
+                .last(),
+            Some(&AuthorityTransferEvent {
+                entity: server_entity,
+                from: AuthorityPeer::Client(client_id),
+                to: AuthorityPeer::Server,
+                acknowledged: true,
+            })
+        );
+        assert_eq!(
+            stepper
+                .client_app
+                .world()
+                .resource::<TransferEvents>()
+                .0
+                .last(),
+            Some(&AuthorityTransferEvent {
+                entity: client_entity,
+                from: AuthorityPeer::Client(client_id),
+                to: AuthorityPeer::Server,
+                acknowledged: true,
+            })
+        );
+    }
+
+    /// If the client disconnects before acknowledging the transfer, the authority reverts to the server
+    #[test]
+    fn test_authority_transfer_reverts_on_disconnect() {
+        let mut stepper = BevyStepper::default();
+        record_transfer_events_on(&mut stepper.server_app);
+        let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+
+        let server_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn((server::Replicate::default(), ComponentSyncModeSimple(1.0)))
+            .id();
+        stepper.frame_step();
+        stepper.frame_step();
+
+        // the client disconnects before it receives the authority transfer
+        stepper
+            .server_app
+            .world_mut()
+            .commands()
+            .entity(server_entity)
+            .transfer_authority(AuthorityPeer::Client(client_id));
+        stepper.flush();
+        stepper.client_app.world_mut().disconnect_client();
+        for _ in 0..5 {
+            stepper.frame_step();
+        }
+
+        let world = stepper.server_app.world();
+        assert!(world.get::<HasAuthority>(server_entity).is_some());
+        assert!(world.get::<Replicated>(server_entity).is_none());
+        assert!(world
+            .get::<PendingAuthorityTransfer>(server_entity)
+            .is_none());
+        assert_eq!(
+            world.get::<AuthorityPeer>(server_entity),
+            Some(&AuthorityPeer::Server)
+        );
+        assert_eq!(
+            world.resource::<TransferEvents>().0,
+            vec![
+                AuthorityTransferEvent {
+                    entity: server_entity,
+                    from: AuthorityPeer::Server,
+                    to: AuthorityPeer::Client(client_id),
+                    acknowledged: false,
+                },
+                AuthorityTransferEvent {
+                    entity: server_entity,
+                    from: AuthorityPeer::Client(client_id),
+                    to: AuthorityPeer::Server,
+                    acknowledged: true,
+                },
+            ]
+        );
+    }
+
+    #[derive(Resource, Default)]
+    struct AuthorityRequests(Vec<AuthorityRequestEvent>);
+
+    fn record_authority_requests(
+        mut reader: EventReader<AuthorityRequestEvent>,
+        mut requests: ResMut<AuthorityRequests>,
+    ) {
+        requests.0.extend(reader.read().copied());
+    }
+
+    /// Two clients request authority over the same entity on the same tick: the server sees
+    /// both requests and grants the authority to one of them
+    #[test]
+    fn test_request_authority() {
+        let mut stepper = MultiBevyStepper::default();
+        stepper.server_app.init_resource::<AuthorityRequests>();
+        stepper
+            .server_app
+            .add_systems(Update, record_authority_requests);
+        let client_id_1 = ClientId::Netcode(TEST_CLIENT_ID_1);
+        let client_id_2 = ClientId::Netcode(TEST_CLIENT_ID_2);
+
+        let server_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn((server::Replicate::default(), ComponentSyncModeSimple(1.0)))
+            .id();
+        stepper.frame_step();
+        stepper.frame_step();
+        let client_entity_1 = stepper
+            .client_app_1
+            .world()
+            .resource::<client::ConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(server_entity)
+            .expect("entity was not replicated to client 1");
+        let client_entity_2 = stepper
+            .client_app_2
+            .world()
+            .resource::<client::ConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(server_entity)
+            .expect("entity was not replicated to client 2");
+
+        stepper
+            .client_app_1
+            .world_mut()
+            .request_authority(client_entity_1);
+        stepper
+            .client_app_2
+            .world_mut()
+            .request_authority(client_entity_2);
+        stepper.frame_step();
+        stepper.frame_step();
+
+        let requests = &stepper.server_app.world().resource::<AuthorityRequests>().0;
+        assert_eq!(requests.len(), 2);
+        assert!(requests.contains(&AuthorityRequestEvent {
+            client_id: client_id_1,
+            entity: server_entity,
+        }));
+        assert!(requests.contains(&AuthorityRequestEvent {
+            client_id: client_id_2,
+            entity: server_entity,
+        }));
+
+        // the game logic picks client 2 as the winner
+        stepper
+            .client_app_2
+            .world_mut()
+            .entity_mut(client_entity_2)
+            .insert(client::Replicate::default());
+        stepper
+            .server_app
+            .world_mut()
+            .commands()
+            .entity(server_entity)
+            .transfer_authority(AuthorityPeer::Client(client_id_2));
+        stepper.flush();
+        stepper.frame_step();
+        stepper.frame_step();
+        assert!(stepper
+            .client_app_2
+            .world()
+            .get::<HasAuthority>(client_entity_2)
+            .is_some());
+        assert!(stepper
+            .client_app_1
+            .world()
+            .get::<HasAuthority>(client_entity_1)
+            .is_none());
+    }
+
+    /// The client has authority over the entity, but some components are server-authoritative.
+    /// - `ComponentSyncModeFull` is replicated from the client to the server
+    /// - `ComponentServerAuthoritative` is replicated from the server to the client
+    #[test]
+    fn test_server_authoritative_component() {
+        let mut stepper = BevyStepper::default();
+
+        let server_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn((
+                server::Replicate::default(),
+                ComponentSyncModeFull(1.0),
+                ComponentServerAuthoritative(1.0),
+            ))
+            .id();
+        stepper.frame_step();
+        stepper.frame_step();
+        let client_entity = stepper
+            .client_app
+            .world()
+            .resource::<client::ConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(server_entity)
+            .expect("entity was not replicated to client");
+
+        // transfer authority from server to client
+        stepper
+            .client_app
+            .world_mut()
+            .entity_mut(client_entity)
+            .insert(client::Replicate::default());
+        stepper
+            .server_app
+            .world_mut()
+            .commands()
+            .entity(server_entity)
+            .transfer_authority(AuthorityPeer::Client(ClientId::Netcode(TEST_CLIENT_ID)));
+        for _ in 0..5 {
+            stepper.frame_step();
+        }
+        assert!(stepper
+            .client_app
+            .world()
+            .get::<HasAuthority>(client_entity)
+            .is_some());
+
+        // the client updates both components: only the one that it controls is accepted by the server
+        stepper
+            .client_app
+            .world_mut()
+            .get_mut::<ComponentSyncModeFull>(client_entity)
+            .unwrap()
+            .0 = 2.0;
+        stepper
+            .client_app
+            .world_mut()
+            .get_mut::<ComponentServerAuthoritative>(client_entity)
+            .unwrap()
+            .0 = 2.0;
+        for _ in 0..10 {
+            stepper.frame_step();
+        }
+        assert_eq!(
+            stepper
+                .server_app
+                .world()
+                .get::<ComponentSyncModeFull>(server_entity)
+                .unwrap()
+                .0,
+            2.0
+        );
+        assert_eq!(
+            stepper
+                .server_app
+                .world()
+                .get::<ComponentServerAuthoritative>(server_entity)
+                .unwrap()
+                .0,
+            1.0
+        );
+
+        // the server updates both components: only the server-authoritative one is accepted by the client
+        stepper
+            .server_app
+            .world_mut()
+            .get_mut::<ComponentSyncModeFull>(server_entity)
+            .unwrap()
+            .0 = 3.0;
+        stepper
+            .server_app
+            .world_mut()
+            .get_mut::<ComponentServerAuthoritative>(server_entity)
+            .unwrap()
+            .0 = 3.0;
+        for _ in 0..10 {
+            stepper.frame_step();
+        }
+        assert_eq!(
+            stepper
+                .client_app
+                .world()
+                .get::<ComponentSyncModeFull>(client_entity)
+                .unwrap()
+                .0,
+            2.0
+        );
+        assert_eq!(
+            stepper
+                .client_app
+                .world()
+                .get::<ComponentServerAuthoritative>(client_entity)
+                .unwrap()
+                .0,
+            3.0
+        );
+    }
+
+    #[derive(Resource, Default)]
+    struct AuthorityViolations(Vec<server::AuthorityViolation>);
+
+    fn collect_authority_violations(
+        mut reader: EventReader<server::AuthorityViolation>,
+        mut violations: ResMut<AuthorityViolations>,
+    ) {
+        violations.0.extend(reader.read().copied());
+    }
+
+    /// A client that sends updates for an entity without having authority over it is rejected by the server,
+    /// which emits an `AuthorityViolation` event, unless the `AuthorityConflictPolicy` of the entity allows it
+    #[test]
+    fn test_authority_violation() {
+        let mut stepper = BevyStepper::default();
+        stepper.server_app.init_resource::<AuthorityViolations>();
+        stepper
+            .server_app
+            .add_systems(Update, collect_authority_violations);
+
+        let server_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn((server::Replicate::default(), ComponentSyncModeFull(1.0)))
+            .id();
+        stepper.frame_step();
+        stepper.frame_step();
+        let client_entity = stepper
+            .client_app
+            .world()
+            .resource::<client::ConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(server_entity)
+            .expect("entity was not replicated to client");
+
+        // the client starts replicating the entity without having been given authority
+        stepper
+            .client_app
+            .world_mut()
+            .entity_mut(client_entity)
+            .insert(client::Replicate::default());
+        stepper
+            .client_app
+            .world_mut()
+            .get_mut::<ComponentSyncModeFull>(client_entity)
+            .unwrap()
+            .0 = 2.0;
+        for _ in 0..10 {
+            stepper.frame_step();
+        }
+        assert_eq!(
+            stepper
+                .server_app
+                .world()
+                .get::<ComponentSyncModeFull>(server_entity)
+                .unwrap()
+                .0,
+            1.0
+        );
+        assert!(stepper
+            .server_app
+            .world()
+            .resource::<AuthorityViolations>()
+            .0
+            .contains(&server::AuthorityViolation {
+                client_id: ClientId::Netcode(TEST_CLIENT_ID),
+                entity: server_entity,
+            }));
+
+        // with LastWriterWins, the updates of the client are accepted
+        stepper
+            .server_app
+            .world_mut()
+            .entity_mut(server_entity)
+            .insert(AuthorityConflictPolicy::LastWriterWins);
+        stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<AuthorityViolations>()
+            .0
+            .clear();
+        stepper
+            .client_app
+            .world_mut()
+            .get_mut::<ComponentSyncModeFull>(client_entity)
+            .unwrap()
+            .0 = 3.0;
+        for _ in 0..10 {
+            stepper.frame_step();
+        }
+        assert_eq!(
+            stepper
+                .server_app
+                .world()
+                .get::<ComponentSyncModeFull>(server_entity)
+                .unwrap()
+                .0,
+            3.0
+        );
+        assert!(stepper
+            .server_app
+            .world()
+            .resource::<AuthorityViolations>()
+            .0
+            .is_empty());
+    }
+
+    #[test]
+    fn test_authority_conflict_policy() {
+        let client_1 = ClientId::Netcode(TEST_CLIENT_ID_1);
+        let client_2 = ClientId::Netcode(TEST_CLIENT_ID_2);
+        let authority = AuthorityPeer::Client(client_1);
+        for policy in [
+            AuthorityConflictPolicy::ServerArbitrated,
+            AuthorityConflictPolicy::LastWriterWins,
+            AuthorityConflictPolicy::HighestClientId,
+        ] {
+            assert!(policy.accepts(Some(&authority), client_1));
+        }
+        assert!(!AuthorityConflictPolicy::ServerArbitrated.accepts(Some(&authority), client_2));
+        assert!(AuthorityConflictPolicy::LastWriterWins.accepts(Some(&authority), client_2));
+        assert!(AuthorityConflictPolicy::HighestClientId.accepts(Some(&authority), client_2));
+        assert!(!AuthorityConflictPolicy::HighestClientId
+            .accepts(Some(&AuthorityPeer::Client(client_2)), client_1));
+        assert!(!AuthorityConflictPolicy::HighestClientId
+            .accepts(Some(&AuthorityPeer::Server), client_2));
+    }
 }
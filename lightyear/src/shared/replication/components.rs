@@ -10,6 +10,7 @@ use crate::protocol::component::ComponentKind;
 use crate::serialize::reader::Reader;
 use crate::serialize::{SerializationError, ToBytes};
 use crate::shared::replication::network_target::NetworkTarget;
+use crate::shared::tick_manager::Tick;
 
 /// Marker component that indicates that the entity was initially spawned via replication
 /// (it was being replicated from a remote world)
@@ -62,6 +63,18 @@ pub struct Controlled;
 #[reflect(Component)]
 pub struct Replicating;
 
+/// Identifier of a replicated entity that is the same on the server and on the clients
+/// (unlike the [`Entity`], which is different in each world).
+///
+/// It is allocated by the server when the entity starts being replicated, if enabled via
+/// [`NetworkIdConfig`](crate::prelude::server::NetworkIdConfig), and then replicated to the clients.
+///
+/// It is registered as the last component of the protocol, so the client and the server must both
+/// use a version of lightyear that registers it.
+#[derive(Component, Clone, Copy, PartialEq, Eq, Hash, Debug, Reflect, Serialize, Deserialize)]
+#[reflect(Component)]
+pub struct NetworkId(pub u64);
+
 /// Keeps track of the last known state of a component, so that we can compute
 /// the delta between the old and new state.
 #[derive(Component, Clone, Debug, PartialEq, Reflect)]
@@ -242,6 +255,26 @@ pub enum ReplicationGroupIdBuilder {
 ///
 /// If multiple entities are part of the same replication group, they will be sent together in the same message.
 /// It is guaranteed that these entities will be updated at the same time on the remote world.
+///
+/// # Atomicity
+///
+/// For a given tick, the entity actions (spawns, despawns, component inserts and removals) of a group are sent in a
+/// single reliable message, and the component updates of the group are sent in a single unreliable message.
+/// The receiver guarantees that:
+/// - each message is applied all-or-nothing: a message that is fragmented across several packets is only read once
+///   all its fragments are received, and all of it is applied at the same time.
+/// - the actions messages of a group are applied in order.
+/// - an updates message is never applied before the actions it depends on: it stays buffered until the receiver
+///   has applied all the actions of the group that were sent before it.
+///
+/// The actions and the updates of the same tick are not applied together: if the updates message is lost, the
+/// actions of that tick are still applied, without the updates.
+///
+/// For example, if the `Position` of entity A is updated on the same tick where a component is inserted on entity B,
+/// and A and B share a group, the receiver will never observe the new `Position` of A without the inserted component on B;
+/// but it can observe the inserted component on B without the new `Position` of A.
+///
+/// To put several entities in the same group, use the same id with [`ReplicationGroup::new_id`].
 #[derive(Component, Debug, Clone, PartialEq, Reflect)]
 #[reflect(Component)]
 pub struct ReplicationGroup {
@@ -265,6 +298,8 @@ pub struct ReplicationGroup {
     /// This is to avoid having to track the send_tick for each replication group separately)
     // TODO: maybe buffer the updates exactly at 30ms, 60ms, 90ms and include the send_tick in the message?
     pub(crate) should_send: bool,
+    /// Tick at which the updates of the entity were last buffered, if the entity has a [`ReplicationInterval`]
+    pub(crate) interval_send_tick: Option<Tick>,
 }
 
 impl Default for ReplicationGroup {
@@ -274,6 +309,7 @@ impl Default for ReplicationGroup {
             base_priority: 1.0,
             send_frequency: None,
             should_send: true,
+            interval_send_tick: None,
         }
     }
 }
@@ -285,6 +321,7 @@ impl ReplicationGroup {
             base_priority: 1.0,
             send_frequency: None,
             should_send: true,
+            interval_send_tick: None,
         }
     }
 
@@ -294,6 +331,7 @@ impl ReplicationGroup {
             base_priority: 1.0,
             send_frequency: None,
             should_send: true,
+            interval_send_tick: None,
         }
     }
 
@@ -306,6 +344,14 @@ impl ReplicationGroup {
         }
     }
 
+    /// Returns the id of the group, or `None` if the group id is derived from the entity
+    pub fn id(&self) -> Option<u64> {
+        match self.id_builder {
+            ReplicationGroupIdBuilder::FromEntity => None,
+            ReplicationGroupIdBuilder::Group(id) => Some(id),
+        }
+    }
+
     pub(crate) fn priority(&self) -> f32 {
         self.base_priority
     }
@@ -334,6 +380,42 @@ impl ReplicationGroup {
     }
 }
 
+/// Component to specify the replication priority of an entity.
+///
+/// When the bandwidth cap is enabled (see `PacketConfig::enable_bandwidth_cap`), every client has a send budget.
+/// Each replication group accumulates its priority every time the replication messages are buffered,
+/// and the messages with the highest accumulated priority are sent first, within the budget.
+/// The accumulated priority of a group is reset once its message is actually sent.
+///
+/// An entity with a low priority is therefore never starved: if its messages are not sent,
+/// its accumulated priority keeps growing until it exceeds the priority of the other entities.
+///
+/// The priority applies to the whole [`ReplicationGroup`] of the entity, and takes precedence over
+/// [`ReplicationGroup::set_priority`]. Entities without this component use the priority of their group (1.0 by default).
+#[derive(Component, Debug, Clone, Copy, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct ReplicationPriority(pub f32);
+
+impl Default for ReplicationPriority {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
+/// Component to send the replication updates of an entity only every `N` ticks, independently from
+/// the tick rate of the simulation.
+///
+/// For example with a 64Hz tick rate, `ReplicationInterval(3)` sends the updates of the entity at about 20Hz;
+/// the remote interpolates across the wider gaps. The updates are sent as soon as the entity starts being
+/// replicated, and then every `N` ticks.
+///
+/// The default interval for all the entities is set with [`ReplicationConfig::replication_interval`](crate::prelude::ReplicationConfig::replication_interval);
+/// this component overrides it. It takes precedence over [`ReplicationGroup::set_send_frequency`].
+/// An interval of 0 or 1 sends the updates every time the replication messages are sent.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+pub struct ReplicationInterval(pub u16);
+
 #[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, Reflect)]
 pub struct ReplicationGroupId(pub u64);
 
@@ -355,6 +437,66 @@ impl ToBytes for ReplicationGroupId {
     }
 }
 
+/// Reason why a replicated entity was despawned, sent to the remote along with the despawn.
+///
+/// Insert this component on an entity before despawning it (or use
+/// [`despawn_with_reason`](crate::prelude::server::DespawnReplicationCommandExt::despawn_with_reason))
+/// so that the remote can react differently depending on the cause of the despawn.
+/// The reason is available on the remote in the [`EntityDespawnEvent`](crate::shared::events::components::EntityDespawnEvent).
+#[derive(Component, Clone, Copy, Default, Debug, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+pub enum DespawnReason {
+    /// No reason was provided
+    #[default]
+    Unspecified,
+    /// The client that controlled the entity disconnected
+    OwnerDisconnected,
+    /// The entity was destroyed by the game logic
+    Destroyed,
+    /// The entity is not relevant anymore to the remote (it lost visibility of the entity, or
+    /// is not part of the replication target anymore). The entity still exists on the sender.
+    OutOfView,
+    /// Application-specific reason
+    Custom(u16),
+}
+
+impl ToBytes for DespawnReason {
+    fn len(&self) -> usize {
+        match self {
+            DespawnReason::Custom(_) => 3,
+            _ => 1,
+        }
+    }
+
+    fn to_bytes<T: WriteBytesExt>(&self, buffer: &mut T) -> Result<(), SerializationError> {
+        match self {
+            DespawnReason::Unspecified => buffer.write_u8(0)?,
+            DespawnReason::OwnerDisconnected => buffer.write_u8(1)?,
+            DespawnReason::Destroyed => buffer.write_u8(2)?,
+            DespawnReason::OutOfView => buffer.write_u8(3)?,
+            DespawnReason::Custom(code) => {
+                buffer.write_u8(4)?;
+                buffer.write_u16::<NetworkEndian>(*code)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn from_bytes(buffer: &mut Reader) -> Result<Self, SerializationError>
+    where
+        Self: Sized,
+    {
+        match buffer.read_u8()? {
+            0 => Ok(DespawnReason::Unspecified),
+            1 => Ok(DespawnReason::OwnerDisconnected),
+            2 => Ok(DespawnReason::Destroyed),
+            3 => Ok(DespawnReason::OutOfView),
+            4 => Ok(DespawnReason::Custom(buffer.read_u16::<NetworkEndian>()?)),
+            _ => Err(SerializationError::InvalidPacketType),
+        }
+    }
+}
+
 #[derive(Component, Clone, Copy, Default, Debug, PartialEq, Reflect)]
 #[reflect(Component)]
 pub enum NetworkRelevanceMode {
@@ -0,0 +1,133 @@
+//! Compute the replication messages without sending them
+//!
+//! When the [`ReplicationDryRun`] resource is present on the server, all the replication decisions
+//! (relevance, replication targets, priorities, serialization) are made as usual, but the resulting
+//! replication messages are recorded in the resource instead of being sent to the clients.
+//!
+//! This is useful to validate a new relevance configuration or to estimate the bandwidth usage of
+//! a scene without affecting the clients.
+//!
+//! The replication state of the server considers that the recorded messages were sent, so the clients
+//! will be out of sync with the server if the resource is removed during a live session.
+use bevy::prelude::{Entity, Resource};
+use bevy::utils::HashMap;
+
+use crate::prelude::{ClientId, Tick};
+use crate::shared::replication::components::ReplicationGroupId;
+
+/// Type of replication message that would have been sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DryRunMessageKind {
+    /// Entity actions (spawn, despawn, component inserts and removals), sent reliably
+    Actions,
+    /// Component updates, sent unreliably
+    Updates,
+}
+
+/// A replication message that would have been sent to a client
+#[derive(Debug, Clone, PartialEq)]
+pub struct DryRunRecord {
+    pub group_id: ReplicationGroupId,
+    pub kind: DryRunMessageKind,
+    /// Entities included in the message
+    pub entities: Vec<Entity>,
+    /// Size of the serialized message
+    pub num_bytes: usize,
+    pub tick: Tick,
+}
+
+/// Insert this resource on the server to compute the replication messages without sending them.
+///
+/// The resource contains the messages that would have been sent during the last replication send.
+#[derive(Resource, Debug, Default)]
+pub struct ReplicationDryRun {
+    pub(crate) records: HashMap<ClientId, Vec<DryRunRecord>>,
+}
+
+impl ReplicationDryRun {
+    /// Replication messages that would have been sent to the client during the last replication send
+    pub fn records(&self, client_id: ClientId) -> &[DryRunRecord] {
+        self.records.get(&client_id).map_or(&[], |r| r.as_slice())
+    }
+
+    /// Iterate through the replication messages that would have been sent to each client
+    pub fn iter(&self) -> impl Iterator<Item = (&ClientId, &[DryRunRecord])> {
+        self.records.iter().map(|(c, r)| (c, r.as_slice()))
+    }
+
+    /// Total number of bytes that would have been sent to the client during the last replication send
+    pub fn num_bytes(&self, client_id: ClientId) -> usize {
+        self.records(client_id).iter().map(|r| r.num_bytes).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::server::Replicate;
+    use crate::prelude::{client, Replicated};
+    use crate::tests::protocol::ComponentSyncModeFull;
+    use crate::tests::stepper::{BevyStepper, TEST_CLIENT_ID};
+    use bevy::prelude::*;
+
+    /// In dry-run mode, the replication messages are recorded but not sent to the client
+    #[test]
+    fn test_replication_dry_run() {
+        let mut stepper = BevyStepper::default();
+        stepper.server_app.init_resource::<ReplicationDryRun>();
+        let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+
+        let server_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn((Replicate::default(), ComponentSyncModeFull(1.0)))
+            .id();
+        stepper.frame_step();
+
+        // the spawn is recorded
+        let dry_run = stepper.server_app.world().resource::<ReplicationDryRun>();
+        let records = dry_run.records(client_id);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].kind, DryRunMessageKind::Actions);
+        assert_eq!(records[0].entities, vec![server_entity]);
+        assert!(records[0].num_bytes > 0);
+        assert_eq!(dry_run.num_bytes(client_id), records[0].num_bytes);
+
+        // updates are recorded
+        stepper
+            .server_app
+            .world_mut()
+            .get_mut::<ComponentSyncModeFull>(server_entity)
+            .unwrap()
+            .0 = 2.0;
+        stepper.frame_step();
+        let records = stepper
+            .server_app
+            .world()
+            .resource::<ReplicationDryRun>()
+            .records(client_id);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].kind, DryRunMessageKind::Updates);
+        assert_eq!(records[0].entities, vec![server_entity]);
+
+        // nothing was sent to the client
+        for _ in 0..5 {
+            stepper.frame_step();
+        }
+        assert!(stepper
+            .client_app
+            .world()
+            .resource::<client::ConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(server_entity)
+            .is_none());
+        assert!(stepper
+            .client_app
+            .world_mut()
+            .query_filtered::<Entity, With<Replicated>>()
+            .iter(stepper.client_app.world())
+            .next()
+            .is_none());
+    }
+}
@@ -2,7 +2,12 @@
 use bevy::ecs::entity::{EntityHashMap, EntityMapper};
 use bevy::prelude::{Deref, DerefMut, Entity, EntityWorldMut, World};
 use bevy::reflect::Reflect;
-use tracing::{debug, error, trace};
+use bevy::utils::HashMap;
+use bytes::Bytes;
+use tracing::{debug, trace};
+
+use crate::prelude::Tick;
+use crate::protocol::component::ComponentKind;
 
 const MARKED: u64 = 1 << 62;
 
@@ -46,8 +51,25 @@ impl EntityMapper for SendEntityMap {
     }
 }
 
+/// Map from remote entities to local entities, used to map the entities contained in the
+/// components and messages that we receive.
+///
+/// A replicated component can reference a remote entity that hasn't been replicated yet (for
+/// example if the two entities are in different replication groups). In that case the entity is
+/// mapped to `Entity::PLACEHOLDER`, and the component is kept as pending: it is written again
+/// once new entities are replicated, so that the reference gets resolved.
 #[derive(Default, Debug, Reflect, Deref, DerefMut)]
-pub struct ReceiveEntityMap(pub(crate) EntityHashMap<Entity>);
+pub struct ReceiveEntityMap {
+    #[deref]
+    pub(crate) map: EntityHashMap<Entity>,
+    /// True if an entity could not be mapped since the last call to `take_unmapped`
+    #[reflect(ignore)]
+    unmapped: bool,
+    /// Components (local entity and kind) that contain entities that could not be mapped, with
+    /// the tick and the bytes of the last update received for them
+    #[reflect(ignore)]
+    pub(crate) pending: HashMap<(Entity, ComponentKind), (Tick, Bytes)>,
+}
 
 impl EntityMapper for ReceiveEntityMap {
     /// Map an entity from the remote World to the local World
@@ -57,17 +79,68 @@ impl EntityMapper for ReceiveEntityMap {
         if RemoteEntityMap::is_mapped(entity) {
             RemoteEntityMap::mark_unmapped(entity)
         } else {
-            // if we don't find the entity, return Entity::PLACEHOLDER as an error
-            self.0.get(&entity).copied().unwrap_or_else(|| {
-                error!("Failed to map entity {entity:?}");
+            // if we don't find the entity, return Entity::PLACEHOLDER
+            self.map.get(&entity).copied().unwrap_or_else(|| {
+                debug!("Failed to map entity {entity:?}");
+                self.unmapped = true;
                 Entity::PLACEHOLDER
             })
         }
     }
 }
 
+impl ReceiveEntityMap {
+    /// Returns true if an entity could not be mapped since the last call, and resets the flag
+    pub(crate) fn take_unmapped(&mut self) -> bool {
+        std::mem::take(&mut self.unmapped)
+    }
+
+    /// Update the pending status of a component that was just written on the local entity.
+    ///
+    /// If some of its entities could not be mapped, the component will be written again with the
+    /// same bytes once new entities are replicated.
+    pub(crate) fn track_pending(
+        &mut self,
+        local_entity: Entity,
+        kind: ComponentKind,
+        tick: Tick,
+        bytes: Bytes,
+    ) {
+        if self.take_unmapped() {
+            trace!(
+                ?local_entity,
+                ?kind,
+                "Component references an entity that is not replicated yet"
+            );
+            self.pending.insert((local_entity, kind), (tick, bytes));
+        } else if !self.pending.is_empty() {
+            // the component was updated with entities that are all mapped
+            self.pending.remove(&(local_entity, kind));
+        }
+    }
+
+    /// Stop tracking the pending components of the local entity
+    pub(crate) fn remove_pending(&mut self, local_entity: Entity) {
+        if !self.pending.is_empty() {
+            self.pending
+                .retain(|(entity, _), _| *entity != local_entity);
+        }
+    }
+}
+
 #[derive(Default, Debug, Reflect)]
 /// Map between local and remote entities. (used mostly on client because it's when we receive entity updates)
+///
+/// # Entity reuse
+///
+/// Remote entities are identified by the full [`Entity`] of the sender, including its generation.
+/// When the sender despawns an entity, bevy can reuse its index right away for a new entity, but
+/// the generation of that index is incremented. The new entity is therefore a different key in this map,
+/// even if the despawn of the old entity has not been processed by the receiver yet, so updates for
+/// the old and the new entity can never be confused and no despawn confirmation is needed before the index is reused.
+///
+/// The only caveat is that the generation wraps around after ~2^30 reuses of the same index
+/// (the bit above that is used to mark entities that were already mapped by the sender).
 pub struct RemoteEntityMap {
     pub(crate) remote_to_local: ReceiveEntityMap,
     pub(crate) local_to_remote: SendEntityMap,
@@ -273,6 +346,197 @@ mod tests {
         );
     }
 
+    /// An entity references another entity that is not replicated yet: the reference is mapped
+    /// to a placeholder, and resolved once the other entity is replicated
+    #[test]
+    fn test_entity_mapping_deferred_until_replicated() {
+        let mut stepper = BevyStepper::default();
+        let server_target = stepper.server_app.world_mut().spawn_empty().id();
+        let server_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn((ComponentMapEntities(server_target), Replicate::default()))
+            .id();
+        stepper.frame_step();
+        stepper.frame_step();
+
+        let client_entity = stepper
+            .client_app
+            .world()
+            .resource::<client::ConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(server_entity)
+            .unwrap();
+        assert_eq!(
+            stepper
+                .client_app
+                .world()
+                .get::<ComponentMapEntities>(client_entity),
+            Some(&ComponentMapEntities(Entity::PLACEHOLDER))
+        );
+
+        // the referenced entity gets replicated
+        stepper
+            .server_app
+            .world_mut()
+            .entity_mut(server_target)
+            .insert(Replicate::default());
+        stepper.frame_step();
+        stepper.frame_step();
+
+        let receiver = &stepper
+            .client_app
+            .world()
+            .resource::<client::ConnectionManager>()
+            .replication_receiver;
+        let client_target = receiver.remote_entity_map.get_local(server_target).unwrap();
+        assert!(receiver
+            .remote_entity_map
+            .remote_to_local
+            .pending
+            .is_empty());
+        assert_eq!(
+            stepper
+                .client_app
+                .world()
+                .get::<ComponentMapEntities>(client_entity),
+            Some(&ComponentMapEntities(client_target))
+        );
+    }
+
+    /// A component that references an entity that is not replicated yet is removed: it should
+    /// not be inserted again when other entities get replicated
+    #[test]
+    fn test_entity_mapping_pending_component_removed() {
+        let mut stepper = BevyStepper::default();
+        let server_target = stepper.server_app.world_mut().spawn_empty().id();
+        let server_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn((ComponentMapEntities(server_target), Replicate::default()))
+            .id();
+        stepper.frame_step();
+        stepper.frame_step();
+        let client_entity = stepper
+            .client_app
+            .world()
+            .resource::<client::ConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(server_entity)
+            .unwrap();
+        assert!(stepper
+            .client_app
+            .world()
+            .get::<ComponentMapEntities>(client_entity)
+            .is_some());
+
+        stepper
+            .server_app
+            .world_mut()
+            .entity_mut(server_entity)
+            .remove::<ComponentMapEntities>();
+        stepper.frame_step();
+        stepper.frame_step();
+        assert!(stepper
+            .client_app
+            .world()
+            .get::<ComponentMapEntities>(client_entity)
+            .is_none());
+
+        // another entity gets replicated
+        stepper
+            .server_app
+            .world_mut()
+            .spawn((ComponentSyncModeFull(0.0), Replicate::default()));
+        stepper.frame_step();
+        stepper.frame_step();
+        assert!(stepper
+            .client_app
+            .world()
+            .resource::<client::ConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .remote_to_local
+            .pending
+            .is_empty());
+        assert!(stepper
+            .client_app
+            .world()
+            .get::<ComponentMapEntities>(client_entity)
+            .is_none());
+    }
+
+    /// The server despawns an entity and immediately spawns a new one that reuses the same index.
+    /// The client receives the despawn and the spawn at the same time and should not confuse the two entities.
+    #[test]
+    fn test_entity_reuse_after_despawn() {
+        let mut stepper = BevyStepper::default();
+        let mut server_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn((ComponentSyncModeFull(0.0), Replicate::default()))
+            .id();
+        stepper.frame_step();
+        stepper.frame_step();
+
+        for i in 1..5 {
+            let old_client_entity = stepper
+                .client_app
+                .world()
+                .resource::<client::ConnectionManager>()
+                .replication_receiver
+                .remote_entity_map
+                .get_local(server_entity)
+                .unwrap();
+
+            stepper.server_app.world_mut().despawn(server_entity);
+            let new_server_entity = stepper
+                .server_app
+                .world_mut()
+                .spawn((ComponentSyncModeFull(i as f32), Replicate::default()))
+                .id();
+            // the index is reused, but with a different generation
+            assert_eq!(new_server_entity.index(), server_entity.index());
+            assert_ne!(new_server_entity, server_entity);
+            stepper.frame_step();
+            stepper.frame_step();
+
+            let remote_entity_map = &stepper
+                .client_app
+                .world()
+                .resource::<client::ConnectionManager>()
+                .replication_receiver
+                .remote_entity_map;
+            assert!(remote_entity_map.get_local(server_entity).is_none());
+            let new_client_entity = remote_entity_map.get_local(new_server_entity).unwrap();
+            assert!(stepper
+                .client_app
+                .world()
+                .get_entity(old_client_entity)
+                .is_err());
+            assert_eq!(
+                stepper
+                    .client_app
+                    .world()
+                    .get::<ComponentSyncModeFull>(new_client_entity),
+                Some(&ComponentSyncModeFull(i as f32))
+            );
+            // only the new entity exists on the client
+            assert_eq!(
+                stepper
+                    .client_app
+                    .world_mut()
+                    .query::<&ComponentSyncModeFull>()
+                    .iter(stepper.client_app.world())
+                    .count(),
+                1
+            );
+            server_entity = new_server_entity;
+        }
+    }
+
     /// Check that the EntityMap (used for PredictionEntityMap and InterpolationEntityMap)
     /// doesn't map to Entity::PLACEHOLDER if the mapping fails.
     ///
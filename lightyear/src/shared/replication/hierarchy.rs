@@ -13,7 +13,7 @@ use crate::server::replication::send::ReplicationTarget;
 use crate::server::replication::send::SyncTarget;
 use crate::shared::replication::authority::{AuthorityPeer, HasAuthority};
 use crate::shared::replication::components::ReplicateHierarchy;
-use crate::shared::replication::{ReplicationPeer, ReplicationSend};
+use crate::shared::replication::{ReplicationReceive, ReplicationSend};
 use crate::shared::sets::{InternalMainSet, InternalReplicationSet};
 
 /// This component can be added to an entity to replicate the entity's hierarchy to the remote world.
@@ -22,14 +22,31 @@ use crate::shared::sets::{InternalMainSet, InternalReplicationSet};
 ///
 /// Updates entity's `Parent` component on change.
 /// Removes the parent if `None`.
+///
+/// If the parent has not been spawned yet on the receiving side (for example because it is
+/// replicated in a different [`ReplicationGroup`]), the parenting is deferred until it is.
 #[derive(Component, Default, Reflect, Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
 #[reflect(Component)]
-pub struct ParentSync(Option<Entity>);
+pub struct ParentSync(
+    Option<Entity>,
+    /// Remote parent entity that could not be mapped to a local entity yet
+    #[serde(skip)]
+    #[reflect(ignore)]
+    Option<Entity>,
+);
 
 impl MapEntities for ParentSync {
     fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
-        if let Some(entity) = &mut self.0 {
-            *entity = entity_mapper.map_entity(*entity);
+        if let Some(entity) = self.0 {
+            let mapped = entity_mapper.map_entity(entity);
+            if mapped == Entity::PLACEHOLDER {
+                // the parent does not exist locally yet, keep the remote entity around
+                // so that we can set the parent once it is spawned
+                self.0 = None;
+                self.1 = Some(entity);
+            } else {
+                self.0 = Some(mapped);
+            }
         }
     }
 }
@@ -119,7 +136,7 @@ impl<R: ReplicationSend> HierarchySendPlugin<R> {
                     enabled: recursive,
                     recursive,
                 },
-                ParentSync(None),
+                ParentSync(None, None),
             ));
 
             // On the client, we want to add the PrePredicted component to the children
@@ -237,7 +254,7 @@ impl<R: ReplicationSend> HierarchySendPlugin<R> {
                     ?parent_sync,
                     "Update parent sync because hierarchy has changed"
                 );
-                parent_sync.set_if_neq(ParentSync(Some(**parent)));
+                parent_sync.set_if_neq(ParentSync(Some(**parent), None));
             }
         }
     }
@@ -282,7 +299,27 @@ impl<R> Default for HierarchyReceivePlugin<R> {
     }
 }
 
-impl<R> HierarchyReceivePlugin<R> {
+impl<R: ReplicationReceive> HierarchyReceivePlugin<R> {
+    /// Set the parent of the entities whose parent was not spawned locally when their
+    /// `ParentSync` was received, once the parent exists
+    ///
+    /// This only runs on the receiving side
+    fn resolve_pending_parent(
+        manager: Res<R>,
+        mut hierarchy: Query<(&mut ParentSync, &Replicated), Without<ReplicationTarget>>,
+    ) {
+        for (mut parent_sync, replicated) in hierarchy.iter_mut() {
+            let Some(remote_parent) = parent_sync.1 else {
+                continue;
+            };
+            if let Some(local_parent) = manager.get_local_entity(remote_parent, replicated.from) {
+                trace!(?remote_parent, ?local_parent, "Resolved pending parent");
+                parent_sync.0 = Some(local_parent);
+                parent_sync.1 = None;
+            }
+        }
+    }
+
     /// Update parent/children hierarchy if parent_sync changed
     ///
     /// This only runs on the receiving side
@@ -300,6 +337,10 @@ impl<R> HierarchyReceivePlugin<R> {
                 parent_sync,
                 parent
             );
+            // the parent hasn't been spawned locally yet
+            if parent_sync.1.is_some() {
+                continue;
+            }
             if let Some(new_parent) = parent_sync.0 {
                 if parent.filter(|&parent| **parent == new_parent).is_none() {
                     commands.entity(entity).set_parent(new_parent);
@@ -311,13 +352,21 @@ impl<R> HierarchyReceivePlugin<R> {
     }
 }
 
-impl<R: ReplicationPeer> Plugin for HierarchyReceivePlugin<R> {
+impl<R: ReplicationReceive> Plugin for HierarchyReceivePlugin<R> {
     fn build(&self, app: &mut App) {
         // REFLECTION
         app.register_type::<ParentSync>();
 
         // TODO: does this work for client replication? (client replicating to other clients via the server?)
         // when we receive a ParentSync update from the remote, update the hierarchy
+        app.add_systems(
+            PreUpdate,
+            Self::resolve_pending_parent
+                .after(InternalMainSet::<R::SetMarker>::Receive)
+                .before(PredictionSet::Sync)
+                .before(InterpolationSet::SpawnHistory)
+                .before(Self::update_parent),
+        );
         app.add_systems(
             PreUpdate,
             Self::update_parent
@@ -427,7 +476,7 @@ mod tests {
                 .world_mut()
                 .entity_mut(parent)
                 .get::<ParentSync>(),
-            Some(&ParentSync(None))
+            Some(&ParentSync(None, None))
         );
 
         // 2. make sure that the parent has been removed on the receiver side, and that ParentSync has been updated
@@ -437,7 +486,7 @@ mod tests {
                 .world_mut()
                 .entity_mut(client_parent)
                 .get::<ParentSync>(),
-            Some(&ParentSync(None))
+            Some(&ParentSync(None, None))
         );
         assert_eq!(
             stepper
@@ -455,6 +504,84 @@ mod tests {
             .is_none());
     }
 
+    /// The child is replicated in a different group than its parent, and the parent
+    /// is only replicated later: the parenting is deferred until the parent is spawned
+    #[test]
+    fn test_update_parent_deferred_until_parent_is_spawned() {
+        let (mut stepper, grandparent, parent, _) = setup_hierarchy();
+
+        stepper.server_app.world_mut().entity_mut(parent).insert((
+            Replicate {
+                hierarchy: ReplicateHierarchy {
+                    enabled: true,
+                    recursive: false,
+                },
+                group: ReplicationGroup::new_id(1),
+                ..default()
+            },
+            ParentSync::default(),
+        ));
+        stepper.frame_step();
+        stepper.frame_step();
+
+        let client_parent = stepper
+            .client_app
+            .world_mut()
+            .query_filtered::<Entity, With<ComponentSyncModeSimple>>()
+            .get_single(stepper.client_app.world())
+            .unwrap();
+        assert!(stepper
+            .client_app
+            .world()
+            .get::<Parent>(client_parent)
+            .is_none());
+        assert_eq!(
+            stepper
+                .client_app
+                .world()
+                .get::<ParentSync>(client_parent)
+                .unwrap()
+                .1,
+            Some(grandparent)
+        );
+
+        // replicate the grandparent in its own group
+        stepper
+            .server_app
+            .world_mut()
+            .entity_mut(grandparent)
+            .insert(Replicate {
+                hierarchy: ReplicateHierarchy {
+                    enabled: false,
+                    recursive: false,
+                },
+                group: ReplicationGroup::new_id(2),
+                ..default()
+            });
+        stepper.frame_step();
+        stepper.frame_step();
+
+        let client_grandparent = stepper
+            .client_app
+            .world_mut()
+            .query_filtered::<Entity, With<ComponentSyncModeFull>>()
+            .get_single(stepper.client_app.world())
+            .unwrap();
+        assert_eq!(
+            stepper
+                .client_app
+                .world()
+                .get::<Parent>(client_parent)
+                .unwrap()
+                .get(),
+            client_grandparent
+        );
+        assert_eq!(
+            stepper.client_app.world().get::<ParentSync>(client_parent),
+            Some(&ParentSync(Some(client_grandparent), None))
+        );
+    }
+
     #[test]
     fn test_propagate_hierarchy() {
         // tracing_subscriber::FmtSubscriber::builder()
@@ -579,7 +706,7 @@ mod tests {
                 .world()
                 .get::<ParentSync>(server_child)
                 .unwrap(),
-            &ParentSync(Some(server_parent))
+            &ParentSync(Some(server_parent), None)
         );
     }
 
@@ -0,0 +1,144 @@
+//! Tick of the initial replication snapshot of a client
+//!
+//! When a client connects, the server replicates all the entities that are relevant to it in the next
+//! replication send. The server tick of that send is the reference tick of the initial state of the client:
+//! all the entities of the snapshot are confirmed at that tick.
+//!
+//! The server records it in
+//! [`ConnectionManager::initial_replication_tick`](crate::prelude::server::ConnectionManager::initial_replication_tick)
+//! and sends it to the client, where it is available in
+//! [`ConnectionManager::initial_replication_tick`](crate::prelude::client::ConnectionManager::initial_replication_tick).
+//!
+//! The server can also send the previous states of the interpolated entities along with their spawn,
+//! so that the client can interpolate them right away
+//! (see [`interpolation_snapshots`](crate::server::interpolation_snapshots)).
+use bevy::prelude::Entity;
+use bytes::Bytes;
+
+use crate::prelude::{Deserialize, Serialize, Tick};
+
+/// Message sent by the server to notify a client of the tick of its initial replication snapshot
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct InitialReplication {
+    pub tick: Tick,
+}
+
+/// Message sent by the server along with the spawn of an interpolated entity, containing the
+/// previous updates of its interpolated components
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct InterpolationSnapshots {
+    /// The entity in the server's World
+    pub entity: Entity,
+    /// The serialized components of each snapshot, from oldest to most recent
+    pub snapshots: Vec<(Tick, Vec<Bytes>)>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::client::interpolation::InterpolateStatus;
+    use crate::prelude::client::{Confirmed, InterpolationConfig};
+    use crate::prelude::server::{Replicate, SyncTarget};
+    use crate::prelude::{client, server, ClientId, NetworkTarget, SharedConfig, TickConfig};
+    use crate::tests::protocol::ComponentSyncModeFull;
+    use crate::tests::stepper::{BevyStepper, TEST_CLIENT_ID};
+    use bevy::prelude::default;
+
+    /// An interpolated entity that exists before the client connects starts interpolating
+    /// from the tick of the initial replication snapshot
+    #[test]
+    fn test_initial_replication_tick() {
+        let tick_duration = Duration::from_millis(10);
+        let shared_config = SharedConfig {
+            tick: TickConfig::new(tick_duration),
+            ..default()
+        };
+        let client_config = client::ClientConfig {
+            interpolation: InterpolationConfig::default()
+                .with_min_delay(Duration::from_millis(100)),
+            ..default()
+        };
+        let mut stepper = BevyStepper::new(shared_config, client_config, tick_duration);
+        stepper.build();
+        let server_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn((
+                Replicate {
+                    sync: SyncTarget {
+                        interpolation: NetworkTarget::All,
+                        ..default()
+                    },
+                    ..default()
+                },
+                ComponentSyncModeFull(1.0),
+            ))
+            .id();
+        stepper.init();
+        stepper.frame_step();
+        stepper.frame_step();
+
+        let snapshot_tick = stepper
+            .server_app
+            .world()
+            .resource::<server::ConnectionManager>()
+            .initial_replication_tick(ClientId::Netcode(TEST_CLIENT_ID))
+            .unwrap()
+            .expect("the initial snapshot was not sent");
+        let connection = stepper
+            .client_app
+            .world()
+            .resource::<client::ConnectionManager>();
+        assert_eq!(connection.initial_replication_tick(), Some(snapshot_tick));
+
+        let confirmed_entity = connection
+            .replication_receiver
+            .remote_entity_map
+            .get_local(server_entity)
+            .expect("entity was not replicated to client");
+        let confirmed = stepper
+            .client_app
+            .world()
+            .get::<Confirmed>(confirmed_entity)
+            .unwrap();
+        assert_eq!(confirmed.tick, snapshot_tick);
+        let interpolated_entity = confirmed.interpolated.unwrap();
+
+        // the interpolation hasn't reached the snapshot tick yet: the initial value is the next value to interpolate towards
+        let status = stepper
+            .client_app
+            .world()
+            .get::<InterpolateStatus<ComponentSyncModeFull>>(interpolated_entity)
+            .unwrap();
+        assert!(status.current_tick < snapshot_tick);
+        assert!(status.start.is_none());
+        assert_eq!(
+            status.end,
+            Some((snapshot_tick, ComponentSyncModeFull(1.0)))
+        );
+
+        // the interpolation starts from the snapshot tick
+        loop {
+            stepper.frame_step();
+            let status = stepper
+                .client_app
+                .world()
+                .get::<InterpolateStatus<ComponentSyncModeFull>>(interpolated_entity)
+                .unwrap();
+            if let Some((start_tick, start_value)) = &status.start {
+                assert_eq!(*start_tick, snapshot_tick);
+                assert_eq!(start_value, &ComponentSyncModeFull(1.0));
+                break;
+            }
+            assert!(status.current_tick < snapshot_tick);
+        }
+        assert_eq!(
+            stepper
+                .client_app
+                .world()
+                .get::<ComponentSyncModeFull>(interpolated_entity),
+            Some(&ComponentSyncModeFull(1.0))
+        );
+    }
+}
@@ -18,25 +18,32 @@ use crate::serialize::varint::{varint_len, VarIntReadExt, VarIntWriteExt};
 use crate::serialize::writer::Writer;
 use crate::serialize::{SerializationError, ToBytes};
 use crate::shared::events::connection::{
-    ClearEvents, IterComponentInsertEvent, IterComponentRemoveEvent, IterComponentUpdateEvent,
-    IterEntityDespawnEvent, IterEntitySpawnEvent,
+    ClearEvents, IterComponentDeserializationErrorEvent, IterComponentInsertEvent,
+    IterComponentRemoveEvent, IterComponentUpdateEvent, IterEntityDespawnEvent,
+    IterEntitySpawnEvent,
 };
-use crate::shared::replication::components::ReplicationGroupId;
+use crate::shared::replication::components::{DespawnReason, ReplicationGroupId};
+use crate::shared::replication::network_target::NetworkTarget;
 
 pub mod components;
 
 pub(crate) mod archetypes;
 pub(crate) mod authority;
 pub mod delta;
+pub mod dry_run;
 pub mod entity_map;
 pub mod error;
 pub(crate) mod hierarchy;
+pub(crate) mod initial;
 pub mod network_target;
+pub mod origin;
 pub(crate) mod plugin;
 pub(crate) mod prespawn;
+pub(crate) mod ready;
 pub(crate) mod receive;
 pub(crate) mod resources;
 pub(crate) mod send;
+pub(crate) mod subscription;
 pub(crate) mod systems;
 pub(crate) mod utils;
 
@@ -110,7 +117,7 @@ impl ToBytes for EntityActions {
 pub(crate) enum SpawnAction {
     None,
     Spawn,
-    Despawn,
+    Despawn(DespawnReason),
     // the u64 is the entity's bits (we cannot use Entity directly because it doesn't implement Encode/Decode)
     Reuse(Entity),
 }
@@ -120,7 +127,7 @@ impl ToBytes for SpawnAction {
         match &self {
             SpawnAction::None => 1,
             SpawnAction::Spawn => 1,
-            SpawnAction::Despawn => 1,
+            SpawnAction::Despawn(reason) => 1 + reason.len(),
             SpawnAction::Reuse(entity) => 1 + entity.len(),
         }
     }
@@ -129,7 +136,10 @@ impl ToBytes for SpawnAction {
         match &self {
             SpawnAction::None => buffer.write_u8(0)?,
             SpawnAction::Spawn => buffer.write_u8(1)?,
-            SpawnAction::Despawn => buffer.write_u8(2)?,
+            SpawnAction::Despawn(reason) => {
+                buffer.write_u8(2)?;
+                reason.to_bytes(buffer)?;
+            }
             SpawnAction::Reuse(entity) => {
                 buffer.write_u8(3)?;
                 entity.to_bytes(buffer)?;
@@ -145,7 +155,7 @@ impl ToBytes for SpawnAction {
         match buffer.read_u8()? {
             0 => Ok(SpawnAction::None),
             1 => Ok(SpawnAction::Spawn),
-            2 => Ok(SpawnAction::Despawn),
+            2 => Ok(SpawnAction::Despawn(DespawnReason::from_bytes(buffer)?)),
             3 => Ok(SpawnAction::Reuse(Entity::from_bytes(buffer)?)),
             _ => Err(SerializationError::InvalidPacketType),
         }
@@ -309,6 +319,7 @@ pub(crate) trait ReplicationPeer: Resource {
         + IterComponentUpdateEvent<Self::EventContext>
         + IterEntitySpawnEvent<Self::EventContext>
         + IterEntityDespawnEvent<Self::EventContext>
+        + IterComponentDeserializationErrorEvent<Self::EventContext>
         + ClearEvents;
     /// Type of the context associated with the events emitted/received by this replication peer
     type EventContext: EventContext;
@@ -327,6 +338,12 @@ pub(crate) trait ReplicationReceive: Resource + ReplicationPeer {
     /// Do some regular cleanup on the internals of replication
     /// - account for tick wrapping by resetting some internal ticks for each replication group
     fn cleanup(&mut self, tick: Tick);
+
+    /// Map an entity of the remote world to the local world.
+    ///
+    /// `from` is the client that replicated the entity, or `None` if it is the server.
+    /// Returns `None` if the remote entity has not been spawned locally yet.
+    fn get_local_entity(&self, remote_entity: Entity, from: Option<ClientId>) -> Option<Entity>;
 }
 
 #[doc(hidden)]
@@ -345,4 +362,7 @@ pub(crate) trait ReplicationSend: Resource + ReplicationPeer {
     /// Do some regular cleanup on the internals of replication
     /// - account for tick wrapping by resetting some internal ticks for each replication group
     fn cleanup(&mut self, tick: Tick);
+
+    /// Restrict the target of a replicated resource to the peers that are allowed to receive it
+    fn restrict_resource_target(&self, _target: &mut NetworkTarget) {}
 }
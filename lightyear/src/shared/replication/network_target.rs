@@ -5,6 +5,7 @@ use bevy::prelude::Reflect;
 use bevy::utils::HashSet;
 use byteorder::{ReadBytesExt, WriteBytesExt};
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 
 #[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Reflect)]
 /// NetworkTarget indicated which clients should receive some message
@@ -25,6 +26,17 @@ pub enum NetworkTarget {
     Single(ClientId),
 }
 
+/// Lists of clients up to this length are searched linearly by a [`TargetMatcher`],
+/// longer lists use a binary search
+const LINEAR_SEARCH_MAX_LEN: usize = 16;
+
+/// Sort and deduplicate the list of clients
+fn sorted(mut client_ids: Vec<ClientId>) -> Vec<ClientId> {
+    client_ids.sort_unstable();
+    client_ids.dedup();
+    client_ids
+}
+
 impl ToBytes for NetworkTarget {
     fn len(&self) -> usize {
         match self {
@@ -74,11 +86,13 @@ impl ToBytes for NetworkTarget {
             1 => Ok(NetworkTarget::AllExceptSingle(ClientId::from_bytes(
                 buffer,
             )?)),
-            2 => Ok(NetworkTarget::AllExcept(Vec::<ClientId>::from_bytes(
-                buffer,
-            )?)),
+            2 => Ok(NetworkTarget::AllExcept(sorted(
+                Vec::<ClientId>::from_bytes(buffer)?,
+            ))),
             3 => Ok(NetworkTarget::All),
-            4 => Ok(NetworkTarget::Only(Vec::<ClientId>::from_bytes(buffer)?)),
+            4 => Ok(NetworkTarget::Only(sorted(Vec::<ClientId>::from_bytes(
+                buffer,
+            )?))),
             5 => Ok(NetworkTarget::Single(ClientId::from_bytes(buffer)?)),
             _ => Err(SerializationError::InvalidPacketType),
         }
@@ -100,6 +114,7 @@ impl FromIterator<ClientId> for NetworkTarget {
 
 impl From<Vec<ClientId>> for NetworkTarget {
     fn from(value: Vec<ClientId>) -> Self {
+        let value = sorted(value);
         match value.len() {
             0 => NetworkTarget::None,
             1 => NetworkTarget::Single(value[0]),
@@ -119,7 +134,7 @@ impl NetworkTarget {
     }
 
     pub fn from_exclude(client_ids: impl IntoIterator<Item = ClientId>) -> Self {
-        let client_ids = client_ids.into_iter().collect::<Vec<_>>();
+        let client_ids = sorted(client_ids.into_iter().collect::<Vec<_>>());
         match client_ids.len() {
             0 => NetworkTarget::All,
             1 => NetworkTarget::AllExceptSingle(client_ids[0]),
@@ -139,18 +154,66 @@ impl NetworkTarget {
         }
     }
 
+    /// Returns a [`TargetMatcher`] to check if many clients are part of this target.
+    ///
+    /// Lists of more than 16 clients are sorted once (they are borrowed if they are already sorted),
+    /// so that each check is a binary search instead of a linear search.
+    pub fn matcher(&self) -> TargetMatcher<'_> {
+        let sorted_client_ids = match self {
+            NetworkTarget::AllExcept(client_ids) | NetworkTarget::Only(client_ids)
+                if client_ids.len() > LINEAR_SEARCH_MAX_LEN =>
+            {
+                Some(if client_ids.is_sorted() {
+                    Cow::Borrowed(client_ids.as_slice())
+                } else {
+                    Cow::Owned(sorted(client_ids.clone()))
+                })
+            }
+            _ => None,
+        };
+        TargetMatcher {
+            target: self,
+            sorted_client_ids,
+        }
+    }
+
     /// Compute the intersection of this target with another one (A ∩ B)
-    pub(crate) fn intersection(&mut self, target: &NetworkTarget) {
+    ///
+    /// The result uses the most compact variant possible, e.g. `All ∩ Single(x) == Single(x)`
+    pub fn intersection(&mut self, target: &NetworkTarget) {
         match self {
             NetworkTarget::All => {
                 *self = target.clone();
             }
-            // TODO: write the implementation by hand as an optimization!
-            NetworkTarget::AllExceptSingle(existing_client_id) => {
-                let mut a = NetworkTarget::AllExcept(vec![*existing_client_id]);
-                a.intersection(target);
-                *self = a;
-            }
+            NetworkTarget::AllExceptSingle(existing_client_id) => match target {
+                NetworkTarget::None => {
+                    *self = NetworkTarget::None;
+                }
+                NetworkTarget::AllExceptSingle(target_client_id) => {
+                    if existing_client_id != target_client_id {
+                        *self =
+                            NetworkTarget::from_exclude([*existing_client_id, *target_client_id]);
+                    }
+                }
+                NetworkTarget::AllExcept(target_client_ids) => {
+                    let mut new_excluded_ids = HashSet::from_iter(target_client_ids.clone());
+                    new_excluded_ids.insert(*existing_client_id);
+                    *self = NetworkTarget::from_exclude(new_excluded_ids);
+                }
+                NetworkTarget::All => {}
+                NetworkTarget::Only(target_client_ids) => {
+                    let mut new_included_ids = HashSet::from_iter(target_client_ids.clone());
+                    new_included_ids.remove(existing_client_id);
+                    *self = NetworkTarget::from(Vec::from_iter(new_included_ids));
+                }
+                NetworkTarget::Single(target_client_id) => {
+                    if existing_client_id == target_client_id {
+                        *self = NetworkTarget::None;
+                    } else {
+                        *self = NetworkTarget::Single(*target_client_id);
+                    }
+                }
+            },
             NetworkTarget::AllExcept(existing_client_ids) => match target {
                 NetworkTarget::None => {
                     *self = NetworkTarget::None;
@@ -158,14 +221,14 @@ impl NetworkTarget {
                 NetworkTarget::AllExceptSingle(target_client_id) => {
                     let mut new_excluded_ids = HashSet::from_iter(existing_client_ids.clone());
                     new_excluded_ids.insert(*target_client_id);
-                    *existing_client_ids = Vec::from_iter(new_excluded_ids);
+                    *self = NetworkTarget::from_exclude(new_excluded_ids);
                 }
                 NetworkTarget::AllExcept(target_client_ids) => {
                     let mut new_excluded_ids = HashSet::from_iter(existing_client_ids.clone());
                     target_client_ids.iter().for_each(|id| {
                         new_excluded_ids.insert(*id);
                     });
-                    *existing_client_ids = Vec::from_iter(new_excluded_ids);
+                    *self = NetworkTarget::from_exclude(new_excluded_ids);
                 }
                 NetworkTarget::All => {}
                 NetworkTarget::Only(target_client_ids) => {
@@ -173,7 +236,7 @@ impl NetworkTarget {
                     existing_client_ids.iter_mut().for_each(|id| {
                         new_included_ids.remove(id);
                     });
-                    *self = NetworkTarget::Only(Vec::from_iter(new_included_ids));
+                    *self = NetworkTarget::from(Vec::from_iter(new_included_ids));
                 }
                 NetworkTarget::Single(target_client_id) => {
                     if existing_client_ids.contains(target_client_id) {
@@ -224,7 +287,9 @@ impl NetworkTarget {
     }
 
     /// Compute the union of this target with another one (A U B)
-    pub(crate) fn union(&mut self, target: &NetworkTarget) {
+    ///
+    /// The result uses the most compact variant possible, e.g. `AllExceptSingle(x) ∪ Single(x) == All`
+    pub fn union(&mut self, target: &NetworkTarget) {
         match self {
             NetworkTarget::All => {}
             NetworkTarget::AllExceptSingle(existing_client_id) => {
@@ -244,11 +309,8 @@ impl NetworkTarget {
                 NetworkTarget::AllExcept(target_client_ids) => {
                     let new_excluded_ids = HashSet::from_iter(existing_client_ids.clone());
                     let target_excluded_ids = HashSet::from_iter(target_client_ids.clone());
-                    let intersection = new_excluded_ids
-                        .intersection(&target_excluded_ids)
-                        .copied()
-                        .collect();
-                    *existing_client_ids = intersection;
+                    let intersection = new_excluded_ids.intersection(&target_excluded_ids).copied();
+                    *self = NetworkTarget::from_exclude(intersection);
                 }
                 NetworkTarget::All => {
                     *self = NetworkTarget::All;
@@ -262,6 +324,7 @@ impl NetworkTarget {
                 }
                 NetworkTarget::Single(target_client_id) => {
                     existing_client_ids.retain(|id| id != target_client_id);
+                    *self = NetworkTarget::from_exclude(existing_client_ids.clone());
                 }
             },
             NetworkTarget::Only(existing_client_ids) => match target {
@@ -288,7 +351,7 @@ impl NetworkTarget {
                             );
                         }
                         _ => {
-                            *self = NetworkTarget::AllExcept(Vec::from_iter(target_excluded_ids));
+                            *self = NetworkTarget::from_exclude(target_excluded_ids);
                         }
                     }
                 }
@@ -298,13 +361,14 @@ impl NetworkTarget {
                 NetworkTarget::Single(target_client_id) => {
                     if !existing_client_ids.contains(target_client_id) {
                         existing_client_ids.push(*target_client_id);
+                        existing_client_ids.sort_unstable();
                     }
                 }
                 NetworkTarget::Only(target_client_ids) => {
                     let new_included_ids = HashSet::from_iter(existing_client_ids.clone());
                     let target_included_ids = HashSet::from_iter(target_client_ids.clone());
                     let union = new_included_ids.union(&target_included_ids);
-                    *existing_client_ids = union.into_iter().copied().collect::<Vec<_>>();
+                    *existing_client_ids = sorted(union.into_iter().copied().collect::<Vec<_>>());
                 }
             },
             NetworkTarget::Single(existing_client_id) => match target {
@@ -331,7 +395,7 @@ impl NetworkTarget {
                 }
                 NetworkTarget::Single(target_client_id) => {
                     if existing_client_id != target_client_id {
-                        *self = NetworkTarget::Only(vec![*existing_client_id, *target_client_id]);
+                        *self = NetworkTarget::from(vec![*existing_client_id, *target_client_id]);
                     }
                 }
             },
@@ -366,13 +430,36 @@ impl NetworkTarget {
     }
 
     /// Compute the difference of this target with another one (A - B)
-    pub(crate) fn exclude(&mut self, target: &NetworkTarget) {
+    ///
+    /// The result uses the most compact variant possible, e.g. `All - Only([x]) == AllExcept([x])`
+    pub fn difference(&mut self, target: &NetworkTarget) {
         let mut target = target.clone();
         target.inverse();
         self.intersection(&target);
     }
 }
 
+/// Checks if clients are part of a [`NetworkTarget`], see [`NetworkTarget::matcher`]
+#[derive(Debug, Clone)]
+pub struct TargetMatcher<'a> {
+    target: &'a NetworkTarget,
+    /// Sorted copy of the clients of a large `Only` or `AllExcept` list
+    sorted_client_ids: Option<Cow<'a, [ClientId]>>,
+}
+
+impl TargetMatcher<'_> {
+    /// Return true if the target contains the specified client
+    pub fn targets(&self, client_id: &ClientId) -> bool {
+        match (&self.sorted_client_ids, self.target) {
+            (Some(client_ids), NetworkTarget::AllExcept(_)) => {
+                client_ids.binary_search(client_id).is_err()
+            }
+            (Some(client_ids), _) => client_ids.binary_search(client_id).is_ok(),
+            (None, target) => target.targets(client_id),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -389,19 +476,19 @@ mod tests {
     }
 
     #[test]
-    fn test_exclude() {
+    fn test_difference() {
         let client_0 = ClientId::Netcode(0);
         let client_1 = ClientId::Netcode(1);
         let client_2 = ClientId::Netcode(2);
         let mut target = NetworkTarget::All;
         assert!(target.targets(&client_0));
-        target.exclude(&NetworkTarget::Only(vec![client_1, client_2]));
+        target.difference(&NetworkTarget::Only(vec![client_1, client_2]));
         assert_eq!(target, NetworkTarget::AllExcept(vec![client_1, client_2]));
 
         target = NetworkTarget::AllExcept(vec![client_0]);
         assert!(!target.targets(&client_0));
         assert!(target.targets(&client_1));
-        target.exclude(&NetworkTarget::Only(vec![client_0, client_1]));
+        target.difference(&NetworkTarget::Only(vec![client_0, client_1]));
         assert!(matches!(target, NetworkTarget::AllExcept(_)));
 
         if let NetworkTarget::AllExcept(ids) = target {
@@ -412,14 +499,14 @@ mod tests {
         target = NetworkTarget::Only(vec![client_0]);
         assert!(target.targets(&client_0));
         assert!(!target.targets(&client_1));
-        target.exclude(&NetworkTarget::Single(client_1));
+        target.difference(&NetworkTarget::Single(client_1));
         assert_eq!(target, NetworkTarget::Single(client_0));
-        target.exclude(&NetworkTarget::Only(vec![client_0, client_2]));
+        target.difference(&NetworkTarget::Only(vec![client_0, client_2]));
         assert_eq!(target, NetworkTarget::None);
 
         target = NetworkTarget::None;
         assert!(!target.targets(&client_0));
-        target.exclude(&NetworkTarget::Single(client_1));
+        target.difference(&NetworkTarget::Single(client_1));
         assert_eq!(target, NetworkTarget::None);
     }
 
@@ -443,7 +530,7 @@ mod tests {
 
         target = NetworkTarget::AllExcept(vec![client_0, client_1]);
         target.intersection(&NetworkTarget::Only(vec![client_0, client_2]));
-        assert_eq!(target, NetworkTarget::Only(vec![client_2]));
+        assert_eq!(target, NetworkTarget::Single(client_2));
 
         target = NetworkTarget::Only(vec![client_0, client_1]);
         target.intersection(&NetworkTarget::Only(vec![client_0, client_2]));
@@ -490,4 +577,147 @@ mod tests {
         target.union(&NetworkTarget::AllExcept(vec![client_0, client_2]));
         assert_eq!(target, NetworkTarget::AllExcept(vec![client_0, client_2]));
     }
+
+    #[test]
+    fn test_compact_variants() {
+        let client_0 = ClientId::Netcode(0);
+        let client_1 = ClientId::Netcode(1);
+
+        let mut target = NetworkTarget::All;
+        target.intersection(&NetworkTarget::Single(client_0));
+        assert_eq!(target, NetworkTarget::Single(client_0));
+
+        target = NetworkTarget::All;
+        target.difference(&NetworkTarget::Only(vec![client_0]));
+        assert_eq!(target, NetworkTarget::AllExcept(vec![client_0]));
+
+        target = NetworkTarget::AllExceptSingle(client_0);
+        target.intersection(&NetworkTarget::All);
+        assert_eq!(target, NetworkTarget::AllExceptSingle(client_0));
+
+        target = NetworkTarget::AllExcept(vec![client_0, client_1]);
+        target.union(&NetworkTarget::Single(client_1));
+        assert_eq!(target, NetworkTarget::AllExceptSingle(client_0));
+        target.union(&NetworkTarget::Single(client_0));
+        assert_eq!(target, NetworkTarget::All);
+
+        target = NetworkTarget::AllExcept(vec![client_0]);
+        target.intersection(&NetworkTarget::Only(vec![client_0]));
+        assert_eq!(target, NetworkTarget::None);
+    }
+
+    #[test]
+    fn test_all_except_single_with_only() {
+        let client_0 = ClientId::Netcode(0);
+        let client_1 = ClientId::Netcode(1);
+        let client_2 = ClientId::Netcode(2);
+
+        // intersection
+        let mut target = NetworkTarget::AllExceptSingle(client_0);
+        target.intersection(&NetworkTarget::Only(vec![client_0, client_1]));
+        assert_eq!(target, NetworkTarget::Single(client_1));
+
+        target = NetworkTarget::AllExceptSingle(client_0);
+        target.intersection(&NetworkTarget::Only(vec![client_1, client_2]));
+        assert!(matches!(target, NetworkTarget::Only(_)));
+        assert!(!target.targets(&client_0));
+        assert!(target.targets(&client_1));
+        assert!(target.targets(&client_2));
+
+        target = NetworkTarget::Only(vec![client_0, client_1]);
+        target.intersection(&NetworkTarget::AllExceptSingle(client_1));
+        assert_eq!(target, NetworkTarget::Single(client_0));
+
+        // union
+        target = NetworkTarget::AllExceptSingle(client_0);
+        target.union(&NetworkTarget::Only(vec![client_0, client_1]));
+        assert_eq!(target, NetworkTarget::All);
+
+        target = NetworkTarget::AllExceptSingle(client_0);
+        target.union(&NetworkTarget::Only(vec![client_1, client_2]));
+        assert_eq!(target, NetworkTarget::AllExceptSingle(client_0));
+
+        target = NetworkTarget::Only(vec![client_1, client_2]);
+        target.union(&NetworkTarget::AllExceptSingle(client_0));
+        assert_eq!(target, NetworkTarget::AllExceptSingle(client_0));
+
+        // difference
+        target = NetworkTarget::AllExceptSingle(client_0);
+        target.difference(&NetworkTarget::Only(vec![client_0]));
+        assert_eq!(target, NetworkTarget::AllExceptSingle(client_0));
+
+        target = NetworkTarget::AllExceptSingle(client_0);
+        target.difference(&NetworkTarget::Only(vec![client_1, client_2]));
+        assert!(matches!(target, NetworkTarget::AllExcept(_)));
+        assert!(!target.targets(&client_0));
+        assert!(!target.targets(&client_1));
+        assert!(!target.targets(&client_2));
+        assert!(target.targets(&ClientId::Netcode(3)));
+
+        target = NetworkTarget::Only(vec![client_0, client_1]);
+        target.difference(&NetworkTarget::AllExceptSingle(client_0));
+        assert_eq!(target, NetworkTarget::Single(client_0));
+
+        target = NetworkTarget::Only(vec![client_1, client_2]);
+        target.difference(&NetworkTarget::AllExceptSingle(client_0));
+        assert_eq!(target, NetworkTarget::None);
+    }
+
+    /// The matcher uses a binary search for large lists, which must agree with a linear search
+    #[test]
+    fn test_large_target_membership() {
+        // unsorted ids with duplicates, across several ClientId variants
+        let ids: Vec<ClientId> = (0..5000u64)
+            .rev()
+            .map(|i| {
+                let id = (i * 7919) % 10000;
+                if i % 2 == 0 {
+                    ClientId::Netcode(id)
+                } else {
+                    ClientId::Steam(id)
+                }
+            })
+            .chain([ClientId::Netcode(0), ClientId::Server])
+            .collect();
+        let only = NetworkTarget::from(ids.clone());
+        let all_except = NetworkTarget::from_exclude(ids.clone());
+        assert!(matches!(only, NetworkTarget::Only(_)));
+        assert!(matches!(all_except, NetworkTarget::AllExcept(_)));
+        // the variants are public, so the lists can also be unsorted
+        let unsorted_only = NetworkTarget::Only(ids.clone());
+        let unsorted_all_except = NetworkTarget::AllExcept(ids.clone());
+        let (only_matcher, all_except_matcher) = (only.matcher(), all_except.matcher());
+        let (unsorted_only_matcher, unsorted_all_except_matcher) =
+            (unsorted_only.matcher(), unsorted_all_except.matcher());
+        for id in (0..10000u64)
+            .flat_map(|i| [ClientId::Netcode(i), ClientId::Steam(i), ClientId::Local(i)])
+            .chain([ClientId::Server])
+        {
+            let expected = ids.contains(&id);
+            assert_eq!(only.targets(&id), expected, "{id:?}");
+            assert_eq!(all_except.targets(&id), !expected, "{id:?}");
+            assert_eq!(unsorted_only.targets(&id), expected, "{id:?}");
+            assert_eq!(unsorted_all_except.targets(&id), !expected, "{id:?}");
+            assert_eq!(only_matcher.targets(&id), expected, "{id:?}");
+            assert_eq!(all_except_matcher.targets(&id), !expected, "{id:?}");
+            assert_eq!(unsorted_only_matcher.targets(&id), expected, "{id:?}");
+            assert_eq!(
+                unsorted_all_except_matcher.targets(&id),
+                !expected,
+                "{id:?}"
+            );
+        }
+
+        // the lists stay sorted after set operations and serialization
+        let mut target = only.clone();
+        target.union(&NetworkTarget::Single(ClientId::Local(1)));
+        assert!(target.targets(&ClientId::Local(1)));
+        assert!(target.targets(&ClientId::Server));
+        let mut writer = Writer::default();
+        target.to_bytes(&mut writer).unwrap();
+        let mut reader = Reader::from(writer.to_bytes());
+        let deserialized = NetworkTarget::from_bytes(&mut reader).unwrap();
+        assert!(deserialized.targets(&ClientId::Local(1)));
+        assert!(!deserialized.targets(&ClientId::Local(2)));
+    }
 }
@@ -0,0 +1,214 @@
+//! Rebase the replicated value of position-like components around a per-client origin.
+//!
+//! In very large worlds, `f32` coordinates lose precision far away from the world origin.
+//! Components registered with [`register_component`](crate::prelude::AppComponentExt::register_component)
+//! followed by `add_origin_rebasing()` are serialized relative to the origin of the client they are sent to (set with
+//! [`ConnectionManager::set_replication_origin`](crate::prelude::server::ConnectionManager::set_replication_origin)),
+//! so that the values in the wire representation stay close to zero.
+//! That makes them a good fit for quantization in a custom serialization function.
+//!
+//! The origin is written alongside the rebased value, so that the client can add it back on reception
+//! regardless of when the origin was changed on the server.
+use std::any::TypeId;
+
+use bevy::math::Vec3;
+use bevy::ptr::{Ptr, PtrMut};
+use byteorder::{NetworkEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::prelude::Message;
+use crate::protocol::serialize::ErasedSerializeFns;
+use crate::serialize::reader::Reader;
+use crate::serialize::writer::Writer;
+use crate::serialize::SerializationError;
+use crate::shared::replication::entity_map::SendEntityMap;
+
+/// Trait for components that can be expressed relative to an origin when they are replicated.
+///
+/// # Example
+///
+/// ```rust
+/// use bevy::prelude::*;
+/// use lightyear::prelude::*;
+///
+/// #[derive(Component, Clone, PartialEq)]
+/// struct Position(Vec3);
+///
+/// impl OriginRebase for Position {
+///     fn rebase(&self, origin: Vec3) -> Self {
+///         Position(self.0 - origin)
+///     }
+///
+///     fn restore(&mut self, origin: Vec3) {
+///         self.0 += origin;
+///     }
+/// }
+/// ```
+pub trait OriginRebase {
+    /// Returns the value expressed relative to `origin`
+    fn rebase(&self, origin: Vec3) -> Self;
+
+    /// Converts a value expressed relative to `origin` back to absolute coordinates
+    fn restore(&mut self, origin: Vec3);
+}
+
+type ErasedSerializeRebasedFn = unsafe fn(
+    erased_serialize_fns: &ErasedSerializeFns,
+    data: Ptr,
+    origin: Vec3,
+    writer: &mut Writer,
+    entity_map: &mut SendEntityMap,
+) -> Result<(), SerializationError>;
+type ErasedRestoreFn = unsafe fn(data: PtrMut, origin: Vec3);
+
+/// SAFETY: the Ptr must be a valid pointer to a value of type C, and the ErasedSerializeFns
+/// must have been created for type C
+unsafe fn erased_serialize_rebased<C: Message + OriginRebase>(
+    erased_serialize_fns: &ErasedSerializeFns,
+    data: Ptr,
+    origin: Vec3,
+    writer: &mut Writer,
+    entity_map: &mut SendEntityMap,
+) -> Result<(), SerializationError> {
+    let rebased = data.deref::<C>().rebase(origin);
+    erased_serialize_fns.serialize::<C>(&rebased, writer, entity_map)
+}
+
+/// SAFETY: the PtrMut must be a valid pointer to a value of type C
+unsafe fn erased_restore<C: OriginRebase>(data: PtrMut, origin: Vec3) {
+    data.deref_mut::<C>().restore(origin);
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct ErasedOriginRebaseFns {
+    type_id: TypeId,
+    pub serialize_rebased: ErasedSerializeRebasedFn,
+    pub restore: ErasedRestoreFn,
+}
+
+/// Function pointers are not guaranteed to be unique, so we only compare the types
+impl PartialEq for ErasedOriginRebaseFns {
+    fn eq(&self, other: &Self) -> bool {
+        self.type_id == other.type_id
+    }
+}
+
+impl ErasedOriginRebaseFns {
+    pub(crate) fn new<C: Message + OriginRebase>() -> Self {
+        Self {
+            type_id: TypeId::of::<C>(),
+            serialize_rebased: erased_serialize_rebased::<C>,
+            restore: erased_restore::<C>,
+        }
+    }
+}
+
+/// Write the origin that the value was rebased around (if any)
+pub(crate) fn write_origin(
+    writer: &mut Writer,
+    origin: Option<Vec3>,
+) -> Result<(), SerializationError> {
+    match origin {
+        None => writer.write_u8(0)?,
+        Some(origin) => {
+            writer.write_u8(1)?;
+            writer.write_f32::<NetworkEndian>(origin.x)?;
+            writer.write_f32::<NetworkEndian>(origin.y)?;
+            writer.write_f32::<NetworkEndian>(origin.z)?;
+        }
+    }
+    Ok(())
+}
+
+/// Read the origin that the value was rebased around (if any)
+pub(crate) fn read_origin(reader: &mut Reader) -> Result<Option<Vec3>, SerializationError> {
+    match reader.read_u8()? {
+        0 => Ok(None),
+        1 => Ok(Some(Vec3::new(
+            reader.read_f32::<NetworkEndian>()?,
+            reader.read_f32::<NetworkEndian>()?,
+            reader.read_f32::<NetworkEndian>()?,
+        ))),
+        _ => Err(SerializationError::InvalidValue),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::server;
+    use crate::prelude::server::Replicate;
+    use crate::prelude::{client, ClientId};
+    use crate::tests::protocol::ComponentOriginRebase;
+    use crate::tests::stepper::{BevyStepper, TEST_CLIENT_ID};
+
+    #[test]
+    fn test_origin_serialization() {
+        let mut writer = Writer::default();
+        write_origin(&mut writer, None).unwrap();
+        write_origin(&mut writer, Some(Vec3::new(1.0e6, -2.5, 3.0))).unwrap();
+        let bytes = writer.to_bytes();
+        let mut reader = Reader::from(bytes);
+        assert_eq!(read_origin(&mut reader).unwrap(), None);
+        assert_eq!(
+            read_origin(&mut reader).unwrap(),
+            Some(Vec3::new(1.0e6, -2.5, 3.0))
+        );
+    }
+
+    /// Check that a component far from the world origin is reconstructed accurately on the client
+    /// when the server replicates it relative to the client's origin.
+    #[test]
+    fn test_replicate_far_component_with_origin() {
+        let mut stepper = BevyStepper::default();
+        let origin = Vec3::new(1_000_000.0, 0.0, -2_000_000.0);
+        stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<server::ConnectionManager>()
+            .set_replication_origin(ClientId::Netcode(TEST_CLIENT_ID), Some(origin))
+            .unwrap();
+
+        let position = Vec3::new(1_000_000.5, 1.25, -2_000_000.5);
+        let server_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn((Replicate::default(), ComponentOriginRebase(position)))
+            .id();
+        stepper.frame_step();
+        stepper.frame_step();
+        let client_entity = stepper
+            .client_app
+            .world()
+            .resource::<client::ConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(server_entity)
+            .expect("entity was not replicated to client");
+        assert_eq!(
+            stepper
+                .client_app
+                .world()
+                .get::<ComponentOriginRebase>(client_entity)
+                .expect("component missing"),
+            &ComponentOriginRebase(position)
+        );
+
+        // updates are also rebased
+        let position = Vec3::new(1_000_010.0, -3.5, -1_999_990.0);
+        stepper
+            .server_app
+            .world_mut()
+            .entity_mut(server_entity)
+            .insert(ComponentOriginRebase(position));
+        stepper.frame_step();
+        stepper.frame_step();
+        assert_eq!(
+            stepper
+                .client_app
+                .world()
+                .get::<ComponentOriginRebase>(client_entity)
+                .expect("component missing"),
+            &ComponentOriginRebase(position)
+        );
+    }
+}
@@ -1,6 +1,7 @@
 //! This module contains the `ReplicationReceivePlugin` and `ReplicationSendPlugin` plugins, which control
 //! the replication of entities and resources.
 //!
+use crate::shared::replication::components::ReplicationInterval;
 use crate::shared::replication::hierarchy::{HierarchyReceivePlugin, HierarchySendPlugin};
 use crate::shared::replication::resources::{
     receive::ResourceReceivePlugin, send::ResourceSendPlugin,
@@ -20,6 +21,11 @@ pub struct ReplicationConfig {
     ///
     /// Set to `Duration::default()` to send updates every frame.
     pub send_interval: Duration,
+    /// Default number of ticks between two replication updates of an entity.
+    ///
+    /// Can be overridden per entity with the [`ReplicationInterval`] component.
+    /// Set to `None` to send the updates every `send_interval`.
+    pub replication_interval: Option<ReplicationInterval>,
 }
 
 #[derive(Clone, Copy, Debug, Reflect)]
@@ -47,6 +53,7 @@ impl Default for ReplicationConfig {
         Self {
             send_updates_mode: SendUpdatesMode::SinceLastAck,
             send_interval: Duration::default(),
+            replication_interval: None,
         }
     }
 }
@@ -88,14 +95,22 @@ pub(crate) mod receive {
 
 pub(crate) mod send {
     use super::*;
-    use crate::prelude::{Replicating, ReplicationGroup, TimeManager};
+    use crate::prelude::{Replicating, ReplicationGroup, TickManager, TimeManager};
 
     pub(crate) struct ReplicationSendPlugin<R> {
         send_interval: Duration,
+        replication_interval: Option<ReplicationInterval>,
         clean_interval: Duration,
         _marker: std::marker::PhantomData<R>,
     }
 
+    /// Default [`ReplicationInterval`] for the entities that don't have the component
+    #[derive(Resource, Debug)]
+    pub(crate) struct DefaultReplicationInterval<R: Send + Sync + 'static> {
+        pub(crate) interval: Option<ReplicationInterval>,
+        _marker: std::marker::PhantomData<R>,
+    }
+
     #[derive(Resource, Debug)]
     pub(crate) struct SendIntervalTimer<R: Send + Sync + 'static> {
         pub(crate) timer: Option<Timer>,
@@ -103,9 +118,14 @@ pub(crate) mod send {
     }
 
     impl<R: Send + Sync + 'static> ReplicationSendPlugin<R> {
-        pub(crate) fn new(tick_interval: Duration, send_interval: Duration) -> Self {
+        pub(crate) fn new(
+            tick_interval: Duration,
+            send_interval: Duration,
+            replication_interval: Option<ReplicationInterval>,
+        ) -> Self {
             Self {
                 send_interval,
+                replication_interval,
                 // TODO: find a better constant for the clean interval?
                 clean_interval: tick_interval * (i16::MAX as u32 / 3),
                 _marker: std::marker::PhantomData,
@@ -137,6 +157,50 @@ pub(crate) mod send {
             }
         }
 
+        /// Compute if the updates of the entities with a [`ReplicationInterval`] should be buffered
+        /// on this tick. The first updates are buffered right away.
+        fn update_replication_interval_should_send(
+            tick_manager: Res<TickManager>,
+            default_interval: Res<DefaultReplicationInterval<R>>,
+            mut replication_groups: Query<
+                (&mut ReplicationGroup, Option<&ReplicationInterval>),
+                With<Replicating>,
+            >,
+        ) {
+            let tick = tick_manager.tick();
+            for (mut replication_group, interval) in replication_groups.iter_mut() {
+                let Some(interval) = interval.or(default_interval.interval.as_ref()) else {
+                    continue;
+                };
+                if interval.0 <= 1 {
+                    continue;
+                }
+                replication_group.should_send = replication_group
+                    .interval_send_tick
+                    .is_none_or(|send_tick| tick - send_tick >= interval.0 as i16);
+            }
+        }
+
+        /// After we buffer updates, store the tick at which the updates of the entities
+        /// with a [`ReplicationInterval`] were buffered
+        fn update_replication_interval_send_tick(
+            tick_manager: Res<TickManager>,
+            default_interval: Res<DefaultReplicationInterval<R>>,
+            mut replication_groups: Query<
+                (&mut ReplicationGroup, Option<&ReplicationInterval>),
+                With<Replicating>,
+            >,
+        ) {
+            let tick = tick_manager.tick();
+            for (mut replication_group, interval) in replication_groups.iter_mut() {
+                if interval.or(default_interval.interval.as_ref()).is_some()
+                    && replication_group.should_send
+                {
+                    replication_group.interval_send_tick = Some(tick);
+                }
+            }
+        }
+
         /// After we buffer updates, reset all the `should_send` to false
         /// for the replication groups that have a `send_frequency`
         fn update_replication_group_should_send(
@@ -168,6 +232,10 @@ pub(crate) mod send {
                 },
                 _marker: std::marker::PhantomData,
             });
+            app.insert_resource(DefaultReplicationInterval::<R> {
+                interval: self.replication_interval,
+                _marker: std::marker::PhantomData,
+            });
 
             // SETS
             app.configure_sets(
@@ -230,9 +298,17 @@ pub(crate) mod send {
             app.add_systems(
                 PostUpdate,
                 (
-                    ReplicationSendPlugin::<R>::tick_replication_group_timers
+                    (
+                        ReplicationSendPlugin::<R>::tick_replication_group_timers,
+                        ReplicationSendPlugin::<R>::update_replication_interval_should_send,
+                    )
+                        .chain()
                         .in_set(InternalReplicationSet::<R::SetMarker>::BeforeBuffer),
-                    ReplicationSendPlugin::<R>::update_replication_group_should_send
+                    (
+                        ReplicationSendPlugin::<R>::update_replication_interval_send_tick,
+                        ReplicationSendPlugin::<R>::update_replication_group_should_send,
+                    )
+                        .chain()
                         // note that this runs every send_interval
                         .in_set(InternalReplicationSet::<R::SetMarker>::AfterBuffer),
                 ),
@@ -249,13 +325,13 @@ pub(crate) mod shared {
     use crate::client::replication::send::ReplicateToServer;
     use crate::prelude::{
         NetworkRelevanceMode, PrePredicted, RemoteEntityMap, ReplicateHierarchy, Replicated,
-        ReplicationConfig, ReplicationGroup, ShouldBePredicted, TargetEntity,
+        ReplicationConfig, ReplicationGroup, ReplicationPriority, ShouldBePredicted, TargetEntity,
     };
     use crate::server::replication::send::ReplicationTarget;
-    use crate::shared::replication::authority::{AuthorityPeer, HasAuthority};
+    use crate::shared::replication::authority::{AuthorityHistory, AuthorityPeer, HasAuthority};
     use crate::shared::replication::components::{
-        Controlled, Replicating, ReplicationGroupId, ReplicationGroupIdBuilder,
-        ShouldBeInterpolated,
+        Controlled, DespawnReason, NetworkId, Replicating, ReplicationGroupId,
+        ReplicationGroupIdBuilder, ReplicationInterval, ShouldBeInterpolated,
     };
     use crate::shared::replication::entity_map::{InterpolatedEntityMap, PredictedEntityMap};
     use crate::shared::replication::network_target::NetworkTarget;
@@ -270,14 +346,18 @@ pub(crate) mod shared {
                 .register_type::<Replicated>()
                 .register_type::<Controlled>()
                 .register_type::<Replicating>()
+                .register_type::<NetworkId>()
                 .register_type::<ReplicationTarget>()
                 .register_type::<ReplicateToServer>()
                 .register_type::<ReplicateHierarchy>()
                 .register_type::<ReplicationGroupIdBuilder>()
                 .register_type::<ReplicationGroup>()
+                .register_type::<ReplicationPriority>()
+                .register_type::<ReplicationInterval>()
                 .register_type::<ReplicationConfig>()
                 .register_type::<ReplicationGroupId>()
                 .register_type::<NetworkRelevanceMode>()
+                .register_type::<DespawnReason>()
                 .register_type::<NetworkTarget>()
                 .register_type::<ShouldBeInterpolated>()
                 .register_type::<PrePredicted>()
@@ -286,6 +366,7 @@ pub(crate) mod shared {
                 .register_type::<PredictedEntityMap>()
                 .register_type::<HasAuthority>()
                 .register_type::<AuthorityPeer>()
+                .register_type::<AuthorityHistory>()
                 .register_type::<InterpolatedEntityMap>();
         }
     }
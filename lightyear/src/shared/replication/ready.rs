@@ -0,0 +1,108 @@
+//! Application-level readiness of replicated entities
+//!
+//! Transport-level acks only tell the server that a replication message was received; they don't tell
+//! whether the client actually finished setting up the entity (loading assets, spawning the predicted
+//! entity, etc.). A client can notify the server that an entity is ready with
+//! [`ConnectionManager::mark_entity_ready`](crate::prelude::client::ConnectionManager::mark_entity_ready),
+//! and the server can check it with
+//! [`ConnectionManager::is_client_entity_ready`](crate::prelude::server::ConnectionManager::is_client_entity_ready),
+//! for example to hold gameplay for a player until their character is fully set up.
+use crate::prelude::{Deserialize, Serialize};
+use bevy::ecs::entity::MapEntities;
+use bevy::prelude::*;
+
+/// Message sent by a client to notify the server that a replicated entity is ready on the client
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct EntityReady {
+    pub entity: Entity,
+}
+
+impl MapEntities for EntityReady {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        self.entity = entity_mapper.map_entity(self.entity);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::server::Replicate;
+    use crate::prelude::{client, server, ClientId};
+    use crate::tests::protocol::ComponentSyncModeFull;
+    use crate::tests::stepper::{BevyStepper, TEST_CLIENT_ID};
+    use bevy::prelude::*;
+
+    #[derive(Resource, Default)]
+    struct ProcessedInputs(usize);
+
+    /// Only process the inputs for the entities that the client has marked as ready
+    fn process_inputs(
+        manager: Res<server::ConnectionManager>,
+        query: Query<Entity, With<ComponentSyncModeFull>>,
+        mut processed: ResMut<ProcessedInputs>,
+    ) {
+        for entity in query.iter() {
+            if manager.is_client_entity_ready(ClientId::Netcode(TEST_CLIENT_ID), entity) {
+                processed.0 += 1;
+            }
+        }
+    }
+
+    #[test]
+    fn test_entity_ready() {
+        let mut stepper = BevyStepper::default();
+        stepper.server_app.init_resource::<ProcessedInputs>();
+        stepper.server_app.add_systems(Update, process_inputs);
+
+        let server_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn((Replicate::default(), ComponentSyncModeFull(1.0)))
+            .id();
+        stepper.frame_step();
+        stepper.frame_step();
+        let client_entity = stepper
+            .client_app
+            .world()
+            .resource::<client::ConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(server_entity)
+            .expect("entity was not replicated to client");
+
+        // the entity is replicated but the client hasn't marked it as ready yet
+        assert!(!stepper
+            .server_app
+            .world()
+            .resource::<server::ConnectionManager>()
+            .is_client_entity_ready(ClientId::Netcode(TEST_CLIENT_ID), server_entity));
+        assert_eq!(
+            stepper.server_app.world().resource::<ProcessedInputs>().0,
+            0
+        );
+
+        stepper
+            .client_app
+            .world_mut()
+            .resource_mut::<client::ConnectionManager>()
+            .mark_entity_ready(client_entity)
+            .unwrap();
+        stepper.frame_step();
+        stepper.frame_step();
+
+        assert!(stepper
+            .server_app
+            .world()
+            .resource::<server::ConnectionManager>()
+            .is_client_entity_ready(ClientId::Netcode(TEST_CLIENT_ID), server_entity));
+        assert!(stepper.server_app.world().resource::<ProcessedInputs>().0 > 0);
+
+        // the readiness is reset when the entity is despawned
+        stepper.server_app.world_mut().despawn(server_entity);
+        stepper.frame_step();
+        assert!(!stepper
+            .server_app
+            .world()
+            .resource::<server::ConnectionManager>()
+            .is_client_entity_ready(ClientId::Netcode(TEST_CLIENT_ID), server_entity));
+    }
+}
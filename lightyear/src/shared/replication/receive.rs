@@ -9,7 +9,7 @@ use crate::prelude::{ClientId, Tick};
 use crate::protocol::component::ComponentRegistry;
 use crate::serialize::reader::Reader;
 use crate::shared::events::connection::ConnectionEvents;
-use crate::shared::replication::authority::{AuthorityPeer, HasAuthority};
+use crate::shared::replication::authority::{AuthorityConflictPolicy, AuthorityPeer, HasAuthority};
 use crate::shared::replication::components::{InitialReplicated, Replicated, ReplicationGroupId};
 #[cfg(test)]
 use crate::utils::captures::Captures;
@@ -38,6 +38,9 @@ pub struct ReplicationReceiver {
     // BOTH
     /// Buffer to so that we have an ordered receiver per group
     pub(crate) group_channels: EntityHashMap<ReplicationGroupId, GroupChannel>,
+
+    /// Remote ticks of the replication messages that were applied to the world by the latest `apply_world`
+    pub(crate) applied_ticks: Vec<Tick>,
 }
 
 impl ReplicationReceiver {
@@ -48,6 +51,7 @@ impl ReplicationReceiver {
             local_entity_to_group: Default::default(),
             // BOTH
             group_channels: Default::default(),
+            applied_ticks: Vec::new(),
         }
     }
 
@@ -245,6 +249,7 @@ impl ReplicationReceiver {
         current_tick: Tick,
         events: &mut ConnectionEvents,
     ) {
+        self.applied_ticks.clear();
         // apply actions first
 
         // TODO: this would be how we do it, but the borrow-checked prevents us...
@@ -300,6 +305,7 @@ impl ReplicationReceiver {
                 channel.actions_pending_recv_message_id += 1;
                 // Update the latest server tick that we have processed
                 channel.latest_tick = Some(remote_tick);
+                self.applied_ticks.push(remote_tick);
 
                 channel.apply_actions_message(
                     world,
@@ -335,6 +341,9 @@ impl ReplicationReceiver {
                 while channel.buffered_updates.len() > max_applicable_idx {
                     let (remote_tick, message) = channel.buffered_updates.pop_oldest().unwrap();
                     let is_history = channel.buffered_updates.len() != max_applicable_idx;
+                    if !is_history {
+                        self.applied_ticks.push(remote_tick);
+                    }
                     channel.apply_updates_message(
                         world,
                         remote,
@@ -585,6 +594,7 @@ impl GroupChannel {
         // NOTE: order matters here, because some components can depend on other entities.
         // These components could even form a cycle, for example A.HasWeapon(B) and B.HasHolder(A)
         // Our solution is to first handle spawn for all entities separately.
+        let mut spawned = false;
         for (remote_entity, actions) in message.actions.iter() {
             trace!(?remote_entity, ?remote, ?actions, "Received entity actions");
             // spawn
@@ -639,6 +649,7 @@ impl GroupChannel {
                     }
 
                     remote_entity_map.insert(*remote_entity, local_entity.id());
+                    spawned = true;
                     trace!("Updated remote entity map: {:?}", remote_entity_map);
                     debug!("Received entity spawn for remote entity {remote_entity:?}. Spawned local entity {:?}", local_entity.id());
                     events.push_spawn(local_entity.id());
@@ -654,24 +665,28 @@ impl GroupChannel {
                     local_entity_to_group.insert(local_entity, group_id);
                     // no need to update the entity mapping since the remote already is aware of the mapping?
                     remote_entity_map.insert(*remote_entity, local_entity);
+                    spawned = true;
                 }
                 _ => {}
             }
         }
 
-        for (entity, actions) in message.actions.into_iter() {
+        for (entity, mut actions) in message.actions.into_iter() {
             trace!(remote_entity = ?entity, "Received entity actions");
 
             // despawn
-            if actions.spawn == SpawnAction::Despawn {
+            if let SpawnAction::Despawn(reason) = actions.spawn {
                 trace!(remote_entity = ?entity, "Received entity despawn");
                 if let Some(local_entity) = remote_entity_map.remove_by_remote(entity) {
                     self.local_entities.remove(&local_entity);
+                    remote_entity_map
+                        .remote_to_local
+                        .remove_pending(local_entity);
                     // TODO: we despawn all children as well right now, but that might not be what we want?
                     if let Ok(entity_mut) = world.get_entity_mut(local_entity) {
                         entity_mut.despawn_recursive();
                     }
-                    events.push_despawn(local_entity);
+                    events.push_despawn(local_entity, reason);
                     local_entity_to_group.remove(&local_entity);
                 } else {
                     error!("Received despawn for an entity that does not exist")
@@ -684,10 +699,34 @@ impl GroupChannel {
                 error!(?entity, "cannot find entity");
                 continue;
             };
-            if !Self::authority_check(&mut local_entity_mut, remote) {
+            let entity_authority = Self::authority_check(&mut local_entity_mut, remote);
+            if !entity_authority && remote.is_some() {
                 trace!("Ignored a replication action received from peer {:?} that does not have authority over the entity: {:?}", remote, entity);
+                events.push_authority_violation(local_entity_mut.id());
                 continue;
             }
+            // only keep the components for which the remote has authority
+            actions.insert.retain(|bytes| {
+                Self::component_authority_check(
+                    component_registry.is_server_authoritative_raw(bytes),
+                    entity_authority,
+                    remote,
+                )
+            });
+            actions.remove.retain(|net_id| {
+                Self::component_authority_check(
+                    component_registry.is_server_authoritative_net_id(*net_id),
+                    entity_authority,
+                    remote,
+                )
+            });
+            actions.updates.retain(|bytes| {
+                Self::component_authority_check(
+                    component_registry.is_server_authoritative_raw(bytes),
+                    entity_authority,
+                    remote,
+                )
+            });
 
             // NOTE: 2 options
             //  - send the raw data to a separate typed system
@@ -727,7 +766,13 @@ impl GroupChannel {
             // removals
             trace!(remote_entity = ?entity, ?actions.remove, "Received RemoveComponent");
             for kind in actions.remove {
-                component_registry.raw_remove(kind, &mut local_entity_mut, remote_tick, events);
+                component_registry.raw_remove(
+                    kind,
+                    &mut local_entity_mut,
+                    remote_tick,
+                    &mut remote_entity_map.remote_to_local,
+                    events,
+                );
             }
 
             // updates
@@ -748,6 +793,15 @@ impl GroupChannel {
             }
         }
 
+        // components of other entities might reference the entities that were just spawned
+        if spawned && !remote_entity_map.remote_to_local.pending.is_empty() {
+            component_registry.apply_pending_entity_mappings(
+                world,
+                &mut remote_entity_map.remote_to_local,
+                events,
+            );
+        }
+
         // TODO: apply authority check for the update confirmed tick?
         self.update_confirmed_tick(world, group_id, remote_tick, remote_entity_map);
     }
@@ -755,7 +809,8 @@ impl GroupChannel {
     // TODO: should we accept updates from the client that lost authority if they are from a
     //  tick before the moment where we changed authority? seems like we should?
     /// Check if we can accept updates for this entity, based on the authority
-    /// - on the server: only accept updates from the client who has authority
+    /// - on the server: only accept updates from the client who has authority, or from the clients
+    ///   allowed by the [`AuthorityConflictPolicy`] of the entity
     /// - on the client: only accept updates if we don't have authority
     ///
     /// Returns true if we can accept updates for this entity
@@ -763,12 +818,29 @@ impl GroupChannel {
         match remote {
             // we are the server receiving an update from a client
             Some(c) => entity_mut
-                .get::<AuthorityPeer>()
-                .is_some_and(|authority| *authority == AuthorityPeer::Client(c)),
+                .get::<AuthorityConflictPolicy>()
+                .copied()
+                .unwrap_or_default()
+                .accepts(entity_mut.get::<AuthorityPeer>(), c),
             None => entity_mut.get::<HasAuthority>().is_none(),
         }
     }
 
+    /// Check if we can accept updates for a component, given the result of the entity-level [`Self::authority_check`]
+    /// - server-authoritative components are only accepted from the server
+    /// - other components are only accepted from the peer that has authority over the entity
+    fn component_authority_check(
+        server_authoritative: bool,
+        entity_authority: bool,
+        remote: Option<ClientId>,
+    ) -> bool {
+        if server_authoritative {
+            remote.is_none()
+        } else {
+            entity_authority
+        }
+    }
+
     pub(crate) fn apply_updates_message(
         &mut self,
         world: &mut World,
@@ -800,11 +872,21 @@ impl GroupChannel {
                 info!(remote_entity = ?entity, "update for entity that doesn't exist?");
                 continue;
             };
-            if !Self::authority_check(&mut local_entity_mut, remote) {
+            let entity_authority = Self::authority_check(&mut local_entity_mut, remote);
+            if !entity_authority && remote.is_some() {
                 trace!("Ignored a replication update received from peer {:?} that does not have authority over the entity: {:?}", remote, entity);
+                events.push_authority_violation(local_entity_mut.id());
                 continue;
             };
             for component in components {
+                if !Self::component_authority_check(
+                    component_registry.is_server_authoritative_raw(&component),
+                    entity_authority,
+                    remote,
+                ) {
+                    trace!(remote_entity = ?entity, "Ignored a component update received from peer {:?} that does not have authority over the component", remote);
+                    continue;
+                }
                 let mut reader = Reader::from(component);
                 let _ = component_registry
                     .raw_write(
@@ -868,11 +950,15 @@ impl GroupChannel {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::prelude::client;
+    use crate::prelude::client::ComponentDeserializationErrorEvent;
     use crate::prelude::ServerReplicate;
     use crate::shared::replication::EntityActions;
-    use crate::tests::protocol::{ComponentSyncModeOnce, ComponentSyncModeSimple};
+    use crate::tests::protocol::{
+        ComponentDeserializeFail, ComponentSyncModeOnce, ComponentSyncModeSimple,
+    };
     use crate::tests::stepper::BevyStepper;
-    use bevy::prelude::{OnAdd, Query, Trigger, With};
+    use bevy::prelude::{Events, OnAdd, Query, Trigger, With};
 
     /// Test that the UpdatesIterator works correctly, when we want to iterate through
     /// the buffered updates we have received
@@ -1017,6 +1103,56 @@ mod tests {
         assert!(it.next().is_none());
     }
 
+    /// The actions and the updates of a replication group for the same tick are sent in different messages.
+    /// Check that if the actions message is withheld, the updates of the group are not applied
+    /// until the actions message is received.
+    #[test]
+    fn test_replication_group_atomic_per_tick() {
+        let mut manager = ReplicationReceiver::new();
+        let group_id = ReplicationGroupId(7);
+
+        // the first actions of the group are received and applied
+        manager.recv_actions(
+            EntityActionsMessage {
+                group_id,
+                sequence_id: MessageId(0),
+                actions: Default::default(),
+            },
+            Tick(3),
+        );
+        assert_eq!(manager.read_actions(Tick(3)).count(), 1);
+
+        // on tick 5, the remote inserts a component on one entity of the group and updates
+        // another entity of the group: only the updates message is received
+        let updates = EntityUpdatesMessage {
+            group_id,
+            last_action_tick: Some(Tick(5)),
+            updates: Default::default(),
+        };
+        manager.recv_updates(updates.clone(), Tick(5));
+        assert_eq!(manager.read_actions(Tick(5)).count(), 0);
+        assert_eq!(manager.read_updates().count(), 0);
+
+        // the withheld actions message is received: the actions and the updates can be applied
+        manager.recv_actions(
+            EntityActionsMessage {
+                group_id,
+                sequence_id: MessageId(1),
+                actions: Default::default(),
+            },
+            Tick(5),
+        );
+        assert_eq!(manager.read_actions(Tick(5)).count(), 1);
+        assert_eq!(
+            manager.read_updates().collect::<Vec<_>>(),
+            vec![Update {
+                remote_tick: Tick(5),
+                message: updates,
+                is_history: false,
+            }]
+        );
+    }
+
     #[allow(clippy::get_first)]
     #[test]
     fn test_recv_replication_messages() {
@@ -1258,4 +1394,81 @@ mod tests {
             .get_single(stepper.client_app.world())
             .is_ok());
     }
+
+    /// Test that a component that cannot be deserialized is skipped and reported,
+    /// while the rest of the entity is still replicated and the connection stays alive
+    #[test]
+    fn test_component_deserialization_error() {
+        let mut stepper = BevyStepper::default();
+
+        let server_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn((
+                ServerReplicate::default(),
+                ComponentSyncModeSimple(1.0),
+                ComponentDeserializeFail(-1.0),
+            ))
+            .id();
+        stepper.frame_step();
+        stepper.frame_step();
+
+        let client_entity = stepper
+            .client_app
+            .world()
+            .resource::<client::ConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(server_entity)
+            .expect("entity was not replicated to client");
+        assert_eq!(
+            stepper
+                .client_app
+                .world()
+                .get::<ComponentSyncModeSimple>(client_entity),
+            Some(&ComponentSyncModeSimple(1.0))
+        );
+        assert!(stepper
+            .client_app
+            .world()
+            .get::<ComponentDeserializeFail>(client_entity)
+            .is_none());
+
+        // the error was reported
+        let events = stepper
+            .client_app
+            .world()
+            .resource::<Events<ComponentDeserializationErrorEvent>>();
+        let mut cursor = events.get_cursor();
+        let errors: Vec<_> = cursor.read(events).collect();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].entity(), client_entity);
+        assert_eq!(
+            errors[0].component(),
+            Some(std::any::type_name::<ComponentDeserializeFail>())
+        );
+
+        // the connection is still alive and later updates are still applied
+        stepper
+            .server_app
+            .world_mut()
+            .entity_mut(server_entity)
+            .insert((ComponentSyncModeSimple(2.0), ComponentDeserializeFail(3.0)));
+        stepper.frame_step();
+        stepper.frame_step();
+        assert_eq!(
+            stepper
+                .client_app
+                .world()
+                .get::<ComponentSyncModeSimple>(client_entity),
+            Some(&ComponentSyncModeSimple(2.0))
+        );
+        assert_eq!(
+            stepper
+                .client_app
+                .world()
+                .get::<ComponentDeserializeFail>(client_entity),
+            Some(&ComponentDeserializeFail(3.0))
+        );
+    }
 }
@@ -37,6 +37,21 @@ mod command {
         }
     }
 
+    /// Start replicating a resource directly from the [`App`], for example when building the server app:
+    ///
+    /// ```rust,ignore
+    /// app.replicate_resource::<Score, MyChannel>(NetworkTarget::All);
+    /// ```
+    impl ReplicateResourceExt for App {
+        fn replicate_resource<R: Resource, C: Channel>(&mut self, target: NetworkTarget) {
+            self.insert_resource(ReplicateResourceMetadata::<R> {
+                target,
+                channel: ChannelKind::of::<C>(),
+                _marker: PhantomData,
+            });
+        }
+    }
+
     /// Extension trait to be able to stop replicating a resource to remote clients via [`Commands`].
     pub trait StopReplicateResourceExt {
         /// Stop replicating a resource to remote clients.
@@ -50,6 +65,13 @@ mod command {
             self.remove_resource::<ReplicateResourceMetadata<R>>();
         }
     }
+
+    impl StopReplicateResourceExt for App {
+        fn stop_replicate_resource<R: Resource>(&mut self) {
+            self.world_mut()
+                .remove_resource::<ReplicateResourceMetadata<R>>();
+        }
+    }
 }
 
 /// Metadata indicating how a resource should be replicated.
@@ -116,15 +138,17 @@ pub(crate) mod send {
     }
 
     /// Send a message indicating that the resource was removed
-    fn send_resource_removal<R: Resource + Message, S: MessageSend>(
+    fn send_resource_removal<R: Resource + Message, S: MessageSend + ReplicationSend>(
         mut connection_manager: ResMut<S>,
         replication_resource: Option<Res<ReplicateResourceMetadata<R>>>,
     ) {
         if let Some(replication_resource) = replication_resource {
+            let mut target = replication_resource.target.clone();
+            connection_manager.restrict_resource_target(&mut target);
             let _ = connection_manager.erased_send_message_to_target::<DespawnResource<R>>(
                 &DespawnResource::default(),
                 replication_resource.channel,
-                replication_resource.target.clone(),
+                target,
             );
         }
     }
@@ -146,10 +170,12 @@ pub(crate) mod send {
                         "sending resource replication update to new clients: {:?}",
                         std::any::type_name::<R>()
                     );
+                    let mut target = NetworkTarget::Only(new_clients.clone());
+                    connection_manager.restrict_resource_target(&mut target);
                     let _ = connection_manager.erased_send_message_to_target(
                         resource.as_mut(),
                         replication_resource.channel,
-                        NetworkTarget::Only(new_clients.clone()),
+                        target,
                     );
                 }
             }
@@ -163,11 +189,12 @@ pub(crate) mod send {
                     );
                     let mut target = replication_resource.target.clone();
                     // no need to send a duplicate message to new clients
-                    target.exclude(&NetworkTarget::Only(new_clients));
+                    target.difference(&NetworkTarget::Only(new_clients));
                     // if running in host-server mode, we don't want to replicate the resource to the local client
                     if let Some(local_client) = local_client_connection.as_ref() {
-                        target.exclude(&NetworkTarget::Single(local_client.client.id()));
+                        target.difference(&NetworkTarget::Single(local_client.client.id()));
                     }
+                    connection_manager.restrict_resource_target(&mut target);
                     let _ = connection_manager.erased_send_message_to_target(
                         resource.as_mut(),
                         replication_resource.channel,
@@ -304,9 +331,11 @@ pub(crate) mod receive {
 #[cfg(test)]
 mod tests {
     use super::StopReplicateResourceExt;
+    use crate::prelude::ClientId;
     use crate::shared::replication::network_target::NetworkTarget;
     use crate::shared::replication::resources::ReplicateResourceExt;
     use crate::tests::host_server_stepper::HostServerStepper;
+    use crate::tests::multi_stepper::{MultiBevyStepper, TEST_CLIENT_ID_1};
     use crate::tests::protocol::{Channel1, Resource1, Resource2};
     use crate::tests::stepper::BevyStepper;
     use bevy::prelude::*;
@@ -399,6 +428,52 @@ mod tests {
         assert_eq!(stepper.client_app.world().resource::<Resource1>().0, 1.0);
     }
 
+    /// Start replicating a resource from the App to a single client: the resource is inserted
+    /// and updated on that client, and not replicated to the other clients
+    #[test]
+    fn test_resource_replication_via_app_with_target() {
+        let mut stepper = MultiBevyStepper::default();
+        stepper
+            .server_app
+            .replicate_resource::<Resource1, Channel1>(NetworkTarget::Single(ClientId::Netcode(
+                TEST_CLIENT_ID_1,
+            )));
+
+        // add the resource
+        stepper
+            .server_app
+            .world_mut()
+            .insert_resource(Resource1(1.0));
+        stepper.frame_step();
+        stepper.frame_step();
+
+        // check that the resource was inserted on the targeted client only
+        assert_eq!(stepper.client_app_1.world().resource::<Resource1>().0, 1.0);
+        assert!(stepper
+            .client_app_2
+            .world()
+            .get_resource::<Resource1>()
+            .is_none());
+
+        // update the resource
+        stepper.server_app.world_mut().resource_mut::<Resource1>().0 = 2.0;
+        stepper.frame_step();
+        stepper.frame_step();
+        assert_eq!(stepper.client_app_1.world().resource::<Resource1>().0, 2.0);
+        assert!(stepper
+            .client_app_2
+            .world()
+            .get_resource::<Resource1>()
+            .is_none());
+
+        // stop replicating the resource
+        stepper.server_app.stop_replicate_resource::<Resource1>();
+        stepper.server_app.world_mut().resource_mut::<Resource1>().0 = 3.0;
+        stepper.frame_step();
+        stepper.frame_step();
+        assert_eq!(stepper.client_app_1.world().resource::<Resource1>().0, 2.0);
+    }
+
     #[test]
     fn test_resource_replication_via_commands_host_server() {
         let mut stepper = HostServerStepper::default();
@@ -1,5 +1,6 @@
 //! General struct handling replication
 use std::iter::Extend;
+use std::time::Duration;
 
 use crate::channel::builder::{EntityActionsChannel, EntityUpdatesChannel};
 use bevy::ecs::component::Tick as BevyTick;
@@ -9,7 +10,7 @@ use bevy::ptr::Ptr;
 use bevy::utils::{hashbrown, HashMap};
 use bytes::Bytes;
 use crossbeam_channel::Receiver;
-use tracing::{debug, error, trace};
+use tracing::{debug, error, trace, warn};
 #[cfg(feature = "trace")]
 use tracing::{instrument, Level};
 
@@ -22,10 +23,12 @@ use crate::prelude::{
 use crate::protocol::component::{ComponentKind, ComponentNetId};
 use crate::serialize::writer::Writer;
 use crate::serialize::{SerializationError, ToBytes};
-use crate::shared::replication::components::ReplicationGroupId;
+use crate::shared::replication::components::{DespawnReason, ReplicationGroupId};
 use crate::shared::replication::delta::DeltaManager;
+use crate::shared::replication::dry_run::{DryRunMessageKind, DryRunRecord};
 use crate::shared::replication::error::ReplicationError;
 use crate::shared::replication::plugin::{ReplicationConfig, SendUpdatesMode};
+use crate::shared::time_manager::WrappedTime;
 #[cfg(test)]
 use {
     super::{EntityActionsMessage, EntityUpdatesMessage},
@@ -77,6 +80,32 @@ pub(crate) struct ReplicationSender {
 
     replication_config: ReplicationConfig,
     bandwidth_cap_enabled: bool,
+
+    // PAUSE
+    /// If true, the replication messages are not sent to the remote
+    pub(crate) paused: bool,
+    /// Entities whose spawn was discarded while replication was paused; the remote doesn't know about them
+    spawned_while_paused: EntityHashSet<Entity>,
+
+    // DRY RUN
+    /// If true, the replication messages are recorded in `dry_run_records` instead of being buffered
+    pub(crate) dry_run: bool,
+    pub(crate) dry_run_records: Vec<DryRunRecord>,
+
+    // SEND RATE
+    /// Current time, used to limit the send rate of components registered with a `max_send_rate`
+    current_time: WrappedTime,
+    /// Send state of the rate-limited components of each entity
+    rate_limited_components: EntityHashMap<Entity, HashMap<ComponentKind, RateLimitedComponent>>,
+}
+
+/// Send state of a component that is registered with a `max_send_rate`
+#[derive(Debug, Default)]
+struct RateLimitedComponent {
+    /// Time at which the component was last sent
+    last_send: Option<WrappedTime>,
+    /// True if the component changed since it was last sent
+    pending: bool,
 }
 
 impl ReplicationSender {
@@ -100,6 +129,117 @@ impl ReplicationSender {
             // PRIORITY
             message_send_receiver,
             bandwidth_cap_enabled,
+            // PAUSE
+            paused: false,
+            spawned_while_paused: EntityHashSet::default(),
+            // DRY RUN
+            dry_run: false,
+            dry_run_records: Vec::new(),
+            // SEND RATE
+            current_time: WrappedTime::default(),
+            rate_limited_components: EntityHashMap::default(),
+        }
+    }
+
+    /// Returns true if an update for a component registered with a `max_send_rate` can be sent now.
+    ///
+    /// If the component changed but was sent less than `interval` ago, the update is kept pending
+    /// and the latest value of the component will be sent once the interval has elapsed.
+    pub(crate) fn check_send_rate(
+        &mut self,
+        entity: Entity,
+        kind: ComponentKind,
+        interval: Duration,
+        changed: bool,
+    ) -> bool {
+        let state = self
+            .rate_limited_components
+            .entry(entity)
+            .or_default()
+            .entry(kind)
+            .or_default();
+        if !changed && !state.pending {
+            return false;
+        }
+        if state.last_send.is_some_and(|last_send| {
+            self.current_time.elapsed.saturating_sub(last_send.elapsed) < interval
+        }) {
+            state.pending = true;
+            return false;
+        }
+        state.last_send = Some(self.current_time);
+        state.pending = false;
+        true
+    }
+
+    /// Stop sending replication messages to the remote
+    pub(crate) fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// Start sending replication messages to the remote again
+    ///
+    /// The caller is responsible for sending the full state of the world to the remote,
+    /// since all spawns/inserts/updates were discarded while replication was paused.
+    pub(crate) fn resume(&mut self) {
+        self.paused = false;
+        self.spawned_while_paused.clear();
+    }
+
+    /// Number of distinct entities that have actions or updates waiting to be sent
+    pub(crate) fn num_pending_entities(&self) -> usize {
+        let mut entities = EntityHashSet::default();
+        for group_id in self
+            .group_with_actions
+            .iter()
+            .chain(self.group_with_updates.iter())
+        {
+            if let Some(channel) = self.group_channels.get(group_id) {
+                entities.extend(channel.pending_actions.keys().copied());
+                entities.extend(channel.pending_updates.keys().copied());
+            }
+        }
+        entities.len()
+    }
+
+    /// Discard the replication data that was buffered while replication is paused.
+    ///
+    /// Despawns and component removals are kept for the entities that the remote already knows about,
+    /// so that the remote doesn't keep stale entities or components after the resync.
+    pub(crate) fn discard_paused_messages(&mut self) {
+        let spawned_while_paused = &mut self.spawned_while_paused;
+        let mut group_with_actions = EntityHashSet::default();
+        for group_id in std::mem::take(&mut self.group_with_actions) {
+            let Some(channel) = self.group_channels.get_mut(&group_id) else {
+                continue;
+            };
+            channel.pending_actions.retain(|entity, actions| {
+                actions.insert.clear();
+                actions.updates.clear();
+                match actions.spawn {
+                    SpawnAction::Spawn | SpawnAction::Reuse(_) => {
+                        spawned_while_paused.insert(*entity);
+                        false
+                    }
+                    SpawnAction::Despawn(_) => {
+                        actions.remove.clear();
+                        !spawned_while_paused.remove(entity)
+                    }
+                    SpawnAction::None => {
+                        !actions.remove.is_empty() && !spawned_while_paused.contains(entity)
+                    }
+                }
+            });
+            if !channel.pending_actions.is_empty() {
+                group_with_actions.insert(group_id);
+            }
+        }
+        self.group_with_actions = group_with_actions;
+        for group_id in self.group_with_updates.drain() {
+            if let Some(channel) = self.group_channels.get_mut(&group_id) {
+                channel.pending_updates.clear();
+                channel.pending_delta_updates.clear();
+            }
         }
     }
 
@@ -147,7 +287,8 @@ impl ReplicationSender {
 
     /// Internal bookkeeping:
     /// 1. handle all nack update messages (by resetting the send_tick to the previous ack_tick)
-    pub(crate) fn update(&mut self, world_tick: BevyTick) {
+    pub(crate) fn update(&mut self, world_tick: BevyTick, current_time: WrappedTime) {
+        self.current_time = current_time;
         // 1. handle all nack update messages
         while let Ok(message_id) = self.updates_nack_receiver.try_recv() {
             // remember to remove the entry from the map to avoid memory leakage
@@ -157,28 +298,27 @@ impl ReplicationSender {
                 ..
             }) = self.updates_message_id_to_group_id.remove(&message_id)
             {
-                if let SendUpdatesMode::SinceLastSend = self.replication_config.send_updates_mode {
-                    if let Some(channel) = self.group_channels.get_mut(&group_id) {
-                        // when we know an update message has been lost, we need to reset our send_tick
-                        // to our previous ack_tick
-                        trace!(
+                if let Some(channel) = self.group_channels.get_mut(&group_id) {
+                    // when we know an update message has been lost, we need to reset our send_tick
+                    // to our previous ack_tick. This is needed regardless of the `SendUpdatesMode`
+                    // because the change-detection for component updates is done against the `send_tick`
+                    trace!(
                         "Update channel send_tick back to ack_tick because a message has been lost"
                     );
-                        // only reset the send tick if the bevy_tick of the message that was lost is
-                        // newer than the current ack_tick
-                        // (otherwise it just means we lost some old message, and we don't need to do anything)
-                        if channel
-                            .ack_bevy_tick
-                            .is_some_and(|ack_tick| bevy_tick.is_newer_than(ack_tick, world_tick))
-                        {
-                            channel.send_tick = channel.ack_bevy_tick;
-                        }
-
-                        // TODO: if all clients lost a given message, than we can immediately drop the
-                        //  delta-compression data for that tick
-                    } else {
-                        error!("Received an update message-id nack but the corresponding group channel does not exist");
+                    // only reset the send tick if the bevy_tick of the message that was lost is
+                    // newer than the current ack_tick
+                    // (otherwise it just means we lost some old message, and we don't need to do anything)
+                    if channel
+                        .ack_bevy_tick
+                        .is_some_and(|ack_tick| bevy_tick.is_newer_than(ack_tick, world_tick))
+                    {
+                        channel.send_tick = channel.ack_bevy_tick;
                     }
+
+                    // TODO: if all clients lost a given message, than we can immediately drop the
+                    //  delta-compression data for that tick
+                } else {
+                    error!("Received an update message-id nack but the corresponding group channel does not exist");
                 }
             } else {
                 // NOTE: this happens when a message-id is split between multiple packets (fragmented messages)
@@ -351,19 +491,28 @@ impl ReplicationSender {
     }
 
     #[cfg_attr(feature = "trace", instrument(level = Level::INFO, skip_all))]
-    pub(crate) fn prepare_entity_despawn(&mut self, entity: Entity, group_id: ReplicationGroupId) {
+    pub(crate) fn prepare_entity_despawn(
+        &mut self,
+        entity: Entity,
+        group_id: ReplicationGroupId,
+        reason: DespawnReason,
+    ) {
         #[cfg(feature = "metrics")]
         {
             metrics::counter!("replication::send::entity_despawn").increment(1);
         }
+        self.rate_limited_components.remove(&entity);
         self.group_with_actions.insert(group_id);
-        self.group_channels
+        let actions = self
+            .group_channels
             .entry(group_id)
             .or_default()
             .pending_actions
             .entry(entity)
-            .or_default()
-            .spawn = SpawnAction::Despawn;
+            .or_default();
+        actions.spawn = SpawnAction::Despawn(reason);
+        // the despawn will remove all the components on the remote, no need to send the removals
+        actions.remove.clear();
     }
 
     // we want to send all component inserts that happen together for the same entity in a single message
@@ -402,15 +551,19 @@ impl ReplicationSender {
         {
             metrics::counter!("replication::send::component_remove").increment(1);
         }
-        self.group_with_actions.insert(group_id);
-        self.group_channels
+        let actions = self
+            .group_channels
             .entry(group_id)
             .or_default()
             .pending_actions
             .entry(entity)
-            .or_default()
-            .remove
-            .push(kind);
+            .or_default();
+        // if the entity is getting despawned, there is no need to send a redundant removal
+        if matches!(actions.spawn, SpawnAction::Despawn(_)) {
+            return;
+        }
+        actions.remove.push(kind);
+        self.group_with_actions.insert(group_id);
     }
 
     #[cfg_attr(feature = "trace", instrument(level = Level::INFO, skip_all))]
@@ -454,29 +607,36 @@ impl ReplicationSender {
             metrics::counter!("replication::send::component_update_delta").increment(1);
         }
         let group_channel = self.group_channels.entry(group_id).or_default();
-        // Get the latest acked tick for this entity/component
-        let raw_data = group_channel
+        // Get the latest acked tick for this entity/component.
+        // The baseline is tracked per client: a client that just started receiving this entity
+        // (for example a client that joined mid-stream) has no acked baseline, so it receives the
+        // full state (a diff from the base value) while the other clients receive a normal diff.
+        let baseline = group_channel
             .delta_ack_ticks
             .get(&(entity, kind))
-            .map(|&ack_tick| {
-                // we have an ack tick for this replication group, get the corresponding component value
-                // so we can compute a diff
+            .copied()
+            .and_then(|ack_tick| {
+                // NOTE: remember to use the local entity for local bookkeeping
                 let old_data = delta_manager
                     .data
-                    // NOTE: remember to use the local entity for local bookkeeping
-                    .get_component_value(entity, ack_tick, kind, group_id)
-                    .ok_or(ReplicationError::DeltaCompressionError(
-                        "could not find old component value to compute delta".to_string(),
-                    ))
-                    .inspect_err(|e| {
-                        error!(
-                            ?entity,
-                            name = ?registry.name(kind),
-                            "Could not find old component value from tick {:?} to compute delta",
-                            ack_tick
-                        );
-                        error!("DeltaManager data: {:?}", delta_manager.data);
-                    })?;
+                    .get_component_value(entity, ack_tick, kind, group_id);
+                if old_data.is_none() {
+                    // the client cannot apply a diff from a value that we don't have anymore,
+                    // so fallback to sending the full state
+                    warn!(
+                        ?entity,
+                        name = ?registry.name(kind),
+                        "Could not find old component value from tick {:?} to compute delta, sending the full component",
+                        ack_tick
+                    );
+                    group_channel.delta_ack_ticks.remove(&(entity, kind));
+                }
+                old_data.map(|old_data| (ack_tick, old_data))
+            });
+        let raw_data = baseline
+            .map(|(ack_tick, old_data)| {
+                // we have an ack tick for this replication group, get the corresponding component value
+                // so we can compute a diff
                 // SAFETY: the component_data and erased_data is a pointer to a component that corresponds to kind
                 unsafe {
                     registry.serialize_diff(
@@ -663,6 +823,19 @@ impl ReplicationSender {
             // message.emit_send_logs("EntityActionsChannel");
             message.to_bytes(writer).map_err(SerializationError::from)?;
             let message_bytes = writer.split();
+            if self.dry_run {
+                trace!(?group_id, ?tick, "Dry-run replication action");
+                self.dry_run_records.push(DryRunRecord {
+                    group_id,
+                    kind: DryRunMessageKind::Actions,
+                    entities: message.actions.keys().copied().collect(),
+                    num_bytes: message_bytes.len(),
+                    tick,
+                });
+                channel.pending_actions = message.actions;
+                channel.pending_actions.clear();
+                return Ok(());
+            }
             let message_id = message_manager
                 // TODO: use const type_id?
                 .buffer_send_with_priority(
@@ -744,6 +917,26 @@ impl ReplicationSender {
             // message.emit_send_logs("EntityUpdatesChannel");
             message.to_bytes(writer).map_err(SerializationError::from)?;
             let message_bytes = writer.split();
+            if self.dry_run {
+                trace!(?group_id, ?tick, "Dry-run replication update");
+                self.dry_run_records.push(DryRunRecord {
+                    group_id,
+                    kind: DryRunMessageKind::Updates,
+                    entities: message.updates.keys().copied().collect(),
+                    num_bytes: message_bytes.len(),
+                    tick,
+                });
+                // there won't be any ack, so consider that the updates were received
+                for (entity, component_kind) in channel.pending_delta_updates.drain(..) {
+                    channel
+                        .delta_ack_ticks
+                        .insert((entity, component_kind), tick);
+                }
+                channel.send_tick = Some(bevy_tick);
+                channel.pending_updates = message.updates;
+                channel.pending_updates.clear();
+                return Ok(());
+            }
             let message_id = message_manager
                 // TODO: use const type_id?
                 .buffer_send_with_priority(
@@ -1078,14 +1271,14 @@ mod tests {
         // if we receive a nack for the first message, we don't care because that message's bevy tick
         // is lower than our ack tick
         tx_nack.try_send(message_1).unwrap();
-        sender.update(BevyTick::new(10));
+        sender.update(BevyTick::new(10), WrappedTime::default());
         // make sure that the send tick wasn't updated
         let group = sender.group_channels.get(&group_1).unwrap();
         assert_eq!(group.send_tick, Some(bevy_tick_3));
 
         // however if we receive a nack for the third message, we update the `send_tick` back to the `ack_tick`
         tx_nack.try_send(message_3).unwrap();
-        sender.update(BevyTick::new(10));
+        sender.update(BevyTick::new(10), WrappedTime::default());
         let group = sender.group_channels.get(&group_1).unwrap();
         assert!(!sender
             .updates_message_id_to_group_id
@@ -1258,4 +1451,58 @@ mod tests {
             Some(Tick(2))
         );
     }
+
+    /// Component removals are not sent for an entity that is despawned in the same tick
+    #[test]
+    fn test_no_component_remove_on_despawn() {
+        let (_, rx_ack) = crossbeam_channel::unbounded();
+        let (_, rx_nack) = crossbeam_channel::unbounded();
+        let (_, rx_send) = crossbeam_channel::unbounded();
+        let mut manager = ReplicationSender::new(
+            rx_ack,
+            rx_nack,
+            rx_send,
+            ReplicationConfig::default(),
+            false,
+        );
+
+        let entity_1 = Entity::from_raw(0);
+        let entity_2 = Entity::from_raw(1);
+        let group_1 = ReplicationGroupId(0);
+        let net_id_1: ComponentNetId = 0;
+        let net_id_2: ComponentNetId = 1;
+
+        // removal followed by a despawn
+        manager.prepare_component_remove(entity_1, group_1, net_id_1);
+        manager.prepare_entity_despawn(entity_1, group_1, DespawnReason::Destroyed);
+        // despawn followed by a removal
+        manager.prepare_entity_despawn(entity_2, group_1, DespawnReason::Destroyed);
+        manager.prepare_component_remove(entity_2, group_1, net_id_2);
+
+        let actions = manager.actions_to_send(Tick(2), BevyTick::new(2));
+        let (a, _) = actions.first().unwrap();
+        assert_eq!(
+            EntityHashMap::from_iter(a.actions.clone()),
+            EntityHashMap::from_iter(vec![
+                (
+                    entity_1,
+                    EntityActions {
+                        spawn: SpawnAction::Despawn(DespawnReason::Destroyed),
+                        insert: vec![],
+                        remove: vec![],
+                        updates: vec![],
+                    }
+                ),
+                (
+                    entity_2,
+                    EntityActions {
+                        spawn: SpawnAction::Despawn(DespawnReason::Destroyed),
+                        insert: vec![],
+                        remove: vec![],
+                        updates: vec![],
+                    }
+                )
+            ])
+        );
+    }
 }
@@ -0,0 +1,278 @@
+//! Client subscriptions to the replicated components
+//!
+//! A client might only need a subset of the components of an entity (for example a minimap client
+//! only needs the position of the entities). To save bandwidth, a client can unsubscribe from a component,
+//! for a single entity or for all entities, with
+//! [`ComponentSubscriptionCommandsExt::unsubscribe_component`].
+//! The server then stops sending inserts and updates of that component to the client.
+//!
+//! When the client subscribes to the component again, the server sends the current value of the component.
+use bevy::ecs::entity::{EntityHashMap, MapEntities};
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+use tracing::error;
+
+use crate::channel::builder::ComponentSubscriptionChannel;
+use crate::prelude::client::ConnectionManager;
+use crate::prelude::{ComponentRegistry, Deserialize, Serialize};
+use crate::protocol::component::{ComponentKind, ComponentNetId};
+
+/// Message sent by a client to subscribe or unsubscribe to a component
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ComponentSubscription {
+    /// The entity to (un)subscribe to. If None, applies to all entities
+    pub entity: Option<Entity>,
+    pub net_id: ComponentNetId,
+    pub subscribed: bool,
+}
+
+impl MapEntities for ComponentSubscription {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        if let Some(entity) = self.entity.as_mut() {
+            *entity = entity_mapper.map_entity(*entity);
+        }
+    }
+}
+
+/// Components that a client unsubscribed from, stored on the server
+#[derive(Debug, Default)]
+pub(crate) struct ComponentSubscriptions {
+    /// Components that the client unsubscribed from for all entities
+    unsubscribed: HashSet<ComponentKind>,
+    /// Components that the client unsubscribed from for specific entities
+    unsubscribed_entities: EntityHashMap<HashSet<ComponentKind>>,
+    /// Components that the client subscribed to again since the last replication send.
+    /// The client needs to receive the current value of these components.
+    resubscribed: Vec<(Option<Entity>, ComponentKind)>,
+}
+
+impl ComponentSubscriptions {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.unsubscribed.is_empty()
+            && self.unsubscribed_entities.is_empty()
+            && self.resubscribed.is_empty()
+    }
+
+    /// Returns true if the client receives the component `kind` for this entity
+    pub(crate) fn is_subscribed(&self, entity: Entity, kind: ComponentKind) -> bool {
+        !self.unsubscribed.contains(&kind)
+            && self
+                .unsubscribed_entities
+                .get(&entity)
+                .is_none_or(|kinds| !kinds.contains(&kind))
+    }
+
+    /// Returns true if the client subscribed to the component again since the last replication send
+    pub(crate) fn is_resubscribed(&self, entity: Entity, kind: ComponentKind) -> bool {
+        self.resubscribed
+            .iter()
+            .any(|(e, k)| *k == kind && e.is_none_or(|e| e == entity))
+    }
+
+    /// Update the subscription of the client to the component `kind`.
+    ///
+    /// If `entity` is None, the subscription applies to all entities.
+    pub(crate) fn update(&mut self, entity: Option<Entity>, kind: ComponentKind, subscribed: bool) {
+        let changed = match (entity, subscribed) {
+            (None, false) => self.unsubscribed.insert(kind),
+            (None, true) => self.unsubscribed.remove(&kind),
+            (Some(entity), false) => self
+                .unsubscribed_entities
+                .entry(entity)
+                .or_default()
+                .insert(kind),
+            (Some(entity), true) => {
+                let Some(kinds) = self.unsubscribed_entities.get_mut(&entity) else {
+                    return;
+                };
+                let removed = kinds.remove(&kind);
+                if kinds.is_empty() {
+                    self.unsubscribed_entities.remove(&entity);
+                }
+                removed
+            }
+        };
+        if subscribed && changed {
+            self.resubscribed.push((entity, kind));
+        }
+    }
+
+    /// Remove the per-entity subscriptions of a despawned entity
+    pub(crate) fn remove_entity(&mut self, entity: Entity) {
+        self.unsubscribed_entities.remove(&entity);
+    }
+
+    pub(crate) fn clear_resubscribed(&mut self) {
+        self.resubscribed.clear();
+    }
+}
+
+/// Extension trait on [`Commands`] to let a client choose which replicated components it receives
+pub trait ComponentSubscriptionCommandsExt {
+    /// Stop receiving the component `C` from the server.
+    ///
+    /// If `entity` is None, the client stops receiving `C` for all entities. Otherwise, `entity`
+    /// is the local entity that was replicated from the server; for predicted or interpolated entities,
+    /// use the [`Confirmed`](crate::prelude::client::Confirmed) entity.
+    fn unsubscribe_component<C: Component>(&mut self, entity: Option<Entity>);
+
+    /// Receive the component `C` from the server again, after calling
+    /// [`unsubscribe_component`](ComponentSubscriptionCommandsExt::unsubscribe_component).
+    ///
+    /// The server sends the current value of the component.
+    fn subscribe_component<C: Component>(&mut self, entity: Option<Entity>);
+}
+
+impl ComponentSubscriptionCommandsExt for Commands<'_, '_> {
+    fn unsubscribe_component<C: Component>(&mut self, entity: Option<Entity>) {
+        self.queue(move |world: &mut World| send_subscription::<C>(world, entity, false));
+    }
+
+    fn subscribe_component<C: Component>(&mut self, entity: Option<Entity>) {
+        self.queue(move |world: &mut World| send_subscription::<C>(world, entity, true));
+    }
+}
+
+fn send_subscription<C: Component>(world: &mut World, entity: Option<Entity>, subscribed: bool) {
+    let Some(net_id) = world.resource::<ComponentRegistry>().get_net_id::<C>() else {
+        error!(
+            "Cannot subscribe to component {} because it is not registered",
+            std::any::type_name::<C>()
+        );
+        return;
+    };
+    let message = ComponentSubscription {
+        entity,
+        net_id,
+        subscribed,
+    };
+    let _ = world
+        .resource_mut::<ConnectionManager>()
+        .send_message::<ComponentSubscriptionChannel, _>(&message)
+        .inspect_err(|e| error!("Could not send the component subscription: {:?}", e));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::client;
+    use crate::prelude::server::Replicate;
+    use crate::tests::protocol::{ComponentSyncModeFull, ComponentSyncModeSimple};
+    use crate::tests::stepper::BevyStepper;
+
+    /// The client unsubscribes from a component: it stops receiving updates of that component,
+    /// but still receives the other components of the entity
+    #[test]
+    fn test_unsubscribe_component() {
+        let mut stepper = BevyStepper::default();
+        let server_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn((
+                Replicate::default(),
+                ComponentSyncModeFull(1.0),
+                ComponentSyncModeSimple(1.0),
+            ))
+            .id();
+        stepper.frame_step();
+        stepper.frame_step();
+        let client_entity = stepper
+            .client_app
+            .world()
+            .resource::<client::ConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(server_entity)
+            .expect("entity was not replicated to client");
+
+        stepper
+            .client_app
+            .world_mut()
+            .commands()
+            .unsubscribe_component::<ComponentSyncModeFull>(Some(client_entity));
+        stepper.client_app.world_mut().flush();
+        stepper.frame_step();
+        stepper.frame_step();
+
+        let mut server_entity_mut = stepper.server_app.world_mut().entity_mut(server_entity);
+        server_entity_mut
+            .get_mut::<ComponentSyncModeFull>()
+            .unwrap()
+            .0 = 2.0;
+        server_entity_mut
+            .get_mut::<ComponentSyncModeSimple>()
+            .unwrap()
+            .0 = 2.0;
+        stepper.frame_step();
+        stepper.frame_step();
+
+        let client_world = stepper.client_app.world();
+        assert_eq!(
+            client_world.get::<ComponentSyncModeFull>(client_entity),
+            Some(&ComponentSyncModeFull(1.0))
+        );
+        assert_eq!(
+            client_world.get::<ComponentSyncModeSimple>(client_entity),
+            Some(&ComponentSyncModeSimple(2.0))
+        );
+
+        // subscribe again: the client receives the current value
+        stepper
+            .client_app
+            .world_mut()
+            .commands()
+            .subscribe_component::<ComponentSyncModeFull>(Some(client_entity));
+        stepper.client_app.world_mut().flush();
+        stepper.frame_step();
+        stepper.frame_step();
+        assert_eq!(
+            stepper
+                .client_app
+                .world()
+                .get::<ComponentSyncModeFull>(client_entity),
+            Some(&ComponentSyncModeFull(2.0))
+        );
+    }
+
+    /// The client unsubscribes from a component for all entities, including the entities spawned later
+    #[test]
+    fn test_unsubscribe_component_globally() {
+        let mut stepper = BevyStepper::default();
+        stepper
+            .client_app
+            .world_mut()
+            .commands()
+            .unsubscribe_component::<ComponentSyncModeFull>(None);
+        stepper.client_app.world_mut().flush();
+        stepper.frame_step();
+        stepper.frame_step();
+
+        let server_entity = stepper
+            .server_app
+            .world_mut()
+            .spawn((
+                Replicate::default(),
+                ComponentSyncModeFull(1.0),
+                ComponentSyncModeSimple(1.0),
+            ))
+            .id();
+        stepper.frame_step();
+        stepper.frame_step();
+        let client_entity = stepper
+            .client_app
+            .world()
+            .resource::<client::ConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(server_entity)
+            .expect("entity was not replicated to client");
+        let client_world = stepper.client_app.world();
+        assert!(client_world
+            .get::<ComponentSyncModeFull>(client_entity)
+            .is_none());
+        assert_eq!(
+            client_world.get::<ComponentSyncModeSimple>(client_entity),
+            Some(&ComponentSyncModeSimple(1.0))
+        );
+    }
+}
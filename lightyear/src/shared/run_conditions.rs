@@ -2,12 +2,17 @@
 use crate::shared::identity::NetworkIdentityState;
 use bevy::prelude::{Res, State};
 
-/// Returns true if the peer is a client (host-server counts as a server)
+/// Returns true if the peer is a client (host-server counts as a server, a relay counts as both)
 pub fn is_client(identity: Option<Res<State<NetworkIdentityState>>>) -> bool {
-    identity.is_some_and(|i| i.get() == &NetworkIdentityState::Client)
+    identity.is_some_and(|i| {
+        matches!(
+            i.get(),
+            NetworkIdentityState::Client | NetworkIdentityState::Relay
+        )
+    })
 }
 
-/// Returns true if the peer is a server
+/// Returns true if the peer is a server (including host-server and relay)
 pub fn is_server(identity: Option<Res<State<NetworkIdentityState>>>) -> bool {
     identity.is_some_and(|i| i.get() != &NetworkIdentityState::Client)
 }
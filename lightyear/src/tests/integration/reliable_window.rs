@@ -0,0 +1,115 @@
+use crate::prelude::server::{ConnectionManager, ReliableWindowFull};
+use crate::prelude::*;
+use crate::tests::protocol::*;
+use crate::tests::stepper::{BevyStepper, TEST_CLIENT_ID};
+use bevy::prelude::*;
+
+#[derive(Resource, Default)]
+struct WindowFullEvents(Vec<ReliableWindowFull>);
+
+fn collect_window_full_events(
+    mut events: EventReader<ReliableWindowFull>,
+    mut collected: ResMut<WindowFullEvents>,
+) {
+    collected.0.extend(events.read().copied());
+}
+
+/// Step only the server, so that the client stops sending acks
+fn server_step(stepper: &mut BevyStepper) {
+    stepper.advance_time(stepper.frame_duration);
+    stepper.server_app.update();
+}
+
+/// If the client stops acking messages, the reliable channel window gets full:
+/// a [`ReliableWindowFull`] event is emitted and the client is disconnected once the
+/// [`WindowFullPolicy::DisconnectOnWindowFull`] timeout elapses
+#[test]
+fn test_reliable_window_full_disconnects_stalled_client() {
+    let mut stepper = BevyStepper::default();
+    stepper.server_app.init_resource::<WindowFullEvents>();
+    stepper
+        .server_app
+        .add_systems(Update, collect_window_full_events);
+    let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+
+    // the window only fits 2 in-flight messages, the third one has to wait
+    for i in 0..3 {
+        stepper
+            .server_app
+            .world_mut()
+            .resource_mut::<ConnectionManager>()
+            .send_message::<ReliableWindowChannel, StringMessage>(
+                client_id,
+                &StringMessage(i.to_string()),
+            )
+            .unwrap();
+    }
+
+    // the client is stalled: the window stays full
+    for _ in 0..10 {
+        server_step(&mut stepper);
+    }
+    assert_eq!(
+        stepper.server_app.world().resource::<WindowFullEvents>().0,
+        vec![ReliableWindowFull {
+            client_id,
+            channel: ChannelKind::of::<ReliableWindowChannel>(),
+        }]
+    );
+    assert!(stepper
+        .server_app
+        .world()
+        .resource::<ConnectionManager>()
+        .connection(client_id)
+        .is_ok());
+
+    // after the timeout, the client gets disconnected
+    for _ in 0..20 {
+        server_step(&mut stepper);
+    }
+    assert!(stepper
+        .server_app
+        .world()
+        .resource::<ConnectionManager>()
+        .connection(client_id)
+        .is_err());
+    // the event was only emitted once
+    assert_eq!(
+        stepper
+            .server_app
+            .world()
+            .resource::<WindowFullEvents>()
+            .0
+            .len(),
+        1
+    );
+}
+
+/// A client that keeps acking messages is not disconnected, even if the server keeps more
+/// messages in the queue than what fits in the window
+#[test]
+fn test_reliable_window_full_acking_client_stays_connected() {
+    let mut stepper = BevyStepper::default();
+    let client_id = ClientId::Netcode(TEST_CLIENT_ID);
+
+    for i in 0..60 {
+        for j in 0..3 {
+            stepper
+                .server_app
+                .world_mut()
+                .resource_mut::<ConnectionManager>()
+                .send_message::<ReliableWindowChannel, StringMessage>(
+                    client_id,
+                    &StringMessage(format!("{i}-{j}")),
+                )
+                .unwrap();
+        }
+        stepper.frame_step();
+    }
+    assert!(stepper
+        .server_app
+        .world()
+        .resource::<ConnectionManager>()
+        .connection(client_id)
+        .is_ok());
+}
@@ -7,4 +7,5 @@ mod integration;
 
 pub(crate) mod multi_stepper;
 pub mod protocol;
+pub(crate) mod relay_stepper;
 pub(crate) mod stepper;
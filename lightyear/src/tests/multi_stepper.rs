@@ -1,5 +1,7 @@
 //! Tests related to the server using multiple transports at the same time to connect to clients
 use crate::client::networking::ClientCommandsExt;
+#[cfg(feature = "leafwing")]
+use bevy::input::InputPlugin;
 use bevy::prelude::{default, App, PluginGroup, Real, Time};
 use bevy::state::app::StatesPlugin;
 use bevy::time::TimeUpdateStrategy;
@@ -55,13 +57,62 @@ impl Default for MultiBevyStepper {
     }
 }
 
+// Do not forget to use --features mock_time when using the LinkConditioner
 impl MultiBevyStepper {
+    /// Create a stepper where the packets received by the server and by both clients go through
+    /// a [`LinkConditioner`](crate::transport::middleware::conditioner::LinkConditioner)
+    /// that adds latency, jitter and packet loss
+    pub fn with_conditioner(conditioner: LinkConditionerConfig) -> Self {
+        let frame_duration = Duration::from_millis(10);
+        let tick_duration = Duration::from_millis(10);
+        let shared_config = SharedConfig {
+            tick: TickConfig::new(tick_duration),
+            ..Default::default()
+        };
+        let mut stepper = Self::new_with_conditioner(
+            shared_config,
+            SyncConfig::default().speedup_factor(1.0),
+            PredictionConfig::default(),
+            InterpolationConfig::default(),
+            frame_duration,
+            Some(conditioner),
+        );
+        stepper.build();
+        stepper.init();
+        // the connection can take longer to complete when packets are lost
+        for _ in 0..1000 {
+            if stepper.is_synced() {
+                break;
+            }
+            stepper.frame_step();
+        }
+        stepper
+    }
+
     pub fn new(
         shared_config: SharedConfig,
         sync_config: SyncConfig,
         prediction_config: PredictionConfig,
         interpolation_config: InterpolationConfig,
         frame_duration: Duration,
+    ) -> Self {
+        Self::new_with_conditioner(
+            shared_config,
+            sync_config,
+            prediction_config,
+            interpolation_config,
+            frame_duration,
+            None,
+        )
+    }
+
+    pub fn new_with_conditioner(
+        shared_config: SharedConfig,
+        sync_config: SyncConfig,
+        prediction_config: PredictionConfig,
+        interpolation_config: InterpolationConfig,
+        frame_duration: Duration,
+        conditioner: Option<LinkConditionerConfig>,
     ) -> Self {
         let now = bevy::utils::Instant::now();
 
@@ -87,10 +138,13 @@ impl MultiBevyStepper {
         // client net config 1: use local channels
         let (from_server_send, from_server_recv) = crossbeam_channel::unbounded();
         let (to_server_send, to_server_recv) = crossbeam_channel::unbounded();
-        let client_io = client::IoConfig::from_transport(ClientTransport::LocalChannel {
+        let mut client_io = client::IoConfig::from_transport(ClientTransport::LocalChannel {
             recv: from_server_recv,
             send: to_server_send,
         });
+        if let Some(conditioner) = &conditioner {
+            client_io = client_io.with_conditioner(conditioner.clone());
+        }
         let client_params = (LOCAL_SOCKET, to_server_recv, from_server_send);
         let net_config_1 = NetConfig::Netcode {
             auth: auth_1,
@@ -100,17 +154,23 @@ impl MultiBevyStepper {
 
         // TODO: maybe we don't need the server Channels transport and instead we can just have multiple
         //  concurrent LocalChannel connections? seems easier to reason about!
-        let server_io_1 = server::IoConfig::from_transport(ServerTransport::Channels {
+        let mut server_io_1 = server::IoConfig::from_transport(ServerTransport::Channels {
             channels: vec![client_params],
         });
+        if let Some(conditioner) = &conditioner {
+            server_io_1 = server_io_1.with_conditioner(conditioner.clone());
+        }
 
         // client net config 2: use local channels
         let (from_server_send, from_server_recv) = crossbeam_channel::unbounded();
         let (to_server_send, to_server_recv) = crossbeam_channel::unbounded();
-        let client_io = client::IoConfig::from_transport(ClientTransport::LocalChannel {
+        let mut client_io = client::IoConfig::from_transport(ClientTransport::LocalChannel {
             recv: from_server_recv,
             send: to_server_send,
         });
+        if let Some(conditioner) = &conditioner {
+            client_io = client_io.with_conditioner(conditioner.clone());
+        }
         let client_params = (LOCAL_SOCKET, to_server_recv, from_server_send);
         let net_config_2 = NetConfig::Netcode {
             auth: auth_2,
@@ -118,9 +178,12 @@ impl MultiBevyStepper {
             io: client_io,
         };
 
-        let server_io_2 = server::IoConfig::from_transport(ServerTransport::Channels {
+        let mut server_io_2 = server::IoConfig::from_transport(ServerTransport::Channels {
             channels: vec![client_params],
         });
+        if let Some(conditioner) = &conditioner {
+            server_io_2 = server_io_2.with_conditioner(conditioner.clone());
+        }
 
         // build server with two distinct transports
         let mut server_app = App::new();
@@ -144,6 +207,11 @@ impl MultiBevyStepper {
         };
         let plugin = server::ServerPlugins::new(config);
         server_app.add_plugins((plugin, ProtocolPlugin));
+        #[cfg(feature = "leafwing")]
+        {
+            server_app.add_plugins(LeafwingInputPlugin::<LeafwingInput1>::default());
+            server_app.add_plugins(LeafwingInputPlugin::<LeafwingInput2>::default());
+        }
         // Initialize Real time (needed only for the first TimeSystem run)
         server_app
             .world_mut()
@@ -165,6 +233,12 @@ impl MultiBevyStepper {
             };
             let plugin = client::ClientPlugins::new(config);
             client_app.add_plugins((plugin, ProtocolPlugin));
+            #[cfg(feature = "leafwing")]
+            {
+                client_app.add_plugins(LeafwingInputPlugin::<LeafwingInput1>::default());
+                client_app.add_plugins(LeafwingInputPlugin::<LeafwingInput2>::default());
+                client_app.add_plugins(InputPlugin);
+            }
             // Initialize Real time (needed only for the first TimeSystem run)
             client_app
                 .world_mut()
@@ -201,23 +275,26 @@ impl MultiBevyStepper {
 
         // Advance the world to let the connection process complete
         for _ in 0..100 {
-            if self
-                .client_app_1
-                .world()
-                .resource::<client::ConnectionManager>()
-                .is_synced()
-                && self
-                    .client_app_2
-                    .world()
-                    .resource::<client::ConnectionManager>()
-                    .is_synced()
-            {
+            if self.is_synced() {
                 return;
             }
             self.frame_step();
         }
     }
 
+    /// Returns true if both clients are synced with the server
+    pub(crate) fn is_synced(&self) -> bool {
+        self.client_app_1
+            .world()
+            .resource::<client::ConnectionManager>()
+            .is_synced()
+            && self
+                .client_app_2
+                .world()
+                .resource::<client::ConnectionManager>()
+                .is_synced()
+    }
+
     pub(crate) fn advance_time(&mut self, duration: Duration) {
         self.current_time += duration;
         self.client_app_1
@@ -2,15 +2,18 @@ use std::ops::{Add, Mul};
 
 use bevy::app::{App, Plugin};
 use bevy::ecs::entity::MapEntities;
-use bevy::prelude::{default, Component, Entity, EntityMapper, Event, Reflect, Resource};
-use bevy::utils::HashSet;
+use bevy::prelude::{
+    default, Component, Entity, EntityMapper, Event, IVec2, Reflect, Resource, Vec3,
+};
+use bevy::utils::{Duration, HashSet};
 use byteorder::{NetworkEndian, ReadBytesExt, WriteBytesExt};
 use cfg_if::cfg_if;
-use lightyear_macros::ChannelInternal;
+use lightyear_macros::{ChannelInternal, DiffableInternal};
 use serde::{Deserialize, Serialize};
 
 use crate::client::components::ComponentSyncMode;
 use crate::prelude::*;
+use crate::protocol::message::intent::AppIntentExt;
 use crate::protocol::message::registry::AppMessageExt;
 use crate::protocol::message::resource::AppResourceExt;
 use crate::protocol::message::trigger::AppTriggerExt;
@@ -77,6 +80,10 @@ pub(crate) fn deserialize_component2(
 #[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq, Reflect)]
 pub struct ComponentSyncModeOnce(pub f32);
 
+/// Marker component without any data
+#[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq, Reflect)]
+pub struct ComponentMarker;
+
 #[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq, Reflect)]
 pub struct ComponentMapEntities(pub Entity);
 
@@ -104,6 +111,27 @@ impl Add<Self> for ComponentSyncModeFull2 {
     }
 }
 
+/// Component interpolated with a custom step function
+#[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq, Reflect)]
+pub struct ComponentInterpolationStep(pub f32);
+
+/// Jumps to the end value halfway through the interpolation
+pub(crate) fn step_interpolation(
+    start: &ComponentInterpolationStep,
+    other: &ComponentInterpolationStep,
+    t: f32,
+) -> ComponentInterpolationStep {
+    if t < 0.5 {
+        start.clone()
+    } else {
+        other.clone()
+    }
+}
+
+/// Component interpolated without an interpolation function
+#[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq, Reflect)]
+pub struct ComponentInterpolationSnap(pub f32);
+
 #[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq, Reflect)]
 pub struct ComponentDeltaCompression(pub Vec<usize>);
 
@@ -150,12 +178,108 @@ impl Diffable for ComponentDeltaCompression2 {
     }
 }
 
+/// Numeric component where the delta is the difference between the two positions.
+/// The delta is not idempotent: applying it to the wrong baseline gives a wrong position.
+#[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq, Reflect)]
+pub struct ComponentDeltaCompressionPosition(pub IVec2);
+
+impl Diffable for ComponentDeltaCompressionPosition {
+    type Delta = IVec2;
+
+    fn base_value() -> Self {
+        Self(IVec2::ZERO)
+    }
+
+    fn diff(&self, other: &Self) -> Self::Delta {
+        other.0 - self.0
+    }
+
+    fn apply_diff(&mut self, delta: &Self::Delta) {
+        self.0 += *delta;
+    }
+}
+
+/// Large component where only the fields that changed are sent
+#[derive(
+    Component, Serialize, Deserialize, Clone, Debug, Default, PartialEq, Reflect, DiffableInternal,
+)]
+#[replicate(field_mask)]
+pub struct ComponentDeltaCompressionFieldMask {
+    pub slots: Vec<u32>,
+    pub gold: u32,
+}
+
 #[derive(Component, Clone, Debug, PartialEq, Reflect)]
 pub struct ComponentRollback(pub f32);
 
 #[derive(Component, Clone, Debug, PartialEq, Reflect, Serialize, Deserialize)]
 pub struct ComponentClientToServer(pub f32);
 
+/// Component that is always controlled by the server, even if a client has authority over the entity
+#[derive(Component, Clone, Debug, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct ComponentServerAuthoritative(pub f32);
+
+/// Position-like component that is serialized relative to the replication origin of each client.
+/// The coordinates are quantized to centimeters in an i16, so they can only be sent accurately
+/// if they are close to the origin.
+#[derive(Component, Clone, Debug, PartialEq, Reflect)]
+pub struct ComponentOriginRebase(pub Vec3);
+
+impl OriginRebase for ComponentOriginRebase {
+    fn rebase(&self, origin: Vec3) -> Self {
+        Self(self.0 - origin)
+    }
+
+    fn restore(&mut self, origin: Vec3) {
+        self.0 += origin;
+    }
+}
+
+pub(crate) fn serialize_origin_rebase(
+    data: &ComponentOriginRebase,
+    writer: &mut Writer,
+) -> Result<(), SerializationError> {
+    for value in data.0.to_array() {
+        let quantized = (value * 100.0)
+            .round()
+            .clamp(i16::MIN as f32, i16::MAX as f32);
+        writer.write_i16::<NetworkEndian>(quantized as i16)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn deserialize_origin_rebase(
+    reader: &mut Reader,
+) -> Result<ComponentOriginRebase, SerializationError> {
+    let mut value = [0.0; 3];
+    for v in value.iter_mut() {
+        *v = reader.read_i16::<NetworkEndian>()? as f32 / 100.0;
+    }
+    Ok(ComponentOriginRebase(Vec3::from_array(value)))
+}
+
+/// Component whose deserialization fails for negative values, to simulate a corrupted payload
+#[derive(Component, Clone, Debug, PartialEq, Reflect)]
+pub struct ComponentDeserializeFail(pub f32);
+
+pub(crate) fn serialize_deserialize_fail(
+    data: &ComponentDeserializeFail,
+    writer: &mut Writer,
+) -> Result<(), SerializationError> {
+    writer.write_f32::<NetworkEndian>(data.0)?;
+    Ok(())
+}
+
+pub(crate) fn deserialize_deserialize_fail(
+    reader: &mut Reader,
+) -> Result<ComponentDeserializeFail, SerializationError> {
+    let value = reader.read_f32::<NetworkEndian>()?;
+    if value < 0.0 {
+        return Err(SerializationError::InvalidValue);
+    }
+    Ok(ComponentDeserializeFail(value))
+}
+
 // Resources
 #[derive(Resource, Serialize, Deserialize, Debug, PartialEq, Clone, Reflect)]
 pub struct Resource1(pub f32);
@@ -177,11 +301,32 @@ pub(crate) fn deserialize_resource2(reader: &mut Reader) -> Result<Resource2, Se
     Ok(Resource2(data))
 }
 
+// Intents
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Reflect)]
+pub struct MoveIntent {
+    pub target: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Reflect)]
+pub struct MoveResult {
+    pub position: i32,
+}
+
+// Tick events
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Reflect)]
+pub struct ExplosionEvent {
+    pub position: i32,
+}
+
 // Inputs
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, Reflect)]
 pub struct MyInput(pub i16);
 
+/// Second input type, handled independently of [`MyInput`]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, Reflect)]
+pub struct MyOtherInput(pub i16);
+
 // Protocol
 cfg_if! {
     if #[cfg(feature = "leafwing")] {
@@ -205,6 +350,13 @@ pub struct Channel1;
 #[derive(ChannelInternal, Reflect)]
 pub struct Channel2;
 
+/// Reliable channel with a small window of in-flight messages
+#[derive(ChannelInternal, Reflect)]
+pub struct ReliableWindowChannel;
+
+#[derive(ChannelInternal, Reflect)]
+pub struct ReliableChannel;
+
 // Protocol
 
 pub(crate) struct ProtocolPlugin;
@@ -216,13 +368,19 @@ impl Plugin for ProtocolPlugin {
         app.register_message::<StringMessage>(ChannelDirection::Bidirectional);
         app.register_message::<EntityMessage>(ChannelDirection::Bidirectional)
             .add_map_entities();
+        // intents
+        app.register_intent::<MoveIntent, MoveResult>();
+        // tick events
+        app.register_tick_event::<ExplosionEvent>();
         // inputs
         app.add_plugins(InputPlugin::<MyInput>::default());
+        app.add_plugins(InputPlugin::<MyOtherInput>::default());
         // components
         app.register_component::<ComponentSyncModeFull>(ChannelDirection::Bidirectional)
             .add_prediction(ComponentSyncMode::Full)
             .add_interpolation(ComponentSyncMode::Full)
-            .add_linear_interpolation_fn();
+            .add_linear_interpolation_fn()
+            .add_lag_compensation();
 
         app.register_component_custom_serde::<ComponentSyncModeSimple>(
             ChannelDirection::ServerToClient,
@@ -236,6 +394,8 @@ impl Plugin for ProtocolPlugin {
         app.register_component::<ComponentSyncModeOnce>(ChannelDirection::ServerToClient)
             .add_prediction(ComponentSyncMode::Once);
 
+        app.register_component::<ComponentMarker>(ChannelDirection::ServerToClient);
+
         app.register_component::<ComponentMapEntities>(ChannelDirection::Bidirectional)
             .add_prediction(ComponentSyncMode::Simple)
             .add_map_entities();
@@ -243,7 +403,15 @@ impl Plugin for ProtocolPlugin {
         app.register_component::<ComponentSyncModeFull2>(ChannelDirection::ServerToClient)
             .add_prediction(ComponentSyncMode::Full)
             .add_interpolation(ComponentSyncMode::Full)
-            .add_linear_interpolation_fn();
+            .add_linear_interpolation_fn()
+            .add_display_smoothing(10.0);
+
+        app.register_component::<ComponentInterpolationStep>(ChannelDirection::ServerToClient)
+            .add_interpolation(ComponentSyncMode::Full)
+            .add_interpolation_fn(step_interpolation);
+
+        app.register_component::<ComponentInterpolationSnap>(ChannelDirection::ServerToClient)
+            .add_interpolation(ComponentSyncMode::Full);
 
         app.register_component::<ComponentDeltaCompression>(ChannelDirection::ServerToClient)
             .add_delta_compression();
@@ -251,10 +419,40 @@ impl Plugin for ProtocolPlugin {
         app.register_component::<ComponentDeltaCompression2>(ChannelDirection::ServerToClient)
             .add_delta_compression();
 
+        app.register_component::<ComponentDeltaCompressionPosition>(
+            ChannelDirection::ServerToClient,
+        )
+        .add_delta_compression();
+
+        app.register_component::<ComponentDeltaCompressionFieldMask>(
+            ChannelDirection::ServerToClient,
+        )
+        .add_delta_compression();
+
         app.add_rollback::<ComponentRollback>();
 
         app.register_component::<ComponentClientToServer>(ChannelDirection::ClientToServer);
 
+        app.register_component::<ComponentServerAuthoritative>(ChannelDirection::Bidirectional)
+            .server_authoritative();
+
+        app.register_component_custom_serde::<ComponentOriginRebase>(
+            ChannelDirection::ServerToClient,
+            SerializeFns {
+                serialize: serialize_origin_rebase,
+                deserialize: deserialize_origin_rebase,
+            },
+        )
+        .add_origin_rebasing();
+
+        app.register_component_custom_serde::<ComponentDeserializeFail>(
+            ChannelDirection::ServerToClient,
+            SerializeFns {
+                serialize: serialize_deserialize_fail,
+                deserialize: deserialize_deserialize_fail,
+            },
+        );
+
         // resources
         app.register_resource::<Resource1>(ChannelDirection::ServerToClient);
         app.register_resource_custom_serde::<Resource2>(
@@ -273,5 +471,19 @@ impl Plugin for ProtocolPlugin {
             mode: ChannelMode::UnorderedUnreliableWithAcks,
             ..default()
         });
+        app.add_channel::<ReliableChannel>(ChannelSettings {
+            mode: ChannelMode::UnorderedReliable(ReliableSettings::default()),
+            ..default()
+        });
+        app.add_channel::<ReliableWindowChannel>(ChannelSettings {
+            mode: ChannelMode::UnorderedReliable(ReliableSettings {
+                max_unacked_messages: Some(2),
+                window_full_policy: WindowFullPolicy::DisconnectOnWindowFull {
+                    timeout: Duration::from_millis(200),
+                },
+                ..default()
+            }),
+            ..default()
+        });
     }
 }
@@ -0,0 +1,199 @@
+//! Stepper with a source server, a relay (client of the source server and server of the client) and a client
+use crate::client::networking::ClientCommandsExt;
+use crate::connection::netcode::generate_key;
+use crate::prelude::client::{Authentication, ClientConfig, ClientTransport, NetConfig};
+use crate::prelude::server::{
+    NetcodeConfig, RelayPlugin, ServerCommandsExt, ServerConfig, ServerTransport,
+};
+use crate::prelude::*;
+use crate::tests::protocol::*;
+use crate::transport::LOCAL_SOCKET;
+use bevy::prelude::{default, App, Real, Time};
+use bevy::state::app::StatesPlugin;
+use bevy::time::TimeUpdateStrategy;
+use bevy::utils::Duration;
+use bevy::MinimalPlugins;
+
+pub const RELAY_CLIENT_ID: u64 = 111;
+pub const TEST_CLIENT_ID: u64 = 112;
+
+pub struct RelayStepper {
+    pub source_app: App,
+    pub relay_app: App,
+    pub client_app: App,
+    pub frame_duration: Duration,
+    pub current_time: bevy::utils::Instant,
+}
+
+/// Create the server io and the client io of a connection between a server and a client
+fn local_io() -> (server::IoConfig, client::IoConfig) {
+    let (from_server_send, from_server_recv) = crossbeam_channel::unbounded();
+    let (to_server_send, to_server_recv) = crossbeam_channel::unbounded();
+    let client_io = client::IoConfig::from_transport(ClientTransport::LocalChannel {
+        send: to_server_send,
+        recv: from_server_recv,
+    });
+    let server_io = server::IoConfig::from_transport(ServerTransport::Channels {
+        channels: vec![(LOCAL_SOCKET, to_server_recv, from_server_send)],
+    });
+    (server_io, client_io)
+}
+
+fn server_config(shared_config: SharedConfig, io: server::IoConfig, key: Key) -> ServerConfig {
+    ServerConfig {
+        shared: shared_config,
+        net: vec![server::NetConfig::Netcode {
+            config: NetcodeConfig::default().with_key(key),
+            io,
+        }],
+        ping: PingConfig {
+            // send pings every tick, so that the acks are received every frame
+            ping_interval: Duration::default(),
+            ..default()
+        },
+        ..default()
+    }
+}
+
+fn client_config(
+    mut client_config: ClientConfig,
+    shared_config: SharedConfig,
+    io: client::IoConfig,
+    key: Key,
+    client_id: u64,
+) -> ClientConfig {
+    client_config.shared = shared_config;
+    // send pings every tick, so that the acks are received every frame
+    client_config.ping.ping_interval = Duration::default();
+    client_config.net = NetConfig::Netcode {
+        auth: Authentication::Manual {
+            server_addr: LOCAL_SOCKET,
+            protocol_id: 0,
+            private_key: key,
+            client_id,
+        },
+        config: Default::default(),
+        io,
+    };
+    client_config
+}
+
+impl RelayStepper {
+    /// `relay_client_config` is the config used by the relay to connect to the source server
+    pub fn new(
+        shared_config: SharedConfig,
+        relay_client_config: ClientConfig,
+        frame_duration: Duration,
+    ) -> Self {
+        let source_key = generate_key();
+        let relay_key = generate_key();
+        let (source_server_io, relay_client_io) = local_io();
+        let (relay_server_io, client_io) = local_io();
+
+        let mut source_app = App::new();
+        source_app.add_plugins((MinimalPlugins, StatesPlugin));
+        source_app.add_plugins((
+            server::ServerPlugins::new(server_config(shared_config, source_server_io, source_key)),
+            ProtocolPlugin,
+        ));
+
+        let mut relay_app = App::new();
+        relay_app.add_plugins((MinimalPlugins, StatesPlugin));
+        relay_app.add_plugins((
+            client::ClientPlugins::new(client_config(
+                relay_client_config,
+                shared_config,
+                relay_client_io,
+                source_key,
+                RELAY_CLIENT_ID,
+            )),
+            server::ServerPlugins::new(server_config(shared_config, relay_server_io, relay_key)),
+            ProtocolPlugin,
+            RelayPlugin::default(),
+        ));
+
+        let mut client_app = App::new();
+        client_app.add_plugins((MinimalPlugins, StatesPlugin));
+        client_app.add_plugins((
+            client::ClientPlugins::new(client_config(
+                ClientConfig::default(),
+                shared_config,
+                client_io,
+                relay_key,
+                TEST_CLIENT_ID,
+            )),
+            ProtocolPlugin,
+        ));
+
+        // Initialize Real time (needed only for the first TimeSystem run)
+        let now = bevy::utils::Instant::now();
+        for app in [&mut source_app, &mut relay_app, &mut client_app] {
+            app.world_mut()
+                .get_resource_mut::<Time<Real>>()
+                .unwrap()
+                .update_with_instant(now);
+        }
+        Self {
+            source_app,
+            relay_app,
+            client_app,
+            frame_duration,
+            current_time: now,
+        }
+    }
+
+    pub(crate) fn build(&mut self) {
+        for app in [
+            &mut self.source_app,
+            &mut self.relay_app,
+            &mut self.client_app,
+        ] {
+            app.finish();
+            app.cleanup();
+        }
+    }
+
+    pub(crate) fn init(&mut self) {
+        self.source_app.world_mut().start_server();
+        self.relay_app.world_mut().start_server();
+        self.relay_app.world_mut().connect_client();
+        self.client_app.world_mut().connect_client();
+        // Advance the world until the relay and the client are synced
+        for _ in 0..100 {
+            if self
+                .relay_app
+                .world()
+                .resource::<client::ConnectionManager>()
+                .is_synced()
+                && self
+                    .client_app
+                    .world()
+                    .resource::<client::ConnectionManager>()
+                    .is_synced()
+            {
+                break;
+            }
+            self.frame_step();
+        }
+    }
+
+    pub(crate) fn advance_time(&mut self, duration: Duration) {
+        self.current_time += duration;
+        for app in [
+            &mut self.source_app,
+            &mut self.relay_app,
+            &mut self.client_app,
+        ] {
+            app.insert_resource(TimeUpdateStrategy::ManualInstant(self.current_time));
+        }
+        mock_instant::global::MockClock::advance(duration);
+    }
+
+    /// Advance the world by one frame duration
+    pub(crate) fn frame_step(&mut self) {
+        self.advance_time(self.frame_duration);
+        self.source_app.update();
+        self.relay_app.update();
+        self.client_app.update();
+    }
+}
@@ -83,6 +83,8 @@ impl BevyStepper {
         let config = ServerConfig {
             shared: shared_config,
             net: vec![net_config],
+            packet: server::PacketConfig::default()
+                .with_compression(client_config.packet.compression),
             ping: PingConfig {
                 // send pings every tick, so that the acks are received every frame
                 ping_interval: Duration::default(),
@@ -108,11 +110,8 @@ impl BevyStepper {
         };
 
         client_config.shared = shared_config;
-        client_config.ping = PingConfig {
-            // send pings every tick, so that the acks are received every frame
-            ping_interval: Duration::default(),
-            ..default()
-        };
+        // send pings every tick, so that the acks are received every frame
+        client_config.ping.ping_interval = Duration::default();
         client_config.net = net_config;
 
         let plugin = client::ClientPlugins::new(client_config);
@@ -1,4 +1,4 @@
-use crate::transport::middleware::compression::CompressionConfig;
+use crate::packet::compression::CompressionConfig;
 use crate::transport::middleware::conditioner::LinkConditionerConfig;
 use bevy::prelude::Reflect;
 
@@ -8,10 +8,16 @@ pub struct SharedIoConfig<T> {
     #[reflect(ignore)]
     pub transport: T,
     pub conditioner: Option<LinkConditionerConfig>,
+    /// Only used if the `compression` of the client's or server's `PacketConfig` is
+    /// [`CompressionConfig::None`].
+    #[deprecated(
+        note = "Packets are now compressed before encryption: use `PacketConfig::compression` instead"
+    )]
     pub compression: CompressionConfig,
 }
 
 impl<T> SharedIoConfig<T> {
+    #[allow(deprecated)]
     pub fn from_transport(transport: T) -> Self {
         Self {
             transport,
@@ -24,6 +30,10 @@ impl<T> SharedIoConfig<T> {
         self
     }
 
+    #[deprecated(
+        note = "Packets are now compressed before encryption: use `PacketConfig::with_compression` instead"
+    )]
+    #[allow(deprecated)]
     pub fn with_compression(mut self, compression_config: CompressionConfig) -> Self {
         self.compression = compression_config;
         self
@@ -1,14 +1,11 @@
 //! Module defining 'wrappers' that modify the behaviour of an existing [`PacketReceiver`] or [`PacketSender`].
 //!
-//! Wrappers are used to add additional functionality to an existing transport, such as encryption, compression, metrics, etc.
+//! Wrappers are used to add additional functionality to an existing transport, such as encryption, metrics, etc.
 use crate::transport::{PacketReceiver, PacketSender};
 
 /// A conditioner is used to simulate network conditions such as latency, jitter and packet loss.
 pub(crate) mod conditioner;
 
-/// Middleware that compresses packets before sending them.
-pub(crate) mod compression;
-
 pub trait PacketReceiverWrapper<T: PacketReceiver> {
     fn wrap(self, receiver: T) -> impl PacketReceiver;
 }
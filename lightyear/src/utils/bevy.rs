@@ -1,10 +1,24 @@
 //! Implement lightyear traits for some common bevy types
+//!
+//! Rotations should not be interpolated with `add_linear_interpolation_fn`:
+//! a component-wise lerp of two quaternions is not normalized and can take the long way around.
+//! Register the spherical interpolation functions instead, for both interpolation and correction:
+//!
+//! ```rust,ignore
+//! app.register_component::<Transform>(ChannelDirection::ServerToClient)
+//!     .add_prediction(ComponentSyncMode::Full)
+//!     .add_interpolation(ComponentSyncMode::Full)
+//!     .add_interpolation_fn(TransformLinearInterpolation::lerp)
+//!     .add_correction_fn(TransformLinearInterpolation::lerp);
+//! ```
 
 use bevy::prelude::{Quat, Transform};
 use tracing::trace;
 
 use crate::client::components::LerpFn;
 
+/// Interpolate the translation and scale of a [`Transform`] linearly,
+/// and its rotation with a spherical linear interpolation
 pub struct TransformLinearInterpolation;
 
 impl LerpFn<Transform> for TransformLinearInterpolation {
@@ -29,6 +43,9 @@ impl LerpFn<Transform> for TransformLinearInterpolation {
 }
 
 /// Perform a spherical linear interpolation between two quaternions
+///
+/// The interpolation always takes the shortest path between the two rotations,
+/// even when they are on each side of the ±180° boundary.
 pub struct QuatSphericalLinearInterpolation;
 
 impl LerpFn<Quat> for QuatSphericalLinearInterpolation {
@@ -36,3 +53,41 @@ impl LerpFn<Quat> for QuatSphericalLinearInterpolation {
         start.slerp(*other, t)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::math::Vec3;
+    use std::f32::consts::PI;
+
+    /// Interpolating across the ±180° boundary goes through 180° instead of going around through 0°
+    #[test]
+    fn test_quat_slerp_across_180_degrees() {
+        let start = Quat::from_rotation_z(170f32.to_radians());
+        let end = Quat::from_rotation_z(-170f32.to_radians());
+        let mut previous = start;
+        for i in 1..=10 {
+            let t = i as f32 / 10.0;
+            let res = QuatSphericalLinearInterpolation::lerp(&start, &end, t);
+            assert!(res.is_normalized());
+            // the rotation progresses by 2° every step, for a total of 20°
+            assert!((start.angle_between(res) - (20.0 * t).to_radians()).abs() < 1e-3);
+            assert!((previous.angle_between(res) - 2f32.to_radians()).abs() < 1e-3);
+            previous = res;
+        }
+        let halfway = QuatSphericalLinearInterpolation::lerp(&start, &end, 0.5);
+        assert!(halfway.angle_between(Quat::from_rotation_z(PI)) < 1e-3);
+    }
+
+    #[test]
+    fn test_transform_lerp() {
+        let start = Transform::from_xyz(0.0, 0.0, 0.0)
+            .with_rotation(Quat::from_rotation_y(-175f32.to_radians()));
+        let end = Transform::from_xyz(2.0, 4.0, 0.0)
+            .with_rotation(Quat::from_rotation_y(175f32.to_radians()));
+        let res = TransformLinearInterpolation::lerp(&start, &end, 0.5);
+        assert_eq!(res.translation, Vec3::new(1.0, 2.0, 0.0));
+        assert_eq!(res.scale, Vec3::ONE);
+        assert!(res.rotation.angle_between(Quat::from_rotation_y(PI)) < 1e-3);
+    }
+}
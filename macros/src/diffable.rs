@@ -0,0 +1,167 @@
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Index, LitStr};
+
+/// Maximum number of fields supported by the field mask (the mask is stored as a u64)
+const MAX_FIELDS: usize = 64;
+
+pub fn diffable_impl(
+    input: proc_macro::TokenStream,
+    shared_crate_name: TokenStream,
+) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    // Only the field-mask mode is supported for now
+    let mut field_mask = false;
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|a| a.path().is_ident("replicate"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("field_mask") {
+                field_mask = true;
+                Ok(())
+            } else {
+                Err(meta.error("unsupported replicate attribute, expected `field_mask`"))
+            }
+        })
+        .unwrap_or_else(|e| panic!("{e}"));
+    }
+    if !field_mask {
+        panic!("Deriving Diffable requires the `#[replicate(field_mask)]` attribute");
+    }
+    if !input.generics.params.is_empty() {
+        panic!("Can only derive Diffable on a struct without generics");
+    }
+
+    // Fields
+    let Data::Struct(data_struct) = &input.data else {
+        panic!("Can only derive Diffable on a struct");
+    };
+    let (accessors, types): (Vec<TokenStream>, Vec<&syn::Type>) = match &data_struct.fields {
+        Fields::Named(fields) => fields
+            .named
+            .iter()
+            .map(|f| {
+                let ident = f.ident.as_ref().unwrap();
+                (quote! { #ident }, &f.ty)
+            })
+            .unzip(),
+        Fields::Unnamed(fields) => fields
+            .unnamed
+            .iter()
+            .enumerate()
+            .map(|(i, f)| {
+                let index = Index::from(i);
+                (quote! { #index }, &f.ty)
+            })
+            .unzip(),
+        Fields::Unit => panic!("Cannot derive Diffable on a Unit struct"),
+    };
+    let num_fields = accessors.len();
+    if num_fields > MAX_FIELDS {
+        panic!("Can only derive Diffable on a struct with at most {MAX_FIELDS} fields");
+    }
+
+    // Names
+    let vis = &input.vis;
+    let struct_name = &input.ident;
+    let delta_name = format_ident!("{}FieldMask", struct_name);
+    let expecting = LitStr::new(
+        &format!("a field mask for {struct_name} followed by the changed fields"),
+        Span::call_site(),
+    );
+    let values: Vec<_> = (0..num_fields)
+        .map(|i| format_ident!("field_{}", i))
+        .collect();
+    // index of the field inside the delta tuple struct (0 is the mask)
+    let delta_indices: Vec<_> = (1..=num_fields).map(Index::from).collect();
+    let bits: Vec<_> = (0..num_fields as u32).collect();
+    let tuple_len = num_fields + 1;
+
+    let gen = quote! {
+        // bitmask of the fields that changed, followed by the new values of those fields
+        #[doc(hidden)]
+        #[derive(Clone, PartialEq)]
+        #vis struct #delta_name(u64, #(Option<#types>),*);
+
+        impl ::serde::Serialize for #delta_name {
+            fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                use ::serde::ser::SerializeTuple;
+                let mut tuple = serializer.serialize_tuple(1 + self.0.count_ones() as usize)?;
+                tuple.serialize_element(&self.0)?;
+                #(
+                    if let Some(value) = &self.#delta_indices {
+                        tuple.serialize_element(value)?;
+                    }
+                )*
+                tuple.end()
+            }
+        }
+
+        impl<'de> ::serde::Deserialize<'de> for #delta_name {
+            fn deserialize<D: ::serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                struct FieldMaskVisitor;
+
+                impl<'de> ::serde::de::Visitor<'de> for FieldMaskVisitor {
+                    type Value = #delta_name;
+
+                    fn expecting(&self, formatter: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+                        formatter.write_str(#expecting)
+                    }
+
+                    fn visit_seq<A: ::serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                        let mask: u64 = seq
+                            .next_element()?
+                            .ok_or_else(|| ::serde::de::Error::invalid_length(0, &self))?;
+                        #(
+                            let #values = if mask & (1u64 << #bits) != 0 {
+                                let index = 1 + (mask & ((1u64 << #bits) - 1)).count_ones() as usize;
+                                Some(seq
+                                    .next_element()?
+                                    .ok_or_else(|| ::serde::de::Error::invalid_length(index, &self))?)
+                            } else {
+                                None
+                            };
+                        )*
+                        Ok(#delta_name(mask, #(#values),*))
+                    }
+                }
+
+                deserializer.deserialize_tuple(#tuple_len, FieldMaskVisitor)
+            }
+        }
+
+        impl #shared_crate_name::shared::replication::delta::Diffable for #struct_name {
+            type Delta = #delta_name;
+
+            fn base_value() -> Self {
+                Default::default()
+            }
+
+            fn diff(&self, new: &Self) -> Self::Delta {
+                let mut mask = 0u64;
+                #(
+                    let #values = if self.#accessors != new.#accessors {
+                        mask |= 1u64 << #bits;
+                        Some(new.#accessors.clone())
+                    } else {
+                        None
+                    };
+                )*
+                #delta_name(mask, #(#values),*)
+            }
+
+            fn apply_diff(&mut self, delta: &Self::Delta) {
+                #(
+                    if let Some(value) = &delta.#delta_indices {
+                        self.#accessors = value.clone();
+                    }
+                )*
+            }
+        }
+    };
+
+    proc_macro::TokenStream::from(gen)
+}
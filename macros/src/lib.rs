@@ -9,8 +9,10 @@ use quote::{format_ident, quote};
 use syn::{parse_macro_input, ItemEnum};
 
 use channel::channel_impl;
+use diffable::diffable_impl;
 
 mod channel;
+mod diffable;
 mod shared;
 
 // Channel
@@ -27,3 +29,35 @@ pub fn channel_derive(input: proc_macro::TokenStream) -> proc_macro::TokenStream
     let shared_crate_name = quote! { lightyear };
     channel_impl(input, shared_crate_name)
 }
+
+// Diffable
+#[doc(hidden)]
+#[proc_macro_derive(DiffableInternal, attributes(replicate))]
+pub fn diffable_derive_internal(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let shared_crate_name = quote! { crate };
+    diffable_impl(input, shared_crate_name)
+}
+
+/// Derives the Diffable trait for a given struct, so that it can be replicated with delta-compression.
+///
+/// With `#[replicate(field_mask)]`, the delta contains a bitmask of the fields that changed
+/// compared to the previous state, followed by only the values of those fields.
+/// The struct must implement `Default` (used as the base value when the receiver has never seen
+/// the component), and every field must implement `Clone`, `PartialEq`, `Serialize` and `Deserialize`.
+///
+/// ```rust,ignore
+/// #[derive(Component, Serialize, Deserialize, Clone, Default, PartialEq, Diffable)]
+/// #[replicate(field_mask)]
+/// struct Inventory {
+///     slots: [u32; 32],
+///     gold: u32,
+/// }
+///
+/// app.register_component::<Inventory>(ChannelDirection::ServerToClient)
+///     .add_delta_compression();
+/// ```
+#[proc_macro_derive(Diffable, attributes(replicate))]
+pub fn diffable_derive(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let shared_crate_name = quote! { lightyear };
+    diffable_impl(input, shared_crate_name)
+}
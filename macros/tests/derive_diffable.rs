@@ -0,0 +1,76 @@
+pub mod some_component {
+    use lightyear_macros::Diffable;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Diffable)]
+    #[replicate(field_mask)]
+    pub struct Inventory {
+        pub slots: Vec<u32>,
+        pub gold: u32,
+        pub name: String,
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Diffable)]
+    #[replicate(field_mask)]
+    pub struct Stats(pub f32, pub f32);
+}
+
+#[cfg(test)]
+mod tests {
+    use lightyear::shared::replication::delta::Diffable;
+
+    use super::some_component::*;
+
+    fn encode<T: serde::Serialize>(value: &T) -> Vec<u8> {
+        bincode::serde::encode_to_vec(value, bincode::config::standard()).unwrap()
+    }
+
+    fn decode<T: serde::de::DeserializeOwned>(bytes: &[u8]) -> T {
+        bincode::serde::decode_from_slice(bytes, bincode::config::standard())
+            .unwrap()
+            .0
+    }
+
+    #[test]
+    fn test_diffable_derive_field_mask() {
+        let old = Inventory {
+            slots: vec![1; 32],
+            gold: 10,
+            name: "inventory".to_string(),
+        };
+        let mut new = old.clone();
+        new.gold = 20;
+
+        // only the changed field is sent
+        let delta = old.diff(&new);
+        let bytes = encode(&delta);
+        assert!(bytes.len() < encode(&new).len());
+
+        let mut received = old.clone();
+        received.apply_diff(&decode(&bytes));
+        assert_eq!(received, new);
+    }
+
+    #[test]
+    fn test_diffable_derive_from_base_value() {
+        let new = Inventory {
+            slots: vec![3; 4],
+            gold: 5,
+            name: "inventory".to_string(),
+        };
+        // a receiver that has never seen the component receives all the fields
+        let delta = Inventory::base_value().diff(&new);
+        let mut received = Inventory::base_value();
+        received.apply_diff(&decode(&encode(&delta)));
+        assert_eq!(received, new);
+    }
+
+    #[test]
+    fn test_diffable_derive_tuple_struct() {
+        let old = Stats(1.0, 2.0);
+        let new = Stats(1.0, 3.0);
+        let mut received = old.clone();
+        received.apply_diff(&decode(&encode(&old.diff(&new))));
+        assert_eq!(received, new);
+    }
+}